@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use super::exchange_id::ExchangeId;
+use super::symbol::Symbol;
+
+/// A base asset and the venues that list it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListedAsset {
+    // Spelled as `Symbol` keeps it: uppercase, bar Hyperliquid's "kPEPE"
+    pub base: String,
+    pub venues: Vec<ExchangeId>,
+}
+
+impl ListedAsset {
+    /// The one venue listing it, when only one does
+    pub fn only_on(&self) -> Option<&ExchangeId> {
+        match self.venues.as_slice() {
+            [venue] => Some(venue),
+            _ => None,
+        }
+    }
+}
+
+/// Every venue's assets merged by base, for the asset picker
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssetDirectory {
+    // Sorted by base
+    pub assets: Vec<ListedAsset>,
+    // Venues whose list failed to load, with why
+    pub failed: Vec<(ExchangeId, String)>,
+}
+
+impl AssetDirectory {
+    pub fn from_listings(listings: impl IntoIterator<Item = (ExchangeId, Vec<String>)>, failed: Vec<(ExchangeId, String)>) -> Self {
+        let mut by_base: BTreeMap<String, Vec<ExchangeId>> = BTreeMap::new();
+        for (exchange, assets) in listings {
+            for asset in assets {
+                let venues = by_base.entry(Symbol::perp(&asset).base().to_string()).or_default();
+                if !venues.contains(&exchange) {
+                    venues.push(exchange.clone());
+                }
+            }
+        }
+        let assets = by_base.into_iter()
+            .map(|(base, mut venues)| {
+                venues.sort();
+                ListedAsset { base, venues }
+            })
+            .collect();
+        Self { assets, failed }
+    }
+
+    /// The venues listing `base`, empty when none do
+    pub fn venues(&self, base: &str) -> Vec<ExchangeId> {
+        self.assets.iter()
+            .find(|asset| asset.base.eq_ignore_ascii_case(base))
+            .map(|asset| asset.venues.clone())
+            .unwrap_or_default()
+    }
+
+    /// Assets matching `query`, best first; all of them for an empty query
+    pub fn search(&self, query: &str) -> Vec<&ListedAsset> {
+        let mut matches: Vec<(u32, &ListedAsset)> = self.assets.iter()
+            .filter_map(|asset| Some((fuzzy_score(query, &asset.base)?, asset)))
+            .collect();
+        // Stable, so equal scores keep alphabetical order
+        matches.sort_by(|a, b| b.0.cmp(&a.0));
+        matches.into_iter().map(|(_, asset)| asset).collect()
+    }
+}
+
+/// How well `query` matches `candidate`, ignoring case: None unless the
+/// query's letters appear in order. An exact match beats a prefix, which
+/// beats letters in a run, which beat scattered ones; shorter candidates win
+/// ties, so "ETH" ranks above "ETHFI".
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    let query = query.trim().to_uppercase();
+    let candidate = candidate.to_uppercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let shortness = 100u32.saturating_sub(candidate.len() as u32);
+    if candidate == query {
+        return Some(10_000);
+    }
+    if candidate.starts_with(&query) {
+        return Some(5_000 + shortness);
+    }
+    if candidate.contains(&query) {
+        return Some(2_000 + shortness);
+    }
+    // In-order letters, scored by how many follow straight on from the last
+    let mut chars = candidate.chars().enumerate();
+    let mut last: Option<usize> = None;
+    let mut adjacent = 0;
+    for wanted in query.chars() {
+        let (index, _) = chars.find(|(_, c)| *c == wanted)?;
+        if last.is_some_and(|last| last + 1 == index) {
+            adjacent += 1;
+        }
+        last = Some(index);
+    }
+    Some(1_000 + adjacent * 10 + shortness.min(9))
+}
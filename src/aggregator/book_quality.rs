@@ -0,0 +1,72 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use super::exchange_id::ExchangeId;
+use super::types::OrderBook;
+use super::Exchange;
+
+// Spreads kept per market for the rolling average
+pub const SPREAD_SAMPLES: usize = 120;
+// Fewer samples than this are no baseline to judge a spread against
+pub const MIN_SPREAD_SAMPLES: usize = 10;
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Latest book and recent spreads per (exchange, market), sampled from the
+/// venues' cached books so the router can judge market data before trading.
+#[derive(Debug, Clone, Default)]
+pub struct BookQuality {
+    books: HashMap<(ExchangeId, String), OrderBook>,
+    spreads: HashMap<(ExchangeId, String), VecDeque<f64>>,
+}
+
+pub type SharedBookQuality = Arc<Mutex<BookQuality>>;
+
+/// Spread in bps of the mid, if the book is two-sided.
+pub fn spread_bps(book: &OrderBook) -> Option<f64> {
+    book.spread_bps()
+}
+
+impl BookQuality {
+    /// Keep `book` as its market's latest. Two-sided books also add a spread
+    /// sample; a repeat of the last book adds nothing.
+    pub fn record_book(&mut self, book: &OrderBook) {
+        let key = (book.exchange.clone(), book.symbol.clone());
+        if self.books.get(&key).is_some_and(|last| last.timestamp == book.timestamp) {
+            return;
+        }
+        if let Some(spread) = spread_bps(book) {
+            let spreads = self.spreads.entry(key.clone()).or_default();
+            spreads.push_back(spread);
+            while spreads.len() > SPREAD_SAMPLES {
+                spreads.pop_front();
+            }
+        }
+        self.books.insert(key, book.clone());
+    }
+
+    pub fn latest(&self, exchange: &ExchangeId, market: &str) -> Option<&OrderBook> {
+        self.books.get(&(exchange.clone(), market.to_string()))
+    }
+
+    /// Mean of the recent spreads, once there are enough of them
+    pub fn average_spread_bps(&self, exchange: &ExchangeId, market: &str) -> Option<f64> {
+        let spreads = self.spreads.get(&(exchange.clone(), market.to_string()))?;
+        (spreads.len() >= MIN_SPREAD_SAMPLES).then(|| spreads.iter().sum::<f64>() / spreads.len() as f64)
+    }
+}
+
+/// Record every venue's cached book twice a second until aborted.
+pub fn spawn_sampler(quality: SharedBookQuality, exchanges: Vec<Exchange>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            for exchange in &exchanges {
+                let Some(book) = exchange.cached_orderbook().await else { continue };
+                if let Ok(mut quality) = quality.lock() {
+                    quality.record_book(&book);
+                }
+            }
+        }
+    })
+}
@@ -1,175 +1,299 @@
-use anyhow::Result;
+use crate::error::Result;
 use async_trait::async_trait;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 use std::sync::Arc;
-use chrono::Utc;
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
 use tokio::time::Duration;
 use super::traits::ExchangeAggregator;
-use super::types::{OrderBook, MarketSummary, LeverageInfo, Level};
+use super::types::{OrderBook, MarketSummary, LeverageInfo, DEFAULT_BOOK_DEPTH};
+use super::symbol::Symbol;
+use super::exchange_id::ExchangeId;
+use super::health::{ClockSkew, HealthRegistry, SharedHealth};
+use super::trade_flow::{SharedTradeFlow, TradeFlow, TradePrint};
+use super::rest_fallback::{self, feed_receiver, DydxSnapshots, FallbackPolicy, SharedBook};
+use super::feed_tasks::FeedTasks;
+use super::dydx_book::DydxBook;
 use tokio::spawn;
 use crate::error::AggregatorError;
+use crate::trading::dydx_service::DydxServiceError;
 use super::hyperliquid::HyperliquidAggregator;
-use dydx::indexer::{IndexerClient, OrdersMessage, Ticker, IndexerConfig, RestConfig, SockConfig};
+use super::endpoints::{self, IndexerEndpoint};
+use super::retry;
+use dydx::indexer::{IndexerClient, OrdersMessage, PerpetualMarket, TradesMessage, Ticker};
+use dydx::indexer::types::{OrderSide, Price, Quantity};
 use num_traits::ToPrimitive;
+use serde::Deserialize;
+
+const DYDX_TIME_PATH: &str = "/v4/time";
+// Listings change rarely; symbol changes shouldn't wait on the indexer
+pub const AVAILABLE_ASSETS_TTL: Duration = Duration::from_secs(10 * 60);
+// Margin fractions move with governance votes, not between redraws
+pub const LEVERAGE_TTL: Duration = Duration::from_secs(10 * 60);
+// Pause before asking for a fresh snapshot after a bad book
+const RESUBSCRIBE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+struct AssetList {
+    assets: Vec<String>,
+    fetched: std::time::Instant,
+}
+
+/// Max leverage a market's initial margin fraction allows; None for a
+/// fraction that isn't positive.
+pub fn max_leverage_from_imf(initial_margin_fraction: f64) -> Option<f64> {
+    (initial_margin_fraction > 0.0 && initial_margin_fraction.is_finite()).then(|| 1.0 / initial_margin_fraction)
+}
+
+/// Base assets from indexer tickers ("BTC-USD" -> "BTC"), sorted
+pub fn base_assets(tickers: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut assets: Vec<String> = tickers.into_iter()
+        .map(|ticker| ticker.strip_suffix("-USD").map(str::to_string).unwrap_or(ticker))
+        .collect();
+    assets.sort();
+    assets.dedup();
+    assets
+}
+
+fn trade_print(price: &Price, size: &Quantity, side: &OrderSide, created_at: &DateTime<Utc>) -> TradePrint {
+    TradePrint {
+        price: price.0.to_f64().unwrap_or(0.0),
+        size: size.0.to_f64().unwrap_or(0.0),
+        is_buy: matches!(side, OrderSide::Buy),
+        time: created_at.timestamp_millis(),
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct DydxAggregator {
     ws_url: String,
-    current_orderbook: Arc<Mutex<Option<OrderBook>>>,
+    current_orderbook: SharedBook,
     current_summary: Arc<Mutex<Option<MarketSummary>>>,
-    current_leverage: Arc<Mutex<Option<LeverageInfo>>>,
+    // Max leverage by ticker, with when it was fetched
+    max_leverage: Arc<Mutex<HashMap<String, (f64, std::time::Instant)>>>,
     current_symbol: Option<String>,
-    available_assets: Arc<Mutex<Vec<String>>>,
+    // Held across a refetch, so concurrent callers share one request
+    available_assets: Arc<Mutex<Option<AssetList>>>,
     hl_aggregator: Arc<HyperliquidAggregator>,
-    feed_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    // Book and trades websockets and the REST poller
+    tasks: FeedTasks,
+    trade_flow: SharedTradeFlow,
+    health: SharedHealth,
+    fallback: FallbackPolicy,
+    // Levels per side published; the full book is kept behind it
+    depth: usize,
+}
+
+impl DydxAggregator {
+    /// Latest streamed or polled book, without any request
+    pub async fn cached_orderbook(&self) -> Option<OrderBook> {
+        self.current_orderbook.borrow().clone()
+    }
+
+    pub fn with_health(mut self, health: SharedHealth) -> Self {
+        self.health = health;
+        self
+    }
+
+    pub fn with_fallback(mut self, fallback: FallbackPolicy) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// Feed trades into a shared tracker instead of a private one
+    pub fn with_trade_flow(mut self, trade_flow: SharedTradeFlow) -> Self {
+        self.trade_flow = trade_flow;
+        self
+    }
+
+    // Trades run on their own connection so a trades hiccup never drops the book
+    fn spawn_trades_feed(&self, ticker: String, market: String) -> tokio::task::JoinHandle<()> {
+        let trade_flow = self.trade_flow.clone();
+        spawn(async move {
+            loop {
+                let endpoints = endpoints::dydx();
+                let mut switched = endpoints.subscribe();
+                let mut client = IndexerClient::new(endpoints.indexer_config());
+
+                if let Ok(mut feed) = client.feed().trades(&Ticker(ticker.clone()), false).await {
+                    loop {
+                        // Resubscribe on the new endpoint after a failover
+                        let message = tokio::select! {
+                            message = feed.recv() => match message {
+                                Some(message) => message,
+                                None => break,
+                            },
+                            _ = switched.changed() => break,
+                        };
+                        let Ok(mut flow) = trade_flow.lock() else { continue };
+                        // The initial message replays recent trades we may already have
+                        let (prints, after): (Vec<TradePrint>, _) = match message {
+                            TradesMessage::Initial(initial) => (
+                                initial.contents.trades.iter()
+                                    .map(|trade| trade_print(&trade.price, &trade.size, &trade.side, &trade.created_at))
+                                    .collect(),
+                                flow.newest(&ExchangeId::Dydx, &market),
+                            ),
+                            // Updates arrive as a batch of trade lists
+                            TradesMessage::Update(update) => (
+                                update.contents.iter()
+                                    .flat_map(|contents| &contents.trades)
+                                    .map(|trade| trade_print(&trade.price, &trade.size, &trade.side, &trade.created_at))
+                                    .collect(),
+                                None,
+                            ),
+                        };
+                        let mut prints: Vec<TradePrint> = prints.into_iter()
+                            .filter(|print| after.is_none_or(|after| print.time > after))
+                            .collect();
+                        // Newest first on the wire
+                        prints.sort_by_key(|print| print.time);
+                        for print in prints {
+                            flow.record(&ExchangeId::Dydx, &market, print);
+                        }
+                    }
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        })
+    }
 }
 
 #[async_trait]
 impl ExchangeAggregator for DydxAggregator {
     async fn new(testnet: bool) -> Result<Self> {
-        let ws_url = if testnet {
-            "wss://indexer.dydx.trade/v4/ws".to_string()
-        } else {
-            "wss://indexer.dydx.trade/v4/ws".to_string()
-        };
+        let ws_url = IndexerEndpoint::from_rest(endpoints::public_indexer(testnet)).ws;
         
         Ok(Self { 
             ws_url,
-            current_orderbook: Arc::new(Mutex::new(None)),
+            current_orderbook: rest_fallback::shared_book(),
             current_summary: Arc::new(Mutex::new(None)),
-            current_leverage: Arc::new(Mutex::new(None)),
+            max_leverage: Arc::new(Mutex::new(HashMap::new())),
             current_symbol: None,
-            available_assets: Arc::new(Mutex::new(Vec::new())),
+            available_assets: Arc::new(Mutex::new(None)),
             hl_aggregator: Arc::new(HyperliquidAggregator::new(testnet).await?),
-            feed_handle: Arc::new(Mutex::new(None)),
+            tasks: FeedTasks::default(),
+            trade_flow: Arc::new(std::sync::Mutex::new(TradeFlow::default())),
+            health: Arc::new(HealthRegistry::default()),
+            fallback: FallbackPolicy::default(),
+            depth: DEFAULT_BOOK_DEPTH,
         })
     }
 
-    async fn start_market_updates(&mut self, symbol: &str) -> Result<()> {
-        // Cancel previous subscription if it exists
-        if let Some(handle) = self.feed_handle.lock().await.take() {
-            handle.abort();
-        }
+    async fn start_market_updates(&mut self, symbol: &Symbol) -> Result<()> {
+        // Cancel previous subscriptions if they exist
+        self.stop_market_updates().await;
 
-        let formatted_symbol = format!("{}-USD", symbol.to_uppercase());
+        let formatted_symbol = symbol.to_dydx_ticker();
         
         // Shared state
         let orderbook = self.current_orderbook.clone();
         let summary = self.current_summary.clone();
         let symbol_clone = symbol.to_string();
+        let health = self.health.clone();
+        let fallback = self.fallback.clone();
+        let depth = self.depth;
+
+        // Idle until the websocket fails often enough to fall back
+        self.tasks.add(rest_fallback::spawn_poller(
+            Box::new(DydxSnapshots { ticker: formatted_symbol.clone(), symbol: symbol_clone.clone() }),
+            orderbook.clone(),
+            health.clone(),
+            ExchangeId::Dydx,
+            fallback.clone(),
+        )).await;
+        if fallback.forced {
+            self.current_symbol = Some(symbol.to_string());
+            return Ok(());
+        }
 
         let handle = spawn(async move {
             'connection_loop: loop {
-                let config = IndexerConfig {
-                    rest: RestConfig {
-                        endpoint: "https://indexer.dydx.trade/".to_string(),
-                    },
-                    sock: SockConfig {
-                        endpoint: "wss://indexer.dydx.trade/v4/ws".to_string(),
-                        timeout: 1000,
-                        rate_limit: std::num::NonZeroU32::new(2).unwrap(),
-                    },
-                };
+                let mut delivered = false;
+                let mut book: Option<DydxBook> = None;
+                let endpoints = endpoints::dydx();
+                let mut switched = endpoints.subscribe();
+                let endpoint = endpoints.active_index();
                 
-                let mut client = IndexerClient::new(config);
+                let mut client = IndexerClient::new(endpoints.indexer_config());
                 let ticker = Ticker(formatted_symbol.clone());
                 
                 match client.feed().orders(&ticker, false).await {
                     Ok(mut feed) => {
-                        while let Some(message) = feed.recv().await {
-                            match message {
-                                OrdersMessage::Initial(initial) => {
-                                    let mut asks = initial.contents.asks.into_iter()
-                                        .map(|level| Level {
-                                            price: level.price.0.to_f64().unwrap_or(0.0),
-                                            size: level.size.0.to_f64().unwrap_or(0.0),
-                                            orders: 1,
-                                        })
-                                        .collect::<Vec<Level>>();
-
-                                    // Sort asks by price (lowest to highest)
-                                    asks.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap_or(std::cmp::Ordering::Equal));
-
-                                    // Take only the first 5 asks
-                                    asks.truncate(5);
-
-                                    let new_book = OrderBook {
-                                        exchange: "dYdX".to_string(),
-                                        symbol: symbol_clone.clone(),
-                                        bids: initial.contents.bids.into_iter()
-                                            .map(|level| Level {
-                                                price: level.price.0.to_f64().unwrap_or(0.0),
-                                                size: level.size.0.to_f64().unwrap_or(0.0),
-                                                orders: 1,
-                                            })
-                                            .collect(),
-                                        asks,
-                                        timestamp: Utc::now().timestamp_millis() as u64,
-                                    };
-                                    *orderbook.lock().await = Some(new_book);
+                        loop {
+                            let message = tokio::select! {
+                                message = feed.recv() => match message {
+                                    Some(message) => message,
+                                    None => break,
                                 },
-                                OrdersMessage::Update(update) => {
-                                    if let Some(book) = orderbook.lock().await.as_mut() {
-                                        // Update asks
-                                        if let Some(asks) = update.contents.asks {
-                                            for ask in asks {
-                                                if ask.size.0.to_f64().unwrap_or(0.0) == 0.0 {
-                                                    book.asks.retain(|a| a.price != ask.price.0.to_f64().unwrap_or(0.0));
-                                                } else {
-                                                    if let Some(existing) = book.asks.iter_mut().find(|a| a.price == ask.price.0.to_f64().unwrap_or(0.0)) {
-                                                        existing.size = ask.size.0.to_f64().unwrap_or(0.0);
-                                                    } else {
-                                                        book.asks.push(Level {
-                                                            price: ask.price.0.to_f64().unwrap_or(0.0),
-                                                            size: ask.size.0.to_f64().unwrap_or(0.0),
-                                                            orders: 1,
-                                                        });
-                                                    }
-                                                }
-                                            }
-                                        }
-
-                                        // Update bids
-                                        if let Some(bids) = update.contents.bids {
-                                            for bid in bids {
-                                                if bid.size.0.to_f64().unwrap_or(0.0) == 0.0 {
-                                                    book.bids.retain(|b| b.price != bid.price.0.to_f64().unwrap_or(0.0));
-                                                } else {
-                                                    if let Some(existing) = book.bids.iter_mut().find(|b| b.price == bid.price.0.to_f64().unwrap_or(0.0)) {
-                                                        existing.size = bid.size.0.to_f64().unwrap_or(0.0);
-                                                    } else {
-                                                        book.bids.push(Level {
-                                                            price: bid.price.0.to_f64().unwrap_or(0.0),
-                                                            size: bid.size.0.to_f64().unwrap_or(0.0),
-                                                            orders: 1,
-                                                        });
-                                                    }
-                                                }
-                                            }
-                                        }
-
-                                        // Sort and limit asks to 5 closest to market price
-                                        book.asks.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap_or(std::cmp::Ordering::Equal));
-                                        book.asks.truncate(10);
-
-                                        // Sort bids highest to lowest
-                                        book.bids.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap_or(std::cmp::Ordering::Equal));
-                                        book.bids.truncate(10);
-
-                                        book.timestamp = Utc::now().timestamp_millis() as u64;
-                                    }
+                                // Another consumer failed the endpoint over; resubscribe
+                                // there straight away, keeping the current book meanwhile
+                                _ = switched.changed() => continue 'connection_loop,
+                            };
+                            if !delivered {
+                                delivered = true;
+                                health.record_ws_connected(&ExchangeId::Dydx, &fallback);
+                            }
+                            let timestamp = Utc::now().timestamp_millis() as u64;
+                            let applied = match message {
+                                OrdersMessage::Initial(initial) => DydxBook::from_snapshot(
+                                    &initial.id,
+                                    initial.contents.bids.iter().map(|level| (level.price.0.to_f64().unwrap_or(0.0), level.size.0.to_f64().unwrap_or(0.0))),
+                                    initial.contents.asks.iter().map(|level| (level.price.0.to_f64().unwrap_or(0.0), level.size.0.to_f64().unwrap_or(0.0))),
+                                ).map(|initial| book = Some(initial)),
+                                // Nothing to apply it to before the snapshot
+                                OrdersMessage::Update(update) => match book.as_mut() {
+                                    Some(book) => book.apply(
+                                        &update.id,
+                                        update.contents.bids.iter().flatten().map(|level| (level.price.0.to_f64().unwrap_or(0.0), level.size.0.to_f64().unwrap_or(0.0))),
+                                        update.contents.asks.iter().flatten().map(|level| (level.price.0.to_f64().unwrap_or(0.0), level.size.0.to_f64().unwrap_or(0.0))),
+                                    ),
+                                    None => continue,
+                                },
+                            };
+                            match applied {
+                                // dYdX book messages carry no server time; stamped at receipt
+                                Ok(()) => if let Some(book) = &book {
+                                    orderbook.send_replace(Some(book.to_order_book(&symbol_clone, depth, timestamp)));
+                                },
+                                // Keep showing the last good book until the fresh snapshot
+                                Err(fault) => {
+                                    tracing::warn!("dYdX {} book dropped, resubscribing: {}", formatted_symbol, fault);
+                                    tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+                                    continue 'connection_loop;
                                 }
                             }
                         }
                         
                         // Channel closed normally or subscription lost
                         //eprintln!("dYdX websocket channel closed, waiting before reconnection...");
-                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        health.record_ws_closed(&ExchangeId::Dydx);
+                        // A session that closed before any book arrived counts as a failed attempt
+                        if !delivered {
+                            endpoints.record_failure(endpoint, "websocket closed before any book", Utc::now().timestamp_millis());
+                        }
+                        let wait = if !delivered && health.record_ws_failure(&ExchangeId::Dydx, &fallback).is_fallback() {
+                            fallback.ws_retry_interval
+                        } else {
+                            Duration::from_secs(1)
+                        };
+                        tokio::time::sleep(wait).await;
                     }
                     Err(e) => {
                         //eprintln!("dYdX connection error: {}. Waiting before retry...", e);
+                        endpoints.record_failure(endpoint, &e.to_string(), Utc::now().timestamp_millis());
+                        if health.record_ws_failure(&ExchangeId::Dydx, &fallback).is_fallback() {
+                            // The poller owns the book now; retry the websocket slowly
+                            tokio::time::sleep(fallback.ws_retry_interval).await;
+                            continue;
+                        }
                         // Clear orderbook on subscription error
-                        *orderbook.lock().await = None;
+                        orderbook.send_replace(None);
                         // Wait before retry to prevent rapid reconnection attempts
                         tokio::time::sleep(Duration::from_secs(1)).await;
                     }
@@ -177,30 +301,21 @@ impl ExchangeAggregator for DydxAggregator {
             }
         });
 
-        *self.feed_handle.lock().await = Some(handle);
+        self.tasks.add(handle).await;
+        self.tasks.add(self.spawn_trades_feed(symbol.to_dydx_ticker(), symbol.to_string())).await;
         self.current_symbol = Some(symbol.to_string());
 
         Ok(())
     }
 
-    async fn get_market_summary(&self, symbol: &str) -> Result<MarketSummary> {
-        let formatted_symbol = format!("{}-USD", symbol.to_uppercase());
-        let config = IndexerConfig {
-            rest: RestConfig {
-                endpoint: "https://indexer.dydx.trade".to_string(),
-            },
-            sock: SockConfig {
-                endpoint: "wss://indexer.dydx.trade/v4/ws".to_string(),
-                timeout: 1000,
-                rate_limit: std::num::NonZeroU32::new(2).unwrap(),
-            },
-        };
-        
-        let client = IndexerClient::new(config);
-        let ticker = Ticker(formatted_symbol);
-        
-        // Get market data
-        match client.markets().get_perpetual_market(&ticker).await {
+    async fn stop_market_updates(&self) {
+        self.tasks.stop().await;
+    }
+
+    async fn get_market_summary(&self, symbol: &Symbol) -> Result<MarketSummary> {
+        let ticker = Ticker(symbol.to_dydx_ticker());
+        let result = retry::with_retry(&retry::policy(), "dYdX market summary", || fetch_perpetual_market(&ticker)).await;
+        match result {
             Ok(market) => {
                 Ok(MarketSummary {
                     symbol: symbol.to_string(),
@@ -208,44 +323,92 @@ impl ExchangeAggregator for DydxAggregator {
                     volume_24h: market.volume_24h.0.to_f64().unwrap_or(0.0),
                     open_interest: market.open_interest.to_f64().unwrap_or(0.0),
                     funding_rate: market.next_funding_rate.to_f64().unwrap_or(0.0),
+                    timestamp: Utc::now().timestamp_millis() as u64,
                 })
             },
             Err(e) => {
                 log::error!("Failed to fetch market data for symbol: {}. Error: {:?}", symbol, e);
-                Err(e.into())
+                Err(e)
             }
         }
     }
 
-    // using same max_leverage as hyperliquid cause dydx doesnt have a way to fetch it, theyre usually the same
-    async fn get_leverage_info(&self, symbol: &str) -> Result<LeverageInfo> {
-        let hl_leverage = self.hl_aggregator.get_leverage_info(symbol).await?;
-        
+    /// Max leverage from the market's initial margin fraction, cached per
+    /// ticker for `LEVERAGE_TTL`. Falls back to Hyperliquid's figure when
+    /// the market can't be fetched.
+    async fn get_leverage_info(&self, symbol: &Symbol) -> Result<LeverageInfo> {
+        let ticker = symbol.to_dydx_ticker();
+        let cached = self.max_leverage.lock().await.get(&ticker).copied();
+        let max_leverage = match cached {
+            Some((max_leverage, fetched)) if fetched.elapsed() < LEVERAGE_TTL => max_leverage,
+            _ => {
+                let market = Ticker(ticker.clone());
+                let result = retry::with_retry(&retry::policy(), "dYdX leverage", || fetch_perpetual_market(&market)).await;
+                let fetched = result.ok()
+                    .and_then(|market| market.initial_margin_fraction.to_f64())
+                    .and_then(max_leverage_from_imf);
+                match fetched {
+                    Some(max_leverage) => {
+                        self.max_leverage.lock().await.insert(ticker, (max_leverage, std::time::Instant::now()));
+                        max_leverage
+                    }
+                    None => {
+                        log::warn!("No dYdX margin fraction for {}, using Hyperliquid's max leverage", symbol);
+                        self.hl_aggregator.get_leverage_info(symbol).await?.max_leverage
+                    }
+                }
+            }
+        };
+
         Ok(LeverageInfo {
-            exchange: "dYdX".to_string(),
+            exchange: ExchangeId::Dydx,
             symbol: symbol.to_string(),
-            max_leverage: hl_leverage.max_leverage,
+            max_leverage,
         })
     }
 
-    async fn get_orderbook(&self, _symbol: &str) -> Result<OrderBook> {
-        if let Some(book) = self.current_orderbook.lock().await.as_ref() {
+    async fn get_orderbook(&self, _symbol: &Symbol) -> Result<OrderBook> {
+        if let Some(book) = self.current_orderbook.borrow().as_ref() {
             Ok(book.clone())
         } else {
             Err(AggregatorError::MarketDataNotFound(
                 "No orderbook data available".to_string()
-            ).into())
+            ))
         }
     }
 
+    fn subscribe_orderbook(&self, symbol: &Symbol) -> Result<watch::Receiver<Option<OrderBook>>> {
+        feed_receiver(&self.current_orderbook, self.current_symbol.as_deref(), symbol)
+    }
+
+    /// Listed base assets, from the indexer's market list. Served from the
+    /// cache until it is older than `AVAILABLE_ASSETS_TTL`; a failed
+    /// refetch falls back to the stale list when there is one.
     async fn get_available_assets(&self) -> Result<Vec<String>> {
-        let assets = self.available_assets.lock().await;
-        if assets.is_empty() {
-            Err(AggregatorError::MarketDataNotFound(
-                "No available assets data yet".to_string()
-            ).into())
-        } else {
-            Ok(assets.clone())
+        let mut cached = self.available_assets.lock().await;
+        if let Some(list) = cached.as_ref().filter(|list| list.fetched.elapsed() < AVAILABLE_ASSETS_TTL) {
+            return Ok(list.assets.clone());
+        }
+
+        let endpoints = endpoints::dydx();
+        let endpoint = endpoints.active_index();
+        let client = IndexerClient::new(endpoints.indexer_config());
+        let started = std::time::Instant::now();
+        let result = client.markets().list_perpetual_markets(None).await;
+        endpoints.record_result(endpoint, &result, started);
+        match result {
+            Ok(markets) => {
+                let assets = base_assets(markets.into_keys().map(|ticker| ticker.0));
+                *cached = Some(AssetList { assets: assets.clone(), fetched: std::time::Instant::now() });
+                Ok(assets)
+            }
+            Err(e) => match cached.as_ref() {
+                Some(stale) => {
+                    log::warn!("Failed to refresh dYdX markets, using the cached list: {:?}", e);
+                    Ok(stale.assets.clone())
+                }
+                None => Err(AggregatorError::MarketDataNotFound(format!("dYdX market list unavailable: {}", e))),
+            },
         }
     }
 
@@ -253,4 +416,34 @@ impl ExchangeAggregator for DydxAggregator {
         // Check if the WebSocket URL contains testnet indicators
         self.ws_url.contains("testnet") || self.ws_url.contains("stage")
     }
-}
\ No newline at end of file
+}
+#[derive(Debug, Deserialize)]
+struct IndexerTime {
+    // Seconds since the epoch, with fractional millis
+    epoch: f64,
+}
+
+// One market from the active indexer; every attempt counts towards that
+// endpoint's health
+async fn fetch_perpetual_market(ticker: &Ticker) -> Result<PerpetualMarket> {
+    let endpoints = endpoints::dydx();
+    let endpoint = endpoints.active_index();
+    let client = IndexerClient::new(endpoints.indexer_config());
+    let started = std::time::Instant::now();
+    let result = client.markets().get_perpetual_market(ticker).await;
+    endpoints.record_result(endpoint, &result, started);
+    Ok(result.map_err(DydxServiceError::from)?)
+}
+
+/// Sample the indexer's clock and record it in `health`. dYdX order messages
+/// carry no server time, so this is the only skew source for the venue.
+pub async fn measure_clock_skew(health: &HealthRegistry) -> Result<ClockSkew> {
+    let sent = Utc::now().timestamp_millis();
+    let response = reqwest::Client::new().get(endpoints::dydx().url(DYDX_TIME_PATH)).send().await?;
+    let time: IndexerTime = response.json().await?;
+    let received = Utc::now().timestamp_millis();
+
+    health.record_round_trip(&ExchangeId::Dydx, (time.epoch * 1000.0).round() as i64, sent, received);
+    health.venue(&ExchangeId::Dydx).clock_skew
+        .ok_or_else(|| AggregatorError::ApiError("No clock skew recorded for dYdX".to_string()))
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use super::exchange_id::ExchangeId;
+use super::types::{BookSource, Level, OrderBook};
+
+// Prices are keyed in billionths, finer than any dYdX tick size, so levels
+// match exactly instead of by f64 equality
+const PRICE_SCALE: f64 = 1e9;
+
+/// Why a book can't be trusted any more; the feed resubscribes for a fresh
+/// snapshot on either.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum BookFault {
+    #[error("update for {got} on the {expected} book")]
+    WrongMarket { expected: String, got: String },
+    #[error("book crossed: bid {bid} >= ask {ask}")]
+    Crossed { bid: f64, ask: f64 },
+}
+
+/// A dYdX book kept from the indexer's orders channel: an initial snapshot,
+/// then batches of absolute level sizes where zero removes the level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DydxBook {
+    market: String,
+    bids: BTreeMap<i64, f64>,
+    asks: BTreeMap<i64, f64>,
+}
+
+fn price_key(price: f64) -> i64 {
+    (price * PRICE_SCALE).round() as i64
+}
+
+fn set_level(side: &mut BTreeMap<i64, f64>, price: f64, size: f64) {
+    if size > 0.0 {
+        side.insert(price_key(price), size);
+    } else {
+        side.remove(&price_key(price));
+    }
+}
+
+impl DydxBook {
+    /// Levels are (price, size) pairs for `market`'s ticker, e.g. "BTC-USD"
+    pub fn from_snapshot(market: &str, bids: impl IntoIterator<Item = (f64, f64)>, asks: impl IntoIterator<Item = (f64, f64)>) -> Result<Self, BookFault> {
+        let mut book = Self { market: market.to_string(), bids: BTreeMap::new(), asks: BTreeMap::new() };
+        book.set_levels(bids, asks);
+        book.validate()?;
+        Ok(book)
+    }
+
+    /// Apply one update batch in full, then check the book still makes sense.
+    /// A faulted book is left as the batch made it; resubscribe rather than
+    /// reuse it.
+    pub fn apply(&mut self, market: &str, bids: impl IntoIterator<Item = (f64, f64)>, asks: impl IntoIterator<Item = (f64, f64)>) -> Result<(), BookFault> {
+        if market != self.market {
+            return Err(BookFault::WrongMarket { expected: self.market.clone(), got: market.to_string() });
+        }
+        self.set_levels(bids, asks);
+        self.validate()
+    }
+
+    fn set_levels(&mut self, bids: impl IntoIterator<Item = (f64, f64)>, asks: impl IntoIterator<Item = (f64, f64)>) {
+        for (price, size) in bids {
+            set_level(&mut self.bids, price, size);
+        }
+        for (price, size) in asks {
+            set_level(&mut self.asks, price, size);
+        }
+    }
+
+    fn validate(&self) -> Result<(), BookFault> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) if bid >= ask => Err(BookFault::Crossed { bid, ask }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.keys().next_back().map(|&key| key as f64 / PRICE_SCALE)
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.keys().next().map(|&key| key as f64 / PRICE_SCALE)
+    }
+
+    /// The top `depth` levels a side, best first
+    pub fn to_order_book(&self, symbol: &str, depth: usize, timestamp: u64) -> OrderBook {
+        let level = |(&key, &size): (&i64, &f64)| Level { price: key as f64 / PRICE_SCALE, size, orders: 1 };
+        OrderBook {
+            exchange: ExchangeId::Dydx,
+            symbol: symbol.to_string(),
+            bids: self.bids.iter().rev().take(depth).map(level).collect(),
+            asks: self.asks.iter().take(depth).map(level).collect(),
+            timestamp,
+            venue_timestamp: None,
+            source: BookSource::Websocket,
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use crate::error::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use dydx::indexer::{IndexerConfig, RestConfig, SockConfig};
+use tokio::sync::watch;
+use crate::config::AggregatorConfig;
+use super::exchange_id::ExchangeId;
+use super::health::SharedHealth;
+
+pub const DYDX_PUBLIC_INDEXER: &str = "https://indexer.dydx.trade";
+pub const DYDX_TESTNET_INDEXER: &str = "https://indexer.v4testnet.dydx.exchange";
+
+/// The public indexer of mainnet or testnet
+pub fn public_indexer(testnet: bool) -> &'static str {
+    if testnet { DYDX_TESTNET_INDEXER } else { DYDX_PUBLIC_INDEXER }
+}
+// Cheap endpoint every indexer serves; used to probe both endpoints
+const PROBE_PATH: &str = "/v4/height";
+
+static DYDX_ENDPOINTS: RwLock<Option<SharedEndpoints>> = RwLock::new(None);
+
+/// REST base and websocket URL of one dYdX indexer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexerEndpoint {
+    pub rest: String,
+    pub ws: String,
+}
+
+impl IndexerEndpoint {
+    /// Endpoint for an indexer at `rest`; its websocket is served from the
+    /// same host under /v4/ws.
+    pub fn from_rest(rest: &str) -> Self {
+        let rest = rest.trim().trim_end_matches('/').to_string();
+        let host = rest.strip_prefix("https://").map(|host| format!("wss://{}", host))
+            .or_else(|| rest.strip_prefix("http://").map(|host| format!("ws://{}", host)))
+            .unwrap_or_else(|| format!("wss://{}", rest));
+        Self { ws: format!("{}/v4/ws", host), rest }
+    }
+
+    /// `path` starts with a slash, e.g. "/v4/height".
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.rest, path)
+    }
+
+    pub fn indexer_config(&self) -> IndexerConfig {
+        IndexerConfig {
+            rest: RestConfig {
+                endpoint: self.rest.clone(),
+            },
+            sock: SockConfig {
+                endpoint: self.ws.clone(),
+                timeout: 1000,
+                rate_limit: std::num::NonZeroU32::new(2).unwrap(),
+            },
+        }
+    }
+}
+
+/// When the selector leaves an endpoint and when it returns to the primary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailoverPolicy {
+    // Consecutive failures before the active endpoint is abandoned
+    pub max_failures: u32,
+    // Slower responses count as failures
+    pub latency_threshold: Duration,
+    // The primary must stay healthy this long before traffic moves back
+    pub failback_after: Duration,
+    pub probe_interval: Duration,
+}
+
+impl Default for FailoverPolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: 3,
+            latency_threshold: Duration::from_secs(2),
+            failback_after: Duration::from_secs(120),
+            probe_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl FailoverPolicy {
+    pub fn from_config(config: &AggregatorConfig) -> Self {
+        Self {
+            latency_threshold: Duration::from_millis(config.indexer_latency_threshold_ms.max(1)),
+            failback_after: Duration::from_secs(config.indexer_failback_secs),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointHealth {
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_latency_ms: Option<u64>,
+    // Local millis timestamp of the first success in the current healthy run
+    pub healthy_since: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EndpointTransition {
+    FailedOver { from: String, to: String, reason: String },
+    FailedBack { from: String, to: String },
+}
+
+impl std::fmt::Display for EndpointTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FailedOver { from, to, reason } => write!(f, "dYdX indexer failed over from {} to {} ({})", from, to, reason),
+            Self::FailedBack { from, to } => write!(f, "dYdX indexer failed back from {} to {}", from, to),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SelectorState {
+    active: usize,
+    health: Vec<EndpointHealth>,
+    transitions: Vec<EndpointTransition>,
+}
+
+/// Picks the dYdX indexer every REST and websocket consumer talks to. Index
+/// 0 is the primary, 1 the optional backup.
+#[derive(Debug)]
+pub struct EndpointSelector {
+    endpoints: Vec<IndexerEndpoint>,
+    policy: FailoverPolicy,
+    state: RwLock<SelectorState>,
+    // Carries the active index so websocket feeds can resubscribe
+    switched: watch::Sender<usize>,
+    health: Option<SharedHealth>,
+}
+
+pub type SharedEndpoints = Arc<EndpointSelector>;
+
+impl Default for EndpointSelector {
+    fn default() -> Self {
+        Self::new(IndexerEndpoint::from_rest(DYDX_PUBLIC_INDEXER), None, FailoverPolicy::default())
+    }
+}
+
+impl EndpointSelector {
+    pub fn new(primary: IndexerEndpoint, backup: Option<IndexerEndpoint>, policy: FailoverPolicy) -> Self {
+        let endpoints: Vec<_> = std::iter::once(primary).chain(backup).collect();
+        let (switched, _) = watch::channel(0);
+        Self {
+            state: RwLock::new(SelectorState {
+                active: 0,
+                health: vec![EndpointHealth::default(); endpoints.len()],
+                transitions: Vec::new(),
+            }),
+            endpoints,
+            policy,
+            switched,
+            health: None,
+        }
+    }
+
+    pub fn from_config(config: &AggregatorConfig) -> Self {
+        let mut primary = IndexerEndpoint::from_rest(config.dydx_indexer.as_deref().unwrap_or(public_indexer(config.testnet)));
+        if let Some(ws) = &config.dydx_indexer_ws {
+            primary.ws = ws.clone();
+        }
+        Self::new(
+            primary,
+            config.dydx_backup_indexer.as_deref().map(IndexerEndpoint::from_rest),
+            FailoverPolicy::from_config(config),
+        )
+    }
+
+    /// Mirror the active endpoint into the venue's health entry
+    pub fn with_health(self, health: SharedHealth) -> Self {
+        health.set_endpoint(&ExchangeId::Dydx, self.endpoints[0].rest.clone());
+        Self { health: Some(health), ..self }
+    }
+
+    pub fn endpoints(&self) -> &[IndexerEndpoint] {
+        &self.endpoints
+    }
+
+    pub fn has_backup(&self) -> bool {
+        self.endpoints.len() > 1
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.state.read().map(|state| state.active).unwrap_or(0)
+    }
+
+    pub fn active(&self) -> IndexerEndpoint {
+        self.endpoints[self.active_index()].clone()
+    }
+
+    /// Full URL of `path` on the active endpoint.
+    pub fn url(&self, path: &str) -> String {
+        self.active().url(path)
+    }
+
+    pub fn indexer_config(&self) -> IndexerConfig {
+        self.active().indexer_config()
+    }
+
+    pub fn endpoint_health(&self, index: usize) -> EndpointHealth {
+        self.state.read()
+            .ok()
+            .and_then(|state| state.health.get(index).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Changes whenever the active endpoint does.
+    pub fn subscribe(&self) -> watch::Receiver<usize> {
+        self.switched.subscribe()
+    }
+
+    /// Transitions since the last call, oldest first
+    pub fn take_transitions(&self) -> Vec<EndpointTransition> {
+        self.state.write()
+            .map(|mut state| std::mem::take(&mut state.transitions))
+            .unwrap_or_default()
+    }
+
+    pub fn record_success(&self, index: usize, latency: Duration, now_ms: i64) -> Option<EndpointTransition> {
+        if latency > self.policy.latency_threshold {
+            return self.record_failure(index, &format!("slow: {}ms", latency.as_millis()), now_ms);
+        }
+        let mut state = self.state.write().ok()?;
+        let health = state.health.get_mut(index)?;
+        health.consecutive_failures = 0;
+        health.last_latency_ms = Some(latency.as_millis() as u64);
+        health.healthy_since.get_or_insert(now_ms);
+        self.evaluate(&mut state, now_ms)
+    }
+
+    pub fn record_failure(&self, index: usize, error: &str, now_ms: i64) -> Option<EndpointTransition> {
+        let mut state = self.state.write().ok()?;
+        let health = state.health.get_mut(index)?;
+        health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+        health.last_error = Some(error.to_string());
+        health.healthy_since = None;
+        self.evaluate(&mut state, now_ms)
+    }
+
+    /// Record the outcome of a call to endpoint `index` that started at
+    /// `started`.
+    pub fn record_result<T, E: std::fmt::Display>(&self, index: usize, result: &std::result::Result<T, E>, started: Instant) -> Option<EndpointTransition> {
+        let now = Utc::now().timestamp_millis();
+        match result {
+            Ok(_) => self.record_success(index, started.elapsed(), now),
+            Err(e) => self.record_failure(index, &e.to_string(), now),
+        }
+    }
+
+    fn is_failing(&self, health: &EndpointHealth) -> bool {
+        health.consecutive_failures >= self.policy.max_failures
+    }
+
+    fn evaluate(&self, state: &mut SelectorState, now_ms: i64) -> Option<EndpointTransition> {
+        let active = state.active;
+        let transition = if active != 0 && state.health[0].healthy_since
+            .is_some_and(|since| now_ms - since >= self.policy.failback_after.as_millis() as i64)
+        {
+            state.active = 0;
+            EndpointTransition::FailedBack { from: self.endpoints[active].rest.clone(), to: self.endpoints[0].rest.clone() }
+        } else if self.is_failing(&state.health[active]) {
+            // Only move to an endpoint that isn't known to be failing too
+            let other = (0..self.endpoints.len()).find(|&index| index != active && !self.is_failing(&state.health[index]))?;
+            state.active = other;
+            EndpointTransition::FailedOver {
+                from: self.endpoints[active].rest.clone(),
+                to: self.endpoints[other].rest.clone(),
+                reason: state.health[active].last_error.clone().unwrap_or_default(),
+            }
+        } else {
+            return None;
+        };
+
+        tracing::warn!("{}", transition);
+        state.transitions.push(transition.clone());
+        self.switched.send_replace(state.active);
+        if let Some(health) = &self.health {
+            health.set_endpoint(&ExchangeId::Dydx, self.endpoints[state.active].rest.clone());
+        }
+        Some(transition)
+    }
+}
+
+/// One health check against an endpoint, returning its latency.
+#[async_trait]
+pub trait EndpointProbe: Send + Sync {
+    async fn probe(&self, endpoint: &IndexerEndpoint) -> Result<Duration>;
+}
+
+pub struct HttpProbe;
+
+#[async_trait]
+impl EndpointProbe for HttpProbe {
+    async fn probe(&self, endpoint: &IndexerEndpoint) -> Result<Duration> {
+        let started = Instant::now();
+        reqwest::Client::new()
+            .get(endpoint.url(PROBE_PATH))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(started.elapsed())
+    }
+}
+
+/// Probe every endpoint once, the standby included, so failback and the
+/// next failover are decided on fresh data.
+pub async fn probe_once(selector: &EndpointSelector, probe: &dyn EndpointProbe, now_ms: i64) -> Vec<EndpointTransition> {
+    let mut transitions = Vec::new();
+    for (index, endpoint) in selector.endpoints().iter().enumerate() {
+        let transition = match probe.probe(endpoint).await {
+            Ok(latency) => selector.record_success(index, latency, now_ms),
+            Err(e) => selector.record_failure(index, &e.to_string(), now_ms),
+        };
+        transitions.extend(transition);
+    }
+    transitions
+}
+
+/// Keep both endpoints warm; idle when no backup is configured.
+pub fn spawn_probe(selector: SharedEndpoints) -> Option<tokio::task::JoinHandle<()>> {
+    if !selector.has_backup() {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        loop {
+            probe_once(&selector, &HttpProbe, Utc::now().timestamp_millis()).await;
+            tokio::time::sleep(selector.policy.probe_interval).await;
+        }
+    }))
+}
+
+/// The selector every dYdX consumer reads from. Until one is installed this
+/// is the public indexer with no backup.
+pub fn dydx() -> SharedEndpoints {
+    if let Some(selector) = DYDX_ENDPOINTS.read().ok().and_then(|installed| installed.clone()) {
+        return selector;
+    }
+    install_dydx(Arc::new(EndpointSelector::default()))
+}
+
+pub fn install_dydx(selector: SharedEndpoints) -> SharedEndpoints {
+    if let Ok(mut installed) = DYDX_ENDPOINTS.write() {
+        *installed = Some(selector.clone());
+    }
+    selector
+}
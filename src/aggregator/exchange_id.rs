@@ -0,0 +1,67 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::error::AggregatorError;
+
+/// Venue identifier. Serialized as its display name ("dYdX", "Hyperliquid")
+/// so journals and exports stay readable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum ExchangeId {
+    Dydx,
+    Hyperliquid,
+    // Venues plugged in at runtime, keyed by their own name
+    Custom(String),
+}
+
+impl ExchangeId {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ExchangeId::Dydx => "dYdX",
+            ExchangeId::Hyperliquid => "Hyperliquid",
+            ExchangeId::Custom(name) => name,
+        }
+    }
+
+    /// The venues this build trades on directly
+    pub fn built_in() -> [ExchangeId; 2] {
+        [ExchangeId::Hyperliquid, ExchangeId::Dydx]
+    }
+}
+
+impl fmt::Display for ExchangeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // pad, not write_str, so width specifiers in table output still apply
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for ExchangeId {
+    type Err = AggregatorError;
+
+    /// Accepts the spellings that have accumulated in configs and journals:
+    /// any case, surrounding whitespace, and "hl" for Hyperliquid.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        match trimmed.to_lowercase().as_str() {
+            "" => Err(AggregatorError::ExchangeError("Empty exchange name".to_string())),
+            "dydx" => Ok(ExchangeId::Dydx),
+            "hyperliquid" | "hl" => Ok(ExchangeId::Hyperliquid),
+            _ => Ok(ExchangeId::Custom(trimmed.to_string())),
+        }
+    }
+}
+
+impl From<ExchangeId> for String {
+    fn from(id: ExchangeId) -> Self {
+        id.as_str().to_string()
+    }
+}
+
+impl TryFrom<String> for ExchangeId {
+    type Error = AggregatorError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
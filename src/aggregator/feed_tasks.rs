@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// The background tasks behind one venue's market feed: websocket readers
+/// and the REST poller. Aborted together when the feed restarts or stops,
+/// so a symbol change never leaves the previous symbol's tasks running.
+#[derive(Debug, Clone, Default)]
+pub struct FeedTasks(Arc<Mutex<Vec<JoinHandle<()>>>>);
+
+impl FeedTasks {
+    pub async fn add(&self, handle: JoinHandle<()>) {
+        self.0.lock().await.push(handle);
+    }
+
+    /// Abort every task; returns how many were still running
+    pub async fn stop(&self) -> usize {
+        let mut handles = self.0.lock().await;
+        let running = handles.iter().filter(|handle| !handle.is_finished()).count();
+        for handle in handles.drain(..) {
+            handle.abort();
+        }
+        running
+    }
+
+    pub async fn running(&self) -> usize {
+        self.0.lock().await.iter().filter(|handle| !handle.is_finished()).count()
+    }
+}
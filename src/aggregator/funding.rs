@@ -0,0 +1,308 @@
+use crate::error::{AggregatorError, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::warn;
+use super::endpoints;
+use super::exchange_id::ExchangeId;
+use super::symbol::Symbol;
+use crate::hyperliquid::meta::info_url;
+const DYDX_FUNDING_PATH: &str = "/v4/historicalFunding";
+
+// Both venues settle funding hourly; nothing new can exist sooner than this
+pub const FUNDING_INTERVAL_MS: i64 = 60 * 60 * 1000;
+
+const HOURS_PER_YEAR: f64 = 24.0 * 365.0;
+
+// Page sizes of the two endpoints
+const HL_PAGE_SIZE: usize = 500;
+const DYDX_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FundingPoint {
+    // Millis timestamp of the funding settlement
+    pub time: i64,
+    // Hourly rate as a fraction, e.g. 0.0000125
+    pub rate: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FundingSeries {
+    exchange: ExchangeId,
+    symbol: Symbol,
+    // Sorted by time, no duplicate timestamps
+    points: Vec<FundingPoint>,
+    // Millis range already fetched, including stretches without any points
+    covered_from: i64,
+    covered_to: i64,
+}
+
+/// Hours each quoted funding rate covers on `exchange`. dYdX's
+/// `nextFundingRate` and Hyperliquid's asset-context `funding` are both
+/// one-hour rates; runtime venues are assumed to quote the same way.
+pub fn funding_period_hours(exchange: &ExchangeId) -> f64 {
+    match exchange {
+        ExchangeId::Dydx | ExchangeId::Hyperliquid | ExchangeId::Custom(_) => 1.0,
+    }
+}
+
+/// A venue's quoted funding rate as an hourly rate
+pub fn hourly_rate(exchange: &ExchangeId, rate: f64) -> f64 {
+    rate / funding_period_hours(exchange)
+}
+
+/// The widest long/short pairing for one market: long where funding is
+/// cheapest, short where it pays most.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FundingSpread {
+    pub symbol: Symbol,
+    pub long_venue: ExchangeId,
+    pub short_venue: ExchangeId,
+    // Hourly rates as fractions
+    pub long_rate: f64,
+    pub short_rate: f64,
+    // Short rate less long rate, as a percent APR
+    pub annualized_spread: f64,
+}
+
+/// Best pairing from each venue's quoted rate, None with fewer than two
+/// venues. Rates are normalized to hourly before comparing.
+pub fn best_funding_spread(symbol: &Symbol, rates: &[(ExchangeId, f64)]) -> Option<FundingSpread> {
+    if rates.len() < 2 {
+        return None;
+    }
+    let hourly: Vec<(&ExchangeId, f64)> = rates.iter().map(|(exchange, rate)| (exchange, hourly_rate(exchange, *rate))).collect();
+    // min_by keeps the first and max_by the last of equals, so the two
+    // venues always differ
+    let (long_venue, long_rate) = *hourly.iter().min_by(|a, b| a.1.total_cmp(&b.1))?;
+    let (short_venue, short_rate) = *hourly.iter().max_by(|a, b| a.1.total_cmp(&b.1))?;
+    Some(FundingSpread {
+        symbol: symbol.clone(),
+        long_venue: long_venue.clone(),
+        short_venue: short_venue.clone(),
+        long_rate,
+        short_rate,
+        annualized_spread: (short_rate - long_rate) * HOURS_PER_YEAR * 100.0,
+    })
+}
+
+/// Funding history per (exchange, symbol), persisted to the config dir so
+/// charts only fetch what's new since the last run.
+#[derive(Debug)]
+pub struct FundingStore {
+    path: PathBuf,
+    series: Vec<FundingSeries>,
+}
+
+impl FundingStore {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("trading_aggregator");
+        fs::create_dir_all(&config_dir)?;
+        Ok(config_dir.join("funding_history.json"))
+    }
+
+    /// Never fails: a missing or corrupt store just means refetching.
+    pub fn load(path: PathBuf) -> Self {
+        let series = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring corrupt funding history {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self { path, series }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&self.series)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    fn find(&self, exchange: &ExchangeId, symbol: &Symbol) -> Option<&FundingSeries> {
+        self.series.iter().find(|s| &s.exchange == exchange && &s.symbol == symbol)
+    }
+
+    /// Stored points at or after `since_ms`, oldest first
+    pub fn points(&self, exchange: &ExchangeId, symbol: &Symbol, since_ms: i64) -> &[FundingPoint] {
+        match self.find(exchange, symbol) {
+            Some(series) => {
+                let start = series.points.partition_point(|p| p.time < since_ms);
+                &series.points[start..]
+            }
+            None => &[],
+        }
+    }
+
+    /// Ranges that still need fetching to cover `[start_ms, now_ms]`: older
+    /// history before what we have, and anything settled since the last fetch.
+    pub fn missing_ranges(&self, exchange: &ExchangeId, symbol: &Symbol, start_ms: i64, now_ms: i64) -> Vec<(i64, i64)> {
+        let Some(series) = self.find(exchange, symbol) else {
+            return vec![(start_ms, now_ms)];
+        };
+
+        let mut ranges = Vec::new();
+        if start_ms < series.covered_from {
+            ranges.push((start_ms, series.covered_from - 1));
+        }
+        if now_ms - series.covered_to >= FUNDING_INTERVAL_MS {
+            ranges.push((series.covered_to + 1, now_ms));
+        }
+        ranges
+    }
+
+    /// Merge points fetched for `[from_ms, to_ms]`. Points already stored are
+    /// skipped; returns how many were new.
+    pub fn record_fetch(&mut self, exchange: &ExchangeId, symbol: &Symbol, from_ms: i64, to_ms: i64, points: Vec<FundingPoint>) -> usize {
+        let index = match self.series.iter().position(|s| &s.exchange == exchange && &s.symbol == symbol) {
+            Some(index) => index,
+            None => {
+                self.series.push(FundingSeries {
+                    exchange: exchange.clone(),
+                    symbol: symbol.clone(),
+                    points: Vec::new(),
+                    covered_from: from_ms,
+                    covered_to: to_ms,
+                });
+                self.series.len() - 1
+            }
+        };
+        let series = &mut self.series[index];
+
+        let before = series.points.len();
+        series.points.extend(points);
+        series.points.sort_by_key(|p| p.time);
+        series.points.dedup_by_key(|p| p.time);
+
+        series.covered_from = series.covered_from.min(from_ms);
+        series.covered_to = series.covered_to.max(to_ms);
+        series.points.len() - before
+    }
+
+    /// Drop points older than `retention`; returns how many were removed.
+    pub fn prune(&mut self, retention: Duration, now_ms: i64) -> usize {
+        let cutoff = now_ms - retention.as_millis() as i64;
+        let mut removed = 0;
+        for series in &mut self.series {
+            let before = series.points.len();
+            series.points.retain(|p| p.time >= cutoff);
+            removed += before - series.points.len();
+            series.covered_from = series.covered_from.max(cutoff);
+        }
+        self.series.retain(|s| s.covered_to >= cutoff);
+        removed
+    }
+}
+
+/// Fetch whatever `store` is missing of the last `window` and merge it in.
+/// Returns the number of new points; the caller saves.
+pub async fn top_up(store: &mut FundingStore, exchange: &ExchangeId, symbol: &Symbol, window: Duration, now_ms: i64, testnet: bool) -> Result<usize> {
+    let start = now_ms - window.as_millis() as i64;
+    let mut added = 0;
+    for (from, to) in store.missing_ranges(exchange, symbol, start, now_ms) {
+        let points = fetch_funding(exchange, symbol, from, to, testnet).await?;
+        added += store.record_fetch(exchange, symbol, from, to, points);
+    }
+    Ok(added)
+}
+
+pub async fn fetch_funding(exchange: &ExchangeId, symbol: &Symbol, from_ms: i64, to_ms: i64, testnet: bool) -> Result<Vec<FundingPoint>> {
+    let client = reqwest::Client::new();
+    let mut points = Vec::new();
+
+    match exchange {
+        ExchangeId::Hyperliquid => {
+            // Pages forward from startTime
+            let mut start = from_ms;
+            loop {
+                let response = client.post(info_url(testnet))
+                    .json(&serde_json::json!({
+                        "type": "fundingHistory",
+                        "coin": symbol.to_hl_coin(),
+                        "startTime": start,
+                        "endTime": to_ms,
+                    }))
+                    .send()
+                    .await?;
+                let page = parse_hyperliquid_funding(&response.text().await?)?;
+                let full = page.len() >= HL_PAGE_SIZE;
+                let Some(last) = page.last().map(|p| p.time) else { break };
+                points.extend(page);
+                if !full {
+                    break;
+                }
+                start = last + 1;
+            }
+        }
+        ExchangeId::Dydx => {
+            // Pages backward from effectiveBeforeOrAt
+            let mut before = to_ms;
+            loop {
+                let before_iso = Utc.timestamp_millis_opt(before).single()
+                    .ok_or_else(|| AggregatorError::ApiError(format!("Invalid timestamp {}", before)))?
+                    .to_rfc3339();
+                let response = client.get(endpoints::dydx().url(&format!("{}/{}", DYDX_FUNDING_PATH, symbol.to_dydx_ticker())))
+                    .query(&[("effectiveBeforeOrAt", before_iso), ("limit", DYDX_PAGE_SIZE.to_string())])
+                    .send()
+                    .await?;
+                let page = parse_dydx_funding(&response.text().await?)?;
+                let full = page.len() >= DYDX_PAGE_SIZE;
+                let Some(oldest) = page.iter().map(|p| p.time).min() else { break };
+                points.extend(page.into_iter().filter(|p| p.time >= from_ms));
+                if !full || oldest <= from_ms {
+                    break;
+                }
+                before = oldest - 1;
+            }
+        }
+        ExchangeId::Custom(_) => return Err(AggregatorError::ExchangeError(format!("No funding history source for {}", exchange))),
+    }
+
+    points.retain(|p| p.time >= from_ms && p.time <= to_ms);
+    Ok(points)
+}
+
+pub fn parse_hyperliquid_funding(json: &str) -> Result<Vec<FundingPoint>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Entry {
+        funding_rate: String,
+        time: i64,
+    }
+
+    let entries: Vec<Entry> = serde_json::from_str(json)?;
+    entries.into_iter()
+        .map(|entry| Ok(FundingPoint { time: entry.time, rate: entry.funding_rate.parse()? }))
+        .collect()
+}
+
+pub fn parse_dydx_funding(json: &str) -> Result<Vec<FundingPoint>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Response {
+        historical_funding: Vec<Entry>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Entry {
+        rate: String,
+        effective_at: String,
+    }
+
+    let response: Response = serde_json::from_str(json)?;
+    response.historical_funding.into_iter()
+        .map(|entry| Ok(FundingPoint {
+            time: DateTime::parse_from_rfc3339(&entry.effective_at)?.timestamp_millis(),
+            rate: entry.rate.parse()?,
+        }))
+        .collect()
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use chrono::Utc;
+use super::exchange_id::ExchangeId;
+use super::rest_fallback::FallbackPolicy;
+use crate::error::{self, AggregatorError};
+use crate::session::{SessionStats, SharedSessionStats};
+
+// Weight of a new sample in the smoothed offset
+const SKEW_SMOOTHING: f64 = 0.2;
+// Consecutive halt-type order rejections before the venue counts as halted
+const HALT_REJECTION_THRESHOLD: u32 = 2;
+// A halt inferred from rejections lapses this long after the last one
+const HALT_REJECTION_TTL_MS: i64 = 5 * 60 * 1000;
+
+/// Estimated venue clock offset: venue clock minus local clock, in millis.
+///
+/// Samples taken from one-way websocket messages include network latency, so
+/// the offset is biased low by roughly the one-way delay; REST samples use the
+/// request midpoint and are closer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockSkew {
+    pub offset_ms: i64,
+    pub samples: u32,
+    // Local millis timestamp of the latest sample
+    pub measured_at: i64,
+}
+
+impl ClockSkew {
+    /// Offset in seconds, e.g. "+182.3s" when the venue's clock is ahead
+    pub fn describe(&self) -> String {
+        format!("{:+.1}s", self.offset_ms as f64 / 1000.0)
+    }
+}
+
+/// How far off a venue's clock the local one is, against the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SkewLevel {
+    #[default]
+    Ok,
+    Warn,
+    // Too far off to sign orders whose expiry comes from the local clock
+    Block,
+}
+
+/// Skew thresholds, in millis either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSkewPolicy {
+    pub warn_ms: i64,
+    pub block_ms: i64,
+}
+
+impl Default for ClockSkewPolicy {
+    fn default() -> Self {
+        Self { warn_ms: 5_000, block_ms: 60_000 }
+    }
+}
+
+impl ClockSkewPolicy {
+    pub fn level(&self, skew: &ClockSkew) -> SkewLevel {
+        let offset = skew.offset_ms.abs();
+        if offset >= self.block_ms {
+            SkewLevel::Block
+        } else if offset >= self.warn_ms {
+            SkewLevel::Warn
+        } else {
+            SkewLevel::Ok
+        }
+    }
+}
+
+/// Whether a venue is accepting orders, with the reason when it isn't.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum VenueStatus {
+    #[default]
+    Operational,
+    Degraded(String),
+    Halted(String),
+}
+
+impl VenueStatus {
+    pub fn is_halted(&self) -> bool {
+        matches!(self, Self::Halted(_))
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Self::Operational => None,
+            Self::Degraded(reason) | Self::Halted(reason) => Some(reason),
+        }
+    }
+
+    /// Short banner for pane titles; empty while operational.
+    pub fn banner(&self) -> String {
+        match self {
+            Self::Operational => String::new(),
+            Self::Degraded(reason) => format!("[DEGRADED: {}]", reason),
+            Self::Halted(reason) => format!("[HALTED: {}]", reason),
+        }
+    }
+}
+
+impl std::fmt::Display for VenueStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Operational => write!(f, "operational"),
+            Self::Degraded(reason) => write!(f, "degraded ({})", reason),
+            Self::Halted(reason) => write!(f, "halted ({})", reason),
+        }
+    }
+}
+
+/// How a venue's book is currently delivered.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Transport {
+    #[default]
+    Websocket,
+    // Polling REST snapshots every `interval`
+    RestFallback { interval: Duration },
+}
+
+impl Transport {
+    pub fn is_fallback(&self) -> bool {
+        matches!(self, Self::RestFallback { .. })
+    }
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Websocket => write!(f, "websocket"),
+            Self::RestFallback { interval } if interval.subsec_millis() == 0 => {
+                write!(f, "REST fallback ({}s)", interval.as_secs())
+            }
+            Self::RestFallback { interval } => write!(f, "REST fallback ({}ms)", interval.as_millis()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VenueHealth {
+    pub clock_skew: Option<ClockSkew>,
+    pub transport: Transport,
+    // Websocket connection attempts that failed since the last good session
+    pub ws_failures: u32,
+    // Latest result of the venue's status poll
+    pub reported_status: VenueStatus,
+    pub halt_rejections: u32,
+    // Local millis timestamp and text of the latest halt-type rejection
+    pub last_halt_rejection: Option<(i64, String)>,
+    // Base URL of the endpoint in use, for venues with failover
+    pub endpoint: Option<String>,
+}
+
+impl VenueHealth {
+    /// Polled status, overridden by a halt inferred from recent rejections.
+    pub fn status_at(&self, now_ms: i64) -> VenueStatus {
+        if self.halt_rejections >= HALT_REJECTION_THRESHOLD {
+            if let Some((at, message)) = &self.last_halt_rejection {
+                if now_ms - at < HALT_REJECTION_TTL_MS {
+                    return VenueStatus::Halted(format!("orders rejected: {}", message));
+                }
+            }
+        }
+        self.reported_status.clone()
+    }
+}
+
+/// Delivery state of one notification sink, e.g. "discord".
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SinkHealth {
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    // Local millis timestamps
+    pub last_failure: Option<i64>,
+    pub last_success: Option<i64>,
+}
+
+impl SinkHealth {
+    pub fn is_failing(&self) -> bool {
+        self.consecutive_failures > 0
+    }
+}
+
+/// Per-venue health state shared by the feeds, the trading side and the UI.
+#[derive(Debug, Default)]
+pub struct HealthRegistry {
+    venues: RwLock<HashMap<ExchangeId, VenueHealth>>,
+    sinks: RwLock<HashMap<String, SinkHealth>>,
+    // Gets websocket connects and closes, for the session recap
+    session: RwLock<Option<SharedSessionStats>>,
+}
+
+pub type SharedHealth = Arc<HealthRegistry>;
+
+impl HealthRegistry {
+    pub fn observe_session(&self, stats: SharedSessionStats) {
+        if let Ok(mut session) = self.session.write() {
+            *session = Some(stats);
+        }
+    }
+
+    fn with_session(&self, update: impl FnOnce(&mut SessionStats)) {
+        let Ok(session) = self.session.read() else { return };
+        // Bound in its own statement so the guard drops before `session`
+        let Some(Ok(mut stats)) = session.as_ref().map(|stats| stats.lock()) else { return };
+        update(&mut stats);
+    }
+
+    pub fn venue(&self, venue: &ExchangeId) -> VenueHealth {
+        self.venues.read()
+            .map(|venues| venues.get(venue).cloned().unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// Feed one observation of the venue's clock (`server_ms`) taken at local
+    /// time `local_ms`.
+    pub fn record_server_time(&self, venue: &ExchangeId, server_ms: i64, local_ms: i64) {
+        let sample = server_ms - local_ms;
+        let Ok(mut venues) = self.venues.write() else { return };
+        let health = venues.entry(venue.clone()).or_default();
+
+        health.clock_skew = Some(match health.clock_skew {
+            Some(skew) => ClockSkew {
+                offset_ms: (skew.offset_ms as f64 * (1.0 - SKEW_SMOOTHING) + sample as f64 * SKEW_SMOOTHING).round() as i64,
+                samples: skew.samples.saturating_add(1),
+                measured_at: local_ms,
+            },
+            None => ClockSkew { offset_ms: sample, samples: 1, measured_at: local_ms },
+        });
+    }
+
+    /// Convenience for REST calls: the sample is taken at the midpoint of the
+    /// request.
+    pub fn record_round_trip(&self, venue: &ExchangeId, server_ms: i64, sent_ms: i64, received_ms: i64) {
+        self.record_server_time(venue, server_ms, sent_ms + (received_ms - sent_ms) / 2);
+    }
+
+    /// Skew level for `venue`; Ok until its clock has been sampled
+    pub fn skew_level(&self, venue: &ExchangeId, policy: &ClockSkewPolicy) -> SkewLevel {
+        self.venue(venue).clock_skew.map_or(SkewLevel::Ok, |skew| policy.level(&skew))
+    }
+
+    /// Refuse when the local clock is far enough off `venue`'s that orders
+    /// expiring by wall-clock time would be rejected or expire early.
+    pub fn ensure_clock_synced(&self, venue: &ExchangeId, policy: &ClockSkewPolicy) -> Result<(), AggregatorError> {
+        match self.venue(venue).clock_skew {
+            Some(skew) if policy.level(&skew) == SkewLevel::Block => Err(error::ClockSkew {
+                exchange: venue.clone(),
+                offset: skew.describe(),
+                limit_ms: policy.block_ms,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn clock_offset_ms(&self, venue: &ExchangeId) -> i64 {
+        self.venue(venue).clock_skew.map_or(0, |skew| skew.offset_ms)
+    }
+
+    /// Map a venue-clock timestamp onto the local clock. The result is clamped
+    /// to `local_now_ms`: nothing we hold can have been produced in our future.
+    pub fn normalize(&self, venue: &ExchangeId, venue_ms: u64, local_now_ms: u64) -> u64 {
+        let normalized = venue_ms as i64 - self.clock_offset_ms(venue);
+        (normalized.max(0) as u64).min(local_now_ms)
+    }
+
+    pub fn status(&self, venue: &ExchangeId) -> VenueStatus {
+        self.venue(venue).status_at(Utc::now().timestamp_millis())
+    }
+
+    pub fn set_reported_status(&self, venue: &ExchangeId, status: VenueStatus) {
+        let Ok(mut venues) = self.venues.write() else { return };
+        venues.entry(venue.clone()).or_default().reported_status = status;
+    }
+
+    /// Count a venue rejection. Only halt-type messages build towards an
+    /// inferred halt; any other rejection shows the venue is matching orders.
+    pub fn record_order_rejected(&self, venue: &ExchangeId, message: &str, now_ms: i64) {
+        let Ok(mut venues) = self.venues.write() else { return };
+        let health = venues.entry(venue.clone()).or_default();
+        if super::venue_status::is_halt_rejection(message) {
+            health.halt_rejections = health.halt_rejections.saturating_add(1);
+            health.last_halt_rejection = Some((now_ms, message.to_string()));
+        } else {
+            health.halt_rejections = 0;
+        }
+    }
+
+    pub fn record_order_accepted(&self, venue: &ExchangeId) {
+        let Ok(mut venues) = self.venues.write() else { return };
+        let health = venues.entry(venue.clone()).or_default();
+        health.halt_rejections = 0;
+        health.last_halt_rejection = None;
+    }
+
+    pub fn transport(&self, venue: &ExchangeId) -> Transport {
+        self.venue(venue).transport
+    }
+
+    /// Count a failed websocket attempt, dropping to REST polling once the
+    /// policy's limit is reached. Returns the transport now in use.
+    pub fn record_ws_failure(&self, venue: &ExchangeId, policy: &FallbackPolicy) -> Transport {
+        self.record_ws_closed(venue);
+        let Ok(mut venues) = self.venues.write() else { return Transport::Websocket };
+        let health = venues.entry(venue.clone()).or_default();
+        health.ws_failures = health.ws_failures.saturating_add(1);
+        if policy.forced || health.ws_failures >= policy.max_ws_failures {
+            health.transport = Transport::RestFallback { interval: policy.poll_interval };
+        }
+        health.transport
+    }
+
+    /// A websocket session delivered data: back to streaming unless the
+    /// policy forces polling.
+    pub fn record_ws_connected(&self, venue: &ExchangeId, policy: &FallbackPolicy) {
+        self.with_session(|stats| stats.record_ws_connected(venue, Utc::now().timestamp_millis()));
+        let Ok(mut venues) = self.venues.write() else { return };
+        let health = venues.entry(venue.clone()).or_default();
+        health.ws_failures = 0;
+        health.transport = if policy.forced {
+            Transport::RestFallback { interval: policy.poll_interval }
+        } else {
+            Transport::Websocket
+        };
+    }
+
+    /// A websocket session ended, delivered or not
+    pub fn record_ws_closed(&self, venue: &ExchangeId) {
+        self.with_session(|stats| stats.record_ws_closed(venue, Utc::now().timestamp_millis()));
+    }
+
+    pub fn force_rest(&self, venue: &ExchangeId, interval: Duration) {
+        let Ok(mut venues) = self.venues.write() else { return };
+        venues.entry(venue.clone()).or_default().transport = Transport::RestFallback { interval };
+    }
+
+    pub fn set_endpoint(&self, venue: &ExchangeId, endpoint: String) {
+        let Ok(mut venues) = self.venues.write() else { return };
+        venues.entry(venue.clone()).or_default().endpoint = Some(endpoint);
+    }
+
+    pub fn sink(&self, name: &str) -> SinkHealth {
+        self.sinks.read()
+            .map(|sinks| sinks.get(name).cloned().unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// Sinks whose last delivery failed, sorted by name
+    pub fn failing_sinks(&self) -> Vec<(String, SinkHealth)> {
+        let Ok(sinks) = self.sinks.read() else { return Vec::new() };
+        let mut failing: Vec<_> = sinks.iter()
+            .filter(|(_, health)| health.is_failing())
+            .map(|(name, health)| (name.clone(), health.clone()))
+            .collect();
+        failing.sort_by(|a, b| a.0.cmp(&b.0));
+        failing
+    }
+
+    pub fn record_sink_success(&self, name: &str) {
+        let Ok(mut sinks) = self.sinks.write() else { return };
+        let health = sinks.entry(name.to_string()).or_default();
+        health.consecutive_failures = 0;
+        health.last_success = Some(Utc::now().timestamp_millis());
+    }
+
+    pub fn record_sink_failure(&self, name: &str, error: &str) {
+        let Ok(mut sinks) = self.sinks.write() else { return };
+        let health = sinks.entry(name.to_string()).or_default();
+        health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+        health.last_error = Some(error.to_string());
+        health.last_failure = Some(Utc::now().timestamp_millis());
+    }
+
+    pub fn normalize_now(&self, venue: &ExchangeId, venue_ms: u64) -> u64 {
+        self.normalize(venue, venue_ms, Utc::now().timestamp_millis() as u64)
+    }
+}
@@ -7,32 +7,48 @@ use hyperliquid_rust_sdk::{
 use tokio::{
     spawn,
     sync::mpsc::{unbounded_channel},
-    sync::Mutex,
+    sync::{watch, Mutex},
 };
-use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use chrono::Utc;
-use super::types::{LeverageInfo, OrderBook, Level, MarketSummary};
+use super::types::{BookSource, LeverageInfo, OrderBook, Level, MarketSummary, DEFAULT_BOOK_DEPTH};
+use super::symbol::Symbol;
+use super::exchange_id::ExchangeId;
+use super::health::{HealthRegistry, SharedHealth};
+use super::trade_flow::{SharedTradeFlow, TradeFlow, TradePrint};
+use super::rest_fallback::{self, feed_receiver, FallbackPolicy, HyperliquidSnapshots, SharedBook};
+use super::feed_tasks::FeedTasks;
+use super::retry;
+use crate::hyperliquid::meta::info_url;
+use crate::hyperliquid::{AssetContext, AssetContexts, MetaCache};
 use std::sync::Arc;
-use anyhow::Result;
+use crate::error::{AggregatorError, Result};
 use async_trait::async_trait;
 use super::traits::ExchangeAggregator;
-use serde::Deserialize;
-use thiserror::Error;
 
-#[derive(Error, Debug)]
-pub enum AggregatorError {
-    #[error("Asset not found: {0}")]
-    AssetNotFound(String),
-}
+// The TUI redraws several times a second; summaries only need to be this fresh
+pub const ASSET_CONTEXTS_TTL: Duration = Duration::from_secs(3);
 
 #[derive(Clone)]
 pub struct HyperliquidAggregator {
     client: Arc<Mutex<InfoClient>>,
-    subscription_ids: HashMap<String, u32>,
+    testnet: bool,
+    // Live websocket subscriptions on `client`, dropped when the feed stops
+    subscription_ids: Arc<Mutex<Vec<u32>>>,
     current_symbol: Option<String>,
-    current_orderbook: Arc<Mutex<Option<OrderBook>>>,
+    current_orderbook: SharedBook,
     current_summary: Arc<Mutex<Option<MarketSummary>>>,
-    universe_cache: Arc<Mutex<Option<MetaResponse>>>,
+    meta: Arc<MetaCache>,
+    // Last metaAndAssetCtxs response; held across a refetch so concurrent
+    // callers share it
+    asset_contexts: Arc<Mutex<Option<(Instant, Arc<AssetContexts>)>>>,
+    health: SharedHealth,
+    trade_flow: SharedTradeFlow,
+    fallback: FallbackPolicy,
+    // Levels per side kept; the venue sends at most 20
+    depth: usize,
+    // The websocket reader and the REST poller
+    tasks: FeedTasks,
 }
 
 impl std::fmt::Debug for HyperliquidAggregator {
@@ -42,7 +58,6 @@ impl std::fmt::Debug for HyperliquidAggregator {
             .field("current_symbol", &self.current_symbol)
             .field("current_orderbook", &self.current_orderbook)
             .field("current_summary", &self.current_summary)
-            .field("universe_cache", &self.universe_cache)
             .finish_non_exhaustive()
     }
 }
@@ -53,35 +68,74 @@ impl ExchangeAggregator for HyperliquidAggregator {
         let base_url = if testnet { BaseUrl::Testnet } else { BaseUrl::Mainnet };
         Ok(Self {
             client: Arc::new(Mutex::new(InfoClient::new(None, Some(base_url)).await?)),
-            subscription_ids: HashMap::new(),
+            testnet,
+            subscription_ids: Arc::new(Mutex::new(Vec::new())),
             current_symbol: None,
-            current_orderbook: Arc::new(Mutex::new(None)),
+            current_orderbook: rest_fallback::shared_book(),
             current_summary: Arc::new(Mutex::new(None)),
-            universe_cache: Arc::new(Mutex::new(None)),
+            meta: MetaCache::hyperliquid_on(testnet),
+            asset_contexts: Arc::new(Mutex::new(None)),
+            health: Arc::new(HealthRegistry::default()),
+            trade_flow: Arc::new(std::sync::Mutex::new(TradeFlow::default())),
+            fallback: FallbackPolicy::default(),
+            depth: DEFAULT_BOOK_DEPTH,
+            tasks: FeedTasks::default(),
         })
     }
 
-    async fn start_market_updates(&mut self, symbol: &str) -> Result<()> {
+    async fn start_market_updates(&mut self, symbol: &Symbol) -> Result<()> {
+        self.stop_market_updates().await;
         self.current_symbol = Some(symbol.to_string());
         
         // Shared state for updates
         let orderbook = self.current_orderbook.clone();
         let summary = self.current_summary.clone();
+        let coin = symbol.to_hl_coin();
         let symbol = symbol.to_string();
         let client = self.client.clone();
+        let health = self.health.clone();
+        let trade_flow = self.trade_flow.clone();
+        let fallback = self.fallback.clone();
+        let subscription_ids = self.subscription_ids.clone();
+        let depth = self.depth;
+
+        // Idle until the websocket fails often enough to fall back
+        let poller = rest_fallback::spawn_poller(
+            Box::new(HyperliquidSnapshots { client: client.clone(), health: health.clone(), coin: coin.clone(), symbol: symbol.clone() }),
+            orderbook.clone(),
+            health.clone(),
+            ExchangeId::Hyperliquid,
+            fallback.clone(),
+        );
+        self.tasks.add(poller).await;
+        if fallback.forced {
+            return Ok(());
+        }
 
-        spawn(async move {
+        let feed = spawn(async move {
             let mut consecutive_errors = 0;
             
             'connection_loop: loop {
+                let mut delivered = false;
+                // A reconnect replaces the previous session's subscriptions
+                unsubscribe_all(&client, &subscription_ids).await;
                 let (sender, mut receiver) = unbounded_channel();
                 let result = client.lock().await.subscribe(
                     Subscription::L2Book {
-                        coin: symbol.clone(),
+                        coin: coin.clone(),
                     },
-                    sender,
+                    sender.clone(),
                 ).await;
 
+                // Trades share the channel; the book still works without them
+                if let Ok(id) = result {
+                    subscription_ids.lock().await.push(id);
+                    match client.lock().await.subscribe(Subscription::Trades { coin: coin.clone() }, sender).await {
+                        Ok(id) => subscription_ids.lock().await.push(id),
+                        Err(e) => tracing::warn!("Hyperliquid trades subscription failed: {}", e),
+                    }
+                }
+
                 match result {
                     Ok(_) => {
                         consecutive_errors = 0;  // Reset error counter on successful connection
@@ -89,16 +143,41 @@ impl ExchangeAggregator for HyperliquidAggregator {
                         while let Some(msg) = receiver.recv().await {
                             match msg {
                                 Message::L2Book(book) => {
+                                    if !delivered {
+                                        delivered = true;
+                                        health.record_ws_connected(&ExchangeId::Hyperliquid, &fallback);
+                                    }
+                                    let received = Utc::now().timestamp_millis();
+                                    health.record_server_time(&ExchangeId::Hyperliquid, book.data.time as i64, received);
+
+                                    let (Some(bids), Some(asks)) = (book.data.levels.first(), book.data.levels.get(1)) else {
+                                        tracing::warn!("Hyperliquid {} book update without both sides, skipped", symbol);
+                                        continue;
+                                    };
                                     let new_book = OrderBook {
-                                        exchange: "Hyperliquid".to_string(),
+                                        exchange: ExchangeId::Hyperliquid,
                                         symbol: symbol.clone(),
-                                        bids: convert_levels_from_book(&book.data.levels[0]),
-                                        asks: convert_levels_from_book(&book.data.levels[1]),
-                                        timestamp: Utc::now().timestamp_millis() as u64,
+                                        bids: convert_levels_from_book(bids, depth),
+                                        asks: convert_levels_from_book(asks, depth),
+                                        timestamp: health.normalize(&ExchangeId::Hyperliquid, book.data.time, received as u64),
+                                        venue_timestamp: Some(book.data.time),
+                                        source: BookSource::Websocket,
                                     };
                                     
                                     if !new_book.bids.is_empty() && !new_book.asks.is_empty() {
-                                        *orderbook.lock().await = Some(new_book);
+                                        orderbook.send_replace(Some(new_book));
+                                    }
+                                }
+                                Message::Trades(trades) => {
+                                    let Ok(mut flow) = trade_flow.lock() else { continue };
+                                    for trade in trades.data {
+                                        let (Ok(price), Ok(size)) = (trade.px.parse(), trade.sz.parse()) else { continue };
+                                        flow.record(&ExchangeId::Hyperliquid, &symbol, TradePrint {
+                                            price,
+                                            size,
+                                            is_buy: trade.side == "B",
+                                            time: health.normalize_now(&ExchangeId::Hyperliquid, trade.time) as i64,
+                                        });
                                     }
                                 }
                                 _ => {
@@ -110,116 +189,170 @@ impl ExchangeAggregator for HyperliquidAggregator {
                         
                         // Channel closed normally - wait before reconnecting
                         eprintln!("Hyperliquid websocket channel closed, waiting before reconnection...");
-                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        health.record_ws_closed(&ExchangeId::Hyperliquid);
+                        // A session that closed before any book arrived counts as a failed attempt
+                        let wait = if !delivered && health.record_ws_failure(&ExchangeId::Hyperliquid, &fallback).is_fallback() {
+                            fallback.ws_retry_interval
+                        } else {
+                            std::time::Duration::from_secs(1)
+                        };
+                        tokio::time::sleep(wait).await;
                     }
                     Err(e) => {
                         consecutive_errors += 1;
                         eprintln!("Hyperliquid connection error (attempt {}): {}", consecutive_errors, e);
                         
-                        // Implement exponential backoff
-                        let wait_time = std::cmp::min(consecutive_errors * 5, 30);
-                        tokio::time::sleep(std::time::Duration::from_secs(wait_time)).await;
+                        // Implement exponential backoff, slowing to the retry interval while polling
+                        let wait_time = if health.record_ws_failure(&ExchangeId::Hyperliquid, &fallback).is_fallback() {
+                            fallback.ws_retry_interval
+                        } else {
+                            std::time::Duration::from_secs(std::cmp::min(consecutive_errors * 5, 30))
+                        };
+                        tokio::time::sleep(wait_time).await;
                     }
                 }
             }
         });
+        self.tasks.add(feed).await;
 
         Ok(())
     }
 
-    async fn get_market_summary(&self, symbol: &str) -> Result<MarketSummary> {
-        let client = reqwest::Client::new();
-        let response = client.post("https://api.hyperliquid.xyz/info")
-            .json(&serde_json::json!({
-                "type": "metaAndAssetCtxs"
-            }))
-            .send()
-            .await?;
-        
-        let response_text = response.text().await?;
-        let data: Vec<serde_json::Value> = serde_json::from_str(&response_text)?;
-        
-        // Get the asset contexts from the second element of the array
-        let asset_ctxs = &data[1];
-        
-        // Find the index of our symbol in the universe (first element)
-        let universe: Vec<AssetMeta> = serde_json::from_value(data[0]["universe"].clone())?;
-        let symbol_index = universe.iter()
-            .position(|asset| asset.name == symbol)
-            .ok_or_else(|| anyhow::anyhow!("Symbol not found"))?;
-        
-        // Get the corresponding asset context
-        let asset_ctx: AssetContext = serde_json::from_value(asset_ctxs[symbol_index].clone())?;
-        
-        Ok(MarketSummary {
-            symbol: symbol.to_string(),
-            price: asset_ctx.mark_price.parse()?,
-            volume_24h: asset_ctx.volume_24h.parse()?,
-            open_interest: asset_ctx.open_interest.parse()?,
-            funding_rate: asset_ctx.funding_rate.parse()?,
-        })
+    async fn stop_market_updates(&self) {
+        self.tasks.stop().await;
+        unsubscribe_all(&self.client, &self.subscription_ids).await;
     }
 
-    async fn get_leverage_info(&self, symbol: &str) -> Result<LeverageInfo> {
-        // Try to get from cache first
-        let mut cache = self.universe_cache.lock().await;
-        
-        // If cache is empty or expired, fetch new data
-        if cache.is_none() {
-            let client = reqwest::Client::new();
-            let response = client.post("https://api.hyperliquid.xyz/info")
-                .json(&serde_json::json!({
-                    "type": "meta"
-                }))
-                .send()
-                .await?;
-            
-            let response_text = response.text().await?;
-            let meta: MetaResponse = serde_json::from_str(&response_text)?;
-            *cache = Some(meta);
-        }
+    async fn get_market_summary(&self, symbol: &Symbol) -> Result<MarketSummary> {
+        let contexts = self.asset_contexts().await?;
+        let (_, context) = contexts.get(&symbol.to_hl_coin())
+            .ok_or_else(|| AggregatorError::AssetNotFound(format!("Symbol not found: {}", symbol)))?;
+        market_summary(symbol, context, Utc::now().timestamp_millis() as u64)
+    }
 
-        // Find the asset in the universe
-        let asset = cache.as_ref()
-            .and_then(|meta| meta.universe.iter().find(|asset| asset.name == symbol))
+    async fn get_leverage_info(&self, symbol: &Symbol) -> Result<LeverageInfo> {
+        let coin = symbol.to_hl_coin();
+        // Same fetch the summary uses; the meta cache covers it being down
+        let max_leverage = match self.asset_contexts().await {
+            Ok(contexts) => contexts.universe().get(&coin).map(|asset| asset.max_leverage),
+            Err(e) => {
+                tracing::debug!("Asset contexts unavailable, using cached meta: {}", e);
+                self.meta.get(&coin).await?.map(|asset| asset.max_leverage)
+            }
+        };
+        let max_leverage = max_leverage
             .ok_or_else(|| AggregatorError::AssetNotFound(format!("Symbol not found: {}", symbol)))?;
 
         Ok(LeverageInfo {
-            exchange: "Hyperliquid".to_string(),
+            exchange: ExchangeId::Hyperliquid,
             symbol: symbol.to_string(),
-            max_leverage: asset.max_leverage as f64,
+            max_leverage: max_leverage as f64,
         })
     }
 
-    async fn get_orderbook(&self, symbol: &str) -> Result<OrderBook> {
-        let l2_snapshot = self.client.lock().await.l2_snapshot(symbol.to_string()).await?;
-        
+    async fn get_orderbook(&self, symbol: &Symbol) -> Result<OrderBook> {
+        // The poller already fetches snapshots while on fallback
+        if self.health.transport(&ExchangeId::Hyperliquid).is_fallback() {
+            let market = symbol.to_string();
+            if let Some(book) = self.current_orderbook.borrow().as_ref().filter(|book| book.symbol == market) {
+                return Ok(book.clone());
+            }
+        }
+        let coin = symbol.to_hl_coin();
+        // Round trip of the attempt that succeeded
+        let (sent, l2_snapshot) = retry::with_retry(&retry::policy(), "Hyperliquid orderbook", || async {
+            let sent = Utc::now().timestamp_millis();
+            Ok((sent, self.client.lock().await.l2_snapshot(coin.clone()).await?))
+        }).await?;
+        let received = Utc::now().timestamp_millis();
+        self.health.record_round_trip(&ExchangeId::Hyperliquid, l2_snapshot.time as i64, sent, received);
+
         Ok(OrderBook {
-            exchange: "Hyperliquid".to_string(),
+            exchange: ExchangeId::Hyperliquid,
             symbol: symbol.to_string(),
-            bids: convert_levels(l2_snapshot.levels.get(0).map(|v| v.as_slice()).unwrap_or_default()),
-            asks: convert_levels(l2_snapshot.levels.get(1).map(|v| v.as_slice()).unwrap_or_default()),
-            timestamp: l2_snapshot.time,
+            bids: convert_levels(l2_snapshot.levels.get(0).map(|v| v.as_slice()).unwrap_or_default(), self.depth),
+            asks: convert_levels(l2_snapshot.levels.get(1).map(|v| v.as_slice()).unwrap_or_default(), self.depth),
+            timestamp: self.health.normalize(&ExchangeId::Hyperliquid, l2_snapshot.time, received as u64),
+            venue_timestamp: Some(l2_snapshot.time),
+            source: BookSource::Polled,
         })
     }
 
+    fn subscribe_orderbook(&self, symbol: &Symbol) -> Result<watch::Receiver<Option<OrderBook>>> {
+        feed_receiver(&self.current_orderbook, self.current_symbol.as_deref(), symbol)
+    }
+
     async fn get_available_assets(&self) -> Result<Vec<String>> {
-        let meta = self.client.lock().await.meta().await?;
-        Ok(meta.universe.iter()
+        Ok(self.meta.universe().await?.assets().iter()
             .map(|asset| asset.name.clone())
             .collect())
     }
 
     async fn is_testnet(&self) -> bool {
-        matches!(
-            self.client.lock().await.http_client.base_url.as_str(),
-            "https://api.hyperliquid-testnet.xyz/api"
-        )
+        self.testnet
+    }
+}
+
+impl HyperliquidAggregator {
+    /// Universe and live asset contexts, refetched once older than
+    /// `ASSET_CONTEXTS_TTL`.
+    async fn asset_contexts(&self) -> Result<Arc<AssetContexts>> {
+        let mut cached = self.asset_contexts.lock().await;
+        if let Some((fetched, contexts)) = cached.as_ref() {
+            if fetched.elapsed() < ASSET_CONTEXTS_TTL {
+                return Ok(contexts.clone());
+            }
+        }
+        let body = retry::with_retry(&retry::policy(), "Hyperliquid asset contexts", || async {
+            let response = reqwest::Client::new().post(info_url(self.testnet))
+                .json(&serde_json::json!({ "type": "metaAndAssetCtxs" }))
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(response.text().await?)
+        }).await?;
+        let contexts = Arc::new(AssetContexts::parse(&body)?);
+        *cached = Some((Instant::now(), contexts.clone()));
+        Ok(contexts)
+    }
+
+    /// Latest streamed or polled book, without any request
+    pub async fn cached_orderbook(&self) -> Option<OrderBook> {
+        self.current_orderbook.borrow().clone()
+    }
+
+    /// Share a health registry so skew measured from this feed is visible elsewhere
+    pub fn with_health(mut self, health: SharedHealth) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// Feed trades into a shared tracker instead of a private one
+    pub fn with_trade_flow(mut self, trade_flow: SharedTradeFlow) -> Self {
+        self.trade_flow = trade_flow;
+        self
+    }
+
+    pub fn with_fallback(mut self, fallback: FallbackPolicy) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// Read asset metadata through a cache shared with the trading side
+    pub fn with_meta(mut self, meta: Arc<MetaCache>) -> Self {
+        self.meta = meta;
+        self
     }
 }
 
-fn convert_levels_from_book(levels: &Vec<hyperliquid_rust_sdk::BookLevel>) -> Vec<Level> {
+fn convert_levels_from_book(levels: &[hyperliquid_rust_sdk::BookLevel], depth: usize) -> Vec<Level> {
     levels.iter()
+        .take(depth)
         .map(|level| Level {
             price: level.px.parse().unwrap_or(0.0),
             size: level.sz.parse().unwrap_or(0.0),
@@ -228,8 +361,20 @@ fn convert_levels_from_book(levels: &Vec<hyperliquid_rust_sdk::BookLevel>) -> Ve
         .collect()
 }
 
-fn convert_levels(levels: &[hyperliquid_rust_sdk::Level]) -> Vec<Level> {
+// The SDK keeps a subscription's websocket feed alive until told otherwise,
+// even once the reader is gone
+async fn unsubscribe_all(client: &Mutex<InfoClient>, subscription_ids: &Mutex<Vec<u32>>) {
+    let ids: Vec<u32> = subscription_ids.lock().await.drain(..).collect();
+    for id in ids {
+        if let Err(e) = client.lock().await.unsubscribe(id).await {
+            tracing::warn!("Hyperliquid unsubscribe {} failed: {}", id, e);
+        }
+    }
+}
+
+fn convert_levels(levels: &[hyperliquid_rust_sdk::Level], depth: usize) -> Vec<Level> {
     levels.iter()
+        .take(depth)
         .map(|level| Level {
             price: level.px.parse().unwrap_or(0.0),
             size: level.sz.parse().unwrap_or(0.0),
@@ -238,30 +383,17 @@ fn convert_levels(levels: &[hyperliquid_rust_sdk::Level]) -> Vec<Level> {
         .collect()
 }
 
-#[derive(Debug, Deserialize)]
-struct MetaResponse {
-    universe: Vec<AssetMeta>,
-}
-
-#[derive(Debug, Deserialize)]
-struct AssetMeta {
-    name: String,
-    #[serde(rename = "maxLeverage")]
-    max_leverage: u32,
-    #[serde(rename = "szDecimals")]
-    sz_decimals: u8,
-    #[serde(rename = "onlyIsolated", default)]
-    only_isolated: bool,
-}
-
-#[derive(Debug, Deserialize)]
-struct AssetContext {
-    #[serde(rename = "openInterest")]
-    open_interest: String,
-    #[serde(rename = "markPx")]
-    mark_price: String,
-    #[serde(rename = "funding")]
-    funding_rate: String,
-    #[serde(rename = "dayNtlVlm")]
-    volume_24h: String,
+/// Summary from an asset context: mark price, hourly funding, 24h notional
+/// volume and open interest in base units.
+pub fn market_summary(symbol: &Symbol, context: &AssetContext, timestamp: u64) -> Result<MarketSummary> {
+    let number = |field: &str, value: &str| value.parse::<f64>()
+        .map_err(|e| AggregatorError::ApiError(format!("Bad {} '{}' for {}: {}", field, value, symbol, e)));
+    Ok(MarketSummary {
+        symbol: symbol.to_string(),
+        price: number("mark price", &context.mark_px)?,
+        volume_24h: number("volume", &context.day_ntl_vlm)?,
+        open_interest: number("open interest", &context.open_interest)?,
+        funding_rate: number("funding", &context.funding)?,
+        timestamp,
+    })
 }
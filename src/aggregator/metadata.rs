@@ -0,0 +1,385 @@
+use crate::error::{AggregatorError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use super::endpoints;
+use super::exchange_id::ExchangeId;
+use crate::hyperliquid::meta::{info_url, MarginTable, MetaResponse};
+use crate::hyperliquid::AssetMeta;
+
+const DYDX_MARKETS_PATH: &str = "/v4/perpetualMarkets";
+
+/// Static-ish trading parameters for one market.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketSpec {
+    // Base asset, e.g. "BTC"
+    pub base: String,
+    pub max_leverage: f64,
+    pub size_decimals: Option<u32>,
+    pub tick_size: Option<f64>,
+    pub step_size: Option<f64>,
+    // Leverage caps by position size, ascending; empty where the venue
+    // applies max_leverage at every size
+    #[serde(default)]
+    pub margin_tiers: Vec<MarginTier>,
+    // Flat fractions of notional, where the venue publishes them (dYdX)
+    #[serde(default)]
+    pub initial_margin_fraction: Option<f64>,
+    #[serde(default)]
+    pub maintenance_margin_fraction: Option<f64>,
+    // Smallest order notional in USD; 0 where only the size step applies
+    #[serde(default)]
+    pub min_notional: f64,
+}
+
+/// Positions of at least `lower_bound` USD notional are capped at
+/// `max_leverage`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MarginTier {
+    pub lower_bound: f64,
+    pub max_leverage: f64,
+}
+
+impl MarketSpec {
+    /// Smallest base size increment, from the step or the size decimals.
+    pub fn size_step(&self) -> Option<f64> {
+        self.step_size.or_else(|| self.size_decimals.map(|decimals| 10f64.powi(-(decimals as i32))))
+    }
+}
+
+/// Smallest order notional a venue accepts, in USD; 0 where only the size
+/// step applies.
+pub fn min_order_notional(exchange: &ExchangeId) -> f64 {
+    match exchange {
+        ExchangeId::Hyperliquid => 10.0,
+        ExchangeId::Dydx | ExchangeId::Custom(_) => 0.0,
+    }
+}
+
+/// A market the venue no longer lists, or has flagged for final settlement.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delisting {
+    pub base: String,
+    // Price open positions were or will be settled at, when the venue says
+    pub settlement_price: Option<f64>,
+}
+
+impl Delisting {
+    pub fn describe(&self, exchange: &ExchangeId) -> String {
+        match self.settlement_price {
+            Some(price) => format!("{} delisted {}, settled at ${:.4}", exchange, self.base, price),
+            None => format!("{} delisted {}", exchange, self.base),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeMetadata {
+    // Millis timestamp of the fetch this came from
+    pub fetched_at: i64,
+    pub markets: Vec<MarketSpec>,
+    // Kept across refreshes so a delisted market stays terminal
+    #[serde(default)]
+    pub delisted: Vec<Delisting>,
+}
+
+impl ExchangeMetadata {
+    pub fn new(markets: Vec<MarketSpec>) -> Self {
+        Self {
+            fetched_at: Utc::now().timestamp_millis(),
+            markets,
+            delisted: Vec::new(),
+        }
+    }
+
+    pub fn age(&self, now_ms: i64) -> Duration {
+        Duration::from_millis((now_ms - self.fetched_at).max(0) as u64)
+    }
+
+    pub fn market(&self, base: &str) -> Option<&MarketSpec> {
+        self.markets.iter().find(|m| m.base == base)
+    }
+
+    pub fn delisting(&self, base: &str) -> Option<&Delisting> {
+        self.delisted.iter().find(|d| d.base == base)
+    }
+}
+
+/// "cached (2h old)"
+pub fn cached_label(age: Duration) -> String {
+    let secs = age.as_secs();
+    let age = if secs >= 86_400 {
+        format!("{}d", secs / 86_400)
+    } else if secs >= 3_600 {
+        format!("{}h", secs / 3_600)
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    };
+    format!("cached ({} old)", age)
+}
+
+/// Market specs per exchange, persisted to the config dir so a cold start on
+/// a bad connection still has leverage caps and sizing rules to show.
+#[derive(Debug)]
+pub struct MetadataCache {
+    path: PathBuf,
+    exchanges: HashMap<ExchangeId, ExchangeMetadata>,
+}
+
+pub type SharedMetadata = Arc<RwLock<MetadataCache>>;
+
+impl MetadataCache {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("trading_aggregator");
+        fs::create_dir_all(&config_dir)?;
+        Ok(config_dir.join("metadata_cache.json"))
+    }
+
+    /// Never fails: a missing or corrupt cache file just means starting empty.
+    pub fn load(path: PathBuf) -> Self {
+        let exchanges = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring corrupt metadata cache {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self { path, exchanges }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write-then-rename so a crash mid-write can't leave a truncated cache
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&self.exchanges)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    pub fn get(&self, exchange: &ExchangeId) -> Option<&ExchangeMetadata> {
+        self.exchanges.get(exchange)
+    }
+
+    /// Store a fresh fetch, diffing it against the previous one: markets
+    /// that dropped out of the universe are recorded as delisted, and earlier
+    /// delistings are kept unless the market was relisted. Returns the
+    /// delistings not on record before.
+    pub fn insert(&mut self, exchange: &ExchangeId, mut metadata: ExchangeMetadata) -> Vec<Delisting> {
+        let previous = self.exchanges.get(exchange);
+        if let Some(previous) = previous {
+            for market in &previous.markets {
+                if metadata.market(&market.base).is_none() && metadata.delisting(&market.base).is_none() {
+                    metadata.delisted.push(Delisting { base: market.base.clone(), settlement_price: None });
+                }
+            }
+            for earlier in &previous.delisted {
+                if metadata.market(&earlier.base).is_some() {
+                    continue;
+                }
+                match metadata.delisted.iter_mut().find(|d| d.base == earlier.base) {
+                    Some(current) => current.settlement_price = current.settlement_price.or(earlier.settlement_price),
+                    None => metadata.delisted.push(earlier.clone()),
+                }
+            }
+        }
+        let fresh = metadata.delisted.iter()
+            .filter(|d| previous.is_none_or(|previous| previous.delisting(&d.base).is_none()))
+            .cloned()
+            .collect();
+        self.exchanges.insert(exchange.clone(), metadata);
+        fresh
+    }
+
+    /// Whether `base` is delisted on `exchange`, as of the latest fetch.
+    pub fn delisting(&self, exchange: &ExchangeId, base: &str) -> Option<&Delisting> {
+        self.get(exchange)?.delisting(base)
+    }
+
+    pub fn needs_refresh(&self, exchange: &ExchangeId, refresh_after: Duration, now_ms: i64) -> bool {
+        self.get(exchange).is_none_or(|meta| meta.age(now_ms) >= refresh_after)
+    }
+
+    /// Cached spec for `base` together with its age, ignoring entries older
+    /// than `max_age`.
+    pub fn market(&self, exchange: &ExchangeId, base: &str, max_age: Duration, now_ms: i64) -> Option<(MarketSpec, Duration)> {
+        let meta = self.get(exchange)?;
+        let age = meta.age(now_ms);
+        if age > max_age {
+            return None;
+        }
+        meta.market(base).map(|spec| (spec.clone(), age))
+    }
+}
+
+/// An asset's margin tiers from its margin table id. Ids under 50 are a
+/// single tier at that leverage; unknown ids give no tiers.
+fn hyperliquid_margin_tiers(table_id: Option<u32>, tables: &[(u32, MarginTable)]) -> Vec<MarginTier> {
+    match table_id {
+        Some(id) if id < 50 => vec![MarginTier { lower_bound: 0.0, max_leverage: id as f64 }],
+        Some(id) => {
+            let Some((_, table)) = tables.iter().find(|(table_id, _)| *table_id == id) else {
+                return Vec::new();
+            };
+            let mut tiers: Vec<MarginTier> = table.margin_tiers.iter()
+                .filter_map(|tier| Some(MarginTier { lower_bound: tier.lower_bound.parse().ok()?, max_leverage: tier.max_leverage as f64 }))
+                .collect();
+            tiers.sort_by(|a, b| a.lower_bound.total_cmp(&b.lower_bound));
+            tiers
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Listed markets, and those the venue has flagged as delisted.
+pub fn parse_hyperliquid_meta(json: &str) -> Result<(Vec<MarketSpec>, Vec<Delisting>)> {
+    let meta: MetaResponse = serde_json::from_str(json)?;
+    let tables = meta.margin_tables;
+    let (listed, delisted): (Vec<AssetMeta>, Vec<AssetMeta>) = meta.universe.into_iter().partition(|asset| !asset.is_delisted);
+    let markets = listed.into_iter()
+        .map(|asset| MarketSpec {
+            margin_tiers: hyperliquid_margin_tiers(asset.margin_table_id, &tables),
+            base: asset.name,
+            max_leverage: asset.max_leverage as f64,
+            size_decimals: Some(asset.sz_decimals),
+            tick_size: None,
+            step_size: Some(10f64.powi(-(asset.sz_decimals as i32))),
+            initial_margin_fraction: None,
+            maintenance_margin_fraction: None,
+            min_notional: min_order_notional(&ExchangeId::Hyperliquid),
+        })
+        .collect();
+    let delisted = delisted.into_iter()
+        .map(|asset| Delisting { base: asset.name, settlement_price: None })
+        .collect();
+    Ok((markets, delisted))
+}
+
+/// Active markets, and those in final settlement at their oracle price.
+pub fn parse_dydx_markets(json: &str) -> Result<(Vec<MarketSpec>, Vec<Delisting>)> {
+    #[derive(Deserialize)]
+    struct Markets {
+        markets: HashMap<String, Market>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Market {
+        ticker: String,
+        initial_margin_fraction: String,
+        #[serde(default)]
+        maintenance_margin_fraction: Option<String>,
+        tick_size: String,
+        step_size: String,
+        #[serde(default)]
+        status: Option<String>,
+        #[serde(default)]
+        oracle_price: Option<String>,
+    }
+
+    let markets: Markets = serde_json::from_str(json)?;
+    let (settling, active): (Vec<Market>, Vec<Market>) = markets.markets.into_values()
+        .partition(|market| market.status.as_deref() == Some("FINAL_SETTLEMENT"));
+    let mut delisted: Vec<Delisting> = settling.into_iter()
+        .map(|market| Delisting {
+            base: market.ticker.strip_suffix("-USD").unwrap_or(&market.ticker).to_string(),
+            settlement_price: market.oracle_price.and_then(|price| price.parse().ok()),
+        })
+        .collect();
+    delisted.sort_by(|a, b| a.base.cmp(&b.base));
+    let mut specs: Vec<MarketSpec> = active.into_iter()
+        .filter_map(|market| {
+            let imf: f64 = market.initial_margin_fraction.parse().ok()?;
+            Some(MarketSpec {
+                base: market.ticker.strip_suffix("-USD").unwrap_or(&market.ticker).to_string(),
+                max_leverage: if imf > 0.0 { (1.0 / imf).floor() } else { 1.0 },
+                size_decimals: None,
+                tick_size: market.tick_size.parse().ok(),
+                step_size: market.step_size.parse().ok(),
+                // Flat: dYdX scales IMF with the market's open interest, not
+                // with the size of one position
+                margin_tiers: Vec::new(),
+                initial_margin_fraction: Some(imf),
+                maintenance_margin_fraction: market.maintenance_margin_fraction.and_then(|mmf| mmf.parse().ok()),
+                min_notional: min_order_notional(&ExchangeId::Dydx),
+            })
+        })
+        .collect();
+    specs.sort_by(|a, b| a.base.cmp(&b.base));
+    Ok((specs, delisted))
+}
+
+pub async fn fetch_metadata(exchange: &ExchangeId, testnet: bool) -> Result<ExchangeMetadata> {
+    let client = reqwest::Client::new();
+    let (markets, delisted) = match exchange {
+        ExchangeId::Hyperliquid => {
+            let response = client.post(info_url(testnet))
+                .json(&serde_json::json!({ "type": "meta" }))
+                .send()
+                .await?;
+            parse_hyperliquid_meta(&response.text().await?)?
+        }
+        ExchangeId::Dydx => {
+            let response = client.get(endpoints::dydx().url(DYDX_MARKETS_PATH)).send().await?;
+            parse_dydx_markets(&response.text().await?)?
+        }
+        ExchangeId::Custom(_) => return Err(AggregatorError::ExchangeError(format!("No metadata source for {}", exchange))),
+    };
+    Ok(ExchangeMetadata { delisted, ..ExchangeMetadata::new(markets) })
+}
+
+/// Background preload: refreshes each exchange whose cache entry is missing
+/// or older than `refresh_after`, retrying with backoff until it succeeds,
+/// then again every `refresh_after` so delistings are picked up. Runs off
+/// the UI path; readers see cached values until it lands.
+pub fn spawn_preload(cache: SharedMetadata, exchanges: Vec<ExchangeId>, refresh_after: Duration, testnet: bool) -> Vec<tokio::task::JoinHandle<()>> {
+    // A zero interval would refetch in a tight loop
+    let refresh_after = refresh_after.max(Duration::from_secs(60));
+    exchanges.into_iter()
+        .map(|exchange| {
+            let cache = cache.clone();
+            tokio::spawn(async move {
+                let mut attempt: u64 = 0;
+                loop {
+                    let now = Utc::now().timestamp_millis();
+                    let due = cache.read().await.get(&exchange)
+                        .map_or(Duration::ZERO, |meta| refresh_after.saturating_sub(meta.age(now)));
+                    if attempt == 0 && !due.is_zero() {
+                        tokio::time::sleep(due).await;
+                    }
+
+                    attempt += 1;
+                    match fetch_metadata(&exchange, testnet).await {
+                        Ok(metadata) => {
+                            info!("Loaded {} markets for {}", metadata.markets.len(), exchange);
+                            let mut cache = cache.write().await;
+                            for delisting in cache.insert(&exchange, metadata) {
+                                warn!("{}", delisting.describe(&exchange));
+                            }
+                            if let Err(e) = cache.save() {
+                                warn!("Failed to save metadata cache: {}", e);
+                            }
+                            attempt = 0;
+                        }
+                        Err(e) => {
+                            warn!("Metadata fetch for {} failed (attempt {}): {}", exchange, attempt, e);
+                            let wait_time = std::cmp::min(attempt * 5, 60);
+                            tokio::time::sleep(Duration::from_secs(wait_time)).await;
+                        }
+                    }
+                }
+            })
+        })
+        .collect()
+}
@@ -1,18 +1,55 @@
 pub mod types;
+pub mod symbol;
+pub mod exchange_id;
+pub mod metadata;
+pub mod health;
+pub mod endpoints;
+pub mod funding;
+pub mod venue_status;
+pub mod rest_fallback;
+pub mod feed_tasks;
+pub mod retry;
+pub mod trade_flow;
+pub mod price_history;
+pub mod book_quality;
 pub mod traits;
 pub mod hyperliquid;
 pub mod dydx;
+pub mod dydx_book;
 pub mod websocket;
+pub mod assets;
 
-use anyhow::Result;
+use crate::error::Result;
 use std::collections::HashMap;
 use crate::config::AggregatorConfig;
+use crate::error::AggregatorError;
+use crate::hyperliquid::MetaCache;
+use crate::risk::{self, MarginComparison};
 use async_trait::async_trait;
 use traits::ExchangeAggregator;
 use dydx::DydxAggregator;
 use hyperliquid::HyperliquidAggregator;
-use types::{LeverageInfo, OrderBook, MarketSummary};
+use types::{aggregate_books, AggregatedOrderBook, ImpactEstimate, LeverageInfo, OrderBook, MarketSummary};
+use symbol::Symbol;
+use exchange_id::ExchangeId;
+use funding::FundingSpread;
+use metadata::{Delisting, MarketSpec, MetadataCache, SharedMetadata};
+use health::{HealthRegistry, SharedHealth};
+use endpoints::{EndpointSelector, SharedEndpoints};
+use trade_flow::{SharedTradeFlow, TradeFlow, TradeFlowSnapshot};
+use price_history::{PriceHistory, SharedPriceHistory, PRICE_HISTORY_SAMPLES};
+use book_quality::SharedBookQuality;
+use rest_fallback::FallbackPolicy;
 use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use tokio::sync::watch;
+
+const CLOCK_PROBE_INTERVAL_SECS: u64 = 60;
+// Books older than this are left out of the cross-venue book
+const AGGREGATED_BOOK_MAX_AGE_MS: u64 = 10_000;
 
 #[derive(Debug, Clone)]
 pub enum Exchange {
@@ -20,40 +57,70 @@ pub enum Exchange {
     Hyperliquid(HyperliquidAggregator),
 }
 
+impl Exchange {
+    pub fn id(&self) -> ExchangeId {
+        match self {
+            Exchange::Dydx(_) => ExchangeId::Dydx,
+            Exchange::Hyperliquid(_) => ExchangeId::Hyperliquid,
+        }
+    }
+
+    pub async fn cached_orderbook(&self) -> Option<OrderBook> {
+        match self {
+            Exchange::Dydx(e) => e.cached_orderbook().await,
+            Exchange::Hyperliquid(e) => e.cached_orderbook().await,
+        }
+    }
+}
+
 #[async_trait]
 impl ExchangeAggregator for Exchange {
     async fn new(testnet: bool) -> Result<Self> {
         unimplemented!("Use specific constructor")
     }
 
-    async fn start_market_updates(&mut self, symbol: &str) -> Result<()> {
+    async fn start_market_updates(&mut self, symbol: &Symbol) -> Result<()> {
         match self {
             Exchange::Dydx(e) => e.start_market_updates(symbol).await,
             Exchange::Hyperliquid(e) => e.start_market_updates(symbol).await,
         }
     }
 
-    async fn get_market_summary(&self, symbol: &str) -> Result<MarketSummary> {
+    async fn stop_market_updates(&self) {
+        match self {
+            Exchange::Dydx(e) => e.stop_market_updates().await,
+            Exchange::Hyperliquid(e) => e.stop_market_updates().await,
+        }
+    }
+
+    async fn get_market_summary(&self, symbol: &Symbol) -> Result<MarketSummary> {
         match self {
             Exchange::Dydx(e) => e.get_market_summary(symbol).await,
             Exchange::Hyperliquid(e) => e.get_market_summary(symbol).await,
         }
     }
 
-    async fn get_leverage_info(&self, symbol: &str) -> Result<LeverageInfo> {
+    async fn get_leverage_info(&self, symbol: &Symbol) -> Result<LeverageInfo> {
         match self {
             Exchange::Dydx(e) => e.get_leverage_info(symbol).await,
             Exchange::Hyperliquid(e) => e.get_leverage_info(symbol).await,
         }
     }
 
-    async fn get_orderbook(&self, symbol: &str) -> Result<OrderBook> {
+    async fn get_orderbook(&self, symbol: &Symbol) -> Result<OrderBook> {
         match self {
             Exchange::Dydx(e) => e.get_orderbook(symbol).await,
             Exchange::Hyperliquid(e) => e.get_orderbook(symbol).await,
         }
     }
 
+    fn subscribe_orderbook(&self, symbol: &Symbol) -> Result<watch::Receiver<Option<OrderBook>>> {
+        match self {
+            Exchange::Dydx(e) => e.subscribe_orderbook(symbol),
+            Exchange::Hyperliquid(e) => e.subscribe_orderbook(symbol),
+        }
+    }
+
     async fn get_available_assets(&self) -> Result<Vec<String>> {
         match self {
             Exchange::Dydx(e) => e.get_available_assets().await,
@@ -71,32 +138,280 @@ impl ExchangeAggregator for Exchange {
 
 pub struct DerivativesAggregator {
     config: AggregatorConfig,
-    pub exchanges: HashMap<String, Exchange>,
-    last_known_summaries: HashMap<String, types::MarketSummary>,
+    exchanges: HashMap<ExchangeId, Exchange>,
+    last_known_summaries: HashMap<ExchangeId, types::MarketSummary>,
+    pub metadata: SharedMetadata,
+    pub health: SharedHealth,
+    pub trade_flow: SharedTradeFlow,
+    pub endpoints: SharedEndpoints,
+    pub price_history: SharedPriceHistory,
+    // Latest books and spreads the router checks market orders against
+    pub book_quality: SharedBookQuality,
+    // Hyperliquid asset metadata, shared with the trading side
+    pub hl_meta: Arc<MetaCache>,
+    background: Vec<tokio::task::JoinHandle<()>>,
+    // Status probe, price sampler and metadata preload over the current
+    // venues; respawned when one is registered or removed
+    venue_tasks: Vec<tokio::task::JoinHandle<()>>,
 }
 
 impl DerivativesAggregator {
     pub async fn new(config: AggregatorConfig) -> Result<Self> {
         let mut exchanges = HashMap::new();
+        let dydx_enabled = config.exchanges.contains(&ExchangeId::Dydx);
+        let health: SharedHealth = Arc::new(HealthRegistry::default());
+        let trade_flow: SharedTradeFlow = Arc::new(std::sync::Mutex::new(TradeFlow::new(
+            Duration::from_secs(config.trade_flow_window_secs),
+            config.volume_profile_bucket_ticks,
+        )));
+        // Installed before any dYdX consumer starts so they all share it
+        let endpoints = endpoints::install_dydx(Arc::new(EndpointSelector::from_config(&config).with_health(health.clone())));
         
-        exchanges.insert(
-            "dYdX".to_string(),
-            Exchange::Dydx(DydxAggregator::new(config.testnet).await?)
-        );
-        
-        exchanges.insert(
-            "Hyperliquid".to_string(),
-            Exchange::Hyperliquid(HyperliquidAggregator::new(config.testnet).await?)
-        );
-        
-        Ok(Self { 
+        // Disabled venues are never constructed: no clients, feeds or probes
+        let mut background = Vec::new();
+        if dydx_enabled {
+            exchanges.insert(
+                ExchangeId::Dydx,
+                Exchange::Dydx(DydxAggregator::new(config.testnet).await?
+                    .with_health(health.clone())
+                    .with_trade_flow(trade_flow.clone())
+                    .with_fallback(FallbackPolicy::from_config(&config, &ExchangeId::Dydx))
+                    .with_depth(config.book_depth))
+            );
+
+            // dYdX feeds carry no server time, so its clock is sampled separately
+            let dydx_health = health.clone();
+            background.push(tokio::spawn(async move {
+                loop {
+                    if let Err(e) = dydx::measure_clock_skew(&dydx_health).await {
+                        tracing::warn!("dYdX clock skew probe failed: {}", e);
+                    }
+                    tokio::time::sleep(Duration::from_secs(CLOCK_PROBE_INTERVAL_SECS)).await;
+                }
+            }));
+            background.extend(endpoints::spawn_probe(endpoints.clone()));
+        }
+
+        let hl_meta = MetaCache::hyperliquid_on(config.testnet);
+        if config.exchanges.contains(&ExchangeId::Hyperliquid) {
+            exchanges.insert(
+                ExchangeId::Hyperliquid,
+                Exchange::Hyperliquid(HyperliquidAggregator::new(config.testnet).await?
+                    .with_meta(hl_meta.clone())
+                    .with_health(health.clone())
+                    .with_trade_flow(trade_flow.clone())
+                    .with_fallback(FallbackPolicy::from_config(&config, &ExchangeId::Hyperliquid))
+                    .with_depth(config.book_depth))
+            );
+        }
+
+        let price_history: SharedPriceHistory = Arc::new(std::sync::Mutex::new(PriceHistory::new(
+            Duration::from_secs(config.price_history_interval_secs.max(1)),
+            PRICE_HISTORY_SAMPLES,
+            price_history::MAX_MARKETS,
+        )));
+
+        // Serve last session's metadata immediately, refresh it in the background
+        let cache_path = MetadataCache::default_path()
+            .unwrap_or_else(|_| PathBuf::from("metadata_cache.json"));
+        let metadata = Arc::new(tokio::sync::RwLock::new(MetadataCache::load(cache_path)));
+
+        let mut aggregator = Self { 
             config, 
             exchanges,
             last_known_summaries: HashMap::new(),
-        })
+            metadata,
+            health,
+            trade_flow,
+            endpoints,
+            price_history,
+            book_quality: SharedBookQuality::default(),
+            hl_meta,
+            background,
+            venue_tasks: Vec::new(),
+        };
+        aggregator.respawn_venue_tasks();
+        Ok(aggregator)
+    }
+
+    fn respawn_venue_tasks(&mut self) {
+        for handle in self.venue_tasks.drain(..) {
+            handle.abort();
+        }
+        self.venue_tasks.push(venue_status::spawn_status_probe(self.health.clone(), self.exchanges.keys().cloned().collect(), self.config.testnet));
+        self.venue_tasks.push(price_history::spawn_sampler(self.price_history.clone(), self.exchanges.values().cloned().collect()));
+        self.venue_tasks.push(book_quality::spawn_sampler(self.book_quality.clone(), self.exchanges.values().cloned().collect()));
+        self.venue_tasks.extend(metadata::spawn_preload(
+            self.metadata.clone(),
+            self.exchanges.keys().cloned().collect(),
+            Duration::from_secs(self.config.metadata_refresh_secs),
+            self.config.testnet,
+        ));
+    }
+
+    /// Add a venue, or replace the one registered under the same name. The
+    /// name must be the venue's own, e.g. "hyperliquid" for a Hyperliquid
+    /// aggregator.
+    pub async fn register_exchange(&mut self, name: &str, exchange: Exchange) -> Result<ExchangeId> {
+        let id: ExchangeId = name.parse()?;
+        if id != exchange.id() {
+            return Err(AggregatorError::ExchangeError(format!("Cannot register a {} aggregator as '{}'", exchange.id(), name.trim())));
+        }
+        if let Some(previous) = self.exchanges.insert(id.clone(), exchange) {
+            previous.stop_market_updates().await;
+        }
+        self.respawn_venue_tasks();
+        Ok(id)
     }
 
-    pub async fn display_aggregated_data(&mut self, symbol: &str) {
+    /// Stop and drop a venue; everything else carries on with the rest.
+    pub async fn remove_exchange(&mut self, name: &str) -> Option<Exchange> {
+        let id: ExchangeId = name.parse().ok()?;
+        let exchange = self.exchanges.remove(&id)?;
+        exchange.stop_market_updates().await;
+        self.last_known_summaries.remove(&id);
+        self.respawn_venue_tasks();
+        Some(exchange)
+    }
+
+    /// Which venues list `symbol`'s base asset, and which couldn't say
+    /// because their asset list failed to load.
+    pub async fn asset_listings(&self, symbol: &Symbol) -> (Vec<ExchangeId>, Vec<ExchangeId>) {
+        let mut listed = Vec::new();
+        let mut unknown = Vec::new();
+        for exchange_id in self.exchange_ids() {
+            let Some(exchange) = self.exchanges.get(&exchange_id) else { continue };
+            match exchange.get_available_assets().await {
+                Ok(assets) if assets.iter().any(|asset| asset == symbol.base()) => listed.push(exchange_id),
+                Ok(_) => {}
+                Err(_) => unknown.push(exchange_id),
+            }
+        }
+        (listed, unknown)
+    }
+
+    /// Every venue's asset list merged by base, noting venues whose list
+    /// failed to load
+    pub async fn asset_directory(&self) -> assets::AssetDirectory {
+        let mut listings = Vec::new();
+        let mut failed = Vec::new();
+        for exchange_id in self.exchange_ids() {
+            let Some(exchange) = self.exchanges.get(&exchange_id) else { continue };
+            match exchange.get_available_assets().await {
+                Ok(assets) => listings.push((exchange_id, assets)),
+                Err(e) => failed.push((exchange_id, e.to_string())),
+            }
+        }
+        assets::AssetDirectory::from_listings(listings, failed)
+    }
+
+    pub fn exchange(&self, id: &ExchangeId) -> Option<&Exchange> {
+        self.exchanges.get(id)
+    }
+
+    pub fn has_exchange(&self, id: &ExchangeId) -> bool {
+        self.exchanges.contains_key(id)
+    }
+
+    /// Every registered venue, in no particular order
+    pub fn exchanges(&self) -> impl Iterator<Item = (&ExchangeId, &Exchange)> {
+        self.exchanges.iter()
+    }
+
+    /// The venues currently registered, in a stable order
+    pub fn exchange_ids(&self) -> Vec<ExchangeId> {
+        let mut ids: Vec<ExchangeId> = self.exchanges.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Abort the aggregator's own background tasks and every venue's feed.
+    pub async fn shutdown(&mut self) {
+        for handle in self.background.drain(..).chain(self.venue_tasks.drain(..)) {
+            handle.abort();
+        }
+        for exchange in self.exchanges.values() {
+            exchange.stop_market_updates().await;
+        }
+    }
+
+    /// Live max leverage, falling back to the metadata cache when the venue
+    /// can't be reached. The note is set when the value came from the cache.
+    pub async fn get_max_leverage(&self, exchange: &ExchangeId, symbol: &Symbol) -> Option<(f64, Option<String>)> {
+        if let Some(exch) = self.exchanges.get(exchange) {
+            if let Ok(info) = exch.get_leverage_info(symbol).await {
+                return Some((info.max_leverage, None));
+            }
+        }
+
+        let max_age = Duration::from_secs(self.config.metadata_max_age_secs);
+        let now = Utc::now().timestamp_millis();
+        self.metadata.read().await
+            .market(exchange, symbol.base(), max_age, now)
+            .map(|(spec, age)| (spec.max_leverage, Some(metadata::cached_label(age))))
+    }
+
+    /// Cached price tick for a market, when the venue publishes one.
+    pub async fn tick_size(&self, exchange: &ExchangeId, symbol: &Symbol) -> Option<f64> {
+        self.market_spec(exchange, symbol).await.and_then(|spec| spec.tick_size)
+    }
+
+    /// Cached sizing rules for the market, if not too old to trust.
+    pub async fn market_spec(&self, exchange: &ExchangeId, symbol: &Symbol) -> Option<MarketSpec> {
+        let max_age = Duration::from_secs(self.config.metadata_max_age_secs);
+        let now = Utc::now().timestamp_millis();
+        self.metadata.read().await
+            .market(exchange, symbol.base(), max_age, now)
+            .map(|(spec, _)| spec)
+    }
+
+    /// Margin each venue asks for `notional` of `symbol`, from the cached
+    /// market specs.
+    pub async fn margin_comparison(&self, symbol: &Symbol, notional: f64) -> MarginComparison {
+        let mut specs = Vec::new();
+        for exchange in self.exchange_ids() {
+            let spec = self.market_spec(&exchange, symbol).await;
+            specs.push((exchange, spec));
+        }
+        risk::margin_comparison(symbol, notional, &specs)
+    }
+
+    /// The market's delisting on `exchange`, if the latest metadata shows one.
+    pub async fn delisting(&self, exchange: &ExchangeId, symbol: &Symbol) -> Option<Delisting> {
+        self.metadata.read().await.delisting(exchange, symbol.base()).cloned()
+    }
+
+    /// Every delisting on record, per exchange.
+    pub async fn delistings(&self) -> Vec<(ExchangeId, Delisting)> {
+        let metadata = self.metadata.read().await;
+        self.exchange_ids().into_iter()
+            .flat_map(|exchange| {
+                let delisted = metadata.get(&exchange).map(|meta| meta.delisted.clone()).unwrap_or_default();
+                delisted.into_iter().map(move |delisting| (exchange.clone(), delisting))
+            })
+            .collect()
+    }
+
+    /// Up to the last `n` sampled mids for the market, oldest first.
+    pub fn price_history(&self, exchange: &ExchangeId, symbol: &Symbol, n: usize) -> Vec<f64> {
+        self.price_history.lock()
+            .map(|history| history.recent(exchange, &symbol.to_string(), n))
+            .unwrap_or_default()
+    }
+
+    /// Volume profile and cumulative delta for the market's trades feed, or
+    /// None until the first print arrives.
+    pub async fn trade_flow(&self, exchange: &ExchangeId, symbol: &Symbol, cvd_points: usize) -> Option<TradeFlowSnapshot> {
+        let tick = self.tick_size(exchange, symbol).await;
+        let market = symbol.to_string();
+        let mut flow = self.trade_flow.lock().ok()?;
+        if let Some(tick) = tick {
+            flow.set_tick(exchange, &market, tick);
+        }
+        flow.snapshot(exchange, &market, Utc::now().timestamp_millis(), cvd_points)
+    }
+
+    pub async fn display_aggregated_data(&mut self, symbol: &Symbol) {
         print!("\x1B[u\x1B[J");
         
         println!("Aggregated Market Data for {} (Press Ctrl+C to exit)\n", symbol);
@@ -198,8 +513,15 @@ impl DerivativesAggregator {
         let _ = std::io::stdout().flush();
     }
 
-    pub async fn start_all_market_updates(&mut self, symbol: &str) -> Result<()> {
-        for exchange in self.exchanges.values_mut() {
+    /// (Re)start every venue's feed for `symbol`. Venues that delisted the
+    /// market get their feed stopped instead of resubscribing to nothing.
+    pub async fn start_all_market_updates(&mut self, symbol: &Symbol) -> Result<()> {
+        let metadata = self.metadata.clone();
+        for (exchange_id, exchange) in self.exchanges.iter_mut() {
+            if metadata.read().await.delisting(exchange_id, symbol.base()).is_some() {
+                exchange.stop_market_updates().await;
+                continue;
+            }
             if let Err(e) = exchange.start_market_updates(symbol).await {
                 eprintln!("Failed to start updates for exchange: {}", e);
                 // Continue with other exchanges even if one fails
@@ -209,7 +531,7 @@ impl DerivativesAggregator {
         Ok(())
     }
 
-    pub async fn display_market_summaries(&mut self, symbol: &str) {
+    pub async fn display_market_summaries(&mut self, symbol: &Symbol) {
         // Update market summaries before displaying
         let mut futures = Vec::new();
         
@@ -241,9 +563,8 @@ impl DerivativesAggregator {
         println!("{:-<74}", "");
         
         for (name, summary) in &self.last_known_summaries {
-            let leverage = if let Ok(info) = self.exchanges.get(name)
-                .unwrap()
-                .get_leverage_info(symbol).await {
+            let Some(exchange) = self.exchanges.get(name) else { continue };
+            let leverage = if let Ok(info) = exchange.get_leverage_info(symbol).await {
                 info.max_leverage
             } else {
                 20.0
@@ -259,7 +580,7 @@ impl DerivativesAggregator {
         }
     }
 
-    pub async fn display_exchange_orderbook(&self, exchange: &str, symbol: &str) {
+    pub async fn display_exchange_orderbook(&self, exchange: &ExchangeId, symbol: &Symbol) {
         if let Some(exchange) = self.exchanges.get(exchange) {
             // Always fetch fresh orderbook data
             if let Ok(book) = exchange.get_orderbook(symbol).await {
@@ -301,19 +622,94 @@ impl DerivativesAggregator {
         }
     }
 
-    pub async fn get_exchange_orderbook(&self, exchange: &str, symbol: &str) -> Result<OrderBook> {
+    pub async fn get_exchange_orderbook(&self, exchange: &ExchangeId, symbol: &Symbol) -> Result<OrderBook> {
+        self.ensure_listed(exchange, symbol).await?;
         if let Some(exch) = self.exchanges.get(exchange) {
             exch.get_orderbook(symbol).await
         } else {
-            Err(anyhow::anyhow!("Exchange not found"))
+            Err(AggregatorError::ExchangeError(format!("{} is not connected", exchange)))
         }
     }
 
-    pub async fn get_exchange_summary(&self, exchange: &str, symbol: &str) -> Result<MarketSummary> {
+    /// What a market order of `usd_value` would cost against `exchange`'s
+    /// current book
+    pub async fn estimate_market_impact(&self, exchange: &ExchangeId, symbol: &Symbol, usd_value: f64, is_buy: bool) -> Result<ImpactEstimate> {
+        if usd_value.is_nan() || usd_value <= 0.0 {
+            return Err(AggregatorError::InvalidAmount(format!("${:.2} is not a positive notional", usd_value)));
+        }
+        let book = self.get_exchange_orderbook(exchange, symbol).await?;
+        book.market_impact(is_buy, usd_value).ok_or_else(|| {
+            AggregatorError::MarketDataNotFound(format!("{} {} book has no {}", exchange, symbol, if is_buy { "asks" } else { "bids" }))
+        })
+    }
+
+    /// Change notifications for one venue's streamed book of `symbol`
+    pub fn subscribe_orderbook(&self, exchange: &ExchangeId, symbol: &Symbol) -> Result<watch::Receiver<Option<OrderBook>>> {
+        match self.exchanges.get(exchange) {
+            Some(exch) => exch.subscribe_orderbook(symbol),
+            None => Err(AggregatorError::ExchangeError(format!("{} is not connected", exchange))),
+        }
+    }
+
+    /// Every venue's book for `symbol` in one, each level tagged with its
+    /// venue. A venue that fails or has a stale book is skipped and listed in
+    /// `skipped`; errors only when no venue contributes.
+    pub async fn get_aggregated_orderbook(&self, symbol: &Symbol) -> Result<AggregatedOrderBook> {
+        let mut venues: Vec<&ExchangeId> = self.exchanges.keys().collect();
+        venues.sort();
+        let mut books = Vec::new();
+        for exchange in venues {
+            let book = self.get_exchange_orderbook(exchange, symbol).await.map_err(|e| e.to_string());
+            books.push((exchange.clone(), book));
+        }
+        let aggregated = aggregate_books(&symbol.to_string(), books, AGGREGATED_BOOK_MAX_AGE_MS, Utc::now().timestamp_millis() as u64);
+        if aggregated.sources.is_empty() {
+            let reasons: Vec<String> = aggregated.skipped.iter().map(|(exchange, reason)| format!("{}: {}", exchange, reason)).collect();
+            return Err(AggregatorError::MarketDataNotFound(format!("No venue has a book for {} ({})", symbol, reasons.join(", "))));
+        }
+        Ok(aggregated)
+    }
+
+    /// Funding spreads for `symbols` across every venue, widest first, at
+    /// least `min_spread` percent APR. Summaries are fetched concurrently;
+    /// symbols not listed on two venues are skipped.
+    pub async fn find_funding_spreads(&self, symbols: &[Symbol], min_spread: f64) -> Vec<FundingSpread> {
+        let scans = symbols.iter().map(|symbol| async move {
+            let quotes = self.exchanges.keys().map(|exchange_id| async move {
+                self.get_exchange_summary(exchange_id, symbol).await
+                    .ok()
+                    .map(|summary| (exchange_id.clone(), summary.funding_rate))
+            });
+            let mut rates: Vec<(ExchangeId, f64)> = futures::future::join_all(quotes).await.into_iter().flatten().collect();
+            // HashMap order varies; keep tie-breaks stable
+            rates.sort_by(|a, b| a.0.cmp(&b.0));
+            funding::best_funding_spread(symbol, &rates)
+        });
+        let mut spreads: Vec<FundingSpread> = futures::future::join_all(scans).await.into_iter()
+            .flatten()
+            .filter(|spread| spread.annualized_spread >= min_spread)
+            .collect();
+        spreads.sort_by(|a, b| b.annualized_spread.total_cmp(&a.annualized_spread));
+        spreads
+    }
+
+    pub async fn get_exchange_summary(&self, exchange: &ExchangeId, symbol: &Symbol) -> Result<MarketSummary> {
+        self.ensure_listed(exchange, symbol).await?;
         if let Some(exch) = self.exchanges.get(exchange) {
             exch.get_market_summary(symbol).await
         } else {
-            Err(anyhow::anyhow!("Exchange not found"))
+            Err(AggregatorError::ExchangeError(format!("{} is not connected", exchange)))
+        }
+    }
+
+    // Data for a delisted market is terminal; don't serve the last cached copy
+    async fn ensure_listed(&self, exchange: &ExchangeId, symbol: &Symbol) -> Result<()> {
+        if self.delisting(exchange, symbol).await.is_some() {
+            return Err(AggregatorError::MarketDelisted { exchange: exchange.clone(), symbol: symbol.to_string() });
         }
+        Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,121 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::Utc;
+use super::exchange_id::ExchangeId;
+use super::types::OrderBook;
+use super::Exchange;
+
+// Samples kept per market: one sparkline's worth
+pub const PRICE_HISTORY_SAMPLES: usize = 60;
+// Markets kept at once; the least recently sampled is dropped past this
+pub const MAX_MARKETS: usize = 32;
+// Books older than this are a dead feed, not a flat price
+const MAX_BOOK_AGE_MS: i64 = 30_000;
+
+#[derive(Debug, Clone, Default)]
+struct Series {
+    mids: VecDeque<f64>,
+    // Millis timestamp of the latest sample
+    last_sample: i64,
+}
+
+/// Recent mid prices per (exchange, market), one sample per interval. Kept
+/// across symbol switches for the session, within a fixed memory budget.
+#[derive(Debug, Clone)]
+pub struct PriceHistory {
+    interval_ms: i64,
+    capacity: usize,
+    max_markets: usize,
+    series: HashMap<(ExchangeId, String), Series>,
+}
+
+pub type SharedPriceHistory = Arc<Mutex<PriceHistory>>;
+
+impl Default for PriceHistory {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5), PRICE_HISTORY_SAMPLES, MAX_MARKETS)
+    }
+}
+
+impl PriceHistory {
+    pub fn new(interval: Duration, capacity: usize, max_markets: usize) -> Self {
+        Self {
+            interval_ms: interval.as_millis().max(1) as i64,
+            capacity: capacity.max(1),
+            max_markets: max_markets.max(1),
+            series: HashMap::new(),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms as u64)
+    }
+
+    /// Add a sample unless the market was sampled less than an interval ago.
+    /// Returns whether it was kept.
+    pub fn record(&mut self, exchange: &ExchangeId, market: &str, mid: f64, now_ms: i64) -> bool {
+        if !mid.is_finite() || mid <= 0.0 {
+            return false;
+        }
+        let key = (exchange.clone(), market.to_string());
+        if let Some(series) = self.series.get(&key) {
+            // Timer ticks can land a little early, so allow a tenth of slack
+            if now_ms - series.last_sample < self.interval_ms - self.interval_ms / 10 {
+                return false;
+            }
+        } else if self.series.len() >= self.max_markets {
+            let oldest = self.series.iter()
+                .min_by_key(|(_, series)| series.last_sample)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.series.remove(&oldest);
+            }
+        }
+
+        let series = self.series.entry(key).or_default();
+        series.mids.push_back(mid);
+        while series.mids.len() > self.capacity {
+            series.mids.pop_front();
+        }
+        series.last_sample = now_ms;
+        true
+    }
+
+    /// Up to the last `n` mids, oldest first.
+    pub fn recent(&self, exchange: &ExchangeId, market: &str, n: usize) -> Vec<f64> {
+        self.series.get(&(exchange.clone(), market.to_string()))
+            .map(|series| series.mids.iter().skip(series.mids.len().saturating_sub(n)).copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn markets(&self) -> usize {
+        self.series.len()
+    }
+
+    /// Sample a venue's cached book, if it is fresh and two-sided.
+    pub fn record_book(&mut self, book: &OrderBook, now_ms: i64) -> bool {
+        if now_ms - book.timestamp as i64 > MAX_BOOK_AGE_MS {
+            return false;
+        }
+        let Some(mid) = book.mid_price() else { return false };
+        self.record(&book.exchange, &book.symbol, mid, now_ms)
+    }
+}
+
+/// Sample every venue's cached book once per interval until aborted.
+pub fn spawn_sampler(history: SharedPriceHistory, exchanges: Vec<Exchange>) -> tokio::task::JoinHandle<()> {
+    let interval = history.lock().map(|history| history.interval()).unwrap_or(Duration::from_secs(5));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for exchange in &exchanges {
+                let Some(book) = exchange.cached_orderbook().await else { continue };
+                if let Ok(mut history) = history.lock() {
+                    history.record_book(&book, Utc::now().timestamp_millis());
+                }
+            }
+        }
+    })
+}
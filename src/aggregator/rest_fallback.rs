@@ -0,0 +1,212 @@
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::Utc;
+use hyperliquid_rust_sdk::InfoClient;
+use serde::Deserialize;
+use tokio::sync::{watch, Mutex};
+use crate::config::AggregatorConfig;
+use crate::error::{AggregatorError, Result};
+use super::endpoints;
+use super::exchange_id::ExchangeId;
+use super::health::SharedHealth;
+use super::symbol::Symbol;
+use super::types::{BookSource, Level, OrderBook};
+
+const DYDX_ORDERBOOK_PATH: &str = "/v4/orderbooks/perpetualMarket";
+
+/// When a venue's book falls back from the websocket to REST polling.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FallbackPolicy {
+    // Poll from the start and never try the websocket
+    pub forced: bool,
+    pub max_ws_failures: u32,
+    pub poll_interval: Duration,
+    // Levels kept per side; snapshots are larger than the streamed book
+    pub depth: usize,
+    // Gap between websocket retries while polling
+    pub ws_retry_interval: Duration,
+}
+
+impl Default for FallbackPolicy {
+    fn default() -> Self {
+        Self {
+            forced: false,
+            max_ws_failures: 3,
+            poll_interval: Duration::from_secs(2),
+            depth: 10,
+            ws_retry_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl FallbackPolicy {
+    pub fn from_config(config: &AggregatorConfig, venue: &ExchangeId) -> Self {
+        Self {
+            forced: config.rest_fallback_venues.contains(venue),
+            max_ws_failures: config.ws_failures_before_fallback.max(1),
+            poll_interval: Duration::from_millis(config.rest_poll_interval_ms.max(250)),
+            depth: config.rest_poll_depth.max(1),
+            ..Self::default()
+        }
+    }
+}
+
+/// A REST orderbook snapshot endpoint.
+#[async_trait]
+pub trait SnapshotSource: Send + Sync {
+    async fn snapshot(&self, depth: usize) -> Result<OrderBook>;
+}
+
+/// Latest book of a venue feed. Writers replace it; readers subscribe for
+/// change notifications or borrow the current value.
+pub type SharedBook = Arc<watch::Sender<Option<OrderBook>>>;
+
+pub fn shared_book() -> SharedBook {
+    Arc::new(watch::channel(None).0)
+}
+
+/// Subscribe to `book` if its feed follows `symbol`. A feed carries whatever
+/// symbol it was last started for, so receivers should still check
+/// `OrderBook::symbol` after a symbol change.
+pub fn feed_receiver(book: &SharedBook, following: Option<&str>, symbol: &Symbol) -> Result<watch::Receiver<Option<OrderBook>>, AggregatorError> {
+    match following {
+        Some(current) if current == symbol.to_string() => Ok(book.subscribe()),
+        Some(current) => Err(AggregatorError::MarketDataNotFound(format!("Feed follows {}, not {}", current, symbol))),
+        None => Err(AggregatorError::MarketDataNotFound(format!("No feed started for {}", symbol))),
+    }
+}
+
+/// Poll one snapshot into `book` while the venue is on REST fallback.
+/// Returns whether the book was written.
+pub async fn poll_once(source: &dyn SnapshotSource, book: &SharedBook, health: &SharedHealth, venue: &ExchangeId, depth: usize) -> Result<bool> {
+    if !health.transport(venue).is_fallback() {
+        return Ok(false);
+    }
+    let mut snapshot = source.snapshot(depth).await?;
+    // The websocket may have come back while the request was in flight
+    if !health.transport(venue).is_fallback() {
+        return Ok(false);
+    }
+    snapshot.source = BookSource::Polled;
+    snapshot.bids.truncate(depth);
+    snapshot.asks.truncate(depth);
+    book.send_replace(Some(snapshot));
+    Ok(true)
+}
+
+/// Background poller for one venue; idle while the websocket is healthy.
+pub fn spawn_poller(source: Box<dyn SnapshotSource>, book: SharedBook, health: SharedHealth, venue: ExchangeId, policy: FallbackPolicy) -> tokio::task::JoinHandle<()> {
+    if policy.forced {
+        health.force_rest(&venue, policy.poll_interval);
+    }
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = poll_once(source.as_ref(), &book, &health, &venue, policy.depth).await {
+                tracing::warn!("{} REST snapshot failed: {}", venue, e);
+            }
+            tokio::time::sleep(policy.poll_interval).await;
+        }
+    })
+}
+
+pub struct HyperliquidSnapshots {
+    pub client: Arc<Mutex<InfoClient>>,
+    pub health: SharedHealth,
+    pub coin: String,
+    pub symbol: String,
+}
+
+#[async_trait]
+impl SnapshotSource for HyperliquidSnapshots {
+    async fn snapshot(&self, depth: usize) -> Result<OrderBook> {
+        let sent = Utc::now().timestamp_millis();
+        let snapshot = self.client.lock().await.l2_snapshot(self.coin.clone()).await?;
+        let received = Utc::now().timestamp_millis();
+        self.health.record_round_trip(&ExchangeId::Hyperliquid, snapshot.time as i64, sent, received);
+
+        let side = |index: usize| -> Vec<Level> {
+            snapshot.levels.get(index)
+                .map(|levels| levels.iter()
+                    .take(depth)
+                    .filter_map(|level| Some(Level {
+                        price: level.px.parse().ok()?,
+                        size: level.sz.parse().ok()?,
+                        orders: level.n,
+                    }))
+                    .collect())
+                .unwrap_or_default()
+        };
+        Ok(OrderBook {
+            exchange: ExchangeId::Hyperliquid,
+            symbol: self.symbol.clone(),
+            bids: side(0),
+            asks: side(1),
+            timestamp: self.health.normalize(&ExchangeId::Hyperliquid, snapshot.time, received as u64),
+            venue_timestamp: Some(snapshot.time),
+            source: BookSource::Polled,
+        })
+    }
+}
+
+pub struct DydxSnapshots {
+    pub ticker: String,
+    pub symbol: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DydxBookLevel {
+    price: String,
+    size: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DydxBook {
+    bids: Vec<DydxBookLevel>,
+    asks: Vec<DydxBookLevel>,
+}
+
+/// Parse the indexer's orderbook response. Bids come highest first and
+/// asks lowest first; both are cut to `depth`.
+pub fn parse_dydx_orderbook(body: &str, symbol: &str, depth: usize, now_ms: u64) -> Result<OrderBook> {
+    let book: DydxBook = serde_json::from_str(body)?;
+    let side = |levels: &[DydxBookLevel]| -> Vec<Level> {
+        levels.iter()
+            .filter_map(|level| Some(Level { price: level.price.parse().ok()?, size: level.size.parse().ok()?, orders: 1 }))
+            .filter(|level| level.size > 0.0)
+            .collect()
+    };
+    let mut bids = side(&book.bids);
+    let mut asks = side(&book.asks);
+    bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+    asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+    bids.truncate(depth);
+    asks.truncate(depth);
+    Ok(OrderBook {
+        exchange: ExchangeId::Dydx,
+        symbol: symbol.to_string(),
+        bids,
+        asks,
+        // No server time in the response either; stamped at receipt
+        timestamp: now_ms,
+        venue_timestamp: None,
+        source: BookSource::Polled,
+    })
+}
+
+#[async_trait]
+impl SnapshotSource for DydxSnapshots {
+    async fn snapshot(&self, depth: usize) -> Result<OrderBook> {
+        let endpoints = endpoints::dydx();
+        let endpoint = endpoints.active_index();
+        let started = std::time::Instant::now();
+        let result = reqwest::Client::new()
+            .get(endpoints.url(&format!("{}/{}", DYDX_ORDERBOOK_PATH, self.ticker)))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        endpoints.record_result(endpoint, &result, started);
+        let body = result?.text().await?;
+        parse_dydx_orderbook(&body, &self.symbol, depth, Utc::now().timestamp_millis() as u64)
+    }
+}
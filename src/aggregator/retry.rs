@@ -0,0 +1,92 @@
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use rand::Rng;
+use crate::config::AggregatorConfig;
+use crate::error::{AggregatorError, Result};
+
+static POLICY: RwLock<Option<RetryPolicy>> = RwLock::new(None);
+
+/// Attempts and per-attempt timeout for venue REST calls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    // Total tries, the first included
+    pub attempts: u32,
+    pub timeout: Duration,
+    // Doubled after each failed attempt, up to `max_delay`
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            timeout: Duration::from_millis(5000),
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &AggregatorConfig) -> Self {
+        Self {
+            attempts: config.retry_attempts.max(1),
+            timeout: Duration::from_millis(config.timeout_ms.max(1)),
+            ..Self::default()
+        }
+    }
+
+    /// Upper bound of the wait after failed attempt `attempt` (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+
+    /// Somewhere in the upper half of the backoff, so clients that failed
+    /// together don't all retry at the same instant
+    fn jittered(&self, attempt: u32) -> Duration {
+        self.backoff(attempt).mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Use `policy` for every venue call made through `policy()`. Set once at
+/// startup from the config.
+pub fn install(policy: RetryPolicy) {
+    if let Ok(mut installed) = POLICY.write() {
+        *installed = Some(policy);
+    }
+}
+
+pub fn policy() -> RetryPolicy {
+    POLICY.read().ok().and_then(|installed| *installed).unwrap_or_default()
+}
+
+/// Run `call` up to `policy.attempts` times, each bounded by
+/// `policy.timeout`, backing off between transient failures. Anything else
+/// is returned as is on the first failure.
+pub async fn with_retry<T, F, Fut>(policy: &RetryPolicy, what: &str, mut call: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let attempts = policy.attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        let started = Instant::now();
+        let error = match tokio::time::timeout(policy.timeout, call()).await {
+            Ok(Ok(value)) => return Ok(value),
+            Ok(Err(e)) => e,
+            Err(_) => AggregatorError::Timeout { what: what.to_string(), elapsed_ms: started.elapsed().as_millis() as u64 },
+        };
+        if attempt >= attempts || !error.is_transient() {
+            return Err(error);
+        }
+        let delay = policy.jittered(attempt);
+        tracing::warn!("{} failed (attempt {}/{}), retrying in {}ms: {}", what, attempt, attempts, delay.as_millis(), error);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
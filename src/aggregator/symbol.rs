@@ -0,0 +1,127 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::error::AggregatorError;
+
+// dYdX only lists perpetuals, all quoted in USD
+const DYDX_QUOTE: &str = "USD";
+// Suffixes users (or venue responses) append to a perp symbol
+const PERP_SUFFIXES: [&str; 2] = ["-USD", "-PERP"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MarketKind {
+    Perp,
+    Spot { quote: String },
+}
+
+/// A market identifier that knows how each venue spells it.
+///
+/// The display form is what the UI shows ("BTC", "PURR/USDC"); use
+/// `to_dydx_ticker` / `to_hl_coin` when talking to an exchange.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Symbol {
+    base: String,
+    kind: MarketKind,
+}
+
+impl Symbol {
+    pub fn perp(base: &str) -> Self {
+        Self {
+            base: normalize_base(base),
+            kind: MarketKind::Perp,
+        }
+    }
+
+    pub fn spot(base: &str, quote: &str) -> Self {
+        Self {
+            base: normalize_base(base),
+            kind: MarketKind::Spot { quote: quote.trim().to_uppercase() },
+        }
+    }
+
+    /// Parse whatever the user typed (or a venue returned): "btc", "BTC-USD",
+    /// "eth-perp", "kPEPE" and "PURR/USDC" are all accepted.
+    pub fn parse_user_input(input: &str) -> Result<Self, AggregatorError> {
+        let trimmed = input.trim();
+
+        if let Some((base, quote)) = trimmed.split_once('/') {
+            if !is_valid_part(base) || !is_valid_part(quote) {
+                return Err(AggregatorError::AssetNotFound(trimmed.to_string()));
+            }
+            return Ok(Self::spot(base, quote));
+        }
+
+        // ASCII uppercasing keeps byte offsets, so the suffix can be cut from
+        // the input as typed
+        let upper = trimmed.to_ascii_uppercase();
+        let base = PERP_SUFFIXES.iter()
+            .find_map(|suffix| upper.strip_suffix(suffix).map(|stripped| &trimmed[..stripped.len()]))
+            .unwrap_or(trimmed);
+
+        if !is_valid_part(base) {
+            return Err(AggregatorError::AssetNotFound(input.trim().to_string()));
+        }
+
+        Ok(Self::perp(base))
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    pub fn kind(&self) -> &MarketKind {
+        &self.kind
+    }
+
+    pub fn is_spot(&self) -> bool {
+        matches!(self.kind, MarketKind::Spot { .. })
+    }
+
+    /// dYdX perpetual ticker, e.g. "BTC-USD". dYdX has no spot markets, so a
+    /// spot symbol maps onto the perp of the same base asset.
+    pub fn to_dydx_ticker(&self) -> String {
+        format!("{}-{}", self.base, DYDX_QUOTE)
+    }
+
+    /// Hyperliquid coin name: the bare base for perps ("BTC"), "BASE/QUOTE"
+    /// for spot pairs. Spot pairs other than the canonical ones are named
+    /// "@index" by Hyperliquid and need the spot meta to resolve.
+    pub fn to_hl_coin(&self) -> String {
+        match &self.kind {
+            MarketKind::Perp => self.base.clone(),
+            MarketKind::Spot { quote } => format!("{}/{}", self.base, quote),
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            MarketKind::Perp => write!(f, "{}", self.base),
+            MarketKind::Spot { quote } => write!(f, "{}/{}", self.base, quote),
+        }
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = AggregatorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_user_input(s)
+    }
+}
+
+/// Uppercase, except Hyperliquid's lowercase "k" for markets quoted per
+/// thousand units ("kPEPE", "kSHIB"), which its coin names are matched on
+/// exactly
+fn normalize_base(base: &str) -> String {
+    let base = base.trim();
+    match base.strip_prefix('k') {
+        Some(rest) if rest.len() >= 2 && rest.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) => format!("k{}", rest),
+        _ => base.to_uppercase(),
+    }
+}
+
+fn is_valid_part(part: &str) -> bool {
+    !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric())
+}
@@ -0,0 +1,1787 @@
+#[cfg(test)]
+mod symbol_tests {
+    use crate::aggregator::symbol::{MarketKind, Symbol};
+
+    #[test]
+    fn test_parse_plain_and_lowercase() {
+        let symbol = Symbol::parse_user_input(" btc ").unwrap();
+        assert_eq!(symbol, Symbol::perp("BTC"));
+        assert_eq!(symbol.to_string(), "BTC");
+        assert_eq!(symbol.to_hl_coin(), "BTC");
+        assert_eq!(symbol.to_dydx_ticker(), "BTC-USD");
+    }
+
+    #[test]
+    fn test_parse_already_suffixed() {
+        for input in ["BTC-USD", "btc-usd", "BTC-PERP"] {
+            let symbol = Symbol::parse_user_input(input).unwrap();
+            assert_eq!(symbol.base(), "BTC");
+            // Must never be double-suffixed
+            assert_eq!(symbol.to_dydx_ticker(), "BTC-USD");
+            assert_eq!(symbol.to_hl_coin(), "BTC");
+        }
+    }
+
+    #[test]
+    fn test_thousand_unit_coins_keep_their_k() {
+        for input in ["kPEPE", " kPEPE-PERP", "kPEPE-usd"] {
+            let symbol = Symbol::parse_user_input(input).unwrap();
+            assert_eq!(symbol, Symbol::perp("kPEPE"));
+            assert_eq!(symbol.to_hl_coin(), "kPEPE");
+            assert_eq!(symbol.to_string(), "kPEPE");
+        }
+        // Lowercase throughout is an ordinary ticker, as is a K that is part of one
+        assert_eq!(Symbol::parse_user_input("kas").unwrap().to_hl_coin(), "KAS");
+        assert_eq!(Symbol::perp("KAVA").to_hl_coin(), "KAVA");
+    }
+
+    #[test]
+    fn test_parse_spot_pair() {
+        let symbol = Symbol::parse_user_input("purr/usdc").unwrap();
+        assert!(symbol.is_spot());
+        assert_eq!(symbol.kind(), &MarketKind::Spot { quote: "USDC".to_string() });
+        assert_eq!(symbol.to_string(), "PURR/USDC");
+        assert_eq!(symbol.to_hl_coin(), "PURR/USDC");
+        assert_eq!(symbol.to_dydx_ticker(), "PURR-USD");
+    }
+
+    #[test]
+    fn test_parse_rejects_garbage() {
+        for input in ["", "   ", "-USD", "BTC/", "/USDC", "BT C", "BTC-USD-USD"] {
+            assert!(Symbol::parse_user_input(input).is_err(), "accepted {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_from_str_round_trip() {
+        let symbol: Symbol = "eth".parse().unwrap();
+        let reparsed: Symbol = symbol.to_string().parse().unwrap();
+        assert_eq!(symbol, reparsed);
+    }
+}
+
+#[cfg(test)]
+mod metadata_tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::metadata::{
+        cached_label, parse_dydx_markets, parse_hyperliquid_meta, Delisting, ExchangeMetadata, MarketSpec, MetadataCache,
+    };
+
+    const HOUR_MS: i64 = 60 * 60 * 1000;
+    const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+    fn cache_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("hl_aggregator_metadata_{}_{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn btc_spec(max_leverage: f64) -> MarketSpec {
+        MarketSpec {
+            base: "BTC".to_string(),
+            max_leverage,
+            size_decimals: Some(5),
+            tick_size: None,
+            step_size: Some(0.00001),
+            margin_tiers: Vec::new(),
+            initial_margin_fraction: None,
+            maintenance_margin_fraction: None,
+            min_notional: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_cache_hit_after_reload() {
+        let path = cache_path("hit");
+        let mut cache = MetadataCache::load(path.clone());
+        let metadata = ExchangeMetadata { fetched_at: 1_000, markets: vec![btc_spec(50.0)], delisted: Vec::new() };
+        cache.insert(&ExchangeId::Hyperliquid, metadata);
+        cache.save().unwrap();
+
+        let reloaded = MetadataCache::load(path);
+        let (spec, age) = reloaded.market(&ExchangeId::Hyperliquid, "BTC", MAX_AGE, 1_000 + 2 * HOUR_MS).unwrap();
+        assert_eq!(spec.max_leverage, 50.0);
+        assert_eq!(cached_label(age), "cached (2h old)");
+        assert!(reloaded.market(&ExchangeId::Dydx, "BTC", MAX_AGE, 1_000).is_none());
+    }
+
+    #[test]
+    fn test_refresh_replaces_stale_entry() {
+        let mut cache = MetadataCache::load(cache_path("refresh"));
+        let refresh_after = Duration::from_secs(6 * 60 * 60);
+        assert!(cache.needs_refresh(&ExchangeId::Dydx, refresh_after, 0));
+
+        cache.insert(&ExchangeId::Dydx, ExchangeMetadata { fetched_at: 0, markets: vec![btc_spec(20.0)], delisted: Vec::new() });
+        assert!(!cache.needs_refresh(&ExchangeId::Dydx, refresh_after, HOUR_MS));
+        assert!(cache.needs_refresh(&ExchangeId::Dydx, refresh_after, 7 * HOUR_MS));
+
+        cache.insert(&ExchangeId::Dydx, ExchangeMetadata { fetched_at: 7 * HOUR_MS, markets: vec![btc_spec(10.0)], delisted: Vec::new() });
+        let (spec, _) = cache.market(&ExchangeId::Dydx, "BTC", MAX_AGE, 7 * HOUR_MS).unwrap();
+        assert_eq!(spec.max_leverage, 10.0);
+
+        // Far past the max age the entry is no longer served
+        assert!(cache.market(&ExchangeId::Dydx, "BTC", MAX_AGE, 7 * HOUR_MS + 8 * 24 * HOUR_MS).is_none());
+    }
+
+    #[test]
+    fn test_corrupt_cache_falls_back_to_empty() {
+        let path = cache_path("corrupt");
+        std::fs::write(&path, "{\"Hyperliquid\": {\"fetched_at\": 12, \"mark").unwrap();
+
+        let mut cache = MetadataCache::load(path.clone());
+        assert!(cache.get(&ExchangeId::Hyperliquid).is_none());
+
+        // And the next save overwrites the garbage
+        cache.insert(&ExchangeId::Hyperliquid, ExchangeMetadata { fetched_at: 5, markets: vec![btc_spec(40.0)], delisted: Vec::new() });
+        cache.save().unwrap();
+        assert!(MetadataCache::load(path).get(&ExchangeId::Hyperliquid).is_some());
+    }
+
+    #[test]
+    fn test_parse_venue_metadata() {
+        let (hl, _) = parse_hyperliquid_meta(
+            r#"{"universe":[{"name":"BTC","szDecimals":5,"maxLeverage":50},{"name":"ETH","szDecimals":4,"maxLeverage":25}]}"#
+        ).unwrap();
+        assert_eq!(hl[1].base, "ETH");
+        assert_eq!(hl[1].max_leverage, 25.0);
+        assert_eq!(hl[1].size_decimals, Some(4));
+
+        let (dydx, _) = parse_dydx_markets(
+            r#"{"markets":{"BTC-USD":{"ticker":"BTC-USD","initialMarginFraction":"0.05","tickSize":"1","stepSize":"0.0001"}}}"#
+        ).unwrap();
+        assert_eq!(dydx[0].base, "BTC");
+        assert_eq!(dydx[0].max_leverage, 20.0);
+        assert_eq!(dydx[0].tick_size, Some(1.0));
+    }
+
+    #[test]
+    fn test_margin_parameters_parsed() {
+        let (hl, _) = parse_hyperliquid_meta(r#"{
+            "universe":[
+                {"name":"BTC","szDecimals":5,"maxLeverage":40,"marginTableId":56},
+                {"name":"ETH","szDecimals":4,"maxLeverage":25,"marginTableId":25},
+                {"name":"SOL","szDecimals":2,"maxLeverage":20}
+            ],
+            "marginTables":[[56,{"description":"tiered 40x","marginTiers":[
+                {"lowerBound":"150000000.0","maxLeverage":20},{"lowerBound":"0.0","maxLeverage":40}
+            ]}]]
+        }"#).unwrap();
+        let tiers: Vec<(f64, f64)> = hl[0].margin_tiers.iter().map(|tier| (tier.lower_bound, tier.max_leverage)).collect();
+        assert_eq!(tiers, vec![(0.0, 40.0), (150_000_000.0, 20.0)]);
+        // Ids under 50 are a single tier at that leverage
+        assert_eq!(hl[1].margin_tiers.len(), 1);
+        assert_eq!(hl[1].margin_tiers[0].max_leverage, 25.0);
+        assert!(hl[2].margin_tiers.is_empty());
+
+        let (dydx, _) = parse_dydx_markets(
+            r#"{"markets":{"BTC-USD":{"ticker":"BTC-USD","initialMarginFraction":"0.05","maintenanceMarginFraction":"0.03","tickSize":"1","stepSize":"0.0001"}}}"#
+        ).unwrap();
+        assert_eq!(dydx[0].initial_margin_fraction, Some(0.05));
+        assert_eq!(dydx[0].maintenance_margin_fraction, Some(0.03));
+    }
+
+    fn spec(base: &str) -> MarketSpec {
+        MarketSpec { base: base.to_string(), ..btc_spec(10.0) }
+    }
+
+    #[test]
+    fn test_universe_shrink_records_delisting() {
+        let mut cache = MetadataCache::load(cache_path("delist"));
+        let first = ExchangeMetadata { fetched_at: 0, markets: vec![btc_spec(50.0), spec("XYZ")], delisted: Vec::new() };
+        assert!(cache.insert(&ExchangeId::Hyperliquid, first).is_empty());
+
+        // XYZ drops out of the universe on the next refresh
+        let second = ExchangeMetadata { fetched_at: HOUR_MS, markets: vec![btc_spec(50.0)], delisted: Vec::new() };
+        let fresh = cache.insert(&ExchangeId::Hyperliquid, second);
+        assert_eq!(fresh, vec![Delisting { base: "XYZ".to_string(), settlement_price: None }]);
+        assert!(cache.delisting(&ExchangeId::Hyperliquid, "XYZ").is_some());
+        assert!(cache.market(&ExchangeId::Hyperliquid, "XYZ", MAX_AGE, HOUR_MS).is_none());
+        assert!(cache.delisting(&ExchangeId::Hyperliquid, "BTC").is_none());
+
+        // Still terminal on later refreshes, without being reported again
+        let third = ExchangeMetadata { fetched_at: 2 * HOUR_MS, markets: vec![btc_spec(50.0)], delisted: Vec::new() };
+        assert!(cache.insert(&ExchangeId::Hyperliquid, third).is_empty());
+        assert!(cache.delisting(&ExchangeId::Hyperliquid, "XYZ").is_some());
+
+        // A relisted market is live again
+        let relisted = ExchangeMetadata { fetched_at: 3 * HOUR_MS, markets: vec![btc_spec(50.0), spec("XYZ")], delisted: Vec::new() };
+        assert!(cache.insert(&ExchangeId::Hyperliquid, relisted).is_empty());
+        assert!(cache.delisting(&ExchangeId::Hyperliquid, "XYZ").is_none());
+    }
+
+    #[test]
+    fn test_settlement_price_survives_refresh() {
+        let mut cache = MetadataCache::load(cache_path("settle"));
+        cache.insert(&ExchangeId::Dydx, ExchangeMetadata { fetched_at: 0, markets: vec![btc_spec(20.0), spec("XYZ")], delisted: Vec::new() });
+        let settling = vec![Delisting { base: "XYZ".to_string(), settlement_price: Some(1.25) }];
+        let fresh = cache.insert(&ExchangeId::Dydx, ExchangeMetadata { fetched_at: HOUR_MS, markets: vec![btc_spec(20.0)], delisted: settling });
+        assert_eq!(fresh[0].settlement_price, Some(1.25));
+
+        // Gone from the listing entirely, the known price is kept
+        cache.insert(&ExchangeId::Dydx, ExchangeMetadata { fetched_at: 2 * HOUR_MS, markets: vec![btc_spec(20.0)], delisted: Vec::new() });
+        assert_eq!(cache.delisting(&ExchangeId::Dydx, "XYZ").unwrap().settlement_price, Some(1.25));
+    }
+
+    #[test]
+    fn test_parse_venue_delisting_flags() {
+        let (hl, delisted) = parse_hyperliquid_meta(
+            r#"{"universe":[{"name":"BTC","szDecimals":5,"maxLeverage":50},{"name":"XYZ","szDecimals":0,"maxLeverage":3,"isDelisted":true}]}"#
+        ).unwrap();
+        assert_eq!(hl.len(), 1);
+        assert_eq!(delisted, vec![Delisting { base: "XYZ".to_string(), settlement_price: None }]);
+
+        let (dydx, delisted) = parse_dydx_markets(r#"{"markets":{
+            "BTC-USD":{"ticker":"BTC-USD","initialMarginFraction":"0.05","tickSize":"1","stepSize":"0.0001","status":"ACTIVE"},
+            "XYZ-USD":{"ticker":"XYZ-USD","initialMarginFraction":"0.2","tickSize":"0.01","stepSize":"1","status":"FINAL_SETTLEMENT","oraclePrice":"0.4321"}
+        }}"#).unwrap();
+        assert_eq!(dydx.len(), 1);
+        assert_eq!(delisted, vec![Delisting { base: "XYZ".to_string(), settlement_price: Some(0.4321) }]);
+        assert_eq!(delisted[0].describe(&ExchangeId::Dydx), "dYdX delisted XYZ, settled at $0.4321");
+    }
+}
+
+#[cfg(test)]
+mod health_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::health::{ClockSkewPolicy, HealthRegistry, SkewLevel};
+    use crate::error::AggregatorError;
+    use crate::aggregator::types::{BookSource, OrderBook};
+
+    const LOCAL_NOW: i64 = 1_700_000_000_000;
+    const THREE_MINUTES: i64 = 3 * 60 * 1000;
+
+    #[test]
+    fn test_unknown_venue_passes_through() {
+        let health = HealthRegistry::default();
+        assert_eq!(health.clock_offset_ms(&ExchangeId::Dydx), 0);
+        assert_eq!(health.normalize(&ExchangeId::Dydx, LOCAL_NOW as u64 - 500, LOCAL_NOW as u64), LOCAL_NOW as u64 - 500);
+    }
+
+    #[test]
+    fn test_venue_ahead_is_normalized_to_local() {
+        let health = HealthRegistry::default();
+        // Venue clock runs three minutes fast
+        health.record_server_time(&ExchangeId::Hyperliquid, LOCAL_NOW + THREE_MINUTES, LOCAL_NOW);
+        assert_eq!(health.clock_offset_ms(&ExchangeId::Hyperliquid), THREE_MINUTES);
+
+        let venue_ts = (LOCAL_NOW + THREE_MINUTES - 200) as u64;
+        assert_eq!(health.normalize(&ExchangeId::Hyperliquid, venue_ts, LOCAL_NOW as u64), LOCAL_NOW as u64 - 200);
+    }
+
+    #[test]
+    fn test_venue_behind_and_round_trip_midpoint() {
+        let health = HealthRegistry::default();
+        // Request took 400ms; server stamped it at the midpoint, two seconds slow
+        health.record_round_trip(&ExchangeId::Dydx, LOCAL_NOW + 200 - 2_000, LOCAL_NOW, LOCAL_NOW + 400);
+        assert_eq!(health.clock_offset_ms(&ExchangeId::Dydx), -2_000);
+        assert_eq!(health.venue(&ExchangeId::Dydx).clock_skew.unwrap().samples, 1);
+    }
+
+    #[test]
+    fn test_offset_is_smoothed_and_clamped() {
+        let health = HealthRegistry::default();
+        health.record_server_time(&ExchangeId::Hyperliquid, LOCAL_NOW + 1_000, LOCAL_NOW);
+        // A single slow message shouldn't swing the estimate
+        health.record_server_time(&ExchangeId::Hyperliquid, LOCAL_NOW - 4_000, LOCAL_NOW);
+        assert_eq!(health.clock_offset_ms(&ExchangeId::Hyperliquid), 0);
+
+        // Timestamps that would land in our future are clamped to now
+        assert_eq!(health.normalize(&ExchangeId::Hyperliquid, (LOCAL_NOW + 5_000) as u64, LOCAL_NOW as u64), LOCAL_NOW as u64);
+    }
+
+    #[test]
+    fn test_book_staleness_uses_normalized_timestamp() {
+        let health = HealthRegistry::default();
+        health.record_server_time(&ExchangeId::Hyperliquid, LOCAL_NOW + THREE_MINUTES, LOCAL_NOW);
+
+        let venue_ts = (LOCAL_NOW + THREE_MINUTES - 1_000) as u64;
+        let book = OrderBook {
+            exchange: ExchangeId::Hyperliquid,
+            symbol: "BTC".to_string(),
+            bids: Vec::new(),
+            asks: Vec::new(),
+            timestamp: health.normalize(&ExchangeId::Hyperliquid, venue_ts, LOCAL_NOW as u64),
+            venue_timestamp: Some(venue_ts),
+            source: BookSource::Websocket,
+        };
+        assert_eq!(book.age_ms(LOCAL_NOW as u64), 1_000);
+        assert!(!book.is_stale(5_000, LOCAL_NOW as u64));
+        assert!(book.is_stale(5_000, LOCAL_NOW as u64 + 10_000));
+    }
+
+    #[test]
+    fn test_skew_levels_at_thresholds() {
+        let policy = ClockSkewPolicy { warn_ms: 5_000, block_ms: 60_000 };
+        let level = |server_offset: i64| {
+            let health = HealthRegistry::default();
+            health.record_server_time(&ExchangeId::Dydx, LOCAL_NOW + server_offset, LOCAL_NOW);
+            health.skew_level(&ExchangeId::Dydx, &policy)
+        };
+        assert_eq!(level(4_999), SkewLevel::Ok);
+        assert_eq!(level(5_000), SkewLevel::Warn);
+        // The local clock running fast counts the same as slow
+        assert_eq!(level(-59_999), SkewLevel::Warn);
+        assert_eq!(level(-60_000), SkewLevel::Block);
+        assert_eq!(level(THREE_MINUTES), SkewLevel::Block);
+        // No sample yet: nothing to warn about
+        assert_eq!(HealthRegistry::default().skew_level(&ExchangeId::Dydx, &policy), SkewLevel::Ok);
+    }
+
+    #[test]
+    fn test_block_threshold_refuses_with_typed_error() {
+        let policy = ClockSkewPolicy::default();
+        let health = HealthRegistry::default();
+        health.record_round_trip(&ExchangeId::Dydx, LOCAL_NOW + 100 - THREE_MINUTES, LOCAL_NOW, LOCAL_NOW + 200);
+        let err = health.ensure_clock_synced(&ExchangeId::Dydx, &policy).unwrap_err();
+        match &err {
+            AggregatorError::ClockSkew(skew) => {
+                assert_eq!(skew.exchange, ExchangeId::Dydx);
+                assert_eq!(skew.offset, "-180.0s");
+                assert_eq!(skew.limit_ms, policy.block_ms);
+            }
+            other => panic!("expected ClockSkew, got {:?}", other),
+        }
+        assert!(err.to_string().contains("sync the clock"));
+
+        // Only warned about below the block threshold
+        let health = HealthRegistry::default();
+        health.record_server_time(&ExchangeId::Dydx, LOCAL_NOW + 10_000, LOCAL_NOW);
+        assert_eq!(health.skew_level(&ExchangeId::Dydx, &policy), SkewLevel::Warn);
+        assert!(health.ensure_clock_synced(&ExchangeId::Dydx, &policy).is_ok());
+        assert!(health.ensure_clock_synced(&ExchangeId::Hyperliquid, &policy).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod exchange_id_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+
+    #[test]
+    fn test_parse_legacy_spellings() {
+        for input in ["dYdX", "dydx", "DYDX", "dYdX ", " dydx\n"] {
+            assert_eq!(input.parse::<ExchangeId>().unwrap(), ExchangeId::Dydx, "{:?}", input);
+        }
+        for input in ["Hyperliquid", "hyperliquid", " HYPERLIQUID", "hl"] {
+            assert_eq!(input.parse::<ExchangeId>().unwrap(), ExchangeId::Hyperliquid, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_custom_and_empty() {
+        assert_eq!(" Vertex ".parse::<ExchangeId>().unwrap(), ExchangeId::Custom("Vertex".to_string()));
+        assert!("".parse::<ExchangeId>().is_err());
+        assert!("   ".parse::<ExchangeId>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for id in [ExchangeId::Dydx, ExchangeId::Hyperliquid, ExchangeId::Custom("Vertex".to_string())] {
+            assert_eq!(id.to_string().parse::<ExchangeId>().unwrap(), id);
+        }
+        assert_eq!(format!("{:<6}|", ExchangeId::Dydx), "dYdX  |");
+    }
+
+    #[test]
+    fn test_serde_is_human_readable() {
+        assert_eq!(serde_json::to_string(&ExchangeId::Dydx).unwrap(), "\"dYdX\"");
+        assert_eq!(serde_json::from_str::<ExchangeId>("\"hyperliquid\"").unwrap(), ExchangeId::Hyperliquid);
+        assert!(serde_json::from_str::<ExchangeId>("\"\"").is_err());
+    }
+}
+
+#[cfg(test)]
+mod funding_tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::funding::{
+        best_funding_spread, hourly_rate, parse_dydx_funding, parse_hyperliquid_funding, FundingPoint, FundingStore,
+        FUNDING_INTERVAL_MS,
+    };
+    use crate::aggregator::symbol::Symbol;
+
+    const HOUR_MS: i64 = FUNDING_INTERVAL_MS;
+    const NOW: i64 = 1_700_000_000_000;
+
+    fn store_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("hl_aggregator_funding_{}_{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn hourly(from: i64, hours: i64) -> Vec<FundingPoint> {
+        (0..hours).map(|h| FundingPoint { time: from + h * HOUR_MS, rate: 0.0001 }).collect()
+    }
+
+    #[test]
+    fn test_empty_store_fetches_whole_window() {
+        let store = FundingStore::load(store_path("empty"));
+        let start = NOW - 24 * HOUR_MS;
+        assert_eq!(store.missing_ranges(&ExchangeId::Hyperliquid, &Symbol::perp("BTC"), start, NOW), vec![(start, NOW)]);
+    }
+
+    #[test]
+    fn test_incremental_fetch_boundaries() {
+        let mut store = FundingStore::load(store_path("incremental"));
+        let (hl, btc) = (ExchangeId::Hyperliquid, Symbol::perp("BTC"));
+        let start = NOW - 24 * HOUR_MS;
+        store.record_fetch(&hl, &btc, start, NOW, hourly(start, 24));
+
+        // Less than one funding interval later there's nothing new to fetch
+        assert!(store.missing_ranges(&hl, &btc, start, NOW + HOUR_MS - 1).is_empty());
+
+        // One interval later only the recent tail is fetched
+        let later = NOW + HOUR_MS;
+        assert_eq!(store.missing_ranges(&hl, &btc, start + HOUR_MS, later), vec![(NOW + 1, later)]);
+
+        // A longer window also needs the older head
+        let earlier = start - 48 * HOUR_MS;
+        assert_eq!(
+            store.missing_ranges(&hl, &btc, earlier, later),
+            vec![(earlier, start - 1), (NOW + 1, later)]
+        );
+
+        // Other venues are tracked separately
+        assert_eq!(store.missing_ranges(&ExchangeId::Dydx, &btc, start, NOW), vec![(start, NOW)]);
+    }
+
+    #[test]
+    fn test_overlapping_points_are_deduplicated() {
+        let mut store = FundingStore::load(store_path("dedup"));
+        let (hl, btc) = (ExchangeId::Hyperliquid, Symbol::perp("BTC"));
+        let start = NOW - 10 * HOUR_MS;
+
+        assert_eq!(store.record_fetch(&hl, &btc, start, NOW - 4 * HOUR_MS, hourly(start, 6)), 6);
+        // Overlaps the last two points of the first fetch
+        assert_eq!(store.record_fetch(&hl, &btc, NOW - 6 * HOUR_MS, NOW, hourly(NOW - 6 * HOUR_MS, 7)), 5);
+
+        let points = store.points(&hl, &btc, 0);
+        assert_eq!(points.len(), 11);
+        assert!(points.windows(2).all(|w| w[1].time - w[0].time == HOUR_MS));
+        assert_eq!(store.points(&hl, &btc, NOW - HOUR_MS).len(), 2);
+    }
+
+    #[test]
+    fn test_round_trip_and_prune() {
+        let path = store_path("prune");
+        let (dydx, eth) = (ExchangeId::Dydx, Symbol::perp("ETH"));
+        let start = NOW - 48 * HOUR_MS;
+
+        let mut store = FundingStore::load(path.clone());
+        store.record_fetch(&dydx, &eth, start, NOW, hourly(start, 48));
+        store.save().unwrap();
+
+        let mut store = FundingStore::load(path);
+        assert_eq!(store.points(&dydx, &eth, 0).len(), 48);
+        assert_eq!(store.prune(Duration::from_secs(24 * 60 * 60), NOW), 24);
+        assert_eq!(store.points(&dydx, &eth, 0).len(), 24);
+        // Pruned history counts as missing again for a longer window
+        assert_eq!(store.missing_ranges(&dydx, &eth, start, NOW), vec![(start, NOW - 24 * HOUR_MS - 1)]);
+    }
+
+    #[test]
+    fn test_parse_venue_responses() {
+        let hl = parse_hyperliquid_funding(
+            r#"[{"coin":"BTC","fundingRate":"0.0000125","premium":"0.0001","time":1700000000000}]"#,
+        ).unwrap();
+        assert_eq!(hl, vec![FundingPoint { time: NOW, rate: 0.0000125 }]);
+
+        let dydx = parse_dydx_funding(
+            r#"{"historicalFunding":[{"ticker":"BTC-USD","rate":"-0.00001","price":"65000","effectiveAt":"2023-11-14T22:13:20.000Z","effectiveAtHeight":"1"}]}"#,
+        ).unwrap();
+        assert_eq!(dydx, vec![FundingPoint { time: NOW, rate: -0.00001 }]);
+    }
+
+    #[test]
+    fn test_best_funding_spread_longs_the_cheaper_venue() {
+        let btc = Symbol::perp("BTC");
+        let spread = best_funding_spread(&btc, &[(ExchangeId::Dydx, 0.00002), (ExchangeId::Hyperliquid, -0.00001)]).unwrap();
+        assert_eq!(spread.long_venue, ExchangeId::Hyperliquid);
+        assert_eq!(spread.short_venue, ExchangeId::Dydx);
+        // 0.003%/h over a year
+        assert!((spread.annualized_spread - 26.28).abs() < 1e-9, "{}", spread.annualized_spread);
+
+        let flat = best_funding_spread(&btc, &[(ExchangeId::Dydx, 0.0001), (ExchangeId::Hyperliquid, 0.0001)]).unwrap();
+        assert_ne!(flat.long_venue, flat.short_venue);
+        assert_eq!(flat.annualized_spread, 0.0);
+    }
+
+    #[test]
+    fn test_single_venue_has_no_spread() {
+        assert!(best_funding_spread(&Symbol::perp("PURR"), &[(ExchangeId::Hyperliquid, 0.0001)]).is_none());
+        assert!(best_funding_spread(&Symbol::perp("PURR"), &[]).is_none());
+        // Both built-ins already quote hourly
+        assert_eq!(hourly_rate(&ExchangeId::Dydx, 0.0001), 0.0001);
+        assert_eq!(hourly_rate(&ExchangeId::Hyperliquid, 0.0001), 0.0001);
+    }
+}
+
+#[cfg(test)]
+mod venue_status_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::health::{HealthRegistry, VenueStatus};
+    use crate::aggregator::venue_status::{
+        dydx_status, is_halt_rejection, parse_dydx_height, parse_hyperliquid_status,
+    };
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    #[test]
+    fn test_halt_rejections_are_recognised() {
+        assert!(is_halt_rejection("Trading is halted for this asset."));
+        assert!(is_halt_rejection("Exchange under maintenance, try again later"));
+        assert!(!is_halt_rejection("Insufficient margin to place order."));
+        assert!(!is_halt_rejection("Order has invalid price."));
+    }
+
+    #[test]
+    fn test_dydx_block_lag() {
+        let (height, time) = parse_dydx_height(r#"{"height":"31415926","time":"2023-11-14T22:13:20.000Z"}"#).unwrap();
+        assert_eq!(height, 31_415_926);
+        assert_eq!(time, NOW);
+
+        assert_eq!(dydx_status(NOW - 2_000, NOW), VenueStatus::Operational);
+        assert!(matches!(dydx_status(NOW - 20_000, NOW), VenueStatus::Degraded(_)));
+        assert_eq!(dydx_status(NOW - 90_000, NOW), VenueStatus::Halted("no new blocks for 90s".to_string()));
+    }
+
+    #[test]
+    fn test_hyperliquid_special_statuses() {
+        assert_eq!(parse_hyperliquid_status(r#"{"time":1700000000000,"specialStatuses":null}"#).unwrap(), VenueStatus::Operational);
+        assert_eq!(parse_hyperliquid_status(r#"{"time":1700000000000}"#).unwrap(), VenueStatus::Operational);
+        assert!(parse_hyperliquid_status(r#"{"specialStatuses":["Trading halted for upgrade"]}"#).unwrap().is_halted());
+        assert!(matches!(
+            parse_hyperliquid_status(r#"{"specialStatuses":["Reduced API limits"]}"#).unwrap(),
+            VenueStatus::Degraded(_)
+        ));
+    }
+
+    #[test]
+    fn test_repeated_halt_rejections_halt_the_venue() {
+        let health = HealthRegistry::default();
+        let venue = ExchangeId::Hyperliquid;
+
+        health.record_order_rejected(&venue, "Trading is halted", NOW);
+        assert_eq!(health.venue(&venue).status_at(NOW), VenueStatus::Operational);
+        health.record_order_rejected(&venue, "Trading is halted", NOW + 1_000);
+        assert!(health.venue(&venue).status_at(NOW + 1_000).is_halted());
+
+        // Lapses without further rejections
+        assert_eq!(health.venue(&venue).status_at(NOW + 10 * 60 * 1000), VenueStatus::Operational);
+
+        // An accepted order clears it immediately
+        health.record_order_accepted(&venue);
+        assert_eq!(health.venue(&venue).status_at(NOW + 1_000), VenueStatus::Operational);
+    }
+
+    #[test]
+    fn test_ordinary_rejection_resets_the_count() {
+        let health = HealthRegistry::default();
+        let venue = ExchangeId::Dydx;
+
+        health.record_order_rejected(&venue, "Trading is halted", NOW);
+        health.record_order_rejected(&venue, "Insufficient margin", NOW);
+        health.record_order_rejected(&venue, "Trading is halted", NOW);
+        assert_eq!(health.venue(&venue).status_at(NOW), VenueStatus::Operational);
+
+        // Polled status still shows through
+        health.set_reported_status(&venue, VenueStatus::Degraded("blocks 20s behind".to_string()));
+        assert_eq!(health.venue(&venue).status_at(NOW).banner(), "[DEGRADED: blocks 20s behind]");
+    }
+}
+
+#[cfg(test)]
+mod trade_flow_tests {
+    use std::time::Duration;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::trade_flow::{fallback_tick, TradeFlow, TradeFlowTracker, TradePrint};
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    fn print(price: f64, size: f64, is_buy: bool, time: i64) -> TradePrint {
+        TradePrint { price, size, is_buy, time }
+    }
+
+    #[test]
+    fn test_profile_buckets_on_tick_multiples() {
+        // 0.5 tick, 2 ticks per bucket -> buckets [100, 101), [101, 102)
+        let mut tracker = TradeFlowTracker::new(0.5, 2, WINDOW);
+        tracker.record(print(100.0, 1.0, true, 0));
+        tracker.record(print(100.5, 2.0, false, 1));
+        tracker.record(print(101.0, 0.5, true, 2));
+        tracker.record(print(101.5, 0.25, false, 3));
+
+        let profile = tracker.profile();
+        assert_eq!(profile.len(), 2);
+        assert_eq!(profile[0].price, 101.0);
+        assert_eq!((profile[0].buy_volume, profile[0].sell_volume), (0.5, 0.25));
+        assert_eq!(profile[1].price, 100.0);
+        assert_eq!((profile[1].buy_volume, profile[1].sell_volume), (1.0, 2.0));
+        assert_eq!(profile[1].volume(), 3.0);
+    }
+
+    #[test]
+    fn test_cvd_is_buy_minus_sell() {
+        let mut tracker = TradeFlowTracker::new(1.0, 1, WINDOW);
+        tracker.record(print(100.0, 3.0, true, 0));
+        tracker.record(print(100.0, 1.0, false, 1));
+        tracker.record(print(99.0, 0.5, false, 2));
+        assert_eq!(tracker.cvd(), 1.5);
+    }
+
+    #[test]
+    fn test_prints_leave_the_window() {
+        let mut tracker = TradeFlowTracker::new(1.0, 1, WINDOW);
+        tracker.record(print(100.0, 2.0, true, 0));
+        tracker.record(print(105.0, 1.0, false, 30_000));
+        // The first print ages out as the third arrives
+        tracker.record(print(105.0, 1.0, true, 61_000));
+
+        assert_eq!(tracker.len(), 2);
+        assert_eq!(tracker.cvd(), 0.0);
+        assert_eq!(tracker.profile().len(), 1);
+
+        tracker.evict(200_000);
+        assert!(tracker.is_empty());
+        assert!(tracker.profile().is_empty());
+        assert_eq!(tracker.cvd(), 0.0);
+    }
+
+    #[test]
+    fn test_replayed_and_invalid_prints_are_ignored() {
+        let mut tracker = TradeFlowTracker::new(1.0, 1, WINDOW);
+        tracker.record(print(100.0, 1.0, true, 10));
+        tracker.record(print(100.0, 1.0, true, 5));
+        tracker.record(print(100.0, 0.0, true, 11));
+        tracker.record(print(0.0, 1.0, true, 12));
+        assert_eq!(tracker.len(), 1);
+        assert_eq!(tracker.newest(), Some(10));
+    }
+
+    #[test]
+    fn test_set_tick_rebuckets() {
+        let mut tracker = TradeFlowTracker::new(1.0, 1, WINDOW);
+        tracker.record(print(100.2, 1.0, true, 0));
+        tracker.record(print(101.7, 1.0, false, 1));
+        assert_eq!(tracker.profile().len(), 2);
+
+        tracker.set_tick(1.0, 5);
+        let profile = tracker.profile();
+        assert_eq!(profile.len(), 1);
+        assert_eq!(profile[0].price, 100.0);
+        assert_eq!(tracker.cvd(), 0.0);
+    }
+
+    #[test]
+    fn test_cvd_series_accumulates_over_slices() {
+        let mut tracker = TradeFlowTracker::new(1.0, 1, WINDOW);
+        tracker.record(print(100.0, 2.0, true, 5_000));
+        tracker.record(print(100.0, 1.0, false, 35_000));
+        tracker.record(print(100.0, 4.0, true, 55_000));
+        // Window [0, 60s) in three 20s slices
+        assert_eq!(tracker.cvd_series(60_000, 3), vec![2.0, 1.0, 5.0]);
+        assert!(tracker.cvd_series(60_000, 0).is_empty());
+    }
+
+    #[test]
+    fn test_fallback_tick_is_five_significant_figures() {
+        assert!((fallback_tick(65_432.0) - 1.0).abs() < 1e-12);
+        assert!((fallback_tick(3_210.5) - 0.1).abs() < 1e-12);
+        assert!((fallback_tick(0.12345) - 0.00001).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_flow_keeps_venues_apart() {
+        let mut flow = TradeFlow::new(WINDOW, 1);
+        flow.set_tick(&ExchangeId::Hyperliquid, "BTC-PERP", 1.0);
+        flow.record(&ExchangeId::Hyperliquid, "BTC-PERP", print(100.0, 1.0, true, 0));
+        flow.record(&ExchangeId::Dydx, "BTC-PERP", print(100.0, 2.0, false, 0));
+
+        let hl = flow.snapshot(&ExchangeId::Hyperliquid, "BTC-PERP", 1_000, 4).unwrap();
+        assert_eq!(hl.cvd, 1.0);
+        assert_eq!(hl.bucket, 1.0);
+        assert_eq!(hl.trades, 1);
+        let dydx = flow.snapshot(&ExchangeId::Dydx, "BTC-PERP", 1_000, 4).unwrap();
+        assert_eq!(dydx.cvd, -2.0);
+        assert!(flow.snapshot(&ExchangeId::Dydx, "ETH-PERP", 1_000, 4).is_none());
+    }
+}
+
+#[cfg(test)]
+mod rest_fallback_tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use crate::error::Result;
+    use async_trait::async_trait;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::health::{HealthRegistry, SharedHealth, Transport};
+    use crate::aggregator::rest_fallback::{feed_receiver, parse_dydx_orderbook, poll_once, shared_book, FallbackPolicy, SharedBook, SnapshotSource};
+    use crate::aggregator::symbol::Symbol;
+    use crate::error::AggregatorError;
+    use crate::aggregator::types::{BookSource, OrderBook};
+    use crate::test_fixtures::book;
+
+    // Counts snapshot requests and serves a fixed two-level book
+    struct MockSnapshots {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SnapshotSource for MockSnapshots {
+        async fn snapshot(&self, _depth: usize) -> Result<OrderBook> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(OrderBook {
+                symbol: "BTC-PERP".to_string(),
+                ..book(ExchangeId::Dydx, &[(99.0, 1.0), (98.0, 1.0)], &[(101.0, 1.0), (102.0, 1.0)], 0)
+            })
+        }
+    }
+
+    fn policy() -> FallbackPolicy {
+        FallbackPolicy { max_ws_failures: 2, depth: 1, ..FallbackPolicy::default() }
+    }
+
+    fn fixtures() -> (MockSnapshots, SharedBook, SharedHealth) {
+        (MockSnapshots { calls: AtomicUsize::new(0) }, shared_book(), Arc::new(HealthRegistry::default()))
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_after_ws_failures_and_polls() {
+        let (source, book, health) = fixtures();
+        let venue = ExchangeId::Dydx;
+
+        // Healthy websocket: the poller stays idle
+        assert!(!poll_once(&source, &book, &health, &venue, 1).await.unwrap());
+        assert_eq!(source.calls.load(Ordering::SeqCst), 0);
+
+        assert_eq!(health.record_ws_failure(&venue, &policy()), Transport::Websocket);
+        assert_eq!(
+            health.record_ws_failure(&venue, &policy()),
+            Transport::RestFallback { interval: Duration::from_secs(2) }
+        );
+
+        assert!(poll_once(&source, &book, &health, &venue, 1).await.unwrap());
+        let polled = book.borrow().clone().unwrap();
+        assert_eq!(polled.source, BookSource::Polled);
+        assert_eq!(polled.bids.len(), 1);
+        assert_eq!(polled.asks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_recovers_to_websocket_when_it_delivers() {
+        let (source, book, health) = fixtures();
+        let venue = ExchangeId::Hyperliquid;
+        for _ in 0..2 {
+            health.record_ws_failure(&venue, &policy());
+        }
+        assert!(health.transport(&venue).is_fallback());
+
+        health.record_ws_connected(&venue, &policy());
+        assert_eq!(health.transport(&venue), Transport::Websocket);
+        assert_eq!(health.venue(&venue).ws_failures, 0);
+        assert!(!poll_once(&source, &book, &health, &venue, 1).await.unwrap());
+        assert_eq!(source.calls.load(Ordering::SeqCst), 0);
+        // The other venue is unaffected throughout
+        assert_eq!(health.transport(&ExchangeId::Dydx), Transport::Websocket);
+    }
+
+    #[tokio::test]
+    async fn test_forced_fallback_ignores_websocket() {
+        let (source, book, health) = fixtures();
+        let venue = ExchangeId::Dydx;
+        let forced = FallbackPolicy { forced: true, ..policy() };
+
+        health.force_rest(&venue, forced.poll_interval);
+        health.record_ws_connected(&venue, &forced);
+        assert!(health.transport(&venue).is_fallback());
+        assert!(poll_once(&source, &book, &health, &venue, 1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_polled_books_notify_subscribers() {
+        let (source, book, health) = fixtures();
+        let venue = ExchangeId::Dydx;
+        health.force_rest(&venue, policy().poll_interval);
+        let mut updates = feed_receiver(&book, Some("BTC"), &Symbol::perp("BTC")).unwrap();
+        assert!(!updates.has_changed().unwrap());
+
+        assert!(poll_once(&source, &book, &health, &venue, 1).await.unwrap());
+        tokio::time::timeout(Duration::from_secs(1), updates.changed()).await.unwrap().unwrap();
+        assert_eq!(updates.borrow_and_update().as_ref().map(|book| book.bids.len()), Some(1));
+        assert!(!updates.has_changed().unwrap());
+    }
+
+    #[test]
+    fn test_subscribing_needs_the_feed_symbol() {
+        let book = shared_book();
+        let eth = Symbol::perp("ETH");
+        assert!(matches!(feed_receiver(&book, Some("BTC"), &eth), Err(AggregatorError::MarketDataNotFound(_))));
+        assert!(matches!(feed_receiver(&book, None, &eth), Err(AggregatorError::MarketDataNotFound(_))));
+    }
+
+    #[test]
+    fn test_transport_label() {
+        assert_eq!(Transport::RestFallback { interval: Duration::from_secs(2) }.to_string(), "REST fallback (2s)");
+        assert_eq!(Transport::RestFallback { interval: Duration::from_millis(500) }.to_string(), "REST fallback (500ms)");
+        assert_eq!(Transport::Websocket.to_string(), "websocket");
+    }
+
+    #[test]
+    fn test_parse_dydx_orderbook() {
+        let body = r#"{
+            "bids": [{"price": "99.5", "size": "1"}, {"price": "100", "size": "2"}, {"price": "98", "size": "0"}],
+            "asks": [{"price": "101", "size": "3"}, {"price": "100.5", "size": "4"}]
+        }"#;
+        let book = parse_dydx_orderbook(body, "BTC-PERP", 1, 42).unwrap();
+        assert_eq!(book.bids.len(), 1);
+        assert_eq!(book.bids[0].price, 100.0);
+        assert_eq!(book.asks[0].price, 100.5);
+        assert_eq!(book.source, BookSource::Polled);
+        assert_eq!(book.timestamp, 42);
+        assert!(parse_dydx_orderbook("{}", "BTC-PERP", 1, 0).is_err());
+    }
+}
+
+#[cfg(test)]
+mod bucketing_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::types::{merge_bucketed, Level, PriceBucket};
+    use crate::test_fixtures::book;
+
+    fn prices(levels: &[Level]) -> Vec<f64> {
+        levels.iter().map(|level| level.price).collect()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_bids_bucket_down_and_asks_bucket_up() {
+        let book = book(
+            ExchangeId::Hyperliquid,
+            &[(64123.43, 1.0), (64123.4, 2.0), (64122.9, 0.5)],
+            &[(64123.51, 1.0), (64123.6, 3.0), (64124.01, 0.25)],
+            0,
+        );
+        let bucketed = book.bucketed(0.5);
+
+        // 64123.43 and 64123.4 both floor to 64123.0; 64122.9 to 64122.5
+        assert_eq!(bucketed.bids.len(), 2);
+        assert!(close(bucketed.bids[0].price, 64123.0));
+        assert_eq!(bucketed.bids[0].size, 3.0);
+        assert_eq!(bucketed.bids[0].orders, 2);
+        assert!(close(bucketed.bids[1].price, 64122.5));
+
+        // Asks ceil: 64123.51 and 64123.6 to 64124.0, 64124.01 to 64124.5
+        assert_eq!(bucketed.asks.len(), 2);
+        assert!(close(bucketed.asks[0].price, 64124.0));
+        assert_eq!(bucketed.asks[0].size, 4.0);
+        assert!(close(bucketed.asks[1].price, 64124.5));
+
+        // No bucket shows a bid above or an ask below a real level in it
+        assert!(bucketed.bids.iter().all(|bucket| bucket.price <= 64123.43));
+        assert!(bucketed.asks.iter().all(|bucket| bucket.price >= 64123.51));
+    }
+
+    #[test]
+    fn test_prices_on_an_edge_stay_put() {
+        let book = book(ExchangeId::Dydx, &[(100.1, 1.0)], &[(100.3, 1.0)], 0);
+        let bucketed = book.bucketed(0.1);
+        assert!(close(bucketed.bids[0].price, 100.1));
+        assert!(close(bucketed.asks[0].price, 100.3));
+        assert_eq!(prices(&book.bucketed(0.0).bids), prices(&book.bids));
+    }
+
+    #[test]
+    fn test_merge_keeps_per_venue_contributions() {
+        let hl = book(ExchangeId::Hyperliquid, &[(64123.43, 1.0)], &[(64123.6, 2.0)], 0);
+        let dydx = book(ExchangeId::Dydx, &[(64123.4, 0.5), (64122.0, 1.0)], &[(64123.8, 1.0)], 0);
+        let merged = merge_bucketed(&[hl, dydx], 1.0);
+
+        assert_eq!(merged.bids.len(), 2);
+        assert!(close(merged.bids[0].price, 64123.0));
+        assert_eq!(merged.bids[0].size, 1.5);
+        assert_eq!(merged.bids[0].contributions, vec![(ExchangeId::Hyperliquid, 1.0), (ExchangeId::Dydx, 0.5)]);
+        assert_eq!(merged.bids[0].detail(), "Hyperliquid 1.0000 / dYdX 0.5000");
+        assert!(close(merged.bids[1].price, 64122.0));
+
+        assert_eq!(merged.asks.len(), 1);
+        assert!(close(merged.asks[0].price, 64124.0));
+        assert_eq!(merged.asks[0].size, 3.0);
+    }
+
+    #[test]
+    fn test_parse_price_bucket() {
+        assert_eq!("0.5".parse::<PriceBucket>().unwrap(), PriceBucket::Absolute(0.5));
+        assert_eq!("5t".parse::<PriceBucket>().unwrap(), PriceBucket::Ticks(5));
+        assert_eq!(" 10 ticks ".parse::<PriceBucket>().unwrap(), PriceBucket::Ticks(10));
+        assert!("0t".parse::<PriceBucket>().is_err());
+        assert!("-1".parse::<PriceBucket>().is_err());
+        assert_eq!(PriceBucket::Ticks(5).width(Some(0.1)).map(|w| (w * 10.0).round()), Some(5.0));
+        assert_eq!(PriceBucket::Ticks(5).width(None), None);
+    }
+}
+
+#[cfg(test)]
+mod endpoint_tests {
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use crate::error::{AggregatorError, Result};
+    use async_trait::async_trait;
+    use crate::aggregator::endpoints::{probe_once, EndpointProbe, EndpointSelector, EndpointTransition, FailoverPolicy, IndexerEndpoint, DYDX_TESTNET_INDEXER};
+    use crate::config::AggregatorConfig;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::health::HealthRegistry;
+
+    const PRIMARY: &str = "https://indexer.dydx.trade";
+    const BACKUP: &str = "http://10.0.0.2:3002";
+    const FAST: Duration = Duration::from_millis(50);
+
+    // Replays a script of outcomes per endpoint; healthy once a script runs out
+    #[derive(Default)]
+    struct ScriptedProbe {
+        scripts: Mutex<HashMap<String, VecDeque<Result<Duration, String>>>>,
+    }
+
+    impl ScriptedProbe {
+        fn script(&self, rest: &str, outcomes: Vec<Result<Duration, String>>) {
+            self.scripts.lock().unwrap().entry(rest.to_string()).or_default().extend(outcomes);
+        }
+    }
+
+    #[async_trait]
+    impl EndpointProbe for ScriptedProbe {
+        async fn probe(&self, endpoint: &IndexerEndpoint) -> Result<Duration> {
+            let next = self.scripts.lock().unwrap().get_mut(&endpoint.rest).and_then(|script| script.pop_front());
+            next.unwrap_or(Ok(FAST)).map_err(AggregatorError::ApiError)
+        }
+    }
+
+    fn selector() -> EndpointSelector {
+        let policy = FailoverPolicy {
+            max_failures: 2,
+            latency_threshold: Duration::from_millis(500),
+            failback_after: Duration::from_secs(60),
+            ..FailoverPolicy::default()
+        };
+        EndpointSelector::new(IndexerEndpoint::from_rest(PRIMARY), Some(IndexerEndpoint::from_rest(BACKUP)), policy)
+    }
+
+    fn down(n: usize) -> Vec<Result<Duration, String>> {
+        vec![Err("connection refused".to_string()); n]
+    }
+
+    #[test]
+    fn test_endpoint_urls() {
+        let primary = IndexerEndpoint::from_rest("https://indexer.dydx.trade/");
+        assert_eq!(primary.rest, PRIMARY);
+        assert_eq!(primary.ws, "wss://indexer.dydx.trade/v4/ws");
+        assert_eq!(primary.url("/v4/height"), "https://indexer.dydx.trade/v4/height");
+        assert_eq!(IndexerEndpoint::from_rest(BACKUP).ws, "ws://10.0.0.2:3002/v4/ws");
+    }
+
+    #[tokio::test]
+    async fn test_failover_and_failback_sequence() {
+        let health = Arc::new(HealthRegistry::default());
+        let selector = selector().with_health(health.clone());
+        let mut switched = selector.subscribe();
+        let probe = ScriptedProbe::default();
+        probe.script(PRIMARY, down(2));
+
+        // One failure is tolerated
+        assert!(probe_once(&selector, &probe, 0).await.is_empty());
+        assert_eq!(selector.active().rest, PRIMARY);
+
+        let transitions = probe_once(&selector, &probe, 10_000).await;
+        assert_eq!(transitions, vec![EndpointTransition::FailedOver {
+            from: PRIMARY.to_string(),
+            to: BACKUP.to_string(),
+            reason: "API error: connection refused".to_string(),
+        }]);
+        assert_eq!(selector.active().rest, BACKUP);
+        assert_eq!(health.venue(&ExchangeId::Dydx).endpoint.as_deref(), Some(BACKUP));
+        // Websocket feeds see the switch and resubscribe
+        assert!(switched.has_changed().unwrap());
+        assert_eq!(*switched.borrow_and_update(), 1);
+
+        // The primary recovers, but must stay healthy for the whole window
+        assert!(probe_once(&selector, &probe, 20_000).await.is_empty());
+        assert!(probe_once(&selector, &probe, 50_000).await.is_empty());
+        assert_eq!(selector.active().rest, BACKUP);
+
+        let transitions = probe_once(&selector, &probe, 80_000).await;
+        assert_eq!(transitions, vec![EndpointTransition::FailedBack { from: BACKUP.to_string(), to: PRIMARY.to_string() }]);
+        assert_eq!(selector.active().rest, PRIMARY);
+        assert_eq!(health.venue(&ExchangeId::Dydx).endpoint.as_deref(), Some(PRIMARY));
+        assert_eq!(*switched.borrow_and_update(), 0);
+
+        // Both transitions are queued for notification, once
+        assert_eq!(selector.take_transitions().len(), 2);
+        assert!(selector.take_transitions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_flapping_primary_restarts_failback_window() {
+        let selector = selector();
+        let probe = ScriptedProbe::default();
+        probe.script(PRIMARY, down(2));
+        probe_once(&selector, &probe, 0).await;
+        probe_once(&selector, &probe, 10_000).await;
+        assert_eq!(selector.active_index(), 1);
+
+        // Healthy from 20s, fails again at 40s, healthy again from 50s
+        probe.script(PRIMARY, vec![Ok(FAST), Err("timeout".to_string()), Ok(FAST)]);
+        probe_once(&selector, &probe, 20_000).await;
+        probe_once(&selector, &probe, 40_000).await;
+        probe_once(&selector, &probe, 50_000).await;
+        assert!(probe_once(&selector, &probe, 90_000).await.is_empty());
+        assert_eq!(selector.active_index(), 1);
+        assert_eq!(probe_once(&selector, &probe, 110_000).await.len(), 1);
+        assert_eq!(selector.active_index(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_slow_responses_count_as_failures() {
+        let selector = selector();
+        let probe = ScriptedProbe::default();
+        probe.script(PRIMARY, vec![Ok(Duration::from_millis(900)); 2]);
+        probe_once(&selector, &probe, 0).await;
+        let transitions = probe_once(&selector, &probe, 10_000).await;
+        assert!(matches!(&transitions[..], [EndpointTransition::FailedOver { reason, .. }] if reason == "slow: 900ms"));
+        assert_eq!(selector.endpoint_health(0).consecutive_failures, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stays_put_when_backup_is_also_down() {
+        let selector = selector();
+        let probe = ScriptedProbe::default();
+        // The standby dies first, then the primary
+        probe.script(PRIMARY, vec![Ok(FAST), Ok(FAST), Err("timeout".to_string()), Err("timeout".to_string()), Err("timeout".to_string()), Err("timeout".to_string())]);
+        probe.script(BACKUP, down(5));
+        for now in [0, 10_000, 20_000, 30_000, 40_000] {
+            assert!(probe_once(&selector, &probe, now).await.is_empty());
+        }
+        assert_eq!(selector.active_index(), 0);
+
+        // Whichever recovers first takes the traffic
+        assert_eq!(probe_once(&selector, &probe, 50_000).await.len(), 1);
+        assert_eq!(selector.active().rest, BACKUP);
+    }
+
+    #[test]
+    fn test_consumer_failures_trigger_failover() {
+        // Feeds report against the index they connected to; a stale report for
+        // an endpoint no longer active never moves traffic
+        let selector = selector();
+        selector.record_failure(0, "websocket closed before any book", 0);
+        assert!(selector.record_failure(0, "websocket closed before any book", 1).is_some());
+        assert_eq!(selector.active_index(), 1);
+        assert!(selector.record_failure(0, "late error", 2).is_none());
+        assert_eq!(selector.active_index(), 1);
+    }
+
+    #[test]
+    fn test_single_endpoint_never_switches() {
+        let selector = EndpointSelector::default();
+        assert!(!selector.has_backup());
+        for now in 0..5 {
+            assert!(selector.record_failure(0, "down", now).is_none());
+        }
+        assert_eq!(selector.active().rest, PRIMARY);
+    }
+
+    #[test]
+    fn test_testnet_defaults_to_the_testnet_indexer() {
+        let testnet = AggregatorConfig { testnet: true, ..AggregatorConfig::default() };
+        let selector = EndpointSelector::from_config(&testnet);
+        assert_eq!(selector.active().rest, DYDX_TESTNET_INDEXER);
+        assert_eq!(selector.active().ws, "wss://indexer.v4testnet.dydx.exchange/v4/ws");
+        assert_eq!(EndpointSelector::from_config(&AggregatorConfig::default()).active().rest, PRIMARY);
+
+        // An explicit indexer wins on either network
+        let own = AggregatorConfig { dydx_indexer: Some(BACKUP.to_string()), ..testnet };
+        assert_eq!(EndpointSelector::from_config(&own).active().rest, BACKUP);
+    }
+
+    #[test]
+    fn test_configured_websocket_replaces_the_derived_one() {
+        let config = AggregatorConfig {
+            dydx_indexer: Some(BACKUP.to_string()),
+            dydx_indexer_ws: Some("wss://stream.example.org/v4/ws".to_string()),
+            ..AggregatorConfig::default()
+        };
+        let active = EndpointSelector::from_config(&config).active();
+        assert_eq!(active.rest, BACKUP);
+        assert_eq!(active.ws, "wss://stream.example.org/v4/ws");
+    }
+}
+
+#[cfg(test)]
+mod price_history_tests {
+    use std::time::Duration;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::price_history::PriceHistory;
+    use crate::test_fixtures::book;
+
+    const SECOND: i64 = 1000;
+
+    fn history(capacity: usize, max_markets: usize) -> PriceHistory {
+        PriceHistory::new(Duration::from_secs(5), capacity, max_markets)
+    }
+
+    #[test]
+    fn test_samples_once_per_interval() {
+        let mut history = history(60, 4);
+        assert!(history.record(&ExchangeId::Dydx, "BTC", 100.0, 0));
+        assert!(!history.record(&ExchangeId::Dydx, "BTC", 101.0, 2 * SECOND));
+        // A tick landing slightly early still counts
+        assert!(history.record(&ExchangeId::Dydx, "BTC", 102.0, 5 * SECOND - 200));
+        assert!(!history.record(&ExchangeId::Dydx, "BTC", 103.0, 9 * SECOND));
+        assert!(history.record(&ExchangeId::Dydx, "BTC", 104.0, 10 * SECOND));
+        assert_eq!(history.recent(&ExchangeId::Dydx, "BTC", 60), vec![100.0, 102.0, 104.0]);
+        // Venues are sampled independently
+        assert!(history.record(&ExchangeId::Hyperliquid, "BTC", 100.5, 10 * SECOND));
+    }
+
+    #[test]
+    fn test_drops_oldest_samples_past_capacity() {
+        let mut history = history(3, 4);
+        for i in 0..5 {
+            history.record(&ExchangeId::Dydx, "ETH", 10.0 + i as f64, i * 5 * SECOND);
+        }
+        assert_eq!(history.recent(&ExchangeId::Dydx, "ETH", 60), vec![12.0, 13.0, 14.0]);
+        assert_eq!(history.recent(&ExchangeId::Dydx, "ETH", 2), vec![13.0, 14.0]);
+        assert!(history.recent(&ExchangeId::Dydx, "SOL", 60).is_empty());
+    }
+
+    #[test]
+    fn test_evicts_least_recently_sampled_market() {
+        let mut history = history(60, 2);
+        history.record(&ExchangeId::Dydx, "BTC", 100.0, 0);
+        history.record(&ExchangeId::Dydx, "ETH", 10.0, SECOND);
+        // BTC is sampled again, so ETH is now the stalest
+        history.record(&ExchangeId::Dydx, "BTC", 101.0, 5 * SECOND);
+        history.record(&ExchangeId::Dydx, "SOL", 1.0, 6 * SECOND);
+        assert_eq!(history.markets(), 2);
+        assert!(history.recent(&ExchangeId::Dydx, "ETH", 60).is_empty());
+        assert_eq!(history.recent(&ExchangeId::Dydx, "BTC", 60), vec![100.0, 101.0]);
+    }
+
+    #[test]
+    fn test_history_survives_symbol_switch() {
+        let mut history = history(60, 4);
+        history.record(&ExchangeId::Hyperliquid, "BTC", 100.0, 0);
+        history.record(&ExchangeId::Hyperliquid, "BTC", 101.0, 5 * SECOND);
+        // Viewing ETH for a while, then back to BTC
+        history.record(&ExchangeId::Hyperliquid, "ETH", 10.0, 10 * SECOND);
+        history.record(&ExchangeId::Hyperliquid, "BTC", 102.0, 15 * SECOND);
+        assert_eq!(history.recent(&ExchangeId::Hyperliquid, "BTC", 60), vec![100.0, 101.0, 102.0]);
+    }
+
+    #[test]
+    fn test_ignores_stale_and_one_sided_books() {
+        let mut history = history(60, 4);
+        let mut book = book(ExchangeId::Dydx, &[(99.0, 1.0)], &[(101.0, 1.0)], 100_000);
+        // A dead feed keeps its last book around
+        assert!(!history.record_book(&book, 200_000));
+        assert!(history.record_book(&book, 100_500));
+        book.asks.clear();
+        assert!(!history.record_book(&book, 110_000));
+        assert_eq!(history.recent(&ExchangeId::Dydx, "BTC", 60), vec![100.0]);
+    }
+}
+
+#[cfg(test)]
+mod aggregated_book_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::types::aggregate_books;
+    use crate::test_fixtures::book;
+
+    const NOW: u64 = 1_700_000_000_000;
+
+    #[test]
+    fn test_levels_tagged_and_sorted_across_venues() {
+        let books = vec![
+            (ExchangeId::Dydx, Ok(book(ExchangeId::Dydx, &[(100.0, 1.0), (99.0, 2.0)], &[(101.0, 1.0), (103.0, 1.0)], NOW))),
+            (ExchangeId::Hyperliquid, Ok(book(ExchangeId::Hyperliquid, &[(100.5, 3.0), (100.0, 4.0)], &[(101.0, 5.0), (102.0, 1.0)], NOW - 500))),
+        ];
+        let merged = aggregate_books("BTC", books, 10_000, NOW);
+        let bids: Vec<(&str, f64, f64)> = merged.bids.iter().map(|l| (l.exchange.as_str(), l.price, l.size)).collect();
+        assert_eq!(bids, vec![("Hyperliquid", 100.5, 3.0), ("dYdX", 100.0, 1.0), ("Hyperliquid", 100.0, 4.0), ("dYdX", 99.0, 2.0)]);
+        let asks: Vec<(&str, f64)> = merged.asks.iter().map(|l| (l.exchange.as_str(), l.price)).collect();
+        // Same price on both venues: two entries, in venue order
+        assert_eq!(asks, vec![("dYdX", 101.0), ("Hyperliquid", 101.0), ("Hyperliquid", 102.0), ("dYdX", 103.0)]);
+        assert_eq!(merged.sources, vec![ExchangeId::Dydx, ExchangeId::Hyperliquid]);
+        assert!(merged.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_failed_and_stale_venues_are_skipped() {
+        let books = vec![
+            (ExchangeId::Dydx, Err("connection refused".to_string())),
+            (ExchangeId::Hyperliquid, Ok(book(ExchangeId::Hyperliquid, &[(100.0, 1.0)], &[(101.0, 1.0)], NOW))),
+        ];
+        let merged = aggregate_books("BTC", books, 10_000, NOW);
+        assert_eq!(merged.sources, vec![ExchangeId::Hyperliquid]);
+        assert_eq!(merged.skipped, vec![(ExchangeId::Dydx, "connection refused".to_string())]);
+        assert_eq!(merged.bids.len(), 1);
+
+        let stale = vec![(ExchangeId::Dydx, Ok(book(ExchangeId::Dydx, &[(100.0, 1.0)], &[], NOW - 60_000)))];
+        let merged = aggregate_books("BTC", stale, 10_000, NOW);
+        assert!(merged.sources.is_empty());
+        assert!(merged.bids.is_empty());
+        assert_eq!(merged.skipped, vec![(ExchangeId::Dydx, "stale (60000ms old)".to_string())]);
+    }
+
+    #[test]
+    fn test_estimate_fill_walks_levels() {
+        let book = book(ExchangeId::Dydx, &[(100.0, 1.0), (99.0, 2.0)], &[(101.0, 1.0), (103.0, 1.0)], NOW);
+
+        // $101 from the first ask, $51.50 from the second
+        let estimate = book.estimate_fill(true, 152.5, None).unwrap();
+        assert_eq!(estimate.levels, 2);
+        assert!((estimate.size - 1.5).abs() < 1e-9);
+        assert!((estimate.avg_price - 152.5 / 1.5).abs() < 1e-9);
+
+        let sell = book.estimate_fill(false, 50.0, None).unwrap();
+        assert_eq!(sell.levels, 1);
+        assert!((sell.avg_price - 100.0).abs() < 1e-9);
+
+        // Deeper than the book, or past the limit price
+        assert_eq!(book.estimate_fill(true, 500.0, None), None);
+        assert_eq!(book.estimate_fill(true, 152.5, Some(102.0)), None);
+        assert_eq!(book.estimate_fill(true, 0.0, None), None);
+    }
+}
+
+#[cfg(test)]
+mod order_book_price_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::types::{OrderBook, Side};
+    use crate::test_fixtures;
+
+    fn book(bids: &[(f64, f64)], asks: &[(f64, f64)]) -> OrderBook {
+        test_fixtures::book(ExchangeId::Hyperliquid, bids, asks, 0)
+    }
+
+    #[test]
+    fn test_best_prices_mid_and_spread() {
+        let book = book(&[(99.5, 1.0), (99.0, 1.0)], &[(100.5, 1.0), (101.0, 1.0)]);
+        assert_eq!(book.best_bid(), Some(99.5));
+        assert_eq!(book.best_ask(), Some(100.5));
+        assert_eq!(book.mid_price(), Some(100.0));
+        assert_eq!(book.spread(), Some(1.0));
+        assert!((book.spread_bps().unwrap() - 100.0).abs() < 1e-9);
+        assert!(!book.is_crossed());
+    }
+
+    #[test]
+    fn test_one_sided_books_have_no_mid_or_spread() {
+        let bids_only = book(&[(99.5, 1.0)], &[]);
+        assert_eq!(bids_only.best_bid(), Some(99.5));
+        assert_eq!(bids_only.best_ask(), None);
+        assert_eq!(bids_only.mid_price(), None);
+        assert_eq!(bids_only.spread(), None);
+        assert_eq!(bids_only.spread_bps(), None);
+
+        let empty = book(&[], &[]);
+        assert_eq!(empty.best_bid(), None);
+        assert_eq!(empty.mid_price(), None);
+        assert!(!empty.is_crossed());
+    }
+
+    #[test]
+    fn test_crossed_books_have_no_mid() {
+        let crossed = book(&[(101.0, 1.0)], &[(100.0, 1.0)]);
+        assert!(crossed.is_crossed());
+        assert_eq!(crossed.mid_price(), None);
+        assert_eq!(crossed.spread(), Some(-1.0));
+        assert!(crossed.spread_bps().unwrap() < 0.0);
+
+        // Locked is not crossed
+        let locked = book(&[(100.0, 1.0)], &[(100.0, 1.0)]);
+        assert!(!locked.is_crossed());
+        assert_eq!(locked.mid_price(), Some(100.0));
+        assert_eq!(locked.spread(), Some(0.0));
+    }
+
+    #[test]
+    fn test_vwap_walks_the_taken_side() {
+        let book = book(&[(100.0, 1.0), (99.0, 2.0)], &[(101.0, 1.0), (103.0, 1.0)]);
+
+        // $101 from the first ask, $51.50 from the second
+        let (price, filled) = book.vwap_for_notional(152.5, Side::Buy).unwrap();
+        assert!((price - 152.5 / 1.5).abs() < 1e-9);
+        assert!((filled - 152.5).abs() < 1e-9);
+
+        let (price, filled) = book.vwap_for_notional(50.0, Side::Sell).unwrap();
+        assert!((price - 100.0).abs() < 1e-9);
+        assert!((filled - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_vwap_fills_in_part_past_the_book() {
+        let thin = book(&[(100.0, 1.0)], &[(101.0, 1.0), (103.0, 1.0)]);
+        let (price, filled) = thin.vwap_for_notional(1_000.0, Side::Buy).unwrap();
+        assert!((filled - 204.0).abs() < 1e-9);
+        assert!((price - 102.0).abs() < 1e-9);
+
+        assert_eq!(thin.vwap_for_notional(0.0, Side::Buy), None);
+        assert_eq!(thin.vwap_for_notional(-5.0, Side::Sell), None);
+        assert_eq!(thin.vwap_for_notional(f64::NAN, Side::Sell), None);
+        let no_bids = book(&[], &[(101.0, 1.0)]);
+        assert_eq!(no_bids.vwap_for_notional(100.0, Side::Sell), None);
+    }
+
+    #[test]
+    fn test_market_impact_against_the_mid() {
+        let book = book(&[(99.0, 1.0), (98.0, 2.0)], &[(101.0, 1.0), (103.0, 1.0)]);
+
+        // $101 from the first ask, $51.50 from the second, against a 100 mid
+        let buy = book.market_impact(true, 152.5).unwrap();
+        assert_eq!(buy.levels_consumed, 2);
+        assert_eq!(buy.worst_price, 103.0);
+        assert!((buy.avg_price - 152.5 / 1.5).abs() < 1e-9);
+        assert!((buy.slippage_bps - (152.5 / 1.5 - 100.0) * 100.0).abs() < 1e-6);
+        assert!(buy.fully_fillable);
+
+        let sell = book.market_impact(false, 49.5).unwrap();
+        assert_eq!(sell.levels_consumed, 1);
+        assert!((sell.slippage_bps - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_market_impact_past_the_book() {
+        let book = book(&[], &[(101.0, 1.0), (103.0, 1.0)]);
+        let impact = book.market_impact(true, 1_000.0).unwrap();
+        assert!(!impact.fully_fillable);
+        assert_eq!(impact.levels_consumed, 2);
+        assert!((impact.avg_price - 102.0).abs() < 1e-9);
+        // No bids, so measured from the best ask
+        assert!((impact.slippage_bps - (102.0 - 101.0) / 101.0 * 10_000.0).abs() < 1e-6);
+
+        assert_eq!(book.market_impact(false, 100.0), None);
+        assert_eq!(book.market_impact(true, 0.0), None);
+    }
+}
+
+#[cfg(test)]
+mod available_assets_tests {
+    use crate::aggregator::dydx::base_assets;
+
+    #[test]
+    fn test_tickers_stripped_to_sorted_base_assets() {
+        let tickers = ["ETH-USD", "BTC-USD", "SOL-USD", "BTC-USD", "XAU"].map(String::from);
+        assert_eq!(base_assets(tickers), vec!["BTC", "ETH", "SOL", "XAU"]);
+        assert!(base_assets(Vec::new()).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod hyperliquid_summary_tests {
+    use crate::aggregator::hyperliquid::market_summary;
+    use crate::aggregator::symbol::Symbol;
+    use crate::hyperliquid::AssetContext;
+
+    #[test]
+    fn test_summary_takes_open_interest_and_mark_from_context() {
+        let context = AssetContext {
+            open_interest: "12000.5".to_string(),
+            mark_px: "65000.0".to_string(),
+            funding: "0.0000125".to_string(),
+            day_ntl_vlm: "1500000000.0".to_string(),
+        };
+        let summary = market_summary(&Symbol::perp("BTC"), &context, 42).unwrap();
+        assert_eq!(summary.open_interest, 12000.5);
+        assert_eq!(summary.volume_24h, 1_500_000_000.0);
+        assert_eq!(summary.price, 65000.0);
+        assert_eq!(summary.funding_rate, 0.0000125);
+        assert_eq!(summary.timestamp, 42);
+
+        let broken = AssetContext { mark_px: "n/a".to_string(), ..context };
+        assert!(market_summary(&Symbol::perp("BTC"), &broken, 42).is_err());
+    }
+}
+
+#[cfg(test)]
+mod dydx_leverage_tests {
+    use crate::aggregator::dydx::max_leverage_from_imf;
+
+    #[test]
+    fn test_max_leverage_is_inverse_of_imf() {
+        assert_eq!(max_leverage_from_imf(0.05), Some(20.0));
+        assert_eq!(max_leverage_from_imf(0.02), Some(50.0));
+        assert_eq!(max_leverage_from_imf(0.1), Some(10.0));
+        assert!((max_leverage_from_imf(0.03).unwrap() - 33.333).abs() < 0.001);
+        assert_eq!(max_leverage_from_imf(0.0), None);
+        assert_eq!(max_leverage_from_imf(-0.05), None);
+    }
+}
+
+#[cfg(test)]
+mod dydx_book_tests {
+    use crate::aggregator::dydx_book::{BookFault, DydxBook};
+
+    const MARKET: &str = "BTC-USD";
+
+    // Prices as top-of-book pairs, best first
+    fn top(book: &DydxBook, depth: usize) -> (Vec<(f64, f64)>, Vec<(f64, f64)>) {
+        let book = book.to_order_book("BTC", depth, 0);
+        (
+            book.bids.iter().map(|level| (level.price, level.size)).collect(),
+            book.asks.iter().map(|level| (level.price, level.size)).collect(),
+        )
+    }
+
+    fn initial() -> DydxBook {
+        DydxBook::from_snapshot(MARKET, [(99.0, 1.0), (98.5, 2.0), (98.0, 3.0)], [(100.0, 1.0), (100.5, 2.0)]).unwrap()
+    }
+
+    #[test]
+    fn test_replayed_updates_give_the_expected_book() {
+        let mut book = initial();
+        // Resize a bid, add one above it, remove an ask and add a deeper one
+        book.apply(MARKET, [(98.5, 4.0), (99.5, 0.5)], [(100.0, 0.0), (101.0, 1.5)]).unwrap();
+        // Prices that differ only in float noise hit the same level
+        book.apply(MARKET, [(0.1 + 0.2 + 98.2, 0.0)], []).unwrap();
+
+        let (bids, asks) = top(&book, 10);
+        assert_eq!(bids, vec![(99.5, 0.5), (99.0, 1.0), (98.0, 3.0)]);
+        assert_eq!(asks, vec![(100.5, 2.0), (101.0, 1.5)]);
+        assert_eq!(book.best_bid(), Some(99.5));
+        assert_eq!(book.best_ask(), Some(100.5));
+
+        let (bids, asks) = top(&book, 1);
+        assert_eq!((bids.len(), asks.len()), (1, 1));
+    }
+
+    #[test]
+    fn test_levels_beyond_the_served_depth_are_kept() {
+        let mut book = initial();
+        // Deeper than the one level served below, yet still tracked
+        book.apply(MARKET, [(98.5, 5.0)], [(100.5, 0.0), (102.0, 3.0)]).unwrap();
+        assert_eq!(top(&book, 1), (vec![(99.0, 1.0)], vec![(100.0, 1.0)]));
+
+        // Clearing the top reveals them as updated
+        book.apply(MARKET, [(99.0, 0.0)], [(100.0, 0.0)]).unwrap();
+        assert_eq!(top(&book, 1), (vec![(98.5, 5.0)], vec![(102.0, 3.0)]));
+        assert_eq!(top(&book, 25).0.len(), 2);
+    }
+
+    #[test]
+    fn test_a_batch_is_judged_once_it_is_fully_applied() {
+        let mut book = initial();
+        // The new bid crosses the old best ask, which the same batch removes
+        book.apply(MARKET, [(100.2, 1.0)], [(100.0, 0.0)]).unwrap();
+        assert_eq!(book.best_bid(), Some(100.2));
+        assert_eq!(book.best_ask(), Some(100.5));
+    }
+
+    #[test]
+    fn test_crossed_books_are_faults() {
+        let mut book = initial();
+        assert_eq!(book.apply(MARKET, [(100.0, 1.0)], []), Err(BookFault::Crossed { bid: 100.0, ask: 100.0 }));
+
+        let crossed = DydxBook::from_snapshot(MARKET, [(101.0, 1.0)], [(100.0, 1.0)]);
+        assert_eq!(crossed, Err(BookFault::Crossed { bid: 101.0, ask: 100.0 }));
+    }
+
+    #[test]
+    fn test_updates_for_another_market_are_faults() {
+        let mut book = initial();
+        let fault = book.apply("ETH-USD", [(99.7, 1.0)], []).unwrap_err();
+        assert!(matches!(fault, BookFault::WrongMarket { ref got, .. } if got == "ETH-USD"));
+        // Nothing from the stray update was applied
+        assert_eq!(book, initial());
+    }
+}
+
+#[cfg(test)]
+mod book_quality_tests {
+    use crate::aggregator::book_quality::{spread_bps, BookQuality, MIN_SPREAD_SAMPLES, SPREAD_SAMPLES};
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::types::OrderBook;
+    use crate::test_fixtures::polled_top;
+
+    fn book(bid: Option<f64>, ask: Option<f64>, timestamp: u64) -> OrderBook {
+        polled_top(ExchangeId::Dydx, bid, ask, timestamp)
+    }
+
+    #[test]
+    fn test_spread_needs_both_sides() {
+        assert!((spread_bps(&book(Some(99.5), Some(100.5), 1)).unwrap() - 100.0).abs() < 1e-9);
+        assert_eq!(spread_bps(&book(Some(99.5), None, 1)), None);
+        assert_eq!(spread_bps(&book(None, None, 1)), None);
+    }
+
+    #[test]
+    fn test_average_waits_for_enough_samples() {
+        let mut quality = BookQuality::default();
+        for t in 0..MIN_SPREAD_SAMPLES as u64 - 1 {
+            quality.record_book(&book(Some(99.5), Some(100.5), t));
+        }
+        assert_eq!(quality.average_spread_bps(&ExchangeId::Dydx, "BTC"), None);
+        // A repeat of the last book is no new sample
+        quality.record_book(&book(Some(99.5), Some(100.5), MIN_SPREAD_SAMPLES as u64 - 2));
+        assert_eq!(quality.average_spread_bps(&ExchangeId::Dydx, "BTC"), None);
+        quality.record_book(&book(Some(99.5), Some(100.5), 100));
+        assert!((quality.average_spread_bps(&ExchangeId::Dydx, "BTC").unwrap() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_one_sided_books_are_kept_but_not_sampled() {
+        let mut quality = BookQuality::default();
+        for t in 0..MIN_SPREAD_SAMPLES as u64 {
+            quality.record_book(&book(Some(99.5), Some(100.5), t));
+        }
+        quality.record_book(&book(Some(99.5), None, 100));
+        assert!(quality.latest(&ExchangeId::Dydx, "BTC").unwrap().asks.is_empty());
+        assert!((quality.average_spread_bps(&ExchangeId::Dydx, "BTC").unwrap() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_window_drops_oldest_spreads() {
+        let mut quality = BookQuality::default();
+        for t in 0..SPREAD_SAMPLES as u64 {
+            quality.record_book(&book(Some(99.0), Some(101.0), t));
+        }
+        for t in 0..SPREAD_SAMPLES as u64 {
+            quality.record_book(&book(Some(99.5), Some(100.5), 1_000 + t));
+        }
+        assert!((quality.average_spread_bps(&ExchangeId::Dydx, "BTC").unwrap() - 100.0).abs() < 1e-9);
+    }
+}
+
+#[cfg(test)]
+mod retry_tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    use crate::aggregator::retry::{with_retry, RetryPolicy};
+    use crate::config::AggregatorConfig;
+    use crate::error::AggregatorError;
+    use crate::trading::dydx_service::DydxServiceError;
+
+    fn fast(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            timeout: Duration::from_millis(50),
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+        }
+    }
+
+    fn connection_reset() -> AggregatorError {
+        std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset by peer").into()
+    }
+
+    #[test]
+    fn test_policy_follows_the_config() {
+        let config = AggregatorConfig { retry_attempts: 5, timeout_ms: 1500, ..AggregatorConfig::default() };
+        let policy = RetryPolicy::from_config(&config);
+        assert_eq!(policy.attempts, 5);
+        assert_eq!(policy.timeout, Duration::from_millis(1500));
+        // Zero attempts would never make the call
+        assert_eq!(RetryPolicy::from_config(&AggregatorConfig { retry_attempts: 0, ..config }).attempts, 1);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(250));
+        assert_eq!(policy.backoff(2), Duration::from_millis(500));
+        assert_eq!(policy.backoff(3), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(40), policy.max_delay);
+    }
+
+    #[test]
+    fn test_unknown_symbols_are_not_transient() {
+        assert!(connection_reset().is_transient());
+        assert!(AggregatorError::Timeout { what: "test".to_string(), elapsed_ms: 5000 }.is_transient());
+        assert!(!AggregatorError::AssetNotFound("Symbol not found: FOO-PERP".to_string()).is_transient());
+        assert!(!AggregatorError::ApiError("400 Bad Request".to_string()).is_transient());
+    }
+
+    #[test]
+    fn test_only_connection_io_errors_are_transient() {
+        use std::io::{Error, ErrorKind};
+        assert!(AggregatorError::Io(Error::new(ErrorKind::TimedOut, "timed out")).is_transient());
+        assert!(AggregatorError::Io(Error::new(ErrorKind::BrokenPipe, "broken pipe")).is_transient());
+        assert!(!AggregatorError::Io(Error::new(ErrorKind::NotFound, "no such file")).is_transient());
+        assert!(!AggregatorError::Io(Error::new(ErrorKind::PermissionDenied, "denied")).is_transient());
+    }
+
+    #[test]
+    fn test_dydx_sdk_connection_resets_are_transient() {
+        let reset = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset by peer");
+        assert!(AggregatorError::Dydx(DydxServiceError::from(anyhow::Error::new(reset))).is_transient());
+        assert!(!AggregatorError::Dydx(DydxServiceError::from(anyhow::anyhow!("market not found"))).is_transient());
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        assert!(!AggregatorError::Dydx(DydxServiceError::indexer(missing)).is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let calls = AtomicU32::new(0);
+        let value = with_retry(&fast(3), "test", || async {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 { Err(connection_reset()) } else { Ok(42) }
+        }).await.unwrap();
+        assert_eq!(value, 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_the_configured_attempts() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), AggregatorError> = with_retry(&fast(2), "test", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(connection_reset())
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_failures_return_at_once() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), AggregatorError> = with_retry(&fast(3), "test", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(AggregatorError::AssetNotFound("FOO".to_string()))
+        }).await;
+        assert!(matches!(result, Err(AggregatorError::AssetNotFound(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_report_the_elapsed_time() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), AggregatorError> = with_retry(&fast(2), "dYdX market summary", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        }).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        match result {
+            Err(AggregatorError::Timeout { what, elapsed_ms }) => {
+                assert_eq!(what, "dYdX market summary");
+                assert!(elapsed_ms >= 50, "{}", elapsed_ms);
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod feed_tasks_tests {
+    use crate::aggregator::feed_tasks::FeedTasks;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // Stands in for a websocket reader writing into the cached book
+    fn spawn_feed(updates: Arc<AtomicU32>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                updates.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+    }
+
+    async fn restart(tasks: &FeedTasks, updates: &Arc<AtomicU32>) {
+        tasks.stop().await;
+        tasks.add(spawn_feed(updates.clone())).await;
+        tasks.add(spawn_feed(updates.clone())).await;
+    }
+
+    #[tokio::test]
+    async fn test_updates_stop_with_the_feed() {
+        let tasks = FeedTasks::default();
+        let updates = Arc::new(AtomicU32::new(0));
+        restart(&tasks, &updates).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(updates.load(Ordering::SeqCst) > 0);
+
+        assert_eq!(tasks.stop().await, 2);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let stopped_at = updates.load(Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(updates.load(Ordering::SeqCst), stopped_at);
+        assert_eq!(tasks.running().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_symbol_changes_do_not_accumulate_tasks() {
+        let metrics = tokio::runtime::Handle::current().metrics();
+        let tasks = FeedTasks::default();
+        let updates = Arc::new(AtomicU32::new(0));
+        restart(&tasks, &updates).await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let alive = metrics.num_alive_tasks();
+
+        for _ in 0..10 {
+            restart(&tasks, &updates).await;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(metrics.num_alive_tasks(), alive);
+        assert_eq!(tasks.running().await, 2);
+
+        tasks.stop().await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(metrics.num_alive_tasks(), alive - 2);
+    }
+}
+
+#[cfg(test)]
+mod asset_directory_tests {
+    use crate::aggregator::assets::{fuzzy_score, AssetDirectory};
+    use crate::aggregator::exchange_id::ExchangeId;
+
+    fn directory() -> AssetDirectory {
+        let listings = vec![
+            (ExchangeId::Hyperliquid, vec!["BTC".to_string(), "ETH".to_string(), "ETHFI".to_string(), "kPEPE".to_string()]),
+            (ExchangeId::Dydx, vec!["BTC".to_string(), "ETH".to_string(), "SEI".to_string()]),
+        ];
+        AssetDirectory::from_listings(listings, Vec::new())
+    }
+
+    fn bases(directory: &AssetDirectory, query: &str) -> Vec<String> {
+        directory.search(query).into_iter().map(|asset| asset.base.clone()).collect()
+    }
+
+    #[test]
+    fn test_union_keeps_each_venue_once() {
+        let directory = directory();
+        let btc = &directory.assets[0];
+        assert_eq!(btc.base, "BTC");
+        assert_eq!(btc.venues.len(), 2);
+        assert_eq!(btc.only_on(), None);
+        assert_eq!(directory.venues("sei"), vec![ExchangeId::Dydx]);
+        assert_eq!(directory.venues("kPEPE"), vec![ExchangeId::Hyperliquid]);
+        // Listed under the venue's own spelling
+        assert!(directory.assets.iter().any(|asset| asset.base == "kPEPE"));
+        assert!(directory.venues("DOGE").is_empty());
+    }
+
+    #[test]
+    fn test_search_ranks_exact_then_prefix_then_scattered() {
+        let directory = directory();
+        assert_eq!(bases(&directory, "eth"), vec!["ETH", "ETHFI"]);
+        assert_eq!(bases(&directory, "pp"), vec!["kPEPE"]);
+        assert_eq!(bases(&directory, "").len(), 5);
+        assert!(bases(&directory, "xyz").is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_score_needs_letters_in_order() {
+        assert!(fuzzy_score("bt", "BTC").unwrap() > fuzzy_score("tc", "BTC").unwrap());
+        assert!(fuzzy_score("tc", "BTC").unwrap() > fuzzy_score("bc", "BTC").unwrap());
+        assert_eq!(fuzzy_score("cb", "BTC"), None);
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use super::exchange_id::ExchangeId;
+
+// Hard cap on buffered prints per market, whatever the window
+const MAX_TRADES: usize = 50_000;
+const DEFAULT_WINDOW: Duration = Duration::from_secs(15 * 60);
+const DEFAULT_BUCKET_TICKS: u32 = 10;
+// Bucket volume below this is treated as empty and dropped
+const VOLUME_EPSILON: f64 = 1e-12;
+
+/// One print from a venue's trades feed. `is_buy` is the aggressor side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradePrint {
+    pub price: f64,
+    pub size: f64,
+    pub is_buy: bool,
+    // Millis timestamp
+    pub time: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileLevel {
+    // Lower edge of the bucket
+    pub price: f64,
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+}
+
+impl ProfileLevel {
+    pub fn volume(&self) -> f64 {
+        self.buy_volume + self.sell_volume
+    }
+}
+
+/// Hyperliquid prices carry five significant figures; used as the tick when
+/// the venue's own isn't cached.
+pub fn fallback_tick(price: f64) -> f64 {
+    if price <= 0.0 {
+        return 1.0;
+    }
+    10f64.powi(price.log10().floor() as i32 - 4)
+}
+
+/// Rolling volume profile and cumulative volume delta over a time window.
+/// Prints live in a ring buffer; the profile and delta are updated as prints
+/// enter and leave it, so no query rescans the window.
+#[derive(Debug, Clone)]
+pub struct TradeFlowTracker {
+    window_ms: i64,
+    // Bucket width: a whole number of ticks
+    bucket: f64,
+    trades: VecDeque<TradePrint>,
+    // Bucket index -> (buy volume, sell volume)
+    profile: BTreeMap<i64, (f64, f64)>,
+    cvd: f64,
+    // Time of the latest print, so replays after a reconnect are ignored
+    newest: Option<i64>,
+}
+
+impl TradeFlowTracker {
+    pub fn new(tick: f64, bucket_ticks: u32, window: Duration) -> Self {
+        Self {
+            window_ms: window.as_millis() as i64,
+            bucket: tick * bucket_ticks.max(1) as f64,
+            trades: VecDeque::new(),
+            profile: BTreeMap::new(),
+            cvd: 0.0,
+            newest: None,
+        }
+    }
+
+    pub fn bucket_width(&self) -> f64 {
+        self.bucket
+    }
+
+    fn bucket_of(&self, price: f64) -> i64 {
+        // Floor, so each bucket is [edge, edge + width); the epsilon keeps
+        // prices on an edge from falling into the bucket below
+        (price / self.bucket + 1e-9).floor() as i64
+    }
+
+    fn apply(&mut self, print: &TradePrint, sign: f64) {
+        let key = self.bucket_of(print.price);
+        let entry = self.profile.entry(key).or_default();
+        if print.is_buy {
+            entry.0 += sign * print.size;
+        } else {
+            entry.1 += sign * print.size;
+        }
+        if entry.0 + entry.1 < VOLUME_EPSILON {
+            self.profile.remove(&key);
+        }
+        self.cvd += sign * if print.is_buy { print.size } else { -print.size };
+    }
+
+    /// Add a print and evict everything that has left the window since.
+    /// Prints older than the newest one seen are ignored.
+    pub fn record(&mut self, print: TradePrint) {
+        if print.size <= 0.0 || print.price <= 0.0 || self.newest.is_some_and(|newest| print.time < newest) {
+            return;
+        }
+        self.newest = Some(print.time);
+        self.apply(&print, 1.0);
+        self.trades.push_back(print);
+        self.evict(print.time);
+    }
+
+    /// Drop prints older than the window ending at `now_ms`, and the oldest
+    /// prints beyond the buffer cap.
+    pub fn evict(&mut self, now_ms: i64) {
+        while let Some(oldest) = self.trades.front().copied() {
+            if oldest.time > now_ms - self.window_ms && self.trades.len() <= MAX_TRADES {
+                break;
+            }
+            self.trades.pop_front();
+            self.apply(&oldest, -1.0);
+        }
+    }
+
+    /// Re-bucket on a new tick size, e.g. once market metadata arrives.
+    pub fn set_tick(&mut self, tick: f64, bucket_ticks: u32) {
+        self.bucket = tick * bucket_ticks.max(1) as f64;
+        self.profile.clear();
+        self.cvd = 0.0;
+        for print in self.trades.clone() {
+            self.apply(&print, 1.0);
+        }
+    }
+
+    pub fn newest(&self) -> Option<i64> {
+        self.newest
+    }
+
+    /// Buffered prints strictly newer than `after`, oldest first.
+    pub fn prints_since(&self, after: i64) -> Vec<TradePrint> {
+        self.trades.iter().filter(|print| print.time > after).copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    /// Buy minus sell volume over the window
+    pub fn cvd(&self) -> f64 {
+        self.cvd
+    }
+
+    /// Highest price first
+    pub fn profile(&self) -> Vec<ProfileLevel> {
+        self.profile.iter()
+            .rev()
+            .map(|(key, (buy, sell))| ProfileLevel {
+                price: *key as f64 * self.bucket,
+                buy_volume: buy.max(0.0),
+                sell_volume: sell.max(0.0),
+            })
+            .collect()
+    }
+
+    /// Running delta across the window, sampled at the end of `points` equal
+    /// time slices ending at `now_ms`.
+    pub fn cvd_series(&self, now_ms: i64, points: usize) -> Vec<f64> {
+        if points == 0 {
+            return Vec::new();
+        }
+        let start = now_ms - self.window_ms;
+        let slice = self.window_ms as f64 / points as f64;
+        let mut series = vec![0.0; points];
+        for print in &self.trades {
+            let index = (((print.time - start) as f64 / slice) as usize).min(points - 1);
+            series[index] += if print.is_buy { print.size } else { -print.size };
+        }
+        let mut running = 0.0;
+        for value in series.iter_mut() {
+            running += *value;
+            *value = running;
+        }
+        series
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeFlowSnapshot {
+    pub profile: Vec<ProfileLevel>,
+    pub bucket: f64,
+    pub cvd: f64,
+    pub cvd_series: Vec<f64>,
+    pub trades: usize,
+}
+
+/// Trackers for every market the feeds have seen, keyed by venue and the
+/// symbol's display form.
+#[derive(Debug)]
+pub struct TradeFlow {
+    window: Duration,
+    bucket_ticks: u32,
+    ticks: HashMap<(ExchangeId, String), f64>,
+    trackers: HashMap<(ExchangeId, String), TradeFlowTracker>,
+}
+
+pub type SharedTradeFlow = Arc<Mutex<TradeFlow>>;
+
+impl Default for TradeFlow {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW, DEFAULT_BUCKET_TICKS)
+    }
+}
+
+impl TradeFlow {
+    pub fn new(window: Duration, bucket_ticks: u32) -> Self {
+        Self { window, bucket_ticks, ticks: HashMap::new(), trackers: HashMap::new() }
+    }
+
+    pub fn set_tick(&mut self, exchange: &ExchangeId, market: &str, tick: f64) {
+        let key = (exchange.clone(), market.to_string());
+        if self.ticks.get(&key) == Some(&tick) {
+            return;
+        }
+        if let Some(tracker) = self.trackers.get_mut(&key) {
+            tracker.set_tick(tick, self.bucket_ticks);
+        }
+        self.ticks.insert(key, tick);
+    }
+
+    pub fn record(&mut self, exchange: &ExchangeId, market: &str, print: TradePrint) {
+        let key = (exchange.clone(), market.to_string());
+        let tick = self.ticks.get(&key).copied().unwrap_or_else(|| fallback_tick(print.price));
+        let (window, bucket_ticks) = (self.window, self.bucket_ticks);
+        self.trackers.entry(key)
+            .or_insert_with(|| TradeFlowTracker::new(tick, bucket_ticks, window))
+            .record(print);
+    }
+
+    pub fn newest(&self, exchange: &ExchangeId, market: &str) -> Option<i64> {
+        self.trackers.get(&(exchange.clone(), market.to_string()))?.newest()
+    }
+
+    pub fn prints_since(&self, exchange: &ExchangeId, market: &str, after: i64) -> Vec<TradePrint> {
+        self.trackers.get(&(exchange.clone(), market.to_string()))
+            .map(|tracker| tracker.prints_since(after))
+            .unwrap_or_default()
+    }
+
+    pub fn snapshot(&mut self, exchange: &ExchangeId, market: &str, now_ms: i64, cvd_points: usize) -> Option<TradeFlowSnapshot> {
+        let tracker = self.trackers.get_mut(&(exchange.clone(), market.to_string()))?;
+        tracker.evict(now_ms);
+        Some(TradeFlowSnapshot {
+            profile: tracker.profile(),
+            bucket: tracker.bucket_width(),
+            cvd: tracker.cvd(),
+            cvd_series: tracker.cvd_series(now_ms, cvd_points),
+            trades: tracker.len(),
+        })
+    }
+}
@@ -1,14 +1,22 @@
 use async_trait::async_trait;
-use anyhow::Result;
+use tokio::sync::watch;
+use crate::error::Result;
+use super::symbol::Symbol;
 use super::types::{LeverageInfo, OrderBook, MarketSummary};
 
 #[async_trait]
 pub trait ExchangeAggregator {
     async fn new(testnet: bool) -> Result<Self> where Self: Sized;
-    async fn start_market_updates(&mut self, symbol: &str) -> Result<()>;
-    async fn get_market_summary(&self, symbol: &str) -> Result<MarketSummary>;
-    async fn get_leverage_info(&self, symbol: &str) -> Result<LeverageInfo>;
-    async fn get_orderbook(&self, symbol: &str) -> Result<OrderBook>;
+    async fn start_market_updates(&mut self, symbol: &Symbol) -> Result<()>;
+    /// Abort the feed's tasks and drop its subscriptions. The cached book is
+    /// kept but no longer updates.
+    async fn stop_market_updates(&self);
+    async fn get_market_summary(&self, symbol: &Symbol) -> Result<MarketSummary>;
+    async fn get_leverage_info(&self, symbol: &Symbol) -> Result<LeverageInfo>;
+    async fn get_orderbook(&self, symbol: &Symbol) -> Result<OrderBook>;
+    /// Change notifications for the streamed book of `symbol`, which must be
+    /// the symbol the feed was started for.
+    fn subscribe_orderbook(&self, symbol: &Symbol) -> Result<watch::Receiver<Option<OrderBook>>>;
     async fn get_available_assets(&self) -> Result<Vec<String>>;
     async fn is_testnet(&self) -> bool;
 }
@@ -1,20 +1,392 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
 use serde::{Deserialize, Serialize};
 use crate::trading::positions::Position;
+use super::exchange_id::ExchangeId;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LeverageInfo {
-    pub exchange: String,
+    pub exchange: ExchangeId,
     pub symbol: String,
     pub max_leverage: f64,
 }
 
+// Levels per side kept in a venue's book unless configured otherwise
+pub const DEFAULT_BOOK_DEPTH: usize = 25;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OrderBook {
-    pub exchange: String,
+    pub exchange: ExchangeId,
     pub symbol: String,
     pub bids: Vec<Level>,
     pub asks: Vec<Level>,
+    // Local-clock millis, corrected for venue clock skew
     pub timestamp: u64,
+    // The venue's own timestamp, when the message carried one
+    #[serde(default)]
+    pub venue_timestamp: Option<u64>,
+    #[serde(default)]
+    pub source: BookSource,
+}
+
+/// How a book reached us: streamed over the websocket or polled over REST.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum BookSource {
+    #[default]
+    Websocket,
+    Polled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl OrderBook {
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.first().map(|level| level.price)
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|level| level.price)
+    }
+
+    /// Best bid above the best ask; a locked book (equal prices) isn't crossed
+    pub fn is_crossed(&self) -> bool {
+        matches!((self.best_bid(), self.best_ask()), (Some(bid), Some(ask)) if bid > ask)
+    }
+
+    /// None unless the book is two-sided and not crossed
+    pub fn mid_price(&self) -> Option<f64> {
+        if self.is_crossed() {
+            return None;
+        }
+        Some((self.best_bid()? + self.best_ask()?) / 2.0)
+    }
+
+    /// Ask minus bid, negative when the book is crossed
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.best_ask()? - self.best_bid()?)
+    }
+
+    /// Spread in bps of the average of the best prices, crossed or not
+    pub fn spread_bps(&self) -> Option<f64> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        let average = (bid + ask) / 2.0;
+        (average > 0.0).then(|| (ask - bid) / average * 10_000.0)
+    }
+
+    /// Average price and notional filled taking up to `usd` of liquidity:
+    /// asks for a buy, bids for a sell. Unlike `estimate_fill` a book too thin
+    /// for the whole notional still fills in part, with less than `usd`
+    /// filled. None for an empty side or a non-positive notional.
+    pub fn vwap_for_notional(&self, usd: f64, side: Side) -> Option<(f64, f64)> {
+        if usd.is_nan() || usd <= 0.0 {
+            return None;
+        }
+        let levels = match side {
+            Side::Buy => &self.asks,
+            Side::Sell => &self.bids,
+        };
+        let (mut filled_usd, mut size) = (0.0, 0.0);
+        for level in levels.iter().filter(|level| level.price > 0.0) {
+            let taken = (usd - filled_usd).min(level.price * level.size);
+            filled_usd += taken;
+            size += taken / level.price;
+            if usd - filled_usd <= usd * 1e-9 {
+                break;
+            }
+        }
+        (size > 0.0).then(|| (filled_usd / size, filled_usd))
+    }
+
+    /// What a market order taking `usd_value` would do to the book: asks for
+    /// a buy, bids for a sell. Slippage is measured from the mid, or from the
+    /// touch when the book is one-sided or crossed. A book too thin for the
+    /// whole notional gives the estimate for what it holds, not fully
+    /// fillable. None for an empty side or a non-positive notional.
+    pub fn market_impact(&self, is_buy: bool, usd_value: f64) -> Option<ImpactEstimate> {
+        if usd_value.is_nan() || usd_value <= 0.0 {
+            return None;
+        }
+        let levels = if is_buy { &self.asks } else { &self.bids };
+        let (mut filled_usd, mut size, mut levels_consumed, mut worst_price) = (0.0, 0.0, 0, 0.0);
+        for level in levels.iter().filter(|level| level.price > 0.0) {
+            let taken = (usd_value - filled_usd).min(level.price * level.size);
+            filled_usd += taken;
+            size += taken / level.price;
+            levels_consumed += 1;
+            worst_price = level.price;
+            if usd_value - filled_usd <= usd_value * 1e-9 {
+                break;
+            }
+        }
+        if size <= 0.0 {
+            return None;
+        }
+        let avg_price = filled_usd / size;
+        let reference = self.mid_price().or(if is_buy { self.best_ask() } else { self.best_bid() })?;
+        let worse = if is_buy { avg_price - reference } else { reference - avg_price };
+        Some(ImpactEstimate {
+            avg_price,
+            worst_price,
+            slippage_bps: worse / reference * 10_000.0,
+            levels_consumed,
+            fully_fillable: usd_value - filled_usd <= usd_value * 1e-9,
+        })
+    }
+
+    pub fn age_ms(&self, local_now_ms: u64) -> u64 {
+        local_now_ms.saturating_sub(self.timestamp)
+    }
+
+    pub fn is_stale(&self, max_age_ms: u64, local_now_ms: u64) -> bool {
+        self.age_ms(local_now_ms) > max_age_ms
+    }
+
+    /// Levels summed into buckets of `bucket` width. Bids round down and
+    /// asks round up, so a bucket's price is never better than what can
+    /// actually be filled. Unchanged for a non-positive width.
+    pub fn bucketed(&self, bucket: f64) -> OrderBook {
+        if bucket.is_nan() || bucket <= 0.0 {
+            return self.clone();
+        }
+        let side = |levels: &[Level], is_bid: bool| -> Vec<Level> {
+            let mut buckets: BTreeMap<i64, Level> = BTreeMap::new();
+            for level in levels {
+                let key = bucket_key(level.price, bucket, is_bid);
+                let entry = buckets.entry(key).or_insert(Level { price: key as f64 * bucket, size: 0.0, orders: 0 });
+                entry.size += level.size;
+                entry.orders += level.orders;
+            }
+            let levels = buckets.into_values();
+            if is_bid { levels.rev().collect() } else { levels.collect() }
+        };
+        OrderBook {
+            bids: side(&self.bids, true),
+            asks: side(&self.asks, false),
+            ..self.clone()
+        }
+    }
+
+    /// What taking `usd_value` of liquidity would fill at: asks for a buy,
+    /// bids for a sell, no further than `limit` when given. None when the
+    /// book doesn't hold enough within it.
+    pub fn estimate_fill(&self, is_buy: bool, usd_value: f64, limit: Option<f64>) -> Option<FillEstimate> {
+        if usd_value.is_nan() || usd_value <= 0.0 {
+            return None;
+        }
+        let levels = if is_buy { &self.asks } else { &self.bids };
+        let mut remaining = usd_value;
+        let mut estimate = FillEstimate { avg_price: 0.0, size: 0.0, usd_value, levels: 0 };
+        for level in levels {
+            let beyond_limit = limit.is_some_and(|limit| if is_buy { level.price > limit } else { level.price < limit });
+            if beyond_limit || level.price <= 0.0 {
+                break;
+            }
+            let taken = remaining.min(level.price * level.size);
+            estimate.size += taken / level.price;
+            estimate.levels += 1;
+            remaining -= taken;
+            if remaining <= usd_value * 1e-9 {
+                estimate.avg_price = usd_value / estimate.size;
+                return Some(estimate);
+            }
+        }
+        None
+    }
+}
+
+/// Expected result of walking a book for a given notional.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FillEstimate {
+    // Volume-weighted over the levels consumed
+    pub avg_price: f64,
+    // Base size
+    pub size: f64,
+    pub usd_value: f64,
+    // Levels touched, the last one possibly in part
+    pub levels: usize,
+}
+
+/// Expected cost of a market order against one venue's book.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ImpactEstimate {
+    // Volume-weighted over what the book can fill
+    pub avg_price: f64,
+    // Price of the last level reached
+    pub worst_price: f64,
+    // Average price against the mid, positive when worse
+    pub slippage_bps: f64,
+    pub levels_consumed: usize,
+    // False when the book holds less than the whole notional
+    pub fully_fillable: bool,
+}
+
+// Bucket index for a price: floor for bids, ceil for asks. The epsilon keeps
+// prices already on an edge from spilling into the next bucket.
+fn bucket_key(price: f64, bucket: f64, is_bid: bool) -> i64 {
+    let ratio = price / bucket;
+    if is_bid { (ratio + 1e-9).floor() as i64 } else { (ratio - 1e-9).ceil() as i64 }
+}
+
+/// Width of the price buckets used to merge near-identical levels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceBucket {
+    Absolute(f64),
+    Ticks(u32),
+}
+
+impl Default for PriceBucket {
+    fn default() -> Self {
+        Self::Ticks(1)
+    }
+}
+
+impl PriceBucket {
+    /// Width in price units; None for tick-based buckets without a tick.
+    pub fn width(&self, tick: Option<f64>) -> Option<f64> {
+        match self {
+            Self::Absolute(width) => Some(*width),
+            Self::Ticks(ticks) => tick.map(|tick| tick * (*ticks).max(1) as f64),
+        }
+    }
+}
+
+impl FromStr for PriceBucket {
+    type Err = String;
+
+    /// "0.5" for an absolute width, "5t" or "5 ticks" for a tick multiple.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().to_lowercase();
+        if let Some(ticks) = trimmed.strip_suffix("ticks").or_else(|| trimmed.strip_suffix('t')) {
+            return match ticks.trim().parse::<u32>() {
+                Ok(ticks) if ticks > 0 => Ok(Self::Ticks(ticks)),
+                _ => Err(format!("Invalid tick count '{}'", s.trim())),
+            };
+        }
+        match trimmed.parse::<f64>() {
+            Ok(width) if width > 0.0 => Ok(Self::Absolute(width)),
+            _ => Err(format!("Invalid bucket size '{}': use a price like 0.5 or ticks like 5t", s.trim())),
+        }
+    }
+}
+
+/// One level of a cross-venue book, keeping each venue's share.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedLevel {
+    pub price: f64,
+    pub size: f64,
+    // Size per venue, in the order the books were given
+    pub contributions: Vec<(ExchangeId, f64)>,
+}
+
+impl MergedLevel {
+    /// "HL 1.2000 / dYdX 0.5000" style detail line
+    pub fn detail(&self) -> String {
+        self.contributions.iter()
+            .map(|(exchange, size)| format!("{} {:.4}", exchange, size))
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergedBook {
+    pub bids: Vec<MergedLevel>,
+    pub asks: Vec<MergedLevel>,
+}
+
+/// Bucket every book at the same width and sum them per bucket, bids
+/// highest first and asks lowest first.
+pub fn merge_bucketed(books: &[OrderBook], bucket: f64) -> MergedBook {
+    let side = |is_bid: bool| -> Vec<MergedLevel> {
+        let mut merged: BTreeMap<i64, MergedLevel> = BTreeMap::new();
+        for book in books {
+            let levels = if is_bid { &book.bids } else { &book.asks };
+            for level in levels {
+                let (key, price) = if bucket.is_nan() || bucket <= 0.0 {
+                    // No bucketing: only identical prices merge
+                    (level.price.to_bits() as i64, level.price)
+                } else {
+                    let key = bucket_key(level.price, bucket, is_bid);
+                    (key, key as f64 * bucket)
+                };
+                let entry = merged.entry(key).or_insert(MergedLevel { price, size: 0.0, contributions: Vec::new() });
+                entry.size += level.size;
+                match entry.contributions.iter_mut().find(|(exchange, _)| *exchange == book.exchange) {
+                    Some((_, size)) => *size += level.size,
+                    None => entry.contributions.push((book.exchange.clone(), level.size)),
+                }
+            }
+        }
+        let mut levels: Vec<MergedLevel> = merged.into_values().collect();
+        if is_bid {
+            levels.sort_by(|a, b| b.price.total_cmp(&a.price));
+        } else {
+            levels.sort_by(|a, b| a.price.total_cmp(&b.price));
+        }
+        levels
+    };
+    MergedBook { bids: side(true), asks: side(false) }
+}
+
+/// One venue's level in a cross-venue book. Levels at the same price on
+/// different venues stay separate entries.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AggregatedLevel {
+    pub exchange: ExchangeId,
+    pub price: f64,
+    pub size: f64,
+    pub orders: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AggregatedOrderBook {
+    pub symbol: String,
+    // Highest first
+    pub bids: Vec<AggregatedLevel>,
+    // Lowest first
+    pub asks: Vec<AggregatedLevel>,
+    // Venues whose books went into the merge
+    pub sources: Vec<ExchangeId>,
+    // Venues left out, with the reason
+    pub skipped: Vec<(ExchangeId, String)>,
+}
+
+/// Merge each venue's book result into one, tagging every level with its
+/// venue. Failed books and books older than `max_age_ms` are skipped rather
+/// than failing the merge. Equal prices keep the order the venues were given.
+pub fn aggregate_books(symbol: &str, books: Vec<(ExchangeId, Result<OrderBook, String>)>, max_age_ms: u64, now_ms: u64) -> AggregatedOrderBook {
+    let mut aggregated = AggregatedOrderBook { symbol: symbol.to_string(), ..AggregatedOrderBook::default() };
+    for (exchange, book) in books {
+        let book = match book {
+            Ok(book) if book.is_stale(max_age_ms, now_ms) => {
+                aggregated.skipped.push((exchange, format!("stale ({}ms old)", book.age_ms(now_ms))));
+                continue;
+            }
+            Ok(book) => book,
+            Err(e) => {
+                aggregated.skipped.push((exchange, e));
+                continue;
+            }
+        };
+        let tag = |level: &Level| AggregatedLevel {
+            exchange: exchange.clone(),
+            price: level.price,
+            size: level.size,
+            orders: level.orders,
+        };
+        aggregated.bids.extend(book.bids.iter().map(tag));
+        aggregated.asks.extend(book.asks.iter().map(tag));
+        aggregated.sources.push(exchange);
+    }
+    // Stable sorts, so ties stay in venue order
+    aggregated.bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+    aggregated.asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+    aggregated
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,6 +403,9 @@ pub struct MarketSummary {
     pub volume_24h: f64,
     pub open_interest: f64,
     pub funding_rate: f64,
+    // Local-clock millis at receipt
+    #[serde(default)]
+    pub timestamp: u64,
 }
 
 #[derive(Debug, Default)]
@@ -0,0 +1,126 @@
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::warn;
+use super::endpoints;
+use super::exchange_id::ExchangeId;
+use super::health::{SharedHealth, VenueStatus};
+use crate::hyperliquid::meta::info_url;
+const DYDX_HEIGHT_PATH: &str = "/v4/height";
+
+pub const STATUS_PROBE_INTERVAL_SECS: u64 = 30;
+// dYdX produces a block every second or so; lag past these means trouble
+const DYDX_DEGRADED_LAG_MS: i64 = 15_000;
+const DYDX_HALTED_LAG_MS: i64 = 60_000;
+
+// Lowercase fragments of venue rejections that mean trading is paused,
+// rather than something wrong with the order itself
+const HALT_PATTERNS: &[&str] = &[
+    "trading halted",
+    "trading is halted",
+    "trading is paused",
+    "trading paused",
+    "market is halted",
+    "exchange is paused",
+    "under maintenance",
+    "maintenance mode",
+];
+
+pub fn is_halt_rejection(message: &str) -> bool {
+    let message = message.to_lowercase();
+    HALT_PATTERNS.iter().any(|pattern| message.contains(pattern))
+}
+
+#[derive(Debug, Deserialize)]
+struct DydxHeight {
+    height: String,
+    time: String,
+}
+
+/// Latest indexed block of the dYdX chain: height and block time in millis.
+pub fn parse_dydx_height(json: &str) -> Result<(u64, i64)> {
+    let height: DydxHeight = serde_json::from_str(json)?;
+    Ok((height.height.parse()?, DateTime::parse_from_rfc3339(&height.time)?.timestamp_millis()))
+}
+
+/// A chain that stops producing blocks can't match orders, whatever the
+/// indexer says.
+pub fn dydx_status(block_time_ms: i64, now_ms: i64) -> VenueStatus {
+    let lag_ms = now_ms - block_time_ms;
+    if lag_ms >= DYDX_HALTED_LAG_MS {
+        VenueStatus::Halted(format!("no new blocks for {}s", lag_ms / 1000))
+    } else if lag_ms >= DYDX_DEGRADED_LAG_MS {
+        VenueStatus::Degraded(format!("blocks {}s behind", lag_ms / 1000))
+    } else {
+        VenueStatus::Operational
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HyperliquidExchangeStatus {
+    #[serde(default)]
+    special_statuses: Option<serde_json::Value>,
+}
+
+/// Hyperliquid reports maintenance and other special states as a non-null
+/// `specialStatuses` on its exchange status.
+pub fn parse_hyperliquid_status(json: &str) -> Result<VenueStatus> {
+    let status: HyperliquidExchangeStatus = serde_json::from_str(json)?;
+    Ok(match status.special_statuses {
+        None | Some(serde_json::Value::Null) => VenueStatus::Operational,
+        Some(serde_json::Value::Array(statuses)) if statuses.is_empty() => VenueStatus::Operational,
+        Some(special) => {
+            let reason = special.to_string();
+            if is_halt_rejection(&reason) {
+                VenueStatus::Halted(reason)
+            } else {
+                VenueStatus::Degraded(reason)
+            }
+        }
+    })
+}
+
+/// Fetch the current status of `exchange`. An unreachable status endpoint
+/// degrades the venue rather than halting it.
+pub async fn probe_status(exchange: &ExchangeId, testnet: bool) -> VenueStatus {
+    let result = match exchange {
+        ExchangeId::Dydx => probe_dydx().await,
+        ExchangeId::Hyperliquid => probe_hyperliquid(testnet).await,
+        ExchangeId::Custom(_) => return VenueStatus::Operational,
+    };
+    result.unwrap_or_else(|e| {
+        warn!("{} status probe failed: {}", exchange, e);
+        VenueStatus::Degraded("status check failed".to_string())
+    })
+}
+
+async fn probe_dydx() -> Result<VenueStatus> {
+    let response = reqwest::Client::new().get(endpoints::dydx().url(DYDX_HEIGHT_PATH)).send().await?.error_for_status()?;
+    let (_, block_time) = parse_dydx_height(&response.text().await?)?;
+    Ok(dydx_status(block_time, Utc::now().timestamp_millis()))
+}
+
+async fn probe_hyperliquid(testnet: bool) -> Result<VenueStatus> {
+    let response = reqwest::Client::new()
+        .post(info_url(testnet))
+        .json(&serde_json::json!({ "type": "exchangeStatus" }))
+        .send()
+        .await?
+        .error_for_status()?;
+    parse_hyperliquid_status(&response.text().await?)
+}
+
+/// Poll every venue's status into `health` until aborted.
+pub fn spawn_status_probe(health: SharedHealth, exchanges: Vec<ExchangeId>, testnet: bool) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            for exchange in &exchanges {
+                let status = probe_status(exchange, testnet).await;
+                health.set_reported_status(exchange, status);
+            }
+            tokio::time::sleep(Duration::from_secs(STATUS_PROBE_INTERVAL_SECS)).await;
+        }
+    })
+}
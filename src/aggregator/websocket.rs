@@ -1,5 +1,5 @@
 use tokio_tungstenite::{connect_async, WebSocketStream};
-use anyhow::Result;
+use crate::error::Result;
 
 pub struct WebSocketClient {
     url: String,
@@ -0,0 +1,252 @@
+use crate::error::{AggregatorError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use chrono::Utc;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::aggregator::types::{Level, OrderBook};
+use crate::analytics::{fair_price, fair_price_divergence_bps};
+
+pub mod notify;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CrossDirection {
+    Above,
+    Below,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AlertRule {
+    PriceCross { price: f64, direction: CrossDirection },
+    // Venues' fair prices for `notional` at least `bps` apart, which a thin
+    // book can't fake the way it can a mid
+    FairPriceDivergence { notional: f64, bps: f64 },
+}
+
+impl AlertRule {
+    /// Short form for alert lists
+    pub fn label(&self) -> String {
+        match self {
+            AlertRule::PriceCross { price, .. } => format!("${:.2}", price),
+            AlertRule::FairPriceDivergence { notional, bps } => format!("fair ${:.0} >= {} bps", notional, bps),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: u64,
+    pub symbol: Symbol,
+    pub rule: AlertRule,
+    // Millis timestamp of the first trigger; alerts fire once
+    pub triggered_at: Option<i64>,
+}
+
+impl Alert {
+    /// The price line, for rules that have one
+    pub fn price(&self) -> Option<f64> {
+        match self.rule {
+            AlertRule::PriceCross { price, .. } => Some(price),
+            AlertRule::FairPriceDivergence { .. } => None,
+        }
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.triggered_at.is_some()
+    }
+
+    fn crossed(&self, last_price: f64) -> bool {
+        match self.rule {
+            AlertRule::PriceCross { price, direction: CrossDirection::Above } => last_price >= price,
+            AlertRule::PriceCross { price, direction: CrossDirection::Below } => last_price <= price,
+            AlertRule::FairPriceDivergence { .. } => false,
+        }
+    }
+
+    fn diverged(&self, books: &[(ExchangeId, &OrderBook)]) -> bool {
+        match self.rule {
+            AlertRule::FairPriceDivergence { notional, bps } => {
+                let fair: Vec<(ExchangeId, Option<f64>)> = books.iter()
+                    .map(|(exchange, book)| (exchange.clone(), fair_price(book, notional)))
+                    .collect();
+                fair_price_divergence_bps(&fair).is_some_and(|divergence| divergence >= bps)
+            }
+            AlertRule::PriceCross { .. } => false,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.rule {
+            AlertRule::PriceCross { price, direction } => format!(
+                "{} crossed {} ${:.2}",
+                self.symbol,
+                if direction == CrossDirection::Above { "above" } else { "below" },
+                price
+            ),
+            AlertRule::FairPriceDivergence { notional, bps } => format!(
+                "{} fair prices ${:.0} deep diverged by {} bps or more across venues",
+                self.symbol, notional, bps
+            ),
+        }
+    }
+}
+
+/// Where an alert line lands relative to the visible part of the book.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinePlacement {
+    // Index into the visible asks, best ask first
+    Ask(usize),
+    // Index into the visible bids, best bid first
+    Bid(usize),
+    AboveBook,
+    BelowBook,
+}
+
+/// Snap an alert price to the nearest visible level, or report it off-book
+/// when it lies outside the displayed depth.
+pub fn place_line(price: f64, asks: &[Level], bids: &[Level]) -> Option<LinePlacement> {
+    let top = asks.iter().map(|l| l.price).fold(None, |max: Option<f64>, p| Some(max.map_or(p, |m| m.max(p))));
+    let bottom = bids.iter().map(|l| l.price).fold(None, |min: Option<f64>, p| Some(min.map_or(p, |m| m.min(p))));
+
+    if top.is_some_and(|top| price > top) {
+        return Some(LinePlacement::AboveBook);
+    }
+    if bottom.is_some_and(|bottom| price < bottom) {
+        return Some(LinePlacement::BelowBook);
+    }
+
+    let nearest_ask = asks.iter().enumerate()
+        .map(|(i, l)| (LinePlacement::Ask(i), (l.price - price).abs()));
+    let nearest_bid = bids.iter().enumerate()
+        .map(|(i, l)| (LinePlacement::Bid(i), (l.price - price).abs()));
+
+    nearest_ask.chain(nearest_bid)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(placement, _)| placement)
+}
+
+/// Alert rules, persisted to the config dir so they survive restarts.
+pub struct AlertEngine {
+    path: PathBuf,
+    alerts: Vec<Alert>,
+}
+
+impl AlertEngine {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("trading_aggregator");
+        fs::create_dir_all(&config_dir)?;
+        Ok(config_dir.join("alerts.json"))
+    }
+
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let alerts = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, alerts })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.alerts)?)?;
+        Ok(())
+    }
+
+    pub fn alerts(&self) -> &[Alert] {
+        &self.alerts
+    }
+
+    pub fn for_symbol<'a>(&'a self, symbol: &'a Symbol) -> impl Iterator<Item = &'a Alert> {
+        self.alerts.iter().filter(move |a| &a.symbol == symbol)
+    }
+
+    /// Add a line at `price`; the cross direction is taken from which side of
+    /// the current price it was set on.
+    pub fn add_price_cross(&mut self, symbol: &Symbol, price: f64, current_price: f64) -> Result<&Alert> {
+        if !price.is_finite() || price <= 0.0 {
+            return Err(AggregatorError::InvalidInput(format!("Invalid alert price: {}", price)));
+        }
+
+        let direction = if price >= current_price { CrossDirection::Above } else { CrossDirection::Below };
+        let id = self.alerts.iter().map(|a| a.id).max().unwrap_or(0) + 1;
+        self.alerts.push(Alert {
+            id,
+            symbol: symbol.clone(),
+            rule: AlertRule::PriceCross { price, direction },
+            triggered_at: None,
+        });
+        self.save()?;
+        Ok(self.alerts.last().expect("alert was just pushed"))
+    }
+
+    /// Alert once the venues' fair prices for `notional` are `bps` apart
+    pub fn add_fair_price_divergence(&mut self, symbol: &Symbol, notional: f64, bps: f64) -> Result<&Alert> {
+        if !notional.is_finite() || notional <= 0.0 {
+            return Err(AggregatorError::InvalidInput(format!("Invalid notional: {}", notional)));
+        }
+        if !bps.is_finite() || bps <= 0.0 {
+            return Err(AggregatorError::InvalidInput(format!("Invalid divergence: {} bps", bps)));
+        }
+
+        let id = self.alerts.iter().map(|a| a.id).max().unwrap_or(0) + 1;
+        self.alerts.push(Alert {
+            id,
+            symbol: symbol.clone(),
+            rule: AlertRule::FairPriceDivergence { notional, bps },
+            triggered_at: None,
+        });
+        self.save()?;
+        Ok(self.alerts.last().expect("alert was just pushed"))
+    }
+
+    pub fn remove(&mut self, id: u64) -> Result<bool> {
+        let before = self.alerts.len();
+        self.alerts.retain(|a| a.id != id);
+        let removed = self.alerts.len() != before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Check `symbol`'s pending alerts against the latest price and return the
+    /// ones that just fired.
+    pub fn evaluate(&mut self, symbol: &Symbol, last_price: f64) -> Vec<Alert> {
+        self.fire(symbol, |alert| alert.crossed(last_price))
+    }
+
+    /// Check `symbol`'s pending divergence alerts against each venue's book
+    /// and return the ones that just fired. Books too thin for an alert's
+    /// notional are left out of its comparison.
+    pub fn evaluate_books(&mut self, symbol: &Symbol, books: &[(ExchangeId, &OrderBook)]) -> Vec<Alert> {
+        self.fire(symbol, |alert| alert.diverged(books))
+    }
+
+    fn fire(&mut self, symbol: &Symbol, condition: impl Fn(&Alert) -> bool) -> Vec<Alert> {
+        let now = Utc::now().timestamp_millis();
+        let mut fired = Vec::new();
+
+        for alert in self.alerts.iter_mut() {
+            if &alert.symbol == symbol && !alert.is_triggered() && condition(alert) {
+                alert.triggered_at = Some(now);
+                fired.push(alert.clone());
+            }
+        }
+
+        if !fired.is_empty() {
+            if let Err(e) = self.save() {
+                tracing::error!("Failed to save alerts: {}", e);
+            }
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,240 @@
+use crate::error::{AggregatorError, Result};
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+use super::Alert;
+use crate::aggregator::health::SharedHealth;
+use crate::aggregator::symbol::Symbol;
+use crate::config::AggregatorConfig;
+
+// Discord embed sidebar colours
+const COLOR_ALERT: u32 = 0xE6_7E_22;
+const COLOR_INFO: u32 = 0x34_98_DB;
+
+/// Something worth telling the user about outside the TUI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub title: String,
+    pub symbol: Option<Symbol>,
+    // Price or other value that triggered it
+    pub value: Option<f64>,
+    pub message: String,
+    // Millis timestamp
+    pub timestamp: i64,
+}
+
+impl Notification {
+    pub fn message(message: impl Into<String>) -> Self {
+        Self {
+            title: "Notice".to_string(),
+            symbol: None,
+            value: None,
+            message: message.into(),
+            timestamp: Utc::now().timestamp_millis(),
+        }
+    }
+
+    pub fn alert(alert: &Alert, last_price: f64) -> Self {
+        Self {
+            title: format!("Alert #{}", alert.id),
+            symbol: Some(alert.symbol.clone()),
+            value: Some(last_price),
+            message: alert.describe(),
+            timestamp: alert.triggered_at.unwrap_or_else(|| Utc::now().timestamp_millis()),
+        }
+    }
+
+    pub fn test() -> Self {
+        Self {
+            title: "Test notification".to_string(),
+            ..Self::message("If you can read this, notifications are set up correctly.")
+        }
+    }
+
+    fn is_alert(&self) -> bool {
+        self.symbol.is_some()
+    }
+}
+
+/// A delivery channel. One implementation per service: Discord now, Slack or
+/// Telegram later.
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    // Stable name, used as the key in the health registry
+    fn name(&self) -> &str;
+    async fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+pub struct DiscordSink {
+    webhook_url: String,
+    // Role id to ping, e.g. "123456789012345678"
+    mention_role: Option<String>,
+    client: reqwest::Client,
+}
+
+impl DiscordSink {
+    pub fn new(webhook_url: String, mention_role: Option<String>) -> Self {
+        Self {
+            webhook_url,
+            mention_role,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Webhook body: one embed per notification, plus an optional role ping.
+    pub fn payload(&self, notification: &Notification) -> Value {
+        let mut fields = Vec::new();
+        if let Some(symbol) = &notification.symbol {
+            fields.push(json!({ "name": "Symbol", "value": symbol.to_string(), "inline": true }));
+        }
+        if let Some(value) = notification.value {
+            fields.push(json!({ "name": "Value", "value": format!("{:.2}", value), "inline": true }));
+        }
+
+        let timestamp = Utc.timestamp_millis_opt(notification.timestamp)
+            .single()
+            .unwrap_or_else(Utc::now)
+            .to_rfc3339();
+
+        let mut payload = json!({
+            "embeds": [{
+                "title": notification.title,
+                "description": notification.message,
+                "color": if notification.is_alert() { COLOR_ALERT } else { COLOR_INFO },
+                "fields": fields,
+                "timestamp": timestamp,
+            }],
+        });
+
+        if let Some(role) = &self.mention_role {
+            payload["content"] = json!(format!("<@&{}>", role));
+            // Without this Discord renders the mention but doesn't ping
+            payload["allowed_mentions"] = json!({ "roles": [role] });
+        }
+
+        payload
+    }
+}
+
+#[async_trait]
+impl NotificationSink for DiscordSink {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        self.client
+            .post(&self.webhook_url)
+            .json(&self.payload(notification))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    // Doubled after each failed attempt
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Fans notifications out to every configured sink, retrying each
+/// independently. Failures end up in the health registry, not just the log.
+pub struct Notifier {
+    sinks: Vec<Box<dyn NotificationSink>>,
+    retry: RetryPolicy,
+    health: SharedHealth,
+}
+
+impl Notifier {
+    pub fn new(health: SharedHealth) -> Self {
+        Self {
+            sinks: Vec::new(),
+            retry: RetryPolicy::default(),
+            health,
+        }
+    }
+
+    /// Notifier with every sink enabled in `config`; may be empty.
+    pub fn from_config(config: &AggregatorConfig, health: SharedHealth) -> Self {
+        let mut notifier = Self::new(health);
+        if let Some(url) = &config.discord_webhook_url {
+            notifier = notifier.with_sink(Box::new(DiscordSink::new(url.clone(), config.discord_mention_role.clone())));
+        }
+        notifier
+    }
+
+    pub fn with_sink(mut self, sink: Box<dyn NotificationSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Deliver to every sink. Returns the sinks that still failed after all
+    /// retries, with their last error.
+    pub async fn notify(&self, notification: &Notification) -> Vec<(String, AggregatorError)> {
+        let mut failed = Vec::new();
+        for sink in &self.sinks {
+            match self.send_with_retry(sink.as_ref(), notification).await {
+                Ok(()) => self.health.record_sink_success(sink.name()),
+                Err(e) => {
+                    self.health.record_sink_failure(sink.name(), &e.to_string());
+                    failed.push((sink.name().to_string(), e));
+                }
+            }
+        }
+        failed
+    }
+
+    /// Fire-and-forget delivery for callers that can't wait, like the UI loop
+    pub fn spawn_notify(self: &Arc<Self>, notification: Notification) {
+        if self.is_empty() {
+            return;
+        }
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            for (sink, e) in notifier.notify(&notification).await {
+                warn!("Notification to {} failed: {}", sink, e);
+            }
+        });
+    }
+
+    async fn send_with_retry(&self, sink: &dyn NotificationSink, notification: &Notification) -> Result<()> {
+        let mut delay = self.retry.base_delay;
+        let mut attempt = 1;
+        loop {
+            match sink.send(notification).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.retry.attempts => return Err(e),
+                Err(e) => {
+                    warn!("Notification to {} failed (attempt {}): {}", sink.name(), attempt, e);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
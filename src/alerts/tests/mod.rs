@@ -0,0 +1,214 @@
+#[cfg(test)]
+mod alert_engine_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::aggregator::types::Level;
+    use crate::alerts::{place_line, AlertEngine, CrossDirection, AlertRule, LinePlacement};
+    use crate::test_fixtures::book;
+
+    fn engine(name: &str) -> AlertEngine {
+        let path = std::env::temp_dir()
+            .join(format!("hl_aggregator_alerts_{}_{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        AlertEngine::load(path).unwrap()
+    }
+
+    fn levels(prices: &[f64]) -> Vec<Level> {
+        prices.iter().map(|&price| Level { price, size: 1.0, orders: 1 }).collect()
+    }
+
+    #[test]
+    fn test_direction_from_current_price() {
+        let mut engine = engine("direction");
+        let btc = Symbol::perp("BTC");
+
+        let above = engine.add_price_cross(&btc, 70_000.0, 65_000.0).unwrap().rule.clone();
+        assert_eq!(above, AlertRule::PriceCross { price: 70_000.0, direction: CrossDirection::Above });
+
+        let below = engine.add_price_cross(&btc, 60_000.0, 65_000.0).unwrap().rule.clone();
+        assert_eq!(below, AlertRule::PriceCross { price: 60_000.0, direction: CrossDirection::Below });
+    }
+
+    #[test]
+    fn test_alert_fires_once_for_its_symbol() {
+        let mut engine = engine("fires_once");
+        let btc = Symbol::perp("BTC");
+        engine.add_price_cross(&btc, 70_000.0, 65_000.0).unwrap();
+
+        assert!(engine.evaluate(&Symbol::perp("ETH"), 80_000.0).is_empty());
+        assert!(engine.evaluate(&btc, 69_999.0).is_empty());
+        assert_eq!(engine.evaluate(&btc, 70_100.0).len(), 1);
+        assert!(engine.evaluate(&btc, 71_000.0).is_empty());
+        assert!(engine.alerts()[0].is_triggered());
+    }
+
+    #[test]
+    fn test_alerts_persist_and_delete() {
+        let mut engine = engine("persist");
+        let path = std::env::temp_dir()
+            .join(format!("hl_aggregator_alerts_persist_{}.json", std::process::id()));
+        let eth = Symbol::perp("ETH");
+        let id = engine.add_price_cross(&eth, 4_000.0, 3_500.0).unwrap().id;
+
+        let reloaded = AlertEngine::load(path.clone()).unwrap();
+        assert_eq!(reloaded.for_symbol(&eth).count(), 1);
+
+        assert!(engine.remove(id).unwrap());
+        assert!(!engine.remove(id).unwrap());
+        assert_eq!(AlertEngine::load(path).unwrap().alerts().len(), 0);
+    }
+
+    #[test]
+    fn test_fair_price_divergence_ignores_a_thin_top_of_book() {
+        let mut engine = engine("fair_price");
+        let btc = Symbol::perp("BTC");
+        engine.add_fair_price_divergence(&btc, 10_000.0, 50.0).unwrap();
+        assert!(engine.add_fair_price_divergence(&btc, 0.0, 50.0).is_err());
+        assert_eq!(engine.alerts()[0].price(), None);
+
+        let hl = book(ExchangeId::Hyperliquid, &[(99.9, 1_000.0)], &[(100.1, 1_000.0)], 0);
+        // A dust bid lifts dYdX's mid 200 bps, but the depth agrees with Hyperliquid
+        let skewed = book(ExchangeId::Dydx, &[(101.9, 0.01), (97.9, 1_000.0)], &[(102.1, 1_000.0)], 0);
+        assert!(engine.evaluate_books(&btc, &[(ExchangeId::Hyperliquid, &hl), (ExchangeId::Dydx, &skewed)]).is_empty());
+
+        // Too thin to price is left out, leaving nothing to compare
+        let thin = book(ExchangeId::Dydx, &[(101.9, 0.01)], &[(102.1, 0.01)], 0);
+        assert!(engine.evaluate_books(&btc, &[(ExchangeId::Hyperliquid, &hl), (ExchangeId::Dydx, &thin)]).is_empty());
+        // Price cross checks leave divergence rules alone
+        assert!(engine.evaluate(&btc, 1_000_000.0).is_empty());
+
+        let moved = book(ExchangeId::Dydx, &[(100.9, 1_000.0)], &[(101.1, 1_000.0)], 0);
+        let books = [(ExchangeId::Hyperliquid, &hl), (ExchangeId::Dydx, &moved)];
+        assert!(engine.evaluate_books(&Symbol::perp("ETH"), &books).is_empty());
+        assert_eq!(engine.evaluate_books(&btc, &books).len(), 1);
+        assert!(engine.evaluate_books(&btc, &books).is_empty());
+    }
+
+    #[test]
+    fn test_line_placement() {
+        let asks = levels(&[101.0, 102.0, 103.0]);
+        let bids = levels(&[100.0, 99.0, 98.0]);
+
+        assert_eq!(place_line(102.2, &asks, &bids), Some(LinePlacement::Ask(1)));
+        assert_eq!(place_line(99.1, &asks, &bids), Some(LinePlacement::Bid(1)));
+        assert_eq!(place_line(150.0, &asks, &bids), Some(LinePlacement::AboveBook));
+        assert_eq!(place_line(50.0, &asks, &bids), Some(LinePlacement::BelowBook));
+        assert_eq!(place_line(100.0, &[], &[]), None);
+    }
+}
+
+#[cfg(test)]
+mod notify_tests {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use crate::aggregator::health::HealthRegistry;
+    use crate::aggregator::symbol::Symbol;
+    use crate::alerts::notify::{DiscordSink, Notification, Notifier, RetryPolicy};
+
+    /// Minimal HTTP server answering with `statuses` in order (204 once they
+    /// run out) and recording every request body.
+    async fn mock_server(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let recorded = bodies.clone();
+        let mut statuses: VecDeque<u16> = statuses.into();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                // Read headers, then as much body as Content-Length says
+                let body = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text.lines()
+                            .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            break String::from_utf8_lossy(&request[end + 4..end + 4 + length]).to_string();
+                        }
+                    }
+                };
+                recorded.lock().unwrap().push(serde_json::from_str(&body).unwrap());
+
+                let status = statuses.pop_front().unwrap_or(204);
+                let response = format!("HTTP/1.1 {} Mock\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, bodies)
+    }
+
+    fn notifier(url: String, role: Option<&str>, health: Arc<HealthRegistry>) -> Notifier {
+        Notifier::new(health)
+            .with_sink(Box::new(DiscordSink::new(url, role.map(str::to_string))))
+            .with_retry(RetryPolicy { attempts: 3, base_delay: Duration::from_millis(10) })
+    }
+
+    fn alert_notification() -> Notification {
+        Notification {
+            title: "Alert #1".to_string(),
+            symbol: Some(Symbol::perp("BTC")),
+            value: Some(65_000.0),
+            message: "BTC crossed above $65000.00".to_string(),
+            timestamp: 1_700_000_000_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discord_payload_is_an_embed_with_mention() {
+        let (url, bodies) = mock_server(vec![]).await;
+        let health = Arc::new(HealthRegistry::default());
+        let failed = notifier(url, Some("42"), health.clone()).notify(&alert_notification()).await;
+        assert!(failed.is_empty());
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 1);
+        let payload = &bodies[0];
+        assert_eq!(payload["content"], "<@&42>");
+        assert_eq!(payload["allowed_mentions"]["roles"][0], "42");
+
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["title"], "Alert #1");
+        assert_eq!(embed["description"], "BTC crossed above $65000.00");
+        assert_eq!(embed["fields"][0]["value"], Symbol::perp("BTC").to_string());
+        assert_eq!(embed["fields"][1]["value"], "65000.00");
+        assert!(embed["timestamp"].as_str().unwrap().starts_with("2023-11-14T22:13:20"));
+        assert!(health.sink("discord").last_success.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_delivered() {
+        let (url, bodies) = mock_server(vec![500, 429]).await;
+        let health = Arc::new(HealthRegistry::default());
+        let failed = notifier(url, None, health.clone()).notify(&Notification::test()).await;
+
+        assert!(failed.is_empty());
+        assert_eq!(bodies.lock().unwrap().len(), 3);
+        assert!(bodies.lock().unwrap()[0].get("content").is_none());
+        assert!(!health.sink("discord").is_failing());
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_retries_are_surfaced_in_health() {
+        let (url, bodies) = mock_server(vec![500, 500, 500, 500]).await;
+        let health = Arc::new(HealthRegistry::default());
+        let failed = notifier(url, None, health.clone()).notify(&Notification::test()).await;
+
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, "discord");
+        assert_eq!(bodies.lock().unwrap().len(), 3);
+
+        let sink = health.sink("discord");
+        assert_eq!(sink.consecutive_failures, 1);
+        assert!(sink.last_error.unwrap().contains("500"));
+        assert_eq!(health.failing_sinks().len(), 1);
+    }
+}
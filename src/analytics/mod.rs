@@ -0,0 +1,358 @@
+use crate::error::{AggregatorError, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use crate::aggregator::endpoints;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::aggregator::metadata::MarketSpec;
+use crate::aggregator::types::OrderBook;
+use crate::hyperliquid::meta::info_url;
+const DYDX_CANDLES_PATH: &str = "/v4/candles/perpetualMarkets";
+
+// ATR is computed on hourly candles
+pub const CANDLE_INTERVAL_MS: i64 = 60 * 60 * 1000;
+pub const DEFAULT_ATR_PERIOD: usize = 14;
+// A new hourly candle only matters once a minute or so for sizing
+const CANDLE_CACHE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candle {
+    // Millis timestamp of the candle open
+    pub open_time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+/// Largest of the candle's range and its gaps from the previous close.
+pub fn true_range(candle: &Candle, prev_close: Option<f64>) -> f64 {
+    let range = candle.high - candle.low;
+    match prev_close {
+        Some(prev) => range.max((candle.high - prev).abs()).max((candle.low - prev).abs()),
+        None => range,
+    }
+}
+
+/// Wilder's average true range over `period` candles, oldest first. Seeds with
+/// the simple mean of the first `period` true ranges, then smooths the rest.
+pub fn atr_from_candles(candles: &[Candle], period: usize) -> Option<f64> {
+    if period == 0 || candles.len() < period {
+        return None;
+    }
+
+    let ranges: Vec<f64> = candles.iter()
+        .enumerate()
+        .map(|(i, candle)| true_range(candle, i.checked_sub(1).map(|prev| candles[prev].close)))
+        .collect();
+
+    let seed = ranges[..period].iter().sum::<f64>() / period as f64;
+    Some(ranges[period..].iter().fold(seed, |atr, tr| (atr * (period as f64 - 1.0) + tr) / period as f64))
+}
+
+/// Where the stop sits: an explicit price, or a multiple of ATR from entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopSpec {
+    Price(f64),
+    AtrMultiple { atr: f64, multiple: f64 },
+}
+
+/// Parse a stop entered as an ATR multiple, e.g. "2atr" or "1.5 ATR".
+pub fn parse_atr_multiple(input: &str) -> Option<f64> {
+    let lower = input.trim().to_lowercase();
+    let multiple = lower.strip_suffix("atr")?.trim().trim_end_matches('x').trim();
+    multiple.parse().ok().filter(|m: &f64| *m > 0.0)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskSizing {
+    pub entry_price: f64,
+    pub stop_price: f64,
+    pub stop_distance: f64,
+    // Loss at the stop
+    pub risk_usd: f64,
+    pub notional: f64,
+}
+
+impl RiskSizing {
+    /// The math behind the size, for the confirmation dialog.
+    pub fn explain(&self) -> String {
+        format!(
+            "Risk ${:.2} / stop distance {:.2}% (entry ${:.2} → stop ${:.2}) = ${:.2} notional",
+            self.risk_usd,
+            self.stop_distance / self.entry_price * 100.0,
+            self.entry_price,
+            self.stop_price,
+            self.notional
+        )
+    }
+}
+
+/// USD notional such that a fill at `entry_price` stopped out at the stop
+/// loses exactly `risk_pct` percent of `account_equity` (before fees and
+/// slippage).
+pub fn size_by_risk(account_equity: f64, risk_pct: f64, entry_price: f64, is_buy: bool, stop: StopSpec) -> Result<RiskSizing> {
+    if account_equity.is_nan() || account_equity <= 0.0 {
+        return Err(AggregatorError::InvalidInput("Account equity must be positive".to_string()));
+    }
+    if !(0.0..=100.0).contains(&risk_pct) || risk_pct == 0.0 {
+        return Err(AggregatorError::InvalidInput("Risk must be between 0 and 100%".to_string()));
+    }
+    if entry_price.is_nan() || entry_price <= 0.0 {
+        return Err(AggregatorError::InvalidInput("Invalid entry price".to_string()));
+    }
+
+    let stop_price = match stop {
+        StopSpec::Price(price) => price,
+        StopSpec::AtrMultiple { atr, multiple } => {
+            if atr.is_nan() || multiple.is_nan() || atr <= 0.0 || multiple <= 0.0 {
+                return Err(AggregatorError::InvalidInput("ATR and multiple must be positive".to_string()));
+            }
+            if is_buy { entry_price - atr * multiple } else { entry_price + atr * multiple }
+        }
+    };
+
+    let stop_distance = if is_buy { entry_price - stop_price } else { stop_price - entry_price };
+    if stop_distance.is_nan() || stop_distance <= 0.0 || stop_price <= 0.0 {
+        return Err(AggregatorError::InvalidInput(format!(
+            "Stop ${:.2} must be {} entry ${:.2}",
+            stop_price,
+            if is_buy { "below" } else { "above" },
+            entry_price
+        )));
+    }
+
+    let risk_usd = account_equity * risk_pct / 100.0;
+    Ok(RiskSizing {
+        entry_price,
+        stop_price,
+        stop_distance,
+        risk_usd,
+        notional: risk_usd * entry_price / stop_distance,
+    })
+}
+
+/// Percent of free collateral behind each quick-size key, '1' to '4'.
+pub const DEFAULT_QUICK_SIZE_PERCENTS: [f64; 4] = [10.0, 25.0, 50.0, 100.0];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickSize {
+    pub percent: f64,
+    // Base size, rounded down to the market's step
+    pub size: f64,
+    // Notional of the rounded size at the reference price
+    pub usd: f64,
+    // Why the size can't be used, e.g. below the venue minimum
+    pub unavailable: Option<String>,
+}
+
+impl QuickSize {
+    pub fn describe(&self) -> String {
+        match &self.unavailable {
+            Some(reason) => format!("{}% ${:.2} ({})", self.percent, self.usd, reason),
+            None => format!("{}% ${:.2}", self.percent, self.usd),
+        }
+    }
+}
+
+/// Order notionals for each percent of free collateral at `leverage`,
+/// rounded down to the market's size step at `price`. Sizes under the
+/// venue's minimum notional or one step are kept but marked unavailable.
+pub fn quick_sizes(free_collateral: f64, leverage: f64, spec: &MarketSpec, price: f64, min_notional: f64, percents: &[f64]) -> Vec<QuickSize> {
+    if free_collateral.is_nan() || free_collateral <= 0.0 || price.is_nan() || price <= 0.0 {
+        return Vec::new();
+    }
+    let step = spec.size_step();
+    percents.iter()
+        .map(|&percent| {
+            let raw = free_collateral * percent / 100.0 * leverage.max(1.0) / price;
+            // The epsilon keeps exact multiples from rounding down a step
+            let size = step.map_or(raw, |step| (raw / step + 1e-9).floor() * step);
+            let usd = size * price;
+            let unavailable = match step {
+                Some(step) if size < step => {
+                    let decimals = (-step.log10()).ceil().max(0.0) as usize;
+                    Some(format!("below minimum size {:.*}", decimals, step))
+                }
+                _ if usd < min_notional => Some(format!("below ${:.2} minimum", min_notional)),
+                _ => None,
+            };
+            QuickSize { percent, size, usd, unavailable }
+        })
+        .collect()
+}
+
+/// Maintenance margin as a fraction of notional, taken as half the initial
+/// margin at the market's max leverage as both venues roughly do.
+pub fn maintenance_margin_rate(max_leverage: f64) -> f64 {
+    if max_leverage > 0.0 { 0.5 / max_leverage } else { 0.0 }
+}
+
+/// Estimated liquidation price of an isolated position opened at `entry`
+/// with `leverage`: where the loss leaves only the maintenance margin.
+/// None when the leverage is past what the maintenance rate allows.
+pub fn liquidation_price(entry: f64, leverage: f64, is_long: bool, maintenance_rate: f64) -> Option<f64> {
+    if entry <= 0.0 || leverage <= 0.0 || 1.0 / leverage <= maintenance_rate {
+        return None;
+    }
+    let distance = 1.0 / leverage - maintenance_rate;
+    Some(if is_long { entry * (1.0 - distance) } else { entry * (1.0 + distance) })
+}
+
+/// Depth-weighted fair price for `notional`: the midpoint of the average
+/// prices buying and selling that much would get. Unlike the mid it moves
+/// little on a thin top of book. None when either side can't fill the
+/// notional; the venue is flagged rather than extrapolated.
+pub fn fair_price(book: &OrderBook, notional: f64) -> Option<f64> {
+    let buy = book.estimate_fill(true, notional, None)?;
+    let sell = book.estimate_fill(false, notional, None)?;
+    Some((buy.avg_price + sell.avg_price) / 2.0)
+}
+
+/// Widest gap between the venues' fair prices, in basis points of the lower.
+/// Venues without one are left out; None with fewer than two left.
+pub fn fair_price_divergence_bps(fair_prices: &[(ExchangeId, Option<f64>)]) -> Option<f64> {
+    let prices: Vec<f64> = fair_prices.iter().filter_map(|(_, price)| *price).collect();
+    if prices.len() < 2 {
+        return None;
+    }
+    let low = prices.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = prices.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    Some((high - low) / low * 10_000.0)
+}
+
+type CandleCache = Mutex<HashMap<(ExchangeId, Symbol, usize), (Instant, Vec<Candle>)>>;
+
+fn candle_cache() -> &'static CandleCache {
+    static CACHE: OnceLock<CandleCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// ATR of `symbol` on `exchange` over `period` hourly candles.
+pub async fn atr(symbol: &Symbol, exchange: &ExchangeId, period: usize, testnet: bool) -> Result<f64> {
+    // One extra candle so the first true range has a previous close
+    let candles = cached_candles(exchange, symbol, period + 1, testnet).await?;
+    atr_from_candles(&candles, period)
+        .ok_or_else(|| AggregatorError::MarketDataNotFound(format!("Not enough {} candles for a {}-period ATR", exchange, period)))
+}
+
+async fn cached_candles(exchange: &ExchangeId, symbol: &Symbol, count: usize, testnet: bool) -> Result<Vec<Candle>> {
+    let key = (exchange.clone(), symbol.clone(), count);
+    if let Ok(cache) = candle_cache().lock() {
+        if let Some((fetched, candles)) = cache.get(&key) {
+            if fetched.elapsed() < CANDLE_CACHE_TTL {
+                return Ok(candles.clone());
+            }
+        }
+    }
+
+    let candles = fetch_candles(exchange, symbol, count, testnet).await?;
+    if let Ok(mut cache) = candle_cache().lock() {
+        cache.insert(key, (Instant::now(), candles.clone()));
+    }
+    Ok(candles)
+}
+
+/// Whatever hourly candles are cached for a market, however old, oldest
+/// first. Never fetches.
+pub fn cached_candles_for(exchange: &ExchangeId, symbol: &Symbol) -> Option<Vec<Candle>> {
+    let cache = candle_cache().lock().ok()?;
+    cache.iter()
+        .filter(|((ex, sym, _), _)| ex == exchange && sym == symbol)
+        .map(|(_, (_, candles))| candles)
+        .max_by_key(|candles| candles.len())
+        .cloned()
+}
+
+/// The last `count` hourly candles, oldest first.
+pub async fn fetch_candles(exchange: &ExchangeId, symbol: &Symbol, count: usize, testnet: bool) -> Result<Vec<Candle>> {
+    let client = reqwest::Client::new();
+    let mut candles = match exchange {
+        ExchangeId::Hyperliquid => {
+            let end = Utc::now().timestamp_millis();
+            let response = client.post(info_url(testnet))
+                .json(&serde_json::json!({
+                    "type": "candleSnapshot",
+                    "req": {
+                        "coin": symbol.to_hl_coin(),
+                        "interval": "1h",
+                        "startTime": end - CANDLE_INTERVAL_MS * count as i64,
+                        "endTime": end,
+                    },
+                }))
+                .send()
+                .await?;
+            parse_hyperliquid_candles(&response.text().await?)?
+        }
+        ExchangeId::Dydx => {
+            let response = client.get(endpoints::dydx().url(&format!("{}/{}", DYDX_CANDLES_PATH, symbol.to_dydx_ticker())))
+                .query(&[("resolution", "1HOUR".to_string()), ("limit", count.to_string())])
+                .send()
+                .await?;
+            parse_dydx_candles(&response.text().await?)?
+        }
+        ExchangeId::Custom(_) => return Err(AggregatorError::ExchangeError(format!("No candle source for {}", exchange))),
+    };
+
+    candles.sort_by_key(|c| c.open_time);
+    if candles.len() > count {
+        candles.drain(..candles.len() - count);
+    }
+    Ok(candles)
+}
+
+pub fn parse_hyperliquid_candles(json: &str) -> Result<Vec<Candle>> {
+    #[derive(Deserialize)]
+    struct Raw {
+        t: i64,
+        o: String,
+        h: String,
+        l: String,
+        c: String,
+    }
+
+    let raw: Vec<Raw> = serde_json::from_str(json)?;
+    raw.into_iter()
+        .map(|c| Ok(Candle {
+            open_time: c.t,
+            open: c.o.parse()?,
+            high: c.h.parse()?,
+            low: c.l.parse()?,
+            close: c.c.parse()?,
+        }))
+        .collect()
+}
+
+pub fn parse_dydx_candles(json: &str) -> Result<Vec<Candle>> {
+    #[derive(Deserialize)]
+    struct Response {
+        candles: Vec<Raw>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Raw {
+        started_at: String,
+        open: String,
+        high: String,
+        low: String,
+        close: String,
+    }
+
+    let response: Response = serde_json::from_str(json)?;
+    response.candles.into_iter()
+        .map(|c| Ok(Candle {
+            open_time: DateTime::parse_from_rfc3339(&c.started_at)?.timestamp_millis(),
+            open: c.open.parse()?,
+            high: c.high.parse()?,
+            low: c.low.parse()?,
+            close: c.close.parse()?,
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod atr_tests {
+    use crate::analytics::{
+        atr_from_candles, parse_atr_multiple, parse_dydx_candles, parse_hyperliquid_candles,
+        size_by_risk, true_range, Candle, StopSpec,
+    };
+
+    fn candle(high: f64, low: f64, close: f64) -> Candle {
+        Candle { open_time: 0, open: close, high, low, close }
+    }
+
+    #[test]
+    fn test_true_range_includes_gaps() {
+        let c = candle(105.0, 100.0, 102.0);
+        assert_eq!(true_range(&c, None), 5.0);
+        // Gap up from 90: high - prev close dominates
+        assert_eq!(true_range(&c, Some(90.0)), 15.0);
+        // Gap down from 110: prev close - low dominates
+        assert_eq!(true_range(&c, Some(110.0)), 10.0);
+    }
+
+    #[test]
+    fn test_atr_wilder_smoothing() {
+        let candles = vec![
+            candle(10.0, 8.0, 9.0),   // TR 2
+            candle(11.0, 9.0, 10.0),  // TR 2
+            candle(12.0, 10.0, 11.0), // TR 2
+            candle(17.0, 11.0, 16.0), // TR 6
+        ];
+        assert_eq!(atr_from_candles(&candles[..3], 3), Some(2.0));
+        // (2 * 2 + 6) / 3
+        let atr = atr_from_candles(&candles, 3).unwrap();
+        assert!((atr - 10.0 / 3.0).abs() < 1e-9);
+
+        assert_eq!(atr_from_candles(&candles, 5), None);
+        assert_eq!(atr_from_candles(&candles, 0), None);
+    }
+
+    #[test]
+    fn test_size_by_risk_loses_exactly_risk_at_stop() {
+        // $10k equity, 1% risk, long from 100 with a stop at 95
+        let sizing = size_by_risk(10_000.0, 1.0, 100.0, true, StopSpec::Price(95.0)).unwrap();
+        assert_eq!(sizing.risk_usd, 100.0);
+        assert_eq!(sizing.notional, 2_000.0);
+        let loss = sizing.notional / sizing.entry_price * sizing.stop_distance;
+        assert!((loss - sizing.risk_usd).abs() < 1e-9);
+
+        // Short with a 2 ATR stop: stop sits above entry
+        let sizing = size_by_risk(10_000.0, 2.0, 100.0, false, StopSpec::AtrMultiple { atr: 2.5, multiple: 2.0 }).unwrap();
+        assert_eq!(sizing.stop_price, 105.0);
+        assert_eq!(sizing.notional, 4_000.0);
+        assert!(sizing.explain().contains("$4000.00 notional"));
+    }
+
+    #[test]
+    fn test_size_by_risk_rejects_bad_input() {
+        // Stop on the wrong side of entry
+        assert!(size_by_risk(10_000.0, 1.0, 100.0, true, StopSpec::Price(105.0)).is_err());
+        assert!(size_by_risk(10_000.0, 1.0, 100.0, false, StopSpec::Price(95.0)).is_err());
+        assert!(size_by_risk(10_000.0, 1.0, 100.0, true, StopSpec::Price(100.0)).is_err());
+        // ATR stop below zero
+        assert!(size_by_risk(10_000.0, 1.0, 100.0, true, StopSpec::AtrMultiple { atr: 60.0, multiple: 2.0 }).is_err());
+        assert!(size_by_risk(0.0, 1.0, 100.0, true, StopSpec::Price(95.0)).is_err());
+        assert!(size_by_risk(10_000.0, 0.0, 100.0, true, StopSpec::Price(95.0)).is_err());
+        assert!(size_by_risk(10_000.0, 150.0, 100.0, true, StopSpec::Price(95.0)).is_err());
+    }
+
+    #[test]
+    fn test_parse_atr_multiple() {
+        assert_eq!(parse_atr_multiple("2atr"), Some(2.0));
+        assert_eq!(parse_atr_multiple(" 1.5 ATR "), Some(1.5));
+        assert_eq!(parse_atr_multiple("3x atr"), Some(3.0));
+        assert_eq!(parse_atr_multiple("95.5"), None);
+        assert_eq!(parse_atr_multiple("atr"), None);
+        assert_eq!(parse_atr_multiple("-1atr"), None);
+    }
+
+    #[test]
+    fn test_parse_candles() {
+        let hl = r#"[{"t":1700000000000,"T":1700003599999,"s":"BTC","i":"1h","o":"100.0","c":"101.5","h":"102.0","l":"99.0","v":"12.3","n":42}]"#;
+        let candles = parse_hyperliquid_candles(hl).unwrap();
+        assert_eq!(candles, vec![Candle { open_time: 1_700_000_000_000, open: 100.0, high: 102.0, low: 99.0, close: 101.5 }]);
+
+        let dydx = r#"{"candles":[{"startedAt":"2023-11-14T22:00:00.000Z","ticker":"BTC-USD","resolution":"1HOUR","low":"99","high":"102","open":"100","close":"101.5","baseTokenVolume":"1","usdVolume":"100","trades":3}]}"#;
+        let candles = parse_dydx_candles(dydx).unwrap();
+        assert_eq!(candles[0].open_time, 1_699_999_200_000);
+        assert_eq!(candles[0].close, 101.5);
+    }
+}
+
+#[cfg(test)]
+mod quick_size_tests {
+    use crate::aggregator::metadata::MarketSpec;
+    use crate::analytics::{quick_sizes, DEFAULT_QUICK_SIZE_PERCENTS};
+
+    fn spec(step: Option<f64>, size_decimals: Option<u32>) -> MarketSpec {
+        MarketSpec {
+            base: "BTC".to_string(),
+            max_leverage: 50.0,
+            size_decimals,
+            tick_size: Some(1.0),
+            step_size: step,
+            margin_tiers: Vec::new(),
+            initial_margin_fraction: None,
+            maintenance_margin_fraction: None,
+            min_notional: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_percent_of_collateral_at_leverage() {
+        let sizes = quick_sizes(1_000.0, 5.0, &spec(Some(0.0001), None), 50_000.0, 10.0, &DEFAULT_QUICK_SIZE_PERCENTS);
+        let usd: Vec<f64> = sizes.iter().map(|size| size.usd).collect();
+        assert_eq!(usd.len(), 4);
+        for (got, want) in usd.iter().zip([500.0, 1_250.0, 2_500.0, 5_000.0]) {
+            assert!((got - want).abs() < 1e-6, "{} vs {}", got, want);
+        }
+        assert!(sizes.iter().all(|size| size.unavailable.is_none()));
+        assert!((sizes[0].size - 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sizes_round_down_to_step() {
+        // $100 at 1x on a $30 market with 1 size decimal: 3.33 -> 3.3
+        let sizes = quick_sizes(100.0, 1.0, &spec(None, Some(1)), 30.0, 0.0, &[100.0]);
+        assert!((sizes[0].size - 3.3).abs() < 1e-9);
+        assert!((sizes[0].usd - 99.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_below_minimums_are_marked_with_reason() {
+        // 10% of $50 is $5, under Hyperliquid's $10 minimum
+        let sizes = quick_sizes(50.0, 1.0, &spec(Some(0.0001), None), 50_000.0, 10.0, &[10.0, 50.0]);
+        assert_eq!(sizes[0].unavailable.as_deref(), Some("below $10.00 minimum"));
+        assert!(sizes[0].describe().contains("below $10.00 minimum"));
+        assert!(sizes[1].unavailable.is_none());
+
+        // Less than one step rounds to zero
+        let sizes = quick_sizes(20.0, 1.0, &spec(Some(0.001), None), 50_000.0, 0.0, &[10.0]);
+        assert_eq!(sizes[0].size, 0.0);
+        assert_eq!(sizes[0].unavailable.as_deref(), Some("below minimum size 0.001"));
+    }
+
+    #[test]
+    fn test_no_sizes_without_collateral_or_price() {
+        assert!(quick_sizes(0.0, 1.0, &spec(Some(0.001), None), 100.0, 0.0, &[10.0]).is_empty());
+        assert!(quick_sizes(100.0, 1.0, &spec(Some(0.001), None), f64::NAN, 0.0, &[10.0]).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod liquidation_tests {
+    use crate::analytics::{liquidation_price, maintenance_margin_rate};
+
+    #[test]
+    fn test_liquidation_price_both_sides() {
+        let rate = maintenance_margin_rate(50.0);
+        assert!((rate - 0.01).abs() < 1e-12);
+        let long = liquidation_price(100.0, 10.0, true, rate).unwrap();
+        let short = liquidation_price(100.0, 10.0, false, rate).unwrap();
+        assert!((long - 91.0).abs() < 1e-9);
+        assert!((short - 109.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_no_liquidation_price_past_maintenance() {
+        assert_eq!(liquidation_price(100.0, 100.0, true, 0.01), None);
+        assert_eq!(liquidation_price(0.0, 10.0, true, 0.01), None);
+    }
+}
+
+#[cfg(test)]
+mod fair_price_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::analytics::{fair_price, fair_price_divergence_bps};
+    use crate::test_fixtures::book;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_asymmetric_book_pulls_fair_price_off_the_mid() {
+        // Mid is 100, but selling $1000 walks down to 90
+        let book = book(ExchangeId::Hyperliquid, &[(99.0, 1.0), (90.0, 100.0)], &[(101.0, 100.0)], 0);
+        let sell_avg = 1_000.0 / (1.0 + 901.0 / 90.0);
+        assert!(close(fair_price(&book, 1_000.0).unwrap(), (101.0 + sell_avg) / 2.0));
+        // Small enough to fill at the touch on both sides
+        assert!(close(fair_price(&book, 50.0).unwrap(), 100.0));
+    }
+
+    #[test]
+    fn test_thin_side_is_flagged_not_extrapolated() {
+        let thin = book(ExchangeId::Hyperliquid, &[(99.0, 1.0)], &[(101.0, 100.0)], 0);
+        assert_eq!(fair_price(&thin, 1_000.0), None);
+        assert_eq!(fair_price(&book(ExchangeId::Hyperliquid, &[], &[(101.0, 100.0)], 0), 10.0), None);
+    }
+
+    #[test]
+    fn test_divergence_ignores_flagged_venues() {
+        let fair = [
+            (ExchangeId::Hyperliquid, Some(100.0)),
+            (ExchangeId::Dydx, Some(101.0)),
+            (ExchangeId::Custom("thin".to_string()), None),
+        ];
+        assert!(close(fair_price_divergence_bps(&fair).unwrap(), 100.0));
+        assert_eq!(fair_price_divergence_bps(&fair[1..]), None);
+    }
+}
@@ -0,0 +1,500 @@
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::health::ClockSkewPolicy;
+use crate::aggregator::symbol::Symbol;
+use crate::aggregator::types::{PriceBucket, DEFAULT_BOOK_DEPTH};
+use crate::analytics::DEFAULT_QUICK_SIZE_PERCENTS;
+use crate::export::{ExportTarget, Framing};
+use crate::timefmt::{DisplayTimezone, TimeDisplay, DEFAULT_TIME_FORMAT};
+use crate::trading::automation::ma_cross::MaCrossConfig;
+use crate::trading::automation::quoter::QuoterConfig;
+use crate::trading::automation::RiskLimits;
+use crate::trading::confirmation::ConfirmationPolicy;
+use crate::trading::drawdown::{DrawdownAction, DrawdownBasis, DrawdownPolicy};
+use crate::trading::market_gate::MarketGatePolicy;
+use crate::trading::wallet::ARBITRUM_RPC;
+use crate::error::{AggregatorError, Result};
+use crate::ui::theme::Theme;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-venue preferences from the config file's [hyperliquid] and [dydx] tables
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VenueDefaults {
+    // Market shown at startup
+    pub symbol: Option<Symbol>,
+    // Leverage offered for new orders when no open position sets one
+    pub leverage: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AggregatorConfig {
+    pub testnet: bool,
+    // Venues to connect to at startup; the rest are never constructed
+    pub exchanges: Vec<ExchangeId>,
+    pub retry_attempts: u32,
+    pub timeout_ms: u64,
+    pub watchdog_timeout_ms: u64,
+    // Market metadata is refetched in the background once older than this...
+    pub metadata_refresh_secs: u64,
+    // ...and only ignored entirely once older than this
+    pub metadata_max_age_secs: u64,
+    pub reconcile_interval_secs: u64,
+    // Opt-in Hyperliquid cancel-on-disconnect; open orders are cancelled this
+    // long after the app stops refreshing the schedule
+    pub dead_mans_switch_secs: Option<u64>,
+    // Clear the schedule on a clean exit; off leaves it armed so resting
+    // orders are cancelled once the last refresh lapses
+    pub disarm_on_exit: bool,
+    pub discord_webhook_url: Option<String>,
+    // Discord role id pinged on every notification
+    pub discord_mention_role: Option<String>,
+    // Stored funding history older than this is pruned
+    pub funding_retention_days: u64,
+    // Observe only: no state writes, no trading, no locks taken
+    pub read_only: bool,
+    // Order notionals above each threshold need a dialog or the amount typed
+    // back; applies to every order the router sends
+    pub confirmation_policy: ConfirmationPolicy,
+    // USD value pre-filled when ordering from the DOM ladder
+    pub ladder_default_usd: f64,
+    // Levels within one bucket are merged in the cross-venue book and DOM
+    pub price_bucket: PriceBucket,
+    // Percent of free collateral behind the quick-size keys '1' to '4'
+    pub quick_size_percents: Vec<f64>,
+    // Gap between the mid samples behind the summary sparklines
+    pub price_history_interval_secs: u64,
+    // Color scheme: default, high-contrast or monochrome
+    pub theme: Theme,
+    // Trades older than this drop out of the volume profile and delta
+    pub trade_flow_window_secs: u64,
+    // Volume profile bucket width, in ticks
+    pub volume_profile_bucket_ticks: u32,
+    // Venues whose books are always polled over REST, for networks that
+    // block websocket upgrades
+    pub rest_fallback_venues: Vec<ExchangeId>,
+    // Failed websocket attempts before a venue falls back to REST polling
+    pub ws_failures_before_fallback: u32,
+    pub rest_poll_interval_ms: u64,
+    // Book levels per side while polling, to stay within rate limits
+    pub rest_poll_depth: usize,
+    // Levels per side served from each venue's book. dYdX keeps its full
+    // book and cuts it on read; Hyperliquid sends at most 20.
+    pub book_depth: usize,
+    // dYdX indexer REST base; the public indexer unless overridden
+    pub dydx_indexer: Option<String>,
+    // Its websocket; derived from the REST base when unset
+    pub dydx_indexer_ws: Option<String>,
+    // Standby indexer, e.g. a self-hosted node, used while the primary fails
+    pub dydx_backup_indexer: Option<String>,
+    // dYdX client config file; the embedded mainnet config when unset
+    pub dydx_node_config: Option<PathBuf>,
+    // JSON-RPC endpoint for Arbitrum deposits, bridging and balances
+    pub arbitrum_rpc: String,
+    pub venue_defaults: HashMap<ExchangeId, VenueDefaults>,
+    // Indexer responses slower than this count as failures
+    pub indexer_latency_threshold_ms: u64,
+    // How long the primary must stay healthy before traffic moves back
+    pub indexer_failback_secs: u64,
+    // Zone and strftime pattern for timestamps in the UI and logs; exports
+    // always use ISO-8601 with offset
+    pub timezone: DisplayTimezone,
+    pub time_format: String,
+    // Moving-average cross example strategy; absent unless configured
+    pub ma_cross: Option<MaCrossConfig>,
+    // Single-market quoter; absent unless configured
+    pub quoter: Option<QuoterConfig>,
+    // Strategies fill on paper unless this is turned off
+    pub strategy_dry_run: bool,
+    pub strategy_max_order_usd: f64,
+    pub strategy_max_position_usd: f64,
+    // Structured event export for external pipelines; off without a target
+    pub event_export: Option<ExportTarget>,
+    pub event_export_framing: Framing,
+    // Book updates are exported at most this often per market
+    pub event_export_book_ms: u64,
+    // Clock skew against a venue that warns, and that blocks long-term dYdX orders
+    pub clock_skew_warn_ms: i64,
+    pub clock_skew_block_ms: i64,
+    // Books older than this are left out when routing an order to the best venue
+    pub route_max_book_age_ms: u64,
+    // An identical order within this long of another is refused as a likely double submit
+    pub duplicate_window_ms: u64,
+    // USD each side of the book is walked for the depth-weighted fair price
+    pub fair_price_notional: f64,
+    // Estimated slippage past this many bps of the mid asks again before a
+    // market order goes out
+    pub max_slippage_bps: f64,
+    // Market orders are refused while the venue's book is staler than this,
+    // its spread is over this multiple of its recent average, or its mid is
+    // this many bps from another venue's
+    pub gate_max_book_age_ms: i64,
+    pub gate_spread_multiple: f64,
+    pub gate_max_divergence_bps: f64,
+    // Combined equity this many percent below its high triggers the
+    // drawdown guard; None leaves it off
+    pub max_drawdown_pct: Option<f64>,
+    // Notify-only level; half the trigger when unset
+    pub drawdown_warn_pct: Option<f64>,
+    pub drawdown_basis: DrawdownBasis,
+    // What the guard does on triggering besides notifying
+    pub drawdown_action: DrawdownAction,
+    pub drawdown_cooldown_secs: u64,
+}
+
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        Self {
+            testnet: false,
+            exchanges: ExchangeId::built_in().to_vec(),
+            retry_attempts: 3,
+            timeout_ms: 5000,
+            watchdog_timeout_ms: 60_000,
+            metadata_refresh_secs: 6 * 60 * 60,
+            metadata_max_age_secs: 7 * 24 * 60 * 60,
+            reconcile_interval_secs: 5 * 60,
+            dead_mans_switch_secs: None,
+            disarm_on_exit: true,
+            discord_webhook_url: None,
+            discord_mention_role: None,
+            funding_retention_days: 30,
+            read_only: false,
+            confirmation_policy: ConfirmationPolicy::default(),
+            ladder_default_usd: 100.0,
+            price_bucket: PriceBucket::default(),
+            quick_size_percents: DEFAULT_QUICK_SIZE_PERCENTS.to_vec(),
+            price_history_interval_secs: 5,
+            theme: Theme::default(),
+            trade_flow_window_secs: 15 * 60,
+            volume_profile_bucket_ticks: 10,
+            rest_fallback_venues: Vec::new(),
+            ws_failures_before_fallback: 3,
+            rest_poll_interval_ms: 2000,
+            rest_poll_depth: 10,
+            book_depth: DEFAULT_BOOK_DEPTH,
+            dydx_indexer: None,
+            dydx_indexer_ws: None,
+            dydx_backup_indexer: None,
+            dydx_node_config: None,
+            arbitrum_rpc: ARBITRUM_RPC.to_string(),
+            venue_defaults: HashMap::new(),
+            indexer_latency_threshold_ms: 2000,
+            indexer_failback_secs: 120,
+            timezone: DisplayTimezone::Local,
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+            ma_cross: None,
+            quoter: None,
+            strategy_dry_run: true,
+            strategy_max_order_usd: 500.0,
+            strategy_max_position_usd: 1000.0,
+            event_export: None,
+            event_export_framing: Framing::default(),
+            event_export_book_ms: 1000,
+            clock_skew_warn_ms: ClockSkewPolicy::default().warn_ms,
+            clock_skew_block_ms: ClockSkewPolicy::default().block_ms,
+            route_max_book_age_ms: 3000,
+            duplicate_window_ms: 5000,
+            fair_price_notional: 10_000.0,
+            max_slippage_bps: 50.0,
+            gate_max_book_age_ms: MarketGatePolicy::default().max_book_age_ms,
+            gate_spread_multiple: MarketGatePolicy::default().spread_multiple,
+            gate_max_divergence_bps: MarketGatePolicy::default().max_divergence_bps,
+            max_drawdown_pct: None,
+            drawdown_warn_pct: None,
+            drawdown_basis: DrawdownBasis::Session,
+            drawdown_action: DrawdownAction::Notify,
+            drawdown_cooldown_secs: 3600,
+        }
+    }
+}
+
+impl AggregatorConfig {
+    /// Defaults, overridden by the config file when there is one and then by
+    /// environment variables, so secrets like webhook URLs stay out of both.
+    /// A file that doesn't parse is an error rather than silently ignored.
+    pub fn load() -> Result<Self> {
+        let env = |key: &str| std::env::var(key).ok().filter(|value| !value.trim().is_empty());
+        Ok(Self::from_file(&Self::default_path())?.with_env(env))
+    }
+
+    /// ~/.config/trading_aggregator/config.toml, or the platform equivalent
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("trading_aggregator")
+            .join("config.toml")
+    }
+
+    /// Defaults with the file at `path` applied; just the defaults when
+    /// there's no file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let toml = match fs::read_to_string(path) {
+            Ok(toml) => toml,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(AggregatorError::Config(format!("Failed to read config {}: {}", path.display(), e))),
+        };
+        Self::parse(&toml).map_err(|e| AggregatorError::Config(format!("Invalid config {}: {}", path.display(), e)))
+    }
+
+    pub fn parse(toml: &str) -> Result<Self> {
+        let file: ConfigFile = toml::from_str(toml).map_err(|e| AggregatorError::Config(e.to_string()))?;
+        file.apply(Self::default())
+    }
+
+    /// Settings present in `env` replace the ones already loaded
+    pub fn with_env(self, env: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            discord_webhook_url: env("HL_DISCORD_WEBHOOK_URL").or(self.discord_webhook_url),
+            discord_mention_role: env("HL_DISCORD_MENTION_ROLE").or(self.discord_mention_role),
+            // Comma-separated built-in venues, e.g. "hl" to run without dYdX
+            exchanges: env("HL_EXCHANGES")
+                .map(|venues| venues.split(',')
+                    .filter_map(|venue| match venue.parse::<ExchangeId>() {
+                        Ok(exchange) if ExchangeId::built_in().contains(&exchange) => Some(exchange),
+                        _ => {
+                            tracing::warn!("Ignoring unknown venue '{}' in HL_EXCHANGES", venue.trim());
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>())
+                .filter(|venues| !venues.is_empty())
+                .unwrap_or(self.exchanges),
+            // Comma-separated, e.g. "dydx,hl"
+            rest_fallback_venues: env("HL_REST_FALLBACK")
+                .map(|venues| venues.split(',').filter_map(|venue| venue.parse().ok()).collect())
+                .unwrap_or(self.rest_fallback_venues),
+            dydx_indexer: env("HL_DYDX_INDEXER").or(self.dydx_indexer),
+            dydx_indexer_ws: env("HL_DYDX_INDEXER_WS").or(self.dydx_indexer_ws),
+            dydx_backup_indexer: env("HL_DYDX_BACKUP_INDEXER").or(self.dydx_backup_indexer),
+            dydx_node_config: env("HL_DYDX_NODE_CONFIG").map(PathBuf::from).or(self.dydx_node_config),
+            arbitrum_rpc: env("HL_ARBITRUM_RPC").unwrap_or(self.arbitrum_rpc),
+            book_depth: env("HL_BOOK_DEPTH")
+                .and_then(|depth| depth.parse().map_err(|e| tracing::warn!("Ignoring HL_BOOK_DEPTH: {}", e)).ok())
+                .unwrap_or(self.book_depth),
+            // Any value trades and streams on the venues' testnets
+            testnet: self.testnet || env("HL_TESTNET").is_some(),
+            indexer_latency_threshold_ms: env("HL_INDEXER_LATENCY_MS")
+                .and_then(|ms| ms.parse().map_err(|e| tracing::warn!("Ignoring HL_INDEXER_LATENCY_MS: {}", e)).ok())
+                .unwrap_or(self.indexer_latency_threshold_ms),
+            indexer_failback_secs: env("HL_INDEXER_FAILBACK_SECS")
+                .and_then(|secs| secs.parse().map_err(|e| tracing::warn!("Ignoring HL_INDEXER_FAILBACK_SECS: {}", e)).ok())
+                .unwrap_or(self.indexer_failback_secs),
+            price_history_interval_secs: env("HL_PRICE_HISTORY_SECS")
+                .and_then(|secs| secs.parse().map_err(|e| tracing::warn!("Ignoring HL_PRICE_HISTORY_SECS: {}", e)).ok())
+                .unwrap_or(self.price_history_interval_secs),
+            theme: env("HL_THEME")
+                .and_then(|theme| theme.parse().map_err(|e| tracing::warn!("Ignoring HL_THEME: {}", e)).ok())
+                .unwrap_or(self.theme),
+            // "5000:dialog,25000:typed", or "off"
+            confirmation_policy: env("HL_CONFIRMATION_POLICY")
+                .and_then(|policy| policy.parse().map_err(|e| tracing::warn!("Ignoring HL_CONFIRMATION_POLICY: {}", e)).ok())
+                .unwrap_or(self.confirmation_policy),
+            // A price width like "0.5", or ticks like "5t"
+            price_bucket: env("HL_PRICE_BUCKET")
+                .and_then(|bucket| bucket.parse().map_err(|e| tracing::warn!("Ignoring HL_PRICE_BUCKET: {}", e)).ok())
+                .unwrap_or(self.price_bucket),
+            // Up to four comma-separated percents, e.g. "5,10,20,50"
+            quick_size_percents: env("HL_QUICK_SIZES")
+                .map(|percents| percents.split(',')
+                    .filter_map(|percent| percent.trim().trim_end_matches('%').parse::<f64>().ok())
+                    .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+                    .take(4)
+                    .collect::<Vec<_>>())
+                .filter(|percents| !percents.is_empty())
+                .unwrap_or(self.quick_size_percents),
+            // "local", "utc" or an offset like "+02:00"
+            timezone: env("HL_TIMEZONE")
+                .and_then(|zone| zone.parse().map_err(|e| tracing::warn!("Ignoring HL_TIMEZONE: {}", e)).ok())
+                .unwrap_or(self.timezone),
+            time_format: env("HL_TIME_FORMAT").unwrap_or(self.time_format),
+            // "exchange,symbol,fast,slow,notional", e.g. "hyperliquid,BTC,10,30,100"
+            ma_cross: env("HL_MA_CROSS")
+                .and_then(|spec| spec.parse().map_err(|e| tracing::warn!("Ignoring HL_MA_CROSS: {}", e)).ok())
+                .or(self.ma_cross),
+            // "exchange,symbol,spread_bps,size_usd,tolerance_bps,max_inventory_usd"
+            quoter: env("HL_QUOTER")
+                .and_then(|spec| spec.parse().map_err(|e| tracing::warn!("Ignoring HL_QUOTER: {}", e)).ok())
+                .or(self.quoter),
+            // Any value sends strategy orders to the venues
+            strategy_dry_run: self.strategy_dry_run && env("HL_STRATEGY_LIVE").is_none(),
+            // A file path, "tcp://host:port" or "unix:///path/to.sock"
+            event_export: env("HL_EVENT_EXPORT")
+                .and_then(|target| target.parse().map_err(|e| tracing::warn!("Ignoring HL_EVENT_EXPORT: {}", e)).ok())
+                .or(self.event_export),
+            // "ndjson" or "length-prefixed"
+            event_export_framing: env("HL_EVENT_EXPORT_FRAMING")
+                .and_then(|framing| framing.parse().map_err(|e| tracing::warn!("Ignoring HL_EVENT_EXPORT_FRAMING: {}", e)).ok())
+                .unwrap_or(self.event_export_framing),
+            event_export_book_ms: env("HL_EVENT_EXPORT_BOOK_MS")
+                .and_then(|ms| ms.parse().map_err(|e| tracing::warn!("Ignoring HL_EVENT_EXPORT_BOOK_MS: {}", e)).ok())
+                .unwrap_or(self.event_export_book_ms),
+            clock_skew_warn_ms: env("HL_CLOCK_SKEW_WARN_MS")
+                .and_then(|ms| ms.parse().map_err(|e| tracing::warn!("Ignoring HL_CLOCK_SKEW_WARN_MS: {}", e)).ok())
+                .unwrap_or(self.clock_skew_warn_ms),
+            clock_skew_block_ms: env("HL_CLOCK_SKEW_BLOCK_MS")
+                .and_then(|ms| ms.parse().map_err(|e| tracing::warn!("Ignoring HL_CLOCK_SKEW_BLOCK_MS: {}", e)).ok())
+                .unwrap_or(self.clock_skew_block_ms),
+            route_max_book_age_ms: env("HL_ROUTE_MAX_BOOK_AGE_MS")
+                .and_then(|ms| ms.parse().map_err(|e| tracing::warn!("Ignoring HL_ROUTE_MAX_BOOK_AGE_MS: {}", e)).ok())
+                .unwrap_or(self.route_max_book_age_ms),
+            duplicate_window_ms: env("HL_DUPLICATE_WINDOW_MS")
+                .and_then(|ms| ms.parse().map_err(|e| tracing::warn!("Ignoring HL_DUPLICATE_WINDOW_MS: {}", e)).ok())
+                .unwrap_or(self.duplicate_window_ms),
+            fair_price_notional: env("HL_FAIR_PRICE_NOTIONAL")
+                .and_then(|usd| usd.parse().map_err(|e| tracing::warn!("Ignoring HL_FAIR_PRICE_NOTIONAL: {}", e)).ok())
+                .unwrap_or(self.fair_price_notional),
+            max_slippage_bps: env("HL_MAX_SLIPPAGE_BPS")
+                .and_then(|bps| bps.parse().map_err(|e| tracing::warn!("Ignoring HL_MAX_SLIPPAGE_BPS: {}", e)).ok())
+                .unwrap_or(self.max_slippage_bps),
+            gate_max_book_age_ms: env("HL_GATE_MAX_BOOK_AGE_MS")
+                .and_then(|ms| ms.parse().map_err(|e| tracing::warn!("Ignoring HL_GATE_MAX_BOOK_AGE_MS: {}", e)).ok())
+                .unwrap_or(self.gate_max_book_age_ms),
+            gate_spread_multiple: env("HL_GATE_SPREAD_MULTIPLE")
+                .and_then(|multiple| multiple.parse().map_err(|e| tracing::warn!("Ignoring HL_GATE_SPREAD_MULTIPLE: {}", e)).ok())
+                .unwrap_or(self.gate_spread_multiple),
+            gate_max_divergence_bps: env("HL_GATE_MAX_DIVERGENCE_BPS")
+                .and_then(|bps| bps.parse().map_err(|e| tracing::warn!("Ignoring HL_GATE_MAX_DIVERGENCE_BPS: {}", e)).ok())
+                .unwrap_or(self.gate_max_divergence_bps),
+            max_drawdown_pct: env("HL_MAX_DRAWDOWN_PCT")
+                .and_then(|pct| pct.trim_end_matches('%').parse().map_err(|e| tracing::warn!("Ignoring HL_MAX_DRAWDOWN_PCT: {}", e)).ok())
+                .or(self.max_drawdown_pct),
+            drawdown_warn_pct: env("HL_DRAWDOWN_WARN_PCT")
+                .and_then(|pct| pct.trim_end_matches('%').parse().map_err(|e| tracing::warn!("Ignoring HL_DRAWDOWN_WARN_PCT: {}", e)).ok())
+                .or(self.drawdown_warn_pct),
+            // "session" or "daily"
+            drawdown_basis: env("HL_DRAWDOWN_BASIS")
+                .and_then(|basis| basis.parse().map_err(|e| tracing::warn!("Ignoring HL_DRAWDOWN_BASIS: {}", e)).ok())
+                .unwrap_or(self.drawdown_basis),
+            // "notify", "cancel", "halve" or "flatten"
+            drawdown_action: env("HL_DRAWDOWN_ACTION")
+                .and_then(|action| action.parse().map_err(|e| tracing::warn!("Ignoring HL_DRAWDOWN_ACTION: {}", e)).ok())
+                .unwrap_or(self.drawdown_action),
+            drawdown_cooldown_secs: env("HL_DRAWDOWN_COOLDOWN_SECS")
+                .and_then(|secs| secs.parse().map_err(|e| tracing::warn!("Ignoring HL_DRAWDOWN_COOLDOWN_SECS: {}", e)).ok())
+                .unwrap_or(self.drawdown_cooldown_secs),
+            ..self
+        }
+    }
+
+    /// The first enabled venue's default market, else BTC
+    pub fn initial_symbol(&self) -> Symbol {
+        self.exchanges.iter()
+            .find_map(|exchange| self.venue_defaults.get(exchange).and_then(|defaults| defaults.symbol.clone()))
+            .unwrap_or_else(|| Symbol::perp("BTC"))
+    }
+
+    pub fn default_leverage(&self, exchange: &ExchangeId) -> Option<u32> {
+        self.venue_defaults.get(exchange).and_then(|defaults| defaults.leverage)
+    }
+
+    pub fn time_display(&self) -> TimeDisplay {
+        TimeDisplay { timezone: self.timezone, format: self.time_format.clone() }
+    }
+
+    pub fn clock_skew_policy(&self) -> ClockSkewPolicy {
+        ClockSkewPolicy { warn_ms: self.clock_skew_warn_ms, block_ms: self.clock_skew_block_ms }
+    }
+
+    /// None when no positive trigger is configured
+    pub fn drawdown_policy(&self) -> Option<DrawdownPolicy> {
+        let trigger_pct = self.max_drawdown_pct.filter(|pct| *pct > 0.0)?;
+        Some(DrawdownPolicy {
+            warn_pct: self.drawdown_warn_pct.unwrap_or(trigger_pct / 2.0).min(trigger_pct),
+            trigger_pct,
+            basis: self.drawdown_basis,
+            action: self.drawdown_action,
+            cooldown_ms: self.drawdown_cooldown_secs as i64 * 1000,
+        })
+    }
+
+    pub fn market_gate_policy(&self) -> MarketGatePolicy {
+        MarketGatePolicy {
+            spread_multiple: self.gate_spread_multiple,
+            max_book_age_ms: self.gate_max_book_age_ms,
+            max_divergence_bps: self.gate_max_divergence_bps,
+        }
+    }
+
+    pub fn strategy_limits(&self) -> RiskLimits {
+        RiskLimits { max_order_usd: self.strategy_max_order_usd, max_position_usd: self.strategy_max_position_usd }
+    }
+} 
+
+// The config file's layout. Unknown keys are refused so a typo surfaces as
+// an error naming the key instead of a setting that silently does nothing.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    testnet: Option<bool>,
+    read_only: Option<bool>,
+    retry_attempts: Option<u32>,
+    timeout_ms: Option<u64>,
+    watchdog_timeout_ms: Option<u64>,
+    reconcile_interval_secs: Option<u64>,
+    funding_retention_days: Option<u64>,
+    ladder_default_usd: Option<f64>,
+    time_format: Option<String>,
+    arbitrum_rpc: Option<String>,
+    book_depth: Option<usize>,
+    hyperliquid: Option<VenueFile>,
+    dydx: Option<VenueFile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VenueFile {
+    enabled: Option<bool>,
+    rest: Option<String>,
+    ws: Option<String>,
+    backup_rest: Option<String>,
+    default_symbol: Option<String>,
+    default_leverage: Option<u32>,
+}
+
+impl ConfigFile {
+    fn apply(self, config: AggregatorConfig) -> Result<AggregatorConfig> {
+        let mut config = AggregatorConfig {
+            testnet: self.testnet.unwrap_or(config.testnet),
+            read_only: self.read_only.unwrap_or(config.read_only),
+            retry_attempts: self.retry_attempts.unwrap_or(config.retry_attempts),
+            timeout_ms: self.timeout_ms.unwrap_or(config.timeout_ms),
+            watchdog_timeout_ms: self.watchdog_timeout_ms.unwrap_or(config.watchdog_timeout_ms),
+            reconcile_interval_secs: self.reconcile_interval_secs.unwrap_or(config.reconcile_interval_secs),
+            funding_retention_days: self.funding_retention_days.unwrap_or(config.funding_retention_days),
+            ladder_default_usd: self.ladder_default_usd.unwrap_or(config.ladder_default_usd),
+            time_format: self.time_format.unwrap_or(config.time_format),
+            arbitrum_rpc: self.arbitrum_rpc.unwrap_or(config.arbitrum_rpc),
+            book_depth: self.book_depth.unwrap_or(config.book_depth),
+            ..config
+        };
+        for (exchange, key, venue) in [(ExchangeId::Hyperliquid, "hyperliquid", self.hyperliquid), (ExchangeId::Dydx, "dydx", self.dydx)] {
+            let Some(venue) = venue else { continue };
+            if venue.enabled == Some(false) {
+                config.exchanges.retain(|enabled| enabled != &exchange);
+            }
+            if exchange == ExchangeId::Dydx {
+                config.dydx_indexer = venue.rest.or(config.dydx_indexer);
+                config.dydx_indexer_ws = venue.ws.or(config.dydx_indexer_ws);
+                config.dydx_backup_indexer = venue.backup_rest.or(config.dydx_backup_indexer);
+            } else if venue.rest.is_some() || venue.ws.is_some() || venue.backup_rest.is_some() {
+                // The SDK only knows its own mainnet and testnet URLs
+                return Err(AggregatorError::Config(format!("{}: endpoints can't be overridden; set testnet instead", key)));
+            }
+            let symbol = venue.default_symbol
+                .map(|symbol| Symbol::parse_user_input(&symbol).map_err(|e| AggregatorError::Config(format!("{}.default_symbol: {}", key, e))))
+                .transpose()?;
+            if venue.default_leverage == Some(0) {
+                return Err(AggregatorError::Config(format!("{}.default_leverage: must be at least 1", key)));
+            }
+            config.venue_defaults.insert(exchange, VenueDefaults { symbol, leverage: venue.default_leverage });
+        }
+        if config.exchanges.is_empty() {
+            return Err(AggregatorError::Config("every venue is disabled".to_string()));
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests;
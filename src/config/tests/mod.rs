@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod config_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::config::AggregatorConfig;
+    use std::collections::HashMap;
+
+    const FILE: &str = r#"
+testnet = true
+timeout_ms = 8000
+book_depth = 40
+arbitrum_rpc = "https://arb.example.org"
+
+[hyperliquid]
+default_symbol = "ETH"
+default_leverage = 5
+
+[dydx]
+rest = "https://indexer.example.org"
+ws = "wss://stream.example.org/v4/ws"
+default_leverage = 3
+"#;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |key: &str| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn a_missing_file_gives_the_defaults() {
+        let path = std::env::temp_dir().join(format!("missing_config_{}.toml", std::process::id()));
+        let config = AggregatorConfig::from_file(&path).unwrap();
+        assert_eq!(config.timeout_ms, AggregatorConfig::default().timeout_ms);
+        assert_eq!(config.exchanges, ExchangeId::built_in().to_vec());
+        assert_eq!(config.initial_symbol(), Symbol::perp("BTC"));
+    }
+
+    #[test]
+    fn the_file_overrides_defaults() {
+        let config = AggregatorConfig::parse(FILE).unwrap();
+        assert!(config.testnet);
+        assert_eq!(config.timeout_ms, 8000);
+        assert_eq!(config.retry_attempts, AggregatorConfig::default().retry_attempts);
+        assert_eq!(config.arbitrum_rpc, "https://arb.example.org");
+        assert_eq!(config.book_depth, 40);
+        assert_eq!(config.dydx_indexer.as_deref(), Some("https://indexer.example.org"));
+        assert_eq!(config.dydx_indexer_ws.as_deref(), Some("wss://stream.example.org/v4/ws"));
+        assert_eq!(config.initial_symbol(), Symbol::perp("ETH"));
+        assert_eq!(config.default_leverage(&ExchangeId::Hyperliquid), Some(5));
+        assert_eq!(config.default_leverage(&ExchangeId::Dydx), Some(3));
+    }
+
+    #[test]
+    fn environment_variables_override_the_file() {
+        let config = AggregatorConfig::parse(FILE).unwrap()
+            .with_env(env(&[("HL_ARBITRUM_RPC", "https://own-node:8547"), ("HL_DYDX_INDEXER", "https://other.example.org"), ("HL_BOOK_DEPTH", "50"), ("HL_MAX_SLIPPAGE_BPS", "25")]));
+        assert_eq!(config.arbitrum_rpc, "https://own-node:8547");
+        assert_eq!(config.book_depth, 50);
+        assert_eq!(config.max_slippage_bps, 25.0);
+        assert_eq!(config.dydx_indexer.as_deref(), Some("https://other.example.org"));
+        // Settings the environment leaves alone keep their file values
+        assert_eq!(config.timeout_ms, 8000);
+        assert!(config.testnet);
+        assert_eq!(config.default_leverage(&ExchangeId::Hyperliquid), Some(5));
+    }
+
+    #[test]
+    fn disabled_venues_are_not_connected() {
+        let config = AggregatorConfig::parse("[dydx]\nenabled = false\n").unwrap();
+        assert_eq!(config.exchanges, vec![ExchangeId::Hyperliquid]);
+
+        let err = AggregatorConfig::parse("[dydx]\nenabled = false\n[hyperliquid]\nenabled = false\n").unwrap_err();
+        assert!(err.to_string().contains("every venue is disabled"));
+    }
+
+    #[test]
+    fn a_malformed_file_names_the_bad_key() {
+        let unknown = AggregatorConfig::parse("timeout = 8000\n").unwrap_err();
+        assert!(format!("{:#}", unknown).contains("timeout"), "{:#}", unknown);
+
+        let mistyped = AggregatorConfig::parse("timeout_ms = \"fast\"\n").unwrap_err();
+        assert!(format!("{:#}", mistyped).contains("timeout_ms"), "{:#}", mistyped);
+
+        let symbol = AggregatorConfig::parse("[hyperliquid]\ndefault_symbol = \"\"\n").unwrap_err();
+        assert!(symbol.to_string().contains("hyperliquid.default_symbol"));
+
+        let endpoint = AggregatorConfig::parse("[hyperliquid]\nrest = \"https://api.example.org\"\n").unwrap_err();
+        assert!(endpoint.to_string().contains("hyperliquid"));
+    }
+
+    #[test]
+    fn from_file_reports_the_path_of_an_invalid_file() {
+        let path = std::env::temp_dir().join(format!("invalid_config_{}.toml", std::process::id()));
+        std::fs::write(&path, "testnet = maybe\n").unwrap();
+        let err = AggregatorConfig::from_file(&path).unwrap_err();
+        std::fs::remove_file(&path).ok();
+        assert!(err.to_string().contains(&path.display().to_string()));
+    }
+}
@@ -1,3 +1,12 @@
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::trading::confirmation::ConfirmationTier;
+use crate::trading::dydx_service::DydxServiceError;
+use crate::trading::file_lock::LockError;
+use crate::trading::validation::OrderValidationError;
+use ethers::contract::{AbiError, ContractError};
+use ethers::providers::{Middleware, ProviderError};
+use ethers::signers::WalletError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AggregatorError {
     #[error("Asset not found: {0}")]
@@ -14,4 +23,309 @@ pub enum AggregatorError {
     
     #[error("Websocket error: {0}")]
     WebsocketError(String),
+
+    #[error("Trading on {exchange} appears halted: {reason}")]
+    VenueHalted { exchange: ExchangeId, reason: String },
+
+    #[error(transparent)]
+    ConfirmationRequired(Box<ConfirmationRequired>),
+
+    #[error("Confirmation rejected: {0}")]
+    ConfirmationRejected(String),
+
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+
+    #[error(transparent)]
+    InvalidOrder(#[from] OrderValidationError),
+
+    #[error("{symbol} is delisted on {exchange}")]
+    MarketDelisted { exchange: ExchangeId, symbol: String },
+
+    #[error(transparent)]
+    ClockSkew(Box<ClockSkew>),
+
+    #[error(transparent)]
+    NoExecutionVenue(Box<NoExecutionVenue>),
+
+    #[error("Possible duplicate of order {client_id} submitted {age_ms}ms ago; resubmit with force to place it anyway")]
+    PossibleDuplicate { client_id: String, age_ms: i64 },
+
+    #[error(transparent)]
+    TriggerThroughMark(Box<TriggerThroughMark>),
+
+    #[error(transparent)]
+    StaleBook(Box<StaleBook>),
+
+    #[error(transparent)]
+    OneSidedBook(Box<OneSidedBook>),
+
+    #[error(transparent)]
+    SpreadBlowout(Box<SpreadBlowout>),
+
+    #[error(transparent)]
+    VenueMidsDiverge(Box<VenueMidsDiverge>),
+
+    #[error(transparent)]
+    SlippageExceeded(Box<SlippageExceeded>),
+
+    #[error("Wrong passphrase: the wallet file could not be decrypted")]
+    WrongPassphrase,
+
+    #[error("The wallet file is encrypted; a passphrase is needed to unlock it")]
+    WalletLocked,
+
+    #[error(transparent)]
+    MarginInUse(Box<MarginInUse>),
+
+    // Which wallet, e.g. "ETH" or "dYdX"
+    #[error("No {0} wallet configured")]
+    WalletNotConfigured(String),
+
+    #[error("Order rejected: {reason}")]
+    OrderRejected { reason: String },
+
+    #[error("Post-only order on {exchange} would have crossed the book: {reason}")]
+    PostOnlyWouldCross { exchange: ExchangeId, reason: String },
+
+    #[error("Insufficient balance: ${needed:.2} needed, ${available:.2} available")]
+    InsufficientBalance { needed: f64, available: f64 },
+
+    #[error("{what} timed out after {elapsed_ms}ms")]
+    Timeout { what: String, elapsed_ms: u64 },
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    // Boxed: the SDK error alone is over 150 bytes
+    #[error("Hyperliquid error: {0}")]
+    Hyperliquid(Box<hyperliquid_rust_sdk::Error>),
+
+    #[error("dYdX error: {0}")]
+    Dydx(#[from] DydxServiceError),
+
+    #[error("Invalid response: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Lock(#[from] LockError),
+
+    // A config file that can't be read or doesn't make sense
+    #[error("{0}")]
+    Config(String),
+
+    // User input that doesn't parse or is out of range
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    // Key storage, encryption and wallet bookkeeping
+    #[error("Wallet error: {0}")]
+    Wallet(String),
+
+    // Arbitrum RPC calls, contracts and transactions
+    #[error("Ethereum error: {0}")]
+    Ethereum(String),
+
+    #[error("Running read-only: {0}")]
+    ReadOnly(String),
+
+    // A number, time, address or URL that doesn't parse
+    #[error("Parse error: {0}")]
+    Parse(String),
+}
+
+pub type Result<T, E = AggregatorError> = std::result::Result<T, E>;
+
+// Payloads of the wider variants, boxed so every Result stays small
+
+#[derive(Debug, thiserror::Error)]
+#[error("Confirmation required ({tier}) for ${notional:.2}; resubmit with token {token}")]
+pub struct ConfirmationRequired {
+    pub tier: ConfirmationTier,
+    pub notional: f64,
+    pub token: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("System clock is {offset} off {exchange}'s (limit {limit_ms}ms), so long-term orders would expire at the wrong time; sync the clock (e.g. enable NTP) and retry")]
+pub struct ClockSkew {
+    pub exchange: ExchangeId,
+    pub offset: String,
+    pub limit_ms: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("No venue can fill ${usd_value:.2} of {symbol}: {reasons}")]
+pub struct NoExecutionVenue {
+    pub symbol: String,
+    pub usd_value: f64,
+    pub reasons: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Trigger {trigger} would fire at once against the {mark} mark: {reason}")]
+pub struct TriggerThroughMark {
+    pub trigger: f64,
+    pub mark: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{exchange} {symbol} book is {age_ms}ms old (limit {limit_ms}ms); the feed may be down")]
+pub struct StaleBook {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    pub age_ms: i64,
+    pub limit_ms: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{exchange} {symbol} book has no {missing}; a market order could fill far from fair value")]
+pub struct OneSidedBook {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    pub missing: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{exchange} {symbol} spread is {spread_bps:.1}bps, over {multiple}x its recent {average_bps:.1}bps average")]
+pub struct SpreadBlowout {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    pub spread_bps: f64,
+    pub average_bps: f64,
+    pub multiple: f64,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{exchange} {symbol} mid is {divergence_bps:.0}bps from {other}'s (limit {limit_bps:.0}bps); one book may be broken")]
+pub struct VenueMidsDiverge {
+    pub exchange: ExchangeId,
+    pub other: ExchangeId,
+    pub symbol: String,
+    pub divergence_bps: f64,
+    pub limit_bps: f64,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{exchange} {symbol} order would fill {estimated_bps:.1}bps past the touch, over its {limit_bps}bps slippage tolerance")]
+pub struct SlippageExceeded {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    pub estimated_bps: f64,
+    pub limit_bps: u32,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Cannot withdraw ${requested:.2} from {exchange}: only ${withdrawable:.2} is free while open positions use ${margin_used:.2} of margin")]
+pub struct MarginInUse {
+    pub exchange: ExchangeId,
+    pub requested: f64,
+    pub withdrawable: f64,
+    pub margin_used: f64,
+}
+
+// Lets `?` and `.into()` build a boxed variant straight from its payload
+macro_rules! from_payload {
+    ($($payload:ident),* $(,)?) => {
+        $(impl From<$payload> for AggregatorError {
+            fn from(payload: $payload) -> Self {
+                AggregatorError::$payload(Box::new(payload))
+            }
+        })*
+    };
+}
+
+from_payload! {
+    ConfirmationRequired,
+    ClockSkew,
+    NoExecutionVenue,
+    TriggerThroughMark,
+    StaleBook,
+    OneSidedBook,
+    SpreadBlowout,
+    VenueMidsDiverge,
+    SlippageExceeded,
+    MarginInUse,
+}
+
+impl From<hyperliquid_rust_sdk::Error> for AggregatorError {
+    fn from(error: hyperliquid_rust_sdk::Error) -> Self {
+        AggregatorError::Hyperliquid(Box::new(error))
+    }
+}
+
+// Foreign errors with nothing worth keeping but their message
+macro_rules! from_message {
+    ($($error:ty => $variant:ident),* $(,)?) => {
+        $(impl From<$error> for AggregatorError {
+            fn from(error: $error) -> Self {
+                AggregatorError::$variant(error.to_string())
+            }
+        })*
+    };
+}
+
+from_message! {
+    std::num::ParseFloatError => Parse,
+    std::num::ParseIntError => Parse,
+    chrono::ParseError => Parse,
+    url::ParseError => Parse,
+    hex::FromHexError => Parse,
+    bech32::Error => Parse,
+    ProviderError => Ethereum,
+    AbiError => Ethereum,
+    WalletError => Wallet,
+    tokio_tungstenite::tungstenite::Error => WebsocketError,
+}
+
+impl<M: Middleware> From<ContractError<M>> for AggregatorError {
+    fn from(error: ContractError<M>) -> Self {
+        AggregatorError::Ethereum(error.to_string())
+    }
+}
+
+impl AggregatorError {
+    /// Worth retrying as is: timeouts, dropped connections and 5xx answers.
+    /// An unknown symbol or a rejected order fails the same way again.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            AggregatorError::Timeout { .. } => true,
+            AggregatorError::Http(e) => is_transient_http(e),
+            AggregatorError::Hyperliquid(e) => matches!(**e, hyperliquid_rust_sdk::Error::ServerRequest { .. } | hyperliquid_rust_sdk::Error::GenericRequest(_)),
+            AggregatorError::Io(e) => is_transient_io(e),
+            AggregatorError::Dydx(DydxServiceError::IndexerError(e)) => {
+                let root: &(dyn std::error::Error + 'static) = e.as_ref();
+                std::iter::successors(Some(root), |cause| cause.source()).any(|cause| {
+                    cause.downcast_ref::<reqwest::Error>().is_some_and(is_transient_http)
+                        || cause.downcast_ref::<std::io::Error>().is_some_and(is_transient_io)
+                })
+            }
+            _ => false,
+        }
+    }
+}
+
+fn is_transient_http(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect() || error.status().is_some_and(|status| status.is_server_error())
+}
+
+// A dropped or stalled connection; a missing file or denied permission isn't
+fn is_transient_io(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        error.kind(),
+        ErrorKind::TimedOut
+            | ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::ConnectionRefused
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof
+    )
 } 
\ No newline at end of file
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::health::SharedHealth;
+
+pub mod schema;
+
+pub use schema::{Envelope, ExportEvent, SCHEMA_VERSION};
+
+// Account events get the deeper queue, so market data overflows first
+const MARKET_QUEUE: usize = 1024;
+const ACCOUNT_QUEUE: usize = 8192;
+// Wait between connection attempts while the target is unreachable
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// Key in the health registry
+const SINK_NAME: &str = "event-export";
+
+/// Where exported events go.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportTarget {
+    // Appended to, created if missing
+    File(PathBuf),
+    // "host:port"
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl FromStr for ExportTarget {
+    type Err = String;
+
+    /// "tcp://host:port", "unix:///path/to.sock", "file:///path" or a bare
+    /// file path.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(address) = s.strip_prefix("tcp://") {
+            if address.rsplit_once(':').is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err()) {
+                return Err(format!("Expected tcp://host:port, got '{}'", s));
+            }
+            Ok(Self::Tcp(address.to_string()))
+        } else if let Some(path) = s.strip_prefix("unix://") {
+            Ok(Self::Unix(PathBuf::from(path)))
+        } else if s.is_empty() {
+            Err("Empty export target".to_string())
+        } else {
+            Ok(Self::File(PathBuf::from(s.strip_prefix("file://").unwrap_or(s))))
+        }
+    }
+}
+
+impl std::fmt::Display for ExportTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Tcp(address) => write!(f, "tcp://{}", address),
+            Self::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+/// How records are delimited on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    // One JSON object per line
+    #[default]
+    Ndjson,
+    // Each JSON object preceded by its byte length, u32 big-endian
+    LengthPrefixed,
+}
+
+impl FromStr for Framing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            "length-prefixed" | "length_prefixed" | "lp" => Ok(Self::LengthPrefixed),
+            other => Err(format!("Unknown framing '{}'; expected ndjson or length-prefixed", other)),
+        }
+    }
+}
+
+/// One record framed for the wire.
+pub fn encode(envelope: &Envelope, framing: Framing) -> Vec<u8> {
+    // Plain data with string keys; serializing can't fail
+    let json = serde_json::to_vec(envelope).unwrap_or_default();
+    match framing {
+        Framing::Ndjson => {
+            let mut frame = json;
+            frame.push(b'\n');
+            frame
+        }
+        Framing::LengthPrefixed => {
+            let mut frame = (json.len() as u32).to_be_bytes().to_vec();
+            frame.extend(json);
+            frame
+        }
+    }
+}
+
+/// Counts since startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportStats {
+    pub queued: u64,
+    pub written: u64,
+    // Dropped because their queue was full
+    pub dropped_market: u64,
+    pub dropped_account: u64,
+    // Dequeued but lost to an unreachable target or a failed write
+    pub failed: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    queued: AtomicU64,
+    written: AtomicU64,
+    dropped_market: AtomicU64,
+    dropped_account: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Book updates let through at most once per interval per market.
+#[derive(Debug, Default)]
+struct BookThrottle {
+    interval_ms: i64,
+    last: HashMap<(ExchangeId, String), i64>,
+}
+
+impl BookThrottle {
+    fn allow(&mut self, exchange: &ExchangeId, symbol: &str, now_ms: i64) -> bool {
+        let key = (exchange.clone(), symbol.to_string());
+        if self.last.get(&key).is_some_and(|last| now_ms - last < self.interval_ms) {
+            return false;
+        }
+        self.last.insert(key, now_ms);
+        true
+    }
+}
+
+/// Queue ends the writer drains; account events first.
+pub struct ExportQueues {
+    market: mpsc::Receiver<Envelope>,
+    account: mpsc::Receiver<Envelope>,
+}
+
+impl ExportQueues {
+    /// Next record, account events ahead of market data. None once every
+    /// exporter handle is gone and both queues are drained.
+    pub async fn next(&mut self) -> Option<Envelope> {
+        tokio::select! {
+            biased;
+            Some(envelope) = self.account.recv() => Some(envelope),
+            Some(envelope) = self.market.recv() => Some(envelope),
+            else => None,
+        }
+    }
+}
+
+/// Handle the app and trading side emit normalized events through. Emitting
+/// never blocks: a full queue drops the event and counts it.
+#[derive(Clone)]
+pub struct EventExporter {
+    market: mpsc::Sender<Envelope>,
+    account: mpsc::Sender<Envelope>,
+    seq: Arc<AtomicU64>,
+    counters: Arc<Counters>,
+    books: Arc<Mutex<BookThrottle>>,
+}
+
+impl EventExporter {
+    /// Exporter with queues of the given depths and no writer attached
+    pub fn with_queues(market_depth: usize, account_depth: usize, book_interval: Duration) -> (Self, ExportQueues) {
+        let (market, market_rx) = mpsc::channel(market_depth.max(1));
+        let (account, account_rx) = mpsc::channel(account_depth.max(1));
+        let exporter = Self {
+            market,
+            account,
+            seq: Arc::new(AtomicU64::new(0)),
+            counters: Arc::new(Counters::default()),
+            books: Arc::new(Mutex::new(BookThrottle { interval_ms: book_interval.as_millis() as i64, ..BookThrottle::default() })),
+        };
+        (exporter, ExportQueues { market: market_rx, account: account_rx })
+    }
+
+    /// Exporter writing to `target` from a background task, reconnecting
+    /// while the target is unreachable. Delivery failures show in `health`.
+    pub fn spawn(target: ExportTarget, framing: Framing, book_interval: Duration, health: SharedHealth) -> Self {
+        let (exporter, queues) = Self::with_queues(MARKET_QUEUE, ACCOUNT_QUEUE, book_interval);
+        tokio::spawn(run_writer(target, framing, queues, exporter.counters.clone(), health));
+        exporter
+    }
+
+    pub fn emit(&self, event: ExportEvent) {
+        let now = Utc::now().timestamp_millis();
+        if let ExportEvent::Book { exchange, symbol, .. } = &event {
+            let allowed = self.books.lock().map_or(true, |mut books| books.allow(exchange, symbol, now));
+            if !allowed {
+                return;
+            }
+        }
+        let (queue, dropped) = if event.is_market_data() {
+            (&self.market, &self.counters.dropped_market)
+        } else {
+            (&self.account, &self.counters.dropped_account)
+        };
+        let envelope = Envelope {
+            schema_version: SCHEMA_VERSION,
+            seq: self.seq.fetch_add(1, Ordering::Relaxed),
+            time: now,
+            event,
+        };
+        match queue.try_send(envelope) {
+            Ok(()) => {
+                self.counters.queued.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Full(_)) | Err(TrySendError::Closed(_)) => {
+                dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> ExportStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ExportStats {
+            queued: load(&self.counters.queued),
+            written: load(&self.counters.written),
+            dropped_market: load(&self.counters.dropped_market),
+            dropped_account: load(&self.counters.dropped_account),
+            failed: load(&self.counters.failed),
+        }
+    }
+
+    /// Wait for the writer to take everything queued so far
+    pub async fn drain(&self) {
+        while self.market.capacity() < self.market.max_capacity() || self.account.capacity() < self.account.max_capacity() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
+
+type Writer = Box<dyn AsyncWrite + Send + Unpin>;
+
+async fn connect(target: &ExportTarget) -> std::io::Result<Writer> {
+    match target {
+        ExportTarget::File(path) => {
+            let file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+            Ok(Box::new(file))
+        }
+        ExportTarget::Tcp(address) => Ok(Box::new(tokio::net::TcpStream::connect(address).await?)),
+        #[cfg(unix)]
+        ExportTarget::Unix(path) => Ok(Box::new(tokio::net::UnixStream::connect(path).await?)),
+        #[cfg(not(unix))]
+        ExportTarget::Unix(_) => Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "unix sockets need a unix platform")),
+    }
+}
+
+// Records dequeued while the target is down are counted as failed rather
+// than held, so a dead collector can't back the queues up into the app
+async fn run_writer(target: ExportTarget, framing: Framing, mut queues: ExportQueues, counters: Arc<Counters>, health: SharedHealth) {
+    let mut writer: Option<Writer> = None;
+    let mut retry_at: Option<Instant> = None;
+
+    while let Some(envelope) = queues.next().await {
+        if writer.is_none() && retry_at.is_none_or(|at| Instant::now() >= at) {
+            match connect(&target).await {
+                Ok(connected) => {
+                    writer = Some(connected);
+                    retry_at = None;
+                }
+                Err(e) => {
+                    tracing::warn!("Event export to {} unavailable: {}", target, e);
+                    health.record_sink_failure(SINK_NAME, &e.to_string());
+                    retry_at = Some(Instant::now() + RECONNECT_DELAY);
+                }
+            }
+        }
+        let Some(sink) = writer.as_mut() else {
+            counters.failed.fetch_add(1, Ordering::Relaxed);
+            continue;
+        };
+
+        let frame = encode(&envelope, framing);
+        let written = async {
+            sink.write_all(&frame).await?;
+            sink.flush().await
+        }.await;
+        match written {
+            Ok(()) => {
+                counters.written.fetch_add(1, Ordering::Relaxed);
+                health.record_sink_success(SINK_NAME);
+            }
+            Err(e) => {
+                tracing::warn!("Event export to {} failed, reconnecting: {}", target, e);
+                health.record_sink_failure(SINK_NAME, &e.to_string());
+                counters.failed.fetch_add(1, Ordering::Relaxed);
+                writer = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
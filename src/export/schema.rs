@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::health::VenueStatus;
+use crate::aggregator::types::{Level, MarketSummary, OrderBook};
+pub use crate::aggregator::types::Side;
+use crate::trading::positions::episodes::Fill;
+
+/// Bumped on any change a consumer could trip over: a renamed, removed or
+/// retyped field, or a changed meaning. A new event type doesn't bump it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// One exported record. No wallet addresses or keys, only venue, market
+/// and order ids.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub schema_version: u32,
+    // One per event emitted this session; gaps are dropped events
+    pub seq: u64,
+    // Local millis when emitted
+    pub time: i64,
+    pub event: ExportEvent,
+}
+
+/// Where an order is in its life, as far as this app saw it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderState {
+    Placed,
+    Rejected,
+    Cancelled,
+    Filled,
+    // Gone from the venue without a fill we could see
+    Closed,
+    // Found on the venue but placed elsewhere
+    Imported,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    Operational,
+    Degraded,
+    Halted,
+}
+
+/// Every event type the export emits, tagged by `type`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportEvent {
+    Book {
+        exchange: ExchangeId,
+        symbol: String,
+        // Price bucket width the levels were summed into; 0 for raw levels
+        bucket: f64,
+        // [price, size], best first
+        bids: Vec<[f64; 2]>,
+        asks: Vec<[f64; 2]>,
+        // The book's own skew-corrected millis timestamp
+        book_time: u64,
+    },
+    Summary {
+        exchange: ExchangeId,
+        symbol: String,
+        price: f64,
+        volume_24h: f64,
+        open_interest: f64,
+        funding_rate: f64,
+    },
+    Fill {
+        exchange: ExchangeId,
+        asset: String,
+        side: Side,
+        price: f64,
+        size: f64,
+        fee: f64,
+        fill_time: i64,
+        order_id: String,
+    },
+    Order {
+        exchange: ExchangeId,
+        symbol: String,
+        // Empty when the venue rejected the order before assigning one
+        order_id: String,
+        state: OrderState,
+        reason: Option<String>,
+    },
+    Health {
+        exchange: ExchangeId,
+        status: HealthState,
+        reason: Option<String>,
+    },
+}
+
+impl ExportEvent {
+    /// The top `depth` levels per side of `book`, already bucketed at `bucket`
+    pub fn book(book: &OrderBook, bucket: f64, depth: usize) -> Self {
+        let levels = |levels: &[Level]| -> Vec<[f64; 2]> {
+            levels.iter().take(depth).map(|level| [level.price, level.size]).collect()
+        };
+        Self::Book {
+            exchange: book.exchange.clone(),
+            symbol: book.symbol.clone(),
+            bucket: bucket.max(0.0),
+            bids: levels(&book.bids),
+            asks: levels(&book.asks),
+            book_time: book.timestamp,
+        }
+    }
+
+    pub fn summary(exchange: &ExchangeId, summary: &MarketSummary) -> Self {
+        Self::Summary {
+            exchange: exchange.clone(),
+            symbol: summary.symbol.clone(),
+            price: summary.price,
+            volume_24h: summary.volume_24h,
+            open_interest: summary.open_interest,
+            funding_rate: summary.funding_rate,
+        }
+    }
+
+    pub fn fill(fill: &Fill) -> Self {
+        Self::Fill {
+            exchange: fill.exchange.clone(),
+            asset: fill.asset.clone(),
+            side: if fill.is_buy { Side::Buy } else { Side::Sell },
+            price: fill.price,
+            size: fill.size,
+            fee: fill.fee,
+            fill_time: fill.time,
+            order_id: fill.order_id.clone(),
+        }
+    }
+
+    pub fn health(exchange: &ExchangeId, status: &VenueStatus) -> Self {
+        let state = match status {
+            VenueStatus::Operational => HealthState::Operational,
+            VenueStatus::Degraded(_) => HealthState::Degraded,
+            VenueStatus::Halted(_) => HealthState::Halted,
+        };
+        Self::Health {
+            exchange: exchange.clone(),
+            status: state,
+            reason: status.reason().map(str::to_string),
+        }
+    }
+
+    /// Book and summary updates, which are dropped first under backpressure
+    /// since the next update supersedes them anyway
+    pub fn is_market_data(&self) -> bool {
+        matches!(self, Self::Book { .. } | Self::Summary { .. })
+    }
+}
+
+/// Check one emitted record, without framing, against this build's schema:
+/// the version must match and every field must be one the schema declares.
+pub fn validate(record: &str) -> Result<Envelope, String> {
+    let value: serde_json::Value = serde_json::from_str(record).map_err(|e| format!("not JSON: {}", e))?;
+    match value.get("schema_version").and_then(|version| version.as_u64()) {
+        Some(version) if version == SCHEMA_VERSION as u64 => {}
+        Some(version) => return Err(format!("schema version {}, expected {}", version, SCHEMA_VERSION)),
+        None => return Err("missing schema_version".to_string()),
+    }
+    let envelope: Envelope = serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+    // Serde ignores unknown fields, so compare against the re-serialized form
+    let declared = serde_json::to_value(&envelope).map_err(|e| e.to_string())?;
+    if declared != value {
+        return Err("fields outside the declared schema".to_string());
+    }
+    Ok(envelope)
+}
@@ -0,0 +1,249 @@
+#[cfg(test)]
+mod schema_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::health::VenueStatus;
+    use crate::aggregator::types::MarketSummary;
+    use crate::export::schema::{validate, OrderState, SCHEMA_VERSION};
+    use crate::export::{encode, Envelope, ExportEvent, Framing};
+    use crate::test_fixtures::book;
+    use crate::trading::positions::episodes::Fill;
+
+    // One of each event type, as the app would emit them
+    fn samples() -> Vec<ExportEvent> {
+        let summary = MarketSummary {
+            symbol: "BTC-USD".to_string(),
+            price: 100.25,
+            volume_24h: 1.5e9,
+            open_interest: 2.0e8,
+            funding_rate: 0.0000125,
+            timestamp: 1_700_000_000_000,
+        };
+        let fill = Fill {
+            exchange: ExchangeId::Dydx,
+            asset: "BTC-USD".to_string(),
+            is_buy: false,
+            price: 100.5,
+            size: 0.25,
+            fee: 0.0125,
+            time: 1_700_000_000_500,
+            order_id: "abc".to_string(),
+            fill_id: "f-1".to_string(),
+        };
+        let book = book(ExchangeId::Hyperliquid, &[(100.0, 1.0), (99.5, 2.0), (99.0, 3.0)], &[(100.5, 1.5), (101.0, 2.5)], 1_700_000_000_000);
+        vec![
+            ExportEvent::book(&book, 0.0, 2),
+            ExportEvent::summary(&ExchangeId::Dydx, &summary),
+            ExportEvent::fill(&fill),
+            ExportEvent::Order {
+                exchange: ExchangeId::Hyperliquid,
+                symbol: "ETH".to_string(),
+                order_id: String::new(),
+                state: OrderState::Rejected,
+                reason: Some("Insufficient margin".to_string()),
+            },
+            ExportEvent::health(&ExchangeId::Dydx, &VenueStatus::Halted("chain halted".to_string())),
+        ]
+    }
+
+    fn envelope(seq: u64, event: ExportEvent) -> Envelope {
+        Envelope { schema_version: SCHEMA_VERSION, seq, time: 1_700_000_001_000, event }
+    }
+
+    #[test]
+    fn test_samples_validate_against_schema_version() {
+        for (seq, event) in samples().into_iter().enumerate() {
+            let envelope = envelope(seq as u64, event);
+            let frame = encode(&envelope, Framing::Ndjson);
+            let line = std::str::from_utf8(&frame).unwrap();
+            assert!(line.ends_with('\n'));
+            assert_eq!(validate(line.trim_end()), Ok(envelope));
+        }
+    }
+
+    #[test]
+    fn test_stable_field_names() {
+        let json = serde_json::to_value(envelope(7, samples().remove(0))).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["seq"], 7);
+        assert_eq!(json["event"]["type"], "book");
+        assert_eq!(json["event"]["exchange"], "Hyperliquid");
+        // Depth-limited, [price, size] pairs
+        assert_eq!(json["event"]["bids"], serde_json::json!([[100.0, 1.0], [99.5, 2.0]]));
+
+        let health = serde_json::to_value(envelope(8, samples().remove(4))).unwrap();
+        assert_eq!(health["event"]["type"], "health");
+        assert_eq!(health["event"]["status"], "halted");
+        assert_eq!(health["event"]["reason"], "chain halted");
+    }
+
+    #[test]
+    fn test_validate_rejects_other_versions_and_unknown_fields() {
+        let mut json = serde_json::to_value(envelope(1, samples().remove(2))).unwrap();
+        json["schema_version"] = serde_json::json!(SCHEMA_VERSION + 1);
+        assert!(validate(&json.to_string()).unwrap_err().contains("schema version"));
+
+        json["schema_version"] = serde_json::json!(SCHEMA_VERSION);
+        json["event"]["wallet"] = serde_json::json!("0xabc");
+        assert!(validate(&json.to_string()).is_err());
+
+        assert!(validate("{\"seq\": 1}").is_err());
+        assert!(validate("not json").is_err());
+    }
+
+    #[test]
+    fn test_length_prefixed_frame() {
+        let envelope = envelope(3, samples().remove(3));
+        let frame = encode(&envelope, Framing::LengthPrefixed);
+        let length = u32::from_be_bytes(frame[..4].try_into().unwrap()) as usize;
+        assert_eq!(length, frame.len() - 4);
+        assert_eq!(validate(std::str::from_utf8(&frame[4..]).unwrap()), Ok(envelope));
+    }
+
+    #[test]
+    fn test_market_data_classification() {
+        let classes: Vec<bool> = samples().iter().map(ExportEvent::is_market_data).collect();
+        assert_eq!(classes, vec![true, true, false, false, false]);
+    }
+}
+
+#[cfg(test)]
+mod exporter_tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::health::{HealthRegistry, VenueStatus};
+    use crate::export::schema::validate;
+    use crate::export::{EventExporter, ExportEvent, ExportTarget, Framing};
+
+    fn summary_event() -> ExportEvent {
+        ExportEvent::Summary {
+            exchange: ExchangeId::Hyperliquid,
+            symbol: "BTC".to_string(),
+            price: 100.0,
+            volume_24h: 0.0,
+            open_interest: 0.0,
+            funding_rate: 0.0,
+        }
+    }
+
+    fn book_event(symbol: &str) -> ExportEvent {
+        ExportEvent::Book {
+            exchange: ExchangeId::Dydx,
+            symbol: symbol.to_string(),
+            bucket: 0.0,
+            bids: vec![[100.0, 1.0]],
+            asks: vec![[101.0, 1.0]],
+            book_time: 0,
+        }
+    }
+
+    fn health_event() -> ExportEvent {
+        ExportEvent::health(&ExchangeId::Dydx, &VenueStatus::Degraded("slow".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_backpressure_drops_market_data_first() {
+        let (exporter, mut queues) = EventExporter::with_queues(2, 4, Duration::ZERO);
+        for _ in 0..5 {
+            exporter.emit(summary_event());
+        }
+        for _ in 0..3 {
+            exporter.emit(health_event());
+        }
+
+        let stats = exporter.stats();
+        assert_eq!(stats.dropped_market, 3);
+        assert_eq!(stats.dropped_account, 0);
+        assert_eq!(stats.queued, 5);
+
+        // Account events leave the queue first; seq shows the dropped gap
+        let mut drained = Vec::new();
+        for _ in 0..5 {
+            let envelope = queues.next().await.unwrap();
+            drained.push((envelope.event.is_market_data(), envelope.seq));
+        }
+        assert_eq!(drained, vec![(false, 5), (false, 6), (false, 7), (true, 0), (true, 1)]);
+
+        for _ in 0..5 {
+            exporter.emit(health_event());
+        }
+        assert_eq!(exporter.stats().dropped_account, 1);
+    }
+
+    #[tokio::test]
+    async fn test_books_throttled_per_market() {
+        let (exporter, mut queues) = EventExporter::with_queues(16, 16, Duration::from_secs(60));
+        exporter.emit(book_event("BTC-USD"));
+        exporter.emit(book_event("BTC-USD"));
+        exporter.emit(book_event("ETH-USD"));
+        // Throttled books aren't drops; the next update supersedes them
+        assert_eq!(exporter.stats().queued, 2);
+        assert_eq!(exporter.stats().dropped_market, 0);
+
+        drop(exporter);
+        let mut symbols = Vec::new();
+        while let Some(envelope) = queues.next().await {
+            if let ExportEvent::Book { symbol, .. } = envelope.event {
+                symbols.push(symbol);
+            }
+        }
+        assert_eq!(symbols, vec!["BTC-USD", "ETH-USD"]);
+    }
+
+    #[tokio::test]
+    async fn test_file_target_writes_valid_records() {
+        let path = std::env::temp_dir().join(format!("hl-export-{}.ndjson", uuid::Uuid::new_v4()));
+        let health = Arc::new(HealthRegistry::default());
+        let exporter = EventExporter::spawn(ExportTarget::File(path.clone()), Framing::Ndjson, Duration::ZERO, health.clone());
+        exporter.emit(summary_event());
+        exporter.emit(health_event());
+
+        for _ in 0..100 {
+            if exporter.stats().written == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let contents = std::fs::read_to_string(&path).unwrap_or_default();
+        std::fs::remove_file(&path).ok();
+
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            assert!(validate(line).is_ok(), "{}", line);
+        }
+        assert!(!health.sink("event-export").is_failing());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_target_counts_failures() {
+        let path = std::env::temp_dir().join(format!("hl-export-missing-{}", uuid::Uuid::new_v4())).join("events.ndjson");
+        let health = Arc::new(HealthRegistry::default());
+        let exporter = EventExporter::spawn(ExportTarget::File(path), Framing::Ndjson, Duration::ZERO, health.clone());
+        exporter.emit(health_event());
+        exporter.emit(health_event());
+
+        for _ in 0..100 {
+            if exporter.stats().failed == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(exporter.stats().failed, 2);
+        assert!(health.sink("event-export").is_failing());
+    }
+
+    #[test]
+    fn test_target_and_framing_parse() {
+        assert_eq!("tcp://127.0.0.1:9000".parse::<ExportTarget>(), Ok(ExportTarget::Tcp("127.0.0.1:9000".to_string())));
+        assert_eq!("unix:///tmp/events.sock".parse::<ExportTarget>(), Ok(ExportTarget::Unix("/tmp/events.sock".into())));
+        assert_eq!("file:///var/log/events.ndjson".parse::<ExportTarget>(), Ok(ExportTarget::File("/var/log/events.ndjson".into())));
+        assert_eq!("events.ndjson".parse::<ExportTarget>(), Ok(ExportTarget::File("events.ndjson".into())));
+        assert!("tcp://localhost".parse::<ExportTarget>().is_err());
+        assert!(" ".parse::<ExportTarget>().is_err());
+
+        assert_eq!("NDJSON".parse::<Framing>(), Ok(Framing::Ndjson));
+        assert_eq!("length-prefixed".parse::<Framing>(), Ok(Framing::LengthPrefixed));
+        assert!("protobuf".parse::<Framing>().is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use ethers::abi::{encode, Token};
+use ethers::signers::LocalWallet;
+use ethers::types::{Address, Signature, H256, U256};
+use ethers::utils::keccak256;
+use hyperliquid_rust_sdk::ExchangeResponseStatus;
+use serde::Serialize;
+use crate::error::{AggregatorError, Result};
+
+pub const HL_EXCHANGE_URL: &str = "https://api.hyperliquid.xyz/exchange";
+pub const HL_TESTNET_EXCHANGE_URL: &str = "https://api.hyperliquid-testnet.xyz/exchange";
+
+// The EIP-712 domain every L1 action is signed under
+const L1_DOMAIN_NAME: &str = "Exchange";
+const L1_DOMAIN_VERSION: &str = "1";
+const L1_CHAIN_ID: u64 = 1337;
+
+pub fn exchange_url(testnet: bool) -> &'static str {
+    if testnet { HL_TESTNET_EXCHANGE_URL } else { HL_EXCHANGE_URL }
+}
+
+/// Cancel every open order at `time` (ms), or clear the schedule with None.
+/// Not in the SDK, so it's signed and sent here.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduleCancelAction {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<u64>,
+}
+
+impl ScheduleCancelAction {
+    pub fn at(time: Option<u64>) -> Self {
+        Self { kind: "scheduleCancel", time }
+    }
+}
+
+/// The connection id an L1 action is signed over: keccak of the msgpack
+/// action, the nonce, and a zero byte for "no vault"
+pub fn action_hash(action: &impl Serialize, nonce: u64) -> Result<H256> {
+    let mut bytes = rmp_serde::to_vec_named(action)
+        .map_err(|e| AggregatorError::ExchangeError(format!("Could not encode action: {}", e)))?;
+    bytes.extend(nonce.to_be_bytes());
+    bytes.push(0);
+    Ok(H256(keccak256(bytes)))
+}
+
+/// The EIP-712 digest of the `Agent { source, connectionId }` message, with
+/// source "a" on mainnet and "b" on testnet
+pub fn l1_digest(connection_id: H256, testnet: bool) -> H256 {
+    let domain_type = keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)");
+    let domain_separator = keccak256(encode(&[
+        Token::FixedBytes(domain_type.to_vec()),
+        Token::FixedBytes(keccak256(L1_DOMAIN_NAME).to_vec()),
+        Token::FixedBytes(keccak256(L1_DOMAIN_VERSION).to_vec()),
+        Token::Uint(U256::from(L1_CHAIN_ID)),
+        Token::Address(Address::zero()),
+    ]));
+    let agent_type = keccak256("Agent(string source,bytes32 connectionId)");
+    let source = if testnet { "b" } else { "a" };
+    let struct_hash = keccak256(encode(&[
+        Token::FixedBytes(agent_type.to_vec()),
+        Token::FixedBytes(keccak256(source).to_vec()),
+        Token::FixedBytes(connection_id.as_bytes().to_vec()),
+    ]));
+    let mut message = vec![0x19, 0x01];
+    message.extend(domain_separator);
+    message.extend(struct_hash);
+    H256(keccak256(message))
+}
+
+pub fn sign_l1_action(wallet: &LocalWallet, action: &impl Serialize, nonce: u64, testnet: bool) -> Result<Signature> {
+    let digest = l1_digest(action_hash(action, nonce)?, testnet);
+    wallet.sign_hash(digest)
+        .map_err(|e| AggregatorError::ExchangeError(format!("Could not sign action: {}", e)))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExchangePayload<'a, A: Serialize> {
+    action: &'a A,
+    nonce: u64,
+    signature: Signature,
+    vault_address: Option<Address>,
+}
+
+/// Sign `action` with the current time as nonce and post it to /exchange
+pub async fn send_l1_action(client: &reqwest::Client, wallet: &LocalWallet, action: &impl Serialize, testnet: bool) -> Result<ExchangeResponseStatus> {
+    let nonce = chrono::Utc::now().timestamp_millis() as u64;
+    let signature = sign_l1_action(wallet, action, nonce, testnet)?;
+    let payload = ExchangePayload { action, nonce, signature, vault_address: None };
+    Ok(client.post(exchange_url(testnet))
+        .json(&payload)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
@@ -0,0 +1,307 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+use crate::error::{AggregatorError, Result};
+
+pub const HL_INFO_URL: &str = "https://api.hyperliquid.xyz/info";
+pub const HL_TESTNET_INFO_URL: &str = "https://api.hyperliquid-testnet.xyz/info";
+
+pub fn info_url(testnet: bool) -> &'static str {
+    if testnet { HL_TESTNET_INFO_URL } else { HL_INFO_URL }
+}
+// Leverage caps and size decimals change rarely; listings are what move
+pub const DEFAULT_META_TTL: Duration = Duration::from_secs(300);
+// Furthest rounding may move an order's price, as a fraction of it
+pub const MAX_PRICE_ROUNDING: f64 = 0.01;
+
+/// One entry of the Hyperliquid `meta` universe.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetMeta {
+    pub name: String,
+    pub max_leverage: u32,
+    pub sz_decimals: u32,
+    #[serde(default)]
+    pub only_isolated: bool,
+    // Delisted assets stay in the universe with this set
+    #[serde(default)]
+    pub is_delisted: bool,
+    // Entry in `MetaResponse::margin_tables`, for tiered leverage
+    #[serde(default)]
+    pub margin_table_id: Option<u32>,
+}
+
+impl AssetMeta {
+    /// `size` rounded to the asset's size decimals
+    pub fn round_size(&self, size: f64) -> f64 {
+        let scale = 10_f64.powi(self.sz_decimals as i32);
+        (size * scale).round() / scale
+    }
+
+    /// `price` as the venue accepts it: five significant figures and at
+    /// most `6 - sz_decimals` decimals
+    pub fn round_price(&self, price: f64) -> f64 {
+        if price <= 0.0 {
+            return price;
+        }
+        let significant = 4 - price.log10().floor() as i32;
+        let decimals = significant.min(self.max_price_decimals() as i32).max(0);
+        let scale = 10_f64.powi(decimals);
+        (price * scale).round() / scale
+    }
+
+    pub fn max_price_decimals(&self) -> u32 {
+        6_u32.saturating_sub(self.sz_decimals)
+    }
+
+    /// `price` rounded for an order. Refused when it isn't positive or the
+    /// rounding would move it more than `MAX_PRICE_ROUNDING`, e.g. a price
+    /// with more decimals than a low-priced asset allows.
+    pub fn order_price(&self, price: f64) -> Result<f64, AggregatorError> {
+        if !(price > 0.0 && price.is_finite()) {
+            return Err(AggregatorError::InvalidAmount(format!("{} price must be positive, got {}", self.name, price)));
+        }
+        let rounded = self.round_price(price);
+        let moved = (rounded - price).abs() / price;
+        if moved > MAX_PRICE_ROUNDING {
+            return Err(AggregatorError::InvalidAmount(format!(
+                "{} price {} would round to {} ({:.1}% away); it takes five significant figures and at most {} decimals",
+                self.name, price, rounded, moved * 100.0, self.max_price_decimals()
+            )));
+        }
+        Ok(rounded)
+    }
+
+    /// `size` rounded for an order; refused when nothing is left of it
+    pub fn order_size(&self, size: f64) -> Result<f64, AggregatorError> {
+        let rounded = self.round_size(size);
+        if rounded <= 0.0 {
+            return Err(AggregatorError::InvalidAmount(format!(
+                "{} size {} rounds to zero at {} decimals", self.name, size, self.sz_decimals
+            )));
+        }
+        Ok(rounded)
+    }
+}
+
+/// One step of a margin table: positions of at least `lower_bound` USD
+/// notional are capped at `max_leverage`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginTableTier {
+    pub lower_bound: String,
+    pub max_leverage: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginTable {
+    #[serde(default)]
+    pub description: String,
+    pub margin_tiers: Vec<MarginTableTier>,
+}
+
+/// The `meta` response body.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaResponse {
+    pub universe: Vec<AssetMeta>,
+    // (id, table) pairs. Ids under 50 aren't listed: they stand for a single
+    // tier at that many times leverage.
+    #[serde(default)]
+    pub margin_tables: Vec<(u32, MarginTable)>,
+}
+
+/// The universe as fetched, indexed by asset name. Order matches the venue's,
+/// which asset contexts and order asset ids are positional against.
+#[derive(Debug, Clone, Default)]
+pub struct MetaUniverse {
+    assets: Vec<AssetMeta>,
+    index: HashMap<String, usize>,
+}
+
+impl MetaUniverse {
+    pub fn new(assets: Vec<AssetMeta>) -> Self {
+        let index = assets.iter().enumerate().map(|(i, asset)| (asset.name.clone(), i)).collect();
+        Self { assets, index }
+    }
+
+    pub fn parse(json: &str) -> Result<Self> {
+        let meta: MetaResponse = serde_json::from_str(json)?;
+        Ok(Self::new(meta.universe))
+    }
+
+    pub fn get(&self, asset: &str) -> Option<&AssetMeta> {
+        self.index.get(asset).map(|&i| &self.assets[i])
+    }
+
+    /// Position in the venue's universe
+    pub fn position(&self, asset: &str) -> Option<usize> {
+        self.index.get(asset).copied()
+    }
+
+    pub fn assets(&self) -> &[AssetMeta] {
+        &self.assets
+    }
+}
+
+/// One asset's live figures from `metaAndAssetCtxs`, as the venue sends
+/// them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetContext {
+    // In base units
+    pub open_interest: String,
+    pub mark_px: String,
+    // Hourly, as a fraction
+    pub funding: String,
+    // 24h notional volume
+    pub day_ntl_vlm: String,
+}
+
+/// The `metaAndAssetCtxs` response: the universe with each asset's live
+/// context, matched up by position.
+#[derive(Debug, Clone, Default)]
+pub struct AssetContexts {
+    universe: MetaUniverse,
+    contexts: Vec<AssetContext>,
+}
+
+impl AssetContexts {
+    pub fn parse(json: &str) -> Result<Self> {
+        let (meta, contexts): (MetaResponse, Vec<AssetContext>) = serde_json::from_str(json)?;
+        Ok(Self { universe: MetaUniverse::new(meta.universe), contexts })
+    }
+
+    pub fn universe(&self) -> &MetaUniverse {
+        &self.universe
+    }
+
+    pub fn get(&self, asset: &str) -> Option<(&AssetMeta, &AssetContext)> {
+        let position = self.universe.position(asset)?;
+        Some((&self.universe.assets()[position], self.contexts.get(position)?))
+    }
+}
+
+/// Where the universe comes from: the info endpoint in the app, a scripted
+/// source in tests.
+#[async_trait]
+pub trait MetaSource: Send + Sync {
+    async fn fetch(&self) -> Result<MetaUniverse>;
+}
+
+pub struct HttpMetaSource {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpMetaSource {
+    pub fn new(url: &str) -> Self {
+        Self { client: reqwest::Client::new(), url: url.to_string() }
+    }
+}
+
+#[async_trait]
+impl MetaSource for HttpMetaSource {
+    async fn fetch(&self) -> Result<MetaUniverse> {
+        let response = self.client.post(&self.url)
+            .json(&serde_json::json!({ "type": "meta" }))
+            .send()
+            .await?;
+        MetaUniverse::parse(&response.text().await?)
+    }
+}
+
+struct Cached {
+    universe: Arc<MetaUniverse>,
+    fetched: Instant,
+    invalidated: bool,
+}
+
+/// Read-through cache of the universe, shared via `Arc` by everything that
+/// needs asset metadata. Readers get the cached copy until it is older than
+/// the TTL or invalidated; then one of them refetches while the rest wait
+/// for that same fetch.
+pub struct MetaCache {
+    source: Box<dyn MetaSource>,
+    ttl: Duration,
+    cached: RwLock<Option<Cached>>,
+    // Held for the duration of a fetch so only one runs at a time
+    refresh: Mutex<()>,
+}
+
+impl MetaCache {
+    pub fn new(source: Box<dyn MetaSource>, ttl: Duration) -> Self {
+        Self { source, ttl, cached: RwLock::new(None), refresh: Mutex::new(()) }
+    }
+
+    /// Mainnet universe with the default TTL
+    pub fn hyperliquid() -> Arc<Self> {
+        Self::hyperliquid_on(false)
+    }
+
+    /// Mainnet or testnet universe with the default TTL
+    pub fn hyperliquid_on(testnet: bool) -> Arc<Self> {
+        Arc::new(Self::new(Box::new(HttpMetaSource::new(info_url(testnet))), DEFAULT_META_TTL))
+    }
+
+    /// The cached universe, whatever its age, without fetching
+    pub fn peek(&self) -> Option<Arc<MetaUniverse>> {
+        self.cached.read().ok()?.as_ref().map(|cached| cached.universe.clone())
+    }
+
+    fn fresh(&self) -> Option<Arc<MetaUniverse>> {
+        let cached = self.cached.read().ok()?;
+        cached.as_ref()
+            .filter(|cached| !cached.invalidated && cached.fetched.elapsed() < self.ttl)
+            .map(|cached| cached.universe.clone())
+    }
+
+    /// The universe, refetched first if stale. A failed refetch falls back
+    /// to the stale copy when there is one.
+    pub async fn universe(&self) -> Result<Arc<MetaUniverse>> {
+        if let Some(universe) = self.fresh() {
+            return Ok(universe);
+        }
+        let _refresh = self.refresh.lock().await;
+        // Someone else may have refetched while we waited
+        if let Some(universe) = self.fresh() {
+            return Ok(universe);
+        }
+        match self.source.fetch().await {
+            Ok(universe) => {
+                let universe = Arc::new(universe);
+                if let Ok(mut cached) = self.cached.write() {
+                    *cached = Some(Cached { universe: universe.clone(), fetched: Instant::now(), invalidated: false });
+                }
+                Ok(universe)
+            }
+            Err(e) => match self.peek() {
+                Some(stale) => {
+                    warn!("Hyperliquid meta refresh failed, using the cached copy: {}", e);
+                    Ok(stale)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Metadata for one asset, e.g. "BTC"
+    pub async fn get(&self, asset: &str) -> Result<Option<AssetMeta>> {
+        Ok(self.universe().await?.get(asset).cloned())
+    }
+
+    /// Force the next read to refetch, e.g. after the venue rejects an
+    /// asset the cache still lists. The stale copy stays as a fallback.
+    pub fn invalidate(&self) {
+        if let Ok(mut cached) = self.cached.write() {
+            if let Some(cached) = cached.as_mut() {
+                cached.invalidated = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod actions;
+pub mod meta;
+
+pub use meta::{AssetContext, AssetContexts, AssetMeta, MetaCache, MetaUniverse};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,234 @@
+#[cfg(test)]
+mod meta_cache_tests {
+    use crate::error::{AggregatorError, Result};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::hyperliquid::meta::MetaSource;
+    use crate::hyperliquid::{AssetContexts, AssetMeta, MetaCache, MetaUniverse};
+
+    const META: &str = r#"{"universe": [
+        {"name": "BTC", "szDecimals": 5, "maxLeverage": 40},
+        {"name": "ETH", "szDecimals": 4, "maxLeverage": 25},
+        {"name": "XYZ", "szDecimals": 0, "maxLeverage": 3, "onlyIsolated": true, "isDelisted": true}
+    ]}"#;
+
+    // Slow venue whose BTC leverage is the fetch count, so each copy is
+    // distinguishable; fails while `failing` is set
+    #[derive(Default)]
+    struct ScriptedSource {
+        fetches: Arc<AtomicU32>,
+        failing: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl MetaSource for ScriptedSource {
+        async fn fetch(&self) -> Result<MetaUniverse> {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let fetch = self.fetches.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(AggregatorError::ApiError("connection reset".to_string()));
+            }
+            Ok(MetaUniverse::new(vec![AssetMeta {
+                name: "BTC".to_string(),
+                max_leverage: fetch,
+                sz_decimals: 5,
+                only_isolated: false,
+                is_delisted: false,
+                margin_table_id: None,
+            }]))
+        }
+    }
+
+    fn cache(ttl: Duration) -> (Arc<MetaCache>, Arc<AtomicU32>, Arc<AtomicBool>) {
+        let source = ScriptedSource::default();
+        let (fetches, failing) = (source.fetches.clone(), source.failing.clone());
+        (Arc::new(MetaCache::new(Box::new(source), ttl)), fetches, failing)
+    }
+
+    async fn btc_leverage(cache: &MetaCache) -> u32 {
+        cache.get("BTC").await.unwrap().unwrap().max_leverage
+    }
+
+    #[test]
+    fn test_universe_indexed_by_asset() {
+        let universe = MetaUniverse::parse(META).unwrap();
+        assert_eq!(universe.assets().len(), 3);
+        assert_eq!(universe.position("ETH"), Some(1));
+        let btc = universe.get("BTC").unwrap();
+        assert_eq!((btc.max_leverage, btc.sz_decimals, btc.only_isolated, btc.is_delisted), (40, 5, false, false));
+        let xyz = universe.get("XYZ").unwrap();
+        assert!(xyz.only_isolated && xyz.is_delisted);
+        assert!(universe.get("DOGE").is_none());
+        assert_eq!(btc.round_size(0.123456), 0.12346);
+        assert_eq!(xyz.round_size(2.6), 3.0);
+    }
+
+    #[test]
+    fn test_price_rounded_to_significant_figures_and_decimals() {
+        let universe = MetaUniverse::parse(META).unwrap();
+        let btc = universe.get("BTC").unwrap();
+        assert_eq!(btc.round_price(65_432.17), 65_432.0);
+        // Six decimals would be allowed by size decimals, but only five
+        // significant figures are
+        let xyz = universe.get("XYZ").unwrap();
+        assert_eq!(xyz.round_price(1.234567), 1.2346);
+        // BTC's five size decimals leave one price decimal
+        assert_eq!(btc.round_price(12.3456), 12.3);
+        assert_eq!(btc.round_price(0.0), 0.0);
+    }
+
+    #[test]
+    fn test_asset_contexts_line_up_with_universe() {
+        let contexts = AssetContexts::parse(r#"[
+            {"universe": [{"name": "BTC", "szDecimals": 5, "maxLeverage": 40}, {"name": "ETH", "szDecimals": 4, "maxLeverage": 25}]},
+            [
+                {"openInterest": "12000.5", "markPx": "65000.0", "funding": "0.0000125", "dayNtlVlm": "1500000000.0", "oraclePx": "65010.0"},
+                {"openInterest": "300000.0", "markPx": "3200.5", "funding": "-0.00002", "dayNtlVlm": "800000000.0"}
+            ]
+        ]"#).unwrap();
+        let (meta, context) = contexts.get("ETH").unwrap();
+        assert_eq!(meta.max_leverage, 25);
+        assert_eq!((context.open_interest.as_str(), context.mark_px.as_str()), ("300000.0", "3200.5"));
+        assert_eq!(contexts.universe().assets().len(), 2);
+        assert!(contexts.get("DOGE").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_readers_share_one_fetch() {
+        let (cache, fetches, _) = cache(Duration::from_secs(60));
+        let readers: Vec<_> = (0..16)
+            .map(|_| {
+                let cache = cache.clone();
+                tokio::spawn(async move { btc_leverage(&cache).await })
+            })
+            .collect();
+        for reader in readers {
+            assert_eq!(reader.await.unwrap(), 1);
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        // Served from the cache from here on
+        assert_eq!(btc_leverage(&cache).await, 1);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_refetches_while_readers_see_the_old_copy() {
+        let (cache, fetches, _) = cache(Duration::from_secs(60));
+        assert_eq!(btc_leverage(&cache).await, 1);
+
+        cache.invalidate();
+        let refresh = {
+            let cache = cache.clone();
+            tokio::spawn(async move { btc_leverage(&cache).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        // Mid-refresh: the old copy is still there to peek at
+        assert_eq!(cache.peek().unwrap().get("BTC").unwrap().max_leverage, 1);
+        let waiting = {
+            let cache = cache.clone();
+            tokio::spawn(async move { btc_leverage(&cache).await })
+        };
+
+        assert_eq!(refresh.await.unwrap(), 2);
+        assert_eq!(waiting.await.unwrap(), 2);
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_expiry_and_failed_refresh() {
+        let (cache, fetches, failing) = cache(Duration::from_millis(100));
+        failing.store(true, Ordering::SeqCst);
+        // Nothing cached to fall back on
+        assert!(cache.universe().await.is_err());
+
+        failing.store(false, Ordering::SeqCst);
+        assert_eq!(btc_leverage(&cache).await, 2);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(btc_leverage(&cache).await, 3);
+
+        // Expired and the venue is down: the stale copy is served
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        failing.store(true, Ordering::SeqCst);
+        assert_eq!(btc_leverage(&cache).await, 3);
+        assert_eq!(fetches.load(Ordering::SeqCst), 4);
+    }
+}
+
+#[cfg(test)]
+mod order_rounding_tests {
+    use crate::error::AggregatorError;
+    use crate::hyperliquid::AssetMeta;
+
+    fn asset(name: &str, sz_decimals: u32) -> AssetMeta {
+        AssetMeta { name: name.to_string(), max_leverage: 10, sz_decimals, only_isolated: false, is_delisted: false, margin_table_id: None }
+    }
+
+    #[test]
+    fn test_prices_rounded_per_asset() {
+        // Five significant figures, and six decimals less the size decimals
+        assert_eq!(asset("BTC", 5).order_price(65_432.17).unwrap(), 65_432.0);
+        assert_eq!(asset("BTC", 5).order_price(123_456.7).unwrap(), 123_457.0);
+        assert_eq!(asset("ETH", 4).order_price(3_210.987).unwrap(), 3_211.0);
+        assert_eq!(asset("SOL", 2).order_price(145.12345).unwrap(), 145.12);
+        assert_eq!(asset("kPEPE", 0).order_price(0.0123456789).unwrap(), 0.012346);
+        assert_eq!(asset("SOL", 2).max_price_decimals(), 4);
+        assert_eq!(asset("WIDE", 7).max_price_decimals(), 0);
+    }
+
+    #[test]
+    fn test_rounding_far_from_the_price_is_refused() {
+        // One price decimal turns 0.123456 into 0.1
+        let low = asset("LOW", 5);
+        assert!(matches!(low.order_price(0.123456), Err(AggregatorError::InvalidAmount(_))));
+        assert!(low.order_price(12.345).is_ok());
+        assert!(matches!(low.order_price(0.0), Err(AggregatorError::InvalidAmount(_))));
+        assert!(matches!(low.order_price(f64::NAN), Err(AggregatorError::InvalidAmount(_))));
+    }
+
+    #[test]
+    fn test_sizes_rounded_to_size_decimals() {
+        assert_eq!(asset("ETH", 4).order_size(0.12344).unwrap(), 0.1234);
+        assert_eq!(asset("kPEPE", 0).order_size(1_234.4).unwrap(), 1_234.0);
+        assert!(matches!(asset("BTC", 5).order_size(0.000004), Err(AggregatorError::InvalidAmount(_))));
+        assert!(matches!(asset("kPEPE", 0).order_size(0.4), Err(AggregatorError::InvalidAmount(_))));
+    }
+}
+
+#[cfg(test)]
+mod action_signing_tests {
+    use ethers::signers::LocalWallet;
+    use ethers::types::H256;
+    use std::str::FromStr;
+    use crate::hyperliquid::actions::{l1_digest, ScheduleCancelAction};
+
+    // The SDK's own L1 signing vector
+    const KEY: &str = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e";
+    const CONNECTION_ID: &str = "0xde6c4037798a4434ca03cd05f00e3b803126221375cd1e7eaaaf041768be06eb";
+
+    #[test]
+    fn test_l1_signature_matches_sdk() {
+        let wallet: LocalWallet = KEY.parse().unwrap();
+        let connection_id = H256::from_str(CONNECTION_ID).unwrap();
+        assert_eq!(
+            wallet.sign_hash(l1_digest(connection_id, false)).unwrap().to_string(),
+            "fa8a41f6a3fa728206df80801a83bcbfbab08649cd34d9c0bfba7c7b2f99340f53a00226604567b98a1492803190d65a201d6805e5831b7044f17fd530aec7841c",
+        );
+        assert_eq!(
+            wallet.sign_hash(l1_digest(connection_id, true)).unwrap().to_string(),
+            "1713c0fc661b792a50e8ffdd59b637b1ed172d9a3aa4d801d9d88646710fb74b33959f4d075a7ccbec9f2374a6da21ffa4448d58d0413a0d335775f680a881431c",
+        );
+    }
+
+    #[test]
+    fn test_schedule_cancel_encoding() {
+        // Clearing leaves `time` out entirely, as the venue hashes it
+        let clear = rmp_serde::to_vec_named(&ScheduleCancelAction::at(None)).unwrap();
+        assert_eq!(clear, [&[0x81, 0xa4][..], b"type", &[0xae], b"scheduleCancel"].concat());
+
+        let at = rmp_serde::to_vec_named(&ScheduleCancelAction::at(Some(1_700_000_000_000))).unwrap();
+        assert_eq!(at[0], 0x82);
+        assert!(at.ends_with(&[&[0xa4][..], b"time", &[0xcf], &1_700_000_000_000u64.to_be_bytes()].concat()));
+    }
+}
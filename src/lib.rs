@@ -1,7 +1,26 @@
+//! Aggregated order books and trading across dYdX and Hyperliquid.
+//!
+//! Stable: [`prelude`] and the items it re-exports, from `aggregator`,
+//! `config`, `error` and `trading`. Everything else public, such as `ui`,
+//! `analytics`, `alerts` or the `trading` submodules not in the prelude,
+//! serves the bundled terminal app and may change in any release.
+
 pub mod aggregator;
+pub mod alerts;
+pub mod analytics;
 pub mod config;
 pub mod error;
+pub mod export;
+pub mod hyperliquid;
+pub mod prelude;
+pub mod risk;
+pub mod session;
+pub mod shutdown;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
+pub mod timefmt;
 pub mod trading;
+pub mod ui;
 
 pub use config::AggregatorConfig;
 pub use error::AggregatorError;
@@ -1,20 +1,72 @@
 use hl_aggregator::{
     aggregator::{
-        DerivativesAggregator, Exchange
+        DerivativesAggregator
     }, trading::wallet, AggregatorConfig
 };
 use hl_aggregator::aggregator::types::OrderBook;
+use hl_aggregator::aggregator::symbol::Symbol;
+use hl_aggregator::aggregator::exchange_id::ExchangeId;
+use hl_aggregator::aggregator::funding::{self, FundingStore};
+use hl_aggregator::aggregator::health::{ClockSkewPolicy, SkewLevel, Transport, VenueStatus};
+use hl_aggregator::aggregator::trade_flow::TradeFlowSnapshot;
+use hl_aggregator::aggregator::price_history::PRICE_HISTORY_SAMPLES;
 use anyhow::Result;
 use tokio::time::{sleep, Duration};
-use std::io::{self, Write, Stdout};
+use std::io::{self, Stdout};
 use hl_aggregator::trading::{OrderType, TradeRequest};
 use hl_aggregator::trading::hyperliquid_service::{HyperliquidService, OpenOrder};
 use hl_aggregator::trading::wallet::WalletManager;
+use hl_aggregator::trading::bridge::BridgePhase;
+use hl_aggregator::trading::dydx_config;
+use hl_aggregator::aggregator::retry::{self, RetryPolicy};
+use hl_aggregator::config::VenueDefaults;
+use hl_aggregator::trading::hl_account::{HL_MIN_DEPOSIT, HL_MIN_WITHDRAWAL};
+use hl_aggregator::trading::wallet_overview::WalletOverview;
+use hl_aggregator::trading::wallet_store::{self, KeyKind};
+use hl_aggregator::trading::journal::Journal;
+use hl_aggregator::trading::history;
+use hl_aggregator::trading::kill_switch::{self, KillScope};
+use hl_aggregator::trading::drawdown::{DrawdownGuard, GuardEvent};
+use hl_aggregator::trading::mirror::MirroredOrder;
+use hl_aggregator::trading::twap::{TwapEvent, TwapExecutor, TwapHandle, TwapOutcome, TwapProgress};
+use hl_aggregator::trading::router::TradingRouter;
+use hl_aggregator::trading::best_execution::BestExecution;
+use hl_aggregator::trading::confirmation::{Confirmation, ConfirmationTier, Quote};
+use hl_aggregator::trading::amount::{parse_usd_value, Amount, AmountContext};
+use hl_aggregator::trading::dead_mans_switch::DeadMansSwitch;
+use hl_aggregator::trading::automation::{ActionLog, BookTop, StrategyRunner};
+use hl_aggregator::trading::automation::ma_cross::MaCross;
+use hl_aggregator::trading::automation::quoter::SimpleQuoter;
+use hl_aggregator::alerts::{Alert, AlertEngine, AlertRule};
+use hl_aggregator::alerts::notify::{Notification, Notifier};
+use hl_aggregator::analytics::{self, QuickSize, RiskSizing, StopSpec};
+use hl_aggregator::aggregator::metadata::{min_order_notional, Delisting};
+use hl_aggregator::ui::restore_terminal;
+use hl_aggregator::ui::ladder::{self, LadderState};
+use hl_aggregator::ui::trade_flow::render_trade_flow;
+use hl_aggregator::ui::strategies::render_strategies;
+use hl_aggregator::ui::palette::{self, PaletteHistory, VenueQuote};
+use hl_aggregator::ui::input::{any_text, or_default, positive_integer, positive_number, required, yes_no, InputPrompt, MessagePanel, PromptEvent};
+use hl_aggregator::ui::asset_picker::AssetPicker;
+use hl_aggregator::ui::pnl::render_attribution;
+use hl_aggregator::trading::pnl::{self, GroupBy, PnlEntry, PnlRange};
+use hl_aggregator::ui::merged_book::render_merged_book;
+use hl_aggregator::ui::sparkline::render_price_sparkline;
+use hl_aggregator::ui::orderbook::{orderbook_lines, BOOK_DEPTH};
+use hl_aggregator::ui::table::Table;
+use hl_aggregator::ui::theme::Styles;
+use hl_aggregator::shutdown::{Shutdown, ShutdownReport, ShutdownStage};
+use hl_aggregator::export::{EventExporter, ExportEvent};
+use hl_aggregator::session::{SessionStats, SharedSessionStats};
+use hl_aggregator::timefmt;
+use hl_aggregator::ui::watchdog::{run_with_status, OperationCell, Watchdog};
 use hl_aggregator::aggregator::traits::ExchangeAggregator;
 use ratatui::{
     backend::CrosstermBackend,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Gauge, Paragraph},
     layout::{Layout, Constraint, Direction, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
     Terminal,
 };
 use crossterm::{
@@ -25,16 +77,16 @@ use crossterm::{
     cursor,
     terminal::{self, Clear, ClearType},
 };
-use hl_aggregator::aggregator::types::{MarketData, MarketSummary};
+use hl_aggregator::aggregator::types::{merge_bucketed, MarketData, MarketSummary, MergedBook, PriceBucket};
 use env_logger;
-use hl_aggregator::trading::positions::Position;
+use hl_aggregator::trading::positions::{self, LivePnl, Position, PositionFunding};
+use hl_aggregator::trading::portfolio::PortfolioSummary;
+use hl_aggregator::trading::positions::episodes::{self, ClosedSummary, PositionEpisode};
 use ethers::signers::Signer;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use dydx::indexer::types::{OrderSide, OrderType as DydxOrderType};
-use dydx::node::OrderTimeInForce;
-use hl_aggregator::trading::OrderType as LocalOrderType;
+use ethers::types::Address;
 use hyperliquid_rust_sdk::ExchangeResponseStatus;
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
 use chrono;
 use env_logger::{Builder, Target};
 use log::LevelFilter;
@@ -45,6 +97,14 @@ use bigdecimal::BigDecimal;
 use std::str::FromStr;
 use dydx::indexer::types::ApiOrderStatus;
 use hl_aggregator::trading::orders::Order;
+use hl_aggregator::error::AggregatorError;
+use hl_aggregator::trading::delisting::delisted_exposures;
+use hl_aggregator::trading::reconcile::{OrderOrigin, OrderState};
+use hl_aggregator::trading::strategy::{short_id, OrderRow};
+use hl_aggregator::trading::trailing::{TrailDistance, TrailingStop, TrailingStops};
+use hl_aggregator::trading::file_lock::AccessMode;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
 use dydx::indexer::OrderStatus;
 
 pub fn init_logging() {
@@ -85,6 +145,11 @@ pub enum MenuOption {
     PlaceTrade,
     ManageWallets,
     Exit,
+    ManageAlerts,
+    ClosedTrades,
+    Strategies,
+    Pnl,
+    TradeHistory,
 }
 
 impl MenuOption {
@@ -98,24 +163,106 @@ impl MenuOption {
             "6" => Some(Self::PlaceTrade),
             "7" => Some(Self::ManageWallets),
             "8" => Some(Self::Exit),
+            "9" => Some(Self::ManageAlerts),
+            "0" => Some(Self::ClosedTrades),
+            "s" => Some(Self::Strategies),
+            "p" => Some(Self::Pnl),
+            "h" => Some(Self::TradeHistory),
             _ => None,
         }
     }
 }
 
+// Per shutdown step, so one hung venue call can't keep the app from exiting
+const SHUTDOWN_STEP_TIMEOUT: Duration = Duration::from_secs(5);
+// Samples in the cumulative delta sparkline
+const CVD_POINTS: usize = 60;
+// Terminal orders shown on the "Recent Orders" tab
+const RECENT_ORDERS_LIMIT: usize = 50;
+// How long the trade log waits on a market order's fill before reporting it
+// as still open
+const FILL_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+// Balances behind the portfolio panel move slowly; spare the endpoints
+const PORTFOLIO_REFRESH: Duration = Duration::from_secs(30);
+// Levels per side in exported book updates
+const EXPORT_BOOK_DEPTH: usize = 20;
+
 struct App {
     aggregator: DerivativesAggregator,
-    hyperliquid_service: HyperliquidService,
-    wallet_manager: WalletManager,
-    selected_exchange: Option<String>,
-    symbol: String,
+    router: TradingRouter,
+    selected_exchange: Option<ExchangeId>,
+    symbol: Symbol,
     market_data: MarketData,
+    // The selected venue's streamed book; read only when it changed
+    orderbook_updates: Option<(ExchangeId, watch::Receiver<Option<OrderBook>>)>,
+    // Volume profile and delta for the selected venue's trades feed
+    trade_flow: Option<TradeFlowSnapshot>,
     dydx_summary: Option<MarketSummary>,
     hl_summary: Option<MarketSummary>,
-    dydx_leverage: Option<f64>,
-    hl_leverage: Option<f64>,
+    // Max leverage, with a note when it came from the metadata cache
+    dydx_leverage: Option<(f64, Option<String>)>,
+    hl_leverage: Option<(f64, Option<String>)>,
     terminal: Arc<Mutex<Terminal<CrosstermBackend<Stdout>>>>,
     positions: Vec<Position>,
+    operation: OperationCell,
+    alerts: AlertEngine,
+    notice: Option<String>,
+    reconcile_interval: Duration,
+    last_reconcile: Option<std::time::Instant>,
+    // Account health panel over both venues, refetched every PORTFOLIO_REFRESH
+    portfolio: PortfolioSummary,
+    last_portfolio: Option<std::time::Instant>,
+    dead_mans_switch: Option<DeadMansSwitch>,
+    disarm_on_exit: bool,
+    ladder_default_usd: f64,
+    quick_size_percents: Vec<f64>,
+    // Symbol and leverage preferences per venue from the config file
+    venue_defaults: HashMap<ExchangeId, VenueDefaults>,
+    price_bucket: PriceBucket,
+    // Both venues' books bucketed together, shown when no venue is selected
+    merged_book: Option<MergedBook>,
+    // Last status seen per venue, to notify on transitions
+    venue_status: HashMap<ExchangeId, VenueStatus>,
+    notifier: Arc<Notifier>,
+    trailing: TrailingStops,
+    strategies: StrategyRunner,
+    // Newest trade print handed to strategies, per market
+    strategy_trades_seen: HashMap<(ExchangeId, Symbol), i64>,
+    // Newest venue fill handed to strategies
+    strategy_fills_seen: i64,
+    styles: Styles,
+    // Markets the venues delisted, from the latest metadata refresh
+    delistings: Vec<(ExchangeId, Delisting)>,
+    // Delisted markets already warned about, so the warning fires once
+    delisting_warned: HashSet<(ExchangeId, String)>,
+    exporter: Option<EventExporter>,
+    // Newest venue fill recorded and exported
+    export_fills_seen: i64,
+    clock_policy: ClockSkewPolicy,
+    // Last skew level seen per venue, to warn on transitions
+    clock_skew_level: HashMap<ExchangeId, SkewLevel>,
+    route_max_book_age_ms: u64,
+    fair_price_notional: f64,
+    max_slippage_bps: f64,
+    // Attempts at each position close when the kill switch fires
+    retry_attempts: u32,
+    drawdown: Option<DrawdownGuard>,
+    // What the drawdown guard did when it last triggered; shown until it
+    // re-arms
+    drawdown_banner: Option<String>,
+    // Depth-weighted fair price per venue for the current symbol; None
+    // where the book can't fill the notional
+    fair_prices: HashMap<ExchangeId, Option<f64>>,
+    // Command palette lines and results, kept across openings
+    palette_history: PaletteHistory,
+    // Latest streamed mid and its time per (venue, base asset), to mark
+    // positions between fetches
+    marks: HashMap<(ExchangeId, String), (f64, u64)>,
+    positions_fetched_ms: u64,
+    session_stats: SharedSessionStats,
+    // Where the stats are saved for `stats session`; None when read-only
+    session_stats_path: Option<std::path::PathBuf>,
 }
 
 impl Drop for App {
@@ -134,11 +281,63 @@ impl Drop for App {
 }
 
 impl App {
-    async fn new() -> Result<Self> {
-        let config = AggregatorConfig::default();
-        let aggregator = DerivativesAggregator::new(config).await?;
-        let wallet_manager = WalletManager::new().await?;
-        let hyperliquid_service = HyperliquidService::new(&wallet_manager).await?;
+    async fn new(config: AggregatorConfig) -> Result<Self> {
+        let reconcile_interval = Duration::from_secs(config.reconcile_interval_secs);
+        let mode = if config.read_only { AccessMode::ReadOnly } else { AccessMode::ReadWrite };
+        // A read-only instance can't trade, so there's nothing for the switch to protect
+        let config_dms = config.dead_mans_switch_secs.filter(|_| !mode.is_read_only());
+        let aggregator = DerivativesAggregator::new(config.clone()).await?;
+        let notifier = Arc::new(Notifier::from_config(&config, aggregator.health.clone()));
+        let wallet_manager = WalletManager::with_mode(mode).await?
+            .with_arbitrum_rpc(config.arbitrum_rpc.clone())
+            .with_testnet(config.testnet);
+        let hyperliquid_service = HyperliquidService::new(&wallet_manager, config.testnet).await?
+            .with_meta(aggregator.hl_meta.clone());
+        // The switch gets its own client so refreshes never queue behind trading calls
+        let dead_mans_switch = match config_dms {
+            Some(secs) => Some(DeadMansSwitch::arm(
+                Arc::new(HyperliquidService::new(&wallet_manager, config.testnet).await?),
+                Duration::from_secs(secs),
+            )?),
+            None => None,
+        };
+        let journal = Journal::open_with_mode(Journal::default_path()?, mode)?;
+        let exporter = config.event_export.clone().map(|target| {
+            tracing::info!("Exporting events to {}", target);
+            EventExporter::spawn(
+                target,
+                config.event_export_framing,
+                Duration::from_millis(config.event_export_book_ms),
+                aggregator.health.clone(),
+            )
+        });
+        let session_stats = SessionStats::shared(chrono::Utc::now().timestamp_millis());
+        aggregator.health.observe_session(session_stats.clone());
+        let session_stats_path = if mode.is_read_only() { None } else { Some(SessionStats::default_path()?) };
+        let mut router = TradingRouter::new(hyperliquid_service, wallet_manager, journal)
+            .with_session_stats(session_stats.clone())
+            .with_health(aggregator.health.clone())
+            .with_confirmation_policy(config.confirmation_policy.clone())
+            .with_duplicate_window(Duration::from_millis(config.duplicate_window_ms))
+            .with_clock_skew_policy(config.clock_skew_policy())
+            .with_market_gate(aggregator.book_quality.clone(), config.market_gate_policy())
+            .with_metadata(aggregator.metadata.clone(), Duration::from_secs(config.metadata_max_age_secs));
+        if let Some(exporter) = &exporter {
+            router = router.with_exporter(exporter.clone());
+        }
+        let alerts = AlertEngine::load(AlertEngine::default_path()?)?;
+        let trailing = TrailingStops::load(TrailingStops::default_path()?)?;
+        // A read-only instance can't send orders, so its strategies only paper trade
+        let mut strategies = StrategyRunner::new(config.strategy_dry_run || mode.is_read_only(), config.strategy_limits());
+        if !mode.is_read_only() {
+            strategies = strategies.with_log(ActionLog::new(ActionLog::default_path()?));
+        }
+        if let Some(ma_cross) = config.ma_cross.clone() {
+            strategies.add(Box::new(MaCross::new(ma_cross)));
+        }
+        if let Some(quoter) = config.quoter.clone() {
+            strategies.add(Box::new(SimpleQuoter::new(quoter)));
+        }
         
         // Initialize terminal
         enable_raw_mode()?;
@@ -149,79 +348,608 @@ impl App {
         
         Ok(Self {
             aggregator,
-            hyperliquid_service,
-            wallet_manager,
+            router,
             selected_exchange: None,
-            symbol: "BTC".to_string(),
+            symbol: config.initial_symbol(),
             market_data: MarketData::default(),
+            orderbook_updates: None,
+            trade_flow: None,
             dydx_summary: None,
             hl_summary: None,
             dydx_leverage: None,
             hl_leverage: None,
             terminal: Arc::new(Mutex::new(terminal)),
             positions: Vec::new(),
+            operation: OperationCell::default(),
+            alerts,
+            notice: None,
+            reconcile_interval,
+            last_reconcile: None,
+            portfolio: PortfolioSummary::default(),
+            last_portfolio: None,
+            dead_mans_switch,
+            disarm_on_exit: config.disarm_on_exit,
+            ladder_default_usd: config.ladder_default_usd,
+            quick_size_percents: config.quick_size_percents.clone(),
+            venue_defaults: config.venue_defaults.clone(),
+            price_bucket: config.price_bucket,
+            merged_book: None,
+            venue_status: HashMap::new(),
+            notifier,
+            trailing,
+            strategies,
+            strategy_trades_seen: HashMap::new(),
+            strategy_fills_seen: chrono::Utc::now().timestamp_millis(),
+            styles: Styles::for_theme(config.theme),
+            delistings: Vec::new(),
+            delisting_warned: HashSet::new(),
+            exporter,
+            export_fills_seen: chrono::Utc::now().timestamp_millis(),
+            clock_policy: config.clock_skew_policy(),
+            clock_skew_level: HashMap::new(),
+            route_max_book_age_ms: config.route_max_book_age_ms,
+            fair_price_notional: config.fair_price_notional,
+            max_slippage_bps: config.max_slippage_bps,
+            retry_attempts: config.retry_attempts,
+            drawdown: config.drawdown_policy().map(DrawdownGuard::new),
+            drawdown_banner: None,
+            fair_prices: HashMap::new(),
+            palette_history: PaletteHistory::default(),
+            marks: HashMap::new(),
+            positions_fetched_ms: 0,
+            session_stats,
+            session_stats_path,
         })
     }
 
+    /// Ordered teardown for every exit path: stop background tasks, flush the
+    /// journal, disarm the dead man's switch, persist state, then hand the
+    /// terminal back.
+    async fn shutdown(&mut self) -> ShutdownReport {
+        // Strategies pull their resting quotes first, while the router is
+        // still ours to borrow
+        match tokio::time::timeout(SHUTDOWN_STEP_TIMEOUT, self.strategies.stop_all(&mut self.router)).await {
+            Ok(records) => tracing::info!("Shutdown: stopped strategies ({} actions)", records.len()),
+            Err(_) => tracing::error!("Shutdown: stopping strategies timed out"),
+        }
+        let read_only = self.router.wallet_manager.is_read_only();
+        // Left in place when disarming is off; its refresh task dies with the app
+        let switch = if self.disarm_on_exit { self.dead_mans_switch.take() } else { None };
+        let aggregator = &mut self.aggregator;
+        let journal = self.router.journal();
+        let alerts = &self.alerts;
+        let trailing = &self.trailing;
+        let exporter = self.exporter.clone();
+
+        let mut shutdown = Shutdown::new(SHUTDOWN_STEP_TIMEOUT)
+            .step(ShutdownStage::StopTasks, "market feeds", async move {
+                aggregator.shutdown().await;
+                Ok(())
+            })
+            .step(ShutdownStage::Flush, "journal", async move { journal.sync() });
+        if let Some(exporter) = exporter {
+            shutdown = shutdown.step(ShutdownStage::Flush, "event export", async move {
+                exporter.drain().await;
+                tracing::info!("Event export: {:?}", exporter.stats());
+                Ok(())
+            });
+        }
+        if let Some(switch) = switch {
+            shutdown = shutdown.step(ShutdownStage::DisarmSwitch, "dead man's switch", switch.disarm());
+        }
+        if !read_only {
+            shutdown = shutdown
+                .step(ShutdownStage::Persist, "alerts", async move { alerts.save() })
+                .step(ShutdownStage::Persist, "trailing stops", async move { trailing.save() });
+        }
+        let session_stats = &self.session_stats;
+        if let Some(path) = &self.session_stats_path {
+            shutdown = shutdown.step(ShutdownStage::Persist, "session stats", async move {
+                // Stats from a panicked writer are still worth keeping
+                session_stats.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .save(path, chrono::Utc::now().timestamp_millis())
+            });
+        }
+        shutdown
+            .step(ShutdownStage::RestoreTerminal, "terminal", async {
+                restore_terminal();
+                Ok(())
+            })
+            .run()
+            .await
+    }
+
     async fn update(&mut self) -> Result<()> {
         // Update all exchange data
         self.aggregator.start_all_market_updates(&self.symbol).await?;
         
         // Update summaries
         self.dydx_summary = self.aggregator
-            .get_exchange_summary("dYdX", &self.symbol)
+            .get_exchange_summary(&ExchangeId::Dydx, &self.symbol)
             .await
             .ok();
             
         self.hl_summary = self.aggregator
-            .get_exchange_summary("Hyperliquid", &self.symbol)
+            .get_exchange_summary(&ExchangeId::Hyperliquid, &self.symbol)
             .await
             .ok();
         
+        self.export_market_data().await;
+
+        // Fire any price alerts crossed since the last refresh
+        if let Some(price) = self.last_price() {
+            self.check_alerts(price);
+        }
+        self.check_fair_prices().await;
+
         // Update leverage info
-        self.dydx_leverage = match &self.aggregator.exchanges.get("dYdX") {
-            Some(Exchange::Dydx(e)) => {
-                e.get_leverage_info(&self.symbol).await.ok().map(|info| info.max_leverage)
-            },
-            _ => None
-        };
-            
-        self.hl_leverage = match &self.aggregator.exchanges.get("Hyperliquid") {
-            Some(Exchange::Hyperliquid(e)) => {
-                e.get_leverage_info(&self.symbol).await.ok().map(|info| info.max_leverage)
-            },
-            _ => None
-        };
+        self.dydx_leverage = self.aggregator.get_max_leverage(&ExchangeId::Dydx, &self.symbol).await;
+        self.hl_leverage = self.aggregator.get_max_leverage(&ExchangeId::Hyperliquid, &self.symbol).await;
         
         // Update selected exchange orderbook if one is selected
-        if let Some(exchange) = &self.selected_exchange {
-            if let Ok(orderbook) = self.aggregator.get_exchange_orderbook(exchange, &self.symbol).await {
+        if let Some(exchange) = self.selected_exchange.clone() {
+            if let Some(orderbook) = self.streamed_orderbook(&exchange) {
                 self.market_data.orderbook = Some(orderbook);
             }
+            self.trade_flow = self.aggregator.trade_flow(&exchange, &self.symbol, CVD_POINTS).await;
+        } else {
+            self.merged_book = self.merged_book().await;
         }
         
         // Update positions from both exchanges
-        let mut all_positions = Vec::new();
-        
-        // Get Hyperliquid positions
-        if let Ok(hl_positions) = self.hyperliquid_service.get_positions().await {
-            all_positions.extend(hl_positions);
-        }
-        
-        // Get dYdX positions
-        if let Ok(dydx_positions) = self.wallet_manager.get_dydx_positions().await {
-            all_positions.extend(dydx_positions);
-        }
+        let all_positions = self.router.refresh_positions().await.to_vec();
 
         self.market_data.positions = all_positions.clone();
         self.positions = all_positions;
+        self.positions_fetched_ms = chrono::Utc::now().timestamp_millis() as u64;
+        self.record_marks().await;
+
+        self.check_venue_status();
+        self.check_clock_skew();
+        self.check_delistings().await;
+        self.check_drawdown().await;
+
+        if !self.router.wallet_manager.is_read_only() {
+            self.check_trailing_stops().await;
+        }
+
+        if self.last_portfolio.is_none_or(|last| last.elapsed() >= PORTFOLIO_REFRESH) {
+            self.last_portfolio = Some(std::time::Instant::now());
+            self.portfolio.refresh(&self.router.wallet_manager, &self.router.hyperliquid_service, &self.aggregator).await;
+        }
+
+        // Periodically repair drift between our order view and the venues
+        let reconcile_due = self.last_reconcile.is_none_or(|last| last.elapsed() >= self.reconcile_interval);
+        if reconcile_due {
+            self.last_reconcile = Some(std::time::Instant::now());
+            let summary = self.router.reconcile_orders().await;
+            if !summary.is_clean() {
+                self.notify(summary.describe());
+            }
+            let order_filled = summary.corrections.iter()
+                .any(|(_, corrections)| corrections.closed.iter().any(|tracked| tracked.state == OrderState::Filled));
+            self.router.refresh_farms().await;
+            self.record_fills(order_filled).await;
+            self.save_session_stats();
+        }
+
+        // Fills are only polled on the reconcile cadence
+        self.run_strategies(reconcile_due).await;
         Ok(())
     }
+
+    /// Both venues' books merged at the configured bucket width. Tick-based
+    /// buckets use the coarser venue tick, so no bucket is finer than either
+    /// venue's grid.
+    async fn merged_book(&self) -> Option<MergedBook> {
+        let mut books = Vec::new();
+        let mut tick: Option<f64> = None;
+        for exchange in self.aggregator.exchange_ids() {
+            let Ok(book) = self.aggregator.get_exchange_orderbook(&exchange, &self.symbol).await else { continue };
+            let venue_tick = self.aggregator.tick_size(&exchange, &self.symbol).await.or_else(|| ladder::infer_tick(&book));
+            tick = match (tick, venue_tick) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+            books.push(book);
+        }
+        if books.is_empty() {
+            return None;
+        }
+        let width = self.price_bucket.width(tick).unwrap_or(0.0);
+        Some(merge_bucketed(&books, width))
+    }
+
+    /// Summaries and bucketed books for both venues to the event export; the
+    /// exporter throttles the books.
+    async fn export_market_data(&self) {
+        let Some(exporter) = &self.exporter else { return };
+        for (exchange, summary) in [(ExchangeId::Dydx, &self.dydx_summary), (ExchangeId::Hyperliquid, &self.hl_summary)] {
+            if let Some(summary) = summary {
+                exporter.emit(ExportEvent::summary(&exchange, summary));
+            }
+        }
+        for exchange in self.aggregator.exchange_ids() {
+            let Ok(book) = self.aggregator.get_exchange_orderbook(&exchange, &self.symbol).await else { continue };
+            let tick = self.aggregator.tick_size(&exchange, &self.symbol).await.or_else(|| ladder::infer_tick(&book));
+            let width = self.price_bucket.width(tick).unwrap_or(0.0);
+            exporter.emit(ExportEvent::book(&book.bucketed(width), width, EXPORT_BOOK_DEPTH));
+        }
+    }
+
+    /// New venue fills to the session stats and the event export. Only
+    /// fetched when export is on or the reconcile just saw an order fill.
+    async fn record_fills(&mut self, order_filled: bool) {
+        if self.exporter.is_none() && !order_filled {
+            return;
+        }
+        let mut fills: Vec<_> = self.router.fills().await.into_iter()
+            .filter(|fill| fill.time > self.export_fills_seen)
+            .collect();
+        fills.sort_by_key(|fill| fill.time);
+        if let Some(last) = fills.last() {
+            self.export_fills_seen = last.time;
+        }
+        if let Ok(mut stats) = self.session_stats.lock() {
+            for fill in &fills {
+                stats.record_fill(fill);
+            }
+        }
+        if let Some(exporter) = &self.exporter {
+            for fill in &fills {
+                exporter.emit(ExportEvent::fill(fill));
+            }
+        }
+    }
+
+    fn save_session_stats(&self) {
+        let Some(path) = &self.session_stats_path else { return };
+        let result = match self.session_stats.lock() {
+            Ok(stats) => stats.save(path, chrono::Utc::now().timestamp_millis()),
+            Err(_) => return,
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to save session stats: {}", e);
+        }
+    }
+
+    fn session_summary(&self) -> String {
+        self.session_stats.lock()
+            .map(|stats| stats.summary(chrono::Utc::now().timestamp_millis()))
+            .unwrap_or_default()
+    }
+
+    /// Hand running strategies the latest books and trades for their
+    /// markets, new fills when `with_fills`, and one timer tick.
+    async fn run_strategies(&mut self, with_fills: bool) {
+        let markets = self.strategies.markets();
+        if markets.is_empty() {
+            return;
+        }
+        // Resting strategy orders rely on the switch to be pulled if we die
+        let hold = self.dead_mans_switch.as_ref()
+            .filter(|switch| !switch.is_armed())
+            .map(|_| "dead man's switch not armed".to_string());
+        self.strategies.set_hold(hold);
+        let now = chrono::Utc::now().timestamp_millis();
+        for (exchange, symbol) in markets {
+            if let Ok(book) = self.aggregator.get_exchange_orderbook(&exchange, &symbol).await {
+                if let (Some(bid), Some(ask)) = (book.bids.first(), book.asks.first()) {
+                    let top = BookTop {
+                        exchange: exchange.clone(),
+                        symbol: symbol.clone(),
+                        best_bid: bid.price,
+                        best_ask: ask.price,
+                        time: book.timestamp as i64,
+                    };
+                    self.strategies.on_book(top, &mut self.router).await;
+                }
+            }
+
+            let key = (exchange.clone(), symbol.clone());
+            let seen = self.strategy_trades_seen.get(&key).copied().unwrap_or(now);
+            let prints = match self.aggregator.trade_flow.lock() {
+                Ok(flow) => flow.prints_since(&exchange, &symbol.to_string(), seen),
+                Err(_) => Vec::new(),
+            };
+            self.strategy_trades_seen.insert(key, prints.last().map_or(seen, |print| print.time));
+            for print in &prints {
+                self.strategies.on_trade(&exchange, &symbol, print, &mut self.router).await;
+            }
+        }
+
+        if with_fills && !self.strategies.is_dry_run() {
+            let mut fills: Vec<_> = self.router.fills().await.into_iter()
+                .filter(|fill| fill.time > self.strategy_fills_seen)
+                .collect();
+            fills.sort_by_key(|fill| fill.time);
+            if let Some(last) = fills.last() {
+                self.strategy_fills_seen = last.time;
+            }
+            for fill in &fills {
+                self.strategies.on_fill(fill, &mut self.router).await;
+            }
+        }
+
+        self.strategies.on_timer(now, &mut self.router).await;
+        for message in self.strategies.take_notifications() {
+            self.notify(message);
+        }
+    }
+
+    // The selected venue's book when it changed since the last read. A venue
+    // switch subscribes again and takes the current book.
+    fn streamed_orderbook(&mut self, exchange: &ExchangeId) -> Option<OrderBook> {
+        let symbol = self.symbol.to_string();
+        let fresh = !matches!(&self.orderbook_updates, Some((venue, _)) if venue == exchange);
+        if fresh {
+            let updates = self.aggregator.subscribe_orderbook(exchange, &self.symbol).ok()?;
+            self.orderbook_updates = Some((exchange.clone(), updates));
+        }
+        let (_, updates) = self.orderbook_updates.as_mut()?;
+        match updates.has_changed() {
+            Ok(changed) if changed || fresh => {}
+            Ok(_) => return None,
+            // The venue was re-registered; subscribe to its new feed next time
+            Err(_) => {
+                self.orderbook_updates = None;
+                return None;
+            }
+        }
+        let book = updates.borrow_and_update().clone();
+        book.filter(|book| book.symbol == symbol)
+    }
+
+    async fn record_marks(&mut self) {
+        for (exchange, venue) in self.aggregator.exchanges() {
+            let Some(book) = venue.cached_orderbook().await else { continue };
+            let Some(mid) = book.mid_price() else { continue };
+            let Ok(symbol) = Symbol::parse_user_input(&book.symbol) else { continue };
+            self.marks.insert((exchange.clone(), symbol.base().to_string()), (mid, book.timestamp));
+        }
+    }
+
+    /// Each position's PnL at the streamed mark when one arrived after the
+    /// last positions fetch, otherwise as the venue reported it
+    fn live_pnl(&self) -> Vec<LivePnl> {
+        self.market_data.positions.iter()
+            .map(|position| {
+                let mark = position.symbol().ok()
+                    .and_then(|symbol| self.marks.get(&(position.exchange.clone(), symbol.base().to_string())))
+                    .filter(|(_, time)| *time > self.positions_fetched_ms)
+                    .map(|(mark, _)| *mark);
+                positions::live_pnl(position, mark)
+            })
+            .collect()
+    }
+
+    fn check_venue_status(&mut self) {
+        for exchange in self.aggregator.exchange_ids() {
+            let status = self.aggregator.health.status(&exchange);
+            let previous = self.venue_status.insert(exchange.clone(), status.clone()).unwrap_or_default();
+            if previous != status {
+                if let Some(exporter) = &self.exporter {
+                    exporter.emit(ExportEvent::health(&exchange, &status));
+                }
+                self.notify(format!("{} is now {}", exchange, status));
+            }
+        }
+        for transition in self.aggregator.endpoints.take_transitions() {
+            self.notify(transition.to_string());
+        }
+    }
+
+    // Warn when the local clock drifts past a threshold against a venue's,
+    // and again once it's back in sync
+    fn check_clock_skew(&mut self) {
+        for exchange in self.aggregator.exchange_ids() {
+            let Some(skew) = self.aggregator.health.venue(&exchange).clock_skew else { continue };
+            let level = self.clock_policy.level(&skew);
+            let previous = self.clock_skew_level.insert(exchange.clone(), level).unwrap_or_default();
+            if level == previous {
+                continue;
+            }
+            let message = match level {
+                SkewLevel::Ok => format!("System clock is back in sync with {} ({})", exchange, skew.describe()),
+                SkewLevel::Warn => format!("System clock is {} off {}; check NTP", skew.describe(), exchange),
+                SkewLevel::Block if exchange == ExchangeId::Dydx => format!(
+                    "System clock is {} off dYdX; long-term dYdX orders are blocked until it is synced",
+                    skew.describe()
+                ),
+                SkewLevel::Block => format!("System clock is {} off {}; sync it before trading", skew.describe(), exchange),
+            };
+            self.notify(message);
+        }
+    }
+
+    // Warn once for each delisted market the account still has a position
+    // or open order in
+    async fn check_delistings(&mut self) {
+        self.delistings = self.aggregator.delistings().await;
+        let open_orders: Vec<Order> = self.router.orders().open_orders().map(|o| o.order.clone()).collect();
+        for exposure in delisted_exposures(&self.delistings, &self.positions, &open_orders) {
+            if self.delisting_warned.insert((exposure.exchange.clone(), exposure.delisting.base.clone())) {
+                self.notify(exposure.describe());
+            }
+        }
+    }
+
+    fn delisting(&self, exchange: &ExchangeId) -> Option<&Delisting> {
+        self.delistings.iter()
+            .find(|(venue, delisting)| venue == exchange && delisting.base == self.symbol.base())
+            .map(|(_, delisting)| delisting)
+    }
+
+    // Feed the latest combined equity to the drawdown guard and carry out
+    // whatever it escalates to
+    async fn check_drawdown(&mut self) {
+        let equity = self.session_stats.lock().ok().and_then(|stats| stats.last_equity);
+        let (Some(guard), Some(equity)) = (self.drawdown.as_mut(), equity) else { return };
+        let trigger_pct = guard.policy().trigger_pct;
+        match guard.observe(equity, chrono::Utc::now().timestamp_millis()) {
+            Some(GuardEvent::Warned(reading)) => self.notify(format!(
+                "Drawdown warning: {} (guard triggers at {:.2}%)",
+                reading.describe(), trigger_pct
+            )),
+            Some(GuardEvent::Recovered(reading)) => self.notify(format!("Drawdown recovered: {}", reading.describe())),
+            Some(GuardEvent::Rearmed) => {
+                self.drawdown_banner = None;
+                self.notify(format!("Drawdown guard re-armed at equity ${:.2}", equity));
+            }
+            Some(GuardEvent::Triggered(reading, action)) => {
+                self.notify(format!("DRAWDOWN GUARD TRIGGERED: {}; action: {}", reading.describe(), action.describe()));
+                let outcome = match self.router.run_drawdown_action(&reading, action, self.retry_attempts).await {
+                    None => action.describe().to_string(),
+                    Some(Ok(report)) if report.is_clean() => action.describe().to_string(),
+                    Some(Ok(report)) => format!("{}, with failures:\n{}", action.describe(), report.describe()),
+                    Some(Err(e)) => format!("could not act: {}", e),
+                };
+                if let Some(guard) = self.drawdown.as_mut() {
+                    guard.action_done(chrono::Utc::now().timestamp_millis());
+                }
+                let banner = format!("Triggered {}: {}; {}", timefmt::fmt_ts(reading.at_ms), reading.describe(), outcome);
+                self.notify(format!("Drawdown guard: {}", banner));
+                self.drawdown_banner = Some(banner);
+            }
+            None => {}
+        }
+    }
+
+    // Track each trailing stop's mark from the venue summary and close the
+    // position once price retraces past the trail
+    async fn check_trailing_stops(&mut self) {
+        for stop in self.trailing.stops().to_vec() {
+            let Ok(summary) = self.aggregator.get_exchange_summary(&stop.exchange, &stop.symbol).await else { continue };
+            let Some(triggered) = self.trailing.on_mark(&stop.exchange, &stop.symbol, summary.price) else { continue };
+
+            let size = self.positions.iter()
+                .find(|p| p.exchange == triggered.exchange && p.symbol().ok().as_ref() == Some(&triggered.symbol))
+                .map(|p| p.size);
+            let Some(size) = size else {
+                // Could be a failed positions refresh; retry on the next update
+                tracing::warn!("Trailing stop for {} {} triggered but no position found", triggered.exchange, triggered.symbol);
+                continue;
+            };
+
+            match self.router.close_position(&triggered.exchange, &triggered.symbol, size).await {
+                Ok(_) => {
+                    if let Err(e) = self.trailing.remove(&triggered.exchange, &triggered.symbol) {
+                        tracing::error!("Failed to remove trailing stop: {}", e);
+                    }
+                    self.notify(format!(
+                        "Trailing stop closed {} {} near ${:.2}",
+                        triggered.exchange, triggered.symbol, summary.price
+                    ));
+                }
+                Err(e) => self.notify(format!(
+                    "Trailing stop close failed for {} {}, retrying: {}",
+                    triggered.exchange, triggered.symbol, e
+                )),
+            }
+        }
+    }
+
+    fn last_price(&self) -> Option<f64> {
+        self.hl_summary.as_ref()
+            .or(self.dydx_summary.as_ref())
+            .map(|summary| summary.price)
+    }
+
+    // Returns the message for the last alert fired, if any
+    fn check_alerts(&mut self, price: f64) -> Option<String> {
+        let fired = self.alerts.evaluate(&self.symbol, price);
+        for alert in &fired {
+            self.notice = Some(format!("Alert: {}", alert.describe()));
+            tracing::info!("Alert fired: {}", alert.describe());
+            self.notifier.spawn_notify(Notification::alert(alert, price));
+        }
+        fired.last().map(|alert| format!("Alert: {}", alert.describe()))
+    }
+
+    /// Refresh each venue's fair price for the dashboard and fire any
+    /// divergence alerts on the current books
+    async fn check_fair_prices(&mut self) {
+        let mut books = Vec::new();
+        for exchange in self.aggregator.exchange_ids() {
+            if let Ok(book) = self.aggregator.get_exchange_orderbook(&exchange, &self.symbol).await {
+                books.push((exchange, book));
+            }
+        }
+        self.fair_prices = books.iter()
+            .map(|(exchange, book)| (exchange.clone(), analytics::fair_price(book, self.fair_price_notional)))
+            .collect();
+
+        let books: Vec<(ExchangeId, &OrderBook)> = books.iter().map(|(exchange, book)| (exchange.clone(), book)).collect();
+        let fired = self.alerts.evaluate_books(&self.symbol, &books);
+        for alert in &fired {
+            let divergence = match alert.rule {
+                AlertRule::FairPriceDivergence { notional, .. } => {
+                    let fair: Vec<(ExchangeId, Option<f64>)> = books.iter()
+                        .map(|(exchange, book)| (exchange.clone(), analytics::fair_price(book, notional)))
+                        .collect();
+                    analytics::fair_price_divergence_bps(&fair).unwrap_or_default()
+                }
+                AlertRule::PriceCross { .. } => 0.0,
+            };
+            self.notice = Some(format!("Alert: {} ({:.1} bps)", alert.describe(), divergence));
+            tracing::info!("Alert fired: {} ({:.1} bps)", alert.describe(), divergence);
+            self.notifier.spawn_notify(Notification::alert(alert, divergence));
+        }
+    }
+
+    // Fair price line for a venue's summary pane
+    fn fair_price_line(&self, exchange: &ExchangeId) -> String {
+        let label = format!("Fair ({}):", format_volume(self.fair_price_notional));
+        match self.fair_prices.get(exchange) {
+            Some(Some(price)) => format!("{} ${:.4}", label, price),
+            Some(None) => format!("{} book too thin", label),
+            None => format!("{} N/A", label),
+        }
+    }
+
+    // Configured leverage for new orders on `exchange`, else 1x
+    fn default_leverage(&self, exchange: &ExchangeId) -> u32 {
+        self.venue_defaults.get(exchange).and_then(|defaults| defaults.leverage).unwrap_or(1)
+    }
+
+    fn notify(&mut self, message: String) {
+        tracing::info!("{}", message);
+        self.notifier.spawn_notify(Notification::message(message.clone()));
+        self.notice = Some(message);
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     init_file_logging();
+
+    let mut config = AggregatorConfig::load()?;
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--read-only") {
+        args.remove(index);
+        config.read_only = true;
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--testnet") {
+        args.remove(index);
+        config.testnet = true;
+    }
+    // ASCII tables without ANSI styling, for piping subcommand output
+    let plain = match args.iter().position(|arg| arg == "--plain") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+    timefmt::init(config.time_display());
+    dydx_config::init(config.dydx_node_config.clone(), config.testnet);
+    retry::install(RetryPolicy::from_config(&config));
+
+    // Subcommands run without the TUI
+    if !args.is_empty() {
+        return run_command(&args, config, plain).await;
+    }
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -230,14 +958,27 @@ async fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // Create app state
-    let mut app = App::new().await?;
+    let watchdog_timeout = Duration::from_millis(config.watchdog_timeout_ms);
+    // Fails fast when another instance holds the wallet lock; leave the
+    // alternate screen so the error is readable
+    let mut app = match App::new(config).await {
+        Ok(app) => app,
+        Err(e) => {
+            restore_terminal();
+            return Err(e);
+        }
+    };
+    let operation = app.operation.clone();
+    let watchdog = Watchdog::spawn(watchdog_timeout, operation.clone(), restore_terminal);
     
     //std::env::set_var("RUST_LOG", "info");
     //env_logger::init();
     
     loop {
+        watchdog.heartbeat();
+
         // Update market data first
-        if let Err(e) = app.update().await {
+        if let Err(e) = run_with_status(&operation, "refreshing market data", app.update()).await {
             eprintln!("Error updating market data: {}", e);
         }
 
@@ -249,86 +990,103 @@ async fn main() -> Result<()> {
             if let Event::Key(key) = event::read()? {
                 match key.code {
                     KeyCode::Char('q') => break,
+                    KeyCode::Char(':') => command_palette(&mut app, &mut terminal).await?,
+                    KeyCode::Char('S') => view_session_stats(&mut app, &mut terminal)?,
+                    KeyCode::Char('K') => kill_switch(&mut app, &mut terminal).await?,
                     KeyCode::Char(c) => {
                         if let Some(option) = MenuOption::from_str(&c.to_string()) {
                             match option {
                                 MenuOption::ViewDydx => {
-                                    app.selected_exchange = Some("dYdX".to_string());
+                                    app.selected_exchange = Some(ExchangeId::Dydx);
                                     start_market_updates(&mut app.aggregator, &app.symbol).await?;
                                 },
                                 MenuOption::ViewHyperliquid => {
-                                    app.selected_exchange = Some("Hyperliquid".to_string());
+                                    app.selected_exchange = Some(ExchangeId::Hyperliquid);
                                     start_market_updates(&mut app.aggregator, &app.symbol).await?;
                                 },
                                 MenuOption::ViewPositions => {
+                                    let mut funding: Vec<PositionFunding> = Vec::new();
+                                    let mut selected = 0usize;
+                                    let mut status: Option<String> = None;
+                                    // The partial close percentage being typed
+                                    let mut percent_input: Option<String> = None;
                                     loop {
-                                        // Update positions before drawing
-                                        if let Err(e) = app.update().await {
-                                            eprintln!("Error updating positions: {}", e);
+                                        watchdog.heartbeat();
+                                        // Hold the list still while a percentage is typed
+                                        if percent_input.is_none() {
+                                            // Update positions before drawing
+                                            if let Err(e) = run_with_status(&operation, "refreshing positions", app.update()).await {
+                                                eprintln!("Error updating positions: {}", e);
+                                            }
+                                            // Books kept streaming while the rest of the refresh ran
+                                            app.record_marks().await;
+                                            // Funding settles hourly; refetch only when the positions change
+                                            if funding.len() != app.market_data.positions.len() {
+                                                funding = run_with_status(&operation, "fetching funding", load_position_funding(&app)).await;
+                                            }
                                         }
+                                        selected = selected.min(app.market_data.positions.len().saturating_sub(1));
 
+                                        let prompt = percent_input.as_ref().map(|input| match app.market_data.positions.get(selected) {
+                                            Some(position) => format!("Close what % of the {} {} position? {}_  (Enter to confirm, Esc to cancel)", position.asset, position.exchange, input),
+                                            None => String::new(),
+                                        });
                                         terminal.clear()?;
                                         terminal.draw(|f| {
-                                            Position::display_positions(f, &app.market_data.positions);
+                                            Position::display_positions(f, &app.market_data.positions, &app.live_pnl(), &funding, &app.trailing, selected, prompt.as_deref().or(status.as_deref()), &app.styles);
                                         })?;
 
                                         // Check for input with a timeout
                                         if event::poll(Duration::from_millis(500))? {
                                             if let Event::Key(key) = event::read()? {
+                                                if let Some(input) = percent_input.as_mut() {
+                                                    match key.code {
+                                                        KeyCode::Esc => percent_input = None,
+                                                        KeyCode::Backspace => { input.pop(); }
+                                                        KeyCode::Char(c) if c.is_ascii_digit() || c == '.' => input.push(c),
+                                                        KeyCode::Enter => {
+                                                            let input = percent_input.take().unwrap_or_default();
+                                                            if let Some(position) = app.market_data.positions.get(selected).cloned() {
+                                                                status = Some(match input.trim().parse::<f64>() {
+                                                                    Ok(percent) => match partial_close(&mut app, &mut terminal, &position, percent).await? {
+                                                                        Some(message) => message,
+                                                                        None => "Close cancelled".to_string(),
+                                                                    },
+                                                                    Err(_) => format!("Invalid percentage: {}", input),
+                                                                });
+                                                            }
+                                                        }
+                                                        _ => {}
+                                                    }
+                                                    continue;
+                                                }
                                                 match key.code {
                                                     KeyCode::Char('q') | KeyCode::Esc => {
                                                         break;
                                                     }
-                                                    KeyCode::Char(c) => {
-                                                        if let Ok(index) = c.to_string().parse::<usize>() {
-                                                            if index > 0 && index <= app.market_data.positions.len() {
-                                                                let position = &app.market_data.positions[index - 1];
-                                                                
-                                                                // Show confirmation prompt
-                                                                terminal.clear()?;
-                                                                terminal.draw(|f| {
-                                                                    let confirm_text = format!(
-                                                                        "Are you sure you want to close {} {} position?\nSize: {}\nPnL: ${:.2}\n\nPress 'y' to confirm, any other key to cancel",
-                                                                        position.side,
-                                                                        position.asset,
-                                                                        position.size,
-                                                                        position.unrealized_pnl
-                                                                    );
-                                                                    let confirm = Paragraph::new(confirm_text)
-                                                                        .block(Block::default().borders(Borders::ALL).title("Confirm Close Position"));
-                                                                    f.render_widget(confirm, f.area());
-                                                                })?;
-                                                                // Show confirmation prompt
-                                                                if let Event::Key(confirm_key) = event::read()? {
-                                                                    if let KeyCode::Char('y') = confirm_key.code {
-                                                                        match position.exchange.as_str() {
-                                                                            "dYdX" => {
-                                                                                if let Err(e) = app.wallet_manager.close_dydx_position(
-                                                                                    position.asset.clone(),
-                                                                                    position.size
-                                                                                ).await {
-                                                                                    eprintln!("Error closing dYdX position: {}", e);
-                                                                                }
-                                                                            }
-                                                                            "Hyperliquid" => {
-                                                                                if let Err(e) = app.hyperliquid_service.close_position(
-                                                                                    position.asset.clone(),
-                                                                                    position.size
-                                                                                ).await {
-                                                                                    eprintln!("Error closing Hyperliquid position: {}", e);
-                                                                                }
-                                                                            }
-                                                                            _ => {}
-                                                                        }
-                                                                        // Force an immediate update after closing
-                                                                        if let Err(e) = app.update().await {
-                                                                            eprintln!("Error updating after position close: {}", e);
-                                                                        }
-                                                                    }
-                                                                }
+                                                    KeyCode::Up => selected = selected.saturating_sub(1),
+                                                    KeyCode::Down => {
+                                                        if selected + 1 < app.market_data.positions.len() {
+                                                            selected += 1;
+                                                        }
+                                                    }
+                                                    KeyCode::Char('t') => {
+                                                        configure_trailing_stop(&mut app, &mut terminal, &funding, selected).await?;
+                                                        terminal.clear()?;
+                                                    }
+                                                    KeyCode::Char('c') => {
+                                                        if let Some(position) = app.market_data.positions.get(selected).cloned() {
+                                                            if let Some(message) = partial_close(&mut app, &mut terminal, &position, 100.0).await? {
+                                                                status = Some(message);
                                                             }
                                                         }
                                                     }
+                                                    KeyCode::Char('p') => {
+                                                        if !app.market_data.positions.is_empty() {
+                                                            status = None;
+                                                            percent_input = Some(String::new());
+                                                        }
+                                                    }
                                                     _ => {}
                                                 }
                                             }
@@ -336,120 +1094,27 @@ async fn main() -> Result<()> {
                                     }
                                 },
                                 MenuOption::ViewOpenOrders => {
-                                    let mut orders = Vec::new();
-                                    
-                                    // Collect orders (same as before)
-                                    if let Ok(dydx_orders) = app.wallet_manager.get_dydx_orders().await {
-                                        let open_orders: Vec<_> = dydx_orders.into_iter()
-                                            .filter_map(|order| Order::from_dydx_order(&order).ok())
-                                            .filter(|order| order.status == "Open")
-                                            .collect();
-                                        orders.extend(open_orders);
-                                    }
-                                    
-                                    // Get Hyperliquid orders and convert to common Order type
-                                    if let Ok(hl_orders) = app.hyperliquid_service.get_open_orders().await {
-                                        let converted_orders: Vec<_> = hl_orders.into_iter()
-                                            .filter_map(|order| Order::from_hl_order(&order).ok())
-                                            .collect();
-                                        orders.extend(converted_orders);
-                                    }
-                                    
-                                    loop {
-                                        terminal.clear()?;
-                                        terminal.draw(|f| {
-                                            Order::display_orders(f, &orders);
-                                        })?;
-                                        
-                                        if let Event::Key(key) = event::read()? {
-                                            match key.code {
-                                                KeyCode::Char('q') => break,
-                                                KeyCode::Char(c) => {
-                                                    if let Some(num) = c.to_digit(10) {
-                                                        if num > 0 && num <= orders.len() as u32 {
-                                                            let order = &orders[num as usize - 1];
-                                                            
-                                                            // Show confirmation prompt
-                                                            terminal.clear()?;
-                                                            terminal.draw(|f| {
-                                                                let confirm_text = format!(
-                                                                    "Are you sure you want to cancel this {} order?\nSize: {} {}\nPrice: ${:.2}\nSide: {}\n\nPress 'y' to confirm, any other key to cancel",
-                                                                    order.exchange, order.size, order.asset, order.price, order.side
-                                                                );
-                                                                let confirm = Paragraph::new(confirm_text)
-                                                                    .block(Block::default().borders(Borders::ALL).title("Confirm Cancel"));
-                                                                f.render_widget(confirm, f.area());
-                                                            })?;
-                                                            
-                                                            if let Event::Key(confirm_key) = event::read()? {
-                                                                if matches!(confirm_key.code, KeyCode::Char('y')) {
-                                                                    match order.exchange.as_str() {
-                                                                        "dYdX" => {
-                                                                            if let Err(e) = app.wallet_manager.cancel_dydx_order(&order.order_id).await {
-                                                                                eprintln!("Error canceling dYdX order: {}", e);
-                                                                            } else {
-                                                                                // Wait a moment for the cancellation to propagate
-                                                                                tokio::time::sleep(Duration::from_secs(1)).await;
-                                                                                
-                                                                                // Refresh orders list
-                                                                                orders.clear();
-                                                                                if let Ok(dydx_orders) = app.wallet_manager.get_dydx_orders().await {
-                                                                                    let open_orders: Vec<_> = dydx_orders.into_iter()
-                                                                                        .filter_map(|order| Order::from_dydx_order(&order).ok())
-                                                                                        .filter(|order| order.status == "Open")
-                                                                                        .collect();
-                                                                                    orders.extend(open_orders);
-                                                                                }
-                                                                            }
-                                                                        },
-                                                                        "Hyperliquid" => {
-                                                                            if let Err(e) = app.hyperliquid_service.cancel_order(
-                                                                                order.order_id.parse::<u64>().unwrap_or_default(), 
-                                                                                order.asset.clone()
-                                                                            ).await {
-                                                                                eprintln!("Error canceling Hyperliquid order: {}", e);
-                                                                            } else {
-                                                                                // Wait a moment for the cancellation to propagate
-                                                                                tokio::time::sleep(Duration::from_secs(1)).await;
-                                                                                
-                                                                                // Refresh orders list
-                                                                                orders.clear();
-                                                                                if let Ok(hl_orders) = app.hyperliquid_service.get_open_orders().await {
-                                                                                    let converted_orders: Vec<_> = hl_orders.into_iter()
-                                                                                        .filter_map(|order| Order::from_hl_order(&order).ok())
-                                                                                        .collect();
-                                                                                    orders.extend(converted_orders);
-                                                                                }
-                                                                            }
-                                                                        },
-                                                                        _ => {}
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                                _ => {}
+                                    view_open_orders(&mut app, &mut terminal).await?;
+                                },
+                                MenuOption::ChangeSymbol => {
+                                    let picker = AssetPicker::new(app.aggregator.asset_directory().await);
+                                    if let Some((symbol, picker)) = pick_asset(&app, &mut terminal, picker)? {
+                                        let listed = picker.directory().venues(symbol.base());
+                                        let unknown: Vec<ExchangeId> = picker.directory().failed.iter().map(|(exchange, _)| exchange.clone()).collect();
+                                        if listed.is_empty() && unknown.is_empty() {
+                                            app.notice = Some(format!("{} is not listed on any venue", symbol));
+                                        } else {
+                                            let missing: Vec<String> = app.aggregator.exchange_ids().into_iter()
+                                                .filter(|exchange| !listed.contains(exchange) && !unknown.contains(exchange))
+                                                .map(|exchange| exchange.to_string())
+                                                .collect();
+                                            if !missing.is_empty() {
+                                                app.notice = Some(format!("{} is not listed on {}", symbol, missing.join(", ")));
                                             }
+                                            app.symbol = symbol;
+                                            app.aggregator.start_all_market_updates(&app.symbol).await?;
                                         }
                                     }
-                                },
-                                MenuOption::ChangeSymbol => {
-                                    disable_raw_mode()?;
-                                    terminal.clear()?;
-                                    
-                                    print!("\x1b[2J\x1b[1;1H"); // Clear screen
-                                    println!("\x1b[1;36mChange Symbol\x1b[0m\n");
-                                    print!("Enter new symbol: ");
-                                    io::stdout().flush()?;
-                                    
-                                    let mut new_symbol = String::new();
-                                    io::stdin().read_line(&mut new_symbol)?;
-                                    
-                                    app.symbol = new_symbol.trim().to_uppercase();
-                                    app.aggregator.start_all_market_updates(&app.symbol).await?;
-                                    
-                                    enable_raw_mode()?;
                                     terminal.clear()?;
                                 },
                                 MenuOption::PlaceTrade => {
@@ -464,12 +1129,36 @@ async fn main() -> Result<()> {
                                             let block = Block::default();
                                             f.render_widget(block, f.area());
                                         })?;
+                                    } else {
+                                        // No venue picked: send it wherever fills best
+                                        let symbol = app.symbol.clone();
+                                        if let Err(e) = route_trade(&mut app, &mut terminal, &symbol).await {
+                                            let mut panel = MessagePanel::new("Best-execution order");
+                                            panel.push(&format!("Error routing trade: {}", e));
+                                            show(&mut terminal, &app.styles, &main_screen(&app), panel)?;
+                                        }
+                                        terminal.clear()?;
                                     }
                                 },
                                 MenuOption::ManageWallets => {
                                     manage_wallets(&mut app, &mut terminal).await?;
                                 },
                                 MenuOption::Exit => break,
+                                MenuOption::ManageAlerts => {
+                                    manage_alerts(&mut app, &mut terminal)?;
+                                },
+                                MenuOption::ClosedTrades => {
+                                    view_closed_trades(&mut app, &mut terminal).await?;
+                                },
+                                MenuOption::Strategies => {
+                                    view_strategies(&mut app, &mut terminal).await?;
+                                },
+                                MenuOption::Pnl => {
+                                    view_pnl(&mut app, &mut terminal).await?;
+                                },
+                                MenuOption::TradeHistory => {
+                                    view_trade_history(&mut app, &mut terminal).await?;
+                                },
                             }
                         }
                     }
@@ -479,21 +1168,86 @@ async fn main() -> Result<()> {
         }
     }
 
-    // Cleanup
-    disable_raw_mode()?;
-    terminal.show_cursor()?;
-    terminal.backend_mut().execute(LeaveAlternateScreen)?;
-    
+    // Input has stopped; tear down in order
+    let report = app.shutdown().await;
+    for (step, e) in &report.failed {
+        eprintln!("Shutdown step '{}' failed: {}", step, e);
+    }
+    println!("{}", app.session_summary());
+
     Ok(())
 }
 
-async fn start_market_updates(aggregator: &mut DerivativesAggregator, symbol: &str) -> Result<()> {
+async fn start_market_updates(aggregator: &mut DerivativesAggregator, symbol: &Symbol) -> Result<()> {
     aggregator.start_all_market_updates(symbol).await?;
     sleep(Duration::from_secs(2)).await; // Give time for initial data
     Ok(())
 }
 
-async fn place_trade(app: &mut App, symbol: &str, exchange: &str) -> Result<()> {
+// Market order sent to whichever venue's book fills it best, prompted for
+// over the main screen with the outcome in a panel
+async fn route_trade(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, symbol: &Symbol) -> Result<()> {
+    let styles = app.styles.clone();
+    let title = format!("Best-execution {} market order", symbol);
+    let Some(is_buy) = ask(terminal, &styles, &main_screen(app), &title, "Buy or sell? (b/s): ", buy_or_sell)? else { return Ok(()) };
+    let Some(usd_value) = ask(terminal, &styles, &main_screen(app), &title, "Amount in USD: $", positive_number)? else { return Ok(()) };
+    let Some(leverage) = ask(terminal, &styles, &main_screen(app), &title, "Enter leverage [1]: ", or_default(1))? else { return Ok(()) };
+    let request = TradeRequest {
+        asset: symbol.clone(),
+        order_type: OrderType::Market,
+        is_buy,
+        usd_value,
+        price: None,
+        leverage,
+        reduce_only: false,
+        cross_margin: Some(true),
+        strategy_id: None,
+        max_slippage_bps: None,
+        post_only: false,
+        time_in_force: None,
+        good_til_secs: None,
+        cloid: None,
+    };
+
+    let decision = BestExecution::new(&app.aggregator, &mut app.router, app.route_max_book_age_ms).decide(&request).await?;
+    let force = confirm_duplicate(app, terminal, &main_screen(app), &decision.exchange, &request)?;
+    let quote = Quote {
+        price: decision.estimate.avg_price,
+        size_step: app.aggregator.market_spec(&decision.exchange, symbol).await.and_then(|spec| spec.size_step()),
+    };
+    let (tier, notional) = app.router.review_order(&request, &quote);
+    let mut detail = decision.describe();
+    if tier != ConfirmationTier::None {
+        detail.push_str(&margin_comparison(app, symbol, &decision.exchange, notional).await);
+    }
+    let Some(confirmation) = confirm_order(terminal, &styles, &main_screen(app), tier, notional, &detail)? else { return Ok(()) };
+
+    // The books are fetched again, so the venue can change if they moved
+    let max_age = app.route_max_book_age_ms;
+    let routed = run_with_status(&app.operation, "routing order to the best venue", BestExecution::new(&app.aggregator, &mut app.router, max_age).route_trade(request, confirmation, force)).await?;
+    let mut panel = MessagePanel::new(&title);
+    panel.push(&routed.decision.describe());
+    panel.push(&routed.trade.snapshot.describe(symbol));
+    panel.push(&match &routed.trade.result {
+        Ok((message, order_id)) => format!("Placed on {}: {} {}", routed.decision.exchange, message, order_id),
+        Err(e) => format!("Error placing trade on {}: {}", routed.decision.exchange, e),
+    });
+    panel.push(&match (routed.actual_price, routed.slippage_bps(is_buy)) {
+        (Some(actual), Some(slippage)) => format!(
+            "Estimated {:.4}, filled {:.4} ({:+.1}bps)",
+            routed.decision.estimate.avg_price, actual, slippage
+        ),
+        _ => format!("Estimated {:.4}; fills not reported yet", routed.decision.estimate.avg_price),
+    });
+    show(terminal, &styles, &main_screen(app), panel)
+}
+
+// The main menu screen, for prompts drawn over it
+fn main_screen(app: &App) -> impl Fn(&mut ratatui::Frame<'_>) + '_ {
+    move |f| ui(f, app)
+}
+
+async fn place_trade(app: &mut App, symbol: &Symbol, exchange: &ExchangeId) -> Result<()> {
     let mut log_message = None;
     
     // Ensure we start with a clean terminal state
@@ -504,129 +1258,144 @@ async fn place_trade(app: &mut App, symbol: &str, exchange: &str) -> Result<()>
     loop {
         // Get latest orderbook
         let orderbook = app.aggregator.get_exchange_orderbook(exchange, symbol).await.ok();
+        let mid_price = orderbook.as_ref().and_then(OrderBook::mid_price);
+
+        if let Some(price) = mid_price {
+            if let Some(fired) = app.check_alerts(price) {
+                log_message = Some(fired);
+            }
+        }
+
+        let (quick, quick_leverage) = quick_sizes_for(app, symbol, exchange, mid_price).await;
 
         // Draw UI using app's terminal
+        let alerts: Vec<&Alert> = app.alerts.for_symbol(symbol).collect();
+        let status = app.aggregator.health.status(exchange);
         if let Ok(mut terminal) = app.terminal.try_lock() {
             terminal.draw(|f| {
-                trading_ui(f, symbol, exchange, orderbook.as_ref(), log_message.as_deref());
+                trading_ui(f, symbol, exchange, &status, orderbook.as_ref(), &alerts, log_message.as_deref(), &quick, quick_leverage, app.router.mirror_mode(), &app.styles);
             })?;
         }
 
         if event::poll(Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
+                // Prompts draw over the trading screen as it was at the key press
+                let screen_alerts: Vec<Alert> = alerts.into_iter().cloned().collect();
+                let screen_log = log_message.clone();
+                let mirror = app.router.mirror_mode();
+                let styles = app.styles.clone();
+                let screen = |f: &mut ratatui::Frame<'_>| {
+                    let alerts: Vec<&Alert> = screen_alerts.iter().collect();
+                    trading_ui(f, symbol, exchange, &status, orderbook.as_ref(), &alerts, screen_log.as_deref(), &quick, quick_leverage, mirror, &styles);
+                };
                 match key.code {
-                    KeyCode::Char('1'..='4') => {
-                        // Temporarily disable raw mode for input
-                        disable_raw_mode()?;
-                        
-                        // Get amount input
-                        print!("Enter USD value: $");
-                        io::stdout().flush()?;
-                        
-                        let mut amount_input = String::new();
-                        io::stdin().read_line(&mut amount_input)?;
-                        let usd_value = amount_input.trim().parse()?;
-
-                        let (order_type, is_buy) = match key.code {
-                            KeyCode::Char('1') => (OrderType::Market, true),
-                            KeyCode::Char('2') => (OrderType::Market, false),
-                            KeyCode::Char('3') => (OrderType::Limit, true),
-                            KeyCode::Char('4') => (OrderType::Limit, false),
-                            _ => unreachable!(),
-                        };
-
-                        // Get leverage input
-                        print!("Enter leverage: ");
-                        io::stdout().flush()?;
-                        
-                        let mut leverage_input = String::new();
-                        io::stdin().read_line(&mut leverage_input)?;
-                        let leverage = leverage_input.trim().parse().unwrap_or(1);
-
-                        // Only ask for cross margin mode for Hyperliquid
-                        let cross_margin = if exchange == "Hyperliquid" {
-                            print!("Cross margin? (y/n): ");
-                            io::stdout().flush()?;
-                            
-                            let mut margin_input = String::new();
-                            io::stdin().read_line(&mut margin_input)?;
-                            Some(margin_input.trim().to_lowercase().starts_with('y'))
-                        } else {
-                            // dYdX defaults to cross margin
-                            Some(true)
+                    KeyCode::Char(choice @ '1'..='6') => {
+                        let entry = read_order_entry(app, choice, symbol, exchange, mid_price, &quick, quick_leverage, &screen).await;
+                        let OrderEntry { request, sizing_note } = match entry {
+                            Ok(Some(entry)) => entry,
+                            Ok(None) => {
+                                log_message = Some("Order cancelled".to_string());
+                                continue;
+                            }
+                            Err(e) => {
+                                log_message = Some(e.to_string());
+                                continue;
+                            }
                         };
 
-                        let mut price = None;
-                        if matches!(order_type, OrderType::Limit) {
-                            print!("Enter price: ");
-                            io::stdout().flush()?;
-                            
-                            let mut price_input = String::new();
-                            io::stdin().read_line(&mut price_input)?;
-                            price = Some(price_input.trim().parse()?);
-                        }
-
-                        // Re-enable raw mode and clear screen
-                        enable_raw_mode()?;
-                        if let Ok(mut terminal) = app.terminal.try_lock() {
-                            terminal.clear()?;
+                        // The confirmations draw over the trading screen too
+                        let terminal = app.terminal.clone();
+                        if app.router.mirror_mode() {
+                            let placed = place_mirrored(app, &mut *terminal.lock().await, &screen, symbol, exchange, request, orderbook.as_ref()).await;
+                            log_message = Some(placed.unwrap_or_else(|e| format!("Error placing mirrored trade: {}", e)));
+                            continue;
                         }
-
-                        let request = TradeRequest {
-                            asset: symbol.to_string(),
-                            order_type,
-                            is_buy,
-                            usd_value,
-                            price,
-                            leverage,
-                            reduce_only: false,
-                            cross_margin,
+                        let confirmed = confirm_trade(app, &mut *terminal.lock().await, &screen, exchange, symbol, &request, orderbook.as_ref()).await;
+                        let (confirmation, force, quote) = match confirmed {
+                            Ok(Some(confirmed)) => confirmed,
+                            Ok(None) => {
+                                log_message = Some("Order cancelled".to_string());
+                                continue;
+                            }
+                            Err(e) => {
+                                log_message = Some(format!("Error confirming order: {}", e));
+                                continue;
+                            }
                         };
 
                         // Route to correct exchange
-                        let result = match exchange {
-                            "dYdX" => {
-                                let dydx_order_type = match request.order_type {
-                                    LocalOrderType::Market => DydxOrderType::Market,
-                                    LocalOrderType::Limit => DydxOrderType::Limit,
-                                };
+                        let is_market = matches!(request.order_type, OrderType::Market);
+                        let label = format!("placing {} order", exchange);
+                        let routed = run_with_status(&app.operation, &label, app.router.place_trade(exchange, request, quote, confirmation, force)).await;
+                        let diff = routed.snapshot.describe(symbol);
 
-                                app.wallet_manager.place_dydx_order(
-                                    &symbol,
-                                    if request.is_buy { OrderSide::Buy } else { OrderSide::Sell },
-                                    request.usd_value,
-                                    request.price,
-                                    dydx_order_type,
-                                    OrderTimeInForce::Ioc,
-                                    leverage as f64,
-                                    cross_margin,
-                                ).await
-                            },
-                            "Hyperliquid" => {
-                                app.hyperliquid_service.place_trade(request).await
-                                    .map(|response| match response {
-                                        ExchangeResponseStatus::Ok(response) => (response.response_type, String::new()),
-                                        ExchangeResponseStatus::Err(message) => (message, String::new()),
-                                        _ => ("Unknown response status".to_string(), String::new())
-                                    })
+                        match routed.result {
+                            // A market order fills at once; show what it got
+                            Ok((_, order_id)) if is_market && !order_id.is_empty() => {
+                                let fill = match run_with_status(&app.operation, "waiting for fill", app.router.wait_for_fill(exchange, &order_id, FILL_WAIT_TIMEOUT)).await {
+                                    Ok(report) => report.describe(),
+                                    Err(e) => format!("fill unknown: {}", e),
+                                };
+                                log_message = Some(format!("Order {} placed: {}\n{}{}", order_id, fill, diff, sizing_note));
                             },
-                            _ => Err(anyhow::anyhow!("Unknown exchange: {}", exchange))
-                        };
-
-                        match result {
                             Ok(tx_hash) => {
-                                log_message = Some(format!("Trade placed successfully: {} {}", tx_hash.0, tx_hash.1));
+                                log_message = Some(format!("Trade placed successfully: {} {}\n{}{}", tx_hash.0, tx_hash.1, diff, sizing_note));
                             },
                             Err(e) => {
                                 log_message = Some(format!(
-                                    "Error placing trade:\n{}\nTime: {}",
+                                    "Error placing trade:\n{}\n{}\nTime: {}",
                                     e,
-                                    chrono::Local::now().format("%H:%M:%S")
+                                    diff,
+                                    timefmt::fmt_now()
                                 ));
                             }
                         }
                     },
-                    KeyCode::Char('5') | KeyCode::Esc | KeyCode::Char('q') => {
+                    KeyCode::Char('t') => {
+                        let terminal = app.terminal.clone();
+                        let setup = read_twap(app, &mut *terminal.lock().await, &screen, symbol, exchange, mid_price).await;
+                        log_message = Some(match setup {
+                            Ok(Some(twap)) => run_twap(app, symbol, exchange, twap).await?,
+                            Ok(None) => "TWAP cancelled".to_string(),
+                            Err(e) => format!("Error setting up TWAP: {}", e),
+                        });
+                        if let Ok(mut terminal) = app.terminal.try_lock() {
+                            terminal.clear()?;
+                        }
+                    },
+                    KeyCode::Char('m') => {
+                        let on = !app.router.mirror_mode();
+                        app.router.set_mirror_mode(on);
+                        log_message = Some(if on {
+                            let others: Vec<String> = app.router.mirror_venues(exchange).iter().skip(1).map(|venue| venue.to_string()).collect();
+                            format!("Mirror mode on: orders also go to {}", others.join(", "))
+                        } else {
+                            "Mirror mode off".to_string()
+                        });
+                    },
+                    KeyCode::Char('d') => {
+                        if let Err(e) = dom_view(app, symbol, exchange).await {
+                            log_message = Some(format!("DOM ladder error: {}", e));
+                        }
+                        if let Ok(mut terminal) = app.terminal.try_lock() {
+                            terminal.clear()?;
+                        }
+                    },
+                    KeyCode::Char('l') => {
+                        let terminal = app.terminal.clone();
+                        let title = format!("{} {} alert", exchange, symbol);
+                        let Some(price) = ask(&mut *terminal.lock().await, &styles, &screen, &title, "Alert price: $", positive_number)? else { continue };
+
+                        let current_price = mid_price.or_else(|| app.last_price());
+                        log_message = Some(match current_price {
+                            Some(current) => match app.alerts.add_price_cross(symbol, price, current) {
+                                Ok(alert) => format!("Alert line set at {}", alert.rule.label()),
+                                Err(e) => format!("Error setting alert: {}", e),
+                            },
+                            None => "Error setting alert: no current price".to_string(),
+                        });
+                    },
+                    KeyCode::Char('7') | KeyCode::Esc | KeyCode::Char('q') => {
                         // Ensure clean exit from trading menu
                         if let Ok(mut terminal) = app.terminal.try_lock() {
                             terminal.clear()?;
@@ -647,401 +1416,2421 @@ async fn place_trade(app: &mut App, symbol: &str, exchange: &str) -> Result<()>
     Ok(())
 }
 
-fn ui(f: &mut ratatui::Frame<'_>, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints([
-            Constraint::Length(3),   // Menu
-            Constraint::Length(10),  // Market Summaries
-            Constraint::Min(0),      // Selected Exchange Data (Orderbook)
-        ])
-        .split(f.area());
+// Confirm and market-close `percent` of `position`, reduce-only and rounded
+// down to the market's size step. Returns the status line to show, or None
+// when the user backs out.
+async fn partial_close(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, position: &Position, percent: f64) -> Result<Option<String>> {
+    let symbol = match position.symbol() {
+        Ok(symbol) => symbol,
+        Err(e) => return Ok(Some(format!("Error closing position: {}", e))),
+    };
+    let step = app.aggregator.market_spec(&position.exchange, &symbol).await.and_then(|spec| spec.size_step());
+    let Some(size) = positions::partial_close_size(position, percent, step) else {
+        return Ok(Some(format!("{}% of {} {} rounds to nothing", percent, position.size.abs(), position.asset)));
+    };
 
-    // Menu
-    let menu = Paragraph::new("1. View Dydx  2. View Hyperliquid  3. View Positions  4. View Open Orders  5. Change Symbol  6. Place Trade  7. Manage Wallets  8. Exit")
-        .block(Block::default().borders(Borders::ALL).title("Menu"));
-    f.render_widget(menu, chunks[0]);
+    terminal.clear()?;
+    terminal.draw(|f| {
+        let confirm_text = format!(
+            "Are you sure you want to close {}% of the {} {} position?\nClosing: {} of {}\nPnL: ${:.2}\n\nPress 'y' to confirm, any other key to cancel",
+            percent,
+            position.side,
+            position.asset,
+            size.abs(),
+            position.size.abs(),
+            position.unrealized_pnl
+        );
+        let confirm = Paragraph::new(confirm_text)
+            .block(Block::default().borders(Borders::ALL).title("Confirm Close Position"));
+        f.render_widget(confirm, f.area());
+    })?;
+    let Event::Key(key) = event::read()? else { return Ok(None) };
+    if key.code != KeyCode::Char('y') {
+        return Ok(None);
+    }
 
-    // Market Summaries - Split horizontally for each exchanges
-    let summary_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(50),  // dYdX
-            Constraint::Percentage(50),  // Hyperliquid
-        ])
-        .split(chunks[1]);
+    let operation = app.operation.clone();
+    let label = format!("closing {} position", position.exchange);
+    let message = match run_with_status(&operation, &label, app.router.close_position(&position.exchange, &symbol, size)).await {
+        Ok(tx_hash) => format!("Closed {} {} on {}: {}", size.abs(), position.asset, position.exchange, tx_hash),
+        Err(e) => format!("Error closing {} position: {}", position.exchange, e),
+    };
+    // Force an immediate update after closing
+    if let Err(e) = run_with_status(&operation, "refreshing positions", app.update()).await {
+        eprintln!("Error updating after position close: {}", e);
+    }
+    Ok(Some(message))
+}
+
+// Prompts over the positions list for a position's trailing stop; an empty
+// distance removes it
+async fn configure_trailing_stop(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, funding: &[PositionFunding], selected: usize) -> Result<()> {
+    match set_trailing_stop_from_input(app, terminal, funding, selected).await {
+        Ok(Some(message)) => app.notify(message),
+        Ok(None) => {}
+        Err(e) => app.notify(format!("Trailing stop not set: {}", e)),
+    }
+    Ok(())
+}
+
+async fn set_trailing_stop_from_input(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, funding: &[PositionFunding], selected: usize) -> Result<Option<String>> {
+    let switch = match &app.dead_mans_switch {
+        Some(switch) if switch.is_armed() => "The dead man's switch is armed, so resting orders are cancelled if the app dies.",
+        _ => "The dead man's switch is not armed.",
+    };
+    let detail = format!("Warning: trailing stops are client-side and only work while this app is running.\n{}", switch);
+    let live_pnl = app.live_pnl();
+    let positions = &app.market_data.positions;
+    let screen = |f: &mut ratatui::Frame<'_>| {
+        Position::display_positions(f, positions, &live_pnl, funding, &app.trailing, selected, None, &app.styles);
+    };
+
+    let label = format!("Position # (as listed) [{}]: ", selected + 1);
+    let prompt = InputPrompt::new("Trailing Stop", &label).with_detail(&detail);
+    let parse = |input: &str| match or_default(selected + 1)(input)? {
+        index if (1..=positions.len()).contains(&index) => Ok(index),
+        index => Err(format!("No position #{}", index)),
+    };
+    let Some(index) = ask_with(terminal, &app.styles, &screen, prompt, parse)? else { return Ok(None) };
+    let parse = |input: &str| match input {
+        "" => Ok(None),
+        input => TrailDistance::parse(input).map(Some).map_err(|e| e.to_string()),
+    };
+    let Some(distance) = ask(terminal, &app.styles, &screen, "Trailing Stop", "Trail distance (e.g. 2% or 150, empty to remove): ", parse)? else { return Ok(None) };
+    let position = positions[index - 1].clone();
+    let symbol = position.symbol()?;
+
+    let Some(distance) = distance else {
+        app.trailing.remove(&position.exchange, &symbol)?;
+        return Ok(Some(format!("Trailing stop removed for {} {}", position.exchange, symbol)));
+    };
+    // The watermark starts at the current mark
+    let mark = app.aggregator.get_exchange_summary(&position.exchange, &symbol).await?.price;
+    let stop = TrailingStop::new(position.exchange.clone(), symbol, position.size > 0.0, distance, mark);
+    let message = format!("Trailing stop set for {} {}: {}", stop.exchange, stop.symbol, stop.describe());
+    app.trailing.set(stop)?;
+    Ok(Some(message))
+}
+
+// Single-column price ladder for one market. Up/Down scroll the highlighted
+// level, 'b'/'s' place a limit order there, 'c' recentres on mid.
+async fn dom_view(app: &mut App, symbol: &Symbol, exchange: &ExchangeId) -> Result<()> {
+    let mut state = LadderState::default();
+    let mut log_message: Option<String> = None;
+    let cached_tick = app.aggregator.tick_size(exchange, symbol).await;
+
+    loop {
+        let orderbook = app.aggregator.get_exchange_orderbook(exchange, symbol).await.ok();
+        let own_orders: Vec<Order> = app.router.orders().orders().iter()
+            .filter(|tracked| tracked.state == OrderState::Open)
+            .map(|tracked| tracked.order.clone())
+            .filter(|order| &order.exchange == exchange && order.is_for(symbol))
+            .collect();
+
+        let venue_tick = cached_tick.or_else(|| orderbook.as_ref().and_then(ladder::infer_tick));
+        // Rows are one price bucket apart; never finer than the venue's tick
+        let tick = venue_tick.map(|tick| app.price_bucket.width(Some(tick)).map_or(tick, |width| width.max(tick)));
+        let mut rows = Vec::new();
+        if let Ok(mut terminal) = app.terminal.try_lock() {
+            // Odd row count so the highlighted level sits in the middle
+            let height = terminal.size()?.height.saturating_sub(2) as usize;
+            let row_count = if height % 2 == 0 { height.saturating_sub(1).max(1) } else { height };
+            if let (Some(book), Some(tick)) = (orderbook.as_ref(), tick) {
+                rows = ladder::build_ladder(&book.bucketed(tick), &own_orders, tick, state, row_count);
+            }
+            let title = format!(
+                "{} {} DOM  ↑/↓ scroll  b buy  s sell  c centre  q back{}",
+                exchange,
+                symbol,
+                log_message.as_deref().map(|m| format!("  | {}", m)).unwrap_or_default()
+            );
+            terminal.draw(|f| {
+                ladder::render_ladder(f, f.area(), &rows, tick.unwrap_or(1.0), &title, &app.styles);
+            })?;
+        }
+
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else { continue };
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => state.scroll_up(),
+            KeyCode::Down | KeyCode::Char('j') => state.scroll_down(),
+            KeyCode::Char('c') => state.recenter(),
+            KeyCode::Char(side @ ('b' | 's')) => {
+                let Some(row) = ladder::highlighted(&rows) else { continue };
+                let (price, is_buy) = (row.price, side == 'b');
+
+                let terminal = app.terminal.clone();
+                let mut terminal = terminal.lock().await;
+                let styles = app.styles.clone();
+                let ladder_title = format!("{} {} DOM", exchange, symbol);
+                let screen = |f: &mut ratatui::Frame<'_>| ladder::render_ladder(f, f.area(), &rows, tick.unwrap_or(1.0), &ladder_title, &styles);
+                let label = format!(
+                    "Limit {} {} at ${} - amount [${:.2}]: ",
+                    if is_buy { "buy" } else { "sell" },
+                    symbol,
+                    price,
+                    app.ladder_default_usd
+                );
+                let context = AmountContext {
+                    symbol,
+                    price: Some(price),
+                    free_collateral: app.router.free_collateral(exchange),
+                    leverage: 1.0,
+                };
+                let default_usd = app.ladder_default_usd;
+                let parse = |input: &str| match input {
+                    "" => Ok(default_usd),
+                    input => parse_usd_value(input, &context).map(|(_, usd)| usd).map_err(|e| e.to_string()),
+                };
+                let Some(usd_value) = ask(&mut terminal, &styles, &screen, &ladder_title, &label, parse)? else { continue };
+                let request = TradeRequest {
+                    asset: symbol.clone(),
+                    order_type: OrderType::Limit,
+                    is_buy,
+                    usd_value,
+                    price: Some(price),
+                    leverage: 1,
+                    reduce_only: false,
+                    cross_margin: Some(true),
+                    strategy_id: None,
+                    max_slippage_bps: None,
+                    post_only: false,
+                    time_in_force: None,
+                    good_til_secs: None,
+                    cloid: None,
+                };
+                let force = confirm_duplicate(app, &mut terminal, &screen, exchange, &request)?;
+                let quote = order_quote(app, exchange, symbol, &request, None).await;
+                let (tier, notional) = app.router.review_order(&request, &quote);
+                // The ladder always asked before sending; keep at least that
+                let Some(confirmation) = confirm_order(&mut terminal, &styles, &screen, tier.max(ConfirmationTier::Dialog), notional, "")? else { continue };
+                drop(terminal);
+
+                let label = format!("placing {} order", exchange);
+                let routed = run_with_status(&app.operation, &label, app.router.place_trade(exchange, request, quote, confirmation, force)).await;
+                log_message = Some(match routed.result {
+                    Ok((_, order_id)) => format!("Placed {} at ${} ({})", if is_buy { "buy" } else { "sell" }, price, order_id),
+                    Err(e) => format!("Order failed: {}", e),
+                });
+                // Show the new order on the ladder straight away
+                app.router.reconcile_orders().await;
+            }
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// An order read from the trading screen's prompts, with how it was sized
+struct OrderEntry {
+    request: TradeRequest,
+    sizing_note: String,
+}
+
+// What the amount prompt accepted
+enum AmountInput {
+    Preset(QuickSize),
+    // Size by risk, prompted for next
+    Risk,
+    Parsed(Amount, f64),
+}
+
+// A lone digit picks a quick size (every venue minimum is above $4), 'r'
+// sizes by risk, anything else is an amount
+fn parse_amount_input(input: &str, quick: &[QuickSize], context: &AmountContext) -> Result<AmountInput, String> {
+    if let Some(key) = input.parse::<usize>().ok().filter(|key| (1..=4).contains(key)) {
+        let Some(preset) = quick.get(key - 1) else {
+            return Err("No quick size: free collateral, price or market spec not loaded yet".to_string());
+        };
+        if let Some(reason) = &preset.unavailable {
+            return Err(format!("Quick size {}% unavailable: {}", preset.percent, reason));
+        }
+        return Ok(AmountInput::Preset(preset.clone()));
+    }
+    if input.eq_ignore_ascii_case("r") {
+        return Ok(AmountInput::Risk);
+    }
+    parse_usd_value(input, context)
+        .map(|(amount, usd_value)| AmountInput::Parsed(amount, usd_value))
+        .map_err(|e| e.to_string())
+}
+
+fn buy_or_sell(input: &str) -> Result<bool, String> {
+    match input.to_lowercase().as_str() {
+        "b" | "buy" => Ok(true),
+        "s" | "sell" => Ok(false),
+        _ => Err("Expected b or s".to_string()),
+    }
+}
+
+// A stop price or an ATR multiple like "2atr", checked but left as typed
+fn parse_stop_input(input: &str) -> Result<String, String> {
+    if analytics::parse_atr_multiple(input).is_some() || positive_number(input).is_ok() {
+        Ok(input.to_string())
+    } else {
+        Err(format!("Expected a stop price or an ATR multiple like 2atr, got '{}'", input))
+    }
+}
+
+// Ask for one value in a prompt over whatever `screen` draws, until it
+// parses. None when the user escapes.
+fn ask<T>(terminal: &mut Terminal<CrosstermBackend<Stdout>>, styles: &Styles, screen: &dyn Fn(&mut ratatui::Frame<'_>), title: &str, label: &str, parse: impl Fn(&str) -> Result<T, String>) -> Result<Option<T>> {
+    ask_with(terminal, styles, screen, InputPrompt::new(title, label), parse)
+}
+
+// A yes or no over `screen`, with `detail` above it; escaping is a no
+fn confirm(terminal: &mut Terminal<CrosstermBackend<Stdout>>, styles: &Styles, screen: &dyn Fn(&mut ratatui::Frame<'_>), title: &str, detail: &str, label: &str) -> Result<bool> {
+    let prompt = InputPrompt::new(title, label).with_detail(detail);
+    Ok(ask_with(terminal, styles, screen, prompt, yes_no(None))? == Some(true))
+}
+
+// `ask` with a prompt the caller built, e.g. with detail or masked
+fn ask_with<T>(terminal: &mut Terminal<CrosstermBackend<Stdout>>, styles: &Styles, screen: &dyn Fn(&mut ratatui::Frame<'_>), mut prompt: InputPrompt, parse: impl Fn(&str) -> Result<T, String>) -> Result<Option<T>> {
+    loop {
+        terminal.draw(|f| {
+            screen(f);
+            prompt.render_popup(f, styles);
+        })?;
+        let Event::Key(key) = event::read()? else { continue };
+        match prompt.handle_key(key.code, &parse) {
+            PromptEvent::Submitted(value) => return Ok(Some(value)),
+            PromptEvent::Cancelled => return Ok(None),
+            PromptEvent::Pending => {}
+        }
+    }
+}
+
+// Redraw `panel` over `screen`, e.g. after it gains a progress line
+fn draw_panel(terminal: &mut Terminal<CrosstermBackend<Stdout>>, styles: &Styles, screen: &dyn Fn(&mut ratatui::Frame<'_>), panel: &MessagePanel) -> Result<()> {
+    terminal.draw(|f| {
+        screen(f);
+        panel.render_popup(f, styles);
+    })?;
+    Ok(())
+}
+
+// Show `panel` over `screen` as finished, until any key
+fn show(terminal: &mut Terminal<CrosstermBackend<Stdout>>, styles: &Styles, screen: &dyn Fn(&mut ratatui::Frame<'_>), mut panel: MessagePanel) -> Result<()> {
+    panel.finish();
+    draw_panel(terminal, styles, screen, &panel)?;
+    loop {
+        if let Event::Key(_) = event::read()? {
+            return Ok(());
+        }
+    }
+}
+
+// The change-symbol picker over the main screen, handed back with the choice
+// so the caller can see which venues list it. Enter on a base that doesn't
+// parse as a symbol is ignored.
+fn pick_asset(app: &App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, mut picker: AssetPicker) -> Result<Option<(Symbol, AssetPicker)>> {
+    loop {
+        terminal.draw(|f| {
+            ui(f, app);
+            picker.render_popup(f, &app.styles);
+        })?;
+        let Event::Key(key) = event::read()? else { continue };
+        match picker.handle_key(key.code) {
+            PromptEvent::Submitted(base) => {
+                if let Ok(symbol) = Symbol::parse_user_input(&base) {
+                    return Ok(Some((symbol, picker)));
+                }
+            }
+            PromptEvent::Cancelled => return Ok(None),
+            PromptEvent::Pending => {}
+        }
+    }
+}
+
+// The order behind trading-screen keys 1-6, prompted for over `screen`.
+// Ok(None) when the user escapes or declines a step.
+#[allow(clippy::too_many_arguments)]
+async fn read_order_entry(app: &mut App, choice: char, symbol: &Symbol, exchange: &ExchangeId, mid_price: Option<f64>, quick: &[QuickSize], quick_leverage: u32, screen: &dyn Fn(&mut ratatui::Frame<'_>)) -> Result<Option<OrderEntry>> {
+    let terminal = app.terminal.clone();
+    let mut terminal = terminal.lock().await;
+    let styles = app.styles.clone();
+    let title = format!("{} {}", exchange, symbol);
+
+    let (order_type, is_buy) = match choice {
+        '1' => (OrderType::Market, true),
+        '2' => (OrderType::Market, false),
+        '3' => (OrderType::Limit, true),
+        '4' => (OrderType::Limit, false),
+        _ => {
+            let Some(is_buy) = ask(&mut terminal, &styles, screen, &title, "Buy or sell? (b/s): ", buy_or_sell)? else { return Ok(None) };
+            let Some(trigger_price) = ask(&mut terminal, &styles, screen, &title, "Trigger price: ", positive_number)? else { return Ok(None) };
+            let order_type = if choice == '5' {
+                OrderType::StopMarket { trigger_price }
+            } else {
+                OrderType::TakeProfit { trigger_price }
+            };
+            (order_type, is_buy)
+        }
+    };
+
+    let context = AmountContext {
+        symbol,
+        price: mid_price,
+        free_collateral: app.router.free_collateral(exchange),
+        leverage: quick_leverage as f64,
+    };
+    let label = format!("Amount (25k, 0.5{}, 50%), 1-{} for a quick size, or 'r' to size by risk: ", symbol.base().to_lowercase(), quick.len().max(1));
+    let Some(amount) = ask(&mut terminal, &styles, screen, &title, &label, |input| parse_amount_input(input, quick, &context))? else { return Ok(None) };
+
+    let mut price = None;
+    let mut sizing_note = String::new();
+    let mut default_leverage = app.default_leverage(exchange);
+    let usd_value = match amount {
+        AmountInput::Preset(preset) => {
+            let label = format!("Quick size {}% of free collateral at {}x: ${:.2}. Use this size? (y/n): ", preset.percent, quick_leverage, preset.usd);
+            if ask(&mut terminal, &styles, screen, &title, &label, yes_no(None))? != Some(true) {
+                return Ok(None);
+            }
+            default_leverage = quick_leverage;
+            sizing_note = format!("\nQuick size {}% at {}x", preset.percent, quick_leverage);
+            preset.usd
+        }
+        AmountInput::Risk => {
+            // Limit orders risk from their own price, stops from the
+            // trigger, market orders from mid
+            if matches!(order_type, OrderType::Limit) {
+                let Some(limit) = ask(&mut terminal, &styles, screen, &title, "Enter price: ", positive_number)? else { return Ok(None) };
+                price = Some(limit);
+            }
+            let Some(entry) = price.or(order_type.trigger_price()).or(mid_price) else {
+                return Err(anyhow::anyhow!("Error sizing by risk: no current price"));
+            };
+            let Some(risk_pct) = ask(&mut terminal, &styles, screen, &title, "Risk (% of equity): ", |input| positive_number(input.trim_end_matches('%')))? else { return Ok(None) };
+            let Some(stop) = ask(&mut terminal, &styles, screen, &title, "Stop price or ATR multiple (e.g. 2atr): $", parse_stop_input)? else { return Ok(None) };
+            let sizing = size_trade_by_risk(app, symbol, exchange, is_buy, entry, risk_pct, &stop).await
+                .map_err(|e| anyhow::anyhow!("Error sizing by risk: {}", e))?;
+            let label = format!("{} Use this size? (y/n): ", sizing.explain());
+            if ask(&mut terminal, &styles, screen, &title, &label, yes_no(None))? != Some(true) {
+                return Ok(None);
+            }
+            sizing_note = format!("\n{}", sizing.explain());
+            sizing.notional
+        }
+        AmountInput::Parsed(amount, usd_value) => {
+            if !matches!(amount, Amount::Usd(_)) {
+                sizing_note = format!("\n{}", amount.describe(&context, usd_value));
+            }
+            if matches!(amount, Amount::PercentOfCollateral(_)) {
+                default_leverage = quick_leverage;
+            }
+            usd_value
+        }
+    };
+
+    let label = format!("Enter leverage [{}]: ", default_leverage);
+    let Some(leverage) = ask(&mut terminal, &styles, screen, &title, &label, or_default(default_leverage))? else { return Ok(None) };
+
+    // Only ask for cross margin mode for Hyperliquid
+    let cross_margin = if *exchange == ExchangeId::Hyperliquid {
+        let Some(cross) = ask(&mut terminal, &styles, screen, &title, "Cross margin? (y/n): ", yes_no(None))? else { return Ok(None) };
+        Some(cross)
+    } else {
+        // dYdX defaults to cross margin
+        Some(true)
+    };
+
+    let is_limit = matches!(order_type, OrderType::Limit);
+    if is_limit && price.is_none() {
+        let Some(limit) = ask(&mut terminal, &styles, screen, &title, "Enter price: ", positive_number)? else { return Ok(None) };
+        price = Some(limit);
+    }
+    let post_only = is_limit
+        && ask(&mut terminal, &styles, screen, &title, "Post-only? (y/n) [n]: ", yes_no(Some(false)))?.is_some_and(|post_only| post_only);
+
+    // Orders to a halted venue need an explicit override
+    if let Err(e) = app.router.ensure_tradable(exchange) {
+        let label = format!("{} Place anyway? (y/n): ", e);
+        if ask(&mut terminal, &styles, screen, &title, &label, yes_no(None))? == Some(true) {
+            app.router.override_halt(exchange);
+        }
+    }
+
+    let request = TradeRequest {
+        asset: symbol.clone(),
+        order_type,
+        is_buy,
+        usd_value,
+        price,
+        leverage,
+        reduce_only: false,
+        cross_margin,
+        strategy_id: None,
+        max_slippage_bps: None,
+        post_only,
+        time_in_force: None,
+        good_til_secs: None,
+        cloid: None,
+    };
+    request.validate_trigger(mid_price.unwrap_or(0.0)).map_err(|e| anyhow::anyhow!("{}", e))?;
+    // Broken-looking market data needs an explicit override
+    if let Err(e) = app.router.check_market_data(exchange, &request) {
+        let label = format!("{} Place anyway? (y/n): ", e);
+        if ask(&mut terminal, &styles, screen, &title, &label, yes_no(None))? == Some(true) {
+            app.router.override_market_data(exchange, symbol);
+        }
+    }
+    Ok(Some(OrderEntry { request, sizing_note }))
+}
+
+// The checks before an order goes out, asked over `screen`: the fill
+// estimate, the duplicate guard and whatever the confirmation tier wants.
+// None when the user declines; otherwise the confirmation, whether to force
+// past the duplicate guard, and the quote it was sized at.
+async fn confirm_trade(app: &App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, screen: &dyn Fn(&mut ratatui::Frame<'_>), exchange: &ExchangeId, symbol: &Symbol, request: &TradeRequest, book: Option<&OrderBook>) -> Result<Option<(Confirmation, bool, Quote)>> {
+    let mut detail = String::new();
+    if matches!(request.order_type, OrderType::Market) {
+        match confirm_market_impact(app, terminal, screen, exchange, symbol, request).await? {
+            Some(estimate) => detail = estimate,
+            None => return Ok(None),
+        }
+    }
+    let force = confirm_duplicate(app, terminal, screen, exchange, request)?;
+    let quote = order_quote(app, exchange, symbol, request, book).await;
+    let (tier, notional) = app.router.review_order(request, &quote);
+    if tier != ConfirmationTier::None {
+        detail.push_str(&margin_comparison(app, symbol, exchange, notional).await);
+    }
+    Ok(confirm_order(terminal, &app.styles, screen, tier, notional, &detail)?.map(|confirmation| (confirmation, force, quote)))
+}
+
+// Size an order so a fill at `entry` stopped out at `stop_input`, a price or
+// an ATR multiple, loses `risk_pct` of the venue's equity
+async fn size_trade_by_risk(app: &App, symbol: &Symbol, exchange: &ExchangeId, is_buy: bool, entry: f64, risk_pct: f64, stop_input: &str) -> Result<RiskSizing> {
+    let stop = match analytics::parse_atr_multiple(stop_input) {
+        Some(multiple) => {
+            let atr = analytics::atr(symbol, exchange, analytics::DEFAULT_ATR_PERIOD, app.router.hyperliquid_service.is_testnet()).await?;
+            StopSpec::AtrMultiple { atr, multiple }
+        }
+        None => StopSpec::Price(stop_input.parse()?),
+    };
+
+    let equity = app.router.account_equity(exchange).await?;
+    Ok(analytics::size_by_risk(equity, risk_pct, entry, is_buy, stop)?)
+}
+
+// Quick sizes for the trading screen, at the leverage of any open position in
+// the market (1x otherwise). Empty until collateral, price and specs are known.
+async fn quick_sizes_for(app: &App, symbol: &Symbol, exchange: &ExchangeId, price: Option<f64>) -> (Vec<QuickSize>, u32) {
+    let leverage = app.positions.iter()
+        .filter(|position| &position.exchange == exchange && position.symbol().ok().as_ref() == Some(symbol))
+        .find_map(|position| position.leverage)
+        .unwrap_or_else(|| app.default_leverage(exchange));
+    let spec = app.aggregator.market_spec(exchange, symbol).await;
+    let sizes = match (app.router.free_collateral(exchange), spec, price) {
+        (Some(collateral), Some(spec), Some(price)) => analytics::quick_sizes(
+            collateral,
+            leverage as f64,
+            &spec,
+            price,
+            min_order_notional(exchange),
+            &app.quick_size_percents,
+        ),
+        _ => Vec::new(),
+    };
+    (sizes, leverage)
+}
+
+// Price the order is sized at: its limit, else the touch it would take
+async fn order_quote(app: &App, exchange: &ExchangeId, symbol: &Symbol, request: &TradeRequest, book: Option<&OrderBook>) -> Quote {
+    let touch = book
+        .and_then(|book| if request.is_buy { book.asks.first() } else { book.bids.first() })
+        .map(|level| level.price);
+    Quote {
+        price: request.price.or(touch).unwrap_or(0.0),
+        size_step: app.aggregator.market_spec(exchange, symbol).await.and_then(|spec| spec.size_step()),
+    }
+}
+
+type TwapSetup = (TwapExecutor, TwapHandle, tokio::sync::mpsc::UnboundedReceiver<TwapEvent>);
+
+// Market TWAP setup, prompted for over `screen`. The whole order is
+// confirmed here; slices go out unconfirmed, so each must stay under the
+// first tier.
+async fn read_twap(app: &App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, screen: &dyn Fn(&mut ratatui::Frame<'_>), symbol: &Symbol, exchange: &ExchangeId, mid_price: Option<f64>) -> Result<Option<TwapSetup>> {
+    let styles = &app.styles;
+    let title = format!("TWAP {} on {}", symbol, exchange);
+    let Some(is_buy) = ask(terminal, styles, screen, &title, "Buy or sell? (b/s): ", buy_or_sell)? else { return Ok(None) };
+    let context = AmountContext {
+        symbol,
+        price: mid_price,
+        free_collateral: app.router.free_collateral(exchange),
+        leverage: 1.0,
+    };
+    let label = format!("Total amount (25k, 0.5{}): ", symbol.base().to_lowercase());
+    let parse = |input: &str| parse_usd_value(input, &context).map(|(_, usd)| usd).map_err(|e| e.to_string());
+    let Some(usd_value) = ask(terminal, styles, screen, &title, &label, parse)? else { return Ok(None) };
+    let Some(slices) = ask(terminal, styles, screen, &title, "Slices: ", positive_integer)? else { return Ok(None) };
+    let Some(interval) = ask(terminal, styles, screen, &title, "Seconds between slices: ", positive_integer)? else { return Ok(None) };
+    let interval = Duration::from_secs(interval.into());
+    let Some(leverage) = ask(terminal, styles, screen, &title, "Enter leverage [1]: ", or_default(1))? else { return Ok(None) };
+    let cross_margin = if *exchange == ExchangeId::Hyperliquid {
+        let Some(cross) = ask(terminal, styles, screen, &title, "Cross margin? (y/n): ", yes_no(None))? else { return Ok(None) };
+        Some(cross)
+    } else {
+        Some(true)
+    };
+
+    let request = TradeRequest {
+        asset: symbol.clone(),
+        order_type: OrderType::Market,
+        is_buy,
+        usd_value,
+        price: None,
+        leverage,
+        reduce_only: false,
+        cross_margin,
+        strategy_id: None,
+        max_slippage_bps: None,
+        post_only: false,
+        time_in_force: None,
+        good_til_secs: None,
+        cloid: None,
+    };
+    let (executor, handle, events) = TwapExecutor::new(exchange.clone(), request.clone(), slices, interval)?;
+    let quote = Quote { price: mid_price.unwrap_or(0.0), size_step: None };
+    let slice_usd = executor.slice_request().usd_value;
+    if app.router.review_order(executor.slice_request(), &quote).0 != ConfirmationTier::None {
+        return Err(anyhow::anyhow!("Slices of ${:.2} would each need confirmation; use more slices", slice_usd));
+    }
+
+    let mut detail = format!("{} slices of ${:.2} every {}s", slices, slice_usd, interval.as_secs());
+    let (tier, notional) = app.router.review_order(&request, &quote);
+    if tier != ConfirmationTier::None {
+        detail.push_str(&margin_comparison(app, symbol, exchange, notional).await);
+    }
+    // Any tier is asked at least as a dialog, since the run can't be undone
+    let tier = tier.max(ConfirmationTier::Dialog);
+    Ok(confirm_order(terminal, styles, screen, tier, notional, &detail)?.map(|_| (executor, handle, events)))
+}
+
+// Run a TWAP with a progress screen: p pauses or resumes, c cancels. Returns
+// the outcome for the trading screen's log.
+async fn run_twap(app: &mut App, symbol: &Symbol, exchange: &ExchangeId, (executor, handle, mut events): TwapSetup) -> Result<String> {
+    let side = if executor.slice_request().is_buy { "buy" } else { "sell" };
+    let title = format!("TWAP {} {} on {}", side, symbol, exchange);
+    let mut progress = None;
+    let mut last_error = None;
+    let run = executor.run(&mut app.router);
+    tokio::pin!(run);
+
+    loop {
+        tokio::select! {
+            (outcome, last) = &mut run => {
+                return Ok(match outcome {
+                    TwapOutcome::Completed => format!("{} done: {}", title, last.describe()),
+                    TwapOutcome::Cancelled => format!("{} cancelled: {}", title, last.describe()),
+                    TwapOutcome::Failed(error) => format!("{} stopped after repeated failures: {}\n{}", title, last.describe(), error),
+                });
+            }
+            _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+        }
+
+        while let Ok(event) = events.try_recv() {
+            match event {
+                TwapEvent::SliceSent { progress: latest, .. } => progress = Some(latest),
+                TwapEvent::SliceFailed { index, error, progress: latest } => {
+                    last_error = Some(format!("Slice {} failed ({} in a row): {}", index + 1, latest.consecutive_failures, error));
+                    progress = Some(latest);
+                }
+                TwapEvent::Finished { .. } => {}
+            }
+        }
+
+        let paused = handle.is_paused();
+        if let Ok(mut terminal) = app.terminal.try_lock() {
+            terminal.draw(|f| twap_ui(f, &title, progress.as_ref(), paused, last_error.as_deref(), &app.styles))?;
+        }
+
+        if event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                match key.code {
+                    KeyCode::Char('p') if paused => handle.resume(),
+                    KeyCode::Char('p') => handle.pause(),
+                    KeyCode::Char('c') | KeyCode::Esc => handle.cancel(),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn twap_ui(f: &mut ratatui::Frame<'_>, title: &str, progress: Option<&TwapProgress>, paused: bool, last_error: Option<&str>, styles: &Styles) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(f.area());
+
+    let percent = progress.map_or(0.0, TwapProgress::percent);
+    let gauge = Gauge::default()
+        .block(Block::default().borders(Borders::ALL).title(title.to_string()))
+        .ratio((percent / 100.0).clamp(0.0, 1.0))
+        .label(format!("{:.0}%{}", percent, if paused { " (paused)" } else { "" }));
+    f.render_widget(gauge, rows[0]);
+
+    let mut lines = vec![Line::from(progress.map_or_else(|| "Sending the first slice".to_string(), TwapProgress::describe))];
+    if let Some(error) = last_error {
+        lines.push(Line::styled(error.to_string(), styles.warn));
+    }
+    lines.push(Line::styled(format!("p {} · c cancel", if paused { "resume" } else { "pause" }), styles.muted));
+    let details = Paragraph::new(lines)
+        .wrap(ratatui::widgets::Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(details, rows[1]);
+}
+
+// Asks over `screen`, with `detail` above the prompt, for whatever the
+// confirmation tier wants. None means the user declined.
+fn confirm_order(terminal: &mut Terminal<CrosstermBackend<Stdout>>, styles: &Styles, screen: &dyn Fn(&mut ratatui::Frame<'_>), tier: ConfirmationTier, notional: f64, detail: &str) -> Result<Option<Confirmation>> {
+    Ok(match tier {
+        ConfirmationTier::None => Some(Confirmation::None),
+        ConfirmationTier::Dialog => {
+            let label = format!("Place ${:.2} order? (y/n): ", notional);
+            confirm(terminal, styles, screen, "Confirm order", detail, &label)?.then_some(Confirmation::Dialog)
+        }
+        ConfirmationTier::TypedConfirmation => {
+            let label = format!("Large order: type the amount ({:.2}) to confirm: $", notional);
+            let prompt = InputPrompt::new("Confirm order", &label).with_detail(detail);
+            ask_with(terminal, styles, screen, prompt, required)?.map(Confirmation::Typed)
+        }
+    })
+}
+
+// What each venue would hold as margin for the order, as lines for its
+// confirmation prompt; empty without margin data
+async fn margin_comparison(app: &App, symbol: &Symbol, exchange: &ExchangeId, notional: f64) -> String {
+    let comparison = app.aggregator.margin_comparison(symbol, notional).await;
+    if comparison.venues.is_empty() {
+        return String::new();
+    }
+    let mut table = Table::new(&["Venue", "Max leverage", "Initial", "Maintenance", ""]);
+    for venue in &comparison.venues {
+        let requirement = &venue.requirement;
+        let mut notes = Vec::new();
+        if &venue.exchange == exchange {
+            notes.push("this order");
+        }
+        if requirement.tiered {
+            notes.push("higher tier");
+        }
+        table.row(vec![
+            venue.exchange.to_string(),
+            format!("{:.0}x", requirement.max_leverage),
+            format!("${:.2}", requirement.initial_margin),
+            format!("${:.2}", requirement.maintenance_margin),
+            notes.join(", "),
+        ]);
+    }
+    for missing in &comparison.missing {
+        table.row(vec![missing.to_string(), "-".to_string(), "-".to_string(), "-".to_string(), "no margin data".to_string()]);
+    }
+    let mut text = format!("\n{}", table.render(true));
+    if let (Some(cheapest), Some(excess)) = (comparison.cheapest(), comparison.excess(exchange)) {
+        if excess >= 0.01 {
+            text.push_str(&format!("{} needs ${:.2} less initial margin", cheapest.exchange, excess));
+        }
+    }
+    text
+}
+
+// Mirror mode placement over `screen`: preview every leg, confirm them
+// together, place them and offer to retry a leg that failed. Returns the
+// message for the log pane.
+async fn place_mirrored(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, screen: &dyn Fn(&mut ratatui::Frame<'_>), symbol: &Symbol, exchange: &ExchangeId, request: TradeRequest, book: Option<&OrderBook>) -> Result<String> {
+    let styles = app.styles.clone();
+    let mut legs = Vec::new();
+    for venue in app.router.mirror_venues(exchange) {
+        let venue_book = if &venue == exchange { None } else { app.aggregator.get_exchange_orderbook(&venue, symbol).await.ok() };
+        let quote = order_quote(app, &venue, symbol, &request, venue_book.as_ref().or(book)).await;
+        legs.push((venue, quote, Confirmation::None));
+    }
+    let mut mirrored = MirroredOrder::new(request, legs);
+
+    let mut preview = "Mirrored order:".to_string();
+    let mut tier = ConfirmationTier::None;
+    for leg in &mirrored.legs {
+        preview.push_str(&format!("\n  {}", leg.preview(&mirrored.request)));
+        tier = tier.max(app.router.review_order(&mirrored.request, &leg.quote).0);
+    }
+    // One dialog covers every leg; a typed tier is typed per leg
+    let confirmed = match tier {
+        ConfirmationTier::None => true,
+        ConfirmationTier::Dialog => confirm(terminal, &styles, screen, "Mirrored order", &preview, "Place both legs? (y/n): ")?,
+        ConfirmationTier::TypedConfirmation => {
+            let mut confirmed = true;
+            for leg in mirrored.legs.iter_mut() {
+                let (leg_tier, notional) = app.router.review_order(&mirrored.request, &leg.quote);
+                let detail = format!("{}\n{}:", preview, leg.exchange);
+                match confirm_order(terminal, &styles, screen, leg_tier.max(ConfirmationTier::Dialog), notional, &detail)? {
+                    Some(confirmation) => leg.confirmation = confirmation,
+                    None => {
+                        confirmed = false;
+                        break;
+                    }
+                }
+            }
+            confirmed
+        }
+    };
+    if !confirmed {
+        return Ok("Mirrored order cancelled".to_string());
+    }
+    if tier == ConfirmationTier::Dialog {
+        for leg in mirrored.legs.iter_mut() {
+            leg.confirmation = Confirmation::Dialog;
+        }
+    }
+
+    let operation = app.operation.clone();
+    run_with_status(&operation, "placing mirrored order", mirrored.place(&mut app.router, false)).await;
+    while !mirrored.is_complete() {
+        if !confirm(terminal, &styles, screen, "Mirrored order", &mirrored.describe(), "Retry the failed leg? (y/n): ")? {
+            break;
+        }
+        run_with_status(&operation, "retrying mirrored leg", mirrored.retry_failed(&mut app.router)).await;
+    }
+    Ok(format!("Mirrored order {}:\n{}", short_id(mirrored.group_id), mirrored.describe()))
+}
+
+// What a market order would fill at on `exchange`, for the confirmations
+// after it. Slippage over the configured limit, or a book too thin for the
+// whole order, needs a yes to go ahead (None when refused); no estimate at
+// all doesn't block.
+async fn confirm_market_impact(app: &App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, screen: &dyn Fn(&mut ratatui::Frame<'_>), exchange: &ExchangeId, symbol: &Symbol, request: &TradeRequest) -> Result<Option<String>> {
+    let impact = match app.aggregator.estimate_market_impact(exchange, symbol, request.usd_value, request.is_buy).await {
+        Ok(impact) => impact,
+        Err(e) => return Ok(Some(format!("No fill estimate: {}", e))),
+    };
+    let estimate = format!(
+        "Estimated fill on {}: avg {:.4}, worst {:.4}, {:.1}bps slippage over {} levels",
+        exchange, impact.avg_price, impact.worst_price, impact.slippage_bps, impact.levels_consumed
+    );
+    let warning = if !impact.fully_fillable {
+        format!("The {} book can't fill the whole ${:.2}", exchange, request.usd_value)
+    } else if impact.slippage_bps > app.max_slippage_bps {
+        format!("Slippage is over the {:.0}bps limit", app.max_slippage_bps)
+    } else {
+        return Ok(Some(estimate));
+    };
+    let detail = format!("{}\n{}", estimate, warning);
+    Ok(confirm(terminal, &app.styles, screen, "Market impact", &detail, "Place anyway? (y/n): ")?.then_some(estimate))
+}
+
+// Ask before resending an order identical to one just placed; true forces it
+fn confirm_duplicate(app: &App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, screen: &dyn Fn(&mut ratatui::Frame<'_>), exchange: &ExchangeId, request: &TradeRequest) -> Result<bool> {
+    match app.router.check_duplicate(exchange, request) {
+        Ok(()) => Ok(false),
+        Err(e) => confirm(terminal, &app.styles, screen, "Duplicate order", &e.to_string(), "Place it anyway? (y/n): "),
+    }
+}
+
+async fn run_command(args: &[String], config: AggregatorConfig, plain: bool) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["notify", "test"] => {
+            let notifier = Notifier::from_config(&config, Default::default());
+            if notifier.is_empty() {
+                return Err(anyhow::anyhow!("No notification sinks configured; set HL_DISCORD_WEBHOOK_URL"));
+            }
+            let failed = notifier.notify(&Notification::test()).await;
+            if failed.is_empty() {
+                println!("Test notification sent");
+                Ok(())
+            } else {
+                for (sink, e) in &failed {
+                    eprintln!("{}: {}", sink, e);
+                }
+                Err(anyhow::anyhow!("{} notification sink(s) failed", failed.len()))
+            }
+        }
+        ["funding", "backfill", rest @ ..] => funding_backfill(rest, &config, plain).await,
+        ["funding", "scan", rest @ ..] => funding_scan(rest, config, plain).await,
+        ["pnl", rest @ ..] => pnl_command(rest, config.testnet, plain).await,
+        ["stats", "session"] => stats_session(),
+        ["import-history", rest @ ..] => import_history(rest, config.testnet, plain).await,
+        _ => Err(anyhow::anyhow!(
+            "Unknown command {:?}. Usage: hl_aggregator [--plain] [notify test | funding backfill SYMBOL... [--days N] | funding scan SYMBOL... [--min APR] | pnl [--group-by asset,strategy] [--range 30d] [--json] | stats session | import-history --since YYYY-MM-DD]",
+            args.join(" ")
+        )),
+    }
+}
+
+// The running or last session's stats, as the TUI last saved them
+fn stats_session() -> Result<()> {
+    let path = SessionStats::default_path()?;
+    if !path.exists() {
+        println!("No session recorded yet");
+        return Ok(());
+    }
+    let stats = SessionStats::load(&path)?;
+    println!("{}", stats.summary(stats.saved_ms.unwrap_or_else(|| chrono::Utc::now().timestamp_millis())));
+    Ok(())
+}
+
+// Bulk-populate the funding store, e.g. `funding backfill BTC ETH --days 30`
+async fn funding_backfill(args: &[&str], config: &AggregatorConfig, plain: bool) -> Result<()> {
+    let mut days = config.funding_retention_days;
+    let mut symbols = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if *arg == "--days" {
+            days = args.next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("--days needs a number"))?;
+        } else {
+            symbols.push(Symbol::parse_user_input(arg)?);
+        }
+    }
+    if symbols.is_empty() {
+        return Err(anyhow::anyhow!("Usage: hl_aggregator funding backfill SYMBOL... [--days N]"));
+    }
+
+    let mut store = FundingStore::load(FundingStore::default_path()?);
+    let window = Duration::from_secs(days * 24 * 60 * 60);
+    let now = chrono::Utc::now().timestamp_millis();
+
+    let mut table = Table::new(&["Exchange", "Symbol", "New points", "Status"]);
+    for symbol in &symbols {
+        for exchange in ExchangeId::built_in() {
+            let (added, status) = match funding::top_up(&mut store, &exchange, symbol, window, now, config.testnet).await {
+                Ok(added) => (added.to_string(), "ok".to_string()),
+                Err(e) => ("-".to_string(), e.to_string()),
+            };
+            table.row(vec![exchange.to_string(), symbol.to_string(), added, status]);
+        }
+    }
+    print!("{}", table.render(plain));
+
+    let retention = Duration::from_secs(config.funding_retention_days.max(days) * 24 * 60 * 60);
+    store.prune(retention, now);
+    Ok(store.save()?)
+}
+
+// Cross-venue funding spreads, e.g. `funding scan BTC ETH SOL --min 5`
+async fn funding_scan(args: &[&str], config: AggregatorConfig, plain: bool) -> Result<()> {
+    let mut min_spread = 0.0;
+    let mut symbols = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if *arg == "--min" {
+            min_spread = args.next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("--min needs an APR percent"))?;
+        } else {
+            symbols.push(Symbol::parse_user_input(arg)?);
+        }
+    }
+    if symbols.is_empty() {
+        return Err(anyhow::anyhow!("Usage: hl_aggregator funding scan SYMBOL... [--min APR]"));
+    }
+
+    let mut aggregator = DerivativesAggregator::new(config).await?;
+    let spreads = aggregator.find_funding_spreads(&symbols, min_spread).await;
+    aggregator.shutdown().await;
+
+    let mut table = Table::new(&["Symbol", "Long", "Short", "Long rate", "Short rate", "APR"]);
+    for spread in &spreads {
+        table.row(vec![
+            spread.symbol.to_string(),
+            spread.long_venue.to_string(),
+            spread.short_venue.to_string(),
+            format!("{:.4}%", spread.long_rate * 100.0),
+            format!("{:.4}%", spread.short_rate * 100.0),
+            format!("{:.2}%", spread.annualized_spread),
+        ]);
+    }
+    print!("{}", table.render(plain));
+    if spreads.len() < symbols.len() {
+        println!("{} of {} symbols skipped: not listed on two venues or under {}% APR", symbols.len() - spreads.len(), symbols.len(), min_spread);
+    }
+    Ok(())
+}
+
+// PnL attribution, e.g. `pnl --group-by exchange,asset --range 30d --json`
+async fn pnl_command(args: &[&str], testnet: bool, plain: bool) -> Result<()> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut range = PnlRange::parse("30d", now).map_err(anyhow::Error::msg)?;
+    let mut group_by = vec![GroupBy::Exchange];
+    let mut json = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--group-by" => {
+                let value = args.next().ok_or_else(|| anyhow::anyhow!("--group-by needs a grouping"))?;
+                group_by = pnl::parse_group_by(value).map_err(anyhow::Error::msg)?;
+            }
+            "--range" => {
+                let value = args.next().ok_or_else(|| anyhow::anyhow!("--range needs a range, e.g. 30d"))?;
+                range = PnlRange::parse(value, now).map_err(anyhow::Error::msg)?;
+            }
+            "--json" => json = true,
+            other => return Err(anyhow::anyhow!("Unknown pnl option '{}'", other)),
+        }
+    }
+
+    // Reads history only, so never contend with a running instance for the wallet
+    let wallet_manager = WalletManager::with_mode(AccessMode::ReadOnly).await?.with_testnet(testnet);
+    let hyperliquid_service = HyperliquidService::new(&wallet_manager, testnet).await?;
+    let journal = Journal::open_with_mode(Journal::default_path()?, AccessMode::ReadOnly)?;
+    let router = TradingRouter::new(hyperliquid_service, wallet_manager, journal);
+    let report = pnl::attribution(&router.pnl_entries(range.start).await?, range, &group_by);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    let mut table = Table::new(&["Group", "Price", "Funding", "Fees", "Net"]);
+    let amounts = |node: &pnl::AttributionNode| [node.price, node.funding, node.fees, node.net()].map(|value| format!("{:.2}", value));
+    for (depth, node) in report.total.rows() {
+        let mut row = vec![format!("{}{}", "  ".repeat(depth), node.key)];
+        row.extend(amounts(node));
+        table.row(row);
+    }
+    let mut total = vec!["Total".to_string()];
+    total.extend(amounts(&report.total));
+    table.row(total);
+    print!("{}", table.render(plain));
+    Ok(())
+}
+
+// Journal venue fill history, e.g. `import-history --since 2024-01-01`
+async fn import_history(args: &[&str], testnet: bool, plain: bool) -> Result<()> {
+    let since = match args {
+        ["--since", date] => history::parse_since(date).map_err(anyhow::Error::msg)?,
+        _ => return Err(anyhow::anyhow!("Usage: hl_aggregator import-history --since YYYY-MM-DD")),
+    };
+
+    // Writes the journal but never trades, so the wallet stays read-only
+    let wallet_manager = WalletManager::with_mode(AccessMode::ReadOnly).await?.with_testnet(testnet);
+    let hyperliquid_service = HyperliquidService::new(&wallet_manager, testnet).await?;
+    let journal = Journal::open(Journal::default_path()?)?;
+    let mut router = TradingRouter::new(hyperliquid_service, wallet_manager, journal);
+    let results = router.import_history(since, |exchange, count| eprintln!("{}: {} fills fetched", exchange, count)).await;
+
+    let mut table = Table::new(&["Exchange", "New fills", "External", "Status"]);
+    for (exchange, result) in results {
+        let row = match result {
+            Ok(journaled) => {
+                let external = journaled.iter().filter(|entry| entry.origin == OrderOrigin::External).count();
+                vec![exchange.to_string(), journaled.len().to_string(), external.to_string(), "ok".to_string()]
+            }
+            Err(e) => vec![exchange.to_string(), "-".to_string(), "-".to_string(), e.to_string()],
+        };
+        table.row(row);
+    }
+    print!("{}", table.render(plain));
+    Ok(())
+}
+
+fn menu_title(app: &App) -> String {
+    let mut title = "Menu".to_string();
+    if app.router.wallet_manager.is_read_only() {
+        title.push_str(" [READ-ONLY]");
+    }
+    for (sink, _) in app.aggregator.health.failing_sinks() {
+        title.push_str(&format!(" [{} failing]", sink));
+    }
+    if let Some(switch) = &app.dead_mans_switch {
+        title.push_str(&if switch.is_armed() {
+            format!(" [DMS armed {}s]", switch.timeout().as_secs())
+        } else {
+            " [DMS not armed]".to_string()
+        });
+    }
+    if let Some(notice) = &app.notice {
+        title.push_str(&format!(" - {}", notice));
+    }
+    title
+}
+
+fn ui(f: &mut ratatui::Frame<'_>, app: &App) {
+    // Always on top on testnet, so it's never taken for the real account
+    let mut area = f.area();
+    if app.router.hyperliquid_service.is_testnet() {
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(area);
+        let banner = Paragraph::new("TESTNET - orders go to the venues' test networks with test funds")
+            .style(app.styles.warn.add_modifier(Modifier::BOLD | Modifier::REVERSED))
+            .alignment(ratatui::layout::Alignment::Center);
+        f.render_widget(banner, split[0]);
+        area = split[1];
+    }
+    // The drawdown guard's banner sits above everything else once it has fired
+    let area = match &app.drawdown_banner {
+        Some(banner) => {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(4), Constraint::Min(0)])
+                .split(area);
+            let banner = Paragraph::new(banner.as_str())
+                .style(app.styles.warn)
+                .wrap(ratatui::widgets::Wrap { trim: true })
+                .block(Block::default().borders(Borders::ALL).border_style(app.styles.warn).title("DRAWDOWN GUARD TRIGGERED"));
+            f.render_widget(banner, split[0]);
+            split[1]
+        }
+        None => area,
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(5),   // Portfolio
+            Constraint::Length(3),   // Menu
+            Constraint::Length(11),  // Market Summaries
+            Constraint::Min(0),      // Selected Exchange Data (Orderbook)
+        ])
+        .split(area);
+
+    let portfolio = Paragraph::new(app.portfolio.lines().join("\n"))
+        .wrap(ratatui::widgets::Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title("Portfolio"));
+    f.render_widget(portfolio, chunks[0]);
+
+    // Menu
+    let menu = Paragraph::new("1. View Dydx  2. View Hyperliquid  3. View Positions  4. View Open Orders  5. Change Symbol  6. Place Trade  7. Manage Wallets  8. Exit  9. Alerts  0. Closed Trades  h. Trade History  s. Strategies  p. PnL  S. Session  K. Kill switch  :. Palette")
+        .block(Block::default().borders(Borders::ALL).title(menu_title(app)));
+    f.render_widget(menu, chunks[1]);
+
+    // Market Summaries - Split horizontally for each exchanges
+    let summary_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(50),  // dYdX
+            Constraint::Percentage(50),  // Hyperliquid
+        ])
+        .split(chunks[2]);
 
     // dYdX Summary
     let dydx_summary = match &app.dydx_summary {
         Some(summary) => format!(
-            "dYdX - {}\nPrice: ${}\n24h Volume: {}\nMax Leverage: {}\nFunding: {:.4}%",
+            "dYdX - {}\nPrice: ${}\n{}\n24h Volume: {}\nMax Leverage: {}\nFunding: {:.4}%",
+            app.symbol,
+            summary.price,
+            app.fair_price_line(&ExchangeId::Dydx),
+            format_volume(summary.volume_24h),
+            format_leverage(app.dydx_leverage.as_ref()),
+            summary.funding_rate * 100.0
+        ),
+        None => match app.delisting(&ExchangeId::Dydx) {
+            Some(delisting) => format!("dYdX - {} [DELISTED]\n{}", app.symbol, delisting.describe(&ExchangeId::Dydx)),
+            None if !app.aggregator.has_exchange(&ExchangeId::Dydx) => format!("dYdX - {}\nDisabled (not in HL_EXCHANGES)", app.symbol),
+            None => format!("dYdX - {}\nNo data available", app.symbol),
+        },
+    };
+    
+    let dydx_title = with_clock_skew(with_transport("dYdX Market", app.aggregator.health.transport(&ExchangeId::Dydx)), app, &ExchangeId::Dydx);
+    let dydx_block = venue_block(&dydx_title, &app.aggregator.health.status(&ExchangeId::Dydx), &app.styles);
+    let dydx_area = dydx_block.inner(summary_chunks[0]);
+    f.render_widget(dydx_block, summary_chunks[0]);
+    let dydx_history = app.aggregator.price_history(&ExchangeId::Dydx, &app.symbol, PRICE_HISTORY_SAMPLES);
+    render_summary_with_sparkline(f, dydx_area, dydx_summary, &dydx_history, &app.styles);
+
+    // Hyperliquid Summary
+    let hl_summary = match &app.hl_summary {
+        Some(summary) => format!(
+            "Hyperliquid - {}\nPrice: ${}\n{}\n24h Volume: {}\nMax Leverage: {}\nFunding: {:.4}%",
             app.symbol,
             summary.price,
+            app.fair_price_line(&ExchangeId::Hyperliquid),
             format_volume(summary.volume_24h),
-            app.dydx_leverage.map_or_else(|| "N/A".to_string(), |l| format!("{:.0}x", l)),
+            format_leverage(app.hl_leverage.as_ref()),
             summary.funding_rate * 100.0
         ),
-        None => format!("dYdX - {}\nNo data available", app.symbol)
+        None => match app.delisting(&ExchangeId::Hyperliquid) {
+            Some(delisting) => format!("Hyperliquid - {} [DELISTED]\n{}", app.symbol, delisting.describe(&ExchangeId::Hyperliquid)),
+            None if !app.aggregator.has_exchange(&ExchangeId::Hyperliquid) => format!("Hyperliquid - {}\nDisabled (not in HL_EXCHANGES)", app.symbol),
+            None => format!("Hyperliquid - {}\nNo data available", app.symbol),
+        },
     };
     
-    let dydx_widget = Paragraph::new(dydx_summary)
-        .block(Block::default().borders(Borders::ALL).title("dYdX Market"));
-    f.render_widget(dydx_widget, summary_chunks[0]);
+    let hl_title = with_clock_skew(with_transport("Hyperliquid Market", app.aggregator.health.transport(&ExchangeId::Hyperliquid)), app, &ExchangeId::Hyperliquid);
+    let hl_block = venue_block(&hl_title, &app.aggregator.health.status(&ExchangeId::Hyperliquid), &app.styles);
+    let hl_area = hl_block.inner(summary_chunks[1]);
+    f.render_widget(hl_block, summary_chunks[1]);
+    let hl_history = app.aggregator.price_history(&ExchangeId::Hyperliquid, &app.symbol, PRICE_HISTORY_SAMPLES);
+    render_summary_with_sparkline(f, hl_area, hl_summary, &hl_history, &app.styles);
+
+    // Orderbook (if an exchange is selected)
+    if let Some(orderbook) = &app.market_data.orderbook {
+        // Trade flow sits beside the book once the venue has printed
+        let book_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(chunks[3]);
+        let book_area = if app.trade_flow.is_some() { book_chunks[0] } else { chunks[3] };
+
+        let orderbook_title = with_transport(&format!("{} Orderbook", orderbook.exchange), app.aggregator.health.transport(&orderbook.exchange));
+        let orderbook_widget = Paragraph::new(orderbook_lines(orderbook, BOOK_DEPTH, &[], &app.styles))
+            .block(venue_block(&orderbook_title, &app.aggregator.health.status(&orderbook.exchange), &app.styles));
+        f.render_widget(orderbook_widget, book_area);
+
+        if let Some(flow) = &app.trade_flow {
+            let title = format!("{} Volume Profile", orderbook.exchange);
+            render_trade_flow(f, book_chunks[1], flow, &title, &app.styles);
+        }
+    } else if let Some(merged) = &app.merged_book {
+        let title = format!("Merged {} Orderbook", app.symbol);
+        render_merged_book(f, chunks[3], merged, &title, &app.styles);
+    }
+}
+
+// Pane title with the transport noted while a venue is polled over REST
+// Summary pane title with the clock skew appended once it passes the warn threshold
+fn with_clock_skew(title: String, app: &App, exchange: &ExchangeId) -> String {
+    match app.aggregator.health.venue(exchange).clock_skew {
+        Some(skew) if app.clock_policy.level(&skew) >= SkewLevel::Warn => format!("{} [CLOCK {}]", title, skew.describe()),
+        _ => title,
+    }
+}
+
+fn with_transport(title: &str, transport: Transport) -> String {
+    if transport.is_fallback() {
+        format!("{} [{}]", title, transport)
+    } else {
+        title.to_string()
+    }
+}
+
+// Summary text on top, the recent mid sparkline in the rows left below it
+fn render_summary_with_sparkline(f: &mut ratatui::Frame<'_>, area: Rect, summary: String, history: &[f64], styles: &Styles) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(0)])
+        .split(area);
+    f.render_widget(Paragraph::new(summary), rows[0]);
+    render_price_sparkline(f, rows[1], history, styles);
+}
+
+// Pane border for a venue, with a status banner while it isn't operational
+fn venue_block<'a>(title: &str, status: &VenueStatus, styles: &Styles) -> Block<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("{} {}", title, status.banner()).trim().to_string());
+    match status {
+        VenueStatus::Operational => block,
+        VenueStatus::Degraded(_) => block.border_style(styles.warn),
+        VenueStatus::Halted(_) => block.border_style(styles.alert),
+    }
+}
+
+fn format_leverage(leverage: Option<&(f64, Option<String>)>) -> String {
+    match leverage {
+        Some((max, Some(note))) => format!("{:.0}x {}", max, note),
+        Some((max, None)) => format!("{:.0}x", max),
+        None => "N/A".to_string(),
+    }
+}
+
+fn format_volume(volume: f64) -> String {
+    if volume >= 1_000_000_000.0 {
+        format!("${:.2}B", volume / 1_000_000_000.0)
+    } else if volume >= 1_000_000.0 {
+        format!("${:.2}M", volume / 1_000_000.0)
+    } else if volume >= 1_000.0 {
+        format!("${:.2}K", volume / 1_000.0)
+    } else {
+        format!("${:.2}", volume)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn trading_ui(f: &mut ratatui::Frame<'_>, symbol: &Symbol, exchange: &ExchangeId, status: &VenueStatus, orderbook: Option<&OrderBook>, alerts: &[&Alert], log_message: Option<&str>, quick: &[QuickSize], quick_leverage: u32, mirror: bool, styles: &Styles) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(70),  // Main content - reduced from Min(0)
+            Constraint::Percentage(30),  // Log area - increased from Length(3)
+        ])
+        .split(f.area());
+
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(30),  // Trading menu
+            Constraint::Percentage(70),  // Orderbook
+        ])
+        .split(chunks[0]);
+
+    // Trading Menu (existing code)
+    let menu_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),   // Title
+            Constraint::Length(13),  // Trading options
+            Constraint::Min(0),      // Quick sizes
+        ])
+        .split(main_chunks[0]);
+
+    // Title
+    let mut title = vec![Span::raw(format!("Trading {} on {}", symbol, exchange))];
+    if mirror {
+        title.push(Span::styled(" MIRROR", styles.warn));
+    }
+    let title = Paragraph::new(Line::from(title))
+        .block(venue_block("", status, styles))
+        .alignment(ratatui::layout::Alignment::Center);
+    f.render_widget(title, menu_chunks[0]);
+
+    // Trading Options
+    let options = Paragraph::new(
+        "1. Market Buy\n2. Market Sell\n3. Limit Buy\n4. Limit Sell\n5. Stop Market\n6. Take Profit\n7. Back to Main Menu\nt. TWAP\nl. Add Alert Line\nd. DOM Ladder\nm. Mirror Mode"
+    )
+    .block(Block::default().borders(Borders::ALL).title("Options"));
+    f.render_widget(options, menu_chunks[1]);
+
+    // Quick sizes, entered at the amount prompt
+    let quick_lines: Vec<Line> = if quick.is_empty() {
+        vec![Line::from("Waiting for collateral and market spec")]
+    } else {
+        quick.iter().enumerate()
+            .map(|(index, size)| {
+                let style = if size.unavailable.is_some() { styles.muted } else { Style::default() };
+                Line::styled(format!("{}. {}", index + 1, size.describe()), style)
+            })
+            .collect()
+    };
+    let quick_sizes = Paragraph::new(quick_lines)
+        .wrap(ratatui::widgets::Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title(format!("Quick Sizes @ {}x", quick_leverage)));
+    f.render_widget(quick_sizes, menu_chunks[2]);
+
+    // Orderbook with any alert lines beside the levels
+    if let Some(orderbook) = orderbook {
+        let orderbook_title = format!("{} Orderbook", orderbook.exchange);
+        let orderbook_widget = Paragraph::new(orderbook_lines(orderbook, BOOK_DEPTH, alerts, styles))
+            .block(Block::default().borders(Borders::ALL).title(orderbook_title));
+        f.render_widget(orderbook_widget, main_chunks[1]);
+    }
+
+    // Add log area with increased size
+    if let Some(message) = log_message {
+        let log = Paragraph::new(message)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Trade Log"))
+            .wrap(ratatui::widgets::Wrap { trim: true });  // Add text wrapping
+        f.render_widget(log, chunks[1]);
+    }
+}
+
+async fn manage_wallets(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    let mut overview = WalletOverview::default();
+    // Set whenever the wallets may have changed
+    let mut stale = true;
+
+    loop {
+        if stale {
+            // Initialize dYdX client before getting balance
+            if let Err(e) = app.router.wallet_manager.init_dydx_client().await {
+                tracing::warn!("dYdX client unavailable: {}", e);
+            }
+            overview.refresh(&app.router.wallet_manager).await;
+            stale = false;
+        }
+
+        let eth_address = app.router.wallet_manager.get_wallet().map(|wallet| format!("{:#x}", wallet.address()));
+        let dydx_address = app.router.wallet_manager.get_dydx_wallet()
+            .and_then(|wallet| wallet.account_offline(0).ok())
+            .map(|account| account.address().to_string());
+        let mut status_lines = overview.lines(eth_address, dydx_address);
+        status_lines.push(if app.router.wallet_manager.is_encrypted() {
+            "Key file: encrypted".to_string()
+        } else {
+            "Key file: UNENCRYPTED (7 to encrypt)".to_string()
+        });
+        for pending in app.router.wallet_manager.pending_bridges().unwrap_or_default() {
+            status_lines.push(format!("Bridge {} (5 to resume)", pending.describe()));
+        }
+        let status_text = status_lines.join("\n");
+        let screen = |f: &mut ratatui::Frame<'_>| draw_wallet_screen(f, &status_text);
+
+        terminal.draw(|f| screen(f))?;
+
+        // Retry only what failed while waiting for input
+        if !event::poll(Duration::from_secs(5))? {
+            if overview.has_failures() {
+                overview.retry_failed(&app.router.wallet_manager).await;
+            }
+            continue;
+        }
+
+        // Handle input
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Char('1') => {
+                    app.router.wallet_manager.create_eth_wallet().await?;
+                    stale = true;
+                }
+                KeyCode::Char('2') => {
+                    app.router.wallet_manager.import_eth_wallet().await?;
+                    stale = true;
+                }
+                KeyCode::Char('3') => {
+                    app.router.wallet_manager.create_dydx_wallet().await?;
+                    stale = true;
+                }
+                KeyCode::Char('4') => {
+                    app.router.wallet_manager.import_dydx_wallet().await?;
+                    stale = true;
+                }
+                KeyCode::Char('5') => {
+                    // Bridge USDC, with progress in a panel as it goes
+                    let title = "Bridge USDC to dYdX";
+                    let mut panel = MessagePanel::new(title);
+                    let pending = app.router.wallet_manager.pending_bridges().unwrap_or_default();
+                    if let Some(pending) = pending.first() {
+                        panel.push(&format!("Resuming bridge {}", pending.describe()));
+                        follow_bridge(&app.router.wallet_manager, terminal, &app.styles, &screen, &mut panel, &pending.burn_tx).await?;
+                        show(terminal, &app.styles, &screen, panel)?;
+                        stale = true;
+                        continue;
+                    }
+
+                    let Some(amount) = ask(terminal, &app.styles, &screen, title, "USDC amount to bridge: ", positive_number)? else { continue };
+                    panel.push(&format!("Initiating bridge of {} USDC to dYdX...", amount));
+                    draw_panel(terminal, &app.styles, &screen, &panel)?;
+                    let (progress, mut events) = tokio::sync::mpsc::channel(8);
+                    let bridge = app.router.wallet_manager.bridge_to_dydx(amount, Some(progress));
+                    // The sender goes with the bridge future, so this ends with it
+                    let follow = async {
+                        while let Some(event) = events.recv().await {
+                            panel.push(&event.describe());
+                            draw_panel(terminal, &app.styles, &screen, &panel)?;
+                        }
+                        Ok::<(), anyhow::Error>(())
+                    };
+                    let (bridged, drawn) = tokio::join!(bridge, follow);
+                    drawn?;
+                    match bridged {
+                        Ok(receipt) => {
+                            panel.push(&format!("\n{}", receipt.describe()));
+                            follow_bridge(&app.router.wallet_manager, terminal, &app.styles, &screen, &mut panel, &receipt.burn_tx).await?;
+                        }
+                        Err(e) => panel.push(&format!("\nBridge failed: {}", e)),
+                    }
+                    show(terminal, &app.styles, &screen, panel)?;
+                    stale = true;
+                },
+                KeyCode::Char('6') => {
+                    terminal.clear()?;
+                    stale |= archived_keys(app, terminal).await?;
+                    terminal.clear()?;
+                }
+                KeyCode::Char('7') => {
+                    let mut panel = MessagePanel::new("Encrypt Wallet File");
+                    let Some(message) = encrypt_wallet_file(&mut app.router.wallet_manager, terminal, &app.styles, &screen)? else { continue };
+                    panel.push(&message);
+                    show(terminal, &app.styles, &screen, panel)?;
+                }
+                KeyCode::Char('8') => {
+                    terminal.clear()?;
+                    stale |= named_wallets(app, terminal).await?;
+                    terminal.clear()?;
+                }
+                KeyCode::Char('d') | KeyCode::Char('D') => {
+                    let mut panel = MessagePanel::new("Deposit USDC to Hyperliquid");
+                    panel.push(&match deposit_to_hyperliquid(&app.router.wallet_manager, terminal, &app.styles, &screen).await {
+                        Ok(message) => message,
+                        Err(e) => format!("Deposit failed: {}", e),
+                    });
+                    show(terminal, &app.styles, &screen, panel)?;
+                    stale = true;
+                }
+                KeyCode::Char('w') | KeyCode::Char('W') => {
+                    let mut panel = MessagePanel::new("Withdraw from Hyperliquid");
+                    panel.push(&match withdraw_from_hyperliquid(app, terminal, &screen).await {
+                        Ok(message) => message,
+                        Err(e) => format!("Withdrawal failed: {}", e),
+                    });
+                    show(terminal, &app.styles, &screen, panel)?;
+                    stale = true;
+                }
+                KeyCode::Char('9') | KeyCode::Char('q') | KeyCode::Esc => {
+                    // Clear screen before exiting
+                    terminal.clear()?;
+                    break;
+                },
+                _ => {}
+            }
+        }
+    }
+
+    // Ensure terminal is properly reset before returning to main menu
+    terminal.clear()?;
+    terminal.draw(|f| {
+        // Draw empty frame to ensure clean state
+        let block = Block::default();
+        f.render_widget(block, f.area());
+    })?;
+
+    Ok(())
+}
+
+// The wallet management screen: balances and keys over the options menu
+fn draw_wallet_screen(f: &mut ratatui::Frame<'_>, status_text: &str) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(3),     // Title
+            Constraint::Length(15),    // Wallet Status
+            Constraint::Length(13),    // Options Menu
+            Constraint::Length(3),     // Input Prompt
+        ].as_ref())
+        .split(f.area());
+
+    // Title
+    let title = Paragraph::new("Wallet Management")
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(ratatui::layout::Alignment::Center);
+    f.render_widget(title, chunks[0]);
+
+    // Wallet Status
+    let status = Paragraph::new(status_text)
+        .block(Block::default().borders(Borders::ALL).title("Wallet Status"));
+    f.render_widget(status, chunks[1]);
+
+    // Options Menu
+    let options = Paragraph::new(
+        "1. Create New ETH Wallet\n\
+         2. Import Existing ETH Wallet\n\
+         3. Create New dYdX Wallet\n\
+         4. Import Existing dYdX Wallet\n\
+         5. Bridge USDC to dYdX\n\
+         6. Archived Keys\n\
+         7. Encrypt Wallet File\n\
+         8. Named Wallets\n\
+         9. Back to Main Menu\n\
+         D. Deposit USDC to Hyperliquid\n\
+         W. Withdraw from Hyperliquid to Arbitrum"
+    )
+    .block(Block::default().borders(Borders::ALL).title("Options"));
+    f.render_widget(options, chunks[2]);
+
+    // Input Prompt
+    let prompt = Paragraph::new("Enter choice (1-9, D, W): ")
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(prompt, chunks[3]);
+}
+
+/// Ask for a passphrase, twice, and encrypt the wallet file with it. An
+/// empty passphrase leaves it as it is; None when the user escapes.
+fn encrypt_wallet_file(wallet_manager: &mut WalletManager, terminal: &mut Terminal<CrosstermBackend<Stdout>>, styles: &Styles, screen: &dyn Fn(&mut ratatui::Frame<'_>)) -> Result<Option<String>> {
+    if wallet_manager.is_encrypted() {
+        return Ok(Some("The wallet file is already encrypted".to_string()));
+    }
+    let title = "Encrypt Wallet File";
+    let prompt = InputPrompt::new(title, "Passphrase (empty to leave keys unencrypted): ").masked();
+    let Some(passphrase) = ask_with(terminal, styles, screen, prompt, any_text)? else { return Ok(None) };
+    if passphrase.is_empty() {
+        return Ok(Some("Wallet file left unencrypted".to_string()));
+    }
+    let prompt = InputPrompt::new(title, "Repeat the passphrase: ").masked();
+    let Some(repeated) = ask_with(terminal, styles, screen, prompt, any_text)? else { return Ok(None) };
+    if repeated != passphrase {
+        return Ok(Some("Passphrases don't match; nothing was saved".to_string()));
+    }
+    Ok(Some(match wallet_manager.migrate_to_encrypted(&passphrase) {
+        Ok(()) => "Wallet file encrypted".to_string(),
+        Err(e) => format!("Encryption failed: {}", e),
+    }))
+}
+
+/// Ask for an amount and deposit it into Hyperliquid from the Arbitrum
+/// wallet, prompting over `screen`
+async fn deposit_to_hyperliquid(wallet_manager: &WalletManager, terminal: &mut Terminal<CrosstermBackend<Stdout>>, styles: &Styles, screen: &dyn Fn(&mut ratatui::Frame<'_>)) -> Result<String> {
+    wallet_manager.ensure_writable()?;
+    let title = "Deposit USDC to Hyperliquid";
+    let label = format!("USDC to deposit (minimum {:.2}): ", HL_MIN_DEPOSIT);
+    let Some(amount) = ask(terminal, styles, screen, title, &label, positive_number)? else { return Ok("Deposit cancelled".to_string()) };
+    let label = format!("Deposit {:.2} USDC into Hyperliquid? [y/N]: ", amount);
+    if ask(terminal, styles, screen, title, &label, yes_no(Some(false)))? != Some(true) {
+        return Ok("Deposit cancelled".to_string());
+    }
+    let mut panel = MessagePanel::new(title);
+    panel.push("Sending and waiting for Hyperliquid to credit it...");
+    draw_panel(terminal, styles, screen, &panel)?;
+    let deposit = wallet_manager.deposit_to_hyperliquid(amount).await?;
+    Ok(if deposit.credited {
+        format!("{:.2} USDC credited to Hyperliquid (tx {})", deposit.amount, deposit.transfer_tx)
+    } else {
+        format!("Deposit tx {} confirmed but not credited yet; check the account value again shortly", deposit.transfer_tx)
+    })
+}
+
+/// Ask for an amount and an Arbitrum address, defaulting to the wallet's
+/// own, and withdraw there from Hyperliquid, prompting over `screen`
+async fn withdraw_from_hyperliquid(app: &App, terminal: &mut Terminal<CrosstermBackend<Stdout>>, screen: &dyn Fn(&mut ratatui::Frame<'_>)) -> Result<String> {
+    let wallet_manager = &app.router.wallet_manager;
+    wallet_manager.ensure_writable()?;
+    let own = wallet_manager.get_wallet()
+        .map(|wallet| wallet.address())
+        .ok_or_else(|| anyhow::anyhow!("No ETH wallet configured"))?;
+    let state = app.router.hyperliquid_service.get_account_state().await?;
+    let title = "Withdraw from Hyperliquid";
+    let detail = format!("Withdrawable: ${:.2} (minimum ${:.2}, less a $1 fee)", state.withdrawable_balance(), HL_MIN_WITHDRAWAL);
+
+    let prompt = InputPrompt::new(title, "Amount in USD: ").with_detail(&detail);
+    let Some(amount) = ask_with(terminal, &app.styles, screen, prompt, positive_number)? else { return Ok("Withdrawal cancelled".to_string()) };
+    let label = format!("Destination on Arbitrum [{:#x}]: ", own);
+    let parse = |input: &str| match input {
+        "" => Ok(own),
+        input => input.parse::<Address>().map_err(|e| format!("Invalid address '{}': {}", input, e)),
+    };
+    let Some(destination) = ask(terminal, &app.styles, screen, title, &label, parse)? else { return Ok("Withdrawal cancelled".to_string()) };
+    let label = format!("Withdraw ${:.2} to {:#x}? [y/N]: ", amount, destination);
+    if ask(terminal, &app.styles, screen, title, &label, yes_no(Some(false)))? != Some(true) {
+        return Ok("Withdrawal cancelled".to_string());
+    }
+    Ok(match app.router.hyperliquid_service.withdraw(amount, destination).await? {
+        ExchangeResponseStatus::Ok(response) => format!("Withdrawal accepted ({}); the USDC reaches Arbitrum in a few minutes", response.response_type),
+        ExchangeResponseStatus::Err(message) => format!("Withdrawal rejected: {}", message),
+    })
+}
+
+/// Add a bridge's phases to `panel` as it moves towards being minted on
+/// dYdX, redrawing it over `screen`
+async fn follow_bridge(wallet_manager: &WalletManager, terminal: &mut Terminal<CrosstermBackend<Stdout>>, styles: &Styles, screen: &dyn Fn(&mut ratatui::Frame<'_>), panel: &mut MessagePanel, burn_tx: &str) -> Result<()> {
+    panel.push("Waiting for Circle's attestation and the USDC on dYdX...");
+    draw_panel(terminal, styles, screen, panel)?;
+    let (progress, mut events) = tokio::sync::mpsc::channel(8);
+    let wait = wallet_manager.wait_for_bridge_completion(burn_tx, Some(progress));
+    let follow = async {
+        while let Some(event) = events.recv().await {
+            panel.push(&event.describe());
+            draw_panel(terminal, styles, screen, panel)?;
+        }
+        Ok::<(), anyhow::Error>(())
+    };
+    let (waited, drawn) = tokio::join!(wait, follow);
+    drawn?;
+    panel.push(&match waited {
+        Ok(pending) if pending.phase == BridgePhase::Minted => format!("\n{:.2} USDC arrived on dYdX", pending.amount),
+        Ok(pending) => format!("\nStill {}; resume it from the wallet menu (5)", pending.phase.progress()),
+        Err(e) => format!("\nCould not follow the bridge: {}", e),
+    });
+    Ok(())
+}
+
+/// Keys replaced by earlier creates and imports, with restore and purge.
+/// Returns whether the active wallets changed.
+async fn archived_keys(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<bool> {
+    let mut status: Option<String> = None;
+    let mut restored = false;
+
+    loop {
+        let text = match app.router.wallet_manager.list_archived() {
+            Ok(archived) if archived.is_empty() => "No archived keys. Creating or importing a wallet archives the one it replaces.".to_string(),
+            Ok(archived) => archived.iter().rev()
+                .map(|key| format!("{:<5} {:<46} archived {}", key.kind.to_string(), key.address, timefmt::fmt_ts(key.archived_ms)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(e) => format!("Error reading wallet file: {}", e),
+        };
+
+        let screen = |f: &mut ratatui::Frame<'_>| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(1)
+                .constraints([
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ])
+                .split(f.area());
+
+            let list = Paragraph::new(text.as_str())
+                .block(Block::default().borders(Borders::ALL).title("Archived Keys"));
+            f.render_widget(list, chunks[0]);
+
+            let help = Paragraph::new(status.clone().unwrap_or_else(|| "r. Restore by address  p. Purge all  q. Back".to_string()))
+                .block(Block::default().borders(Borders::ALL));
+            f.render_widget(help, chunks[1]);
+        };
+        terminal.draw(|f| screen(f))?;
+
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Char('r') => {
+                    let Some(address) = ask(terminal, &app.styles, &screen, "Archived Keys", "Address to restore: ", required)? else { continue };
+                    status = Some(match app.router.wallet_manager.restore_archived(&address).await {
+                        Ok(()) => {
+                            restored = true;
+                            format!("Restored {}; the key it replaced was archived", address)
+                        }
+                        Err(e) => format!("Error restoring key: {}", e),
+                    });
+                },
+                KeyCode::Char('p') => {
+                    let label = format!("Type {} to confirm: ", wallet_store::PURGE_CONFIRMATION);
+                    let prompt = InputPrompt::new("Archived Keys", &label)
+                        .with_detail("This permanently deletes every archived key. Funds they control are lost unless backed up elsewhere.");
+                    let Some(typed) = ask_with(terminal, &app.styles, &screen, prompt, any_text)? else { continue };
+
+                    status = Some(match app.router.wallet_manager.purge_archived(&typed) {
+                        Ok(purged) => format!("Purged {} archived key(s)", purged),
+                        Err(e) => format!("Not purged: {}", e),
+                    });
+                },
+                KeyCode::Char('q') | KeyCode::Esc => break,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(restored)
+}
+
+// Saved wallets; a digit switches to that wallet. Returns whether the
+// active wallets changed.
+async fn named_wallets(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<bool> {
+    let mut status: Option<String> = None;
+    let mut switched = false;
+
+    loop {
+        let wallets = app.router.wallet_manager.list_wallets();
+        let text = match &wallets {
+            Ok(wallets) if wallets.is_empty() => "No named wallets. Add one with +; an existing active key is saved as \"default\".".to_string(),
+            Ok(wallets) => wallets.iter().enumerate()
+                .map(|(i, wallet)| format!(
+                    "{} {} {:<16} {:<5} {}",
+                    i + 1,
+                    if wallet.active { "*" } else { " " },
+                    wallet.name,
+                    wallet.kind.to_string(),
+                    wallet.address,
+                ))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(e) => format!("Error reading wallet file: {}", e),
+        };
+
+        let screen = |f: &mut ratatui::Frame<'_>| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(1)
+                .constraints([
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ])
+                .split(f.area());
+
+            let list = Paragraph::new(text.as_str())
+                .block(Block::default().borders(Borders::ALL).title("Named Wallets (* active)"));
+            f.render_widget(list, chunks[0]);
+
+            let help = Paragraph::new(status.clone().unwrap_or_else(|| "1-9. Switch  +. Add  -. Remove  q. Back".to_string()))
+                .block(Block::default().borders(Borders::ALL));
+            f.render_widget(help, chunks[1]);
+        };
+        terminal.draw(|f| screen(f))?;
+
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Char(c @ '1'..='9') => {
+                    let index = c as usize - '1' as usize;
+                    let Some(wallet) = wallets.as_ref().ok().and_then(|wallets| wallets.get(index)).cloned() else { continue };
+                    status = Some(match app.router.wallet_manager.set_active(&wallet.name).await {
+                        Ok(entry) => {
+                            switched = true;
+                            if entry.kind == KeyKind::Eth {
+                                // Hyperliquid signs with the ETH key, so its client follows the switch
+                                app.router.hyperliquid_service = HyperliquidService::new(&app.router.wallet_manager, app.router.hyperliquid_service.is_testnet()).await?
+                                    .with_meta(app.aggregator.hl_meta.clone());
+                            }
+                            format!("{} wallet is now {} ({})", entry.kind, entry.name, entry.address)
+                        }
+                        Err(e) => format!("Error switching wallet: {}", e),
+                    });
+                }
+                KeyCode::Char('+') => {
+                    let title = "Named Wallets";
+                    let Some(kind) = ask(terminal, &app.styles, &screen, title, "Kind (eth/dydx): ", key_kind)? else { continue };
+                    let Some(name) = ask(terminal, &app.styles, &screen, title, "Name: ", required)? else { continue };
+                    let label = if kind == KeyKind::Dydx { "Mnemonic: " } else { "Private key (hex): " };
+                    let prompt = InputPrompt::new(title, label).masked();
+                    let Some(secret) = ask_with(terminal, &app.styles, &screen, prompt, required)? else { continue };
+
+                    status = Some(match app.router.wallet_manager.add_wallet(&name, kind, &secret).await {
+                        Ok(()) => {
+                            switched = true;
+                            format!("Saved {} wallet {}", kind, name)
+                        }
+                        Err(e) => format!("Not saved: {}", e),
+                    });
+                }
+                KeyCode::Char('-') => {
+                    let Some(name) = ask(terminal, &app.styles, &screen, "Named Wallets", "Name of the wallet to remove: ", required)? else { continue };
+
+                    status = Some(match app.router.wallet_manager.remove_wallet(&name) {
+                        Ok(()) => format!("Removed {}; its key is in Archived Keys", name),
+                        Err(e) => format!("Not removed: {}", e),
+                    });
+                }
+                KeyCode::Char('q') | KeyCode::Esc => break,
+                _ => {}
+            }
+        }
+    }
 
-    // Hyperliquid Summary
-    let hl_summary = match &app.hl_summary {
-        Some(summary) => format!(
-            "Hyperliquid - {}\nPrice: ${}\n24h Volume: {}\nMax Leverage: {}\nFunding: {:.4}%",
-            app.symbol,
-            summary.price,
-            format_volume(summary.volume_24h),
-            app.hl_leverage.map_or_else(|| "N/A".to_string(), |l| format!("{:.0}x", l)),
-            summary.funding_rate * 100.0
-        ),
-        None => format!("Hyperliquid - {}\nNo data available", app.symbol)
+    Ok(switched)
+}
+
+fn key_kind(input: &str) -> Result<KeyKind, String> {
+    match input.to_lowercase().as_str() {
+        "eth" => Ok(KeyKind::Eth),
+        "dydx" => Ok(KeyKind::Dydx),
+        _ => Err("Expected eth or dydx".to_string()),
+    }
+}
+
+// Funding on each open position: settled since its episode opened, and a
+// day's worth at the venue's current rate
+async fn load_position_funding(app: &App) -> Vec<PositionFunding> {
+    let episodes = app.router.position_episodes().await;
+    let opened_at = |position: &Position| episodes.iter()
+        .find(|episode| !episode.is_closed() && episode.exchange == position.exchange && episode.asset == position.asset)
+        .map(|episode| episode.opened_at);
+    let payments = match app.market_data.positions.iter().filter_map(opened_at).min() {
+        Some(since) => app.router.funding_payments(since).await,
+        None => Vec::new(),
     };
-    
-    let hl_widget = Paragraph::new(hl_summary)
-        .block(Block::default().borders(Borders::ALL).title("Hyperliquid Market"));
-    f.render_widget(hl_widget, summary_chunks[1]);
+    let mut funding = Vec::new();
+    for position in &app.market_data.positions {
+        let summary = match position.symbol() {
+            Ok(symbol) => app.aggregator.get_exchange_summary(&position.exchange, &symbol).await.ok(),
+            Err(_) => None,
+        };
+        let rate = summary.as_ref().map(|summary| funding::hourly_rate(&position.exchange, summary.funding_rate));
+        let price = summary.map(|summary| summary.price);
+        funding.push(positions::position_funding(position, opened_at(position), &payments, rate, price));
+    }
+    funding
+}
 
-    // Orderbook (if an exchange is selected)
-    if let Some(orderbook) = &app.market_data.orderbook {
-        let mut orderbook_text = String::new();
-        
-        // Helper function to format price with dynamic decimal places
-        let format_price = |price: f64| -> String {
-            if price < 10.0 {
-                format!("${:.6}", price)
-            } else {
-                format!("${:.2}", price)
+async fn load_episodes(app: &App) -> Vec<PositionEpisode> {
+    let now = chrono::Utc::now().timestamp_millis();
+    run_with_status(&app.operation, "fetching fills", app.router.position_history()).await
+        .into_iter()
+        .map(|episode| {
+            // MAE/MFE only from candles already fetched this session
+            let candles = Symbol::parse_user_input(&episode.asset).ok()
+                .and_then(|symbol| analytics::cached_candles_for(&episode.exchange, &symbol));
+            match candles {
+                Some(candles) => episode.with_excursion(&candles, now),
+                None => episode,
             }
-        };
-        
-        // Display asks in red (reversed order)
-        orderbook_text.push_str("\x1b[0mAsks:\n");
-        orderbook_text.push_str("      Size          Price\n");
-        orderbook_text.push_str("------------------------------\n");
-        
-        for ask in orderbook.asks.iter().rev().take(5) {
-            orderbook_text.push_str(&format!("\x1b[31m{:>10.4}     {}\x1b[0m\n",
-                ask.size,
-                format_price(ask.price)
-            ));
+        })
+        .collect()
+}
+
+// Closed position episodes, newest first. 'x' exports every episode to CSV.
+async fn view_closed_trades(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    let mut episodes = load_episodes(app).await;
+    let mut status: Option<String> = None;
+
+    loop {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut lines: Vec<String> = episodes.iter()
+            .rev()
+            .filter(|episode| episode.is_closed())
+            .map(|episode| {
+                let excursion = episode.excursion
+                    .map(|e| format!("MAE ${:.2} MFE ${:.2}", e.mae, e.mfe))
+                    .unwrap_or_else(|| "MAE/MFE n/a".to_string());
+                format!(
+                    "{} {} {} {} {:.4} @ ${:.2} -> ${:.2}  PnL ${:.2} (fees ${:.2}, funding ${:.2})  held {}m  {}",
+                    episode.closed_at.map(timefmt::fmt_ts).unwrap_or_default(),
+                    episode.exchange,
+                    episode.asset,
+                    episode.side(),
+                    episode.max_size,
+                    episode.entry_avg,
+                    episode.exit_avg.unwrap_or_default(),
+                    episode.net_pnl(),
+                    episode.fees,
+                    episode.funding,
+                    episode.holding_ms(now) / 60_000,
+                    excursion
+                )
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push("No closed trades in the fill history".to_string());
         }
-        
-        // Display bids in green
-        orderbook_text.push_str("\x1b[0mBids:\n");
-        for bid in orderbook.bids.iter().take(5) {
-            orderbook_text.push_str(&format!("\x1b[32m{:>10.4}     {}\x1b[0m\n",
-                bid.size,
-                format_price(bid.price)
+        for (label, days) in [("Last 30d", 30), ("Last 7d", 7)] {
+            let summary = ClosedSummary::since(&episodes, now - days * DAY_MS);
+            lines.insert(0, format!(
+                "{}: {} trades  net ${:.2}  (realized ${:.2}, fees ${:.2}, funding ${:.2})",
+                label, summary.trades, summary.net_pnl(), summary.realized_pnl, summary.fees, summary.funding
             ));
         }
-        
-        let orderbook_title = format!("{} Orderbook", orderbook.exchange);
-        let orderbook_widget = Paragraph::new(orderbook_text)
-            .block(Block::default().borders(Borders::ALL).title(orderbook_title));
-        f.render_widget(orderbook_widget, chunks[2]);
+        if let Some(status) = &status {
+            lines.insert(0, format!("{}\n", status));
+        }
+
+        terminal.clear()?;
+        terminal.draw(|f| {
+            let list = Paragraph::new(lines.join("\n"))
+                .block(Block::default().borders(Borders::ALL).title("Closed Trades  x export CSV  r refresh  q back"));
+            f.render_widget(list, f.area());
+        })?;
+
+        let Event::Key(key) = event::read()? else { continue };
+        match key.code {
+            KeyCode::Char('x') => {
+                status = Some(match episodes::default_csv_path()
+                    .and_then(|path| episodes::write_csv(&path, &episodes, now).map(|_| path))
+                {
+                    Ok(path) => format!("Exported {} trades to {}", episodes.len(), path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+            KeyCode::Char('r') => episodes = load_episodes(app).await,
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            _ => {}
+        }
     }
+    Ok(())
 }
 
-fn format_volume(volume: f64) -> String {
-    if volume >= 1_000_000_000.0 {
-        format!("${:.2}B", volume / 1_000_000_000.0)
-    } else if volume >= 1_000_000.0 {
-        format!("${:.2}M", volume / 1_000_000.0)
-    } else if volume >= 1_000.0 {
-        format!("${:.2}K", volume / 1_000.0)
-    } else {
-        format!("${:.2}", volume)
+// Every fill on both venues, newest first, with running fee and realized PnL
+// totals per market. 'f' limits it to fills since a date.
+async fn view_trade_history(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    let mut fills = run_with_status(&app.operation, "fetching fills", app.router.fills()).await;
+    let mut since: Option<i64> = None;
+
+    loop {
+        let mut lines: Vec<String> = history::trade_history(&fills, since)
+            .iter()
+            .map(|row| format!(
+                "{} {} {} {} {:.4} @ ${:.2}  fee ${:.2}  realized ${:.2}  |  {} fees ${:.2} realized ${:.2}",
+                timefmt::fmt_ts(row.fill.time),
+                row.fill.exchange,
+                row.fill.asset,
+                if row.fill.is_buy { "Buy" } else { "Sell" },
+                row.fill.size,
+                row.fill.price,
+                row.fill.fee,
+                row.realized,
+                row.fill.asset,
+                row.total_fees,
+                row.total_realized,
+            ))
+            .collect();
+        if lines.is_empty() {
+            lines.push("No fills".to_string());
+        }
+
+        let title = match since {
+            Some(since) => format!("Trade History since {}  f filter  r refresh  q back", timefmt::fmt_ts(since)),
+            None => "Trade History  f filter  r refresh  q back".to_string(),
+        };
+        let text = lines.join("\n");
+        let screen = |f: &mut ratatui::Frame<'_>| {
+            let list = Paragraph::new(text.as_str())
+                .block(Block::default().borders(Borders::ALL).title(title.as_str()));
+            f.render_widget(list, f.area());
+        };
+        terminal.clear()?;
+        terminal.draw(|f| screen(f))?;
+
+        let Event::Key(key) = event::read()? else { continue };
+        match key.code {
+            KeyCode::Char('f') => {
+                let parse = |input: &str| match input {
+                    "" => Ok(None),
+                    input => history::parse_since(input).map(Some),
+                };
+                if let Some(parsed) = ask(terminal, &app.styles, &screen, "Trade History", "Show fills since (YYYY-MM-DD, empty for all): ", parse)? {
+                    since = parsed;
+                }
+            }
+            KeyCode::Char('r') => fills = run_with_status(&app.operation, "fetching fills", app.router.fills()).await,
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            _ => {}
+        }
     }
+    Ok(())
 }
 
-fn trading_ui(f: &mut ratatui::Frame<'_>, symbol: &str, exchange: &str, orderbook: Option<&OrderBook>, log_message: Option<&str>) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(70),  // Main content - reduced from Min(0)
-            Constraint::Percentage(30),  // Log area - increased from Length(3)
-        ])
-        .split(f.area());
-
-    let main_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(30),  // Trading menu
-            Constraint::Percentage(70),  // Orderbook
-        ])
-        .split(chunks[0]);
+// Open orders grouped by strategy. Selecting an order cancels it and any
+// mirrored twins; selecting a strategy header expands it or cancels all of
+// its open legs.
+async fn view_open_orders(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    let operation = app.operation.clone();
+    let mut expanded: HashSet<Uuid> = HashSet::new();
+    let mut orders = app.router.open_orders().await;
+    let mut grouped = app.router.group_orders(&orders)?;
+    let mut selected = 0usize;
+    let mut status: Option<String> = None;
 
-    // Trading Menu (existing code)
-    let menu_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),   // Title
-            Constraint::Length(8),   // Trading options
-            Constraint::Min(0),      // Remaining space
-        ])
-        .split(main_chunks[0]);
+    loop {
+        let row_count = grouped.rows(&expanded).len();
+        selected = selected.min(row_count.saturating_sub(1));
+        terminal.clear()?;
+        terminal.draw(|f| {
+            Order::display_order_rows(f, &grouped.rows(&expanded), selected, status.as_deref(), &app.styles);
+        })?;
 
-    // Title
-    let title = Paragraph::new(format!("Trading {} on {}", symbol, exchange))
-        .block(Block::default().borders(Borders::ALL))
-        .alignment(ratatui::layout::Alignment::Center);
-    f.render_widget(title, menu_chunks[0]);
+        let Event::Key(key) = event::read()? else { continue };
+        // 'x' cancels the highlighted order straight away; Enter or a row's
+        // number asks first, and is how strategy groups expand
+        let (index, immediate) = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Char('h') => {
+                view_recent_orders(app, terminal).await?;
+                continue;
+            }
+            KeyCode::Up => {
+                selected = selected.saturating_sub(1);
+                continue;
+            }
+            KeyCode::Down => {
+                if selected + 1 < row_count {
+                    selected += 1;
+                }
+                continue;
+            }
+            KeyCode::Char('x') => (selected, true),
+            KeyCode::Enter => (selected, false),
+            KeyCode::Char(c) => match c.to_digit(10) {
+                Some(num) if num > 0 => (num as usize - 1, false),
+                _ => continue,
+            },
+            _ => continue,
+        };
 
-    // Trading Options
-    let options = Paragraph::new(
-        "1. Market Buy\n2. Market Sell\n3. Limit Buy\n4. Limit Sell\n5. Back to Main Menu"
-    )
-    .block(Block::default().borders(Borders::ALL).title("Options"));
-    f.render_widget(options, menu_chunks[1]);
+        let rows = grouped.rows(&expanded);
+        let Some(row) = rows.get(index).copied() else { continue };
+        selected = index;
 
-    // Orderbook (reuse existing orderbook display code)
-    if let Some(orderbook) = orderbook {
-        let mut orderbook_text = String::new();
-        
-        // Helper function to format price with dynamic decimal places
-        let format_price = |price: f64| -> String {
-            if price < 10.0 {
-                format!("${:>10.6}", price)
-            } else {
-                format!("${:>10.2}", price)
+        let confirmed = match row {
+            OrderRow::Order(_) if immediate => KeyCode::Char('y'),
+            _ => {
+                let confirm_text = match row {
+                    OrderRow::Group(group) => format!(
+                        "{}\n\nPress 'e' to expand/collapse, 'y' to cancel all {} open orders, any other key to go back",
+                        group.header(),
+                        group.cancel_targets().len()
+                    ),
+                    OrderRow::Order(order) => format!(
+                        "Are you sure you want to cancel this {} order?\nSize: {} {}\nPrice: ${:.2}\nSide: {}\n\nPress 'y' to confirm, any other key to cancel",
+                        order.exchange, order.size, order.asset, order.price, order.side
+                    ),
+                };
+                terminal.clear()?;
+                terminal.draw(|f| {
+                    let confirm = Paragraph::new(confirm_text.as_str())
+                        .block(Block::default().borders(Borders::ALL).title("Confirm Cancel"));
+                    f.render_widget(confirm, f.area());
+                })?;
+                let Event::Key(confirm_key) = event::read()? else { continue };
+                confirm_key.code
             }
         };
-        
-        // Display asks in red (reversed order)
-        orderbook_text.push_str("\x1b[0mAsks:\n");
-        orderbook_text.push_str("      Size          Price\n");
-        orderbook_text.push_str("------------------------------\n");
-        
-        for ask in orderbook.asks.iter().rev().take(5) {
-            orderbook_text.push_str(&format!("\x1b[31m{:>10.4}     {}\x1b[0m\n",
-                ask.size,
-                format_price(ask.price)
-            ));
+        match (row, confirmed) {
+            (OrderRow::Group(group), KeyCode::Char('e')) => {
+                if !expanded.remove(&group.strategy_id) {
+                    expanded.insert(group.strategy_id);
+                }
+                continue;
+            }
+            (OrderRow::Group(group), KeyCode::Char('y')) => {
+                let group = group.clone();
+                let failed = run_with_status(&operation, "cancelling strategy orders", app.router.cancel_strategy(&group)).await;
+                status = Some(match failed.as_slice() {
+                    [] => format!("Cancelled {} strategy orders", group.cancel_targets().len()),
+                    failed => format!("{} of {} cancels failed: {}", failed.len(), group.cancel_targets().len(), cancel_error(&failed[0].0, &failed[0].1)),
+                });
+            }
+            // A mirrored leg takes its twins on the other venues with it
+            (OrderRow::Order(order), KeyCode::Char('y')) => {
+                let order = order.clone();
+                let label = format!("cancelling {} order", order.exchange);
+                let failed = run_with_status(&operation, &label, app.router.cancel_with_twins(&order, &orders)).await;
+                status = Some(match failed.first() {
+                    None => format!("Cancelled {} {} order", order.asset, order.exchange),
+                    Some((order, e)) => cancel_error(order, e),
+                });
+                if failed.iter().any(|(failed, _)| failed.order_id == order.order_id && failed.exchange == order.exchange) {
+                    continue;
+                }
+            }
+            _ => continue,
         }
-        
-        // Show market price
-        if let (Some(lowest_ask), Some(highest_bid)) = (orderbook.asks.first(), orderbook.bids.first()) {
-            let market_price = (lowest_ask.price + highest_bid.price) / 2.0;
-            orderbook_text.push_str("\x1b[0m------------------------------\n");
-            orderbook_text.push_str(&format!("Market Price: ${:.2}\n", market_price));
-            orderbook_text.push_str("------------------------------\n");
+
+        // Wait a moment for the cancellation to propagate, then refresh
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        orders = app.router.open_orders().await;
+        grouped = app.router.group_orders(&orders)?;
+    }
+
+    Ok(())
+}
+
+// Status line for a failed cancel; a dYdX short-term order has often just
+// expired off the book
+fn cancel_error(order: &Order, e: &AggregatorError) -> String {
+    let hint = if order.is_short_term() { " (short-term orders expire within a few blocks; it may already be gone)" } else { "" };
+    format!("Failed to cancel {} {} order {}: {}{}", order.asset, order.exchange, order.order_id, e, hint)
+}
+
+async fn view_recent_orders(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    let operation = app.operation.clone();
+    let mut orders = run_with_status(&operation, "fetching order history", app.router.recent_orders(RECENT_ORDERS_LIMIT)).await;
+
+    loop {
+        terminal.clear()?;
+        terminal.draw(|f| Order::display_recent_orders(f, &orders))?;
+
+        let Event::Key(key) = event::read()? else { continue };
+        match key.code {
+            KeyCode::Char('r') => {
+                orders = run_with_status(&operation, "fetching order history", app.router.recent_orders(RECENT_ORDERS_LIMIT)).await;
+            }
+            KeyCode::Char('h') | KeyCode::Char('q') | KeyCode::Esc => break,
+            _ => {}
         }
-        
-        // Display bids in green
-        orderbook_text.push_str("\x1b[0mBids:\n");
-        for bid in orderbook.bids.iter().take(5) {
-            orderbook_text.push_str(&format!("\x1b[32m{:>10.4}     {}\x1b[0m\n",
-                bid.size,
-                format_price(bid.price)
-            ));
+    }
+    Ok(())
+}
+
+async fn load_pnl_entries(app: &App, range: PnlRange) -> Result<Vec<PnlEntry>> {
+    Ok(run_with_status(&app.operation, "fetching fills and funding", app.router.pnl_entries(range.start)).await?)
+}
+
+// Where the PnL came from: one grouping per level, Enter drills into the
+// selected group and Backspace goes back up. 'g' changes the outer grouping,
+// 't' the range.
+async fn view_pnl(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    const RANGES: [&str; 4] = ["7d", "30d", "mtd", "all"];
+    let mut range_index = 1;
+    let mut outer = 0;
+    let mut path: Vec<usize> = Vec::new();
+    let mut selected = 0;
+    let mut status: Option<String> = None;
+    let range_for = |index: usize| PnlRange::parse(RANGES[index], chrono::Utc::now().timestamp_millis());
+
+    let mut range = range_for(range_index).map_err(anyhow::Error::msg)?;
+    let mut entries = load_pnl_entries(app, range).await.unwrap_or_else(|e| {
+        status = Some(format!("Failed to load PnL: {}", e));
+        Vec::new()
+    });
+
+    loop {
+        // The outer grouping first, then the rest in a fixed order
+        let group_by: Vec<GroupBy> = std::iter::once(GroupBy::ALL[outer])
+            .chain(GroupBy::ALL.into_iter().filter(|group| *group != GroupBy::ALL[outer]))
+            .collect();
+        let report = pnl::attribution(&entries, range, &group_by);
+        let node = report.total.at(&path).unwrap_or(&report.total);
+        selected = selected.min(node.children.len().saturating_sub(1));
+
+        let mut breadcrumb = vec![RANGES[range_index].to_string()];
+        let mut walk = &report.total;
+        for &index in &path {
+            walk = &walk.children[index];
+            breadcrumb.push(walk.key.clone());
+        }
+        let title = format!(
+            "PnL by {} [{}]  Enter drill  Bksp up  g group  t range  r refresh  q back{}",
+            group_by[path.len().min(group_by.len() - 1)],
+            breadcrumb.join(" > "),
+            status.as_deref().map(|s| format!("  | {}", s)).unwrap_or_default()
+        );
+        terminal.clear()?;
+        terminal.draw(|f| render_attribution(f, node, selected, &title, &app.styles))?;
+
+        let Event::Key(key) = event::read()? else { continue };
+        match key.code {
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected += 1,
+            KeyCode::Enter => {
+                if node.children.get(selected).is_some_and(|child| !child.children.is_empty()) {
+                    path.push(selected);
+                    selected = 0;
+                }
+            }
+            KeyCode::Backspace | KeyCode::Left => {
+                if let Some(index) = path.pop() {
+                    selected = index;
+                }
+            }
+            KeyCode::Char('g') => {
+                outer = (outer + 1) % GroupBy::ALL.len();
+                path.clear();
+                selected = 0;
+            }
+            KeyCode::Char('t') | KeyCode::Char('r') => {
+                if key.code == KeyCode::Char('t') {
+                    range_index = (range_index + 1) % RANGES.len();
+                }
+                range = range_for(range_index).map_err(anyhow::Error::msg)?;
+                status = None;
+                entries = load_pnl_entries(app, range).await.unwrap_or_else(|e| {
+                    status = Some(format!("Failed to load PnL: {}", e));
+                    Vec::new()
+                });
+                path.clear();
+                selected = 0;
+            }
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            _ => {}
         }
-        
-        let orderbook_title = format!("{} Orderbook", orderbook.exchange);
-        let orderbook_widget = Paragraph::new(orderbook_text)
-            .block(Block::default().borders(Borders::ALL).title(orderbook_title));
-        f.render_widget(orderbook_widget, main_chunks[1]);
     }
+    Ok(())
+}
 
-    // Add log area with increased size
-    if let Some(message) = log_message {
-        let log = Paragraph::new(message)
-            .block(Block::default()
-                .borders(Borders::ALL)
-                .title("Trade Log"))
-            .wrap(ratatui::widgets::Wrap { trim: true });  // Add text wrapping
-        f.render_widget(log, chunks[1]);
+// Each venue's mark, hourly funding and max leverage for the palette.
+// Venues that fail or don't list the market are left out.
+async fn palette_quotes(app: &App, symbol: &Symbol) -> Vec<VenueQuote> {
+    let mut quotes = Vec::new();
+    for exchange in app.aggregator.exchange_ids() {
+        let Ok(summary) = app.aggregator.get_exchange_summary(&exchange, symbol).await else { continue };
+        let max_leverage = app.aggregator.market_spec(&exchange, symbol).await.map(|spec| spec.max_leverage);
+        quotes.push(VenueQuote { exchange, mark: summary.price, funding_rate: summary.funding_rate, max_leverage });
     }
+    quotes
 }
 
-fn display_open_orders(terminal: &mut Terminal<CrosstermBackend<Stdout>>, orders: &[OpenOrder]) -> Result<()> {
-    terminal.draw(|f| {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(1)
-            .constraints([
-                Constraint::Length(3),    // Title
-                Constraint::Min(0),       // Orders
-                Constraint::Length(3),    // Menu
-            ].as_ref())
-            .split(f.area());
-
-        // Title
-        let title = Paragraph::new("Open Orders")
-            .block(Block::default().borders(Borders::ALL))
-            .alignment(ratatui::layout::Alignment::Center);
-        f.render_widget(title, chunks[0]);
-
-        // Orders content
-        let mut orders_text = String::new();
-        orders_text.push_str("Asset    Side    USD Amount    Price    Order ID\n");
-        orders_text.push_str("------------------------------------------------\n");
-
-        for order in orders {
-            let usd_amount = order.size * order.price;
-            orders_text.push_str(&format!(
-                "{:<8} {:<7} ${:<11.2} {:<8.2} {}\n",
-                order.asset,
-                order.side,
-                usd_amount,
-                order.price,
-                order.order_id,
-            ));
-        }
+// Desk-calculator popup over the main screen: conversions, liquidation,
+// spread and funding estimates against live data.
+async fn command_palette(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    let mut input = String::new();
+    loop {
+        terminal.draw(|f| {
+            ui(f, app);
+            palette::render_palette(f, &input, &app.palette_history, &app.styles);
+        })?;
 
-        if orders.is_empty() {
-            orders_text.push_str("\nNo open orders");
+        let Event::Key(key) = event::read()? else { continue };
+        match key.code {
+            KeyCode::Esc => break,
+            KeyCode::Enter if input.trim().is_empty() => {}
+            KeyCode::Enter => {
+                let result = match palette::parse(&input) {
+                    Ok(command) => {
+                        let quotes = run_with_status(&app.operation, "fetching palette data", palette_quotes(app, command.symbol())).await;
+                        palette::evaluate(&command, &quotes)
+                    }
+                    Err(e) => Err(e),
+                };
+                app.palette_history.push(std::mem::take(&mut input), result);
+            }
+            KeyCode::Up => {
+                if let Some(previous) = app.palette_history.previous() {
+                    input = previous.to_string();
+                }
+            }
+            KeyCode::Down => input = app.palette_history.next().unwrap_or_default().to_string(),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
         }
+    }
+    Ok(())
+}
 
-        let orders_widget = Paragraph::new(orders_text)
-            .block(Block::default()
-                .borders(Borders::ALL)
-                .title("Orders List"));
+// Start/stop strategies and watch their PnL. Strategies keep running while
+// this screen is open; it refreshes with the rest of the app.
+async fn view_strategies(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    let operation = app.operation.clone();
+    let mut status: Option<String> = None;
 
-        f.render_widget(orders_widget, chunks[1]);
+    loop {
+        let farms: Vec<_> = app.router.farms().symbols().iter()
+            .filter_map(|symbol| app.router.farm_report(symbol))
+            .collect();
+        terminal.clear()?;
+        terminal.draw(|f| render_strategies(f, &app.strategies.statuses(), &farms, app.strategies.is_dry_run(), status.as_deref(), &app.styles))?;
 
-        // Menu
-        let menu = Paragraph::new("Press 'q' to return to main menu")
-            .block(Block::default().borders(Borders::ALL))
-            .alignment(ratatui::layout::Alignment::Center);
-        f.render_widget(menu, chunks[2]);
+        if event::poll(Duration::from_secs(1))? {
+            let Event::Key(key) = event::read()? else { continue };
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => break,
+                KeyCode::Char('f') => {
+                    let symbol = app.symbol.clone();
+                    match app.router.start_farm(&symbol).await {
+                        Ok(()) => status = Some(format!("Tracking {} funding farm", symbol)),
+                        Err(e) => status = Some(e.to_string()),
+                    }
+                }
+                KeyCode::Char('x') => {
+                    let symbol = app.symbol.clone();
+                    app.router.stop_farm(&symbol);
+                    status = Some(format!("Stopped tracking {} funding farm", symbol));
+                }
+                KeyCode::Char(c) => {
+                    let statuses = app.strategies.statuses();
+                    let Some(status) = c.to_digit(10)
+                        .filter(|num| *num > 0)
+                        .and_then(|num| statuses.get(num as usize - 1)) else { continue };
+                    if status.running {
+                        app.strategies.stop(status.id, &mut app.router).await;
+                    } else {
+                        app.strategies.set_running(status.id, true);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Err(e) = run_with_status(&operation, "running strategies", app.update()).await {
+            eprintln!("Error updating market data: {}", e);
+        }
+    }
+    Ok(())
+}
+
+// The live session recap, redrawn every second until dismissed
+// Cancel every order and close every position on both venues, after a y/n
+// confirmation, then show what happened.
+async fn kill_switch(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    terminal.clear()?;
+    terminal.draw(|f| {
+        let warning = Paragraph::new("Cancel ALL open orders and market-close ALL positions on every venue?\n\nPress 'y' to confirm, any other key to cancel")
+            .style(app.styles.warn)
+            .block(Block::default().borders(Borders::ALL).title("Kill Switch"));
+        f.render_widget(warning, f.area());
     })?;
+    let Event::Key(key) = event::read()? else { return Ok(()) };
+    if key.code != KeyCode::Char('y') {
+        return Ok(());
+    }
 
-    // Wait for key press to return to menu
-    loop {
-        if let Event::Key(key) = event::read()? {
-            if let KeyCode::Char('q') | KeyCode::Esc = key.code {
-                break;
+    let operation = app.operation.clone();
+    let router = &mut app.router;
+    let report = run_with_status(
+        &operation,
+        "kill switch: cancelling orders and closing positions",
+        kill_switch::kill_switch(&mut router.hyperliquid_service, &mut router.wallet_manager, KillScope::Flatten, app.retry_attempts),
+    ).await;
+    let text = match report {
+        Ok(report) => {
+            if report.is_clean() {
+                tracing::info!("Kill switch: {}", report.describe());
+            } else {
+                tracing::error!("Kill switch incomplete: {}", report.describe());
             }
+            report.describe()
         }
+        Err(e) => format!("Kill switch refused: {}", e),
+    };
+    if let Err(e) = run_with_status(&operation, "refreshing positions", app.update()).await {
+        eprintln!("Error updating after kill switch: {}", e);
     }
 
+    terminal.clear()?;
+    terminal.draw(|f| {
+        let result = Paragraph::new(text.as_str())
+            .wrap(ratatui::widgets::Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title("Kill Switch (any key to close)"));
+        f.render_widget(result, f.area());
+    })?;
+    event::read()?;
     Ok(())
 }
 
-async fn manage_wallets(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+fn view_session_stats(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
     loop {
-        // Initialize dYdX client before getting balance
-        app.wallet_manager.init_dydx_client().await?;
-        
-        // Get wallet info and dYdX balance
-        let wallet_info = app.wallet_manager.get_wallet_info().await?;
-        let dydx_balance = app.wallet_manager.get_dydx_balance().await?;
-        
-        // Draw UI
+        let summary = app.session_summary();
+        terminal.clear()?;
         terminal.draw(|f| {
+            let stats = Paragraph::new(summary.as_str())
+                .wrap(ratatui::widgets::Wrap { trim: false })
+                .block(Block::default().borders(Borders::ALL).title("Session (q to close)"));
+            f.render_widget(stats, f.area());
+        })?;
+        if event::poll(Duration::from_secs(1))? {
+            if let Event::Key(key) = event::read()? {
+                if let KeyCode::Char('q') | KeyCode::Esc = key.code {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn manage_alerts(app: &mut App, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    let mut status: Option<String> = None;
+
+    loop {
+        let screen = |f: &mut ratatui::Frame<'_>| {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .margin(1)
                 .constraints([
-                    Constraint::Length(3),     // Title
-                    Constraint::Length(12),    // Wallet Status (increased from 8)
-                    Constraint::Length(8),     // Options Menu
-                    Constraint::Length(3),     // Input Prompt
-                ].as_ref())
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ])
                 .split(f.area());
 
-            // Title
-            let title = Paragraph::new("Wallet Management")
-                .block(Block::default().borders(Borders::ALL))
-                .alignment(ratatui::layout::Alignment::Center);
-            f.render_widget(title, chunks[0]);
-
-            // Wallet Status
-            let mut status_text = String::new();
-            if let Some(wallet) = app.wallet_manager.get_wallet() {
-                status_text.push_str(&format!("ETH Address: {:#x}\n", wallet.address()));
-                status_text.push_str(&format!("USDC Balance: ${:.2}\n", wallet_info.4));
-                status_text.push_str(&format!("Hyperliquid Portifolio Value: ${:.2}\n", wallet_info.2));
-                status_text.push_str(&format!("Hyperliquid Margin Used: ${:.2}\n", wallet_info.3));
-                let hl_balance = wallet_info.2 - wallet_info.3;
-                status_text.push_str(&format!("Hyperliquid Balance: ${:.2}\n", hl_balance));
-            } else {
-                status_text.push_str("No ETH wallet configured\n");
+            let mut text = String::new();
+            if app.alerts.alerts().is_empty() {
+                text.push_str("No alerts set. Press 'l' on the trading screen to add one.\n");
             }
-
-            if let Some(dydx_wallet) = app.wallet_manager.get_dydx_wallet() {
-                if let Ok(account) = dydx_wallet.account_offline(0) {
-                    status_text.push_str(&format!("dYdX Address: {}\n", account.address()));
-                    if let Some(balance) = dydx_balance {
-                        status_text.push_str(&format!("dYdX Balance: ${:.2}\n", balance));
-                    }
-                }
-            } else {
-                status_text.push_str("No dYdX wallet configured\n");
+            for alert in app.alerts.alerts() {
+                text.push_str(&format!(
+                    "#{:<4} {:<12} {:<24} {}\n",
+                    alert.id,
+                    alert.symbol.to_string(),
+                    alert.rule.label(),
+                    if alert.is_triggered() { "triggered" } else { "active" }
+                ));
             }
 
-            let status = Paragraph::new(status_text)
-                .block(Block::default().borders(Borders::ALL).title("Wallet Status"));
-            f.render_widget(status, chunks[1]);
-
-            // Options Menu
-            let options = Paragraph::new(
-                "1. Create New ETH Wallet\n\
-                 2. Import Existing ETH Wallet\n\
-                 3. Create New dYdX Wallet\n\
-                 4. Import Existing dYdX Wallet\n\
-                 5. Bridge USDC to dYdX\n\
-                 6. Back to Main Menu"
-            )
-            .block(Block::default().borders(Borders::ALL).title("Options"));
-            f.render_widget(options, chunks[2]);
+            let list = Paragraph::new(text)
+                .block(Block::default().borders(Borders::ALL).title("Alerts"));
+            f.render_widget(list, chunks[0]);
 
-            // Input Prompt
-            let prompt = Paragraph::new("Enter choice (1-6): ")
+            let help = Paragraph::new(status.clone().unwrap_or_else(|| "d. Delete alert  f. Fair-price divergence alert  q. Back".to_string()))
                 .block(Block::default().borders(Borders::ALL));
-            f.render_widget(prompt, chunks[3]);
-        })?;
+            f.render_widget(help, chunks[1]);
+        };
+        terminal.draw(|f| screen(f))?;
 
-        // Handle input
         if let Event::Key(key) = event::read()? {
             match key.code {
-                KeyCode::Char('1') => app.wallet_manager.create_eth_wallet().await?,
-                KeyCode::Char('2') => app.wallet_manager.import_eth_wallet().await?,
-                KeyCode::Char('3') => app.wallet_manager.create_dydx_wallet().await?,
-                KeyCode::Char('4') => app.wallet_manager.import_dydx_wallet().await?,
-                KeyCode::Char('5') => {
-                    // Bridge USDC
-                    terminal.clear()?;
-                    disable_raw_mode()?;
-                    
-                    print!("Enter USDC amount to bridge: ");
-                    io::stdout().flush()?;
-                    
-                    let mut input = String::new();
-                    io::stdin().read_line(&mut input)?;
-                    let amount = input.trim().parse::<f64>()?;
-                    
-                    println!("Initiating bridge of {} USDC to dYdX...", amount);
-                    app.wallet_manager.bridge_to_dydx(amount).await?;
-                    
-                    println!("\nPress Enter to continue...");
-                    io::stdin().read_line(&mut input)?;
-                    
-                    enable_raw_mode()?;
-                    terminal.clear()?;
+                KeyCode::Char('d') => {
+                    let parse = |input: &str| input.trim_start_matches('#').parse::<u64>().map_err(|e| format!("Invalid alert number: {}", e));
+                    let Some(id) = ask(terminal, &app.styles, &screen, "Alerts", "Alert # to delete: ", parse)? else { continue };
+
+                    status = Some(match app.alerts.remove(id) {
+                        Ok(true) => format!("Deleted alert #{}", id),
+                        Ok(false) => format!("No alert #{}", id),
+                        Err(e) => format!("Error deleting alert: {}", e),
+                    });
                 },
-                KeyCode::Char('6') | KeyCode::Char('q') | KeyCode::Esc => {
-                    // Clear screen before exiting
-                    terminal.clear()?;
-                    break;
+                KeyCode::Char('f') => {
+                    let label = format!("Notional each side [${:.0}]: $", app.fair_price_notional);
+                    let Some(notional) = ask(terminal, &app.styles, &screen, "Alerts", &label, or_default(app.fair_price_notional))? else { continue };
+                    let label = format!("Alert when {} venues' fair prices are this many bps apart: ", app.symbol);
+                    let Some(bps) = ask(terminal, &app.styles, &screen, "Alerts", &label, positive_number)? else { continue };
+
+                    status = Some(match app.alerts.add_fair_price_divergence(&app.symbol, notional, bps) {
+                        Ok(alert) => format!("Alert #{} set: {}", alert.id, alert.rule.label()),
+                        Err(e) => format!("Error setting alert: {}", e),
+                    });
                 },
+                KeyCode::Char('q') | KeyCode::Esc => break,
                 _ => {}
             }
         }
     }
 
-    // Ensure terminal is properly reset before returning to main menu
     terminal.clear()?;
-    terminal.draw(|f| {
-        // Draw empty frame to ensure clean state
-        let block = Block::default();
-        f.render_widget(block, f.area());
-    })?;
-
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,28 @@
+//! The supported public surface in one import:
+//! `use hl_aggregator::prelude::*;`.
+//!
+//! Every name here is covered by semver. Removing or renaming one is a
+//! breaking change and has to update the snapshot in `prelude/tests`.
+
+pub use crate::aggregator::{DerivativesAggregator, Exchange};
+pub use crate::aggregator::exchange_id::ExchangeId;
+pub use crate::aggregator::symbol::{MarketKind, Symbol};
+pub use crate::aggregator::traits::ExchangeAggregator;
+pub use crate::aggregator::types::{AggregatedOrderBook, Level, MarketData, MarketSummary, OrderBook};
+pub use crate::config::AggregatorConfig;
+pub use crate::error::AggregatorError;
+pub use crate::trading::{OrderType, TradeRequest};
+pub use crate::trading::confirmation::{Confirmation, Quote};
+pub use crate::trading::dydx_service::{DydxService, RestConfig, SockConfig};
+pub use crate::trading::hyperliquid_service::{HyperliquidService, OpenOrder};
+pub use crate::trading::journal::Journal;
+pub use crate::trading::orders::Order;
+pub use crate::trading::positions::Position;
+pub use crate::trading::router::{RoutedTrade, TradingRouter};
+pub use crate::trading::trader::{ExchangeTrader, TradeResult};
+pub use crate::trading::wallet::WalletManager;
+pub use dydx::indexer::IndexerConfig;
+pub use dydx::node::NodeConfig;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod prelude_tests {
+    // The public surface as reviewed. Change it together with the prelude,
+    // never to make this test pass on its own.
+    const SNAPSHOT: &[&str] = &[
+        "AggregatedOrderBook",
+        "AggregatorConfig",
+        "AggregatorError",
+        "Confirmation",
+        "DerivativesAggregator",
+        "DydxService",
+        "Exchange",
+        "ExchangeAggregator",
+        "ExchangeId",
+        "ExchangeTrader",
+        "HyperliquidService",
+        "IndexerConfig",
+        "Journal",
+        "Level",
+        "MarketData",
+        "MarketKind",
+        "MarketSummary",
+        "NodeConfig",
+        "OpenOrder",
+        "Order",
+        "OrderBook",
+        "OrderType",
+        "Position",
+        "Quote",
+        "RestConfig",
+        "RoutedTrade",
+        "SockConfig",
+        "Symbol",
+        "TradeRequest",
+        "TradeResult",
+        "TradingRouter",
+        "WalletManager",
+    ];
+
+    // Names brought in by each `pub use` of the prelude source
+    fn exported_names(source: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        for statement in source.split(';').map(str::trim) {
+            let code = statement.lines()
+                .map(str::trim)
+                .filter(|line| !line.starts_with("//"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            // A doc comment's `;` can leave its tail in front of the statement
+            let Some(start) = code.find("pub use ") else { continue };
+            let path = &code[start + "pub use ".len()..];
+            let items = match (path.find('{'), path.rfind('}')) {
+                (Some(open), Some(close)) => path[open + 1..close].to_string(),
+                _ => path.rsplit("::").next().unwrap_or_default().to_string(),
+            };
+            for item in items.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+                let name = item.rsplit(" as ").next().unwrap_or(item);
+                names.push(name.rsplit("::").next().unwrap_or(name).to_string());
+            }
+        }
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn prelude_matches_reviewed_snapshot() {
+        let exported = exported_names(include_str!("../mod.rs"));
+        assert_eq!(exported, SNAPSHOT, "the public prelude changed; review the API change and update SNAPSHOT");
+    }
+
+    #[test]
+    fn snapshot_parser_reads_groups_and_renames() {
+        let source = "//! `use x::*;`\npub use a::b::{C, D as E};\npub use f::G;\nuse h::I;\n";
+        assert_eq!(exported_names(source), vec!["C", "E", "G"]);
+    }
+
+    // Fails to build if a prelude name stops resolving
+    #[allow(dead_code)]
+    fn prelude_names_resolve() {
+        use crate::prelude::*;
+        let _: Option<(DerivativesAggregator, TradingRouter, HyperliquidService, DydxService, WalletManager, Journal)> = None;
+        let _: Option<(OrderBook, AggregatedOrderBook, MarketSummary, MarketData, Level, OpenOrder, Order, Position)> = None;
+        let _: Option<(TradeRequest, OrderType, TradeResult, RoutedTrade, Quote, Confirmation, AggregatorConfig, AggregatorError)> = None;
+        let _: Option<(Exchange, ExchangeId, Symbol, MarketKind, RestConfig, SockConfig, NodeConfig, IndexerConfig)> = None;
+        fn traits<A: ExchangeAggregator, T: ExchangeTrader>() {}
+    }
+}
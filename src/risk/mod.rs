@@ -0,0 +1,126 @@
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::metadata::{MarginTier, MarketSpec};
+use crate::aggregator::symbol::Symbol;
+use crate::analytics::maintenance_margin_rate;
+
+/// What a venue asks to hold a position of a given notional.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarginRequirement {
+    // Highest leverage allowed at this size
+    pub max_leverage: f64,
+    // Both in USD
+    pub initial_margin: f64,
+    pub maintenance_margin: f64,
+    // The size is past the market's first margin tier
+    pub tiered: bool,
+}
+
+/// Initial and maintenance margin for `notional` USD opened at the highest
+/// leverage the market allows at that size. Published margin fractions are
+/// used as is; otherwise leverage comes from the tier the notional falls in,
+/// and maintenance is half the initial margin at that tier's max leverage,
+/// less a deduction that keeps it continuous across tier boundaries.
+pub fn margin_requirement(spec: &MarketSpec, notional: f64) -> Option<MarginRequirement> {
+    let notional = notional.abs();
+    if let Some(imf) = spec.initial_margin_fraction.filter(|imf| *imf > 0.0) {
+        let mmf = spec.maintenance_margin_fraction.unwrap_or(imf / 2.0);
+        return Some(MarginRequirement {
+            max_leverage: 1.0 / imf,
+            initial_margin: notional * imf,
+            maintenance_margin: notional * mmf,
+            tiered: false,
+        });
+    }
+
+    let tiers = if spec.margin_tiers.is_empty() {
+        vec![MarginTier { lower_bound: 0.0, max_leverage: spec.max_leverage }]
+    } else {
+        spec.margin_tiers.clone()
+    };
+    let mut deduction = 0.0;
+    let mut current: Option<(usize, f64)> = None;
+    for (i, tier) in tiers.iter().enumerate() {
+        if i > 0 && tier.lower_bound > notional {
+            break;
+        }
+        let rate = maintenance_margin_rate(tier.max_leverage);
+        if let Some((_, previous_rate)) = current {
+            deduction += tier.lower_bound * (rate - previous_rate);
+        }
+        current = Some((i, rate));
+    }
+    let (index, rate) = current?;
+    let max_leverage = tiers[index].max_leverage;
+    if max_leverage <= 0.0 {
+        return None;
+    }
+    Some(MarginRequirement {
+        max_leverage,
+        initial_margin: notional / max_leverage,
+        maintenance_margin: notional * rate - deduction,
+        tiered: index > 0,
+    })
+}
+
+/// Price at which a cross-margined account is liquidated through one
+/// position, the others held where they are: where equity falls to the
+/// total maintenance margin. `size` is signed, `price` is the price `equity`
+/// was marked at, and `other_maintenance` is the margin the account's other
+/// positions require. None when no positive price gets there.
+pub fn cross_liquidation_price(size: f64, price: f64, maintenance_fraction: f64, equity: f64, other_maintenance: f64) -> Option<f64> {
+    // equity + size * (liq - price) = other_maintenance + |size| * liq * mmf
+    let denominator = size - size.abs() * maintenance_fraction;
+    if denominator == 0.0 {
+        return None;
+    }
+    let liquidation = (other_maintenance - equity + size * price) / denominator;
+    (liquidation > 0.0).then_some(liquidation)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VenueMargin {
+    pub exchange: ExchangeId,
+    pub requirement: MarginRequirement,
+}
+
+/// The same position's margin on each venue, least collateral first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginComparison {
+    pub symbol: Symbol,
+    pub notional: f64,
+    pub venues: Vec<VenueMargin>,
+    // Venues without margin parameters for the market
+    pub missing: Vec<ExchangeId>,
+}
+
+impl MarginComparison {
+    pub fn cheapest(&self) -> Option<&VenueMargin> {
+        self.venues.first()
+    }
+
+    /// Extra initial margin `exchange` needs over the cheapest venue
+    pub fn excess(&self, exchange: &ExchangeId) -> Option<f64> {
+        let cheapest = self.cheapest()?;
+        let venue = self.venues.iter().find(|venue| &venue.exchange == exchange)?;
+        Some(venue.requirement.initial_margin - cheapest.requirement.initial_margin)
+    }
+}
+
+/// Compare what each venue's `spec` asks for `notional` of `symbol`. A
+/// venue whose spec is missing is listed in `missing`.
+pub fn margin_comparison(symbol: &Symbol, notional: f64, specs: &[(ExchangeId, Option<MarketSpec>)]) -> MarginComparison {
+    let mut venues = Vec::new();
+    let mut missing = Vec::new();
+    for (exchange, spec) in specs {
+        match spec.as_ref().and_then(|spec| margin_requirement(spec, notional)) {
+            Some(requirement) => venues.push(VenueMargin { exchange: exchange.clone(), requirement }),
+            None => missing.push(exchange.clone()),
+        }
+    }
+    // Stable, so ties keep the order the venues were given
+    venues.sort_by(|a, b| a.requirement.initial_margin.total_cmp(&b.requirement.initial_margin));
+    MarginComparison { symbol: symbol.clone(), notional: notional.abs(), venues, missing }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod margin_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::metadata::{MarginTier, MarketSpec};
+    use crate::aggregator::symbol::Symbol;
+    use crate::risk::{cross_liquidation_price, margin_comparison, margin_requirement};
+
+    fn spec(max_leverage: f64, tiers: &[(f64, f64)], fractions: Option<(f64, f64)>) -> MarketSpec {
+        MarketSpec {
+            base: "BTC".to_string(),
+            max_leverage,
+            size_decimals: None,
+            tick_size: None,
+            step_size: None,
+            margin_tiers: tiers.iter().map(|&(lower_bound, max_leverage)| MarginTier { lower_bound, max_leverage }).collect(),
+            initial_margin_fraction: fractions.map(|(imf, _)| imf),
+            maintenance_margin_fraction: fractions.map(|(_, mmf)| mmf),
+            min_notional: 0.0,
+        }
+    }
+
+    // Hyperliquid-style table: 40x up to $150M, 20x past it
+    fn tiered() -> MarketSpec {
+        spec(40.0, &[(0.0, 40.0), (150_000_000.0, 20.0)], None)
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn test_requirements_by_size() {
+        // (spec, notional, max leverage, initial, maintenance, tiered)
+        let cases = [
+            (tiered(), 10_000.0, 40.0, 250.0, 125.0, false),
+            (tiered(), 149_999_999.0, 40.0, 3_749_999.975, 1_874_999.9875, false),
+            // At the boundary maintenance carries on from the tier below
+            (tiered(), 150_000_000.0, 20.0, 7_500_000.0, 1_875_000.0, true),
+            (tiered(), 200_000_000.0, 20.0, 10_000_000.0, 3_125_000.0, true),
+            // No table: max leverage at every size
+            (spec(50.0, &[], None), 10_000.0, 50.0, 200.0, 100.0, false),
+            (spec(50.0, &[], None), 1e9, 50.0, 2e7, 1e7, false),
+            // dYdX publishes its fractions
+            (spec(20.0, &[], Some((0.05, 0.03))), 10_000.0, 20.0, 500.0, 300.0, false),
+            (spec(20.0, &[], Some((0.05, 0.03))), 1e9, 20.0, 5e7, 3e7, false),
+        ];
+        for (spec, notional, leverage, initial, maintenance, tiered) in cases {
+            let requirement = margin_requirement(&spec, notional).unwrap();
+            assert!(close(requirement.max_leverage, leverage), "{} leverage {:?}", notional, requirement);
+            assert!(close(requirement.initial_margin, initial), "{} initial {:?}", notional, requirement);
+            assert!(close(requirement.maintenance_margin, maintenance), "{} maintenance {:?}", notional, requirement);
+            assert_eq!(requirement.tiered, tiered, "{}", notional);
+        }
+        // Without a maintenance fraction it is half the initial
+        let halved = spec(10.0, &[], None);
+        assert!(close(margin_requirement(&MarketSpec { initial_margin_fraction: Some(0.1), ..halved }, 5_000.0).unwrap().maintenance_margin, 250.0));
+        assert!(margin_requirement(&spec(0.0, &[], None), 1_000.0).is_none());
+    }
+
+    #[test]
+    fn test_comparison_orders_by_initial_margin() {
+        let symbol = Symbol::perp("BTC");
+        let specs = vec![
+            (ExchangeId::Dydx, Some(spec(20.0, &[], Some((0.05, 0.03))))),
+            (ExchangeId::Hyperliquid, Some(tiered())),
+            (ExchangeId::Custom("paper".to_string()), None),
+        ];
+        let comparison = margin_comparison(&symbol, -10_000.0, &specs);
+        assert_eq!(comparison.notional, 10_000.0);
+        let order: Vec<&ExchangeId> = comparison.venues.iter().map(|venue| &venue.exchange).collect();
+        assert_eq!(order, vec![&ExchangeId::Hyperliquid, &ExchangeId::Dydx]);
+        assert_eq!(comparison.missing, vec![ExchangeId::Custom("paper".to_string())]);
+        assert!(close(comparison.excess(&ExchangeId::Dydx).unwrap(), 250.0));
+        assert_eq!(comparison.excess(&ExchangeId::Hyperliquid), Some(0.0));
+        assert_eq!(comparison.excess(&ExchangeId::Custom("paper".to_string())), None);
+    }
+
+    #[test]
+    fn test_cross_liquidation_price() {
+        // Where $500 of equity, less the move, meets 5% maintenance
+        let long = cross_liquidation_price(1.0, 2_000.0, 0.05, 500.0, 0.0).unwrap();
+        assert!(close(long, 1_500.0 / 0.95));
+        assert!(close(500.0 + (long - 2_000.0), long * 0.05));
+        let short = cross_liquidation_price(-1.0, 2_000.0, 0.05, 500.0, 0.0).unwrap();
+        assert!(close(short, 2_500.0 / 1.05));
+
+        // Other positions' maintenance brings it closer
+        assert!(cross_liquidation_price(1.0, 2_000.0, 0.05, 500.0, 100.0).unwrap() > long);
+        // Equity covering the whole notional can't be liquidated on a long
+        assert_eq!(cross_liquidation_price(1.0, 2_000.0, 0.05, 3_000.0, 0.0), None);
+        assert_eq!(cross_liquidation_price(1.0, 2_000.0, 1.0, 500.0, 0.0), None);
+    }
+}
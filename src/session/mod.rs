@@ -0,0 +1,280 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::timefmt;
+use crate::trading::positions::episodes::Fill;
+use crate::trading::positions::Position;
+
+/// Running totals for one venue over the session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VenueSession {
+    pub orders_placed: u32,
+    pub orders_filled: u32,
+    pub orders_cancelled: u32,
+    // Notional of the session's fills, USD
+    pub volume: f64,
+    pub fees: f64,
+    pub realized_pnl: f64,
+    // Websocket time up to the current connection, which is still open
+    // when `ws_connected_since` is set
+    pub ws_up_ms: i64,
+    pub ws_connected_since: Option<i64>,
+    pub ws_connects: u32,
+}
+
+impl VenueSession {
+    /// Connections after the first
+    pub fn reconnects(&self) -> u32 {
+        self.ws_connects.saturating_sub(1)
+    }
+
+    pub fn ws_up_ms_at(&self, now_ms: i64) -> i64 {
+        self.ws_up_ms + self.ws_connected_since.map_or(0, |since| (now_ms - since).max(0))
+    }
+}
+
+// Net position built up from fills, for realized PnL
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Lot {
+    // Signed, positive long
+    size: f64,
+    avg_price: f64,
+}
+
+impl Lot {
+    /// Apply a fill, returning the PnL it realized
+    fn fill(&mut self, signed_size: f64, price: f64) -> f64 {
+        if self.size == 0.0 || self.size.signum() == signed_size.signum() {
+            let size = self.size.abs() + signed_size.abs();
+            self.avg_price = (self.size.abs() * self.avg_price + signed_size.abs() * price) / size;
+            self.size += signed_size;
+            return 0.0;
+        }
+        let closed = signed_size.abs().min(self.size.abs());
+        let realized = closed * (price - self.avg_price) * self.size.signum();
+        self.size += signed_size;
+        if self.size.abs() < 1e-12 {
+            *self = Lot::default();
+        } else if self.size.signum() == signed_size.signum() {
+            // Flipped: the remainder opened at this fill
+            self.avg_price = price;
+        }
+        realized
+    }
+}
+
+/// Counters for the running session, updated by the router, the fill
+/// poller and the feeds as things happen. Nothing is re-derived from the
+/// journal; every increment is O(1) apart from fill dedup.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub started_ms: i64,
+    // When the copy on disk was written; None for the live one
+    #[serde(default)]
+    pub saved_ms: Option<i64>,
+    pub venues: BTreeMap<ExchangeId, VenueSession>,
+    // Account equity across venues: the high-water mark, the latest sample
+    // and the deepest fall from a high so far, in USD
+    pub peak_equity: Option<f64>,
+    pub last_equity: Option<f64>,
+    pub max_drawdown: f64,
+    #[serde(skip)]
+    lots: HashMap<(ExchangeId, String), Lot>,
+    #[serde(skip)]
+    seeded: bool,
+    #[serde(skip)]
+    fills_seen: HashSet<(ExchangeId, String, i64, u64, u64)>,
+}
+
+pub type SharedSessionStats = Arc<Mutex<SessionStats>>;
+
+impl SessionStats {
+    pub fn new(started_ms: i64) -> Self {
+        Self { started_ms, ..Self::default() }
+    }
+
+    pub fn shared(started_ms: i64) -> SharedSessionStats {
+        Arc::new(Mutex::new(Self::new(started_ms)))
+    }
+
+    fn venue(&mut self, venue: &ExchangeId) -> &mut VenueSession {
+        self.venues.entry(venue.clone()).or_default()
+    }
+
+    pub fn record_placed(&mut self, venue: &ExchangeId) {
+        self.venue(venue).orders_placed += 1;
+    }
+
+    pub fn record_filled(&mut self, venue: &ExchangeId) {
+        self.venue(venue).orders_filled += 1;
+    }
+
+    pub fn record_cancelled(&mut self, venue: &ExchangeId) {
+        self.venue(venue).orders_cancelled += 1;
+    }
+
+    /// Positions held when the session started, so closing them realizes
+    /// PnL against their entry. Only the first call counts.
+    pub fn seed_positions(&mut self, positions: &[Position]) {
+        if self.seeded {
+            return;
+        }
+        self.seeded = true;
+        for position in positions {
+            let Some(entry) = position.entry_price.filter(|_| position.size != 0.0) else { continue };
+            self.lots.insert(
+                (position.exchange.clone(), position.asset.clone()),
+                Lot { size: position.signed_size(), avg_price: entry },
+            );
+        }
+    }
+
+    /// Add a fill's volume, fee and realized PnL. Fills from before the
+    /// session, or already counted, are ignored.
+    pub fn record_fill(&mut self, fill: &Fill) {
+        if fill.time < self.started_ms {
+            return;
+        }
+        let key = (fill.exchange.clone(), fill.order_id.clone(), fill.time, fill.size.to_bits(), fill.price.to_bits());
+        if !self.fills_seen.insert(key) {
+            return;
+        }
+        let signed_size = if fill.is_buy { fill.size } else { -fill.size };
+        let realized = self.lots.entry((fill.exchange.clone(), fill.asset.clone())).or_default().fill(signed_size, fill.price);
+        let venue = self.venue(&fill.exchange);
+        venue.volume += fill.size * fill.price;
+        venue.fees += fill.fee;
+        venue.realized_pnl += realized;
+    }
+
+    pub fn record_equity(&mut self, equity: f64) {
+        let peak = self.peak_equity.map_or(equity, |peak| peak.max(equity));
+        self.peak_equity = Some(peak);
+        self.last_equity = Some(equity);
+        self.max_drawdown = self.max_drawdown.max(peak - equity);
+    }
+
+    /// A websocket session started delivering data. A venue already
+    /// connected stays on its current connection, e.g. across a resubscribe.
+    pub fn record_ws_connected(&mut self, venue: &ExchangeId, now_ms: i64) {
+        let venue = self.venue(venue);
+        if venue.ws_connected_since.is_none() {
+            venue.ws_connected_since = Some(now_ms);
+            venue.ws_connects += 1;
+        }
+    }
+
+    pub fn record_ws_closed(&mut self, venue: &ExchangeId, now_ms: i64) {
+        let venue = self.venue(venue);
+        if let Some(since) = venue.ws_connected_since.take() {
+            venue.ws_up_ms += (now_ms - since).max(0);
+        }
+    }
+
+    /// Share of the session the venue's websocket was delivering, 0 to 1
+    pub fn ws_uptime(&self, venue: &ExchangeId, now_ms: i64) -> Option<f64> {
+        let elapsed = now_ms - self.started_ms;
+        let venue = self.venues.get(venue)?;
+        (elapsed > 0).then(|| (venue.ws_up_ms_at(now_ms) as f64 / elapsed as f64).min(1.0))
+    }
+
+    pub fn totals(&self) -> VenueSession {
+        self.venues.values().fold(VenueSession::default(), |mut total, venue| {
+            total.orders_placed += venue.orders_placed;
+            total.orders_filled += venue.orders_filled;
+            total.orders_cancelled += venue.orders_cancelled;
+            total.volume += venue.volume;
+            total.fees += venue.fees;
+            total.realized_pnl += venue.realized_pnl;
+            total.ws_connects += venue.ws_connects;
+            total
+        })
+    }
+
+    /// Plain-text recap as of `now_ms`, for the exit summary, the stats
+    /// view and the CLI.
+    pub fn summary(&self, now_ms: i64) -> String {
+        let total = self.totals();
+        let mut lines = vec![
+            format!(
+                "Session {} to {} ({})",
+                timefmt::fmt_ts(self.started_ms),
+                timefmt::fmt_ts(now_ms),
+                duration_label(now_ms - self.started_ms)
+            ),
+            format!(
+                "Orders: {} placed, {} filled, {} cancelled",
+                total.orders_placed, total.orders_filled, total.orders_cancelled
+            ),
+            format!(
+                "Volume ${:.2}, fees ${:.2}, realized PnL {}",
+                total.volume, total.fees, signed_usd(total.realized_pnl)
+            ),
+        ];
+        match (self.peak_equity, self.last_equity) {
+            (Some(peak), Some(last)) => lines.push(format!(
+                "Equity ${:.2} (peak ${:.2}), max drawdown ${:.2}{}",
+                last, peak, self.max_drawdown,
+                if peak > 0.0 { format!(" ({:.2}%)", self.max_drawdown / peak * 100.0) } else { String::new() }
+            )),
+            _ => lines.push("Equity: no samples".to_string()),
+        }
+        for (exchange, venue) in &self.venues {
+            let uptime = self.ws_uptime(exchange, now_ms).map_or("n/a".to_string(), |uptime| format!("{:.1}%", uptime * 100.0));
+            lines.push(format!(
+                "  {}: {} placed, {} filled, {} cancelled, volume ${:.2}, fees ${:.2}, realized {}, websocket up {}, {} reconnects",
+                exchange, venue.orders_placed, venue.orders_filled, venue.orders_cancelled,
+                venue.volume, venue.fees, signed_usd(venue.realized_pnl), uptime, venue.reconnects()
+            ));
+        }
+        lines.join("\n")
+    }
+
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("trading_aggregator");
+        fs::create_dir_all(&config_dir)?;
+        Ok(config_dir.join("session_stats.json"))
+    }
+
+    /// Write the stats as of `now_ms`, so `stats session` can show a running
+    /// or finished session from another process.
+    pub fn save(&self, path: &PathBuf, now_ms: i64) -> Result<()> {
+        let mut snapshot = self.clone();
+        snapshot.saved_ms = Some(now_ms);
+        for venue in snapshot.venues.values_mut() {
+            venue.ws_up_ms = venue.ws_up_ms_at(now_ms);
+            venue.ws_connected_since = None;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&snapshot)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: &PathBuf) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+fn signed_usd(value: f64) -> String {
+    if value < 0.0 { format!("-${:.2}", -value) } else { format!("+${:.2}", value) }
+}
+
+/// "2h 05m", "12m 30s"
+fn duration_label(ms: i64) -> String {
+    let secs = ms.max(0) / 1000;
+    if secs >= 3_600 {
+        format!("{}h {:02}m", secs / 3_600, secs % 3_600 / 60)
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod session_stats_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::session::SessionStats;
+    use crate::test_fixtures;
+    use crate::trading::positions::episodes::Fill;
+    use crate::trading::positions::Position;
+
+    const START: i64 = 1_700_000_000_000;
+
+    // Order ids follow the time, so each fill is its own order
+    fn fill(exchange: ExchangeId, is_buy: bool, size: f64, price: f64, fee: f64, time: i64) -> Fill {
+        Fill { fee, order_id: format!("o{}", time), ..test_fixtures::fill(exchange, is_buy, price, size, time) }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_order_counters_per_venue() {
+        let mut stats = SessionStats::new(START);
+        stats.record_placed(&ExchangeId::Hyperliquid);
+        stats.record_placed(&ExchangeId::Hyperliquid);
+        stats.record_placed(&ExchangeId::Dydx);
+        stats.record_filled(&ExchangeId::Hyperliquid);
+        stats.record_cancelled(&ExchangeId::Dydx);
+
+        let hl = &stats.venues[&ExchangeId::Hyperliquid];
+        assert_eq!((hl.orders_placed, hl.orders_filled, hl.orders_cancelled), (2, 1, 0));
+        let dydx = &stats.venues[&ExchangeId::Dydx];
+        assert_eq!((dydx.orders_placed, dydx.orders_filled, dydx.orders_cancelled), (1, 0, 1));
+        let total = stats.totals();
+        assert_eq!((total.orders_placed, total.orders_filled, total.orders_cancelled), (3, 1, 1));
+    }
+
+    #[test]
+    fn test_fills_add_volume_fees_and_realized_pnl() {
+        let mut stats = SessionStats::new(START);
+        // Open 2 at 100, add 2 at 110, then sell 3 at 120 and flip with 2 more
+        stats.record_fill(&fill(ExchangeId::Hyperliquid, true, 2.0, 100.0, 0.1, START + 1));
+        stats.record_fill(&fill(ExchangeId::Hyperliquid, true, 2.0, 110.0, 0.1, START + 2));
+        stats.record_fill(&fill(ExchangeId::Hyperliquid, false, 3.0, 120.0, 0.2, START + 3));
+        let hl = &stats.venues[&ExchangeId::Hyperliquid];
+        assert!(close(hl.volume, 200.0 + 220.0 + 360.0));
+        assert!(close(hl.fees, 0.4));
+        // 3 closed at 120 against a 105 average
+        assert!(close(hl.realized_pnl, 45.0));
+
+        stats.record_fill(&fill(ExchangeId::Hyperliquid, false, 2.0, 100.0, 0.0, START + 4));
+        // The last 1 closes at 100 (-5); the other 1 opens a short at 100
+        assert!(close(stats.venues[&ExchangeId::Hyperliquid].realized_pnl, 40.0));
+        stats.record_fill(&fill(ExchangeId::Hyperliquid, true, 1.0, 90.0, 0.0, START + 5));
+        assert!(close(stats.venues[&ExchangeId::Hyperliquid].realized_pnl, 50.0));
+    }
+
+    #[test]
+    fn test_fills_before_session_or_repeated_are_ignored() {
+        let mut stats = SessionStats::new(START);
+        stats.record_fill(&fill(ExchangeId::Dydx, true, 1.0, 100.0, 0.5, START - 1));
+        assert!(stats.venues.is_empty());
+
+        let repeated = fill(ExchangeId::Dydx, true, 1.0, 100.0, 0.5, START + 1);
+        stats.record_fill(&repeated);
+        stats.record_fill(&repeated);
+        assert!(close(stats.venues[&ExchangeId::Dydx].volume, 100.0));
+        assert!(close(stats.venues[&ExchangeId::Dydx].fees, 0.5));
+    }
+
+    #[test]
+    fn test_seeded_position_realizes_against_entry() {
+        let mut stats = SessionStats::new(START);
+        let held = Position {
+            exchange: ExchangeId::Dydx,
+            asset: "BTC".to_string(),
+            size: -2.0,
+            entry_price: Some(100.0),
+            liquidation_price: None,
+            unrealized_pnl: 0.0,
+            margin_used: None,
+            leverage: None,
+            roe: None,
+            side: "Short".to_string(),
+        };
+        stats.seed_positions(std::slice::from_ref(&held));
+        // Only the first refresh seeds
+        stats.seed_positions(&[Position { entry_price: Some(50.0), ..held }]);
+        stats.record_fill(&fill(ExchangeId::Dydx, true, 2.0, 90.0, 0.0, START + 1));
+        assert!(close(stats.venues[&ExchangeId::Dydx].realized_pnl, 20.0));
+    }
+
+    #[test]
+    fn test_equity_drawdown_tracks_deepest_fall_from_peak() {
+        let mut stats = SessionStats::new(START);
+        for equity in [1_000.0, 1_100.0, 950.0, 1_050.0, 1_200.0, 1_120.0] {
+            stats.record_equity(equity);
+        }
+        assert_eq!(stats.peak_equity, Some(1_200.0));
+        assert_eq!(stats.last_equity, Some(1_120.0));
+        assert!(close(stats.max_drawdown, 150.0));
+    }
+
+    #[test]
+    fn test_websocket_uptime_and_reconnects() {
+        let mut stats = SessionStats::new(START);
+        let hl = ExchangeId::Hyperliquid;
+        stats.record_ws_connected(&hl, START);
+        // A resubscribe on a live connection isn't a reconnect
+        stats.record_ws_connected(&hl, START + 10_000);
+        stats.record_ws_closed(&hl, START + 40_000);
+        // Closing twice adds nothing
+        stats.record_ws_closed(&hl, START + 45_000);
+        stats.record_ws_connected(&hl, START + 60_000);
+
+        let venue = &stats.venues[&hl];
+        assert_eq!((venue.ws_connects, venue.reconnects()), (2, 1));
+        // 40s up before the drop, 40s since reconnecting, of 100s
+        assert!(close(stats.ws_uptime(&hl, START + 100_000).unwrap(), 0.8));
+        assert_eq!(stats.ws_uptime(&ExchangeId::Dydx, START + 100_000), None);
+    }
+
+    #[test]
+    fn test_saved_copy_round_trips_with_open_connection_closed_out() {
+        let path = std::env::temp_dir().join(format!("hl_aggregator_session_{}.json", std::process::id()));
+        let mut stats = SessionStats::new(START);
+        stats.record_placed(&ExchangeId::Dydx);
+        stats.record_ws_connected(&ExchangeId::Dydx, START);
+        stats.save(&path, START + 30_000).unwrap();
+
+        let loaded = SessionStats::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.saved_ms, Some(START + 30_000));
+        assert_eq!(loaded.venues[&ExchangeId::Dydx].orders_placed, 1);
+        assert_eq!(loaded.venues[&ExchangeId::Dydx].ws_up_ms, 30_000);
+        assert_eq!(loaded.ws_uptime(&ExchangeId::Dydx, START + 30_000), Some(1.0));
+        let summary = loaded.summary(START + 30_000);
+        assert!(summary.contains("Orders: 1 placed, 0 filled, 0 cancelled"), "{}", summary);
+        assert!(summary.contains("websocket up 100.0%, 0 reconnects"), "{}", summary);
+    }
+}
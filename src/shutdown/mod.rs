@@ -0,0 +1,86 @@
+use crate::error::Result;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Phases of an orderly exit, run in declaration order. Input has already
+/// stopped by the time a shutdown runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShutdownStage {
+    // Feeds and other background tasks, so nothing writes while we flush
+    StopTasks,
+    // Journal and other append-only files
+    Flush,
+    DisarmSwitch,
+    // Prefs, alerts and other state saved for the next session
+    Persist,
+    RestoreTerminal,
+}
+
+type StepFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + 'a>>;
+
+struct Step<'a> {
+    stage: ShutdownStage,
+    name: String,
+    run: StepFuture<'a>,
+}
+
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+    pub completed: Vec<String>,
+    pub failed: Vec<(String, String)>,
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// An ordered list of teardown steps. Steps are futures, so nothing runs
+/// until `run`, and `run` consumes the sequence so each step runs once.
+pub struct Shutdown<'a> {
+    steps: Vec<Step<'a>>,
+    step_timeout: Duration,
+}
+
+impl<'a> Shutdown<'a> {
+    pub fn new(step_timeout: Duration) -> Self {
+        Self { steps: Vec::new(), step_timeout }
+    }
+
+    pub fn step(mut self, stage: ShutdownStage, name: impl Into<String>, run: impl Future<Output = Result<()>> + 'a) -> Self {
+        self.steps.push(Step { stage, name: name.into(), run: Box::pin(run) });
+        self
+    }
+
+    /// Run every step by stage, in registration order within a stage. A step
+    /// that fails or overruns its timeout is reported and the rest still run.
+    pub async fn run(self) -> ShutdownReport {
+        let mut steps = self.steps;
+        steps.sort_by_key(|step| step.stage);
+
+        let mut report = ShutdownReport::default();
+        for step in steps {
+            match tokio::time::timeout(self.step_timeout, step.run).await {
+                Ok(Ok(())) => {
+                    info!("Shutdown: {} done", step.name);
+                    report.completed.push(step.name);
+                }
+                Ok(Err(e)) => {
+                    error!("Shutdown: {} failed: {}", step.name, e);
+                    report.failed.push((step.name, e.to_string()));
+                }
+                Err(_) => {
+                    error!("Shutdown: {} timed out", step.name);
+                    report.failed.push((step.name, format!("timed out after {:?}", self.step_timeout)));
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests;
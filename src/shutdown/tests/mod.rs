@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod shutdown_tests {
+    use crate::shutdown::{Shutdown, ShutdownStage};
+    use crate::error::Result;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    struct MockComponent {
+        name: &'static str,
+        log: Log,
+        fail: bool,
+    }
+
+    impl MockComponent {
+        fn new(name: &'static str, log: &Log) -> Self {
+            Self { name, log: log.clone(), fail: false }
+        }
+
+        fn failing(name: &'static str, log: &Log) -> Self {
+            Self { fail: true, ..Self::new(name, log) }
+        }
+
+        async fn flush(&self) -> Result<()> {
+            self.log.lock().unwrap().push(self.name.to_string());
+            if self.fail {
+                return Err(std::io::Error::other(format!("{} flush failed", self.name)).into());
+            }
+            Ok(())
+        }
+    }
+
+    fn calls(log: &Log) -> Vec<String> {
+        log.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn test_steps_run_in_stage_order_exactly_once() {
+        let log = Log::default();
+        let terminal = MockComponent::new("terminal", &log);
+        let prefs = MockComponent::new("prefs", &log);
+        let switch = MockComponent::new("switch", &log);
+        let journal = MockComponent::new("journal", &log);
+        let recorder = MockComponent::new("recorder", &log);
+        let feeds = MockComponent::new("feeds", &log);
+
+        // Registered out of order on purpose
+        let report = Shutdown::new(Duration::from_secs(1))
+            .step(ShutdownStage::RestoreTerminal, "terminal", terminal.flush())
+            .step(ShutdownStage::Persist, "prefs", prefs.flush())
+            .step(ShutdownStage::DisarmSwitch, "switch", switch.flush())
+            .step(ShutdownStage::Flush, "journal", journal.flush())
+            .step(ShutdownStage::Flush, "recorder", recorder.flush())
+            .step(ShutdownStage::StopTasks, "feeds", feeds.flush())
+            .run()
+            .await;
+
+        let expected = ["feeds", "journal", "recorder", "switch", "prefs", "terminal"];
+        assert_eq!(calls(&log), expected);
+        assert_eq!(report.completed, expected);
+        assert!(report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_failed_step_does_not_stop_later_steps() {
+        let log = Log::default();
+        let journal = MockComponent::failing("journal", &log);
+        let terminal = MockComponent::new("terminal", &log);
+
+        let report = Shutdown::new(Duration::from_secs(1))
+            .step(ShutdownStage::Flush, "journal", journal.flush())
+            .step(ShutdownStage::RestoreTerminal, "terminal", terminal.flush())
+            .run()
+            .await;
+
+        assert_eq!(calls(&log), ["journal", "terminal"]);
+        assert_eq!(report.completed, ["terminal"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "journal");
+        assert!(!report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_hung_step_times_out() {
+        let log = Log::default();
+        let terminal = MockComponent::new("terminal", &log);
+
+        let report = Shutdown::new(Duration::from_millis(50))
+            .step(ShutdownStage::StopTasks, "feeds", async {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                Ok(())
+            })
+            .step(ShutdownStage::RestoreTerminal, "terminal", terminal.flush())
+            .run()
+            .await;
+
+        assert_eq!(calls(&log), ["terminal"]);
+        assert_eq!(report.failed[0].0, "feeds");
+        assert!(report.failed[0].1.contains("timed out"));
+    }
+}
@@ -0,0 +1,84 @@
+//! Builders shared by the test modules. Anything not passed in is a plain
+//! default: BTC, one order per level, a market order at 1x, no fees.
+
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::aggregator::types::{BookSource, Level, OrderBook};
+use crate::trading::orders::Order;
+use crate::trading::positions::episodes::Fill;
+use crate::trading::{OrderType, TradeRequest};
+
+pub fn levels(levels: &[(f64, f64)]) -> Vec<Level> {
+    levels.iter().map(|&(price, size)| Level { price, size, orders: 1 }).collect()
+}
+
+pub fn book(exchange: ExchangeId, bids: &[(f64, f64)], asks: &[(f64, f64)], timestamp: u64) -> OrderBook {
+    OrderBook {
+        exchange,
+        symbol: "BTC".to_string(),
+        bids: levels(bids),
+        asks: levels(asks),
+        timestamp,
+        venue_timestamp: None,
+        source: BookSource::Websocket,
+    }
+}
+
+// A polled top of book where either side may be missing
+pub fn polled_top(exchange: ExchangeId, bid: Option<f64>, ask: Option<f64>, timestamp: u64) -> OrderBook {
+    let side = |price: Option<f64>| price.map(|price| Level { price, size: 1.0, orders: 1 }).into_iter().collect();
+    OrderBook {
+        exchange,
+        symbol: "BTC".to_string(),
+        bids: side(bid),
+        asks: side(ask),
+        timestamp,
+        venue_timestamp: None,
+        source: BookSource::Polled,
+    }
+}
+
+pub fn order(exchange: ExchangeId, id: &str) -> Order {
+    Order {
+        exchange,
+        asset: "BTC".to_string(),
+        size: 0.1,
+        price: 50_000.0,
+        side: "B".to_string(),
+        status: "Open".to_string(),
+        order_id: id.to_string(),
+    }
+}
+
+pub fn request(order_type: OrderType, is_buy: bool, usd_value: f64) -> TradeRequest {
+    TradeRequest {
+        asset: Symbol::perp("BTC"),
+        is_buy,
+        order_type,
+        usd_value,
+        price: None,
+        leverage: 1,
+        cross_margin: None,
+        reduce_only: false,
+        strategy_id: None,
+        max_slippage_bps: None,
+        post_only: false,
+        time_in_force: None,
+        good_til_secs: None,
+        cloid: None,
+    }
+}
+
+pub fn fill(exchange: ExchangeId, is_buy: bool, price: f64, size: f64, time: i64) -> Fill {
+    Fill {
+        exchange,
+        asset: "BTC".to_string(),
+        is_buy,
+        price,
+        size,
+        fee: 0.0,
+        time,
+        order_id: String::new(),
+        fill_id: String::new(),
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, Utc};
+
+pub const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Zone timestamps are shown in. Local follows the machine, including DST.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DisplayTimezone {
+    #[default]
+    Local,
+    Utc,
+    Fixed(FixedOffset),
+}
+
+impl FromStr for DisplayTimezone {
+    type Err = String;
+
+    /// "local", "utc", or an offset like "+02:00", "-0530" or "UTC+2".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        match trimmed.to_lowercase().as_str() {
+            "local" | "" => return Ok(Self::Local),
+            "utc" | "z" => return Ok(Self::Utc),
+            _ => {}
+        }
+        let offset = trimmed.strip_prefix("UTC").or_else(|| trimmed.strip_prefix("utc")).unwrap_or(trimmed);
+        let (sign, rest) = match offset.chars().next() {
+            Some('+') => (1, &offset[1..]),
+            Some('-') => (-1, &offset[1..]),
+            _ => return Err(format!("Unknown timezone '{}': use local, utc or an offset like +02:00", trimmed)),
+        };
+        let digits: String = rest.chars().filter(|c| *c != ':').collect();
+        let (hours, minutes) = match digits.len() {
+            1 | 2 => (digits.parse::<i32>().ok(), Some(0)),
+            4 => (digits[..2].parse::<i32>().ok(), digits[2..].parse::<i32>().ok()),
+            _ => (None, None),
+        };
+        let (Some(hours), Some(minutes)) = (hours, minutes) else {
+            return Err(format!("Invalid offset '{}'", trimmed));
+        };
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(Self::Fixed)
+            .ok_or_else(|| format!("Offset out of range '{}'", trimmed))
+    }
+}
+
+impl fmt::Display for DisplayTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local => write!(f, "local"),
+            Self::Utc => write!(f, "UTC"),
+            Self::Fixed(offset) => write!(f, "UTC{}", offset),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeDisplay {
+    pub timezone: DisplayTimezone,
+    // chrono strftime pattern
+    pub format: String,
+}
+
+impl Default for TimeDisplay {
+    fn default() -> Self {
+        Self { timezone: DisplayTimezone::Local, format: DEFAULT_TIME_FORMAT.to_string() }
+    }
+}
+
+static DISPLAY: OnceLock<TimeDisplay> = OnceLock::new();
+
+/// Set the session's display settings; only the first call takes effect.
+pub fn init(display: TimeDisplay) {
+    let _ = DISPLAY.set(display);
+}
+
+fn display() -> &'static TimeDisplay {
+    DISPLAY.get_or_init(TimeDisplay::default)
+}
+
+/// `ms` in `timezone` with a strftime `format`; empty for out-of-range input.
+pub fn format_at(ms: i64, timezone: DisplayTimezone, format: &str) -> String {
+    let Some(utc) = DateTime::<Utc>::from_timestamp_millis(ms) else { return String::new() };
+    match timezone {
+        DisplayTimezone::Local => utc.with_timezone(&Local).format(format).to_string(),
+        DisplayTimezone::Utc => utc.format(format).to_string(),
+        DisplayTimezone::Fixed(offset) => utc.with_timezone(&offset).format(format).to_string(),
+    }
+}
+
+/// ISO-8601 with an explicit offset, e.g. "2024-03-10T03:00:00.000-04:00".
+pub fn iso_at(ms: i64, timezone: DisplayTimezone) -> String {
+    let Some(utc) = DateTime::<Utc>::from_timestamp_millis(ms) else { return String::new() };
+    match timezone {
+        DisplayTimezone::Local => utc.with_timezone(&Local).to_rfc3339_opts(SecondsFormat::Millis, false),
+        DisplayTimezone::Utc => utc.to_rfc3339_opts(SecondsFormat::Millis, false),
+        DisplayTimezone::Fixed(offset) => utc.with_timezone(&offset).to_rfc3339_opts(SecondsFormat::Millis, false),
+    }
+}
+
+/// Millis timestamp for the UI and logs, in the configured zone and format.
+pub fn fmt_ts(ms: i64) -> String {
+    let display = display();
+    format_at(ms, display.timezone, &display.format)
+}
+
+pub fn fmt_now() -> String {
+    fmt_ts(Utc::now().timestamp_millis())
+}
+
+/// Millis timestamp for CSV/JSON exports: always ISO-8601 with offset.
+pub fn iso(ms: i64) -> String {
+    iso_at(ms, display().timezone)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod timefmt_tests {
+    use chrono::FixedOffset;
+    use crate::timefmt::{format_at, iso_at, DisplayTimezone, DEFAULT_TIME_FORMAT};
+
+    // 2024-03-10 07:00:00 UTC: US clocks spring forward from 02:00 EST to 03:00 EDT
+    const SPRING_FORWARD: i64 = 1_710_054_000_000;
+    // 2024-11-03 06:00:00 UTC: US clocks fall back from 02:00 EDT to 01:00 EST
+    const FALL_BACK: i64 = 1_730_613_600_000;
+
+    #[test]
+    fn test_utc_option() {
+        assert_eq!(format_at(SPRING_FORWARD, DisplayTimezone::Utc, DEFAULT_TIME_FORMAT), "2024-03-10 07:00:00");
+        assert_eq!(iso_at(SPRING_FORWARD, DisplayTimezone::Utc), "2024-03-10T07:00:00.000+00:00");
+        assert_eq!(format_at(SPRING_FORWARD + 1_500, DisplayTimezone::Utc, "%H:%M:%S%.3f"), "07:00:01.500");
+    }
+
+    #[test]
+    fn test_fixed_offset() {
+        let zone: DisplayTimezone = "+05:30".parse().unwrap();
+        assert_eq!(format_at(SPRING_FORWARD, zone, DEFAULT_TIME_FORMAT), "2024-03-10 12:30:00");
+        assert_eq!(iso_at(SPRING_FORWARD, zone), "2024-03-10T12:30:00.000+05:30");
+        let west: DisplayTimezone = "UTC-8".parse().unwrap();
+        assert_eq!(iso_at(SPRING_FORWARD, west), "2024-03-09T23:00:00.000-08:00");
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!("local".parse::<DisplayTimezone>().unwrap(), DisplayTimezone::Local);
+        assert_eq!(" UTC ".parse::<DisplayTimezone>().unwrap(), DisplayTimezone::Utc);
+        assert_eq!("-0530".parse::<DisplayTimezone>().unwrap(), DisplayTimezone::Fixed(FixedOffset::west_opt(5 * 3600 + 1800).unwrap()));
+        assert!("Europe/Paris".parse::<DisplayTimezone>().is_err());
+        assert!("+25:00".parse::<DisplayTimezone>().is_err());
+        assert!("+123".parse::<DisplayTimezone>().is_err());
+    }
+
+    #[test]
+    fn test_local_follows_dst_per_instant() {
+        // POSIX rule for US Eastern, so no tz database is needed. Only this
+        // test reads the local zone.
+        std::env::set_var("TZ", "EST5EDT,M3.2.0,M11.1.0");
+
+        // The offset comes from each timestamp, not from the current date
+        assert_eq!(iso_at(SPRING_FORWARD - 1_000, DisplayTimezone::Local), "2024-03-10T01:59:59.000-05:00");
+        assert_eq!(iso_at(SPRING_FORWARD, DisplayTimezone::Local), "2024-03-10T03:00:00.000-04:00");
+
+        // 01:xx happens twice on the fall-back day; the offset tells them apart
+        assert_eq!(iso_at(FALL_BACK - 1_000, DisplayTimezone::Local), "2024-11-03T01:59:59.000-04:00");
+        assert_eq!(iso_at(FALL_BACK, DisplayTimezone::Local), "2024-11-03T01:00:00.000-05:00");
+        assert_eq!(format_at(FALL_BACK, DisplayTimezone::Local, "%H:%M %:z"), "01:00 -05:00");
+    }
+}
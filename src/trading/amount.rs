@@ -0,0 +1,164 @@
+use crate::aggregator::symbol::Symbol;
+use crate::error::AggregatorError;
+
+// Anything above this is a typo, not an order
+const MAX_USD: f64 = 100_000_000.0;
+
+/// A typed amount, before it is turned into an order notional.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Amount {
+    // "25000", "$25k", "1.5m", "300 usdc"
+    Usd(f64),
+    // "0.5btc", in units of the market's base asset
+    Base(f64),
+    // "50%" of free collateral
+    PercentOfCollateral(f64),
+}
+
+/// What an amount is resolved against: the market it is for, the price it
+/// is sized at and the account's free collateral, when known.
+#[derive(Debug, Clone, Copy)]
+pub struct AmountContext<'a> {
+    pub symbol: &'a Symbol,
+    pub price: Option<f64>,
+    pub free_collateral: Option<f64>,
+    // Percent amounts are margin; the notional is this multiple of it
+    pub leverage: f64,
+}
+
+impl Amount {
+    /// Order notional in USD.
+    pub fn usd_value(&self, context: &AmountContext) -> Result<f64, AggregatorError> {
+        let usd = match *self {
+            Self::Usd(usd) => usd,
+            Self::Base(size) => {
+                let price = context.price.filter(|price| price.is_finite() && *price > 0.0)
+                    .ok_or_else(|| invalid(format!("no {} price yet to convert {} {}", context.symbol, size, context.symbol.base())))?;
+                size * price
+            }
+            Self::PercentOfCollateral(percent) => {
+                let collateral = context.free_collateral
+                    .ok_or_else(|| invalid("free collateral not loaded yet for a percent size".to_string()))?;
+                if collateral <= 0.0 {
+                    return Err(invalid(format!("no free collateral to take {}% of", percent)));
+                }
+                collateral * percent / 100.0 * context.leverage.max(1.0)
+            }
+        };
+        check_usd(usd)
+    }
+
+    /// How the amount resolved, e.g. "0.5 BTC = $32000.00".
+    pub fn describe(&self, context: &AmountContext, usd: f64) -> String {
+        match self {
+            Self::Usd(_) => format!("${:.2}", usd),
+            Self::Base(size) => format!("{} {} = ${:.2}", size, context.symbol.base(), usd),
+            Self::PercentOfCollateral(percent) => format!("{}% of free collateral at {}x = ${:.2}", percent, context.leverage.max(1.0), usd),
+        }
+    }
+}
+
+/// Parse an amount field: a USD value with an optional k/m suffix, a size
+/// in the market's base asset ("0.5btc"), or a percent of free collateral
+/// ("50%"). Commas are only accepted as thousands separators.
+pub fn parse_amount(input: &str, context: &AmountContext) -> Result<Amount, AggregatorError> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(invalid("enter an amount".to_string()));
+    }
+    let lowered = trimmed.to_lowercase();
+    let (dollar, rest) = match lowered.strip_prefix('$') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, lowered.as_str()),
+    };
+    if rest.starts_with('-') {
+        return Err(invalid(format!("'{}' must be positive", trimmed)));
+    }
+
+    let split = rest.find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | ',' | '_'))).unwrap_or(rest.len());
+    let (number, unit) = (&rest[..split], rest[split..].trim());
+    let value = parse_number(number, trimmed)?;
+
+    let amount = match unit {
+        "" | "usd" | "usdc" => Amount::Usd(value),
+        "k" => Amount::Usd(value * 1_000.0),
+        "m" => Amount::Usd(value * 1_000_000.0),
+        "%" => {
+            if dollar {
+                return Err(invalid(format!("'{}' mixes $ with a percent", trimmed)));
+            }
+            if value > 100.0 {
+                return Err(invalid(format!("{}% is more than all free collateral", value)));
+            }
+            Amount::PercentOfCollateral(value)
+        }
+        unit if unit.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            if dollar {
+                return Err(invalid(format!("'{}' mixes $ with a {} size", trimmed, unit.to_uppercase())));
+            }
+            if !unit.eq_ignore_ascii_case(context.symbol.base()) {
+                return Err(invalid(format!(
+                    "unknown unit '{}'; use k, m, %, or {} for this market",
+                    unit,
+                    context.symbol.base().to_lowercase()
+                )));
+            }
+            Amount::Base(value)
+        }
+        unit => return Err(invalid(format!("unknown unit '{}' in '{}'", unit, trimmed))),
+    };
+    if value <= 0.0 {
+        return Err(invalid(format!("'{}' must be more than zero", trimmed)));
+    }
+    if let Amount::Usd(usd) = amount {
+        check_usd(usd)?;
+    }
+    Ok(amount)
+}
+
+/// Parse and resolve in one step, for fields that only need the notional.
+pub fn parse_usd_value(input: &str, context: &AmountContext) -> Result<(Amount, f64), AggregatorError> {
+    let amount = parse_amount(input, context)?;
+    let usd = amount.usd_value(context)?;
+    Ok((amount, usd))
+}
+
+// Digits with at most one decimal point; commas must group thousands
+fn parse_number(number: &str, input: &str) -> Result<f64, AggregatorError> {
+    if number.is_empty() || !number.chars().any(|c| c.is_ascii_digit()) {
+        return Err(invalid(format!("'{}' does not start with a number", input)));
+    }
+    if number.matches('.').count() > 1 {
+        return Err(invalid(format!("'{}' has more than one decimal point", input)));
+    }
+    let whole = number.split('.').next().unwrap_or_default();
+    if number.contains(',') {
+        let groups: Vec<&str> = whole.split(',').collect();
+        let grouped = !groups[0].is_empty()
+            && groups[0].len() <= 3
+            && groups[1..].iter().all(|group| group.len() == 3)
+            && !number.split('.').nth(1).is_some_and(|fraction| fraction.contains(','));
+        if !grouped {
+            return Err(invalid(format!("'{}' is ambiguous; use '.' for decimals and ',' only between thousands", input)));
+        }
+    }
+    let cleaned: String = number.chars().filter(|c| !matches!(c, ',' | '_')).collect();
+    cleaned.parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| invalid(format!("'{}' is not a number", input)))
+}
+
+fn check_usd(usd: f64) -> Result<f64, AggregatorError> {
+    if !usd.is_finite() || usd <= 0.0 {
+        return Err(invalid(format!("${:.2} must be more than zero", usd)));
+    }
+    if usd > MAX_USD {
+        return Err(invalid(format!("${:.0} is above the ${:.0} limit", usd, MAX_USD)));
+    }
+    Ok(usd)
+}
+
+fn invalid(message: String) -> AggregatorError {
+    AggregatorError::InvalidAmount(message)
+}
@@ -0,0 +1,523 @@
+use crate::error::{AggregatorError, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use super::TradeRequest;
+use super::confirmation::{Confirmation, Quote};
+use super::orders::Order;
+use super::positions::episodes::Fill;
+use super::router::TradingRouter;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::aggregator::trade_flow::TradePrint;
+
+pub mod ma_cross;
+pub mod quoter;
+
+/// Top of book for one market, as strategies see it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookTop {
+    pub exchange: ExchangeId,
+    pub symbol: Symbol,
+    pub best_bid: f64,
+    pub best_ask: f64,
+    // Millis timestamp
+    pub time: i64,
+}
+
+impl BookTop {
+    pub fn mid(&self) -> f64 {
+        (self.best_bid + self.best_ask) / 2.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StrategyAction {
+    Place { exchange: ExchangeId, request: TradeRequest },
+    Cancel { exchange: ExchangeId, asset: String, order_id: String },
+    Notify(String),
+}
+
+impl StrategyAction {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Place { exchange, request } => format!(
+                "{} {} ${:.2}{} on {}",
+                if request.is_buy { "buy" } else { "sell" },
+                request.asset,
+                request.usd_value,
+                if request.reduce_only { " reduce-only" } else { "" },
+                exchange
+            ),
+            Self::Cancel { exchange, asset, order_id } => format!("cancel {} order {} on {}", asset, order_id, exchange),
+            Self::Notify(message) => format!("notify: {}", message),
+        }
+    }
+}
+
+/// An automated strategy. Callbacks receive normalized events and return the
+/// actions to take; the runner decides whether and how they execute.
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+
+    /// Markets the strategy wants book and trade events for
+    fn markets(&self) -> Vec<(ExchangeId, Symbol)>;
+
+    fn on_book(&mut self, _book: &BookTop) -> Vec<StrategyAction> {
+        Vec::new()
+    }
+
+    fn on_trade(&mut self, _exchange: &ExchangeId, _symbol: &Symbol, _print: &TradePrint) -> Vec<StrategyAction> {
+        Vec::new()
+    }
+
+    /// Fills of orders this strategy placed
+    fn on_fill(&mut self, _fill: &Fill) -> Vec<StrategyAction> {
+        Vec::new()
+    }
+
+    fn on_timer(&mut self, _now_ms: i64) -> Vec<StrategyAction> {
+        Vec::new()
+    }
+
+    /// The venue accepted an order this strategy placed
+    fn on_order(&mut self, _exchange: &ExchangeId, _request: &TradeRequest, _order_id: &str) {}
+
+    /// Called when the strategy is stopped, to pull whatever it left resting
+    fn on_stop(&mut self) -> Vec<StrategyAction> {
+        Vec::new()
+    }
+
+    /// Extra state for the monitor screen, e.g. live quotes
+    fn detail(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Where live actions go. The router in the app; a scripted venue in tests.
+/// Driven from the UI loop, so the futures needn't be `Send`.
+#[async_trait(?Send)]
+pub trait StrategyExecutor {
+    /// Returns the venue order id. `price` is what the order was sized at.
+    async fn place(&mut self, exchange: &ExchangeId, request: TradeRequest, price: f64) -> Result<String>;
+    async fn cancel(&mut self, exchange: &ExchangeId, asset: &str, order_id: &str) -> Result<()>;
+}
+
+#[async_trait(?Send)]
+impl StrategyExecutor for TradingRouter {
+    // Nobody is there to confirm, so orders above the policy's first tier fail.
+    // Strategies pace themselves and may repeat an order on purpose, e.g. a
+    // quote replaced at the same price after a fill, so duplicates are forced.
+    async fn place(&mut self, exchange: &ExchangeId, request: TradeRequest, price: f64) -> Result<String> {
+        let quote = Quote { price, size_step: None };
+        let (message, order_id) = self.place_trade(exchange, request, quote, Confirmation::None, true).await.result?;
+        if order_id.is_empty() {
+            return Err(AggregatorError::OrderRejected { reason: message });
+        }
+        Ok(order_id)
+    }
+
+    async fn cancel(&mut self, exchange: &ExchangeId, asset: &str, order_id: &str) -> Result<()> {
+        self.cancel_order(&Order {
+            exchange: exchange.clone(),
+            asset: asset.to_string(),
+            size: 0.0,
+            price: 0.0,
+            side: String::new(),
+            status: "Open".to_string(),
+            order_id: order_id.to_string(),
+        }).await?;
+        Ok(())
+    }
+}
+
+/// Hard limits every strategy order must pass before it is sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskLimits {
+    pub max_order_usd: f64,
+    // Absolute position notional per strategy, at the order's price
+    pub max_position_usd: f64,
+}
+
+impl RiskLimits {
+    /// Reduce-only orders always pass; they can only shrink exposure.
+    pub fn check(&self, request: &TradeRequest, position: f64, price: f64) -> Result<(), String> {
+        if request.reduce_only {
+            return Ok(());
+        }
+        if request.usd_value > self.max_order_usd {
+            return Err(format!("order ${:.2} over the ${:.2} limit", request.usd_value, self.max_order_usd));
+        }
+        let delta = if request.is_buy { request.usd_value } else { -request.usd_value };
+        let after = (position * price + delta).abs();
+        if after > self.max_position_usd {
+            return Err(format!("position would be ${:.2}, over the ${:.2} limit", after, self.max_position_usd));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ActionOutcome {
+    Executed { order_id: Option<String> },
+    // Dry run: filled on paper at the current book
+    Simulated,
+    Blocked { reason: String },
+    Failed { error: String },
+}
+
+/// One journaled strategy action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionRecord {
+    pub timestamp: i64,
+    pub strategy_id: Uuid,
+    pub strategy: String,
+    pub action: String,
+    pub dry_run: bool,
+    pub outcome: ActionOutcome,
+}
+
+/// Append-only log of every strategy action, one JSON record per line.
+/// Placed orders also reach the trade journal through the router.
+pub struct ActionLog {
+    path: PathBuf,
+}
+
+impl ActionLog {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("trading_aggregator");
+        fs::create_dir_all(&config_dir)?;
+        Ok(config_dir.join("strategy_actions.jsonl"))
+    }
+
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &ActionRecord) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+}
+
+/// Position and PnL from a strategy's own fills, marked to the latest mid.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StrategyPnl {
+    // Signed base size
+    pub position: f64,
+    pub entry_avg: f64,
+    pub realized: f64,
+    pub fees: f64,
+    pub mark: Option<f64>,
+}
+
+impl StrategyPnl {
+    pub fn apply_fill(&mut self, is_buy: bool, price: f64, size: f64, fee: f64) {
+        let signed = if is_buy { size } else { -size };
+        self.fees += fee;
+        if self.position == 0.0 || self.position.signum() == signed.signum() {
+            let total = self.position.abs() + size;
+            self.entry_avg = (self.entry_avg * self.position.abs() + price * size) / total;
+            self.position += signed;
+            return;
+        }
+        let closing = size.min(self.position.abs());
+        self.realized += closing * (price - self.entry_avg) * self.position.signum();
+        self.position += signed;
+        if self.position.abs() < 1e-12 {
+            self.position = 0.0;
+            self.entry_avg = 0.0;
+        } else if self.position.signum() == signed.signum() {
+            // Flipped: the remainder opened at this price
+            self.entry_avg = price;
+        }
+    }
+
+    pub fn unrealized(&self) -> f64 {
+        self.mark.map_or(0.0, |mark| (mark - self.entry_avg) * self.position)
+    }
+
+    pub fn total(&self) -> f64 {
+        self.realized + self.unrealized() - self.fees
+    }
+}
+
+/// Snapshot of one strategy for the monitor screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyStatus {
+    pub id: Uuid,
+    pub name: String,
+    pub running: bool,
+    pub pnl: StrategyPnl,
+    pub last_action: Option<ActionRecord>,
+    pub detail: Option<String>,
+}
+
+struct Slot {
+    id: Uuid,
+    strategy: Box<dyn Strategy>,
+    running: bool,
+    pnl: StrategyPnl,
+    last_action: Option<ActionRecord>,
+}
+
+/// Runs strategies: routes events to them, gates and executes their
+/// actions, and journals every action with the strategy's id.
+pub struct StrategyRunner {
+    slots: Vec<Slot>,
+    dry_run: bool,
+    limits: RiskLimits,
+    log: Option<ActionLog>,
+    // Live order id -> strategy, so venue fills can be attributed
+    orders: HashMap<(ExchangeId, String), Uuid>,
+    // Latest top of book per market, for dry-run fills
+    books: HashMap<(ExchangeId, Symbol), BookTop>,
+    // Why new live orders are held back, e.g. the dead man's switch is down
+    hold: Option<String>,
+    notifications: Vec<String>,
+}
+
+impl StrategyRunner {
+    pub fn new(dry_run: bool, limits: RiskLimits) -> Self {
+        Self {
+            slots: Vec::new(),
+            dry_run,
+            limits,
+            log: None,
+            orders: HashMap::new(),
+            books: HashMap::new(),
+            hold: None,
+            notifications: Vec::new(),
+        }
+    }
+
+    pub fn with_log(mut self, log: ActionLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Register a strategy, stopped. Returns its id.
+    pub fn add(&mut self, strategy: Box<dyn Strategy>) -> Uuid {
+        let id = Uuid::new_v4();
+        self.slots.push(Slot { id, strategy, running: false, pnl: StrategyPnl::default(), last_action: None });
+        id
+    }
+
+    pub fn set_running(&mut self, id: Uuid, running: bool) {
+        if let Some(slot) = self.slots.iter_mut().find(|slot| slot.id == id) {
+            slot.running = running;
+        }
+    }
+
+    /// Stop a strategy, executing the cancels it asks for on the way out.
+    pub async fn stop(&mut self, id: Uuid, executor: &mut dyn StrategyExecutor) -> Vec<ActionRecord> {
+        let Some(index) = self.slots.iter().position(|slot| slot.id == id && slot.running) else {
+            return Vec::new();
+        };
+        let actions = self.slots[index].strategy.on_stop();
+        let records = self.execute(index, actions, executor).await;
+        self.slots[index].running = false;
+        records
+    }
+
+    pub async fn stop_all(&mut self, executor: &mut dyn StrategyExecutor) -> Vec<ActionRecord> {
+        let mut records = Vec::new();
+        let running: Vec<Uuid> = self.slots.iter().filter(|slot| slot.running).map(|slot| slot.id).collect();
+        for id in running {
+            records.extend(self.stop(id, executor).await);
+        }
+        records
+    }
+
+    /// Block new live orders while `reason` is set. Cancels still go out.
+    pub fn set_hold(&mut self, reason: Option<String>) {
+        self.hold = reason;
+    }
+
+    pub fn statuses(&self) -> Vec<StrategyStatus> {
+        self.slots.iter()
+            .map(|slot| StrategyStatus {
+                id: slot.id,
+                name: slot.strategy.name().to_string(),
+                running: slot.running,
+                pnl: slot.pnl,
+                last_action: slot.last_action.clone(),
+                detail: slot.strategy.detail(),
+            })
+            .collect()
+    }
+
+    /// Markets any running strategy wants events for
+    pub fn markets(&self) -> Vec<(ExchangeId, Symbol)> {
+        let mut markets: Vec<(ExchangeId, Symbol)> = Vec::new();
+        for slot in self.slots.iter().filter(|slot| slot.running) {
+            for market in slot.strategy.markets() {
+                if !markets.contains(&market) {
+                    markets.push(market);
+                }
+            }
+        }
+        markets
+    }
+
+    /// Notify actions since the last call, for the app's notifier
+    pub fn take_notifications(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.notifications)
+    }
+
+    pub async fn on_book(&mut self, book: BookTop, executor: &mut dyn StrategyExecutor) -> Vec<ActionRecord> {
+        for slot in &mut self.slots {
+            if slot.strategy.markets().contains(&(book.exchange.clone(), book.symbol.clone())) {
+                slot.pnl.mark = Some(book.mid());
+            }
+        }
+        self.books.insert((book.exchange.clone(), book.symbol.clone()), book.clone());
+        self.dispatch(executor, |strategy| {
+            if strategy.markets().contains(&(book.exchange.clone(), book.symbol.clone())) {
+                strategy.on_book(&book)
+            } else {
+                Vec::new()
+            }
+        }).await
+    }
+
+    pub async fn on_trade(&mut self, exchange: &ExchangeId, symbol: &Symbol, print: &TradePrint, executor: &mut dyn StrategyExecutor) -> Vec<ActionRecord> {
+        let market = (exchange.clone(), symbol.clone());
+        self.dispatch(executor, |strategy| {
+            if strategy.markets().contains(&market) {
+                strategy.on_trade(exchange, symbol, print)
+            } else {
+                Vec::new()
+            }
+        }).await
+    }
+
+    /// A venue fill; only the strategy that placed the order sees it.
+    pub async fn on_fill(&mut self, fill: &Fill, executor: &mut dyn StrategyExecutor) -> Vec<ActionRecord> {
+        let Some(owner) = self.orders.get(&(fill.exchange.clone(), fill.order_id.clone())).copied() else {
+            return Vec::new();
+        };
+        let Some(index) = self.slots.iter().position(|slot| slot.id == owner) else { return Vec::new() };
+        self.slots[index].pnl.apply_fill(fill.is_buy, fill.price, fill.size, fill.fee);
+        if !self.slots[index].running {
+            return Vec::new();
+        }
+        let actions = self.slots[index].strategy.on_fill(fill);
+        self.execute(index, actions, executor).await
+    }
+
+    pub async fn on_timer(&mut self, now_ms: i64, executor: &mut dyn StrategyExecutor) -> Vec<ActionRecord> {
+        self.dispatch(executor, |strategy| strategy.on_timer(now_ms)).await
+    }
+
+    async fn dispatch<F>(&mut self, executor: &mut dyn StrategyExecutor, mut event: F) -> Vec<ActionRecord>
+    where
+        F: FnMut(&mut dyn Strategy) -> Vec<StrategyAction>,
+    {
+        let mut records = Vec::new();
+        for index in 0..self.slots.len() {
+            if !self.slots[index].running {
+                continue;
+            }
+            let actions = event(self.slots[index].strategy.as_mut());
+            records.extend(self.execute(index, actions, executor).await);
+        }
+        records
+    }
+
+    async fn execute(&mut self, index: usize, actions: Vec<StrategyAction>, executor: &mut dyn StrategyExecutor) -> Vec<ActionRecord> {
+        let mut records = Vec::new();
+        for action in actions {
+            let outcome = self.perform(index, &action, executor).await;
+            let slot = &mut self.slots[index];
+            let record = ActionRecord {
+                timestamp: Utc::now().timestamp_millis(),
+                strategy_id: slot.id,
+                strategy: slot.strategy.name().to_string(),
+                action: action.describe(),
+                dry_run: self.dry_run,
+                outcome,
+            };
+            if let Some(log) = &self.log {
+                if let Err(e) = log.append(&record) {
+                    tracing::error!("Failed to journal strategy action: {}", e);
+                }
+            }
+            slot.last_action = Some(record.clone());
+            records.push(record);
+        }
+        records
+    }
+
+    async fn perform(&mut self, index: usize, action: &StrategyAction, executor: &mut dyn StrategyExecutor) -> ActionOutcome {
+        let id = self.slots[index].id;
+        match action {
+            StrategyAction::Notify(message) => {
+                self.notifications.push(format!("[{}] {}", self.slots[index].strategy.name(), message));
+                ActionOutcome::Executed { order_id: None }
+            }
+            StrategyAction::Place { exchange, request } => {
+                let book = self.books.get(&(exchange.clone(), request.asset.clone()));
+                let Some(price) = request.price.or_else(|| book.map(|book| if request.is_buy { book.best_ask } else { book.best_bid })) else {
+                    return ActionOutcome::Blocked { reason: "no price for the market yet".to_string() };
+                };
+                if let Err(reason) = self.limits.check(request, self.slots[index].pnl.position, price) {
+                    return ActionOutcome::Blocked { reason };
+                }
+                if let Some(reason) = self.hold.as_ref().filter(|_| !self.dry_run) {
+                    return ActionOutcome::Blocked { reason: reason.clone() };
+                }
+                if self.dry_run {
+                    self.slots[index].pnl.apply_fill(request.is_buy, price, request.usd_value / price, 0.0);
+                    let fill = Fill {
+                        exchange: exchange.clone(),
+                        asset: request.asset.to_string(),
+                        is_buy: request.is_buy,
+                        price,
+                        size: request.usd_value / price,
+                        fee: 0.0,
+                        time: Utc::now().timestamp_millis(),
+                        order_id: String::new(),
+                        fill_id: String::new(),
+                    };
+                    // Paper fills feed straight back; follow-up actions are dropped
+                    let _ = self.slots[index].strategy.on_fill(&fill);
+                    return ActionOutcome::Simulated;
+                }
+                match executor.place(exchange, request.clone().with_strategy(id), price).await {
+                    Ok(order_id) => {
+                        self.orders.insert((exchange.clone(), order_id.clone()), id);
+                        self.slots[index].strategy.on_order(exchange, request, &order_id);
+                        ActionOutcome::Executed { order_id: Some(order_id) }
+                    }
+                    Err(e) => ActionOutcome::Failed { error: e.to_string() },
+                }
+            }
+            StrategyAction::Cancel { exchange, asset, order_id } => {
+                if self.dry_run {
+                    return ActionOutcome::Simulated;
+                }
+                match executor.cancel(exchange, asset, order_id).await {
+                    Ok(()) => ActionOutcome::Executed { order_id: Some(order_id.clone()) },
+                    Err(e) => ActionOutcome::Failed { error: e.to_string() },
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::trading::{OrderType, TradeRequest};
+use super::{BookTop, Strategy, StrategyAction};
+
+/// Parameters for the moving-average cross example.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaCrossConfig {
+    pub exchange: ExchangeId,
+    pub symbol: Symbol,
+    // Periods are counted in timer ticks
+    pub fast: usize,
+    pub slow: usize,
+    // Target position size in USD
+    pub notional: f64,
+}
+
+impl std::str::FromStr for MaCrossConfig {
+    type Err = String;
+
+    /// "exchange,symbol,fast,slow,notional", e.g. "hyperliquid,BTC,10,30,100".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let [exchange, symbol, fast, slow, notional] = parts.as_slice() else {
+            return Err(format!("Expected exchange,symbol,fast,slow,notional; got '{}'", s));
+        };
+        let exchange = exchange.parse::<ExchangeId>().map_err(|e| e.to_string())?;
+        let symbol = Symbol::parse_user_input(symbol).map_err(|e| e.to_string())?;
+        let fast: usize = fast.parse().map_err(|_| format!("Invalid fast period '{}'", fast))?;
+        let slow: usize = slow.parse().map_err(|_| format!("Invalid slow period '{}'", slow))?;
+        let notional: f64 = notional.parse().map_err(|_| format!("Invalid notional '{}'", notional))?;
+        if fast == 0 || fast >= slow {
+            return Err(format!("Fast period {} must be positive and below slow {}", fast, slow));
+        }
+        if notional <= 0.0 {
+            return Err(format!("Notional must be positive, got {}", notional));
+        }
+        Ok(Self { exchange, symbol, fast, slow, notional })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    Flat,
+    Long,
+    Short,
+}
+
+/// Samples the mid on every timer tick and holds a long while the fast
+/// average is above the slow one, a short while it is below.
+pub struct MaCross {
+    config: MaCrossConfig,
+    name: String,
+    mid: Option<f64>,
+    samples: VecDeque<f64>,
+    side: Side,
+}
+
+impl MaCross {
+    pub fn new(config: MaCrossConfig) -> Self {
+        let name = format!("MA cross {} {}/{}", config.symbol, config.fast, config.slow);
+        Self { config, name, mid: None, samples: VecDeque::new(), side: Side::Flat }
+    }
+
+    fn average(&self, period: usize) -> Option<f64> {
+        if self.samples.len() < period {
+            return None;
+        }
+        Some(self.samples.iter().rev().take(period).sum::<f64>() / period as f64)
+    }
+
+    fn order(&self, is_buy: bool, usd_value: f64) -> StrategyAction {
+        StrategyAction::Place {
+            exchange: self.config.exchange.clone(),
+            request: TradeRequest {
+                asset: self.config.symbol.clone(),
+                is_buy,
+                order_type: OrderType::Market,
+                usd_value,
+                price: None,
+                leverage: 1,
+                cross_margin: None,
+                reduce_only: false,
+                strategy_id: None,
+                max_slippage_bps: None,
+                post_only: false,
+                time_in_force: None,
+                good_til_secs: None,
+                cloid: None,
+            },
+        }
+    }
+}
+
+impl Strategy for MaCross {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn markets(&self) -> Vec<(ExchangeId, Symbol)> {
+        vec![(self.config.exchange.clone(), self.config.symbol.clone())]
+    }
+
+    fn on_book(&mut self, book: &BookTop) -> Vec<StrategyAction> {
+        self.mid = Some(book.mid());
+        Vec::new()
+    }
+
+    fn on_timer(&mut self, _now_ms: i64) -> Vec<StrategyAction> {
+        let Some(mid) = self.mid else { return Vec::new() };
+        self.samples.push_back(mid);
+        while self.samples.len() > self.config.slow {
+            self.samples.pop_front();
+        }
+        let (Some(fast), Some(slow)) = (self.average(self.config.fast), self.average(self.config.slow)) else {
+            return Vec::new();
+        };
+        let wanted = if fast > slow {
+            Side::Long
+        } else if fast < slow {
+            Side::Short
+        } else {
+            return Vec::new();
+        };
+        if wanted == self.side {
+            return Vec::new();
+        }
+        // Flipping closes the old side and opens the new one in one order
+        let usd_value = if self.side == Side::Flat { self.config.notional } else { self.config.notional * 2.0 };
+        self.side = wanted;
+        vec![
+            self.order(wanted == Side::Long, usd_value),
+            StrategyAction::Notify(format!(
+                "{} crossed {} ({:.4} vs {:.4})",
+                self.name,
+                if wanted == Side::Long { "up" } else { "down" },
+                fast,
+                slow
+            )),
+        ]
+    }
+}
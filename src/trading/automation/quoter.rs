@@ -0,0 +1,251 @@
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::trading::positions::episodes::Fill;
+use crate::trading::{OrderType, TradeRequest};
+use super::{BookTop, Strategy, StrategyAction};
+
+// Quotes are replaced at most this often, fills included
+pub const REQUOTE_INTERVAL_MS: i64 = 2_000;
+
+/// Parameters for the single-market quoter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoterConfig {
+    pub exchange: ExchangeId,
+    pub symbol: Symbol,
+    // Distance between bid and ask, in basis points of the mid
+    pub spread_bps: f64,
+    // Notional of each quote in USD
+    pub size_usd: f64,
+    // Mid move, in basis points of the quoted mid, that triggers a re-quote
+    pub tolerance_bps: f64,
+    // Inventory notional past which the side adding to it is pulled
+    pub max_inventory_usd: f64,
+}
+
+impl std::str::FromStr for QuoterConfig {
+    type Err = String;
+
+    /// "exchange,symbol,spread_bps,size_usd,tolerance_bps,max_inventory_usd",
+    /// e.g. "hyperliquid,ETH,20,50,5,500".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let [exchange, symbol, spread, size, tolerance, max_inventory] = parts.as_slice() else {
+            return Err(format!("Expected exchange,symbol,spread_bps,size_usd,tolerance_bps,max_inventory_usd; got '{}'", s));
+        };
+        let exchange = exchange.parse::<ExchangeId>().map_err(|e| e.to_string())?;
+        let symbol = Symbol::parse_user_input(symbol).map_err(|e| e.to_string())?;
+        let number = |value: &str, what: &str| -> Result<f64, String> {
+            match value.parse::<f64>() {
+                Ok(number) if number > 0.0 && number.is_finite() => Ok(number),
+                _ => Err(format!("{} must be a positive number, got '{}'", what, value)),
+            }
+        };
+        Ok(Self {
+            exchange,
+            symbol,
+            spread_bps: number(spread, "Spread")?,
+            size_usd: number(size, "Size")?,
+            tolerance_bps: number(tolerance, "Tolerance")?,
+            max_inventory_usd: number(max_inventory, "Max inventory")?,
+        })
+    }
+}
+
+/// One resting quote. The order id arrives once the venue accepts it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveQuote {
+    pub price: f64,
+    pub order_id: Option<String>,
+}
+
+/// Keeps one bid and one ask around the mid. Re-quotes by cancel/replace
+/// when the mid drifts past the tolerance or a quote fills, skews both
+/// prices against the inventory, and pulls the side that would grow it
+/// past the limit.
+pub struct SimpleQuoter {
+    config: QuoterConfig,
+    name: String,
+    // Signed base size from this strategy's fills
+    inventory: f64,
+    bid: Option<LiveQuote>,
+    ask: Option<LiveQuote>,
+    // Mid the live quotes were placed around
+    quoted_mid: Option<f64>,
+    last_quote_ms: Option<i64>,
+    // Set by a fill; the next allowed book update re-quotes
+    filled: bool,
+    // One side pulled for inventory, to notify once per episode
+    at_limit: bool,
+    mid: Option<f64>,
+}
+
+impl SimpleQuoter {
+    pub fn new(config: QuoterConfig) -> Self {
+        let name = format!("Quoter {} {}bps", config.symbol, config.spread_bps);
+        Self {
+            config,
+            name,
+            inventory: 0.0,
+            bid: None,
+            ask: None,
+            quoted_mid: None,
+            last_quote_ms: None,
+            filled: false,
+            at_limit: false,
+            mid: None,
+        }
+    }
+
+    pub fn bid(&self) -> Option<&LiveQuote> {
+        self.bid.as_ref()
+    }
+
+    pub fn ask(&self) -> Option<&LiveQuote> {
+        self.ask.as_ref()
+    }
+
+    pub fn inventory(&self) -> f64 {
+        self.inventory
+    }
+
+    /// Bid and ask prices wanted at `mid`, None for a pulled side. Both
+    /// shift against the inventory by up to half the spread, so a long
+    /// book sells more eagerly and buys less.
+    pub fn target(&self, mid: f64) -> (Option<f64>, Option<f64>) {
+        let half = mid * self.config.spread_bps / 20_000.0;
+        let exposure = self.inventory * mid;
+        let skew = (exposure / self.config.max_inventory_usd).clamp(-1.0, 1.0) * half;
+        let bid = (exposure < self.config.max_inventory_usd).then_some(mid - half - skew);
+        let ask = (exposure > -self.config.max_inventory_usd).then_some(mid + half - skew);
+        (bid, ask)
+    }
+
+    fn needs_requote(&self, mid: f64) -> bool {
+        if self.filled {
+            return true;
+        }
+        let (bid, ask) = self.target(mid);
+        // A side to add, pull, or one the venue never acknowledged
+        let live = |quote: &Option<LiveQuote>| quote.as_ref().is_some_and(|quote| quote.order_id.is_some());
+        if bid.is_some() != live(&self.bid) || ask.is_some() != live(&self.ask) {
+            return true;
+        }
+        self.quoted_mid.is_none_or(|quoted| ((mid - quoted) / quoted).abs() * 10_000.0 > self.config.tolerance_bps)
+    }
+
+    fn order(&self, is_buy: bool, price: f64) -> StrategyAction {
+        StrategyAction::Place {
+            exchange: self.config.exchange.clone(),
+            request: TradeRequest {
+                asset: self.config.symbol.clone(),
+                is_buy,
+                order_type: OrderType::Limit,
+                usd_value: self.config.size_usd,
+                price: Some(price),
+                leverage: 1,
+                cross_margin: None,
+                reduce_only: false,
+                strategy_id: None,
+                max_slippage_bps: None,
+                post_only: false,
+                time_in_force: None,
+                good_til_secs: None,
+                cloid: None,
+            },
+        }
+    }
+
+    /// Cancels for both live quotes, forgetting them
+    fn pull(&mut self) -> Vec<StrategyAction> {
+        [self.bid.take(), self.ask.take()].into_iter()
+            .flatten()
+            .filter_map(|quote| quote.order_id)
+            .map(|order_id| StrategyAction::Cancel {
+                exchange: self.config.exchange.clone(),
+                asset: self.config.symbol.to_string(),
+                order_id,
+            })
+            .collect()
+    }
+}
+
+impl Strategy for SimpleQuoter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn markets(&self) -> Vec<(ExchangeId, Symbol)> {
+        vec![(self.config.exchange.clone(), self.config.symbol.clone())]
+    }
+
+    fn on_book(&mut self, book: &BookTop) -> Vec<StrategyAction> {
+        let mid = book.mid();
+        self.mid = Some(mid);
+        if mid <= 0.0 || !self.needs_requote(mid) {
+            return Vec::new();
+        }
+        if self.last_quote_ms.is_some_and(|last| book.time - last < REQUOTE_INTERVAL_MS) {
+            return Vec::new();
+        }
+        let mut actions = self.pull();
+        let (bid, ask) = self.target(mid);
+        if let Some(price) = bid {
+            self.bid = Some(LiveQuote { price, order_id: None });
+            actions.push(self.order(true, price));
+        }
+        if let Some(price) = ask {
+            self.ask = Some(LiveQuote { price, order_id: None });
+            actions.push(self.order(false, price));
+        }
+        let at_limit = bid.is_none() || ask.is_none();
+        if at_limit && !self.at_limit {
+            actions.push(StrategyAction::Notify(format!(
+                "{} at inventory limit ({:.4} {}), quoting one side",
+                self.name, self.inventory, self.config.symbol.base()
+            )));
+        }
+        self.at_limit = at_limit;
+        self.quoted_mid = Some(mid);
+        self.last_quote_ms = Some(book.time);
+        self.filled = false;
+        actions
+    }
+
+    fn on_order(&mut self, _exchange: &ExchangeId, request: &TradeRequest, order_id: &str) {
+        let quote = if request.is_buy { &mut self.bid } else { &mut self.ask };
+        if let Some(quote) = quote.as_mut().filter(|quote| Some(quote.price) == request.price) {
+            quote.order_id = Some(order_id.to_string());
+        }
+    }
+
+    fn on_fill(&mut self, fill: &Fill) -> Vec<StrategyAction> {
+        self.inventory += if fill.is_buy { fill.size } else { -fill.size };
+        // Whatever is left of a partly filled quote goes at the re-quote
+        self.filled = true;
+        Vec::new()
+    }
+
+    fn on_stop(&mut self) -> Vec<StrategyAction> {
+        self.quoted_mid = None;
+        self.filled = false;
+        self.pull()
+    }
+
+    fn detail(&self) -> Option<String> {
+        let side = |quote: &Option<LiveQuote>| match quote {
+            Some(quote) if quote.order_id.is_some() => format!("{:.4}", quote.price),
+            Some(quote) => format!("{:.4}?", quote.price),
+            None => "-".to_string(),
+        };
+        let exposure = self.mid.map_or(0.0, |mid| self.inventory * mid);
+        Some(format!(
+            "bid {}  ask {}  ${:.2} each  inv {:.4} (${:.2} of ${:.2})",
+            side(&self.bid),
+            side(&self.ask),
+            self.config.size_usd,
+            self.inventory,
+            exposure,
+            self.config.max_inventory_usd
+        ))
+    }
+}
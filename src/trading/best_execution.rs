@@ -0,0 +1,141 @@
+use chrono::Utc;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::types::{FillEstimate, OrderBook};
+use crate::aggregator::DerivativesAggregator;
+use crate::error::{AggregatorError, NoExecutionVenue, Result};
+use super::confirmation::{Confirmation, Quote};
+use super::router::{RoutedTrade, TradingRouter};
+use super::TradeRequest;
+
+/// Which venue a request should go to, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteDecision {
+    pub exchange: ExchangeId,
+    pub estimate: FillEstimate,
+    // Every venue that could fill the size, best first
+    pub considered: Vec<(ExchangeId, FillEstimate)>,
+    // Venues left out: failed, stale or too thin a book
+    pub excluded: Vec<(ExchangeId, String)>,
+}
+
+impl RouteDecision {
+    pub fn describe(&self) -> String {
+        let mut parts: Vec<String> = self.considered.iter()
+            .map(|(exchange, estimate)| format!("{} ~{:.4} over {} levels", exchange, estimate.avg_price, estimate.levels))
+            .collect();
+        parts.extend(self.excluded.iter().map(|(exchange, reason)| format!("{} skipped: {}", exchange, reason)));
+        format!("Routing to {} ({})", self.exchange, parts.join("; "))
+    }
+}
+
+/// Pick the venue whose book fills `request` best: the lowest average price
+/// for a buy, the highest for a sell. Books that failed, are older than
+/// `max_age_ms` or can't absorb the full size are excluded. Ties go to the
+/// venue given first.
+pub fn choose_venue(request: &TradeRequest, books: Vec<(ExchangeId, Result<OrderBook, String>)>, max_age_ms: u64, now_ms: u64) -> Result<RouteDecision, AggregatorError> {
+    let mut considered: Vec<(ExchangeId, FillEstimate)> = Vec::new();
+    let mut excluded = Vec::new();
+    for (exchange, book) in books {
+        let book = match book {
+            Ok(book) if book.is_stale(max_age_ms, now_ms) => {
+                excluded.push((exchange, format!("stale ({}ms old)", book.age_ms(now_ms))));
+                continue;
+            }
+            Ok(book) => book,
+            Err(e) => {
+                excluded.push((exchange, e));
+                continue;
+            }
+        };
+        match book.estimate_fill(request.is_buy, request.usd_value, request.price) {
+            Some(estimate) => considered.push((exchange, estimate)),
+            None => excluded.push((exchange, format!("not enough depth for ${:.2}", request.usd_value))),
+        }
+    }
+    // Stable, so ties keep the order the venues were given
+    if request.is_buy {
+        considered.sort_by(|a, b| a.1.avg_price.total_cmp(&b.1.avg_price));
+    } else {
+        considered.sort_by(|a, b| b.1.avg_price.total_cmp(&a.1.avg_price));
+    }
+    let Some((exchange, estimate)) = considered.first().cloned() else {
+        let reasons: Vec<String> = excluded.iter().map(|(exchange, reason)| format!("{}: {}", exchange, reason)).collect();
+        return Err(NoExecutionVenue {
+            symbol: request.asset.to_string(),
+            usd_value: request.usd_value,
+            reasons: reasons.join(", "),
+        }
+        .into());
+    };
+    Ok(RouteDecision { exchange, estimate, considered, excluded })
+}
+
+/// A routed order: where it went, what it was expected to fill at and, once
+/// the venue reports the fills, what it did fill at.
+pub struct BestExecutionTrade {
+    pub decision: RouteDecision,
+    pub trade: RoutedTrade,
+    // Size-weighted fill price; None until the venue reports the fills
+    pub actual_price: Option<f64>,
+}
+
+impl BestExecutionTrade {
+    /// Actual against estimated price, in basis points, positive when the
+    /// fill was worse than the estimate
+    pub fn slippage_bps(&self, is_buy: bool) -> Option<f64> {
+        let estimated = self.decision.estimate.avg_price;
+        self.actual_price.map(|actual| {
+            let worse = if is_buy { actual - estimated } else { estimated - actual };
+            worse / estimated * 10_000.0
+        })
+    }
+}
+
+/// Places orders without a venue picked up front, sending each to whichever
+/// venue's book fills it best.
+pub struct BestExecution<'a> {
+    aggregator: &'a DerivativesAggregator,
+    router: &'a mut TradingRouter,
+    max_age_ms: u64,
+}
+
+impl<'a> BestExecution<'a> {
+    pub fn new(aggregator: &'a DerivativesAggregator, router: &'a mut TradingRouter, max_age_ms: u64) -> Self {
+        Self { aggregator, router, max_age_ms }
+    }
+
+    /// Compare both books for `request` without placing anything
+    pub async fn decide(&self, request: &TradeRequest) -> Result<RouteDecision> {
+        let mut books = Vec::new();
+        for exchange in self.aggregator.exchange_ids() {
+            let book = self.aggregator.get_exchange_orderbook(&exchange, &request.asset).await.map_err(|e| e.to_string());
+            books.push((exchange, book));
+        }
+        choose_venue(request, books, self.max_age_ms, Utc::now().timestamp_millis() as u64)
+    }
+
+    /// Route `request` to the best venue and place it there. Errors only
+    /// when no venue qualifies; a failed placement is in `trade.result`.
+    pub async fn route_trade(&mut self, request: TradeRequest, confirmation: Confirmation, force: bool) -> Result<BestExecutionTrade> {
+        let decision = self.decide(&request).await?;
+        let exchange = decision.exchange.clone();
+        let quote = Quote {
+            price: decision.estimate.avg_price,
+            size_step: self.aggregator.market_spec(&exchange, &request.asset).await.and_then(|spec| spec.size_step()),
+        };
+        let trade = self.router.place_trade(&exchange, request, quote, confirmation, force).await;
+        let actual_price = match &trade.result {
+            Ok((_, order_id)) if !order_id.is_empty() => self.actual_price(&exchange, order_id).await,
+            _ => None,
+        };
+        Ok(BestExecutionTrade { decision, trade, actual_price })
+    }
+
+    async fn actual_price(&self, exchange: &ExchangeId, order_id: &str) -> Option<f64> {
+        let fills: Vec<_> = self.router.fills().await.into_iter()
+            .filter(|fill| &fill.exchange == exchange && fill.order_id == order_id)
+            .collect();
+        let size: f64 = fills.iter().map(|fill| fill.size).sum();
+        (size > 0.0).then(|| fills.iter().map(|fill| fill.price * fill.size).sum::<f64>() / size)
+    }
+}
@@ -0,0 +1,366 @@
+use async_trait::async_trait;
+use ethers::abi::{self, ParamType, Token};
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Log, H256, U256};
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use super::wallet::WalletManager;
+use crate::error::{AggregatorError, Result};
+
+// Circle's CCTP domain for the dYdX chain
+pub const DYDX_DOMAIN: u32 = 4;
+// USDC has 6 decimals on Arbitrum
+const USDC_SCALE: f64 = 1_000_000.0;
+pub const ATTESTATION_API: &str = "https://iris-api.circle.com/attestations";
+pub const BRIDGE_POLL_INTERVAL: Duration = Duration::from_secs(15);
+// Attestation alone usually takes 10-15 minutes on Arbitrum
+pub const BRIDGE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+// Share of the amount that must show up on dYdX to count as minted
+const ARRIVAL_SHARE: f64 = 0.99;
+
+/// A step of `WalletManager::bridge_to_dydx`, as it happens
+#[derive(Debug, Clone, PartialEq)]
+pub enum BridgeEvent {
+    // In USDC; an approval follows when `allowance` is short of `needed`
+    AllowanceChecked { allowance: f64, needed: f64 },
+    Approved { tx_hash: String },
+    BurnSubmitted { tx_hash: String },
+    BurnConfirmed { tx_hash: String, block: Option<u64> },
+    // `wait_for_bridge_completion` moved the bridge on
+    Phase(BridgePhase),
+}
+
+impl BridgeEvent {
+    /// e.g. "Burn submitted: 0xabc…"
+    pub fn describe(&self) -> String {
+        match self {
+            BridgeEvent::AllowanceChecked { allowance, needed } if allowance >= needed => {
+                format!("Allowance ok: {:.2} USDC approved", allowance)
+            }
+            BridgeEvent::AllowanceChecked { allowance, needed } => {
+                format!("Allowance {:.2} USDC is short of {:.2}; approving", allowance, needed)
+            }
+            BridgeEvent::Approved { tx_hash } => format!("Bridge approved: {}", tx_hash),
+            BridgeEvent::BurnSubmitted { tx_hash } => format!("Burn submitted: {}", tx_hash),
+            BridgeEvent::BurnConfirmed { tx_hash, block: Some(block) } => format!("Burn confirmed in block {}: {}", block, tx_hash),
+            BridgeEvent::BurnConfirmed { tx_hash, block: None } => format!("Burn confirmed: {}", tx_hash),
+            BridgeEvent::Phase(phase) => format!("Bridge: {}", phase.progress()),
+        }
+    }
+}
+
+/// A bridge whose burn was mined on Arbitrum. The USDC is minted to
+/// `recipient` on dYdX once Circle attests the burn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BridgeReceipt {
+    // None when the existing allowance already covered the amount
+    pub approve_tx: Option<String>,
+    pub burn_tx: String,
+    pub amount: f64,
+    // dYdX bech32 address
+    pub recipient: String,
+}
+
+impl BridgeReceipt {
+    pub fn describe(&self) -> String {
+        format!(
+            "Bridged {:.2} USDC to {}\nBurn tx: {}\nThe USDC arrives on dYdX once Circle attests the burn.",
+            self.amount, self.recipient, self.burn_tx,
+        )
+    }
+}
+
+/// USDC amount in its smallest unit. Refuses non-positive amounts.
+pub fn usdc_units(amount: f64) -> Result<U256> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err(AggregatorError::InvalidAmount(format!("Bridge amount must be positive, got {}", amount)));
+    }
+    Ok(U256::from((amount * USDC_SCALE).round() as u64))
+}
+
+pub fn from_usdc_units(units: U256) -> f64 {
+    // Unlimited approvals don't fit in a u128
+    if units > U256::from(u128::MAX) {
+        return f64::INFINITY;
+    }
+    units.as_u128() as f64 / USDC_SCALE
+}
+
+/// The CCTP mint recipient for a dYdX address: its 20 bytes, left-padded
+/// to 32
+pub fn cctp_recipient(address: &str) -> Result<H256> {
+    let (_, data, _) = bech32::decode(address)?;
+    let address_bytes = bech32::convert_bits(&data, 5, 8, false)?;
+    if address_bytes.len() != 20 {
+        return Err(AggregatorError::InvalidInput(format!("Unexpected dYdX address length {} in {}", address_bytes.len(), address)));
+    }
+    let mut recipient = [0u8; 32];
+    recipient[12..].copy_from_slice(&address_bytes);
+    Ok(H256::from(recipient))
+}
+
+/// Log `event` and pass it on; a dropped receiver doesn't stop the bridge
+pub(crate) async fn emit(progress: &Option<mpsc::Sender<BridgeEvent>>, event: BridgeEvent) {
+    info!("Bridge: {}", event.describe());
+    if let Some(progress) = progress {
+        let _ = progress.send(event).await;
+    }
+}
+
+/// Where a bridge is between the burn on Arbitrum and the USDC on dYdX
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgePhase {
+    // Sent, not yet mined
+    BurnSubmitted,
+    // Mined; Circle has yet to attest the burn
+    AttestationPending,
+    // Attested; waiting for the USDC to show up on dYdX
+    Attested,
+    Minted,
+}
+
+impl BridgePhase {
+    /// e.g. "burn confirmed → attestation pending"
+    pub fn progress(&self) -> &'static str {
+        match self {
+            BridgePhase::BurnSubmitted => "burn submitted",
+            BridgePhase::AttestationPending => "burn confirmed → attestation pending",
+            BridgePhase::Attested => "burn confirmed → attested → minting",
+            BridgePhase::Minted => "burn confirmed → attested → minted",
+        }
+    }
+}
+
+/// A bridge not yet minted, persisted so a restart can pick it up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingBridge {
+    pub burn_tx: String,
+    pub amount: f64,
+    pub recipient: String,
+    pub phase: BridgePhase,
+    // Wallet USDC on dYdX before the burn; arrival is measured against it
+    pub baseline_balance: Option<f64>,
+    // CCTP message from the burn receipt, hex, and its keccak hash
+    pub message: Option<String>,
+    pub message_hash: Option<String>,
+    pub started_ms: i64,
+}
+
+impl PendingBridge {
+    pub fn new(burn_tx: String, amount: f64, recipient: String, baseline_balance: Option<f64>, started_ms: i64) -> Self {
+        Self {
+            burn_tx,
+            amount,
+            recipient,
+            phase: BridgePhase::BurnSubmitted,
+            baseline_balance,
+            message: None,
+            message_hash: None,
+            started_ms,
+        }
+    }
+
+    /// e.g. "25.00 USDC 0xabc…: burn confirmed → attestation pending"
+    pub fn describe(&self) -> String {
+        format!("{:.2} USDC {}: {}", self.amount, self.burn_tx, self.phase.progress())
+    }
+}
+
+/// Bridges not yet minted, keyed by burn tx
+pub struct PendingBridges {
+    path: PathBuf,
+    bridges: Vec<PendingBridge>,
+}
+
+impl PendingBridges {
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let bridges = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, bridges })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.bridges)?)?;
+        Ok(())
+    }
+
+    pub fn bridges(&self) -> &[PendingBridge] {
+        &self.bridges
+    }
+
+    pub fn get(&self, burn_tx: &str) -> Option<&PendingBridge> {
+        self.bridges.iter().find(|bridge| bridge.burn_tx.eq_ignore_ascii_case(burn_tx))
+    }
+
+    /// Add or replace the bridge with the same burn tx
+    pub fn set(&mut self, bridge: PendingBridge) -> Result<()> {
+        self.bridges.retain(|other| !other.burn_tx.eq_ignore_ascii_case(&bridge.burn_tx));
+        self.bridges.push(bridge);
+        self.save()
+    }
+
+    pub fn remove(&mut self, burn_tx: &str) -> Result<()> {
+        self.bridges.retain(|bridge| !bridge.burn_tx.eq_ignore_ascii_case(burn_tx));
+        self.save()
+    }
+}
+
+/// The CCTP message a burn emitted, from its receipt logs
+pub fn burn_message(logs: &[Log]) -> Option<Vec<u8>> {
+    let topic = H256::from(keccak256("MessageSent(bytes)"));
+    logs.iter()
+        .filter(|log| log.topics.first() == Some(&topic))
+        .find_map(|log| match abi::decode(&[ParamType::Bytes], &log.data).ok()?.pop()? {
+            Token::Bytes(message) => Some(message),
+            _ => None,
+        })
+}
+
+/// What Circle's attestation API is keyed by
+pub fn message_hash(message: &[u8]) -> String {
+    format!("{:#x}", H256::from(keccak256(message)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attestation {
+    Pending,
+    Complete(String),
+}
+
+impl Attestation {
+    /// Parse an attestation API response, e.g.
+    /// {"attestation":"0x…","status":"complete"}
+    pub fn from_json(json: &str) -> Result<Self> {
+        let body: serde_json::Value = serde_json::from_str(json)?;
+        match (body["status"].as_str(), body["attestation"].as_str()) {
+            (Some("complete"), Some(attestation)) if attestation.starts_with("0x") => Ok(Self::Complete(attestation.to_string())),
+            _ => Ok(Self::Pending),
+        }
+    }
+}
+
+/// Whether `amount` has shown up on top of `baseline`, allowing for fees
+pub fn has_arrived(baseline: f64, balance: f64, amount: f64) -> bool {
+    balance - baseline >= amount * ARRIVAL_SHARE
+}
+
+/// Where a bridge's progress is read from. The wallet in the app; mocks in
+/// tests.
+#[async_trait(?Send)]
+pub trait BridgeTracker {
+    /// The CCTP message of the burn; None while it isn't mined
+    async fn burn_message(&self, burn_tx: &str) -> Result<Option<Vec<u8>>>;
+    async fn attestation(&self, message_hash: &str) -> Result<Attestation>;
+    /// Wallet USDC on dYdX
+    async fn dydx_usdc_balance(&self) -> Result<f64>;
+}
+
+#[async_trait(?Send)]
+impl BridgeTracker for WalletManager {
+    async fn burn_message(&self, burn_tx: &str) -> Result<Option<Vec<u8>>> {
+        let provider = Provider::<Http>::try_from(self.arbitrum_rpc())?;
+        let receipt = provider.get_transaction_receipt(burn_tx.parse::<H256>().map_err(|e| AggregatorError::Parse(format!("Invalid tx hash {}: {}", burn_tx, e)))?).await?;
+        match receipt {
+            Some(receipt) => burn_message(&receipt.logs)
+                .map(Some)
+                .ok_or_else(|| AggregatorError::Ethereum(format!("No CCTP message in burn {}", burn_tx))),
+            None => Ok(None),
+        }
+    }
+
+    async fn attestation(&self, message_hash: &str) -> Result<Attestation> {
+        let response = reqwest::Client::new()
+            .get(format!("{}/{}", ATTESTATION_API, message_hash))
+            .send()
+            .await?;
+        // Unknown until Circle has seen the burn
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Attestation::Pending);
+        }
+        Attestation::from_json(&response.error_for_status()?.text().await?)
+    }
+
+    async fn dydx_usdc_balance(&self) -> Result<f64> {
+        Ok(self.get_dydx_usdc_balance().await?
+            .ok_or_else(|| AggregatorError::WalletNotConfigured("dYdX".to_string()))?)
+    }
+}
+
+/// Move `bridge` on until it is minted or `timeout` runs out, polling every
+/// `poll`. Each phase reached is saved to `store` and sent to `progress`;
+/// a minted bridge leaves the store. Lookups that fail are retried at the
+/// next poll. On timeout the bridge is left where it got to, to resume.
+pub async fn track(
+    tracker: &impl BridgeTracker,
+    bridge: &mut PendingBridge,
+    store: &mut PendingBridges,
+    progress: &Option<mpsc::Sender<BridgeEvent>>,
+    poll: Duration,
+    timeout: Duration,
+) -> Result<()> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let phase = bridge.phase;
+        if let Err(e) = step(tracker, bridge).await {
+            warn!("Bridge {}: {}", bridge.burn_tx, e);
+        }
+        if bridge.phase == BridgePhase::Minted {
+            store.remove(&bridge.burn_tx)?;
+            emit(progress, BridgeEvent::Phase(bridge.phase)).await;
+            return Ok(());
+        }
+        if bridge.phase != phase {
+            store.set(bridge.clone())?;
+            emit(progress, BridgeEvent::Phase(bridge.phase)).await;
+            continue;
+        }
+        if tokio::time::Instant::now() + poll > deadline {
+            return Ok(());
+        }
+        tokio::time::sleep(poll).await;
+    }
+}
+
+// One lookup for the bridge's current phase
+async fn step(tracker: &impl BridgeTracker, bridge: &mut PendingBridge) -> Result<()> {
+    // Without a balance from before the burn, the earliest one stands in
+    if bridge.baseline_balance.is_none() {
+        bridge.baseline_balance = Some(tracker.dydx_usdc_balance().await?);
+    }
+    match bridge.phase {
+        BridgePhase::BurnSubmitted => {
+            if let Some(message) = tracker.burn_message(&bridge.burn_tx).await? {
+                bridge.message_hash = Some(message_hash(&message));
+                bridge.message = Some(format!("0x{}", hex::encode(&message)));
+                bridge.phase = BridgePhase::AttestationPending;
+            }
+        }
+        BridgePhase::AttestationPending => {
+            let hash = bridge.message_hash.as_deref()
+                .ok_or_else(|| AggregatorError::Ethereum("No CCTP message recorded".to_string()))?;
+            if let Attestation::Complete(_) = tracker.attestation(hash).await? {
+                bridge.phase = BridgePhase::Attested;
+            }
+        }
+        BridgePhase::Attested => {
+            let baseline = bridge.baseline_balance.unwrap_or_default();
+            if has_arrived(baseline, tracker.dydx_usdc_balance().await?, bridge.amount) {
+                bridge.phase = BridgePhase::Minted;
+            }
+        }
+        BridgePhase::Minted => {}
+    }
+    Ok(())
+}
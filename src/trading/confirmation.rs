@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use uuid::Uuid;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::error::{AggregatorError, ConfirmationRequired};
+use super::TradeRequest;
+
+// Unused confirmation tokens lapse after this long
+const TOKEN_TTL_MS: i64 = 2 * 60 * 1000;
+// A typed amount may be off by this much, so whole dollars are accepted
+const TYPED_TOLERANCE_USD: f64 = 1.0;
+
+/// What the user must do before an order goes out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfirmationTier {
+    None,
+    // A yes/no dialog
+    Dialog,
+    // The notional typed back
+    TypedConfirmation,
+}
+
+impl std::fmt::Display for ConfirmationTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Dialog => write!(f, "dialog"),
+            Self::TypedConfirmation => write!(f, "typed"),
+        }
+    }
+}
+
+impl std::str::FromStr for ConfirmationTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "dialog" => Ok(Self::Dialog),
+            "typed" => Ok(Self::TypedConfirmation),
+            other => Err(format!("Unknown confirmation tier '{}'", other)),
+        }
+    }
+}
+
+/// Notional thresholds, in USD, above which each tier applies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfirmationPolicy {
+    // Ascending by threshold
+    tiers: Vec<(f64, ConfirmationTier)>,
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        Self::new(vec![(5_000.0, ConfirmationTier::Dialog), (25_000.0, ConfirmationTier::TypedConfirmation)])
+    }
+}
+
+impl ConfirmationPolicy {
+    pub fn new(mut tiers: Vec<(f64, ConfirmationTier)>) -> Self {
+        tiers.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { tiers }
+    }
+
+    /// Every order goes out without confirmation
+    pub fn off() -> Self {
+        Self { tiers: Vec::new() }
+    }
+
+    /// Tier of the highest threshold the notional is strictly above.
+    pub fn tier_for(&self, notional: f64) -> ConfirmationTier {
+        self.tiers.iter()
+            .filter(|(threshold, _)| notional > *threshold)
+            .map(|(_, tier)| *tier)
+            .last()
+            .unwrap_or(ConfirmationTier::None)
+    }
+}
+
+impl std::str::FromStr for ConfirmationPolicy {
+    type Err = String;
+
+    /// "5000:dialog,25000:typed", or "off".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("off") {
+            return Ok(Self::off());
+        }
+        let tiers = s.split(',')
+            .map(|entry| {
+                let (threshold, tier) = entry.split_once(':')
+                    .ok_or_else(|| format!("Expected threshold:tier, got '{}'", entry.trim()))?;
+                let threshold: f64 = threshold.trim().trim_start_matches('$').parse()
+                    .map_err(|_| format!("Invalid threshold '{}'", threshold.trim()))?;
+                if !threshold.is_finite() || threshold < 0.0 {
+                    return Err(format!("Threshold out of range: {}", threshold));
+                }
+                Ok((threshold, tier.parse()?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self::new(tiers))
+    }
+}
+
+/// The price an order is sized at and the venue's size increment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quote {
+    pub price: f64,
+    pub size_step: Option<f64>,
+}
+
+/// Notional the venue will actually see once the size is rounded to the
+/// step, the way the services round it.
+pub fn rounded_notional(usd_value: f64, quote: &Quote) -> f64 {
+    if quote.price <= 0.0 {
+        return usd_value;
+    }
+    let size = usd_value / quote.price;
+    let size = match quote.size_step {
+        Some(step) if step > 0.0 => (size / step).round() * step,
+        _ => size,
+    };
+    size * quote.price
+}
+
+/// How the caller confirmed the order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Confirmation {
+    None,
+    // The user accepted a dialog in this process
+    Dialog,
+    // The user typed the notional back in this process
+    Typed(String),
+    // A token from an earlier ConfirmationRequired, for callers that can't
+    // prompt, e.g. a daemon client; typed tiers need the amount too
+    Token { token: String, typed: Option<String> },
+}
+
+#[derive(Debug, Clone)]
+struct PendingConfirmation {
+    exchange: ExchangeId,
+    request: String,
+    tier: ConfirmationTier,
+    notional: f64,
+    expires_at: i64,
+}
+
+/// Enforces the policy and hands out one-time tokens for orders that need
+/// confirming.
+#[derive(Debug, Default)]
+pub struct ConfirmationGate {
+    policy: ConfirmationPolicy,
+    pending: HashMap<String, PendingConfirmation>,
+}
+
+impl ConfirmationGate {
+    pub fn new(policy: ConfirmationPolicy) -> Self {
+        Self { policy, pending: HashMap::new() }
+    }
+
+    pub fn policy(&self) -> &ConfirmationPolicy {
+        &self.policy
+    }
+
+    /// Tier and post-rounding notional of an order at `quote`.
+    pub fn review(&self, request: &TradeRequest, quote: &Quote) -> (ConfirmationTier, f64) {
+        let notional = rounded_notional(request.usd_value, quote);
+        (self.policy.tier_for(notional), notional)
+    }
+
+    /// Ok when the order may go out. Otherwise the error carries a token that
+    /// confirms exactly this order once.
+    pub fn check(&mut self, exchange: &ExchangeId, request: &TradeRequest, quote: &Quote, confirmation: Confirmation, now_ms: i64) -> Result<(), AggregatorError> {
+        self.pending.retain(|_, pending| pending.expires_at > now_ms);
+        let (tier, notional) = self.review(request, quote);
+        let satisfied = match (&confirmation, tier) {
+            (_, ConfirmationTier::None) => true,
+            (Confirmation::Dialog, ConfirmationTier::Dialog) => true,
+            (Confirmation::Typed(typed), ConfirmationTier::Dialog | ConfirmationTier::TypedConfirmation) => typed_matches(typed, notional)?,
+            (Confirmation::Token { token, typed }, _) => self.redeem(token, typed.as_deref(), exchange, request)?,
+            _ => false,
+        };
+        if satisfied {
+            return Ok(());
+        }
+
+        let token = Uuid::new_v4().to_string();
+        self.pending.insert(token.clone(), PendingConfirmation {
+            exchange: exchange.clone(),
+            request: fingerprint(request),
+            tier,
+            notional,
+            expires_at: now_ms + TOKEN_TTL_MS,
+        });
+        Err(ConfirmationRequired { tier, notional, token }.into())
+    }
+
+    // Tokens are single-use, even when the typed amount is wrong
+    fn redeem(&mut self, token: &str, typed: Option<&str>, exchange: &ExchangeId, request: &TradeRequest) -> Result<bool, AggregatorError> {
+        let pending = self.pending.remove(token)
+            .ok_or_else(|| AggregatorError::ConfirmationRejected("unknown or expired confirmation token".to_string()))?;
+        if &pending.exchange != exchange || pending.request != fingerprint(request) {
+            return Err(AggregatorError::ConfirmationRejected("token was issued for a different order".to_string()));
+        }
+        match pending.tier {
+            ConfirmationTier::TypedConfirmation => match typed {
+                Some(typed) => typed_matches(typed, pending.notional),
+                None => Err(AggregatorError::ConfirmationRejected(format!("type the amount ${:.2} to confirm", pending.notional))),
+            },
+            _ => Ok(true),
+        }
+    }
+}
+
+fn typed_matches(typed: &str, notional: f64) -> Result<bool, AggregatorError> {
+    let cleaned: String = typed.trim().chars().filter(|c| !matches!(c, '$' | ',' | '_')).collect();
+    match cleaned.parse::<f64>() {
+        Ok(amount) if (amount - notional).abs() <= TYPED_TOLERANCE_USD => Ok(true),
+        _ => Err(AggregatorError::ConfirmationRejected(format!("typed amount '{}' does not match ${:.2}", typed.trim(), notional))),
+    }
+}
+
+// Everything that changes what the order does
+fn fingerprint(request: &TradeRequest) -> String {
+    format!(
+        "{}|{}|{:?}|{}|{:?}|{}|{:?}|{}",
+        request.asset, request.is_buy, request.order_type, request.usd_value, request.price, request.leverage, request.cross_margin, request.reduce_only
+    )
+}
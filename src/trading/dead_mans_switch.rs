@@ -0,0 +1,114 @@
+use crate::error::{AggregatorError, Result};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+// Hyperliquid rejects schedules less than 5 seconds out
+pub const MIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Venue side of the switch: schedule a cancel-all `after` from now, or clear
+/// the schedule.
+#[async_trait]
+pub trait ScheduleCancel: Send + Sync {
+    async fn schedule_cancel(&self, after: Duration) -> Result<()>;
+    async fn clear_scheduled_cancel(&self) -> Result<()>;
+}
+
+/// Keeps a venue cancel-all pushed `timeout` into the future. If the app dies
+/// or loses the network, refreshes stop and the venue cancels every resting
+/// order once the last schedule runs out.
+pub struct DeadMansSwitch {
+    client: Arc<dyn ScheduleCancel>,
+    timeout: Duration,
+    armed: Arc<AtomicBool>,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DeadMansSwitch {
+    /// Refresh three times per window so one failed request doesn't let the
+    /// schedule lapse.
+    pub fn refresh_interval(timeout: Duration) -> Duration {
+        timeout / 3
+    }
+
+    pub fn arm(client: Arc<dyn ScheduleCancel>, timeout: Duration) -> Result<Self> {
+        if timeout < MIN_TIMEOUT {
+            return Err(AggregatorError::InvalidInput(format!(
+                "Dead man's switch timeout must be at least {}s",
+                MIN_TIMEOUT.as_secs()
+            )));
+        }
+        Ok(Self::spawn(client, timeout, Self::refresh_interval(timeout)))
+    }
+
+    pub(crate) fn spawn(client: Arc<dyn ScheduleCancel>, timeout: Duration, interval: Duration) -> Self {
+        let armed = Arc::new(AtomicBool::new(false));
+        let (shutdown, mut shutdown_rx) = oneshot::channel();
+        let task_client = client.clone();
+        let task_armed = armed.clone();
+
+        let handle = tokio::spawn(async move {
+            loop {
+                match task_client.schedule_cancel(timeout).await {
+                    Ok(()) => task_armed.store(true, Ordering::SeqCst),
+                    Err(e) => {
+                        // Keep trying; the previous schedule is still counting down
+                        warn!("Failed to refresh dead man's switch: {}", e);
+                        task_armed.store(false, Ordering::SeqCst);
+                    }
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = &mut shutdown_rx => break,
+                }
+            }
+        });
+
+        info!("Dead man's switch armed with {}s timeout", timeout.as_secs());
+        Self {
+            client,
+            timeout,
+            armed,
+            shutdown: Some(shutdown),
+            handle: Some(handle),
+        }
+    }
+
+    /// True once the venue has accepted a schedule and the last refresh succeeded
+    pub fn is_armed(&self) -> bool {
+        self.armed.load(Ordering::SeqCst)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Stop refreshing and clear the schedule so a clean exit leaves orders alone.
+    pub async fn disarm(mut self) -> Result<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.await;
+        }
+        self.armed.store(false, Ordering::SeqCst);
+        self.client.clear_scheduled_cancel().await?;
+        info!("Dead man's switch disarmed");
+        Ok(())
+    }
+}
+
+impl Drop for DeadMansSwitch {
+    fn drop(&mut self) {
+        // Dropped without disarm: stop refreshing and let the schedule lapse
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::metadata::Delisting;
+use crate::aggregator::symbol::Symbol;
+use super::orders::Order;
+use super::positions::Position;
+
+/// What the account still holds in a market its venue delisted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelistedExposure {
+    pub exchange: ExchangeId,
+    pub delisting: Delisting,
+    // Signed size of the open position, zero when flat
+    pub position_size: f64,
+    pub open_orders: usize,
+}
+
+impl DelistedExposure {
+    pub fn describe(&self) -> String {
+        let mut held = Vec::new();
+        if self.position_size != 0.0 {
+            held.push(format!("a {} {} position", self.position_size, self.delisting.base));
+        }
+        if self.open_orders > 0 {
+            held.push(format!("{} open order(s)", self.open_orders));
+        }
+        format!("DELISTED: {}; you hold {}", self.delisting.describe(&self.exchange), held.join(" and "))
+    }
+}
+
+fn same_market(asset: &str, base: &str) -> bool {
+    Symbol::parse_user_input(asset).is_ok_and(|symbol| symbol.base() == base)
+}
+
+/// Delisted markets where the account has a position or an open order.
+pub fn delisted_exposures(delistings: &[(ExchangeId, Delisting)], positions: &[Position], open_orders: &[Order]) -> Vec<DelistedExposure> {
+    delistings.iter()
+        .filter_map(|(exchange, delisting)| {
+            let position_size = positions.iter()
+                .filter(|p| &p.exchange == exchange && same_market(&p.asset, &delisting.base))
+                .map(|p| p.size)
+                .sum::<f64>();
+            let open_orders = open_orders.iter()
+                .filter(|o| &o.exchange == exchange && same_market(&o.asset, &delisting.base))
+                .count();
+            (position_size != 0.0 || open_orders > 0).then(|| DelistedExposure {
+                exchange: exchange.clone(),
+                delisting: delisting.clone(),
+                position_size,
+                open_orders,
+            })
+        })
+        .collect()
+}
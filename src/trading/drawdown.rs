@@ -0,0 +1,242 @@
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use super::kill_switch::{KillScope, KillStep};
+
+/// What the guard does beyond notifying once equity falls past the trigger
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawdownAction {
+    #[default]
+    Notify,
+    CancelOrders,
+    HalvePositions,
+    Flatten,
+}
+
+impl DrawdownAction {
+    /// The kill switch sweep behind the action; None for notify-only
+    pub fn scope(&self) -> Option<KillScope> {
+        match self {
+            DrawdownAction::Notify => None,
+            DrawdownAction::CancelOrders => Some(KillScope::CancelOrders),
+            DrawdownAction::HalvePositions => Some(KillScope::HalvePositions),
+            DrawdownAction::Flatten => Some(KillScope::Flatten),
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            DrawdownAction::Notify => "notified only",
+            DrawdownAction::CancelOrders => "cancelled all orders",
+            DrawdownAction::HalvePositions => "halved all positions",
+            DrawdownAction::Flatten => "flattened everything",
+        }
+    }
+}
+
+impl FromStr for DrawdownAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "notify" | "none" => Ok(Self::Notify),
+            "cancel" | "cancel-orders" => Ok(Self::CancelOrders),
+            "halve" | "halve-positions" => Ok(Self::HalvePositions),
+            "flatten" => Ok(Self::Flatten),
+            other => Err(format!("Unknown drawdown action '{}'; expected notify, cancel, halve or flatten", other)),
+        }
+    }
+}
+
+/// Which high drawdown is measured from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DrawdownBasis {
+    #[default]
+    Session,
+    // Resets at midnight UTC
+    Daily,
+}
+
+impl FromStr for DrawdownBasis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "session" => Ok(Self::Session),
+            "daily" | "day" => Ok(Self::Daily),
+            other => Err(format!("Unknown drawdown basis '{}'; expected session or daily", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawdownPolicy {
+    // Percent below the high that notifies...
+    pub warn_pct: f64,
+    // ...and that runs `action`
+    pub trigger_pct: f64,
+    pub basis: DrawdownBasis,
+    pub action: DrawdownAction,
+    // After triggering, the guard stays quiet this long, then re-arms from
+    // the equity it finds
+    pub cooldown_ms: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawdownReading {
+    pub high: f64,
+    pub equity: f64,
+    // Percent below `high`
+    pub pct: f64,
+    pub at_ms: i64,
+}
+
+impl DrawdownReading {
+    /// e.g. "equity $9000.00 is 10.00% below its $10000.00 high"
+    pub fn describe(&self) -> String {
+        format!("equity ${:.2} is {:.2}% below its ${:.2} high", self.equity, self.pct, self.high)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GuardState {
+    Armed,
+    Warned,
+    // Its action is running; nothing more fires until it's done
+    Triggered,
+    CoolingDown { until_ms: i64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardEvent {
+    Warned(DrawdownReading),
+    // Back above the warning level without triggering
+    Recovered(DrawdownReading),
+    Triggered(DrawdownReading, DrawdownAction),
+    // Cooldown over; the high restarts from the current equity
+    Rearmed,
+}
+
+/// Watches combined equity and escalates as drawdown from the high grows:
+/// armed → warned → triggered → cooling down → armed.
+#[derive(Debug, Clone)]
+pub struct DrawdownGuard {
+    policy: DrawdownPolicy,
+    state: GuardState,
+    high: Option<f64>,
+    // UTC day the high belongs to, for the daily basis
+    high_day: Option<NaiveDate>,
+    triggered: Option<DrawdownReading>,
+}
+
+fn utc_day(ms: i64) -> Option<NaiveDate> {
+    DateTime::from_timestamp_millis(ms).map(|at| at.date_naive())
+}
+
+impl DrawdownGuard {
+    pub fn new(policy: DrawdownPolicy) -> Self {
+        Self { policy, state: GuardState::Armed, high: None, high_day: None, triggered: None }
+    }
+
+    pub fn policy(&self) -> &DrawdownPolicy {
+        &self.policy
+    }
+
+    pub fn state(&self) -> GuardState {
+        self.state
+    }
+
+    /// The reading that last triggered the guard
+    pub fn last_trigger(&self) -> Option<&DrawdownReading> {
+        self.triggered.as_ref()
+    }
+
+    /// Feed an equity sample; returns the transition it caused, if any.
+    pub fn observe(&mut self, equity: f64, now_ms: i64) -> Option<GuardEvent> {
+        if !equity.is_finite() {
+            return None;
+        }
+        let day = utc_day(now_ms);
+        if self.policy.basis == DrawdownBasis::Daily && self.high_day != day {
+            self.high = None;
+        }
+        self.high_day = day;
+        let high = self.high.map_or(equity, |high| high.max(equity));
+        self.high = Some(high);
+        let pct = if high > 0.0 { (high - equity) / high * 100.0 } else { 0.0 };
+        let reading = DrawdownReading { high, equity, pct, at_ms: now_ms };
+
+        match self.state {
+            GuardState::Triggered => None,
+            GuardState::CoolingDown { until_ms } => {
+                if now_ms < until_ms {
+                    return None;
+                }
+                self.high = Some(equity);
+                self.state = GuardState::Armed;
+                Some(GuardEvent::Rearmed)
+            }
+            GuardState::Armed | GuardState::Warned if pct >= self.policy.trigger_pct => {
+                self.state = GuardState::Triggered;
+                self.triggered = Some(reading);
+                Some(GuardEvent::Triggered(reading, self.policy.action))
+            }
+            GuardState::Armed if pct >= self.policy.warn_pct => {
+                self.state = GuardState::Warned;
+                Some(GuardEvent::Warned(reading))
+            }
+            GuardState::Warned if pct < self.policy.warn_pct => {
+                self.state = GuardState::Armed;
+                Some(GuardEvent::Recovered(reading))
+            }
+            GuardState::Armed | GuardState::Warned => None,
+        }
+    }
+
+    /// The triggered action has finished, whatever its outcome; start the
+    /// cooldown.
+    pub fn action_done(&mut self, now_ms: i64) {
+        if self.state == GuardState::Triggered {
+            self.state = GuardState::CoolingDown { until_ms: now_ms + self.policy.cooldown_ms };
+        }
+    }
+}
+
+/// A drawdown trigger or one step of the action it ran, as journaled. Every
+/// record carries the reading that fired the guard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawdownRecord {
+    pub timestamp: i64,
+    pub high: f64,
+    pub equity: f64,
+    pub drawdown_pct: f64,
+    pub action: DrawdownAction,
+    // e.g. "Hyperliquid: close BTC 0.5"; None on the trigger record itself
+    pub step: Option<String>,
+    // Venue response or error of the step
+    pub result: Option<Result<String, String>>,
+}
+
+impl DrawdownRecord {
+    pub fn trigger(reading: &DrawdownReading, action: DrawdownAction) -> Self {
+        Self {
+            timestamp: reading.at_ms,
+            high: reading.high,
+            equity: reading.equity,
+            drawdown_pct: reading.pct,
+            action,
+            step: None,
+            result: None,
+        }
+    }
+
+    pub fn step(reading: &DrawdownReading, action: DrawdownAction, venue: &str, step: &KillStep, now_ms: i64) -> Self {
+        Self {
+            timestamp: now_ms,
+            step: Some(format!("{}: {}", venue, step.action)),
+            result: Some(step.result.clone()),
+            ..Self::trigger(reading, action)
+        }
+    }
+}
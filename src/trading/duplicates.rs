@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::error::AggregatorError;
+use super::{OrderType, TradeRequest};
+
+pub const DEFAULT_DUPLICATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// What makes two submissions the same order. Amounts compare exactly:
+/// only a resubmission of the very same request matches.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Fingerprint {
+    exchange: ExchangeId,
+    asset: Symbol,
+    is_buy: bool,
+    usd_value: u64,
+    price: Option<u64>,
+    // Market, limit, stop or take-profit, whatever the trigger
+    kind: std::mem::Discriminant<OrderType>,
+    trigger: Option<u64>,
+}
+
+impl Fingerprint {
+    fn of(exchange: &ExchangeId, request: &TradeRequest) -> Self {
+        Self {
+            exchange: exchange.clone(),
+            asset: request.asset.clone(),
+            is_buy: request.is_buy,
+            usd_value: request.usd_value.to_bits(),
+            price: request.price.map(f64::to_bits),
+            kind: std::mem::discriminant(&request.order_type),
+            trigger: request.order_type.trigger_price().map(f64::to_bits),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Submission {
+    client_id: String,
+    at_ms: i64,
+}
+
+/// Recent submissions by fingerprint, to catch a double-pressed confirm or
+/// a client retrying after a timeout. In memory only; entries older than
+/// the window are evicted as new ones arrive.
+#[derive(Debug)]
+pub struct DuplicateGuard {
+    window_ms: i64,
+    recent: HashMap<Fingerprint, Submission>,
+}
+
+impl Default for DuplicateGuard {
+    fn default() -> Self {
+        Self::new(DEFAULT_DUPLICATE_WINDOW)
+    }
+}
+
+impl DuplicateGuard {
+    pub fn new(window: Duration) -> Self {
+        Self { window_ms: window.as_millis() as i64, recent: HashMap::new() }
+    }
+
+    fn evict(&mut self, now_ms: i64) {
+        let window_ms = self.window_ms;
+        self.recent.retain(|_, submission| now_ms - submission.at_ms < window_ms);
+    }
+
+    /// The error an identical submission within the window would get,
+    /// without recording anything
+    pub fn find(&self, exchange: &ExchangeId, request: &TradeRequest, now_ms: i64) -> Result<(), AggregatorError> {
+        match self.recent.get(&Fingerprint::of(exchange, request)) {
+            Some(original) if now_ms - original.at_ms < self.window_ms => Err(AggregatorError::PossibleDuplicate {
+                client_id: original.client_id.clone(),
+                age_ms: now_ms - original.at_ms,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record `request` and return its client id, or refuse it when an
+    /// identical one went out within the window. `force` records it anyway.
+    pub fn check(&mut self, exchange: &ExchangeId, request: &TradeRequest, force: bool, now_ms: i64) -> Result<String, AggregatorError> {
+        self.evict(now_ms);
+        if !force {
+            self.find(exchange, request, now_ms)?;
+        }
+        let client_id = Uuid::new_v4().to_string();
+        self.recent.insert(Fingerprint::of(exchange, request), Submission { client_id: client_id.clone(), at_ms: now_ms });
+        Ok(client_id)
+    }
+
+    /// Drop a submission the venue definitely rejected, so resending it
+    /// isn't taken for a duplicate
+    pub fn forget(&mut self, client_id: &str) {
+        self.recent.retain(|_, submission| submission.client_id != client_id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.recent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recent.is_empty()
+    }
+}
@@ -0,0 +1,55 @@
+use crate::error::{AggregatorError, Result};
+use dydx::config::ClientConfig;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+// Node, indexer and Noble endpoints of each network, built in so the
+// binary runs from any directory
+pub const EMBEDDED_MAINNET: &str = include_str!("../bridge_config/mainnet.toml");
+pub const EMBEDDED_TESTNET: &str = include_str!("../bridge_config/testnet.toml");
+
+static OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+static TESTNET: AtomicBool = AtomicBool::new(false);
+
+/// Pick the embedded testnet config, or read the dYdX client config from
+/// `path` instead. Set once at startup, before any wallet connects.
+pub fn init(path: Option<PathBuf>, testnet: bool) {
+    if let Ok(mut installed) = OVERRIDE.write() {
+        *installed = path;
+    }
+    TESTNET.store(testnet, Ordering::Relaxed);
+}
+
+pub fn override_path() -> Option<PathBuf> {
+    OVERRIDE.read().ok().and_then(|installed| installed.clone())
+}
+
+pub fn is_testnet() -> bool {
+    TESTNET.load(Ordering::Relaxed)
+}
+
+/// The embedded config of mainnet or testnet
+pub fn embedded(testnet: bool) -> Result<ClientConfig> {
+    parse(if testnet { EMBEDDED_TESTNET } else { EMBEDDED_MAINNET })
+        .map_err(|e| AggregatorError::Config(format!("Embedded dYdX config is invalid: {}", e)))
+}
+
+/// The config dYdX clients connect with: the override file when one is
+/// set, the embedded config of the selected network otherwise.
+pub fn client_config() -> Result<ClientConfig> {
+    match override_path() {
+        Some(path) => from_file(&path),
+        None => embedded(is_testnet()),
+    }
+}
+
+pub fn from_file(path: &Path) -> Result<ClientConfig> {
+    let toml = std::fs::read_to_string(path)
+        .map_err(|e| AggregatorError::Config(format!("Failed to read dYdX config {}: {}", path.display(), e)))?;
+    parse(&toml).map_err(|e| AggregatorError::Config(format!("Invalid dYdX config {}: {}", path.display(), e)))
+}
+
+pub fn parse(toml: &str) -> Result<ClientConfig> {
+    toml::from_str(toml).map_err(|e| AggregatorError::Config(e.to_string()))
+}
@@ -1,13 +1,17 @@
+use async_trait::async_trait;
 use dydx::{
-    node::{NodeClient, NodeConfig, NodeError, OrderTimeInForce, Account, OrderBuilder, OrderId, OrderGoodUntil},
-    indexer::{IndexerClient, IndexerConfig,PerpetualPositionStatus,ListPositionsOpts},
+    node::{Address, NodeClient, NodeConfig, NodeError, OrderTimeInForce, Account, OrderBuilder, OrderId, OrderGoodUntil, TxHash},
+    indexer::{Height, IndexerClient, IndexerConfig,PerpetualPositionStatus,ListPositionsOpts},
     indexer::types::{
         Subaccount, OrderSide, OrderType,
         OrderResponseObject,
     },
 };
-use anyhow::Result;
-use std::sync::{Arc, Mutex};
+use crate::error::Result;
+use dydx_proto::cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+use dydx_proto::dydxprotocol::clob::Order as NodeOrder;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use std::fmt;
 use std::error::Error as StdError;
 use tracing::error;
@@ -18,20 +22,72 @@ use std::str::FromStr;
 use dydx::indexer::Ticker;
 use std::ops::Div;
 use std::time::Duration;
+use crate::aggregator::endpoints;
+use crate::aggregator::retry;
+use crate::aggregator::symbol::Symbol;
 
 pub use dydx::indexer::PerpetualPositionResponseObject;
 pub use dydx::indexer::{RestConfig, SockConfig};
 
+/// The node calls `DydxService` makes. Implemented by the SDK's
+/// `NodeClient`; tests stand in their own node.
+#[async_trait]
+pub trait DydxNode: Send {
+    async fn latest_block_height(&mut self) -> Result<Height, NodeError>;
+    async fn place_order(&mut self, account: &mut Account, order: NodeOrder) -> Result<TxHash, NodeError>;
+    async fn cancel_order(&mut self, account: &mut Account, order_id: OrderId, until: OrderGoodUntil) -> Result<TxHash, NodeError>;
+    async fn account_balances(&mut self, address: &Address) -> Result<Vec<Coin>, NodeError>;
+}
+
+#[async_trait]
+impl DydxNode for NodeClient {
+    async fn latest_block_height(&mut self) -> Result<Height, NodeError> {
+        self.get_latest_block_height().await.map_err(NodeError::General)
+    }
+
+    async fn place_order(&mut self, account: &mut Account, order: NodeOrder) -> Result<TxHash, NodeError> {
+        NodeClient::place_order(self, account, order).await
+    }
+
+    async fn cancel_order(&mut self, account: &mut Account, order_id: OrderId, until: OrderGoodUntil) -> Result<TxHash, NodeError> {
+        NodeClient::cancel_order(self, account, order_id, until).await
+    }
+
+    async fn account_balances(&mut self, address: &Address) -> Result<Vec<Coin>, NodeError> {
+        self.get_account_balances(address).await.map_err(NodeError::General)
+    }
+}
+
+/// The node client behind an async lock: a broadcast holds it across
+/// awaits, so another caller parks its task rather than blocking the
+/// runtime thread.
+pub type SharedNode = Arc<Mutex<Box<dyn DydxNode>>>;
+
+// Lifetime of a long-term order unless the request sets one
+pub const DEFAULT_GOOD_TIL_SECS: u64 = 28 * 24 * 60 * 60;
+// Blocks a short-term order stays valid for
+const SHORT_TERM_BLOCKS: u32 = 15;
+
+// Worst fill a market or triggered order accepts unless the request sets one
+pub const DEFAULT_ALLOWED_SLIPPAGE_BPS: u32 = 500;
+
+// The builder takes the tolerance in percent
+fn allowed_slippage_percent(bps: u32) -> BigDecimal {
+    BigDecimal::from(bps) / BigDecimal::from(100)
+}
+
 pub struct DydxService {
-    pub node_client: Arc<Mutex<NodeClient>>,
+    pub node_client: SharedNode,
     pub indexer_client: Arc<IndexerClient>,
     pub account: Account,
+    // REST base `indexer_client` was built for
+    indexer_endpoint: String,
 }
 
 #[derive(Debug)]
 pub enum DydxServiceError {
     ClientError(NodeError),
-    IndexerError(anyhow::Error),
+    IndexerError(Box<dyn StdError + Send + Sync>),
     InvalidParameters(String),
     ParseError(String),
 }
@@ -64,9 +120,34 @@ impl From<NodeError> for DydxServiceError {
     }
 }
 
+impl DydxServiceError {
+    /// Wrap an indexer failure, keeping its cause chain for `is_transient`.
+    pub fn indexer(error: impl Into<Box<dyn StdError + Send + Sync>>) -> Self {
+        DydxServiceError::IndexerError(error.into())
+    }
+}
+
+// The SDK reports its failures as anyhow errors
 impl From<anyhow::Error> for DydxServiceError {
     fn from(error: anyhow::Error) -> Self {
-        DydxServiceError::IndexerError(error)
+        DydxServiceError::indexer(SdkError(error))
+    }
+}
+
+// Keeps the error the SDK wrapped reachable through `source()`, where
+// boxing the anyhow error itself would hide it behind anyhow's own type
+#[derive(Debug)]
+struct SdkError(anyhow::Error);
+
+impl fmt::Display for SdkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl StdError for SdkError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.0.as_ref())
     }
 }
 
@@ -76,17 +157,72 @@ impl From<bigdecimal::ParseBigDecimalError> for DydxServiceError {
     }
 }
 
+/// How much an order is for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderSize {
+    // Notional, converted at the price the order is sized at
+    Usd(f64),
+    // Amount of the base asset, taken as is
+    Base(f64),
+}
+
+impl OrderSize {
+    /// Base asset amount when sized at `price`
+    pub fn in_base(&self, price: &BigDecimal) -> Result<BigDecimal, DydxServiceError> {
+        match self {
+            OrderSize::Usd(usd) => {
+                if *price <= BigDecimal::from(0) {
+                    return Err(DydxServiceError::InvalidParameters(format!("Cannot size ${} at a price of {}", usd, price)));
+                }
+                Ok(BigDecimal::from_str(&usd.to_string())?.div(price))
+            }
+            OrderSize::Base(size) => Ok(BigDecimal::from_str(&size.to_string())?),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct TradeRequest {
-    pub asset: String,
+    pub asset: Symbol,
     pub is_buy: bool,
-    pub size: f64,
+    pub size: OrderSize,
     pub price: Option<f64>,
+    // Required for stop and take-profit orders
+    pub trigger_price: Option<f64>,
     pub order_type: OrderType,
     pub reduce_only: bool,
     pub leverage: f64,
     pub cross_margin: Option<bool>,
+    // Market orders only; DEFAULT_ALLOWED_SLIPPAGE_BPS when unset
+    pub max_slippage_bps: Option<u32>,
+    // Unspecified is good til the good-til time; IOC and FOK limit orders go
+    // out short-term, since they never rest
+    pub time_in_force: OrderTimeInForce,
+    // Long-term orders only; DEFAULT_GOOD_TIL_SECS when unset
+    pub good_til_secs: Option<u64>,
 }
+
+impl TradeRequest {
+    /// Reduce-only market order against a position of `position_size`
+    /// (negative for shorts), sized in the asset rather than in USD
+    pub fn close(market: &Symbol, position_size: f64) -> Self {
+        Self {
+            asset: market.clone(),
+            is_buy: position_size < 0.0, // If short position, need to buy to close
+            size: OrderSize::Base(position_size.abs()),
+            price: None, // Market order
+            trigger_price: None,
+            order_type: OrderType::Market,
+            reduce_only: true,
+            leverage: 1.0, // Default leverage for closing
+            cross_margin: None,
+            max_slippage_bps: None,
+            time_in_force: OrderTimeInForce::Ioc,
+            good_til_secs: None,
+        }
+    }
+}
+
 impl DydxService {
     pub async fn new(
         node_config: NodeConfig, 
@@ -94,13 +230,31 @@ impl DydxService {
         account: Account
     ) -> Result<Self, DydxServiceError> {
         let node_client = NodeClient::connect(node_config).await?;
+        Ok(Self::with_node(Arc::new(Mutex::new(Box::new(node_client))), indexer_config, account))
+    }
+
+    /// A service on an already connected node, which may be shared with
+    /// other services.
+    pub fn with_node(node_client: SharedNode, indexer_config: IndexerConfig, account: Account) -> Self {
+        let indexer_endpoint = indexer_config.rest.endpoint.clone();
         let indexer_client = IndexerClient::new(indexer_config);
-        
-        Ok(Self {
-            node_client: Arc::new(Mutex::new(node_client)),
+
+        Self {
+            node_client,
             indexer_client: Arc::new(indexer_client),
             account,
-        })
+            indexer_endpoint,
+        }
+    }
+
+    /// Indexer client for the currently selected endpoint, so calls follow a
+    /// failover without rebuilding the service.
+    pub fn indexer(&self) -> Arc<IndexerClient> {
+        let endpoints = endpoints::dydx();
+        if endpoints.active().rest == self.indexer_endpoint {
+            return self.indexer_client.clone();
+        }
+        Arc::new(IndexerClient::new(endpoints.indexer_config()))
     }
 
     /// Place a new order
@@ -108,37 +262,33 @@ impl DydxService {
         // Create subaccount from the account
         let subaccount = self.account.subaccount(0)?;
 
-        // Format the asset ticker correctly
-        let formatted_ticker = if !request.asset.contains("-USD") {
-            format!("{}-USD", request.asset)
-        } else {
-            request.asset.clone()
-        };
+        let formatted_ticker = request.asset.to_dydx_ticker();
 
         // Log the request details
         let request_details = format!(
             "Sending request to dYdX:\n\
              Formatted Ticker: {}\n\
              Side: {}\n\
-             Size: {}\n\
+             Size: {:?}\n\
              Price: {}\n\
              Type: {:?}\n\
-             URL: https://indexer.dydx.trade/v4/perpetualMarkets?limit=1&ticker={}",
+             URL: {}/v4/perpetualMarkets?limit=1&ticker={}",
             formatted_ticker,
             if request.is_buy { "Buy" } else { "Sell" },
             request.size,
             request.price.map_or("Market".to_string(), |p| p.to_string()),
             request.order_type,
+            endpoints::dydx().active().rest,
             formatted_ticker
         );
 
         // Get market data from indexer using formatted ticker
-        let market = self.indexer_client
-            .markets()
-            .get_perpetual_market(&Ticker::from(formatted_ticker.as_str()))
-            .await
-            .map_err(|e| DydxServiceError::IndexerError(
-                anyhow::anyhow!("{}\nRequest details:\n{}", e, request_details)
+        let ticker = Ticker::from(formatted_ticker.as_str());
+        let market = retry::with_retry(&retry::policy(), "dYdX market", || async {
+            Ok(self.indexer().markets().get_perpetual_market(&ticker).await.map_err(DydxServiceError::from)?)
+        }).await
+            .map_err(|e| DydxServiceError::indexer(
+                format!("{}\nRequest details:\n{}", e, request_details)
             ))?;
 
         // Convert side
@@ -154,15 +304,11 @@ impl DydxService {
             OrderSide::Sell => NodeOrderSide::Sell,
         };
 
-        // Convert size and price to BigDecimal
-        let size_bd = BigDecimal::from_str(&request.size.to_string())
-            .map_err(|e| DydxServiceError::InvalidParameters(format!("Invalid size: {}", e)))?;
-        
         // Build the order based on type
         let (order_id, order) = match request.order_type {
             OrderType::Market => {
-                let current_block_height = self.node_client.lock().unwrap()
-                    .get_latest_block_height()
+                let current_block_height = self.node_client.lock().await
+                    .latest_block_height()
                     .await?;
 
                 // Get market data to convert USD amount to asset quantity
@@ -171,16 +317,15 @@ impl DydxService {
                     .ok_or_else(|| DydxServiceError::InvalidParameters("No oracle price available".to_string()))?;
 
                 let market_price_bd = BigDecimal::from_str(&market_price.to_string())?;
-                let size_in_asset = BigDecimal::from_str(&request.size.to_string())?
-                    .div(&market_price_bd);
+                let size_in_asset = request.size.in_base(&market_price_bd)?;
 
                 OrderBuilder::new(market, subaccount)
                     .market(side, size_in_asset)
-                    .time_in_force(OrderTimeInForce::Ioc)
+                    .time_in_force(request.time_in_force)
                     .reduce_only(request.reduce_only)
                     .short_term()
-                    .allowed_slippage(BigDecimal::from_str("5.0").unwrap())
-                    .until(current_block_height.ahead(15))
+                    .allowed_slippage(allowed_slippage_percent(request.max_slippage_bps.unwrap_or(DEFAULT_ALLOWED_SLIPPAGE_BPS)))
+                    .until(current_block_height.ahead(SHORT_TERM_BLOCKS))
                     .build(rand::random::<u32>())?
             },
             OrderType::Limit => {
@@ -194,21 +339,59 @@ impl DydxService {
                     .ok_or_else(|| DydxServiceError::InvalidParameters("No oracle price available".to_string()))?;
 
                 let market_price_bd = BigDecimal::from_str(&market_price.to_string())?;
-                let size_in_asset = BigDecimal::from_str(&request.size.to_string())?
-                    .div(&market_price_bd);
+                let size_in_asset = request.size.in_base(&market_price_bd)?;
                 
                 let price_bd = BigDecimal::from_str(&price.to_string())
                     .map_err(|e| DydxServiceError::InvalidParameters(format!("Invalid price: {}", e)))?;
 
-                OrderBuilder::new(market.clone(), subaccount)
+                let builder = OrderBuilder::new(market.clone(), subaccount)
                     .limit(
                         side,
                         price_bd,
                         size_in_asset
                     )
-                    .time_in_force(OrderTimeInForce::Unspecified)
+                    .time_in_force(request.time_in_force)
+                    .reduce_only(request.reduce_only);
+                if matches!(request.time_in_force, OrderTimeInForce::Ioc | OrderTimeInForce::FillOrKill) {
+                    // Never rests, so short-term like a market order
+                    let current_block_height = self.node_client.lock().await
+                        .latest_block_height()
+                        .await?;
+                    builder
+                        .short_term()
+                        .until(current_block_height.ahead(SHORT_TERM_BLOCKS))
+                        .build(rand::random::<u32>())?
+                } else {
+                    let good_til = request.good_til_secs.unwrap_or(DEFAULT_GOOD_TIL_SECS);
+                    builder
+                        .long_term()
+                        .until(Utc::now() + TimeDelta::seconds(good_til as i64))
+                        .build(rand::random::<u32>())?
+                }
+            },
+            OrderType::StopMarket | OrderType::TakeProfitMarket => {
+                let trigger_price = request.trigger_price.ok_or_else(||
+                    DydxServiceError::InvalidParameters("Stop and take-profit orders require a trigger price".to_string())
+                )?;
+
+                // Sized at the trigger, where it will fill
+                let trigger_bd = BigDecimal::from_str(&trigger_price.to_string())
+                    .map_err(|e| DydxServiceError::InvalidParameters(format!("Invalid trigger price: {}", e)))?;
+                let size_in_asset = request.size.in_base(&trigger_bd)?;
+
+                let builder = OrderBuilder::new(market, subaccount);
+                let builder = if matches!(request.order_type, OrderType::StopMarket) {
+                    builder.stop_market(side, trigger_bd, size_in_asset)
+                } else {
+                    builder.take_profit_market(side, trigger_bd, size_in_asset)
+                };
+
+                // Conditional orders are stateful, like long-term ones
+                builder
+                    .conditional()
+                    .time_in_force(OrderTimeInForce::Ioc)
                     .reduce_only(request.reduce_only)
-                    .long_term()
+                    .allowed_slippage(allowed_slippage_percent(DEFAULT_ALLOWED_SLIPPAGE_BPS))
                     .until(Utc::now() + TimeDelta::days(28))
                     .build(rand::random::<u32>())?
             },
@@ -219,25 +402,31 @@ impl DydxService {
             }
         };
 
-        // Place the order with timeout
+        let tx_hash = self.broadcast_order(order).await?;
+        Ok((tx_hash, order_id))
+    }
+
+    /// Send a built order to the node, giving up after 30 seconds; waiting
+    /// for the node lock counts towards it
+    pub async fn broadcast_order(&mut self, order: NodeOrder) -> Result<String, DydxServiceError> {
         let tx_hash = tokio::time::timeout(
             std::time::Duration::from_secs(30),
-            self.node_client.lock().unwrap().place_order(&mut self.account, order)
+            async { self.node_client.lock().await.place_order(&mut self.account, order).await }
         ).await
         .map_err(|_| DydxServiceError::ClientError(NodeError::General(
             anyhow::Error::msg("Order placement timed out after 30 seconds")
         )))??;
 
-        Ok((tx_hash.to_string(), order_id))
+        Ok(tx_hash.to_string())
     }
 
     /// Get all open orders for the account
     pub async fn get_open_orders(&self, subaccount: Subaccount) -> Result<Vec<OrderResponseObject>, DydxServiceError> {
         // Using the indexer client to get orders
-        let orders = self.indexer_client
-            .accounts()
-            .list_parent_orders(&subaccount.parent(), None)
-            .await?;
+        let parent = subaccount.parent();
+        let orders = retry::with_retry(&retry::policy(), "dYdX open orders", || async {
+            Ok(self.indexer().accounts().list_parent_orders(&parent, None).await.map_err(DydxServiceError::from)?)
+        }).await.map_err(DydxServiceError::indexer)?;
 
         Ok(orders)
     }
@@ -245,35 +434,39 @@ impl DydxService {
     /// Get all open positions for the account
     pub async fn get_open_positions(&self, subaccount: Subaccount) 
         -> Result<Vec<PerpetualPositionResponseObject>, DydxServiceError> {
-        let positions_result = self.indexer_client
-            .accounts()
-            .list_parent_positions(
-                &subaccount.parent(),
-                Some(ListPositionsOpts {
-                    status: Some(PerpetualPositionStatus::Open),
-                    ..Default::default()
-                }),
-            )
-            .await;
+        let parent = subaccount.parent();
+        let positions_result = retry::with_retry(&retry::policy(), "dYdX open positions", || async {
+            Ok(self.indexer()
+                .accounts()
+                .list_parent_positions(
+                    &parent,
+                    Some(ListPositionsOpts {
+                        status: Some(PerpetualPositionStatus::Open),
+                        ..Default::default()
+                    }),
+                )
+                .await
+                .map_err(DydxServiceError::from)?)
+        }).await;
 
         match positions_result {
             Ok(positions) => Ok(positions),
             Err(e) => {
                 error!("Error retrieving positions: {:?}", e);
-                Err(DydxServiceError::IndexerError(e.into()))
+                Err(DydxServiceError::indexer(e))
             }
         }
     }
 
     pub fn update_node_client(&mut self, client: NodeClient) {
-        self.node_client = Arc::new(Mutex::new(client));
+        self.node_client = Arc::new(Mutex::new(Box::new(client)));
     }
 
     pub async fn cancel_order(&mut self, order_id: OrderId) -> Result<String, DydxServiceError> {
-        let mut node_client = self.node_client.lock().unwrap();
-        
+        let mut node_client = self.node_client.lock().await;
+
         // Get current block height for good-til-block parameter
-        let current_block_height = node_client.get_latest_block_height().await?;
+        let current_block_height = node_client.latest_block_height().await?;
         
         // For long-term (stateful) orders, use timestamp
         let good_til_block = if order_id.order_flags & 0x40 != 0 { // Check if long-term order flag is set
@@ -298,6 +491,8 @@ impl DydxService {
                 good_til_block
             )
             .await?;
+        // Nothing else needs the node while the cancel lands
+        drop(node_client);
 
         // Wait for transaction to be included in a block
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -309,30 +504,10 @@ impl DydxService {
 
     pub async fn close_position(
         &mut self, 
-        market: String,
+        market: &Symbol,
         position_size: f64
     ) -> Result<(String, OrderId), DydxServiceError> {
-        let subaccount = self.account.subaccount(0)?;
-        let ticker = Ticker::from(market.as_str());
-        
-        // Get market data
-        let market = self.indexer_client
-            .markets()
-            .get_perpetual_market(&ticker)
-            .await?;
-
-        // Create a market order in the opposite direction
-        let request = TradeRequest {
-            asset: market.ticker.to_string(),
-            is_buy: position_size < 0.0, // If short position, need to buy to close
-            size: position_size.abs(),
-            price: None, // Market order
-            order_type: OrderType::Market,
-            reduce_only: true,
-            leverage: 1.0, // Default leverage for closing
-            cross_margin: None,
-        };
-
+        let request = TradeRequest::close(market, position_size);
         self.place_trade(request, 1.0).await
     }
 }
\ No newline at end of file
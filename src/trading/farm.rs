@@ -0,0 +1,329 @@
+use std::collections::{BTreeMap, HashSet};
+use crate::error::Result;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use super::positions::episodes::Fill;
+
+const DAY_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+// APR over less than this much history is mostly noise
+const MIN_REPORT_DAYS: f64 = 1.0 / 24.0;
+
+/// One funding settlement on one of the account's positions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FundingPayment {
+    pub exchange: ExchangeId,
+    // Venue market name, e.g. "BTC" or "BTC-USD"
+    pub asset: String,
+    // Millis timestamp of the settlement
+    pub time: i64,
+    // USD, positive when received, negative when paid
+    pub amount: f64,
+    // The venue's funding rate for the settlement; absent in older journals
+    #[serde(default)]
+    pub rate: Option<f64>,
+}
+
+impl FundingPayment {
+    fn is_for(&self, symbol: &Symbol) -> bool {
+        Symbol::parse_user_input(&self.asset).is_ok_and(|asset| &asset == symbol)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HlFundingEntry {
+    time: i64,
+    delta: HlFundingDelta,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HlFundingDelta {
+    coin: String,
+    usdc: String,
+    funding_rate: Option<String>,
+}
+
+/// Payments from Hyperliquid's `userFunding` info query; `usdc` is already
+/// signed from the account's side.
+pub fn parse_hl_funding_payments(body: &str) -> Result<Vec<FundingPayment>> {
+    let entries: Vec<HlFundingEntry> = serde_json::from_str(body)?;
+    Ok(entries.into_iter()
+        .filter_map(|entry| Some(FundingPayment {
+            exchange: ExchangeId::Hyperliquid,
+            asset: entry.delta.coin,
+            time: entry.time,
+            amount: entry.delta.usdc.parse().ok()?,
+            rate: entry.delta.funding_rate.and_then(|rate| rate.parse().ok()),
+        }))
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DydxFundingPayments {
+    funding_payments: Vec<DydxFundingPayment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DydxFundingPayment {
+    created_at: String,
+    ticker: String,
+    payment: String,
+    rate: Option<String>,
+}
+
+/// Payments from the dYdX indexer's fundingPayments listing.
+pub fn parse_dydx_funding_payments(body: &str) -> Result<Vec<FundingPayment>> {
+    let listing: DydxFundingPayments = serde_json::from_str(body)?;
+    Ok(listing.funding_payments.into_iter()
+        .filter_map(|entry| Some(FundingPayment {
+            exchange: ExchangeId::Dydx,
+            asset: entry.ticker,
+            time: DateTime::parse_from_rfc3339(&entry.created_at).ok()?.timestamp_millis(),
+            amount: entry.payment.parse().ok()?,
+            rate: entry.rate.and_then(|rate| rate.parse().ok()),
+        }))
+        .collect())
+}
+
+/// One side of a delta-neutral farm as it stood when tracking started.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FarmLeg {
+    pub exchange: ExchangeId,
+    // Signed: negative for the short leg
+    pub size: f64,
+    pub entry_price: f64,
+    // Fees paid to open this leg
+    pub open_fees: f64,
+}
+
+impl FarmLeg {
+    pub fn notional(&self) -> f64 {
+        self.size.abs() * self.entry_price
+    }
+}
+
+/// What the journal records about a farm. Replaying these rebuilds the
+/// tracker after a restart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FarmEvent {
+    Opened { symbol: Symbol, time: i64, legs: Vec<FarmLeg> },
+    Funding { symbol: Symbol, payment: FundingPayment },
+    // A fee on a fill in the farm's market after it opened, e.g. a rebalance
+    Fee { symbol: Symbol, exchange: ExchangeId, time: i64, amount: f64 },
+    Closed { symbol: Symbol, time: i64 },
+}
+
+#[derive(Debug, Clone)]
+struct Farm {
+    started: i64,
+    legs: Vec<FarmLeg>,
+    funding: f64,
+    payments: usize,
+    fees: f64,
+    // (exchange, time) of every payment counted, so refetches don't double count
+    seen_payments: HashSet<(ExchangeId, i64)>,
+    // Newest fill whose fee is counted
+    fills_seen: i64,
+}
+
+/// Delta-neutral funding farms, measured from the payments actually
+/// received and the fees actually paid.
+#[derive(Debug, Clone, Default)]
+pub struct FarmTracker {
+    farms: BTreeMap<String, (Symbol, Farm)>,
+}
+
+/// A farm's performance since it opened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FarmReport {
+    pub symbol: Symbol,
+    pub started: i64,
+    pub days: f64,
+    // Mean of the two legs' entry notionals
+    pub notional: f64,
+    // Net funding received across both legs
+    pub funding: f64,
+    pub payments: usize,
+    pub fees: f64,
+    // Mark-to-market PnL of the legs together; zero for a perfect hedge
+    pub drift: f64,
+}
+
+impl FarmReport {
+    fn annualize(&self, amount: f64) -> Option<f64> {
+        (self.notional > 0.0 && self.days >= MIN_REPORT_DAYS).then(|| amount / self.notional / self.days * 365.0 * 100.0)
+    }
+
+    /// Funding alone, as a percent APR on the notional
+    pub fn funding_apr(&self) -> Option<f64> {
+        self.annualize(self.funding)
+    }
+
+    /// Funding net of fees, as a percent APR on the notional
+    pub fn realized_apr(&self) -> Option<f64> {
+        self.annualize(self.funding - self.fees)
+    }
+
+    pub fn net_pnl(&self) -> f64 {
+        self.funding - self.fees + self.drift
+    }
+
+    /// Days until funding covers the fees at the rate earned so far: zero
+    /// once it has, None while funding isn't net positive.
+    pub fn days_to_breakeven(&self) -> Option<f64> {
+        if self.funding >= self.fees {
+            return Some(0.0);
+        }
+        let daily = self.funding / self.days.max(MIN_REPORT_DAYS);
+        (daily > 0.0).then(|| (self.fees - self.funding) / daily)
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let percent = |apr: Option<f64>| apr.map_or_else(|| "n/a".to_string(), |apr| format!("{:+.2}%", apr));
+        let breakeven = match self.days_to_breakeven() {
+            Some(days) if days == 0.0 => "fees covered".to_string(),
+            Some(days) => format!("{:.1} days to cover fees", days),
+            None => "not earning funding".to_string(),
+        };
+        vec![
+            format!("{} farm, {:.1} days on ${:.2} per leg", self.symbol, self.days, self.notional),
+            format!(
+                "   funding {:+.2} ({} payments)  fees {:.2}  drift {:+.2}  net {:+.2}",
+                self.funding, self.payments, self.fees, self.drift, self.net_pnl()
+            ),
+            format!("   APR {} realized, {} gross; {}", percent(self.realized_apr()), percent(self.funding_apr()), breakeven),
+        ]
+    }
+}
+
+impl FarmTracker {
+    /// Rebuild from journaled events, oldest first.
+    pub fn replay(events: &[FarmEvent]) -> Self {
+        let mut tracker = Self::default();
+        for event in events {
+            tracker.apply(event);
+        }
+        tracker
+    }
+
+    pub fn apply(&mut self, event: &FarmEvent) {
+        match event {
+            FarmEvent::Opened { symbol, time, legs } => {
+                let farm = Farm {
+                    started: *time,
+                    legs: legs.clone(),
+                    funding: 0.0,
+                    payments: 0,
+                    fees: legs.iter().map(|leg| leg.open_fees).sum(),
+                    seen_payments: HashSet::new(),
+                    fills_seen: *time,
+                };
+                self.farms.insert(symbol.to_string(), (symbol.clone(), farm));
+            }
+            FarmEvent::Funding { symbol, payment } => {
+                if let Some(farm) = self.farm_mut(symbol) {
+                    if farm.seen_payments.insert((payment.exchange.clone(), payment.time)) {
+                        farm.funding += payment.amount;
+                        farm.payments += 1;
+                    }
+                }
+            }
+            FarmEvent::Fee { symbol, time, amount, .. } => {
+                if let Some(farm) = self.farm_mut(symbol) {
+                    farm.fees += amount;
+                    farm.fills_seen = farm.fills_seen.max(*time);
+                }
+            }
+            FarmEvent::Closed { symbol, .. } => {
+                self.farms.remove(&symbol.to_string());
+            }
+        }
+    }
+
+    fn farm_mut(&mut self, symbol: &Symbol) -> Option<&mut Farm> {
+        self.farms.get_mut(&symbol.to_string()).map(|(_, farm)| farm)
+    }
+
+    pub fn symbols(&self) -> Vec<Symbol> {
+        self.farms.values().map(|(symbol, _)| symbol.clone()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.farms.is_empty()
+    }
+
+    /// Start of the oldest farm, i.e. how far back payments are needed
+    pub fn earliest_start(&self) -> Option<i64> {
+        self.farms.values().map(|(_, farm)| farm.started).min()
+    }
+
+    /// Count payments not seen yet on the farms' venues and markets since
+    /// they opened. Returns the events to journal.
+    pub fn record_payments(&mut self, payments: &[FundingPayment]) -> Vec<FarmEvent> {
+        let mut events = Vec::new();
+        for (symbol, farm) in self.farms.values() {
+            for payment in payments {
+                let key = (payment.exchange.clone(), payment.time);
+                let on_leg = farm.legs.iter().any(|leg| leg.exchange == payment.exchange);
+                if on_leg && payment.time >= farm.started && payment.is_for(symbol) && !farm.seen_payments.contains(&key) {
+                    events.push(FarmEvent::Funding { symbol: symbol.clone(), payment: payment.clone() });
+                }
+            }
+        }
+        for event in &events {
+            self.apply(event);
+        }
+        events
+    }
+
+    /// Count fees on fills in the farms' markets newer than the last one
+    /// counted. Returns the events to journal.
+    pub fn record_fills(&mut self, fills: &[Fill]) -> Vec<FarmEvent> {
+        let mut events = Vec::new();
+        for (symbol, farm) in self.farms.values() {
+            let mut fills: Vec<&Fill> = fills.iter()
+                .filter(|fill| fill.time > farm.fills_seen)
+                .filter(|fill| farm.legs.iter().any(|leg| leg.exchange == fill.exchange))
+                .filter(|fill| Symbol::parse_user_input(&fill.asset).is_ok_and(|asset| &asset == symbol))
+                .collect();
+            fills.sort_by_key(|fill| fill.time);
+            events.extend(fills.into_iter().map(|fill| FarmEvent::Fee {
+                symbol: symbol.clone(),
+                exchange: fill.exchange.clone(),
+                time: fill.time,
+                amount: fill.fee,
+            }));
+        }
+        for event in &events {
+            self.apply(event);
+        }
+        events
+    }
+
+    /// Report at `now_ms`, with the legs marked at `marks`. A leg without a
+    /// mark counts as flat.
+    pub fn report(&self, symbol: &Symbol, marks: &[(ExchangeId, f64)], now_ms: i64) -> Option<FarmReport> {
+        let (symbol, farm) = self.farms.get(&symbol.to_string())?;
+        let drift = farm.legs.iter()
+            .filter_map(|leg| {
+                let (_, mark) = marks.iter().find(|(exchange, _)| exchange == &leg.exchange)?;
+                Some(leg.size * (mark - leg.entry_price))
+            })
+            .sum();
+        let notional = farm.legs.iter().map(FarmLeg::notional).sum::<f64>() / farm.legs.len().max(1) as f64;
+        Some(FarmReport {
+            symbol: symbol.clone(),
+            started: farm.started,
+            days: (now_ms - farm.started).max(0) as f64 / DAY_MS,
+            notional,
+            funding: farm.funding,
+            payments: farm.payments,
+            fees: farm.fees,
+            drift,
+        })
+    }
+}
@@ -0,0 +1,83 @@
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Whether this process may write shared state and trade. A second instance
+/// can run read-only next to a writing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccessMode {
+    #[default]
+    ReadWrite,
+    ReadOnly,
+}
+
+impl AccessMode {
+    pub fn is_read_only(&self) -> bool {
+        *self == AccessMode::ReadOnly
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("{} is locked by another instance{}; start with --read-only to observe", .path.display(), .pid.map(|pid| format!(" (PID {})", pid)).unwrap_or_default())]
+    Held { path: PathBuf, pid: Option<u32> },
+
+    #[error("Failed to lock {}: {source}", .path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+}
+
+/// Exclusive advisory lock on a `.lock` file next to the state it guards.
+/// The file holds the owner's PID so a second instance can say who has it.
+/// Released when dropped, or by the OS if the process dies.
+#[derive(Debug)]
+pub struct FileLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Fails fast instead of waiting when another process holds the lock.
+    pub fn acquire(path: PathBuf) -> Result<Self, LockError> {
+        let io_error = |source| LockError::Io { path: path.clone(), source };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        // Not truncated on open: that would wipe the holder's PID
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(io_error)?;
+
+        if file.try_lock_exclusive().is_err() {
+            return Err(LockError::Held { pid: Self::holder_pid(&path), path });
+        }
+
+        file.set_len(0)
+            .and_then(|_| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| write!(file, "{}", std::process::id()))
+            .and_then(|_| file.flush())
+            .map_err(io_error)?;
+
+        Ok(Self { file, path })
+    }
+
+    /// PID recorded in a lock file, if any
+    pub fn holder_pid(path: &Path) -> Option<u32> {
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
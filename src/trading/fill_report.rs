@@ -0,0 +1,78 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use super::orders::OrderOutcome;
+use super::positions::episodes::Fill;
+
+// How often an order is rechecked while waiting on its fill
+pub const FILL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where an order stood when `wait_for_fill` stopped watching it
+#[derive(Debug, Clone, PartialEq)]
+pub enum FillStatus {
+    // Left the book: filled, or cancelled, expired or rejected after any
+    // partial fill in the report
+    Terminal(OrderOutcome),
+    // Still live at the timeout
+    NonTerminal,
+}
+
+/// What an order actually filled, summed over its fills
+#[derive(Debug, Clone, PartialEq)]
+pub struct FillReport {
+    pub filled_size: f64,
+    // Size-weighted; None until something fills
+    pub avg_fill_price: Option<f64>,
+    pub fees: f64,
+    pub status: FillStatus,
+}
+
+impl FillReport {
+    /// Sum the fills of `order_id` among `fills`
+    pub fn from_fills(fills: &[Fill], order_id: &str, status: FillStatus) -> Self {
+        let fills: Vec<&Fill> = fills.iter().filter(|fill| fill.order_id == order_id).collect();
+        let filled_size: f64 = fills.iter().map(|fill| fill.size).sum();
+        let notional: f64 = fills.iter().map(|fill| fill.size * fill.price).sum();
+        Self {
+            filled_size,
+            avg_fill_price: (filled_size > 0.0).then(|| notional / filled_size),
+            fees: fills.iter().map(|fill| fill.fee).sum(),
+            status,
+        }
+    }
+
+    /// e.g. "Filled 0.0100 @ 60012.50, fees $0.27" or "Open, 0.0050 filled
+    /// @ 60012.50, fees $0.13"
+    pub fn describe(&self) -> String {
+        let filled = match self.avg_fill_price {
+            Some(price) => format!("{:.4} @ {:.2}, fees ${:.2}", self.filled_size, price, self.fees),
+            None => "nothing filled".to_string(),
+        };
+        match &self.status {
+            FillStatus::Terminal(OrderOutcome::Filled) => format!("Filled {}", filled),
+            FillStatus::Terminal(outcome) => format!("{}, {}", outcome, filled),
+            FillStatus::NonTerminal => format!("Open, {}", filled),
+        }
+    }
+}
+
+/// Ask `outcome` every `interval` until it reports how the order left the
+/// book or `timeout` passes. An order the venue doesn't list yet counts as
+/// live.
+pub async fn poll_until_terminal<F, Fut, E>(timeout: Duration, interval: Duration, mut outcome: F) -> Result<FillStatus, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<OrderOutcome>, E>>,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(outcome) = outcome().await? {
+            return Ok(FillStatus::Terminal(outcome));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(FillStatus::NonTerminal);
+        }
+        tokio::time::sleep(interval.min(deadline - now)).await;
+    }
+}
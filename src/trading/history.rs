@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+use crate::error::Result;
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use crate::aggregator::exchange_id::ExchangeId;
+use super::journal::JournalEntry;
+use super::positions::episodes::{fill_pnl, Fill};
+use super::reconcile::OrderOrigin;
+
+// Hyperliquid caps each fills response at this many entries
+pub const HL_FILL_PAGE: usize = 2000;
+// Page size requested from the dYdX indexer
+pub const DYDX_FILL_PAGE: usize = 1000;
+
+/// A venue fill copied into the journal, so history survives the venues'
+/// own retention limits. `origin` is that of the order it filled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournaledFill {
+    pub fill: Fill,
+    pub origin: OrderOrigin,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum FillKey {
+    Venue(ExchangeId, String),
+    // For fills without a venue id
+    Shape(ExchangeId, String, i64, u64, u64),
+}
+
+fn fill_key(fill: &Fill) -> FillKey {
+    if fill.fill_id.is_empty() {
+        FillKey::Shape(fill.exchange.clone(), fill.order_id.clone(), fill.time, fill.size.to_bits(), fill.price.to_bits())
+    } else {
+        FillKey::Venue(fill.exchange.clone(), fill.fill_id.clone())
+    }
+}
+
+/// Fetched fills not in the journal yet, each tagged `Local` if its order id
+/// is one of `app_orders`. Duplicates within `fetched`, as overlapping pages
+/// produce, are dropped too.
+pub fn new_fills(journaled: &[JournaledFill], fetched: Vec<Fill>, app_orders: &HashSet<(ExchangeId, String)>) -> Vec<JournaledFill> {
+    let mut seen: HashSet<FillKey> = journaled.iter().map(|entry| fill_key(&entry.fill)).collect();
+    fetched.into_iter()
+        .filter(|fill| seen.insert(fill_key(fill)))
+        .map(|fill| {
+            let origin = if app_orders.contains(&(fill.exchange.clone(), fill.order_id.clone())) {
+                OrderOrigin::Local
+            } else {
+                OrderOrigin::External
+            };
+            JournaledFill { fill, origin }
+        })
+        .collect()
+}
+
+/// Journaled and freshly fetched fills as one deduplicated history, oldest
+/// first.
+pub fn merge_fills(journaled: Vec<Fill>, fetched: Vec<Fill>) -> Vec<Fill> {
+    let mut seen = HashSet::new();
+    let mut fills: Vec<Fill> = journaled.into_iter()
+        .chain(fetched)
+        .filter(|fill| seen.insert(fill_key(fill)))
+        .collect();
+    fills.sort_by_key(|fill| fill.time);
+    fills
+}
+
+/// One fill in the trade history, with running totals for its market
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeHistoryRow {
+    pub fill: Fill,
+    // Price PnL this fill realized, before fees
+    pub realized: f64,
+    // Totals over the market's fills up to and including this one
+    pub total_fees: f64,
+    pub total_realized: f64,
+}
+
+/// Fills at or after `since_ms`, newest first, with running fee and realized
+/// PnL totals per (exchange, asset) counted from `since_ms`. Realized PnL is
+/// worked out over every fill, so positions opened earlier close at their
+/// true entry.
+pub fn trade_history(fills: &[Fill], since_ms: Option<i64>) -> Vec<TradeHistoryRow> {
+    let mut realized = fill_pnl(fills);
+    realized.sort_by_key(|entry| entry.fill.time);
+    let mut totals: HashMap<(ExchangeId, String), (f64, f64)> = HashMap::new();
+    let mut rows: Vec<TradeHistoryRow> = realized.into_iter()
+        .filter(|entry| since_ms.is_none_or(|since| entry.fill.time >= since))
+        .map(|entry| {
+            let total = totals.entry((entry.fill.exchange.clone(), entry.fill.asset.clone())).or_default();
+            total.0 += entry.fill.fee;
+            total.1 += entry.realized;
+            TradeHistoryRow { total_fees: total.0, total_realized: total.1, realized: entry.realized, fill: entry.fill }
+        })
+        .collect();
+    rows.reverse();
+    rows
+}
+
+/// "YYYY-MM-DD" (midnight UTC) or an RFC 3339 timestamp, as millis.
+pub fn parse_since(input: &str) -> Result<i64, String> {
+    let input = input.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0)
+            .map(|start| start.and_utc().timestamp_millis())
+            .ok_or_else(|| format!("Invalid date '{}'", input));
+    }
+    DateTime::parse_from_rfc3339(input)
+        .map(|at| at.timestamp_millis())
+        .map_err(|_| format!("Invalid date '{}'; expected YYYY-MM-DD", input))
+}
+
+#[derive(Debug, Deserialize)]
+struct HlFill {
+    coin: String,
+    px: String,
+    sz: String,
+    side: String,
+    time: i64,
+    fee: String,
+    oid: u64,
+    #[serde(default)]
+    tid: Option<u64>,
+}
+
+/// Fills from Hyperliquid's `userFills` and `userFillsByTime` info queries,
+/// keyed by trade id.
+pub fn parse_hl_fills(body: &str) -> Result<Vec<Fill>> {
+    let fills: Vec<HlFill> = serde_json::from_str(body)?;
+    Ok(fills.into_iter()
+        .filter_map(|fill| Some(Fill {
+            exchange: ExchangeId::Hyperliquid,
+            is_buy: fill.side == "B",
+            price: fill.px.parse().ok()?,
+            size: fill.sz.parse().ok()?,
+            fee: fill.fee.parse().ok()?,
+            time: fill.time,
+            order_id: fill.oid.to_string(),
+            fill_id: fill.tid.map(|tid| tid.to_string()).unwrap_or_default(),
+            asset: fill.coin,
+        }))
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+struct DydxFills {
+    fills: Vec<DydxFill>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DydxFill {
+    id: String,
+    side: String,
+    market: String,
+    price: String,
+    size: String,
+    fee: String,
+    created_at: String,
+    #[serde(default)]
+    order_id: Option<String>,
+}
+
+/// Fills from the dYdX indexer's fills listing. `order_id` is the indexer's
+/// order uuid; see `parse_dydx_order_aliases`.
+pub fn parse_dydx_fills(body: &str) -> Result<Vec<Fill>> {
+    let listing: DydxFills = serde_json::from_str(body)?;
+    Ok(listing.fills.into_iter()
+        .filter_map(|fill| Some(Fill {
+            exchange: ExchangeId::Dydx,
+            is_buy: fill.side == "BUY",
+            price: fill.price.parse().ok()?,
+            size: fill.size.parse().ok()?,
+            fee: fill.fee.parse().ok()?,
+            time: DateTime::parse_from_rfc3339(&fill.created_at).ok()?.timestamp_millis(),
+            order_id: fill.order_id.unwrap_or_default(),
+            fill_id: fill.id,
+            asset: fill.market,
+        }))
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DydxOrderIds {
+    id: String,
+    client_id: String,
+    clob_pair_id: String,
+    order_flags: String,
+    subaccount_number: u32,
+}
+
+/// Indexer order uuid to the "client_id:clob_pair_id:order_flags:subaccount"
+/// id the journal records, from the dYdX orders listing.
+pub fn parse_dydx_order_aliases(body: &str) -> Result<HashMap<String, String>> {
+    let orders: Vec<DydxOrderIds> = serde_json::from_str(body)?;
+    Ok(orders.into_iter()
+        .map(|order| {
+            let id = format!("{}:{}:{}:{}", order.client_id, order.clob_pair_id, order.order_flags, order.subaccount_number);
+            (order.id, id)
+        })
+        .collect())
+}
+
+/// (exchange, order id) of every order the journal shows this app placed
+pub fn app_order_ids(entries: &[JournalEntry]) -> HashSet<(ExchangeId, String)> {
+    entries.iter()
+        .filter_map(|entry| Some((entry.exchange.clone(), entry.order_id.clone()?)))
+        .collect()
+}
@@ -0,0 +1,250 @@
+use ethers::types::Address;
+use serde::Deserialize;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::error::{AggregatorError, MarginInUse, Result};
+
+// Hyperliquid takes a $1 fee out of every withdrawal
+pub const HL_MIN_WITHDRAWAL: f64 = 2.0;
+// The bridge doesn't credit smaller deposits; the USDC is lost
+pub const HL_MIN_DEPOSIT: f64 = 5.0;
+
+/// Refuse a deposit of `amount` USDC below the bridge minimum, above the
+/// `usdc` held on Arbitrum, or without the ETH to pay `gas_cost`.
+pub fn check_deposit(amount: f64, usdc: f64, eth: f64, gas_cost: f64) -> Result<(), AggregatorError> {
+    if !amount.is_finite() || amount < HL_MIN_DEPOSIT {
+        return Err(AggregatorError::InvalidAmount(format!("Minimum deposit is ${:.2}; smaller deposits are lost", HL_MIN_DEPOSIT)));
+    }
+    if amount > usdc {
+        return Err(AggregatorError::InsufficientBalance { needed: amount, available: usdc });
+    }
+    if eth < gas_cost {
+        return Err(AggregatorError::InvalidAmount(format!("Gas needs {:.6} ETH but the wallet has {:.6}", gas_cost, eth)));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HlMarginSummary {
+    pub account_value: f64,
+    pub total_margin_used: f64,
+    pub total_notional: f64,
+    pub total_raw_usd: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HlMarginMode {
+    Cross,
+    Isolated { raw_usd: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlPositionMargin {
+    pub coin: String,
+    pub size: f64,
+    pub position_value: f64,
+    pub margin_used: f64,
+    pub leverage: u32,
+    pub mode: HlMarginMode,
+    pub liquidation_price: Option<f64>,
+}
+
+/// Typed view of Hyperliquid's `clearinghouseState` for one user.
+///
+/// Parsed from the raw info response rather than the SDK struct because the
+/// SDK does not expose the cross maintenance margin. Spot-only accounts and
+/// accounts without positions parse to zeroed summaries.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HlAccountState {
+    pub margin_summary: HlMarginSummary,
+    pub cross_margin_summary: HlMarginSummary,
+    pub cross_maintenance_margin_used: f64,
+    pub withdrawable: f64,
+    pub positions: Vec<HlPositionMargin>,
+}
+
+impl HlAccountState {
+    /// Fetch `address`'s state from the info endpoint at `url`, mainnet's or
+    /// testnet's
+    pub async fn fetch_from(url: &str, address: Address) -> Result<Self> {
+        let response = reqwest::Client::new()
+            .post(url)
+            .json(&serde_json::json!({
+                "type": "clearinghouseState",
+                "user": format!("{:#x}", address),
+            }))
+            .send()
+            .await?;
+
+        Self::from_json(&response.text().await?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let raw: RawClearinghouseState = serde_json::from_str(json)?;
+
+        let positions = raw.asset_positions.iter()
+            .map(|asset| {
+                let position = &asset.position;
+                Ok(HlPositionMargin {
+                    coin: position.coin.clone(),
+                    size: parse_num(&position.szi)?,
+                    position_value: parse_num(&position.position_value)?,
+                    margin_used: parse_num(&position.margin_used)?,
+                    leverage: position.leverage.value,
+                    mode: match position.leverage.type_string.as_str() {
+                        "isolated" => HlMarginMode::Isolated {
+                            raw_usd: position.leverage.raw_usd.as_deref().map(parse_num).transpose()?.unwrap_or(0.0),
+                        },
+                        _ => HlMarginMode::Cross,
+                    },
+                    liquidation_price: position.liquidation_px.as_deref().map(parse_num).transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            margin_summary: raw.margin_summary.parse()?,
+            cross_margin_summary: raw.cross_margin_summary.parse()?,
+            cross_maintenance_margin_used: parse_num(&raw.cross_maintenance_margin_used)?,
+            withdrawable: parse_num(&raw.withdrawable)?,
+            positions,
+        })
+    }
+
+    pub fn account_value(&self) -> f64 {
+        self.margin_summary.account_value
+    }
+
+    pub fn margin_used(&self) -> f64 {
+        self.margin_summary.total_margin_used
+    }
+
+    /// Margin locked in isolated positions, which cross positions can't draw on
+    pub fn isolated_margin(&self) -> f64 {
+        self.positions.iter()
+            .filter(|p| matches!(p.mode, HlMarginMode::Isolated { .. }))
+            .map(|p| p.margin_used)
+            .sum()
+    }
+
+    /// Cross maintenance margin as a fraction of cross account value; the
+    /// account is liquidated as this approaches 1.0
+    pub fn cross_margin_ratio(&self) -> Option<f64> {
+        if self.cross_margin_summary.account_value > 0.0 {
+            Some(self.cross_maintenance_margin_used / self.cross_margin_summary.account_value)
+        } else {
+            None
+        }
+    }
+
+    pub fn position(&self, coin: &str) -> Option<&HlPositionMargin> {
+        self.positions.iter().find(|p| p.coin == coin)
+    }
+
+    /// What a withdrawal may take: account value less the margin in use
+    pub fn withdrawable_balance(&self) -> f64 {
+        (self.account_value() - self.margin_used()).max(0.0)
+    }
+
+    /// Refuse a withdrawal of `amount` USD below the minimum or above the
+    /// withdrawable balance; the latter is a `MarginInUse` when it's open
+    /// positions that hold the rest.
+    pub fn check_withdrawal(&self, amount: f64) -> Result<(), AggregatorError> {
+        if !amount.is_finite() || amount < HL_MIN_WITHDRAWAL {
+            return Err(AggregatorError::InvalidAmount(format!("Minimum withdrawal is ${:.2}", HL_MIN_WITHDRAWAL)));
+        }
+        let withdrawable = self.withdrawable_balance();
+        if amount <= withdrawable {
+            return Ok(());
+        }
+        if self.margin_used() > 0.0 && amount <= self.account_value() {
+            return Err(MarginInUse {
+                exchange: ExchangeId::Hyperliquid,
+                requested: amount,
+                withdrawable,
+                margin_used: self.margin_used(),
+            }
+            .into());
+        }
+        Err(AggregatorError::InvalidAmount(format!("${:.2} exceeds the ${:.2} withdrawable", amount, withdrawable)))
+    }
+}
+
+fn parse_num(value: &str) -> Result<f64> {
+    value.parse::<f64>()
+        .map_err(|e| AggregatorError::ApiError(format!("Invalid number {:?} in user state: {}", value, e)))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawClearinghouseState {
+    #[serde(default)]
+    asset_positions: Vec<RawAssetPosition>,
+    #[serde(default)]
+    margin_summary: RawMarginSummary,
+    #[serde(default)]
+    cross_margin_summary: RawMarginSummary,
+    #[serde(default = "zero")]
+    cross_maintenance_margin_used: String,
+    #[serde(default = "zero")]
+    withdrawable: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMarginSummary {
+    account_value: String,
+    total_margin_used: String,
+    total_ntl_pos: String,
+    total_raw_usd: String,
+}
+
+impl Default for RawMarginSummary {
+    fn default() -> Self {
+        Self {
+            account_value: zero(),
+            total_margin_used: zero(),
+            total_ntl_pos: zero(),
+            total_raw_usd: zero(),
+        }
+    }
+}
+
+impl RawMarginSummary {
+    fn parse(&self) -> Result<HlMarginSummary> {
+        Ok(HlMarginSummary {
+            account_value: parse_num(&self.account_value)?,
+            total_margin_used: parse_num(&self.total_margin_used)?,
+            total_notional: parse_num(&self.total_ntl_pos)?,
+            total_raw_usd: parse_num(&self.total_raw_usd)?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RawAssetPosition {
+    position: RawPosition,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawPosition {
+    coin: String,
+    szi: String,
+    leverage: RawLeverage,
+    position_value: String,
+    margin_used: String,
+    liquidation_px: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawLeverage {
+    #[serde(rename = "type")]
+    type_string: String,
+    value: u32,
+    raw_usd: Option<String>,
+}
+
+fn zero() -> String {
+    "0.0".to_string()
+}
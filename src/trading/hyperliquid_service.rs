@@ -1,80 +1,127 @@
 use hyperliquid_rust_sdk::{
-    BaseUrl, ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient,
-    ExchangeResponseStatus, InfoClient, ClientCancelRequest,
+    BaseUrl, ClientLimit, ClientOrder, ClientOrderRequest, ClientTrigger, ExchangeClient,
+    ExchangeResponseStatus, InfoClient, ClientCancelRequest, ClientCancelRequestCloid,
 };
-use anyhow::Result;
-use super::{OrderType, TradeRequest};
+use crate::error::{AggregatorError, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use std::collections::HashSet;
+use uuid::Uuid;
+use super::fill_report::{poll_until_terminal, FillReport, FILL_POLL_INTERVAL};
+use super::{OrderType, TimeInForce, TradeRequest};
 use super::positions::Position;
+use super::positions::episodes::Fill;
+use super::orders::{parse_hl_historical_orders, parse_hl_order_status, recent_orders, HistoricalOrder, HlOrderStatus};
+use super::farm::{parse_hl_funding_payments, FundingPayment};
+use super::history::{parse_hl_fills, HL_FILL_PAGE};
+use crate::aggregator::exchange_id::ExchangeId;
 use ethers::signers::Signer;
+use ethers::types::Address;
 use super::wallet::WalletManager;
+use super::hl_account::HlAccountState;
+use super::dead_mans_switch::ScheduleCancel;
+use crate::aggregator::symbol::Symbol;
+use crate::hyperliquid::{AssetMeta, MetaCache};
+use crate::hyperliquid::actions::{send_l1_action, ScheduleCancelAction};
+use crate::hyperliquid::meta::info_url;
+
+// How far past the trigger a triggered market order may fill, as a fraction
+const TRIGGER_SLIPPAGE: f64 = 0.05;
+
+/// Worst price a market order accepts: `bps` above `best` for a buy, below
+/// it for a sell
+pub fn slippage_limit(best: f64, is_buy: bool, bps: u32) -> f64 {
+    let offset = bps as f64 / 10_000.0;
+    if is_buy { best * (1.0 + offset) } else { best * (1.0 - offset) }
+}
+
+/// Hyperliquid's name for a limit order's time in force; it has no
+/// fill-or-kill. Post-only is "Alo", add liquidity only.
+pub fn hl_time_in_force(time_in_force: TimeInForce) -> Result<&'static str> {
+    match time_in_force {
+        TimeInForce::Gtc => Ok("Gtc"),
+        TimeInForce::Ioc => Ok("Ioc"),
+        TimeInForce::PostOnly => Ok("Alo"),
+        TimeInForce::Fok => Err(AggregatorError::ExchangeError("Hyperliquid does not support fill-or-kill orders".to_string())),
+    }
+}
+
+/// A client order id as Hyperliquid writes it: 0x and 32 hex digits
+pub fn cloid_hex(cloid: Uuid) -> String {
+    format!("0x{}", cloid.simple())
+}
 
 pub struct HyperliquidService {
     info_client: InfoClient,
     exchange_client: ExchangeClient,
+    meta: Arc<MetaCache>,
+    testnet: bool,
+    // For the actions the SDK doesn't wrap
+    http: reqwest::Client,
 }
 
 impl HyperliquidService {
-    pub async fn new(wallet_manager: &WalletManager) -> Result<Self> {
+    /// Trade with the wallet's ETH key on mainnet, or on testnet
+    pub async fn new(wallet_manager: &WalletManager, testnet: bool) -> Result<Self> {
         let wallet = wallet_manager.get_wallet()
-            .ok_or_else(|| anyhow::anyhow!("No wallet configured"))?;
+            .ok_or_else(|| AggregatorError::WalletNotConfigured("ETH".to_string()))?;
+        let base_url = if testnet { BaseUrl::Testnet } else { BaseUrl::Mainnet };
 
         let exchange_client = ExchangeClient::new(
             None,
             wallet.clone(),
-            Some(BaseUrl::Mainnet),
+            Some(base_url),
             None,
             None
         ).await?;
 
-        let info_client = InfoClient::new(None, Some(BaseUrl::Mainnet)).await?;
+        let info_client = InfoClient::new(None, Some(base_url)).await?;
 
         Ok(Self {
             info_client,
             exchange_client,
+            meta: MetaCache::hyperliquid_on(testnet),
+            testnet,
+            http: reqwest::Client::new(),
         })
     }
 
-    pub async fn place_trade(&self, request: TradeRequest) -> Result<ExchangeResponseStatus> {
+    pub fn is_testnet(&self) -> bool {
+        self.testnet
+    }
+
+    /// Read asset metadata through a cache shared with the market-data side
+    pub fn with_meta(mut self, meta: Arc<MetaCache>) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    // A miss may be a listing newer than the cache, so refetch once
+    async fn asset_meta(&self, coin: &str) -> Result<AssetMeta> {
+        if let Some(asset) = self.meta.get(coin).await? {
+            return Ok(asset);
+        }
+        self.meta.invalidate();
+        self.meta.get(coin).await?.ok_or_else(|| AggregatorError::AssetNotFound(coin.to_string()))
+    }
+
+    /// Place `request` under its client order id, generating one when unset;
+    /// returns the id with the venue's response
+    pub async fn place_trade(&self, request: TradeRequest) -> Result<(ExchangeResponseStatus, Uuid)> {
+        let coin = request.asset.to_hl_coin();
+        let cloid = request.cloid.unwrap_or_else(Uuid::new_v4);
+
         // Get current orderbook and metadata
-        let meta = self.info_client.meta().await?;
-        let orderbook = self.info_client.l2_snapshot(request.asset.clone()).await?;
-        
-        // Get best bid/ask prices from the orderbook
-        let (best_bid, best_ask) = {
-            let best_bid = orderbook.levels.get(0)
-                .and_then(|levels| levels.first())
-                .map(|level| level.px.parse::<f64>())
-                .transpose()
-                .map_err(|_| anyhow::anyhow!("Failed to parse bid price"))?
-                .ok_or_else(|| anyhow::anyhow!("No bid price available"))?;
-
-            let best_ask = orderbook.levels.get(1)
-                .and_then(|levels| levels.first())
-                .map(|level| level.px.parse::<f64>())
-                .transpose()
-                .map_err(|_| anyhow::anyhow!("Failed to parse ask price"))?
-                .ok_or_else(|| anyhow::anyhow!("No ask price available"))?;
-
-            (best_bid, best_ask)
-        };
+        let asset_meta = self.asset_meta(&coin).await?;
+        let (best_bid, best_ask) = self.best_bid_ask(&coin).await?;
 
         // Get current price based on order side
         let current_price = if request.is_buy { best_ask } else { best_bid };
 
-        // Get asset metadata to determine size decimals
-        let asset_meta = meta.universe
-            .iter()
-            .find(|asset| asset.name == request.asset)
-            .ok_or_else(|| anyhow::anyhow!("Asset metadata not found"))?;
-
         // Calculate size from USD value and round to appropriate decimals
-        let size = request.usd_value / current_price;
-        let size = (size * 10_f64.powi(asset_meta.sz_decimals as i32)).round() / 10_f64.powi(asset_meta.sz_decimals as i32);
-
-        // Ensure size is not zero after rounding
-        if size == 0.0 {
-            return Err(anyhow::anyhow!("Order size too small after rounding"));
-        }
+        let size = asset_meta.order_size(request.usd_value / current_price)?;
 
         // Set leverage if specified
         if request.leverage > 1 {
@@ -82,7 +129,7 @@ impl HyperliquidService {
                 self.exchange_client
                     .update_leverage(
                         request.leverage,
-                        &request.asset,
+                        &coin,
                         cross_margin,
                         None
                     )
@@ -92,44 +139,74 @@ impl HyperliquidService {
 
         match request.order_type {
             OrderType::Market => {
-                // For market orders, use the actual best price from the orderbook
-                let market_price = if request.is_buy {
-                    best_ask  // Use best ask for buys
-                } else {
-                    best_bid  // Use best bid for sells
+                // IOC at the touch, or as far past it as the request allows
+                let market_price = match request.max_slippage_bps {
+                    Some(bps) => asset_meta.order_price(slippage_limit(current_price, request.is_buy, bps))?,
+                    None => asset_meta.order_price(current_price)?,
                 };
 
                 let order = ClientOrderRequest {
-                    asset: request.asset,
+                    asset: coin,
                     is_buy: request.is_buy,
                     reduce_only: request.reduce_only,
                     limit_px: market_price,
                     sz: size,
-                    cloid: None,
+                    cloid: Some(cloid),
                     order_type: ClientOrder::Limit(ClientLimit {
                         tif: "Ioc".to_string(),
                     }),
                 };
 
-                Ok(self.exchange_client.order(order, None).await?)
+                Ok((self.exchange_client.order(order, None).await?, cloid))
             }
             
             OrderType::Limit => {
-                let price = request.price.expect("Limit orders require a price");
+                let price = request.price
+                    .ok_or_else(|| AggregatorError::InvalidAmount("Limit orders require a price".to_string()))?;
+                let price = asset_meta.order_price(price)?;
                 
                 let order = ClientOrderRequest {
-                    asset: request.asset,
+                    asset: coin,
                     is_buy: request.is_buy,
                     reduce_only: request.reduce_only,
                     limit_px: price,
                     sz: size,
-                    cloid: None,
+                    cloid: Some(cloid),
                     order_type: ClientOrder::Limit(ClientLimit {
-                        tif: "Gtc".to_string(), // Good-til-Cancelled for limit orders
+                        tif: hl_time_in_force(request.effective_time_in_force())?.to_string(),
+                    }),
+                };
+
+                Ok((self.exchange_client.order(order, None).await?, cloid))
+            }
+
+            OrderType::StopMarket { trigger_price } | OrderType::TakeProfit { trigger_price } => {
+                let tpsl = if matches!(request.order_type, OrderType::StopMarket { .. }) { "sl" } else { "tp" };
+                // Sized at the trigger, where it will fill
+                let size = asset_meta.order_size(request.usd_value / trigger_price)?;
+                let trigger_px = asset_meta.order_price(trigger_price)?;
+                // Worst fill accepted once triggered
+                let limit_px = if request.is_buy {
+                    trigger_price * (1.0 + TRIGGER_SLIPPAGE)
+                } else {
+                    trigger_price * (1.0 - TRIGGER_SLIPPAGE)
+                };
+
+                let order = ClientOrderRequest {
+                    asset: coin,
+                    is_buy: request.is_buy,
+                    reduce_only: request.reduce_only,
+                    limit_px: asset_meta.round_price(limit_px),
+                    sz: size,
+                    cloid: Some(cloid),
+                    order_type: ClientOrder::Trigger(ClientTrigger {
+                        is_market: true,
+                        trigger_px,
+                        tpsl: tpsl.to_string(),
                     }),
                 };
 
-                Ok(self.exchange_client.order(order, None).await?)
+                Ok((self.exchange_client.order(order, None).await?, cloid))
             }
         }
     }
@@ -147,6 +224,24 @@ impl HyperliquidService {
         Ok(positions)
     }
 
+    pub async fn get_account_state(&self) -> Result<HlAccountState> {
+        HlAccountState::fetch_from(info_url(self.testnet), self.exchange_client.wallet.address()).await
+    }
+
+    /// Withdraw `amount_usd` of USDC to `destination` on Arbitrum, checked
+    /// against the withdrawable balance first.
+    pub async fn withdraw(&self, amount_usd: f64, destination: Address) -> Result<ExchangeResponseStatus> {
+        self.get_account_state().await?.check_withdrawal(amount_usd)?;
+        Ok(self.exchange_client
+            .withdraw_from_bridge(&amount_usd.to_string(), &format!("{:#x}", destination), None)
+            .await?)
+    }
+
+    /// Collateral available for new positions (Hyperliquid's "withdrawable").
+    pub async fn get_free_collateral(&self) -> Result<f64> {
+        Ok(self.get_account_state().await?.withdrawable)
+    }
+
     pub async fn get_open_orders(&self) -> Result<Vec<OpenOrder>> {
         // Get user state using the wallet address
         let address = self.exchange_client.wallet.address();
@@ -170,6 +265,110 @@ impl HyperliquidService {
         Ok(orders)
     }
 
+    /// Hyperliquid's dead man's switch: cancel all open orders `after` from now
+    /// unless rescheduled first.
+    pub async fn schedule_cancel(&self, after: Duration) -> Result<()> {
+        let at = Utc::now().timestamp_millis() as u64 + after.as_millis() as u64;
+        self.send_schedule_cancel(Some(at)).await
+    }
+
+    pub async fn clear_scheduled_cancel(&self) -> Result<()> {
+        self.send_schedule_cancel(None).await
+    }
+
+    async fn send_schedule_cancel(&self, at: Option<u64>) -> Result<()> {
+        match send_l1_action(&self.http, &self.exchange_client.wallet, &ScheduleCancelAction::at(at), self.testnet).await? {
+            ExchangeResponseStatus::Ok(_) => Ok(()),
+            ExchangeResponseStatus::Err(message) => Err(AggregatorError::OrderRejected { reason: format!("Schedule cancel rejected: {}", message) }),
+        }
+    }
+
+    /// Recent fills, oldest first as the venue returns them. Queried
+    /// directly since the SDK's `user_fills` drops `fee` and `tid`.
+    pub async fn get_fills(&self) -> Result<Vec<Fill>> {
+        self.query_fills(serde_json::json!({
+            "type": "userFills",
+            "user": self.exchange_client.wallet.address(),
+        })).await
+    }
+
+    /// Every fill since `since_ms` the venue still holds, paging forward
+    /// through `userFillsByTime`. `progress` gets the running count after
+    /// each page. Pages overlap on their boundary timestamp, so callers
+    /// should dedupe.
+    pub async fn fills_since(&self, since_ms: i64, mut progress: impl FnMut(usize)) -> Result<Vec<Fill>> {
+        let mut fills = Vec::new();
+        let mut start = since_ms.max(0);
+        loop {
+            let page = self.query_fills(serde_json::json!({
+                "type": "userFillsByTime",
+                "user": self.exchange_client.wallet.address(),
+                "startTime": start,
+            })).await?;
+            let full = page.len() >= HL_FILL_PAGE;
+            let newest = page.iter().map(|fill| fill.time).max();
+            fills.extend(page);
+            progress(fills.len());
+            match newest {
+                // A page of fills all at `start` would otherwise repeat forever
+                Some(newest) if full && newest > start => start = newest,
+                _ => return Ok(fills),
+            }
+        }
+    }
+
+    async fn query_fills(&self, query: serde_json::Value) -> Result<Vec<Fill>> {
+        let body = reqwest::Client::new()
+            .post(info_url(self.testnet))
+            .json(&query)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_hl_fills(&body)
+    }
+
+    /// Funding paid and received since `since_ms`.
+    pub async fn funding_payments(&self, since_ms: i64) -> Result<Vec<FundingPayment>> {
+        let body = reqwest::Client::new()
+            .post(info_url(self.testnet))
+            .json(&serde_json::json!({
+                "type": "userFunding",
+                "user": self.exchange_client.wallet.address(),
+                "startTime": since_ms.max(0),
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_hl_funding_payments(&body)
+    }
+
+    /// The `limit` most recent terminal orders with their outcomes, newest
+    /// first. The SDK has no wrapper for this query.
+    pub async fn historical_orders(&self, limit: usize) -> Result<Vec<HistoricalOrder>> {
+        let body = reqwest::Client::new()
+            .post(info_url(self.testnet))
+            .json(&serde_json::json!({
+                "type": "historicalOrders",
+                "user": self.exchange_client.wallet.address(),
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(recent_orders(parse_hl_historical_orders(&body)?, limit))
+    }
+
+    /// Ids of orders with recent fills, as strings to match `Order.order_id`
+    pub async fn get_recent_fill_order_ids(&self) -> Result<HashSet<String>> {
+        let fills = self.info_client.user_fills(self.exchange_client.wallet.address()).await?;
+        Ok(fills.into_iter().map(|fill| fill.oid.to_string()).collect())
+    }
+
     pub async fn cancel_order(&self, order_id: u64, asset: String) -> Result<ExchangeResponseStatus> {
         let cancel_request = ClientCancelRequest {
             asset,
@@ -179,46 +378,104 @@ impl HyperliquidService {
         Ok(self.exchange_client.cancel(cancel_request, None).await?)
     }
 
-    pub async fn close_position(&self, asset: String, size: f64) -> Result<ExchangeResponseStatus> {
-        // Create market order in opposite direction to close position
-        let close_request = TradeRequest {
-            asset: asset.clone(),
-            is_buy: size < 0.0,
-            usd_value: size.abs() * self.get_current_price(&asset).await?,
+    pub async fn cancel_by_cloid(&self, cloid: Uuid, asset: String) -> Result<ExchangeResponseStatus> {
+        Ok(self.exchange_client.cancel_by_cloid(ClientCancelRequestCloid { asset, cloid }, None).await?)
+    }
+
+    /// Where the order placed under `cloid` stands; None when the venue never
+    /// saw it, so a retry after a timeout can tell whether the first attempt
+    /// landed. The SDK has no wrapper for this query.
+    pub async fn get_order_by_cloid(&self, cloid: Uuid) -> Result<Option<HlOrderStatus>> {
+        self.query_order_status(serde_json::json!(cloid_hex(cloid))).await
+    }
+
+    pub async fn get_order_status(&self, oid: u64) -> Result<Option<HlOrderStatus>> {
+        self.query_order_status(serde_json::json!(oid)).await
+    }
+
+    /// Poll order `oid` until it leaves the book or `timeout` passes, then
+    /// report what it filled. Still live at the timeout is no error: the
+    /// report carries any partial fill with a non-terminal status.
+    pub async fn wait_for_fill(&self, oid: u64, timeout: Duration) -> Result<FillReport> {
+        let status = poll_until_terminal(timeout, FILL_POLL_INTERVAL, move || async move {
+            Ok::<_, AggregatorError>(self.get_order_status(oid).await?.and_then(|status| status.outcome))
+        }).await?;
+        Ok(FillReport::from_fills(&self.get_fills().await?, &oid.to_string(), status))
+    }
+
+    // `oid` is the venue's order id or a client order id
+    async fn query_order_status(&self, oid: serde_json::Value) -> Result<Option<HlOrderStatus>> {
+        let body = reqwest::Client::new()
+            .post(info_url(self.testnet))
+            .json(&serde_json::json!({
+                "type": "orderStatus",
+                "user": self.exchange_client.wallet.address(),
+                "oid": oid,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_hl_order_status(&body)
+    }
+
+    /// Close `size` of the position in `asset`, signed like the position:
+    /// positive sells a long down, negative buys a short back. Sent as a
+    /// reduce-only IOC at the touch, sized in coins rather than through a
+    /// USD value, so it can't close more or less than asked.
+    pub async fn close_position(&self, asset: &Symbol, size: f64) -> Result<ExchangeResponseStatus> {
+        let coin = asset.to_hl_coin();
+        let asset_meta = self.asset_meta(&coin).await?;
+        let (best_bid, best_ask) = self.best_bid_ask(&coin).await?;
+        let is_buy = size < 0.0;
+        let touch = if is_buy { best_ask } else { best_bid };
+
+        let order = ClientOrderRequest {
+            asset: coin,
+            is_buy,
             reduce_only: true,
-            order_type: OrderType::Market,
-            leverage: 1,
-            cross_margin: Some(true),
-            price: None
+            limit_px: asset_meta.order_price(touch)?,
+            sz: asset_meta.order_size(size.abs())?,
+            cloid: Some(Uuid::new_v4()),
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: "Ioc".to_string(),
+            }),
         };
 
-        self.place_trade(close_request).await
+        Ok(self.exchange_client.order(order, None).await?)
     }
 
-    async fn get_current_price(&self, asset: &str) -> Result<f64> {
-        let orderbook = self.info_client.l2_snapshot(asset.to_string()).await?;
-        
-        // Get best bid/ask prices from the orderbook
-        let (best_bid, best_ask) = {
-            let best_bid = orderbook.levels.get(0)
-                .and_then(|levels| levels.first())
-                .map(|level| level.px.parse::<f64>())
-                .transpose()
-                .map_err(|_| anyhow::anyhow!("Failed to parse bid price"))?
-                .ok_or_else(|| anyhow::anyhow!("No bid price available"))?;
-
-            let best_ask = orderbook.levels.get(1)
-                .and_then(|levels| levels.first())
-                .map(|level| level.px.parse::<f64>())
-                .transpose()
-                .map_err(|_| anyhow::anyhow!("Failed to parse ask price"))?
-                .ok_or_else(|| anyhow::anyhow!("No ask price available"))?;
-
-            (best_bid, best_ask)
-        };
+    // Top of the book, bids first
+    async fn best_bid_ask(&self, coin: &str) -> Result<(f64, f64)> {
+        let orderbook = self.info_client.l2_snapshot(coin.to_string()).await?;
+
+        let best_bid = orderbook.levels.first()
+            .and_then(|levels| levels.first())
+            .map(|level| level.px.parse::<f64>())
+            .transpose()
+            .map_err(|_| AggregatorError::ApiError("Failed to parse bid price".to_string()))?
+            .ok_or_else(|| AggregatorError::ApiError("No bid price available".to_string()))?;
+
+        let best_ask = orderbook.levels.get(1)
+            .and_then(|levels| levels.first())
+            .map(|level| level.px.parse::<f64>())
+            .transpose()
+            .map_err(|_| AggregatorError::ApiError("Failed to parse ask price".to_string()))?
+            .ok_or_else(|| AggregatorError::ApiError("No ask price available".to_string()))?;
+
+        Ok((best_bid, best_ask))
+    }
+}
+
+#[async_trait]
+impl ScheduleCancel for HyperliquidService {
+    async fn schedule_cancel(&self, after: Duration) -> Result<()> {
+        Ok(HyperliquidService::schedule_cancel(self, after).await?)
+    }
 
-        // Return mid price
-        Ok((best_bid + best_ask) / 2.0)
+    async fn clear_scheduled_cancel(&self) -> Result<()> {
+        Ok(HyperliquidService::clear_scheduled_cancel(self).await?)
     }
 }
 
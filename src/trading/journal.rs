@@ -0,0 +1,253 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use chrono::Utc;
+use super::TradeRequest;
+use super::drawdown::DrawdownRecord;
+use super::farm::FarmEvent;
+use super::history::JournaledFill;
+use super::positions::Position;
+use super::file_lock::{AccessMode, FileLock};
+use crate::aggregator::symbol::Symbol;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::error::{AggregatorError, Result};
+
+/// Position and collateral on one venue for one asset at a point in time.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PositionSnapshot {
+    pub size: f64,
+    pub margin_used: Option<f64>,
+    pub free_collateral: Option<f64>,
+}
+
+impl PositionSnapshot {
+    pub fn capture(positions: &[Position], exchange: &ExchangeId, symbol: &Symbol, free_collateral: Option<f64>) -> Self {
+        let matching: Vec<&Position> = positions.iter()
+            .filter(|p| &p.exchange == exchange)
+            .filter(|p| p.symbol().map(|s| &s == symbol).unwrap_or(false))
+            .collect();
+
+        let margin_used = matching.iter()
+            .map(|p| p.margin_used)
+            .sum::<Option<f64>>()
+            .filter(|_| !matching.is_empty());
+
+        Self {
+            size: matching.iter().map(|p| p.size).sum(),
+            margin_used,
+            free_collateral,
+        }
+    }
+}
+
+/// Before/after view of what a trade did to the affected position.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradeSnapshot {
+    pub before: PositionSnapshot,
+    // None when the post-trade refresh failed
+    pub after: Option<PositionSnapshot>,
+}
+
+impl TradeSnapshot {
+    /// Human readable diff, e.g. "position 0.5000 → 0.7500 BTC, margin $210.00 → $315.00"
+    pub fn describe(&self, symbol: &Symbol) -> String {
+        let after = match &self.after {
+            Some(after) => after,
+            None => return format!(
+                "position {:.4} {} (post-trade refresh failed)",
+                self.before.size, symbol
+            ),
+        };
+
+        let mut parts = vec![format!("position {:.4} → {:.4} {}", self.before.size, after.size, symbol)];
+
+        if self.before.margin_used.is_some() || after.margin_used.is_some() {
+            parts.push(format!(
+                "margin {} → {}",
+                format_usd(self.before.margin_used),
+                format_usd(after.margin_used)
+            ));
+        }
+
+        if self.before.free_collateral.is_some() || after.free_collateral.is_some() {
+            parts.push(format!(
+                "free collateral {} → {}",
+                format_usd(self.before.free_collateral),
+                format_usd(after.free_collateral)
+            ));
+        }
+
+        parts.join(", ")
+    }
+}
+
+fn format_usd(value: Option<f64>) -> String {
+    value.map_or_else(|| "n/a".to_string(), |v| format!("${:.2}", v))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum JournalOutcome {
+    Accepted { response: String },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: i64,
+    pub exchange: ExchangeId,
+    pub request: TradeRequest,
+    pub outcome: JournalOutcome,
+    // Venue order id, which ties strategy legs back to open orders
+    #[serde(default)]
+    pub order_id: Option<String>,
+    pub snapshot: TradeSnapshot,
+}
+
+impl JournalEntry {
+    pub fn new(exchange: &ExchangeId, request: TradeRequest, result: &Result<(String, String), AggregatorError>, snapshot: TradeSnapshot) -> Self {
+        let outcome = match result {
+            Ok((response, id)) => JournalOutcome::Accepted {
+                response: format!("{} {}", response, id).trim().to_string(),
+            },
+            Err(e) => JournalOutcome::Failed { error: e.to_string() },
+        };
+
+        let order_id = result.as_ref().ok()
+            .map(|(_, id)| id.clone())
+            .filter(|id| !id.is_empty());
+
+        Self {
+            timestamp: Utc::now().timestamp_millis(),
+            exchange: exchange.clone(),
+            request,
+            outcome,
+            order_id,
+            snapshot,
+        }
+    }
+}
+
+// Farm events share the journal; the wrapper keeps them from parsing as trades
+#[derive(Debug, Serialize, Deserialize)]
+struct FarmLine {
+    farm: FarmEvent,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DrawdownLine {
+    drawdown: DrawdownRecord,
+}
+
+/// Append-only trade journal, one JSON entry per line.
+pub struct Journal {
+    path: PathBuf,
+    // Only a read-write journal holds the lock and may append
+    lock: Option<FileLock>,
+}
+
+impl Journal {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("trading_aggregator");
+        fs::create_dir_all(&config_dir)?;
+        Ok(config_dir.join("journal.jsonl"))
+    }
+
+    pub fn open(path: PathBuf) -> Result<Self> {
+        Self::open_with_mode(path, AccessMode::ReadWrite)
+    }
+
+    pub fn open_with_mode(path: PathBuf, mode: AccessMode) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lock = match mode {
+            AccessMode::ReadWrite => Some(FileLock::acquire(path.with_extension("lock"))?),
+            AccessMode::ReadOnly => None,
+        };
+        Ok(Self { path, lock })
+    }
+
+    pub fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        self.append_line(entry)
+    }
+
+    pub fn append_farm(&mut self, event: &FarmEvent) -> Result<()> {
+        self.append_line(&FarmLine { farm: event.clone() })
+    }
+
+    pub fn append_fill(&mut self, fill: &JournaledFill) -> Result<()> {
+        self.append_line(fill)
+    }
+
+    pub fn append_drawdown(&mut self, record: &DrawdownRecord) -> Result<()> {
+        self.append_line(&DrawdownLine { drawdown: record.clone() })
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    fn append_line(&mut self, line: &impl Serialize) -> Result<()> {
+        if self.lock.is_none() {
+            return Err(AggregatorError::ReadOnly("the journal can't be written".to_string()));
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(line)?)?;
+        Ok(())
+    }
+
+    /// Fsync the journal. Appends reopen the file, so there is no buffer to
+    /// flush, only the OS cache.
+    pub fn sync(&self) -> Result<()> {
+        if self.lock.is_none() || !self.path.exists() {
+            return Ok(());
+        }
+        OpenOptions::new().append(true).open(&self.path)?.sync_all()?;
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        self.read_lines()
+    }
+
+    /// Funding farm events, oldest first
+    pub fn farm_events(&self) -> Result<Vec<FarmEvent>> {
+        Ok(self.read_lines::<FarmLine>()?.into_iter().map(|line| line.farm).collect())
+    }
+
+    /// Venue fills imported by reconciliation, in the order they were added
+    pub fn fills(&self) -> Result<Vec<JournaledFill>> {
+        self.read_lines()
+    }
+
+    /// Drawdown guard triggers and the steps they ran, oldest first
+    pub fn drawdown_records(&self) -> Result<Vec<DrawdownRecord>> {
+        Ok(self.read_lines::<DrawdownLine>()?.into_iter().map(|line| line.drawdown).collect())
+    }
+
+    fn read_lines<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = fs::File::open(&self.path)?;
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Skip lines written by a newer/older schema rather than failing the whole read
+            if let Ok(entry) = serde_json::from_str(&line) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,212 @@
+use crate::error::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use super::hyperliquid_service::HyperliquidService;
+use super::orders::Order;
+use super::positions::Position;
+use super::trader::ExchangeTrader;
+use super::wallet::WalletManager;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+
+// Pause between attempts at the same close
+pub const CLOSE_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// One venue's side of the kill switch. Driven from the UI loop, so the
+/// futures needn't be `Send`.
+#[async_trait(?Send)]
+pub trait KillSwitchVenue {
+    fn exchange(&self) -> ExchangeId;
+    async fn open_orders(&mut self) -> Result<Vec<Order>>;
+    async fn cancel(&mut self, order: &Order) -> Result<()>;
+    async fn positions(&mut self) -> Result<Vec<Position>>;
+    /// Reduce-only market close of `size` (negative for shorts)
+    async fn close(&mut self, symbol: &Symbol, size: f64) -> Result<String>;
+}
+
+// Any venue the router trades on can be swept
+struct TraderKill<'a>(&'a mut dyn ExchangeTrader);
+
+#[async_trait(?Send)]
+impl KillSwitchVenue for TraderKill<'_> {
+    fn exchange(&self) -> ExchangeId {
+        self.0.exchange()
+    }
+
+    async fn open_orders(&mut self) -> Result<Vec<Order>> {
+        Ok(self.0.get_open_orders().await?)
+    }
+
+    async fn cancel(&mut self, order: &Order) -> Result<()> {
+        Ok(self.0.cancel_order(order).await?)
+    }
+
+    async fn positions(&mut self) -> Result<Vec<Position>> {
+        Ok(self.0.get_positions().await?)
+    }
+
+    async fn close(&mut self, symbol: &Symbol, size: f64) -> Result<String> {
+        Ok(self.0.close_position(symbol, size).await?)
+    }
+}
+
+/// How far a sweep goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KillScope {
+    CancelOrders,
+    // Close half of every position; orders are left alone
+    HalvePositions,
+    // Cancel every order, then close every position
+    Flatten,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KillAction {
+    Cancel { asset: String, order_id: String },
+    Close { asset: String, size: f64 },
+}
+
+impl std::fmt::Display for KillAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KillAction::Cancel { asset, order_id } => write!(f, "cancel {} order {}", asset, order_id),
+            KillAction::Close { asset, size } => write!(f, "close {} {}", asset, size),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KillStep {
+    pub action: KillAction,
+    pub attempts: u32,
+    // Venue response on success, the last error otherwise
+    pub result: Result<String, String>,
+}
+
+/// What the kill switch did on one venue
+#[derive(Debug, Clone, PartialEq)]
+pub struct VenueKill {
+    pub exchange: ExchangeId,
+    pub steps: Vec<KillStep>,
+    // Orders or positions that couldn't be listed, so weren't acted on
+    pub fetch_errors: Vec<String>,
+}
+
+impl VenueKill {
+    pub fn is_clean(&self) -> bool {
+        self.fetch_errors.is_empty() && self.steps.iter().all(|step| step.result.is_ok())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KillSwitchReport {
+    pub venues: Vec<VenueKill>,
+}
+
+impl KillSwitchReport {
+    pub fn is_clean(&self) -> bool {
+        self.venues.iter().all(VenueKill::is_clean)
+    }
+
+    /// One line per venue, then one per failure, e.g.
+    /// "Hyperliquid: 2 orders cancelled, 1 position closed"
+    pub fn describe(&self) -> String {
+        let mut lines = Vec::new();
+        for venue in &self.venues {
+            let done = |cancel: bool| venue.steps.iter()
+                .filter(|step| step.result.is_ok() && matches!(step.action, KillAction::Cancel { .. }) == cancel)
+                .count();
+            lines.push(format!("{}: {} orders cancelled, {} positions closed", venue.exchange, done(true), done(false)));
+            for error in &venue.fetch_errors {
+                lines.push(format!("  FAILED {}", error));
+            }
+            for step in &venue.steps {
+                if let Err(e) = &step.result {
+                    lines.push(format!("  FAILED {} after {} attempt(s): {}", step.action, step.attempts, e));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Cancel every open order on `venue`, then close every open position, as
+/// far as `scope` goes. Each close is tried up to `attempts` times
+/// `retry_delay` apart. Carries on past failures; positions are listed after
+/// the cancels so fills they race with are closed too.
+pub async fn sweep(venue: &mut impl KillSwitchVenue, scope: KillScope, attempts: u32, retry_delay: Duration) -> VenueKill {
+    let mut kill = VenueKill { exchange: venue.exchange(), steps: Vec::new(), fetch_errors: Vec::new() };
+
+    if scope != KillScope::HalvePositions {
+        cancel_all(venue, &mut kill).await;
+    }
+    if scope != KillScope::CancelOrders {
+        let fraction = if scope == KillScope::HalvePositions { 0.5 } else { 1.0 };
+        close_all(venue, fraction, attempts, retry_delay, &mut kill).await;
+    }
+    kill
+}
+
+async fn cancel_all(venue: &mut impl KillSwitchVenue, kill: &mut VenueKill) {
+    match venue.open_orders().await {
+        Ok(orders) => {
+            for order in orders {
+                let result = venue.cancel(&order).await.map(|_| "cancelled".to_string()).map_err(|e| e.to_string());
+                kill.steps.push(KillStep {
+                    action: KillAction::Cancel { asset: order.asset, order_id: order.order_id },
+                    attempts: 1,
+                    result,
+                });
+            }
+        }
+        Err(e) => kill.fetch_errors.push(format!("listing open orders: {}", e)),
+    }
+}
+
+// Close `fraction` of every open position
+async fn close_all(venue: &mut impl KillSwitchVenue, fraction: f64, attempts: u32, retry_delay: Duration, kill: &mut VenueKill) {
+    let positions = match venue.positions().await {
+        Ok(positions) => positions,
+        Err(e) => {
+            kill.fetch_errors.push(format!("listing positions: {}", e));
+            return;
+        }
+    };
+    for position in positions.into_iter().filter(|position| position.size != 0.0) {
+        let size = position.signed_size() * fraction;
+        let action = KillAction::Close { asset: position.asset.clone(), size };
+        let symbol = match position.symbol() {
+            Ok(symbol) => symbol,
+            Err(e) => {
+                kill.steps.push(KillStep { action, attempts: 0, result: Err(e.to_string()) });
+                continue;
+            }
+        };
+        let mut tries = 0;
+        let result = loop {
+            tries += 1;
+            match venue.close(&symbol, size).await {
+                Ok(response) => break Ok(response),
+                Err(e) if tries >= attempts.max(1) => break Err(e.to_string()),
+                Err(_) => tokio::time::sleep(retry_delay).await,
+            }
+        };
+        kill.steps.push(KillStep { action, attempts: tries, result });
+    }
+}
+
+/// The panic button: with `KillScope::Flatten`, cancel all open orders and
+/// close all positions on both venues at once. Refused outright in
+/// read-only mode.
+pub async fn kill_switch(hyperliquid: &mut HyperliquidService, wallet: &mut WalletManager, scope: KillScope, attempts: u32) -> Result<KillSwitchReport> {
+    wallet.ensure_writable()?;
+    let mut hl = TraderKill(hyperliquid);
+    let mut dydx = TraderKill(wallet);
+    let (hl, dydx) = tokio::join!(
+        sweep(&mut hl, scope, attempts, CLOSE_RETRY_DELAY),
+        sweep(&mut dydx, scope, attempts, CLOSE_RETRY_DELAY),
+    );
+    Ok(KillSwitchReport { venues: vec![hl, dydx] })
+}
@@ -0,0 +1,101 @@
+use crate::aggregator::book_quality::{spread_bps, BookQuality};
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::aggregator::types::OrderBook;
+use crate::error::{AggregatorError, OneSidedBook, SlippageExceeded, SpreadBlowout, StaleBook, VenueMidsDiverge};
+use super::{OrderType, TradeRequest};
+
+/// Limits a venue's book must be within before a market order is sent
+/// against it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketGatePolicy {
+    // Spread allowed, as a multiple of the market's recent average
+    pub spread_multiple: f64,
+    pub max_book_age_ms: i64,
+    // Gap allowed between two venues' mids, in bps
+    pub max_divergence_bps: f64,
+}
+
+impl Default for MarketGatePolicy {
+    fn default() -> Self {
+        Self { spread_multiple: 5.0, max_book_age_ms: 5_000, max_divergence_bps: 200.0 }
+    }
+}
+
+/// Judge the latest `exchange` book for `symbol` before a market order. A
+/// market with no sampled book passes, since there is nothing to judge;
+/// the other venues' books only count for divergence while fresh and
+/// two-sided.
+pub fn check_market_data(policy: &MarketGatePolicy, quality: &BookQuality, exchange: &ExchangeId, symbol: &Symbol, now_ms: i64) -> Result<(), AggregatorError> {
+    let market = symbol.to_string();
+    let Some(book) = quality.latest(exchange, &market) else { return Ok(()) };
+
+    let age_ms = now_ms - book.timestamp as i64;
+    if age_ms > policy.max_book_age_ms {
+        return Err(StaleBook { exchange: exchange.clone(), symbol: market, age_ms, limit_ms: policy.max_book_age_ms }.into());
+    }
+    let missing = match (book.bids.is_empty(), book.asks.is_empty()) {
+        (true, true) => Some("bids or asks"),
+        (true, false) => Some("bids"),
+        (false, true) => Some("asks"),
+        (false, false) => None,
+    };
+    if let Some(missing) = missing {
+        return Err(OneSidedBook { exchange: exchange.clone(), symbol: market, missing: missing.to_string() }.into());
+    }
+    if let (Some(spread), Some(average)) = (spread_bps(book), quality.average_spread_bps(exchange, &market)) {
+        if average > 0.0 && spread > average * policy.spread_multiple {
+            return Err(SpreadBlowout {
+                exchange: exchange.clone(),
+                symbol: market,
+                spread_bps: spread,
+                average_bps: average,
+                multiple: policy.spread_multiple,
+            }
+            .into());
+        }
+    }
+
+    let Some(reference) = book.mid_price() else { return Ok(()) };
+    for other in ExchangeId::built_in().into_iter().filter(|other| other != exchange) {
+        let Some(other_book) = quality.latest(&other, &market) else { continue };
+        if now_ms - other_book.timestamp as i64 > policy.max_book_age_ms {
+            continue;
+        }
+        let Some(other_mid) = other_book.mid_price() else { continue };
+        let divergence_bps = (reference - other_mid).abs() / other_mid * 10_000.0;
+        if divergence_bps > policy.max_divergence_bps {
+            return Err(VenueMidsDiverge {
+                exchange: exchange.clone(),
+                other,
+                symbol: market,
+                divergence_bps,
+                limit_bps: policy.max_divergence_bps,
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Refuse a market order its slippage tolerance can't cover: filling it from
+/// `book` would reach further past the touch than `max_slippage_bps`.
+/// Orders without a tolerance, and books with nothing on the taken side,
+/// pass.
+pub fn check_slippage(book: &OrderBook, request: &TradeRequest) -> Result<(), AggregatorError> {
+    let (OrderType::Market, Some(limit_bps)) = (&request.order_type, request.max_slippage_bps) else { return Ok(()) };
+    let touch = if request.is_buy { book.best_ask() } else { book.best_bid() };
+    let (Some(touch), Some(impact)) = (touch, book.market_impact(request.is_buy, request.usd_value)) else { return Ok(()) };
+    let past_touch = if request.is_buy { impact.worst_price - touch } else { touch - impact.worst_price };
+    let estimated_bps = past_touch / touch * 10_000.0;
+    if estimated_bps > limit_bps as f64 {
+        return Err(SlippageExceeded {
+            exchange: book.exchange.clone(),
+            symbol: book.symbol.clone(),
+            estimated_bps,
+            limit_bps,
+        }
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use super::TradeRequest;
+use super::confirmation::{rounded_notional, Confirmation, Quote};
+use super::orders::Order;
+use super::router::TradingRouter;
+use super::strategy::StrategyLegs;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::error::{AggregatorError, Result};
+
+/// Where mirrored legs go. The router in the app; mock traders in tests.
+#[async_trait(?Send)]
+pub trait MirrorVenue {
+    /// Returns the venue order id
+    async fn place_leg(&mut self, exchange: &ExchangeId, request: TradeRequest, quote: Quote, confirmation: Confirmation, force: bool) -> Result<String>;
+    async fn cancel_leg(&mut self, order: &Order) -> Result<(), AggregatorError>;
+}
+
+#[async_trait(?Send)]
+impl MirrorVenue for TradingRouter {
+    async fn place_leg(&mut self, exchange: &ExchangeId, request: TradeRequest, quote: Quote, confirmation: Confirmation, force: bool) -> Result<String> {
+        let (message, order_id) = self.place_trade(exchange, request, quote, confirmation, force).await.result?;
+        if order_id.is_empty() {
+            return Err(AggregatorError::OrderRejected { reason: message });
+        }
+        Ok(order_id)
+    }
+
+    async fn cancel_leg(&mut self, order: &Order) -> Result<(), AggregatorError> {
+        self.cancel_order(order).await
+    }
+}
+
+/// One venue's side of a mirrored order
+#[derive(Debug, Clone)]
+pub struct MirrorLeg {
+    pub exchange: ExchangeId,
+    // The venue's price and size step; each venue rounds the size its own way
+    pub quote: Quote,
+    pub confirmation: Confirmation,
+    // Venue order id once placed, the error otherwise
+    pub result: Result<String, String>,
+}
+
+impl MirrorLeg {
+    /// Notional once rounded to this venue's size step
+    pub fn notional(&self, request: &TradeRequest) -> f64 {
+        rounded_notional(request.usd_value, &self.quote)
+    }
+
+    /// e.g. "Hyperliquid: BUY 0.0153 BTC ≈ $1000.12 @ 65367.00"
+    pub fn preview(&self, request: &TradeRequest) -> String {
+        let notional = self.notional(request);
+        let side = if request.is_buy { "BUY" } else { "SELL" };
+        if self.quote.price > 0.0 {
+            format!("{}: {} {:.6} {} ≈ ${:.2} @ {:.2}", self.exchange, side, notional / self.quote.price, request.asset, notional, self.quote.price)
+        } else {
+            format!("{}: {} ${:.2} of {}", self.exchange, side, notional, request.asset)
+        }
+    }
+}
+
+/// The same order on several venues, tied together by a shared strategy id
+#[derive(Debug, Clone)]
+pub struct MirroredOrder {
+    pub group_id: Uuid,
+    pub request: TradeRequest,
+    pub legs: Vec<MirrorLeg>,
+}
+
+impl MirroredOrder {
+    /// Legs in placement order, the venue traded from first. Nothing is
+    /// sent until `place`.
+    pub fn new(request: TradeRequest, legs: Vec<(ExchangeId, Quote, Confirmation)>) -> Self {
+        let group_id = request.strategy_id.unwrap_or_else(Uuid::new_v4);
+        Self {
+            group_id,
+            request: request.with_strategy(group_id),
+            legs: legs.into_iter()
+                .map(|(exchange, quote, confirmation)| MirrorLeg {
+                    exchange,
+                    quote,
+                    confirmation,
+                    result: Err("not sent".to_string()),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &MirrorLeg> {
+        self.legs.iter().filter(|leg| leg.result.is_err())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.failed().next().is_none()
+    }
+
+    /// Send every leg, one after the other, carrying on past a failure.
+    pub async fn place(&mut self, venue: &mut impl MirrorVenue, force: bool) {
+        for index in 0..self.legs.len() {
+            self.place_leg(venue, index, force).await;
+        }
+    }
+
+    /// Resend only the legs that failed. Forced: a leg that timed out may
+    /// still be recorded as a likely duplicate.
+    pub async fn retry_failed(&mut self, venue: &mut impl MirrorVenue) {
+        for index in 0..self.legs.len() {
+            if self.legs[index].result.is_err() {
+                self.place_leg(venue, index, true).await;
+            }
+        }
+    }
+
+    async fn place_leg(&mut self, venue: &mut impl MirrorVenue, index: usize, force: bool) {
+        let leg = &self.legs[index];
+        let result = venue.place_leg(&leg.exchange, self.request.clone(), leg.quote, leg.confirmation.clone(), force).await;
+        self.legs[index].result = result.map_err(|e| e.to_string());
+    }
+
+    /// One line per leg
+    pub fn describe(&self) -> String {
+        self.legs.iter()
+            .map(|leg| match &leg.result {
+                Ok(order_id) => format!("{} placed ({})", leg.preview(&self.request), order_id),
+                Err(e) => format!("{} FAILED: {}", leg.preview(&self.request), e),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Open orders on other venues sharing `order`'s strategy id: the twins of
+/// a mirrored leg. Strategies that stay on one venue have none.
+pub fn twins<'a>(order: &Order, legs: &StrategyLegs, open: &'a [Order]) -> Vec<&'a Order> {
+    let Some(leg) = legs.leg(&order.exchange, &order.order_id) else { return Vec::new() };
+    open.iter()
+        .filter(|other| other.exchange != order.exchange)
+        .filter(|other| legs.leg(&other.exchange, &other.order_id).is_some_and(|twin| twin.strategy_id == leg.strategy_id))
+        .collect()
+}
+
+/// Cancel `order`, then its twins even if that failed. Returns the orders
+/// that could not be cancelled.
+pub async fn cancel_linked(venue: &mut impl MirrorVenue, order: &Order, twins: &[Order]) -> Vec<(Order, AggregatorError)> {
+    let mut failed = Vec::new();
+    for target in std::iter::once(order).chain(twins) {
+        if let Err(e) = venue.cancel_leg(target).await {
+            failed.push((target.clone(), e));
+        }
+    }
+    failed
+}
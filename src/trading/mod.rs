@@ -1,20 +1,91 @@
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::aggregator::symbol::Symbol;
+use crate::error::{AggregatorError, TriggerThroughMark};
 
 pub mod hyperliquid_service;
 pub mod dydx_service;
 pub mod positions;
 pub mod wallet;
+pub mod wallet_overview;
+pub mod wallet_store;
 pub mod orders;
+pub mod hl_account;
+pub mod journal;
+pub mod farm;
+pub mod pnl;
+pub mod router;
+pub mod confirmation;
+pub mod amount;
+pub mod delisting;
+pub mod reconcile;
+pub mod dead_mans_switch;
+pub mod strategy;
+pub mod automation;
+pub mod trailing;
+pub mod file_lock;
+pub mod best_execution;
+pub mod duplicates;
+pub mod history;
+pub mod twap;
+pub mod market_gate;
+pub mod kill_switch;
+pub mod drawdown;
+pub mod mirror;
+pub mod trader;
+pub mod bridge;
+pub mod dydx_config;
+pub mod validation;
+pub mod fill_report;
+pub mod portfolio;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OrderType {
     Market,
     Limit,
+    // Market order once the price moves through the trigger against the
+    // order's side: up for a buy, down for a sell
+    StopMarket { trigger_price: f64 },
+    // Market order once the price moves through the trigger in its favour
+    TakeProfit { trigger_price: f64 },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl OrderType {
+    pub fn trigger_price(&self) -> Option<f64> {
+        match self {
+            OrderType::StopMarket { trigger_price } | OrderType::TakeProfit { trigger_price } => Some(*trigger_price),
+            OrderType::Market | OrderType::Limit => None,
+        }
+    }
+}
+
+/// How long an order may work before the venue drops it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeInForce {
+    // Good til cancelled, or til the order's good-til time
+    Gtc,
+    // Immediate or cancel: fills what it can at once
+    Ioc,
+    // Fill or kill: fills in full at once or not at all
+    Fok,
+    // Rests on the book, refused rather than taking liquidity
+    PostOnly,
+}
+
+impl std::fmt::Display for TimeInForce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gtc => write!(f, "GTC"),
+            Self::Ioc => write!(f, "IOC"),
+            Self::Fok => write!(f, "FOK"),
+            Self::PostOnly => write!(f, "post-only"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeRequest {
-    pub asset: String,
+    pub asset: Symbol,
     pub is_buy: bool,
     pub order_type: OrderType,
     pub usd_value: f64,
@@ -22,6 +93,74 @@ pub struct TradeRequest {
     pub leverage: u32,
     pub cross_margin: Option<bool>,
     pub reduce_only: bool,
+    // Set when the order is one leg of a ladder, TWAP or bracket
+    #[serde(default)]
+    pub strategy_id: Option<Uuid>,
+    // Market orders only: worst fill accepted, in bps from the touch. None
+    // keeps each venue's default.
+    #[serde(default)]
+    pub max_slippage_bps: Option<u32>,
+    // Limit orders only: refused by the venue rather than taking liquidity
+    #[serde(default)]
+    pub post_only: bool,
+    // None picks by order type: GTC for limits, IOC otherwise
+    #[serde(default)]
+    pub time_in_force: Option<TimeInForce>,
+    // How long a resting dYdX limit order lives; 28 days when unset
+    #[serde(default)]
+    pub good_til_secs: Option<u64>,
+    // Hyperliquid client order id, so a retry can look the order up first;
+    // the router sets its submission id, and one is generated when unset
+    #[serde(default)]
+    pub cloid: Option<Uuid>,
+}
+
+impl TradeRequest {
+    /// The time in force the order goes out with: as set, post-only when
+    /// flagged, else GTC for limits and IOC for market and trigger orders
+    pub fn effective_time_in_force(&self) -> TimeInForce {
+        match (self.time_in_force, &self.order_type) {
+            (Some(time_in_force), _) => time_in_force,
+            (None, _) if self.post_only => TimeInForce::PostOnly,
+            (None, OrderType::Limit) => TimeInForce::Gtc,
+            (None, _) => TimeInForce::Ioc,
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy_id: Uuid) -> Self {
+        self.strategy_id = Some(strategy_id);
+        self
+    }
+
+    /// Refuse a trigger already through `mark`, which would fire on
+    /// placement: a stop-buy must sit above the mark and a stop-sell below
+    /// it, take-profits the other way round. A mark of zero isn't known and
+    /// passes.
+    pub fn validate_trigger(&self, mark: f64) -> Result<(), AggregatorError> {
+        let Some(trigger) = self.order_type.trigger_price() else {
+            return Ok(());
+        };
+        if !(trigger > 0.0 && trigger.is_finite()) {
+            return Err(AggregatorError::InvalidAmount(format!("trigger price must be positive, got {}", trigger)));
+        }
+        if mark <= 0.0 {
+            return Ok(());
+        }
+        let stop = matches!(self.order_type, OrderType::StopMarket { .. });
+        // Stop-buys and take-profit sells trigger on a rise
+        let needs_above = stop == self.is_buy;
+        let fires_now = if needs_above { trigger <= mark } else { trigger >= mark };
+        if !fires_now {
+            return Ok(());
+        }
+        let reason = format!(
+            "a {} {} must trigger {} it",
+            if stop { "stop" } else { "take-profit" },
+            if self.is_buy { "buy" } else { "sell" },
+            if needs_above { "above" } else { "below" },
+        );
+        Err(TriggerThroughMark { trigger, mark, reason }.into())
+    }
 }
 
 // Initialize logging for the trading module
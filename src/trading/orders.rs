@@ -1,15 +1,22 @@
 use dydx::indexer::types::{OrderResponseObject, OrderSide, OrderStatus, ApiOrderStatus, OrderFlags};
 use crate::trading::hyperliquid_service::OpenOrder;
-use anyhow::Result;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::timefmt;
+use super::strategy::OrderRow;
+use crate::error::Result;
 use num_traits::ToPrimitive;
+use serde::Deserialize;
 use ratatui::{
+    text::Line,
     widgets::{Block, Borders, Paragraph},
     layout::{Layout, Constraint, Direction},
 };
+use crate::ui::theme::Styles;
 
 #[derive(Debug, Clone)]
 pub struct Order {
-    pub exchange: String,
+    pub exchange: ExchangeId,
     pub asset: String,
     pub size: f64,
     pub price: f64,
@@ -18,10 +25,233 @@ pub struct Order {
     pub order_id: String,
 }
 
+/// How an order left the book, normalized across venues.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderOutcome {
+    Filled,
+    Expired,
+    Rejected { reason: String },
+    CancelledByUser,
+    // Hyperliquid's scheduled cancel firing
+    CancelledByDms,
+    // Removed by the venue itself, e.g. for margin or self-trade
+    CancelledByVenue { reason: String },
+}
+
+impl OrderOutcome {
+    /// Terminal outcome for a Hyperliquid order status; None while live.
+    pub fn from_hl_status(status: &str) -> Option<Self> {
+        match status {
+            "open" | "triggered" | "pending" => None,
+            "filled" => Some(Self::Filled),
+            "canceled" => Some(Self::CancelledByUser),
+            "scheduledCancel" => Some(Self::CancelledByDms),
+            "rejected" => Some(Self::Rejected { reason: "rejected".to_string() }),
+            other => {
+                if let Some(reason) = other.strip_suffix("Rejected") {
+                    Some(Self::Rejected { reason: split_camel(reason) })
+                } else if let Some(reason) = other.strip_suffix("Canceled") {
+                    Some(Self::CancelledByVenue { reason: split_camel(reason) })
+                } else {
+                    Some(Self::CancelledByVenue { reason: other.to_string() })
+                }
+            }
+        }
+    }
+
+    /// Terminal outcome from a dYdX indexer status and `removalReason`;
+    /// None while the order is live.
+    pub fn from_dydx_status(status: &str, removal_reason: Option<&str>) -> Option<Self> {
+        let reason = removal_reason
+            .map(|reason| reason.trim_start_matches("ORDER_REMOVAL_REASON_"))
+            .unwrap_or_default();
+        match status {
+            "OPEN" | "UNTRIGGERED" | "BEST_EFFORT_OPENED" => None,
+            "FILLED" => Some(Self::Filled),
+            _ => Some(match reason {
+                "USER_CANCELED" | "" => Self::CancelledByUser,
+                "EXPIRED" | "INDEXER_EXPIRED" | "BLOCK_HEIGHT_EXCEEDS_GOOD_TIL_BLOCK" => Self::Expired,
+                "FULLY_FILLED" => Self::Filled,
+                "POST_ONLY_WOULD_CROSS_MAKER_ORDER"
+                | "IMMEDIATE_OR_CANCEL_WOULD_REST_ON_BOOK"
+                | "FOK_ORDER_COULD_NOT_BE_FULLY_FULFILLED"
+                | "SELF_TRADE_ERROR"
+                | "EQUITY_TIER"
+                | "VIOLATES_ISOLATED_SUBACCOUNT_CONSTRAINTS" => Self::Rejected { reason: reason.to_lowercase().replace('_', " ") },
+                other => Self::CancelledByVenue { reason: other.to_lowercase().replace('_', " ") },
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for OrderOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Filled => write!(f, "Filled"),
+            Self::Expired => write!(f, "Expired"),
+            Self::Rejected { reason } => write!(f, "Rejected: {}", reason),
+            Self::CancelledByUser => write!(f, "Cancelled by user"),
+            Self::CancelledByDms => write!(f, "Cancelled by dead man's switch"),
+            Self::CancelledByVenue { reason } => write!(f, "Cancelled by venue: {}", reason),
+        }
+    }
+}
+
+// "perpMargin" -> "perp margin"
+fn split_camel(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.chars() {
+        if c.is_uppercase() && !out.is_empty() {
+            out.push(' ');
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// An order that has left the book, with how it left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoricalOrder {
+    pub exchange: ExchangeId,
+    pub asset: String,
+    pub side: String,
+    pub price: f64,
+    pub size: f64,
+    pub order_id: String,
+    pub outcome: OrderOutcome,
+    // Millis timestamp of the final status
+    pub time: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HlHistoricalOrder {
+    order: HlOrderDetail,
+    status: String,
+    status_timestamp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HlOrderDetail {
+    coin: String,
+    side: String,
+    limit_px: String,
+    orig_sz: String,
+    oid: u64,
+}
+
+/// Terminal orders from Hyperliquid's `historicalOrders` info query.
+pub fn parse_hl_historical_orders(body: &str) -> Result<Vec<HistoricalOrder>> {
+    let orders: Vec<HlHistoricalOrder> = serde_json::from_str(body)?;
+    Ok(orders.into_iter()
+        .filter_map(|entry| Some(HistoricalOrder {
+            exchange: ExchangeId::Hyperliquid,
+            outcome: OrderOutcome::from_hl_status(&entry.status)?,
+            asset: entry.order.coin,
+            side: if entry.order.side == "B" { "Buy" } else { "Sell" }.to_string(),
+            price: entry.order.limit_px.parse().unwrap_or(0.0),
+            size: entry.order.orig_sz.parse().unwrap_or(0.0),
+            order_id: entry.order.oid.to_string(),
+            time: entry.status_timestamp,
+        }))
+        .collect())
+}
+
+/// Where one Hyperliquid order stands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlOrderStatus {
+    pub order_id: String,
+    pub asset: String,
+    // None while the order is live
+    pub outcome: Option<OrderOutcome>,
+    // Millis timestamp of the status
+    pub time: i64,
+}
+
+/// Hyperliquid's `orderStatus` info query; None for an order it doesn't know.
+pub fn parse_hl_order_status(body: &str) -> Result<Option<HlOrderStatus>> {
+    #[derive(Deserialize)]
+    struct Response {
+        status: String,
+        order: Option<HlHistoricalOrder>,
+    }
+
+    let response: Response = serde_json::from_str(body)?;
+    Ok(response.order
+        .filter(|_| response.status == "order")
+        .map(|entry| HlOrderStatus {
+            order_id: entry.order.oid.to_string(),
+            asset: entry.order.coin,
+            outcome: OrderOutcome::from_hl_status(&entry.status),
+            time: entry.status_timestamp,
+        }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DydxHistoricalOrder {
+    client_id: String,
+    ticker: String,
+    side: String,
+    price: String,
+    size: String,
+    status: String,
+    removal_reason: Option<String>,
+    updated_at: Option<String>,
+}
+
+/// Terminal orders from the dYdX indexer's orders listing.
+pub fn parse_dydx_historical_orders(body: &str) -> Result<Vec<HistoricalOrder>> {
+    let orders: Vec<DydxHistoricalOrder> = serde_json::from_str(body)?;
+    Ok(orders.into_iter()
+        .filter_map(|order| Some(HistoricalOrder {
+            exchange: ExchangeId::Dydx,
+            outcome: OrderOutcome::from_dydx_status(&order.status, order.removal_reason.as_deref())?,
+            side: if order.side == "BUY" { "Buy" } else { "Sell" }.to_string(),
+            price: order.price.parse().unwrap_or(0.0),
+            size: order.size.parse().unwrap_or(0.0),
+            time: order.updated_at.as_deref()
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+                .map_or(0, |at| at.timestamp_millis()),
+            asset: order.ticker,
+            order_id: order.client_id,
+        }))
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DydxOrderState {
+    client_id: String,
+    clob_pair_id: String,
+    order_flags: String,
+    subaccount_number: u32,
+    status: String,
+    removal_reason: Option<String>,
+}
+
+/// How the order with "client_id:clob_pair_id:order_flags:subaccount" id
+/// `order_id` left the book, from the dYdX orders listing; None while it's
+/// live or not listed yet.
+pub fn parse_dydx_order_outcome(body: &str, order_id: &str) -> Result<Option<OrderOutcome>> {
+    let orders: Vec<DydxOrderState> = serde_json::from_str(body)?;
+    Ok(orders.into_iter()
+        .find(|order| format!("{}:{}:{}:{}", order.client_id, order.clob_pair_id, order.order_flags, order.subaccount_number) == order_id)
+        .and_then(|order| OrderOutcome::from_dydx_status(&order.status, order.removal_reason.as_deref())))
+}
+
+/// The `limit` most recent terminal orders, newest first.
+pub fn recent_orders(mut orders: Vec<HistoricalOrder>, limit: usize) -> Vec<HistoricalOrder> {
+    orders.sort_by(|a, b| b.time.cmp(&a.time));
+    orders.truncate(limit);
+    orders
+}
+
 impl Order {
     pub fn from_dydx_order(order: &OrderResponseObject) -> Result<Self> {
         Ok(Order {
-            exchange: "dYdX".to_string(),
+            exchange: ExchangeId::Dydx,
             asset: order.ticker.0.clone(),
             size: order.size.0.to_f64().unwrap_or(0.0),
             price: order.price.0.to_f64().unwrap_or(0.0),
@@ -52,9 +282,23 @@ impl Order {
         })
     }
 
+    /// A dYdX short-term order, which expires off the book within a few
+    /// blocks and may be gone before a cancel lands
+    pub fn is_short_term(&self) -> bool {
+        self.exchange == ExchangeId::Dydx && self.order_id.split(':').nth(2) == Some("0")
+    }
+
+    /// Whether the order is on `symbol`, compared in the venue's own naming.
+    pub fn is_for(&self, symbol: &Symbol) -> bool {
+        match self.exchange {
+            ExchangeId::Dydx => self.asset == symbol.to_dydx_ticker(),
+            _ => self.asset == symbol.to_hl_coin(),
+        }
+    }
+
     pub fn from_hl_order(order: &OpenOrder) -> Result<Self> {
         Ok(Order {
-            exchange: "Hyperliquid".to_string(),
+            exchange: ExchangeId::Hyperliquid,
             asset: order.asset.clone(),
             size: order.size,
             price: order.price,
@@ -79,8 +323,8 @@ impl Order {
             .split(f.area());
 
         // Update the title to show exchange breakdown
-        let dydx_count = orders.iter().filter(|p| p.exchange == "dYdX").count();
-        let hl_count = orders.iter().filter(|p| p.exchange == "Hyperliquid").count();
+        let dydx_count = orders.iter().filter(|p| p.exchange == ExchangeId::Dydx).count();
+        let hl_count = orders.iter().filter(|p| p.exchange == ExchangeId::Hyperliquid).count();
         let title = format!("Open Orders (dYdX: {}, Hyperliquid: {})", dydx_count, hl_count);
         
         let title_widget = Paragraph::new(title)
@@ -121,9 +365,114 @@ impl Order {
         }
 
         // Menu
-        let menu = Paragraph::new("Press 'q' to return to main menu, type id to cancel/close")
+        let menu = Paragraph::new("Press 'q' to return to main menu")
             .block(Block::default().borders(Borders::ALL))
             .alignment(ratatui::layout::Alignment::Center);
         f.render_widget(menu, chunks[2]);
     }
-}
\ No newline at end of file
+
+    /// Orders view with strategy groups as collapsible header rows.
+    /// `selected` is highlighted; `status` is the last cancel's outcome.
+    pub fn display_order_rows(f: &mut ratatui::Frame, rows: &[OrderRow], selected: usize, status: Option<&str>, styles: &Styles) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),    // Title
+                Constraint::Min(0),       // Rows
+                Constraint::Length(4),    // Status and menu
+            ])
+            .split(f.area());
+
+        let (groups, orders): (Vec<&OrderRow>, Vec<&OrderRow>) = rows.iter().partition(|row| matches!(row, OrderRow::Group(_)));
+        let title = format!("Open Orders ({} strategies, {} orders shown)", groups.len(), orders.len());
+        let title_widget = Paragraph::new(title)
+            .block(Block::default().borders(Borders::ALL))
+            .alignment(ratatui::layout::Alignment::Center);
+        f.render_widget(title_widget, chunks[0]);
+
+        let row_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                rows.iter()
+                    .map(|row| match row {
+                        OrderRow::Group(_) => Constraint::Length(3),
+                        OrderRow::Order(_) => Constraint::Length(4),
+                    })
+                    .collect::<Vec<_>>()
+            )
+            .split(chunks[1]);
+
+        for (idx, row) in rows.iter().enumerate() {
+            let mut block = Block::default().borders(Borders::ALL);
+            let marker = if idx == selected {
+                block = block.border_style(styles.selected);
+                "> "
+            } else {
+                ""
+            };
+            let widget = match row {
+                OrderRow::Group(group) => Paragraph::new(format!("#{}: {}", idx + 1, group.header()))
+                    .block(block.title(format!("{}Strategy", marker))),
+                OrderRow::Order(order) => Paragraph::new(format!(
+                    "#{}: Size: {} {} | Value: ${:.2}\nPrice: ${:.2} | Side: {} | Status: {}",
+                    idx + 1,
+                    order.size,
+                    order.asset,
+                    order.size * order.price,
+                    order.price,
+                    order.side,
+                    order.status
+                ))
+                .block(block.title(format!("{}{} Order ({})", marker, order.asset, order.exchange))),
+            };
+            f.render_widget(widget, row_chunks[idx]);
+        }
+
+        let menu = Paragraph::new(vec![
+            Line::from(status.unwrap_or("").to_string()),
+            Line::from("Press 'q' to return, 'h' for recent orders, up/down to select, 'x' to cancel, Enter or its number to expand/cancel a strategy"),
+        ])
+            .block(Block::default().borders(Borders::ALL))
+            .alignment(ratatui::layout::Alignment::Center);
+        f.render_widget(menu, chunks[2]);
+    }
+
+    /// "Recent Orders" tab: terminal orders with how each one ended.
+    pub fn display_recent_orders(f: &mut ratatui::Frame, orders: &[HistoricalOrder]) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),    // Title
+                Constraint::Min(0),       // Orders
+                Constraint::Length(3),    // Menu
+            ])
+            .split(f.area());
+
+        let title_widget = Paragraph::new(format!("Recent Orders (last {})", orders.len()))
+            .block(Block::default().borders(Borders::ALL))
+            .alignment(ratatui::layout::Alignment::Center);
+        f.render_widget(title_widget, chunks[0]);
+
+        let mut text = format!("{:<20} {:<12} {:<12} {:<5} {:>12} {:>12}  Outcome\n", "Time", "Exchange", "Asset", "Side", "Size", "Price");
+        for order in orders {
+            let time = timefmt::fmt_ts(order.time);
+            text.push_str(&format!(
+                "{:<20} {:<12} {:<12} {:<5} {:>12} {:>12.2}  {}\n",
+                time, order.exchange, order.asset, order.side, order.size, order.price, order.outcome
+            ));
+        }
+        if orders.is_empty() {
+            text.push_str("\nNo recent orders");
+        }
+        let list = Paragraph::new(text)
+            .block(Block::default().borders(Borders::ALL).title("Order History"));
+        f.render_widget(list, chunks[1]);
+
+        let menu = Paragraph::new("Press 'h' for open orders, 'r' to refresh, 'q' to return")
+            .block(Block::default().borders(Borders::ALL))
+            .alignment(ratatui::layout::Alignment::Center);
+        f.render_widget(menu, chunks[2]);
+    }
+}
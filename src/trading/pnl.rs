@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use uuid::Uuid;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use super::farm::FundingPayment;
+use super::positions::episodes::{fill_pnl, Fill};
+use super::strategy::{short_id, StrategyLegs};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+// Fills and funding without a strategy tag
+const MANUAL: &str = "manual";
+
+/// Where a PnL amount came from. Fees are negative, so the three always
+/// add up to the net, matching `PositionEpisode::net_pnl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Price,
+    Funding,
+    Fees,
+}
+
+impl std::fmt::Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Price => write!(f, "price"),
+            Self::Funding => write!(f, "funding"),
+            Self::Fees => write!(f, "fees"),
+        }
+    }
+}
+
+/// One attributable amount: a fill's realized price PnL or fee, or a
+/// funding payment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PnlEntry {
+    pub exchange: ExchangeId,
+    // Base asset, the same across venues
+    pub asset: String,
+    pub strategy: Option<Uuid>,
+    pub category: Category,
+    pub time: i64,
+    // USD, signed from the account's side
+    pub amount: f64,
+}
+
+fn base_asset(asset: &str) -> String {
+    Symbol::parse_user_input(asset).map(|symbol| symbol.base().to_string()).unwrap_or_else(|_| asset.to_string())
+}
+
+/// Entries from the fill history and funding payments. Price PnL is on the
+/// same average-cost basis as the closed-trades view; funding carries no
+/// strategy tag since venues settle it per position.
+pub fn pnl_entries(fills: &[Fill], payments: &[FundingPayment], legs: &StrategyLegs) -> Vec<PnlEntry> {
+    let mut entries = Vec::new();
+    for realized in fill_pnl(fills) {
+        let fill = &realized.fill;
+        let strategy = legs.leg(&fill.exchange, &fill.order_id).map(|leg| leg.strategy_id);
+        let entry = |category, amount| PnlEntry {
+            exchange: fill.exchange.clone(),
+            asset: base_asset(&fill.asset),
+            strategy,
+            category,
+            time: fill.time,
+            amount,
+        };
+        if realized.realized != 0.0 {
+            entries.push(entry(Category::Price, realized.realized));
+        }
+        if fill.fee != 0.0 {
+            entries.push(entry(Category::Fees, -fill.fee));
+        }
+    }
+    entries.extend(payments.iter().map(|payment| PnlEntry {
+        exchange: payment.exchange.clone(),
+        asset: base_asset(&payment.asset),
+        strategy: None,
+        category: Category::Funding,
+        time: payment.time,
+        amount: payment.amount,
+    }));
+    entries
+}
+
+/// Millis window an attribution covers, start inclusive, end exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PnlRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl PnlRange {
+    /// "7d", "30d", "24h", "mtd" (since the start of this month, UTC) or
+    /// "all", ending at `now_ms`.
+    pub fn parse(input: &str, now_ms: i64) -> Result<Self, String> {
+        let input = input.trim().to_lowercase();
+        let start = match input.as_str() {
+            "all" => i64::MIN,
+            "mtd" => {
+                use chrono::{Datelike, TimeZone, Utc};
+                let now = Utc.timestamp_millis_opt(now_ms).single().ok_or_else(|| "time out of range".to_string())?;
+                Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0).single()
+                    .map(|start| start.timestamp_millis())
+                    .ok_or_else(|| "time out of range".to_string())?
+            }
+            other => {
+                let (count, unit_ms) = if let Some(days) = other.strip_suffix('d') {
+                    (days, DAY_MS)
+                } else if let Some(hours) = other.strip_suffix('h') {
+                    (hours, DAY_MS / 24)
+                } else {
+                    return Err(format!("Unknown range '{}'; expected e.g. 7d, 24h, mtd or all", other));
+                };
+                let count: i64 = count.parse().map_err(|_| format!("Unknown range '{}'; expected e.g. 7d, 24h, mtd or all", other))?;
+                if count <= 0 {
+                    return Err(format!("Range '{}' must be positive", other));
+                }
+                now_ms - count * unit_ms
+            }
+        };
+        Ok(Self { start, end: now_ms.saturating_add(1) })
+    }
+
+    pub fn contains(&self, time: i64) -> bool {
+        time >= self.start && time < self.end
+    }
+}
+
+/// What attribution groups by at each level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    Exchange,
+    Asset,
+    Strategy,
+    Category,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 4] = [GroupBy::Exchange, GroupBy::Asset, GroupBy::Strategy, GroupBy::Category];
+
+    fn key(&self, entry: &PnlEntry) -> String {
+        match self {
+            Self::Exchange => entry.exchange.to_string(),
+            Self::Asset => entry.asset.clone(),
+            Self::Strategy => entry.strategy.map(short_id).unwrap_or_else(|| MANUAL.to_string()),
+            Self::Category => entry.category.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exchange => write!(f, "exchange"),
+            Self::Asset => write!(f, "asset"),
+            Self::Strategy => write!(f, "strategy"),
+            Self::Category => write!(f, "category"),
+        }
+    }
+}
+
+impl std::str::FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "exchange" | "venue" => Ok(Self::Exchange),
+            "asset" | "symbol" => Ok(Self::Asset),
+            "strategy" | "tag" => Ok(Self::Strategy),
+            "category" => Ok(Self::Category),
+            other => Err(format!("Unknown grouping '{}'; expected exchange, asset, strategy or category", other)),
+        }
+    }
+}
+
+/// Comma-separated groupings, outermost first, e.g. "exchange,asset".
+pub fn parse_group_by(input: &str) -> Result<Vec<GroupBy>, String> {
+    let groups: Vec<GroupBy> = input.split(',').map(str::parse).collect::<Result<_, _>>()?;
+    for (i, group) in groups.iter().enumerate() {
+        if groups[..i].contains(group) {
+            return Err(format!("'{}' is grouped by twice", group));
+        }
+    }
+    Ok(groups)
+}
+
+/// Totals for one group, broken down by the next grouping level.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AttributionNode {
+    pub key: String,
+    pub price: f64,
+    pub funding: f64,
+    pub fees: f64,
+    pub children: Vec<AttributionNode>,
+}
+
+impl AttributionNode {
+    pub fn net(&self) -> f64 {
+        self.price + self.funding + self.fees
+    }
+
+    /// The node reached by following child indices from this one
+    pub fn at(&self, path: &[usize]) -> Option<&Self> {
+        path.iter().try_fold(self, |node, &index| node.children.get(index))
+    }
+
+    /// Every descendant depth-first, with its depth below this node
+    pub fn rows(&self) -> Vec<(usize, &Self)> {
+        let mut rows = Vec::new();
+        for child in &self.children {
+            rows.push((0, child));
+            rows.extend(child.rows().into_iter().map(|(depth, node)| (depth + 1, node)));
+        }
+        rows
+    }
+
+    fn add(&mut self, entry: &PnlEntry) {
+        match entry.category {
+            Category::Price => self.price += entry.amount,
+            Category::Funding => self.funding += entry.amount,
+            Category::Fees => self.fees += entry.amount,
+        }
+    }
+
+    fn build(key: String, entries: &[&PnlEntry], group_by: &[GroupBy]) -> Self {
+        let mut node = Self { key, ..Self::default() };
+        for entry in entries {
+            node.add(entry);
+        }
+        if let Some((group, rest)) = group_by.split_first() {
+            let mut groups: BTreeMap<String, Vec<&PnlEntry>> = BTreeMap::new();
+            for entry in entries {
+                groups.entry(group.key(entry)).or_default().push(entry);
+            }
+            node.children = groups.into_iter()
+                .map(|(key, entries)| Self::build(key, &entries, rest))
+                .collect();
+            // Biggest contributors first; ties stay in key order
+            node.children.sort_by(|a, b| b.net().total_cmp(&a.net()));
+        }
+        node
+    }
+}
+
+/// "Where did the PnL come from" over a range: the total at the root, one
+/// level of children per grouping.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttributionReport {
+    pub range: PnlRange,
+    pub group_by: Vec<GroupBy>,
+    pub total: AttributionNode,
+}
+
+pub fn attribution(entries: &[PnlEntry], range: PnlRange, group_by: &[GroupBy]) -> AttributionReport {
+    let in_range: Vec<&PnlEntry> = entries.iter().filter(|entry| range.contains(entry.time)).collect();
+    AttributionReport {
+        range,
+        group_by: group_by.to_vec(),
+        total: AttributionNode::build("total".to_string(), &in_range, group_by),
+    }
+}
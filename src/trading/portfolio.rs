@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::aggregator::DerivativesAggregator;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use super::hyperliquid_service::HyperliquidService;
+use super::positions::{live_pnl, Position};
+use super::wallet::WalletManager;
+use super::wallet_overview::{Availability, WalletOverview};
+
+/// Signed USD exposure to one asset, netted across venues
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetExposure {
+    pub symbol: Symbol,
+    // Positive net long, negative net short
+    pub net_usd: f64,
+    // Some leg had no mark and counted at its entry price
+    pub at_entry: bool,
+}
+
+/// Account health across both venues and the Arbitrum wallet. Each source
+/// keeps its own availability, so totals say which parts they're missing
+/// instead of counting them as zero.
+#[derive(Debug, Clone)]
+pub struct PortfolioSummary {
+    pub wallets: WalletOverview,
+    pub hl_positions: Availability<Vec<Position>>,
+    pub dydx_positions: Availability<Vec<Position>>,
+    // Latest price per (venue, market) with an open position
+    pub marks: HashMap<(ExchangeId, Symbol), f64>,
+}
+
+impl Default for PortfolioSummary {
+    fn default() -> Self {
+        Self {
+            wallets: WalletOverview::default(),
+            hl_positions: Availability::Pending,
+            dydx_positions: Availability::Pending,
+            marks: HashMap::new(),
+        }
+    }
+}
+
+impl PortfolioSummary {
+    /// Fetch balances and positions concurrently, then the marks of every
+    /// market with a position
+    pub async fn compute(wallet: &WalletManager, hl: &HyperliquidService, aggregator: &DerivativesAggregator) -> Self {
+        let mut summary = Self::default();
+        summary.refresh(wallet, hl, aggregator).await;
+        summary
+    }
+
+    /// Refetch everything; a failed source keeps its last value, marked stale
+    pub async fn refresh(&mut self, wallet: &WalletManager, hl: &HyperliquidService, aggregator: &DerivativesAggregator) {
+        let (eth, hl_account, dydx_account, hl_positions, dydx_positions) = futures::join!(
+            wallet.eth_balances(),
+            wallet.hl_account(),
+            wallet.dydx_account(),
+            hl.get_positions(),
+            wallet.get_dydx_positions(),
+        );
+        self.wallets.eth.update(eth);
+        self.wallets.hl.update(hl_account);
+        self.wallets.dydx.update(dydx_account);
+        self.hl_positions.update(hl_positions.map(Some));
+        self.dydx_positions.update(dydx_positions.map(Some));
+
+        let markets: Vec<(ExchangeId, Symbol)> = self.positions()
+            .filter_map(|position| Some((position.exchange.clone(), position.symbol().ok()?)))
+            .collect();
+        let quotes = markets.into_iter().map(|(exchange, symbol)| async move {
+            let price = aggregator.get_exchange_summary(&exchange, &symbol).await.ok()?.price;
+            Some(((exchange, symbol), price))
+        });
+        self.marks = futures::future::join_all(quotes).await.into_iter().flatten().collect();
+    }
+
+    // Open positions from every venue with a value, stale ones included
+    fn positions(&self) -> impl Iterator<Item = &Position> {
+        [&self.hl_positions, &self.dydx_positions].into_iter()
+            .filter_map(|positions| positions.value())
+            .flatten()
+            .filter(|position| position.size != 0.0)
+    }
+
+    fn mark(&self, position: &Position) -> Option<f64> {
+        let symbol = position.symbol().ok()?;
+        self.marks.get(&(position.exchange.clone(), symbol)).copied()
+    }
+
+    /// Hyperliquid account value, dYdX equity and Arbitrum USDC
+    pub fn total_equity(&self) -> f64 {
+        self.wallets.hl.value().map_or(0.0, |account| account.account_value())
+            + self.wallets.dydx.value().map_or(0.0, |account| account.equity)
+            + self.wallets.eth.value().map_or(0.0, |eth| eth.usdc)
+    }
+
+    /// Margin locked on both venues: Hyperliquid's reported figure, dYdX
+    /// equity less free collateral
+    pub fn total_margin_used(&self) -> f64 {
+        self.wallets.hl.value().map_or(0.0, |account| account.margin_used())
+            + self.wallets.dydx.value().map_or(0.0, |account| (account.equity - account.free_collateral).max(0.0))
+    }
+
+    /// Unrealized PnL of every position at its mark
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.positions()
+            .map(|position| live_pnl(position, self.mark(position)).unrealized_pnl)
+            .sum()
+    }
+
+    /// Net signed `size * mark` per asset, largest first, so a long on one
+    /// venue and a short on the other net out
+    pub fn exposures(&self) -> Vec<AssetExposure> {
+        let mut by_symbol: BTreeMap<String, AssetExposure> = BTreeMap::new();
+        for position in self.positions() {
+            let Ok(symbol) = position.symbol() else { continue };
+            let (price, at_entry) = match self.mark(position) {
+                Some(mark) => (mark, false),
+                None => (position.entry_price.unwrap_or(0.0), true),
+            };
+            let exposure = by_symbol.entry(symbol.to_string())
+                .or_insert(AssetExposure { symbol, net_usd: 0.0, at_entry: false });
+            exposure.net_usd += position.signed_size() * price;
+            exposure.at_entry |= at_entry;
+        }
+        let mut exposures: Vec<AssetExposure> = by_symbol.into_values().collect();
+        exposures.sort_by(|a, b| b.net_usd.abs().total_cmp(&a.net_usd.abs()));
+        exposures
+    }
+
+    /// Sources missing from the totals or counted from a stale value, e.g.
+    /// "dYdX equity stale (timeout)"
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        note(&mut problems, "Hyperliquid account", &self.wallets.hl);
+        note(&mut problems, "dYdX equity", &self.wallets.dydx);
+        note(&mut problems, "Arbitrum USDC", &self.wallets.eth);
+        note(&mut problems, "Hyperliquid positions", &self.hl_positions);
+        note(&mut problems, "dYdX positions", &self.dydx_positions);
+        problems
+    }
+
+    /// The main screen's portfolio panel
+    pub fn lines(&self) -> Vec<String> {
+        let pnl = self.unrealized_pnl();
+        let mut lines = vec![format!(
+            "Equity ${:.2}  Margin ${:.2}  uPnL {}${:.2}",
+            self.total_equity(),
+            self.total_margin_used(),
+            if pnl < 0.0 { "-" } else { "+" },
+            pnl.abs(),
+        )];
+        let exposures: Vec<String> = self.exposures().iter()
+            .map(|exposure| format!(
+                "{} {}${:.0}{}",
+                exposure.symbol,
+                if exposure.net_usd < 0.0 { "-" } else { "+" },
+                exposure.net_usd.abs(),
+                if exposure.at_entry { " (at entry)" } else { "" },
+            ))
+            .collect();
+        lines.push(if exposures.is_empty() { "Net exposure: none".to_string() } else { format!("Net exposure: {}", exposures.join("  ")) });
+        let problems = self.problems();
+        if !problems.is_empty() {
+            lines.push(format!("Not counted or stale: {}", problems.join("; ")));
+        }
+        lines
+    }
+}
+
+fn note<T>(problems: &mut Vec<String>, label: &str, source: &Availability<T>) {
+    match source {
+        Availability::Pending => problems.push(format!("{} loading", label)),
+        Availability::Failed { error, last: Some(_) } => problems.push(format!("{} stale ({})", label, error)),
+        Availability::Failed { error, last: None } => problems.push(format!("{} failed ({})", label, error)),
+        Availability::Ready(_) | Availability::NotConfigured => {}
+    }
+}
@@ -1,5 +1,5 @@
 use hyperliquid_rust_sdk::PositionData;
-use anyhow::Result;
+use crate::error::Result;
 use ratatui::prelude::*;
 use ratatui::{
     widgets::{Block, Borders, Paragraph},
@@ -8,10 +8,127 @@ use ratatui::{
 use dydx::indexer::PerpetualPositionResponseObject;
 use num_traits::ToPrimitive;
 use dydx::indexer::types::PositionSide;
+use crate::aggregator::symbol::Symbol;
+use crate::aggregator::exchange_id::ExchangeId;
+use super::farm::FundingPayment;
+use super::trailing::TrailingStops;
+use crate::risk::cross_liquidation_price;
+use crate::ui::theme::{arrow, Styles};
+
+pub mod episodes;
+
+// Marks figures recomputed locally rather than reported by the venue
+pub const LOCAL_PNL_MARKER: &str = "•";
+
+// A liquidation price this close to the mark, as a fraction of it, is
+// flagged in the positions view
+pub const LIQUIDATION_WARN_FRACTION: f64 = 0.10;
+
+/// Unrealized PnL and ROE as displayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LivePnl {
+    pub unrealized_pnl: f64,
+    pub roe: Option<f64>,
+    // Recomputed from a streamed mark since the last positions fetch
+    pub local: bool,
+}
+
+/// PnL and ROE of `position` at `mark`; the venue's own figures when there
+/// is no mark or entry price. ROE is on the initial margin, the entry
+/// notional over the leverage, as Hyperliquid reports it.
+pub fn live_pnl(position: &Position, mark: Option<f64>) -> LivePnl {
+    let mark = mark.filter(|mark| *mark > 0.0);
+    let entry = position.entry_price.filter(|entry| *entry > 0.0);
+    let (Some(mark), Some(entry)) = (mark, entry) else {
+        return position.venue_pnl();
+    };
+    if position.size == 0.0 {
+        return position.venue_pnl();
+    }
+    let unrealized_pnl = position.signed_size() * (mark - entry);
+    let roe = position.leverage
+        .filter(|leverage| *leverage > 0)
+        .map(|leverage| unrealized_pnl / (position.size.abs() * entry / leverage as f64));
+    LivePnl { unrealized_pnl, roe, local: true }
+}
+
+/// Funding on an open position, positive when received
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PositionFunding {
+    // Settled since the position opened; None when the open time is unknown
+    pub so_far: Option<f64>,
+    // At the current rate and notional; None without a rate or price
+    pub projected_daily: Option<f64>,
+}
+
+/// Funding on `position` from the payments on its market after `opened_at`,
+/// and a day's worth at `hourly_rate` on its notional at `price`. Longs pay
+/// a positive rate.
+pub fn position_funding(position: &Position, opened_at: Option<i64>, payments: &[FundingPayment], hourly_rate: Option<f64>, price: Option<f64>) -> PositionFunding {
+    let so_far = opened_at.map(|opened_at| payments.iter()
+        .filter(|p| p.exchange == position.exchange && p.asset == position.asset && p.time > opened_at)
+        .map(|p| p.amount)
+        .sum());
+    let price = price.or(position.entry_price).filter(|price| *price > 0.0);
+    let projected_daily = hourly_rate.zip(price)
+        .map(|(rate, price)| -position.signed_size() * price * rate * 24.0);
+    PositionFunding { so_far, projected_daily }
+}
+
+/// Fill in liquidation prices for `exchange`'s positions, which share one
+/// cross-margined account worth `equity`. Each position's current price is
+/// backed out of its entry and unrealized PnL, the price the equity was
+/// marked at. Left as is when any position's maintenance fraction is unknown,
+/// since the others' margin then can't be counted.
+pub fn set_cross_liquidation_prices(positions: &mut [Position], exchange: &ExchangeId, equity: f64, maintenance_fraction: impl Fn(&Position) -> Option<f64>) {
+    let legs: Option<Vec<(usize, f64, f64)>> = positions.iter()
+        .enumerate()
+        .filter(|(_, position)| &position.exchange == exchange && position.size != 0.0)
+        .map(|(idx, position)| {
+            let price = position.current_price()?;
+            Some((idx, price, maintenance_fraction(position)?))
+        })
+        .collect();
+    let Some(legs) = legs else { return };
+    let total_maintenance: f64 = legs.iter()
+        .map(|&(idx, price, mmf)| positions[idx].size.abs() * price * mmf)
+        .sum();
+    for (idx, price, mmf) in legs {
+        let position = &mut positions[idx];
+        let other_maintenance = total_maintenance - position.size.abs() * price * mmf;
+        position.liquidation_price = cross_liquidation_price(position.signed_size(), price, mmf, equity, other_maintenance);
+    }
+}
+
+/// Signed size to close for `percent` of `position`, rounded down to the
+/// market's size `step` so it never exceeds the position. The whole position
+/// at 100%; None when the portion rounds to nothing.
+pub fn partial_close_size(position: &Position, percent: f64, step: Option<f64>) -> Option<f64> {
+    if !(percent > 0.0 && percent <= 100.0) {
+        return None;
+    }
+    let size = position.signed_size();
+    if percent == 100.0 {
+        return (size != 0.0).then_some(size);
+    }
+    let mut portion = size.abs() * percent / 100.0;
+    if let Some(step) = step.filter(|step| *step > 0.0) {
+        // Nudged so a portion landing on a step isn't floored just below it
+        portion = (portion / step + 1e-9).floor() * step;
+    }
+    (portion > 0.0).then(|| portion.copysign(size))
+}
+
+/// Whether `mark` is within `fraction` of `position`'s liquidation price
+pub fn near_liquidation(position: &Position, mark: f64, fraction: f64) -> bool {
+    position.liquidation_price
+        .filter(|liquidation| *liquidation > 0.0 && mark > 0.0)
+        .is_some_and(|liquidation| ((mark - liquidation) / mark).abs() <= fraction)
+}
 
 #[derive(Debug, Clone)]
 pub struct Position {
-    pub exchange: String,
+    pub exchange: ExchangeId,
     pub asset: String,
     pub size: f64,
     pub entry_price: Option<f64>,
@@ -26,7 +143,7 @@ pub struct Position {
 impl Position {
     pub fn from_position_data(data: &PositionData) -> Result<Self> {
         Ok(Position {
-            exchange: "Hyperliquid".to_string(),
+            exchange: ExchangeId::Hyperliquid,
             asset: data.coin.clone(),
             size: data.szi.parse::<f64>()?,
             entry_price: data.entry_px.as_ref().and_then(|p| p.parse().ok()),
@@ -41,7 +158,7 @@ impl Position {
 
     pub fn from_dydx_position(pos: &PerpetualPositionResponseObject) -> Result<Self> {
         Ok(Position {
-            exchange: "dYdX".to_string(),
+            exchange: ExchangeId::Dydx,
             asset: pos.market.0.clone(),
             size: pos.size.0.to_f64().unwrap_or(0.0),
             entry_price: Some(pos.entry_price.0.to_f64().unwrap_or(0.0)),
@@ -58,47 +175,112 @@ impl Position {
         })
     }
 
-    fn format_position(&self) -> String {
+    /// Positive long, negative short, whichever way the venue signs sizes
+    pub fn signed_size(&self) -> f64 {
+        match self.side.as_str() {
+            "Long" => self.size.abs(),
+            "Short" => -self.size.abs(),
+            _ => self.size,
+        }
+    }
+
+    /// The price the venue last marked the position at, from its entry and
+    /// unrealized PnL
+    pub fn current_price(&self) -> Option<f64> {
+        self.price_at_pnl(self.unrealized_pnl)
+    }
+
+    // The price at which the position shows `unrealized_pnl`
+    fn price_at_pnl(&self, unrealized_pnl: f64) -> Option<f64> {
+        let entry = self.entry_price.filter(|entry| *entry > 0.0)?;
+        let size = self.signed_size();
+        (size != 0.0).then(|| entry + unrealized_pnl / size)
+    }
+
+    /// PnL and ROE as of the last positions fetch
+    pub fn venue_pnl(&self) -> LivePnl {
+        LivePnl { unrealized_pnl: self.unrealized_pnl, roe: self.roe, local: false }
+    }
+
+    /// Venue-independent symbol for this position's market ("BTC-USD" on
+    /// dYdX and "BTC" on Hyperliquid both become `BTC`).
+    pub fn symbol(&self) -> Result<Symbol> {
+        Symbol::parse_user_input(&self.asset)
+    }
+
+    /// The position's fields, PnL and ROE marked with an arrow and styled
+    /// by sign.
+    pub fn position_lines(&self, styles: &Styles) -> Vec<Line<'static>> {
+        self.position_lines_at(&self.venue_pnl(), styles)
+    }
+
+    /// As `position_lines`, with `pnl` in place of the venue's figures and a
+    /// dot after the ones recomputed locally.
+    pub fn position_lines_at(&self, pnl: &LivePnl, styles: &Styles) -> Vec<Line<'static>> {
+        let marked = |mut spans: Vec<Span<'static>>| {
+            if pnl.local {
+                spans.push(Span::styled(format!(" {}", LOCAL_PNL_MARKER), styles.muted));
+            }
+            Line::from(spans)
+        };
         let mut lines = vec![
-            format!("Size: {} {}", self.size, self.side),
-            format!("Entry Price: ${:.2}", self.entry_price.unwrap_or(0.0)),
+            Line::from(format!("Size: {} {}", self.size, self.side)),
+            Line::from(format!("Entry Price: ${:.2}", self.entry_price.unwrap_or(0.0))),
         ];
 
         if let Some(liq_price) = self.liquidation_price {
-            lines.push(format!("Liquidation Price: ${:.2}", liq_price));
+            let near = self.price_at_pnl(pnl.unrealized_pnl)
+                .is_some_and(|mark| near_liquidation(self, mark, LIQUIDATION_WARN_FRACTION));
+            if near {
+                lines.push(Line::from(Span::styled(format!("Liquidation Price: ${:.2} (within {:.0}%)", liq_price, LIQUIDATION_WARN_FRACTION * 100.0), styles.alert)));
+            } else {
+                lines.push(Line::from(format!("Liquidation Price: ${:.2}", liq_price)));
+            }
         }
 
-        lines.push(format!("Unrealized PnL: ${:.2}", self.unrealized_pnl));
+        let unrealized = pnl.unrealized_pnl;
+        lines.push(marked(vec![
+            Span::raw("Unrealized PnL: "),
+            Span::styled(format!("{} {}${:.2}", arrow(unrealized), if unrealized < 0.0 { "-" } else { "+" }, unrealized.abs()), styles.direction(unrealized)),
+        ]));
 
         if let Some(margin) = self.margin_used {
-            lines.push(format!("Margin Used: ${:.2}", margin));
+            lines.push(Line::from(format!("Margin Used: ${:.2}", margin)));
         }
 
         if let Some(lev) = self.leverage {
-            lines.push(format!("Leverage: {}x", lev));
+            lines.push(Line::from(format!("Leverage: {}x", lev)));
         }
 
-        if let Some(roe) = self.roe {
-            lines.push(format!("ROE: {:.2}%", roe * 100.0));
+        if let Some(roe) = pnl.roe {
+            lines.push(marked(vec![
+                Span::raw("ROE: "),
+                Span::styled(format!("{} {:+.2}%", arrow(roe), roe * 100.0), styles.direction(roe)),
+            ]));
         }
 
-        lines.join("\n")
+        lines
     }
 
-    pub fn display_positions(f: &mut Frame<'_>, positions: &[Position]) {
+    /// `pnl` and `funding` hold each position's displayed PnL and funding,
+    /// in the same order.
+    /// `selected` is highlighted; `status` is the last close's outcome or the
+    /// open percentage prompt
+    #[allow(clippy::too_many_arguments)]
+    pub fn display_positions(f: &mut Frame<'_>, positions: &[Position], pnl: &[LivePnl], funding: &[PositionFunding], trailing: &TrailingStops, selected: usize, status: Option<&str>, styles: &Styles) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
             .constraints([
                 Constraint::Length(3),    // Title
                 Constraint::Min(0),       // Positions
-                Constraint::Length(3),    // Menu
+                Constraint::Length(4),    // Status and menu
             ])
             .split(f.area());
 
         // Update the title to show exchange breakdown
-        let dydx_count = positions.iter().filter(|p| p.exchange == "dYdX").count();
-        let hl_count = positions.iter().filter(|p| p.exchange == "Hyperliquid").count();
+        let dydx_count = positions.iter().filter(|p| p.exchange == ExchangeId::Dydx).count();
+        let hl_count = positions.iter().filter(|p| p.exchange == ExchangeId::Hyperliquid).count();
         let title = format!("Current Positions (dYdX: {}, Hyperliquid: {})", dydx_count, hl_count);
         
         let title_widget = Paragraph::new(title)
@@ -106,14 +288,38 @@ impl Position {
             .alignment(ratatui::layout::Alignment::Center);
         f.render_widget(title_widget, chunks[0]);
 
+        let funding_lines: Vec<Vec<String>> = (0..positions.len())
+            .map(|idx| {
+                let funding = funding.get(idx).copied().unwrap_or_default();
+                let mut lines = Vec::new();
+                if let Some(so_far) = funding.so_far {
+                    lines.push(format!("Funding so far: {}${:.2}", if so_far < 0.0 { "-" } else { "+" }, so_far.abs()));
+                }
+                if let Some(daily) = funding.projected_daily {
+                    lines.push(format!("Funding per day at current rate: {}${:.2}", if daily < 0.0 { "-" } else { "+" }, daily.abs()));
+                }
+                lines
+            })
+            .collect();
+
+        let stop_lines: Vec<Option<String>> = positions.iter()
+            .map(|p| {
+                let symbol = p.symbol().ok()?;
+                trailing.get(&p.exchange, &symbol).map(|stop| format!("Trailing Stop: {}", stop.describe()))
+            })
+            .collect();
+
         // Calculate height for each position based on available fields
         let position_heights: Vec<u16> = positions.iter()
-            .map(|p| {
+            .zip(&stop_lines)
+            .zip(&funding_lines)
+            .map(|((p, stop), funding)| {
                 let mut height = 6; // Base height for common fields
                 if p.margin_used.is_some() { height += 1; }
                 if p.leverage.is_some() { height += 1; }
-                if p.roe.is_some() { height += 1; }
-                height
+                if p.roe.is_some() || p.leverage.is_some() { height += 1; }
+                if stop.is_some() { height += 1; }
+                height + funding.len() as u16
             })
             .collect();
 
@@ -129,16 +335,30 @@ impl Position {
 
         // Render positions
         for (idx, position) in positions.iter().enumerate() {
-            let position_text = Self::format_position(position);
-            let position_widget = Paragraph::new(position_text)
-                .block(Block::default()
-                    .borders(Borders::ALL)
-                    .title(format!("{} Position ({})", position.asset, position.exchange)));
+            let live = pnl.get(idx).copied().unwrap_or_else(|| position.venue_pnl());
+            let mut position_lines = position.position_lines_at(&live, styles);
+            position_lines.extend(funding_lines[idx].iter().cloned().map(Line::from));
+            let mut title = format!("{} Position ({})", position.asset, position.exchange);
+            if let Some(stop) = &stop_lines[idx] {
+                position_lines.push(Line::from(stop.clone()));
+                title.push_str(" [TS]");
+            }
+            let mut block = Block::default()
+                .borders(Borders::ALL);
+            if idx == selected {
+                title = format!("> {}", title);
+                block = block.border_style(styles.selected);
+            }
+            let position_widget = Paragraph::new(position_lines)
+                .block(block.title(title));
             f.render_widget(position_widget, position_chunks[idx]);
         }
 
         // Menu
-        let menu = Paragraph::new("Press 'q' to return to main menu")
+        let menu = Paragraph::new(vec![
+            Line::from(status.unwrap_or("").to_string()),
+            Line::from(format!("Press 'q' to return to main menu, up/down to select, 'c' to close, 'p' to partially close, 't' to set a trailing stop  ({} = at the streamed mark)", LOCAL_PNL_MARKER)),
+        ])
             .block(Block::default().borders(Borders::ALL))
             .alignment(ratatui::layout::Alignment::Center);
         f.render_widget(menu, chunks[2]);
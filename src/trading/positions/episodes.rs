@@ -0,0 +1,312 @@
+use crate::error::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::analytics::{Candle, CANDLE_INTERVAL_MS};
+use crate::timefmt;
+use crate::trading::farm::FundingPayment;
+
+// Remaining size below this counts as flat
+const SIZE_EPSILON: f64 = 1e-9;
+
+/// One execution, normalised across venues. `asset` is in the venue's own
+/// naming, like `Position::asset`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fill {
+    pub exchange: ExchangeId,
+    pub asset: String,
+    pub is_buy: bool,
+    pub price: f64,
+    // Always positive; the side is in `is_buy`
+    pub size: f64,
+    pub fee: f64,
+    // Millis timestamp
+    pub time: i64,
+    // Venue order id; empty where the venue doesn't report one
+    #[serde(default)]
+    pub order_id: String,
+    // Venue fill/trade id; empty where the venue doesn't report one
+    #[serde(default)]
+    pub fill_id: String,
+}
+
+/// Worst and best unrealized PnL seen while the episode was open, in USD.
+/// Approximated at the episode's average entry and peak size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Excursion {
+    // Zero or negative
+    pub mae: f64,
+    // Zero or positive
+    pub mfe: f64,
+}
+
+/// A position from first fill to flat: opened, possibly scaled in and out,
+/// then closed. Still-open positions have no `closed_at`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionEpisode {
+    pub exchange: ExchangeId,
+    pub asset: String,
+    pub is_long: bool,
+    pub opened_at: i64,
+    pub closed_at: Option<i64>,
+    pub entry_avg: f64,
+    // Average price of the reducing fills, None until the first one
+    pub exit_avg: Option<f64>,
+    pub max_size: f64,
+    // Remaining size, zero once closed
+    pub open_size: f64,
+    // Gross of fees
+    pub realized_pnl: f64,
+    pub fees: f64,
+    // Funding settled while open, positive when received; zero until
+    // `with_funding`
+    pub funding: f64,
+    pub fills: usize,
+    // None when no candles covered the episode
+    pub excursion: Option<Excursion>,
+    exit_size: f64,
+}
+
+impl PositionEpisode {
+    fn open(fill: &Fill, size: f64, fee: f64) -> Self {
+        Self {
+            exchange: fill.exchange.clone(),
+            asset: fill.asset.clone(),
+            is_long: fill.is_buy,
+            opened_at: fill.time,
+            closed_at: None,
+            entry_avg: fill.price,
+            exit_avg: None,
+            max_size: size,
+            open_size: size,
+            realized_pnl: 0.0,
+            fees: fee,
+            funding: 0.0,
+            fills: 1,
+            excursion: None,
+            exit_size: 0.0,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed_at.is_some()
+    }
+
+    pub fn side(&self) -> &'static str {
+        if self.is_long { "Long" } else { "Short" }
+    }
+
+    pub fn net_pnl(&self) -> f64 {
+        self.realized_pnl - self.fees + self.funding
+    }
+
+    /// Millis from open to close, or to `now_ms` while still open
+    pub fn holding_ms(&self, now_ms: i64) -> i64 {
+        self.closed_at.unwrap_or(now_ms) - self.opened_at
+    }
+
+    fn add(&mut self, fill: &Fill, size: f64, fee: f64) {
+        self.entry_avg = (self.entry_avg * self.open_size + fill.price * size) / (self.open_size + size);
+        self.open_size += size;
+        self.max_size = self.max_size.max(self.open_size);
+        self.fees += fee;
+        self.fills += 1;
+    }
+
+    // Returns the price PnL this fill realized
+    fn reduce(&mut self, fill: &Fill, size: f64, fee: f64) -> f64 {
+        let direction = if self.is_long { 1.0 } else { -1.0 };
+        let realized = (fill.price - self.entry_avg) * size * direction;
+        self.realized_pnl += realized;
+        let exit_notional = self.exit_avg.unwrap_or(0.0) * self.exit_size + fill.price * size;
+        self.exit_size += size;
+        self.exit_avg = Some(exit_notional / self.exit_size);
+        self.open_size -= size;
+        self.fees += fee;
+        self.fills += 1;
+        if self.open_size < SIZE_EPSILON {
+            self.open_size = 0.0;
+            self.closed_at = Some(fill.time);
+        }
+        realized
+    }
+
+    /// Add up the funding settled on this market after the open and up to the
+    /// close, or `now_ms` while still open
+    pub fn with_funding(mut self, payments: &[FundingPayment], now_ms: i64) -> Self {
+        let end = self.closed_at.unwrap_or(now_ms);
+        self.funding = payments.iter()
+            .filter(|p| p.exchange == self.exchange && p.asset == self.asset)
+            .filter(|p| p.time > self.opened_at && p.time <= end)
+            .map(|p| p.amount)
+            .sum();
+        self
+    }
+
+    /// Fill in MAE/MFE from hourly candles, oldest first. Left unavailable
+    /// unless the candles cover the whole episode.
+    pub fn with_excursion(mut self, candles: &[Candle], now_ms: i64) -> Self {
+        let end = self.closed_at.unwrap_or(now_ms);
+        let Some(start) = candles.iter().rev().find(|c| c.open_time <= self.opened_at).map(|c| c.open_time) else {
+            return self;
+        };
+        if !candles.last().is_some_and(|c| c.open_time + CANDLE_INTERVAL_MS > end) {
+            return self;
+        }
+        let span: Vec<&Candle> = candles.iter()
+            .filter(|c| c.open_time >= start && c.open_time <= end)
+            .collect();
+
+        let low = span.iter().map(|c| c.low).fold(f64::INFINITY, f64::min);
+        let high = span.iter().map(|c| c.high).fold(f64::NEG_INFINITY, f64::max);
+        let (worst, best) = if self.is_long {
+            (low - self.entry_avg, high - self.entry_avg)
+        } else {
+            (self.entry_avg - high, self.entry_avg - low)
+        };
+        self.excursion = Some(Excursion {
+            mae: (worst * self.max_size).min(0.0),
+            mfe: (best * self.max_size).max(0.0),
+        });
+        self
+    }
+}
+
+/// Totals over the episodes closed in some window
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClosedSummary {
+    pub trades: usize,
+    pub realized_pnl: f64,
+    pub fees: f64,
+    pub funding: f64,
+}
+
+impl ClosedSummary {
+    /// Episodes closed at or after `since_ms`
+    pub fn since(episodes: &[PositionEpisode], since_ms: i64) -> Self {
+        episodes.iter()
+            .filter(|episode| episode.closed_at.is_some_and(|closed| closed >= since_ms))
+            .fold(Self::default(), |summary, episode| Self {
+                trades: summary.trades + 1,
+                realized_pnl: summary.realized_pnl + episode.realized_pnl,
+                fees: summary.fees + episode.fees,
+                funding: summary.funding + episode.funding,
+            })
+    }
+
+    pub fn net_pnl(&self) -> f64 {
+        self.realized_pnl - self.fees + self.funding
+    }
+}
+
+/// Price PnL one fill realized, gross of its fee. Zero for fills that only
+/// open or add.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FillPnl {
+    pub fill: Fill,
+    pub realized: f64,
+}
+
+/// Fold fills into episodes per (exchange, asset). A fill larger than the
+/// open position closes it and opens the opposite side with the remainder;
+/// its fee is split pro rata. Returned in order of opening.
+pub fn build_episodes(fills: &[Fill]) -> Vec<PositionEpisode> {
+    fold_fills(fills).0
+}
+
+/// Realized price PnL per fill, on the same average-cost basis as the
+/// episodes, so the two always sum to the same total. In time order per
+/// market.
+pub fn fill_pnl(fills: &[Fill]) -> Vec<FillPnl> {
+    fold_fills(fills).1
+}
+
+fn fold_fills(fills: &[Fill]) -> (Vec<PositionEpisode>, Vec<FillPnl>) {
+    let mut by_market: BTreeMap<(String, String), Vec<&Fill>> = BTreeMap::new();
+    for fill in fills {
+        by_market.entry((fill.exchange.to_string(), fill.asset.clone())).or_default().push(fill);
+    }
+
+    let mut episodes = Vec::new();
+    let mut realized = Vec::new();
+    for mut market_fills in by_market.into_values() {
+        market_fills.sort_by_key(|fill| fill.time);
+        let mut current: Option<PositionEpisode> = None;
+
+        for fill in market_fills {
+            if fill.size < SIZE_EPSILON {
+                continue;
+            }
+            let Some(episode) = current.as_mut() else {
+                current = Some(PositionEpisode::open(fill, fill.size, fill.fee));
+                realized.push(FillPnl { fill: fill.clone(), realized: 0.0 });
+                continue;
+            };
+
+            if episode.is_long == fill.is_buy {
+                episode.add(fill, fill.size, fill.fee);
+                realized.push(FillPnl { fill: fill.clone(), realized: 0.0 });
+                continue;
+            }
+
+            let closing = fill.size.min(episode.open_size);
+            let pnl = episode.reduce(fill, closing, fill.fee * closing / fill.size);
+            realized.push(FillPnl { fill: fill.clone(), realized: pnl });
+            if episode.is_closed() {
+                episodes.extend(current.take());
+                let remainder = fill.size - closing;
+                if remainder >= SIZE_EPSILON {
+                    current = Some(PositionEpisode::open(fill, remainder, fill.fee * remainder / fill.size));
+                }
+            }
+        }
+        episodes.extend(current);
+    }
+
+    episodes.sort_by_key(|episode| episode.opened_at);
+    (episodes, realized)
+}
+
+pub fn default_csv_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("trading_aggregator");
+    fs::create_dir_all(&config_dir)?;
+    Ok(config_dir.join("closed_trades.csv"))
+}
+
+pub fn write_csv(path: &Path, episodes: &[PositionEpisode], now_ms: i64) -> Result<()> {
+    fs::write(path, episodes_to_csv(episodes, now_ms))?;
+    Ok(())
+}
+
+/// CSV with one row per episode; open episodes have empty close columns.
+pub fn episodes_to_csv(episodes: &[PositionEpisode], now_ms: i64) -> String {
+    let mut csv = String::from(
+        "exchange,asset,side,opened_at,closed_at,entry_avg,exit_avg,max_size,realized_pnl,fees,funding,net_pnl,holding_secs,mae,mfe\n"
+    );
+    for episode in episodes {
+        let excursion = |f: fn(&Excursion) -> f64| episode.excursion.as_ref().map(|e| format!("{:.2}", f(e))).unwrap_or_default();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{:.2},{:.2},{:.2},{:.2},{},{},{}\n",
+            episode.exchange,
+            episode.asset,
+            episode.side(),
+            timefmt::iso(episode.opened_at),
+            episode.closed_at.map(timefmt::iso).unwrap_or_default(),
+            episode.entry_avg,
+            episode.exit_avg.map(|p| p.to_string()).unwrap_or_default(),
+            episode.max_size,
+            episode.realized_pnl,
+            episode.fees,
+            episode.funding,
+            episode.net_pnl(),
+            episode.holding_ms(now_ms) / 1000,
+            excursion(|e| e.mae),
+            excursion(|e| e.mfe),
+        ));
+    }
+    csv
+}
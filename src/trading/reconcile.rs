@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+use chrono::Utc;
+use super::orders::Order;
+use serde::{Deserialize, Serialize};
+use super::history::JournaledFill;
+use crate::aggregator::exchange_id::ExchangeId;
+
+// Finished orders kept around for display before being dropped
+const MAX_FINISHED_ORDERS: usize = 200;
+
+/// Result of comparing the locally known open orders with what a venue
+/// reports, keyed by (exchange, order id).
+#[derive(Debug, Default)]
+pub struct OrderDiff {
+    // Open locally but no longer open on the venue
+    pub gone: Vec<Order>,
+    // Open on the venue but unknown locally
+    pub new: Vec<Order>,
+    pub matched: usize,
+}
+
+impl OrderDiff {
+    pub fn is_empty(&self) -> bool {
+        self.gone.is_empty() && self.new.is_empty()
+    }
+}
+
+fn key(order: &Order) -> (&ExchangeId, &str) {
+    (&order.exchange, order.order_id.as_str())
+}
+
+pub fn diff_orders(local: &[Order], venue: &[Order]) -> OrderDiff {
+    let local_keys: HashSet<_> = local.iter().map(key).collect();
+    let venue_keys: HashSet<_> = venue.iter().map(key).collect();
+
+    let gone = local.iter()
+        .filter(|o| !venue_keys.contains(&key(o)))
+        .cloned()
+        .collect();
+
+    // Venues occasionally repeat an order across pages; only import it once
+    let mut seen = HashSet::new();
+    let new = venue.iter()
+        .filter(|o| !local_keys.contains(&key(o)) && seen.insert(key(o)))
+        .cloned()
+        .collect();
+
+    OrderDiff {
+        gone,
+        new,
+        matched: local_keys.intersection(&venue_keys).count(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderOrigin {
+    // Placed through this app
+    Local,
+    // Placed elsewhere, e.g. the venue's own UI
+    External,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderState {
+    Open,
+    Filled,
+    // Gone from the venue without a fill we could see: cancelled or expired
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackedOrder {
+    pub order: Order,
+    pub origin: OrderOrigin,
+    pub state: OrderState,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReconcileStats {
+    pub runs: u64,
+    pub orders_closed: u64,
+    pub orders_imported: u64,
+    pub last_run: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct VenueCorrections {
+    pub closed: Vec<TrackedOrder>,
+    pub imported: Vec<Order>,
+}
+
+#[derive(Debug, Default)]
+pub struct ReconcileSummary {
+    pub corrections: Vec<(ExchangeId, VenueCorrections)>,
+    pub failed: Vec<(ExchangeId, String)>,
+    // Venue fills newly copied into the journal
+    pub journaled_fills: Vec<JournaledFill>,
+}
+
+impl ReconcileSummary {
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty()
+            && self.corrections.iter().all(|(_, c)| c.closed.is_empty() && c.imported.is_empty())
+            && self.external_fills() == 0
+    }
+
+    /// Newly journaled fills from orders placed outside the app
+    pub fn external_fills(&self) -> usize {
+        self.journaled_fills.iter().filter(|entry| entry.origin == OrderOrigin::External).count()
+    }
+
+    /// e.g. "Reconciled: dYdX 2 closed, Hyperliquid 1 external imported"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        for (exchange, corrections) in &self.corrections {
+            let mut changes = Vec::new();
+            if !corrections.closed.is_empty() {
+                changes.push(format!("{} closed", corrections.closed.len()));
+            }
+            if !corrections.imported.is_empty() {
+                changes.push(format!("{} external imported", corrections.imported.len()));
+            }
+            if !changes.is_empty() {
+                parts.push(format!("{} {}", exchange, changes.join(", ")));
+            }
+        }
+        if self.external_fills() > 0 {
+            parts.push(format!("{} external fills journaled", self.external_fills()));
+        }
+        for (exchange, error) in &self.failed {
+            parts.push(format!("{} failed: {}", exchange, error));
+        }
+
+        if parts.is_empty() {
+            "Reconciled: no changes".to_string()
+        } else {
+            format!("Reconciled: {}", parts.join(", "))
+        }
+    }
+}
+
+/// Local view of orders, kept honest by periodic reconciliation against the
+/// venues.
+#[derive(Debug, Default)]
+pub struct OrderStore {
+    orders: Vec<TrackedOrder>,
+    // Order ids returned by our own placements, so they aren't flagged external
+    placed: HashSet<(ExchangeId, String)>,
+    // Venues whose first snapshot has been taken
+    seeded: HashSet<ExchangeId>,
+    pub stats: ReconcileStats,
+}
+
+impl OrderStore {
+    pub fn record_placed(&mut self, exchange: &ExchangeId, order_id: &str) {
+        if !order_id.is_empty() {
+            self.placed.insert((exchange.clone(), order_id.to_string()));
+        }
+    }
+
+    pub fn orders(&self) -> &[TrackedOrder] {
+        &self.orders
+    }
+
+    pub fn begin_run(&mut self) {
+        self.stats.runs += 1;
+        self.stats.last_run = Some(Utc::now().timestamp_millis());
+    }
+
+    pub fn open_orders(&self) -> impl Iterator<Item = &TrackedOrder> {
+        self.orders.iter().filter(|o| o.state == OrderState::Open)
+    }
+
+    /// Bring `exchange`'s orders in line with the venue. `filled_ids` are
+    /// order ids with recent fills, used to tell fills from cancels.
+    ///
+    /// The first snapshot of a venue only seeds the store: orders that were
+    /// already open at startup are neither "new" nor "external".
+    pub fn apply(&mut self, exchange: &ExchangeId, venue_open: Vec<Order>, filled_ids: &HashSet<String>) -> VenueCorrections {
+        let first_snapshot = self.seeded.insert(exchange.clone());
+        let local_open: Vec<Order> = self.open_orders()
+            .filter(|o| &o.order.exchange == exchange)
+            .map(|o| o.order.clone())
+            .collect();
+        let diff = diff_orders(&local_open, &venue_open);
+        let mut corrections = VenueCorrections::default();
+
+        for gone in &diff.gone {
+            if let Some(tracked) = self.orders.iter_mut()
+                .find(|o| o.state == OrderState::Open && key(&o.order) == key(gone))
+            {
+                tracked.state = if filled_ids.contains(&gone.order_id) { OrderState::Filled } else { OrderState::Closed };
+                corrections.closed.push(tracked.clone());
+            }
+        }
+
+        for order in diff.new {
+            let placed_here = self.placed.contains(&(order.exchange.clone(), order.order_id.clone()));
+            let origin = if placed_here || first_snapshot { OrderOrigin::Local } else { OrderOrigin::External };
+            if origin == OrderOrigin::External {
+                corrections.imported.push(order.clone());
+            }
+            self.orders.push(TrackedOrder { order, origin, state: OrderState::Open });
+        }
+
+        self.stats.orders_closed += corrections.closed.len() as u64;
+        self.stats.orders_imported += corrections.imported.len() as u64;
+        self.prune_finished();
+        corrections
+    }
+
+    fn prune_finished(&mut self) {
+        let finished = self.orders.iter().filter(|o| o.state != OrderState::Open).count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED_ORDERS);
+        // Oldest first, since orders are appended as they're discovered
+        self.orders.retain(|o| {
+            if excess > 0 && o.state != OrderState::Open {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
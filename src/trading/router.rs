@@ -0,0 +1,821 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use std::collections::HashSet;
+use tracing::error;
+use uuid::Uuid;
+use super::{OrderType, TradeRequest};
+use super::farm::{FarmEvent, FarmLeg, FarmReport, FarmTracker, FundingPayment};
+use super::confirmation::{Confirmation, ConfirmationGate, ConfirmationPolicy, ConfirmationTier, Quote};
+use super::duplicates::DuplicateGuard;
+use super::fill_report::FillReport;
+use super::market_gate::{check_market_data, check_slippage, MarketGatePolicy};
+use super::drawdown::{DrawdownAction, DrawdownReading, DrawdownRecord};
+use super::kill_switch::{kill_switch, KillSwitchReport};
+use super::mirror;
+use super::history::{app_order_ids, merge_fills, new_fills, JournaledFill};
+use super::hyperliquid_service::HyperliquidService;
+use super::journal::{Journal, JournalEntry, PositionSnapshot, TradeSnapshot};
+use super::positions::{set_cross_liquidation_prices, Position};
+use super::positions::episodes::{build_episodes, Fill, PositionEpisode};
+use super::orders::{recent_orders, HistoricalOrder, Order};
+use super::reconcile::{OrderState, OrderStore, ReconcileSummary};
+use super::trader::ExchangeTrader;
+use super::strategy::{group_orders, GroupedOrders, StrategyGroup, StrategyLegs};
+use super::pnl::{pnl_entries, PnlEntry};
+use super::wallet::WalletManager;
+use crate::aggregator::symbol::Symbol;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::health::{ClockSkewPolicy, HealthRegistry, SharedHealth};
+use crate::aggregator::book_quality::SharedBookQuality;
+use crate::aggregator::metadata::SharedMetadata;
+use crate::error::{AggregatorError, Result};
+use crate::export::schema::OrderState as ExportedOrderState;
+use crate::export::{EventExporter, ExportEvent};
+use crate::session::{SessionStats, SharedSessionStats};
+
+// Give the venue a moment to reflect a fill before taking the "after" snapshot
+const SNAPSHOT_SETTLE_DELAY: Duration = Duration::from_millis(1500);
+
+pub struct RoutedTrade {
+    pub result: Result<(String, String), AggregatorError>,
+    pub snapshot: TradeSnapshot,
+    // Local id of the submission, None when it was blocked before going out
+    pub client_id: Option<String>,
+}
+
+/// Single entry point for order placement on both venues. Keeps a cache of
+/// positions/collateral so every trade can be journaled with a before/after
+/// snapshot of what it did.
+pub struct TradingRouter {
+    pub hyperliquid_service: HyperliquidService,
+    pub wallet_manager: WalletManager,
+    journal: Journal,
+    positions: Vec<Position>,
+    free_collateral: HashMap<ExchangeId, f64>,
+    orders: OrderStore,
+    health: SharedHealth,
+    // Venues the user chose to trade on despite a detected halt
+    halt_override: HashSet<ExchangeId>,
+    confirmations: ConfirmationGate,
+    duplicates: DuplicateGuard,
+    // Rebuilt from the journal, so farms survive restarts
+    farms: FarmTracker,
+    // Order lifecycle goes here too when event export is on
+    exporter: Option<EventExporter>,
+    clock_policy: ClockSkewPolicy,
+    // Order and equity counters for the session recap
+    session: Option<SharedSessionStats>,
+    // Books market orders are judged against; unchecked when None
+    book_quality: Option<SharedBookQuality>,
+    market_gate: MarketGatePolicy,
+    // One-shot overrides of a failed market data check
+    gate_override: HashSet<(ExchangeId, Symbol)>,
+    // Market specs orders are validated against, and how old one may be;
+    // unvalidated when None
+    metadata: Option<(SharedMetadata, Duration)>,
+    // Orders from the UI are mirrored onto every other built-in venue
+    mirror_mode: bool,
+    // Venues plugged in beside the two built-in ones
+    traders: HashMap<ExchangeId, Box<dyn ExchangeTrader>>,
+}
+
+impl TradingRouter {
+    pub fn new(hyperliquid_service: HyperliquidService, wallet_manager: WalletManager, journal: Journal) -> Self {
+        let farms = match journal.farm_events() {
+            Ok(events) => FarmTracker::replay(&events),
+            Err(e) => {
+                error!("Failed to read funding farms from the journal: {}", e);
+                FarmTracker::default()
+            }
+        };
+        Self {
+            hyperliquid_service,
+            wallet_manager,
+            journal,
+            positions: Vec::new(),
+            free_collateral: HashMap::new(),
+            orders: OrderStore::default(),
+            health: Arc::new(HealthRegistry::default()),
+            halt_override: HashSet::new(),
+            confirmations: ConfirmationGate::default(),
+            duplicates: DuplicateGuard::default(),
+            farms,
+            exporter: None,
+            clock_policy: ClockSkewPolicy::default(),
+            session: None,
+            book_quality: None,
+            market_gate: MarketGatePolicy::default(),
+            gate_override: HashSet::new(),
+            metadata: None,
+            mirror_mode: false,
+            traders: HashMap::new(),
+        }
+    }
+
+    /// Trade on one more venue, keyed by its own `ExchangeId`.
+    pub fn with_trader(mut self, trader: Box<dyn ExchangeTrader>) -> Self {
+        self.traders.insert(trader.exchange(), trader);
+        self
+    }
+
+    /// The built-in venues, then any plugged in
+    pub fn venues(&self) -> Vec<ExchangeId> {
+        let mut venues = ExchangeId::built_in().to_vec();
+        for venue in self.traders.keys() {
+            venues.push(venue.clone());
+        }
+        venues
+    }
+
+    fn trader(&self, exchange: &ExchangeId) -> Result<&dyn ExchangeTrader, AggregatorError> {
+        match exchange {
+            ExchangeId::Hyperliquid => Ok(&self.hyperliquid_service),
+            ExchangeId::Dydx => Ok(&self.wallet_manager),
+            ExchangeId::Custom(_) => self.traders.get(exchange)
+                .map(|trader| trader.as_ref())
+                .ok_or_else(|| AggregatorError::ExchangeError(format!("Trading not supported on {}", exchange))),
+        }
+    }
+
+    fn trader_mut(&mut self, exchange: &ExchangeId) -> Result<&mut dyn ExchangeTrader, AggregatorError> {
+        match exchange {
+            ExchangeId::Hyperliquid => Ok(&mut self.hyperliquid_service),
+            ExchangeId::Dydx => Ok(&mut self.wallet_manager),
+            ExchangeId::Custom(_) => match self.traders.get_mut(exchange) {
+                Some(trader) => Ok(trader.as_mut()),
+                None => Err(AggregatorError::ExchangeError(format!("Trading not supported on {}", exchange))),
+            },
+        }
+    }
+
+    /// Validate orders against the aggregator's cached market specs, ignoring
+    /// any older than `max_age`.
+    pub fn with_metadata(mut self, metadata: SharedMetadata, max_age: Duration) -> Self {
+        self.metadata = Some((metadata, max_age));
+        self
+    }
+
+    /// Judge market orders against the aggregator's sampled books.
+    pub fn with_market_gate(mut self, book_quality: SharedBookQuality, policy: MarketGatePolicy) -> Self {
+        self.book_quality = Some(book_quality);
+        self.market_gate = policy;
+        self
+    }
+
+    pub fn mirror_mode(&self) -> bool {
+        self.mirror_mode
+    }
+
+    pub fn set_mirror_mode(&mut self, on: bool) {
+        self.mirror_mode = on;
+    }
+
+    /// Venues an order placed on `exchange` goes to: `exchange` first, then
+    /// the others while mirror mode is on.
+    pub fn mirror_venues(&self, exchange: &ExchangeId) -> Vec<ExchangeId> {
+        let mut venues = vec![exchange.clone()];
+        if self.mirror_mode {
+            venues.extend(ExchangeId::built_in().into_iter().filter(|other| other != exchange));
+        }
+        venues
+    }
+
+    /// Share the aggregator's health registry so venue halts block orders.
+    pub fn with_health(mut self, health: SharedHealth) -> Self {
+        self.health = health;
+        self
+    }
+
+    pub fn with_confirmation_policy(mut self, policy: ConfirmationPolicy) -> Self {
+        self.confirmations = ConfirmationGate::new(policy);
+        self
+    }
+
+    pub fn with_duplicate_window(mut self, window: Duration) -> Self {
+        self.duplicates = DuplicateGuard::new(window);
+        self
+    }
+
+    pub fn with_clock_skew_policy(mut self, policy: ClockSkewPolicy) -> Self {
+        self.clock_policy = policy;
+        self
+    }
+
+    pub fn with_exporter(mut self, exporter: EventExporter) -> Self {
+        self.exporter = Some(exporter);
+        self
+    }
+
+    pub fn with_session_stats(mut self, stats: SharedSessionStats) -> Self {
+        self.session = Some(stats);
+        self
+    }
+
+    fn record_session(&self, update: impl FnOnce(&mut SessionStats)) {
+        if let Some(mut stats) = self.session.as_ref().and_then(|stats| stats.lock().ok()) {
+            update(&mut stats);
+        }
+    }
+
+    fn export_order(&self, exchange: &ExchangeId, symbol: &str, order_id: &str, state: ExportedOrderState, reason: Option<String>) {
+        if let Some(exporter) = &self.exporter {
+            exporter.emit(ExportEvent::Order {
+                exchange: exchange.clone(),
+                symbol: symbol.to_string(),
+                order_id: order_id.to_string(),
+                state,
+                reason,
+            });
+        }
+    }
+
+    /// Confirmation the order needs at `quote`, with its post-rounding notional.
+    pub fn review_order(&self, request: &TradeRequest, quote: &Quote) -> (ConfirmationTier, f64) {
+        self.confirmations.review(request, quote)
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+
+    pub fn orders(&self) -> &OrderStore {
+        &self.orders
+    }
+
+    /// Free collateral as of the last position refresh
+    pub fn free_collateral(&self, exchange: &ExchangeId) -> Option<f64> {
+        self.free_collateral.get(exchange).copied()
+    }
+
+    /// Refresh the cached positions and free collateral on every venue.
+    pub async fn refresh_positions(&mut self) -> &[Position] {
+        let mut all_positions = Vec::new();
+
+        for exchange in self.venues() {
+            if let Ok(positions) = self.fetch_positions(&exchange).await {
+                all_positions.extend(positions);
+            }
+            if let Ok(collateral) = self.fetch_free_collateral(&exchange).await {
+                self.free_collateral.insert(exchange, collateral);
+            }
+        }
+
+        self.positions = all_positions;
+        self.fill_dydx_liquidation_prices().await;
+        // Free collateral plus margin in use, which both venues' equity
+        // already nets unrealized PnL into
+        let equity = (!self.free_collateral.is_empty()).then(|| {
+            self.free_collateral.values().sum::<f64>()
+                + self.positions.iter().filter_map(|position| position.margin_used).sum::<f64>()
+        });
+        self.record_session(|stats| {
+            stats.seed_positions(&self.positions);
+            if let Some(equity) = equity {
+                stats.record_equity(equity);
+            }
+        });
+        &self.positions
+    }
+
+    // dYdX doesn't report liquidation prices; work them out from the
+    // subaccount's equity and each market's cached maintenance fraction
+    async fn fill_dydx_liquidation_prices(&mut self) {
+        if !self.positions.iter().any(|position| position.exchange == ExchangeId::Dydx) {
+            return;
+        }
+        let Some((metadata, max_age)) = &self.metadata else { return };
+        let equity = match self.wallet_manager.get_dydx_equity().await {
+            Ok(Some(equity)) => equity,
+            Ok(None) => return,
+            Err(e) => {
+                error!("dYdX equity for liquidation prices: {}", e);
+                return;
+            }
+        };
+        let cache = metadata.read().await;
+        let now = Utc::now().timestamp_millis();
+        set_cross_liquidation_prices(&mut self.positions, &ExchangeId::Dydx, equity, |position| {
+            let symbol = position.symbol().ok()?;
+            cache.market(&ExchangeId::Dydx, symbol.base(), *max_age, now)?.0.maintenance_margin_fraction
+        });
+    }
+
+    /// Every order goes through here, so the confirmation policy holds for
+    /// the UI, strategies and any other caller alike. `quote` is the price the
+    /// order is sized at. An identical order placed moments ago is refused as
+    /// a likely double submit unless `force` is set.
+    pub async fn place_trade(&mut self, exchange: &ExchangeId, request: TradeRequest, quote: Quote, confirmation: Confirmation, force: bool) -> RoutedTrade {
+        let symbol = request.asset.clone();
+        let before = PositionSnapshot::capture(
+            &self.positions,
+            exchange,
+            &symbol,
+            self.free_collateral.get(exchange).copied(),
+        );
+
+        // Blocked locally: nothing reached the venue, so nothing to journal
+        let checks = self.ensure_tradable(exchange)
+            .and_then(|_| self.ensure_clock_synced(exchange, &request))
+            .and_then(|_| request.validate_trigger(quote.price))
+            .and_then(|_| Ok(request.validate_time_in_force()?));
+        let checks = checks
+            .and_then(|_| self.pass_market_gate(exchange, &request))
+            .and_then(|_| self.check_slippage(exchange, &request));
+        if let Err(e) = checks {
+            return RoutedTrade { result: Err(e), snapshot: TradeSnapshot { before, after: None }, client_id: None };
+        }
+        if let Err(e) = self.validate_order(exchange, &request, quote.price).await {
+            return RoutedTrade { result: Err(e), snapshot: TradeSnapshot { before, after: None }, client_id: None };
+        }
+        if let Err(e) = self.confirmations.check(exchange, &request, &quote, confirmation, Utc::now().timestamp_millis()) {
+            return RoutedTrade { result: Err(e), snapshot: TradeSnapshot { before, after: None }, client_id: None };
+        }
+        let client_id = match self.duplicates.check(exchange, &request, force, Utc::now().timestamp_millis()) {
+            Ok(client_id) => client_id,
+            Err(e) => return RoutedTrade { result: Err(e), snapshot: TradeSnapshot { before, after: None }, client_id: None },
+        };
+
+        // The submission id doubles as the venue's client order id
+        let request = TradeRequest { cloid: request.cloid.or_else(|| Uuid::parse_str(&client_id).ok()), ..request };
+        let result = self.submit(exchange, request.clone()).await;
+        let asset = symbol.to_string();
+        match &result {
+            Ok((_, order_id)) if !order_id.is_empty() => {
+                self.orders.record_placed(exchange, order_id);
+                self.health.record_order_accepted(exchange);
+                self.record_session(|stats| stats.record_placed(exchange));
+                self.export_order(exchange, &asset, order_id, ExportedOrderState::Placed, None);
+            }
+            Ok((message, _)) => {
+                // Rejected outright, so resending it is no duplicate. Errors
+                // stay recorded: a timeout may still have placed the order.
+                self.duplicates.forget(&client_id);
+                self.health.record_order_rejected(exchange, message, Utc::now().timestamp_millis());
+                self.export_order(exchange, &asset, "", ExportedOrderState::Rejected, Some(message.clone()));
+            }
+            Err(e) => {
+                self.health.record_order_rejected(exchange, &e.to_string(), Utc::now().timestamp_millis());
+                self.export_order(exchange, &asset, "", ExportedOrderState::Rejected, Some(e.to_string()));
+            }
+        }
+
+        tokio::time::sleep(SNAPSHOT_SETTLE_DELAY).await;
+        let after = self.refresh_exchange(exchange, &symbol).await;
+
+        let snapshot = TradeSnapshot { before, after };
+        let entry = JournalEntry::new(exchange, request, &result, snapshot.clone());
+        if let Err(e) = self.journal.append(&entry) {
+            error!("Failed to journal trade: {}", e);
+        }
+
+        RoutedTrade { result, snapshot, client_id: Some(client_id) }
+    }
+
+    /// Refuse orders to a venue that looks halted unless the user has
+    /// overridden it. The override lapses once the venue recovers.
+    pub fn ensure_tradable(&mut self, exchange: &ExchangeId) -> Result<(), AggregatorError> {
+        let status = self.health.status(exchange);
+        if !status.is_halted() {
+            self.halt_override.remove(exchange);
+            return Ok(());
+        }
+        if self.halt_override.contains(exchange) {
+            return Ok(());
+        }
+        Err(AggregatorError::VenueHalted {
+            exchange: exchange.clone(),
+            reason: status.reason().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// dYdX limit and conditional orders are stateful, with a good-til-time
+    /// taken from the local clock; refuse them while it is too far off the
+    /// venue's.
+    pub fn ensure_clock_synced(&self, exchange: &ExchangeId, request: &TradeRequest) -> Result<(), AggregatorError> {
+        if *exchange == ExchangeId::Dydx && !matches!(request.order_type, OrderType::Market) {
+            self.health.ensure_clock_synced(exchange, &self.clock_policy)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether `request` would be refused as a duplicate right now, so the
+    /// UI can ask before sending it with `force`
+    pub fn check_duplicate(&self, exchange: &ExchangeId, request: &TradeRequest) -> Result<(), AggregatorError> {
+        self.duplicates.find(exchange, request, Utc::now().timestamp_millis())
+    }
+
+    /// Refuse market orders into a book that looks broken: stale, one-sided,
+    /// with a blown-out spread, or far from another venue's mid.
+    pub fn check_market_data(&self, exchange: &ExchangeId, request: &TradeRequest) -> Result<(), AggregatorError> {
+        if !matches!(request.order_type, OrderType::Market) {
+            return Ok(());
+        }
+        let Some(quality) = &self.book_quality else { return Ok(()) };
+        let Ok(quality) = quality.lock() else { return Ok(()) };
+        check_market_data(&self.market_gate, &quality, exchange, &request.asset, Utc::now().timestamp_millis())
+    }
+
+    /// Check `request` against its market's cached spec: minimum notional
+    /// and size, leverage cap and a limit price. Passes when no fresh spec is
+    /// cached, leaving the venue to judge.
+    pub async fn validate_order(&self, exchange: &ExchangeId, request: &TradeRequest, price: f64) -> Result<(), AggregatorError> {
+        let Some((metadata, max_age)) = &self.metadata else { return Ok(()) };
+        let spec = metadata.read().await
+            .market(exchange, request.asset.base(), *max_age, Utc::now().timestamp_millis())
+            .map(|(spec, _)| spec);
+        match spec {
+            Some(spec) => Ok(request.validate(&spec, price)?),
+            None => Ok(()),
+        }
+    }
+
+    /// Refuse a market order the latest sampled book can't fill within the
+    /// request's slippage tolerance
+    pub fn check_slippage(&self, exchange: &ExchangeId, request: &TradeRequest) -> Result<(), AggregatorError> {
+        let Some(quality) = &self.book_quality else { return Ok(()) };
+        let Ok(quality) = quality.lock() else { return Ok(()) };
+        match quality.latest(exchange, &request.asset.to_string()) {
+            Some(book) => check_slippage(book, request),
+            None => Ok(()),
+        }
+    }
+
+    /// Let the next market order on `exchange` for `symbol` through the
+    /// market data check.
+    pub fn override_market_data(&mut self, exchange: &ExchangeId, symbol: &Symbol) {
+        self.gate_override.insert((exchange.clone(), symbol.clone()));
+    }
+
+    fn pass_market_gate(&mut self, exchange: &ExchangeId, request: &TradeRequest) -> Result<(), AggregatorError> {
+        if self.gate_override.remove(&(exchange.clone(), request.asset.clone())) {
+            return Ok(());
+        }
+        self.check_market_data(exchange, request)
+    }
+
+    pub fn override_halt(&mut self, exchange: &ExchangeId) {
+        self.halt_override.insert(exchange.clone());
+    }
+
+    // (venue response, order id); an empty id means the venue rejected it
+    async fn submit(&mut self, exchange: &ExchangeId, request: TradeRequest) -> Result<(String, String), AggregatorError> {
+        self.wallet_manager.ensure_writable()?;
+        let result = self.trader_mut(exchange)?.place_trade(request).await?;
+        Ok((result.tx_hash, result.order_id))
+    }
+
+    /// Wait up to `timeout` for an order placed through `place_trade` to leave
+    /// the book, and report what it filled
+    pub async fn wait_for_fill(&self, exchange: &ExchangeId, order_id: &str, timeout: Duration) -> Result<FillReport, AggregatorError> {
+        self.trader(exchange)?.wait_for_fill(order_id, timeout).await
+    }
+
+    /// Compare the local order store with each venue's open orders and recent
+    /// fills, repairing drift. A venue that can't be reached is skipped, never
+    /// treated as having no orders.
+    pub async fn reconcile_orders(&mut self) -> ReconcileSummary {
+        let mut summary = ReconcileSummary::default();
+        self.orders.begin_run();
+
+        for exchange in self.venues() {
+            match self.fetch_order_state(&exchange).await {
+                Ok((open, filled_ids)) => {
+                    let corrections = self.orders.apply(&exchange, open, &filled_ids);
+                    for tracked in &corrections.closed {
+                        self.record_session(|stats| match tracked.state {
+                            OrderState::Filled => stats.record_filled(&exchange),
+                            _ => stats.record_cancelled(&exchange),
+                        });
+                        let state = if tracked.state == OrderState::Filled { ExportedOrderState::Filled } else { ExportedOrderState::Closed };
+                        self.export_order(&exchange, &tracked.order.asset, &tracked.order.order_id, state, None);
+                    }
+                    for order in &corrections.imported {
+                        self.export_order(&exchange, &order.asset, &order.order_id, ExportedOrderState::Imported, None);
+                    }
+                    summary.corrections.push((exchange, corrections));
+                }
+                Err(e) => summary.failed.push((exchange, e.to_string())),
+            }
+        }
+
+        if self.journal.is_writable() {
+            let fetched = self.venue_fills().await;
+            match self.journal_fills(fetched) {
+                Ok(journaled) => summary.journaled_fills = journaled,
+                Err(e) => error!("Failed to journal fills: {}", e),
+            }
+        }
+
+        summary
+    }
+
+    /// Copy `fetched` fills the journal doesn't have yet into it, tagged by
+    /// whether the journal shows this app placed their order. Returns what
+    /// was added.
+    pub fn journal_fills(&mut self, fetched: Vec<Fill>) -> Result<Vec<JournaledFill>> {
+        let app_orders = app_order_ids(&self.journal.entries()?);
+        let fresh = new_fills(&self.journal.fills()?, fetched, &app_orders);
+        for entry in &fresh {
+            self.journal.append_fill(entry)?;
+        }
+        Ok(fresh)
+    }
+
+    /// Journal every fill since `since_ms` the venues still hold, paging
+    /// through each venue's history. `progress` gets the venue and its
+    /// running fill count after each page.
+    pub async fn import_history(&mut self, since_ms: i64, mut progress: impl FnMut(&ExchangeId, usize)) -> Vec<(ExchangeId, Result<Vec<JournaledFill>>)> {
+        let mut results = Vec::new();
+        for exchange in self.venues() {
+            let Ok(trader) = self.trader(&exchange) else { continue };
+            let fetched = trader.fills_since(since_ms, &mut |count| progress(&exchange, count)).await;
+            let result = fetched.and_then(|fills| self.journal_fills(fills));
+            results.push((exchange, result));
+        }
+        results
+    }
+
+    /// Open orders on every reachable venue
+    pub async fn open_orders(&self) -> Vec<Order> {
+        let mut orders = Vec::new();
+        for exchange in self.venues() {
+            match self.fetch_order_state(&exchange).await {
+                Ok((open, _)) => orders.extend(open),
+                Err(e) => error!("Failed to fetch {} orders: {}", exchange, e),
+            }
+        }
+        orders
+    }
+
+    /// Recent fills on every reachable venue, merged with those journaled
+    /// earlier, oldest first
+    pub async fn fills(&self) -> Vec<Fill> {
+        let fetched = self.venue_fills().await;
+        match self.journal.fills() {
+            Ok(journaled) => merge_fills(journaled.into_iter().map(|entry| entry.fill).collect(), fetched),
+            Err(e) => {
+                error!("Failed to read fills from the journal: {}", e);
+                fetched
+            }
+        }
+    }
+
+    async fn venue_fills(&self) -> Vec<Fill> {
+        let mut fills = Vec::new();
+        for exchange in self.venues() {
+            let Ok(trader) = self.trader(&exchange) else { continue };
+            match trader.fills().await {
+                Ok(venue_fills) => fills.extend(venue_fills),
+                Err(e) => error!("Failed to fetch {} fills: {}", exchange, e),
+            }
+        }
+        fills
+    }
+
+    /// The `limit` most recent terminal orders across venues, newest first
+    pub async fn recent_orders(&self, limit: usize) -> Vec<HistoricalOrder> {
+        let mut orders = Vec::new();
+        for exchange in self.venues() {
+            let Ok(trader) = self.trader(&exchange) else { continue };
+            match trader.historical_orders(limit).await {
+                Ok(venue_orders) => orders.extend(venue_orders),
+                Err(e) => error!("Failed to fetch {} order history: {}", exchange, e),
+            }
+        }
+        recent_orders(orders, limit)
+    }
+
+    /// Fill history folded into open/close episodes.
+    pub async fn position_episodes(&self) -> Vec<PositionEpisode> {
+        build_episodes(&self.fills().await)
+    }
+
+    /// Position episodes with the funding each one paid or received
+    pub async fn position_history(&self) -> Vec<PositionEpisode> {
+        let episodes = self.position_episodes().await;
+        let Some(since) = episodes.iter().map(|episode| episode.opened_at).min() else { return episodes };
+        let payments = self.funding_payments(since).await;
+        let now = Utc::now().timestamp_millis();
+        episodes.into_iter().map(|episode| episode.with_funding(&payments, now)).collect()
+    }
+
+    /// Funding paid and received since `since_ms` on every reachable venue
+    pub async fn funding_payments(&self, since_ms: i64) -> Vec<FundingPayment> {
+        let mut payments = Vec::new();
+        for exchange in self.venues() {
+            let Ok(trader) = self.trader(&exchange) else { continue };
+            match trader.funding_payments(since_ms).await {
+                Ok(venue_payments) => payments.extend(venue_payments),
+                Err(e) => error!("Failed to fetch {} funding payments: {}", exchange, e),
+            }
+        }
+        payments
+    }
+
+    /// Everything PnL attribution needs: realized price PnL and fees from
+    /// the fill history, funding paid since `since_ms`, tagged with the
+    /// strategies the journal recorded.
+    pub async fn pnl_entries(&self, since_ms: i64) -> Result<Vec<PnlEntry>> {
+        let legs = StrategyLegs::from_journal(&self.journal.entries()?);
+        let fills = self.fills().await;
+        let payments = self.funding_payments(since_ms.max(0)).await;
+        Ok(pnl_entries(&fills, &payments, &legs))
+    }
+
+    pub fn farms(&self) -> &FarmTracker {
+        &self.farms
+    }
+
+    /// Track the delta-neutral position in `symbol` as a funding farm: a
+    /// long on one venue against a short on the other, as of the last
+    /// position refresh. Fees of the open episodes count as opening fees.
+    pub async fn start_farm(&mut self, symbol: &Symbol) -> Result<()> {
+        let episodes = self.position_episodes().await;
+        let mut legs = Vec::new();
+        for exchange in ExchangeId::built_in() {
+            let Some(position) = self.positions.iter()
+                .find(|p| p.exchange == exchange && p.size != 0.0 && p.symbol().ok().as_ref() == Some(symbol)) else { continue };
+            let open_fees = episodes.iter()
+                .filter(|episode| !episode.is_closed() && episode.exchange == exchange)
+                .filter(|episode| Symbol::parse_user_input(&episode.asset).is_ok_and(|asset| &asset == symbol))
+                .map(|episode| episode.fees)
+                .sum();
+            legs.push(FarmLeg {
+                exchange,
+                size: position.size,
+                entry_price: position.entry_price.unwrap_or(0.0),
+                open_fees,
+            });
+        }
+        let hedged = legs.len() == 2 && legs[0].size.signum() != legs[1].size.signum();
+        if !hedged {
+            return Err(AggregatorError::InvalidInput(format!("A {} farm needs a long on one venue and a short on the other", symbol)));
+        }
+        self.record_farm_events(vec![FarmEvent::Opened { symbol: symbol.clone(), time: Utc::now().timestamp_millis(), legs }]);
+        Ok(())
+    }
+
+    pub fn stop_farm(&mut self, symbol: &Symbol) {
+        self.record_farm_events(vec![FarmEvent::Closed { symbol: symbol.clone(), time: Utc::now().timestamp_millis() }]);
+    }
+
+    /// Pull new funding payments and fills into the farms.
+    pub async fn refresh_farms(&mut self) {
+        let Some(since) = self.farms.earliest_start() else { return };
+        let payments = self.funding_payments(since).await;
+        let fills = self.fills().await;
+        let mut events = self.farms.record_payments(&payments);
+        events.extend(self.farms.record_fills(&fills));
+        self.journal_farm_events(&events);
+    }
+
+    /// How the farm in `symbol` has done so far, with its legs marked from
+    /// the last position refresh.
+    pub fn farm_report(&self, symbol: &Symbol) -> Option<FarmReport> {
+        let marks: Vec<(ExchangeId, f64)> = self.positions.iter()
+            .filter(|p| p.size != 0.0 && p.symbol().ok().as_ref() == Some(symbol))
+            .filter_map(|p| Some((p.exchange.clone(), p.entry_price? + p.unrealized_pnl / p.size)))
+            .collect();
+        self.farms.report(symbol, &marks, Utc::now().timestamp_millis())
+    }
+
+    fn record_farm_events(&mut self, events: Vec<FarmEvent>) {
+        for event in &events {
+            self.farms.apply(event);
+        }
+        self.journal_farm_events(&events);
+    }
+
+    /// Run what a triggered drawdown guard calls for and journal the trigger
+    /// and every step taken, each with the reading behind it. Returns None
+    /// for notify-only.
+    pub async fn run_drawdown_action(&mut self, reading: &DrawdownReading, action: DrawdownAction, attempts: u32) -> Option<Result<KillSwitchReport>> {
+        self.journal_drawdown(&DrawdownRecord::trigger(reading, action));
+        let scope = action.scope()?;
+        let report = kill_switch(&mut self.hyperliquid_service, &mut self.wallet_manager, scope, attempts).await;
+        if let Ok(report) = &report {
+            let now = Utc::now().timestamp_millis();
+            for venue in &report.venues {
+                for step in &venue.steps {
+                    self.journal_drawdown(&DrawdownRecord::step(reading, action, venue.exchange.as_str(), step, now));
+                }
+            }
+        }
+        Some(report)
+    }
+
+    fn journal_drawdown(&mut self, record: &DrawdownRecord) {
+        if !self.journal.is_writable() {
+            return;
+        }
+        if let Err(e) = self.journal.append_drawdown(record) {
+            error!("Failed to journal drawdown guard action: {}", e);
+        }
+    }
+
+    // A read-only instance tracks in memory; the writer journals the same events
+    fn journal_farm_events(&mut self, events: &[FarmEvent]) {
+        if self.wallet_manager.is_read_only() {
+            return;
+        }
+        for event in events {
+            if let Err(e) = self.journal.append_farm(event) {
+                error!("Failed to journal funding farm event: {}", e);
+            }
+        }
+    }
+
+    /// Group open orders by the strategy recorded for them in the journal.
+    pub fn group_orders(&self, open: &[Order]) -> Result<GroupedOrders> {
+        let legs = StrategyLegs::from_journal(&self.journal.entries()?);
+        let filled = self.orders.orders().iter()
+            .filter(|o| o.state == OrderState::Filled)
+            .map(|o| (o.order.exchange.clone(), o.order.order_id.clone()))
+            .collect();
+        Ok(group_orders(open, &legs, &filled))
+    }
+
+    pub async fn cancel_order(&mut self, order: &Order) -> Result<(), AggregatorError> {
+        self.wallet_manager.ensure_writable()?;
+        self.trader_mut(&order.exchange)?.cancel_order(order).await?;
+        self.export_order(&order.exchange, &order.asset, &order.order_id, ExportedOrderState::Cancelled, None);
+        Ok(())
+    }
+
+    /// Market-close `size` of a position (negative for shorts).
+    pub async fn close_position(&mut self, exchange: &ExchangeId, symbol: &Symbol, size: f64) -> Result<String, AggregatorError> {
+        self.wallet_manager.ensure_writable()?;
+        self.trader_mut(exchange)?.close_position(symbol, size).await
+    }
+
+    /// Cancel `order` and, if it is a mirrored leg, its twins among `open`.
+    /// Returns the orders that could not be cancelled.
+    pub async fn cancel_with_twins(&mut self, order: &Order, open: &[Order]) -> Vec<(Order, AggregatorError)> {
+        let twins: Vec<Order> = match self.journal.entries() {
+            Ok(entries) => mirror::twins(order, &StrategyLegs::from_journal(&entries), open).into_iter().cloned().collect(),
+            Err(e) => {
+                error!("Failed to read the journal for mirrored orders: {}", e);
+                Vec::new()
+            }
+        };
+        mirror::cancel_linked(self, order, &twins).await
+    }
+
+    /// Cancel every still-open leg of a strategy, continuing past failures.
+    /// Returns the legs that could not be cancelled.
+    pub async fn cancel_strategy(&mut self, group: &StrategyGroup) -> Vec<(Order, AggregatorError)> {
+        let mut failed = Vec::new();
+        for order in group.cancel_targets() {
+            if let Err(e) = self.cancel_order(order).await {
+                failed.push((order.clone(), e));
+            }
+        }
+        failed
+    }
+
+    // Open orders plus ids of recently filled orders
+    async fn fetch_order_state(&self, exchange: &ExchangeId) -> Result<(Vec<Order>, HashSet<String>)> {
+        self.trader(exchange)?.order_state().await
+    }
+
+    // Targeted refresh of one venue after a trade; also updates the cache
+    async fn refresh_exchange(&mut self, exchange: &ExchangeId, symbol: &Symbol) -> Option<PositionSnapshot> {
+        let positions = match self.fetch_positions(exchange).await {
+            Ok(positions) => positions,
+            Err(e) => {
+                error!("Failed to refresh {} positions after trade: {}", exchange, e);
+                return None;
+            }
+        };
+
+        let free_collateral = self.fetch_free_collateral(exchange).await.ok();
+        if let Some(collateral) = free_collateral {
+            self.free_collateral.insert(exchange.clone(), collateral);
+        }
+
+        let snapshot = PositionSnapshot::capture(&positions, exchange, symbol, free_collateral);
+        self.positions.retain(|p| &p.exchange != exchange);
+        self.positions.extend(positions);
+        Some(snapshot)
+    }
+
+    async fn fetch_positions(&self, exchange: &ExchangeId) -> Result<Vec<Position>, AggregatorError> {
+        self.trader(exchange)?.get_positions().await
+    }
+
+    /// Account equity on one venue, the base for risk-based sizing.
+    pub async fn account_equity(&self, exchange: &ExchangeId) -> Result<f64, AggregatorError> {
+        self.trader(exchange)?.account_equity().await
+    }
+
+    async fn fetch_free_collateral(&self, exchange: &ExchangeId) -> Result<f64, AggregatorError> {
+        self.trader(exchange)?.free_collateral().await
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+use super::journal::{JournalEntry, JournalOutcome};
+use super::orders::Order;
+use crate::aggregator::exchange_id::ExchangeId;
+
+type OrderKey = (ExchangeId, String);
+
+/// One order placed as part of a strategy, as recorded in the journal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyLeg {
+    pub strategy_id: Uuid,
+    pub exchange: ExchangeId,
+    pub order_id: String,
+    // Notional requested at placement
+    pub notional: f64,
+}
+
+/// Strategy membership of every tagged order, rebuilt from the journal so it
+/// survives restarts. Venues know nothing about strategies.
+#[derive(Debug, Clone, Default)]
+pub struct StrategyLegs {
+    legs: HashMap<OrderKey, StrategyLeg>,
+}
+
+impl StrategyLegs {
+    pub fn from_journal(entries: &[JournalEntry]) -> Self {
+        let mut legs = HashMap::new();
+        for entry in entries {
+            let (Some(strategy_id), Some(order_id)) = (entry.request.strategy_id, &entry.order_id) else {
+                continue;
+            };
+            if order_id.is_empty() || !matches!(entry.outcome, JournalOutcome::Accepted { .. }) {
+                continue;
+            }
+            legs.insert((entry.exchange.clone(), order_id.clone()), StrategyLeg {
+                strategy_id,
+                exchange: entry.exchange.clone(),
+                order_id: order_id.clone(),
+                notional: entry.request.usd_value,
+            });
+        }
+        Self { legs }
+    }
+
+    pub fn leg(&self, exchange: &ExchangeId, order_id: &str) -> Option<&StrategyLeg> {
+        self.legs.get(&(exchange.clone(), order_id.to_string()))
+    }
+
+    pub fn for_strategy(&self, strategy_id: Uuid) -> impl Iterator<Item = &StrategyLeg> {
+        self.legs.values().filter(move |leg| leg.strategy_id == strategy_id)
+    }
+}
+
+/// Open orders of one strategy plus aggregate progress over all its legs.
+#[derive(Debug, Clone)]
+pub struct StrategyGroup {
+    pub strategy_id: Uuid,
+    pub open: Vec<Order>,
+    pub placed_notional: f64,
+    pub open_notional: f64,
+    pub filled_notional: f64,
+}
+
+impl StrategyGroup {
+    pub fn filled_pct(&self) -> f64 {
+        if self.placed_notional > 0.0 {
+            (self.filled_notional / self.placed_notional * 100.0).min(100.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// e.g. "Strategy 1a2b3c4d: 3 open, $1500.00 notional, 40% filled"
+    pub fn header(&self) -> String {
+        format!(
+            "Strategy {}: {} open, ${:.2} notional, {:.0}% filled",
+            short_id(self.strategy_id),
+            self.open.len(),
+            self.placed_notional,
+            self.filled_pct()
+        )
+    }
+
+    /// Orders to cancel for cancel-whole-strategy; filled legs are already gone
+    pub fn cancel_targets(&self) -> &[Order] {
+        &self.open
+    }
+}
+
+pub fn short_id(id: Uuid) -> String {
+    id.simple().to_string()[..8].to_string()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GroupedOrders {
+    pub groups: Vec<StrategyGroup>,
+    pub ungrouped: Vec<Order>,
+}
+
+/// A selectable line of the orders view
+#[derive(Debug, Clone, Copy)]
+pub enum OrderRow<'a> {
+    Group(&'a StrategyGroup),
+    Order(&'a Order),
+}
+
+impl GroupedOrders {
+    /// Groups first, each followed by its legs when expanded, then untagged orders.
+    pub fn rows(&self, expanded: &HashSet<Uuid>) -> Vec<OrderRow<'_>> {
+        let mut rows = Vec::new();
+        for group in &self.groups {
+            rows.push(OrderRow::Group(group));
+            if expanded.contains(&group.strategy_id) {
+                rows.extend(group.open.iter().map(OrderRow::Order));
+            }
+        }
+        rows.extend(self.ungrouped.iter().map(OrderRow::Order));
+        rows
+    }
+}
+
+/// Group open orders by strategy. `filled` holds orders known to have filled
+/// completely; an open leg counts as partially filled by however much of its
+/// placed notional is no longer resting.
+pub fn group_orders(open: &[Order], legs: &StrategyLegs, filled: &HashSet<OrderKey>) -> GroupedOrders {
+    let mut grouped = GroupedOrders::default();
+    let mut by_strategy: HashMap<Uuid, Vec<Order>> = HashMap::new();
+    let mut order_of_appearance = Vec::new();
+
+    for order in open {
+        match legs.leg(&order.exchange, &order.order_id) {
+            Some(leg) => {
+                if !by_strategy.contains_key(&leg.strategy_id) {
+                    order_of_appearance.push(leg.strategy_id);
+                }
+                by_strategy.entry(leg.strategy_id).or_default().push(order.clone());
+            }
+            None => grouped.ungrouped.push(order.clone()),
+        }
+    }
+
+    for strategy_id in order_of_appearance {
+        let open = by_strategy.remove(&strategy_id).unwrap_or_default();
+        let mut group = StrategyGroup {
+            strategy_id,
+            open_notional: open.iter().map(|o| o.size * o.price).sum(),
+            open,
+            placed_notional: 0.0,
+            filled_notional: 0.0,
+        };
+
+        for leg in legs.for_strategy(strategy_id) {
+            group.placed_notional += leg.notional;
+            let resting = group.open.iter()
+                .find(|o| o.exchange == leg.exchange && o.order_id == leg.order_id);
+            group.filled_notional += match resting {
+                Some(order) => (leg.notional - order.size * order.price).max(0.0),
+                None if filled.contains(&(leg.exchange.clone(), leg.order_id.clone())) => leg.notional,
+                // Cancelled or expired without a fill we saw
+                None => 0.0,
+            };
+        }
+
+        grouped.groups.push(group);
+    }
+
+    grouped
+}
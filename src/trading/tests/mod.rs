@@ -1,8 +1,9 @@
 #[cfg(test)]
 mod dydx_tests {
     use crate::trading::wallet::WalletManager;
+    use crate::trading::file_lock::AccessMode;
     use crate::trading::init_logging;
-    use anyhow::Result;
+    use crate::error::{AggregatorError, Result};
     use tracing::{info, debug, error};
     use std::time::Duration;
 
@@ -11,7 +12,8 @@ mod dydx_tests {
         init_logging();
         info!("Starting dYdX positions test");
         
-        let mut wallet_manager = WalletManager::new().await?;
+        // Read-only: both tests run at once and would contend for the wallet lock
+        let mut wallet_manager = WalletManager::with_mode(AccessMode::ReadOnly).await?;
         info!("WalletManager initialized");
         
         wallet_manager.init_dydx_client().await?;
@@ -20,7 +22,7 @@ mod dydx_tests {
         if let Some(dydx_service) = wallet_manager.get_dydx_service() {
             if let Some(dydx_wallet) = wallet_manager.get_dydx_wallet() {
                 if let Ok(account) = dydx_wallet.account_offline(0) {
-                    let subaccount = account.subaccount(0)?;
+                    let subaccount = account.subaccount(0).map_err(|e| AggregatorError::Wallet(e.to_string()))?;
                     debug!("Using subaccount: {:?}", subaccount);
                     
                     // Add retry logic with delay
@@ -59,7 +61,8 @@ mod dydx_tests {
         init_logging();
         info!("Starting dYdX orders test");
         
-        let mut wallet_manager = WalletManager::new().await?;
+        // Read-only: both tests run at once and would contend for the wallet lock
+        let mut wallet_manager = WalletManager::with_mode(AccessMode::ReadOnly).await?;
         info!("WalletManager initialized");
         
         wallet_manager.init_dydx_client().await?;
@@ -68,7 +71,7 @@ mod dydx_tests {
         if let Some(dydx_service) = wallet_manager.get_dydx_service() {
             if let Some(dydx_wallet) = wallet_manager.get_dydx_wallet() {
                 if let Ok(account) = dydx_wallet.account_offline(0) {
-                    let subaccount = account.subaccount(0)?;
+                    let subaccount = account.subaccount(0).map_err(|e| AggregatorError::Wallet(e.to_string()))?;
                     debug!("Using subaccount: {:?}", subaccount);
                     
                     // Get all orders with no filters at all
@@ -95,3 +98,4400 @@ mod dydx_tests {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod journal_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::trading::journal::{PositionSnapshot, TradeSnapshot};
+    use crate::trading::positions::Position;
+
+    fn position(exchange: ExchangeId, asset: &str, size: f64, margin_used: Option<f64>) -> Position {
+        Position {
+            exchange,
+            asset: asset.to_string(),
+            size,
+            entry_price: Some(100.0),
+            liquidation_price: None,
+            unrealized_pnl: 0.0,
+            margin_used,
+            leverage: None,
+            roe: None,
+            side: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_capture_matches_exchange_and_symbol() {
+        let positions = vec![
+            position(ExchangeId::Hyperliquid, "BTC", 0.5, Some(210.0)),
+            position(ExchangeId::Hyperliquid, "ETH", 2.0, Some(100.0)),
+            position(ExchangeId::Dydx, "BTC-USD", -1.0, None),
+        ];
+        let btc = Symbol::perp("BTC");
+
+        let hl = PositionSnapshot::capture(&positions, &ExchangeId::Hyperliquid, &btc, Some(1000.0));
+        assert_eq!(hl, PositionSnapshot { size: 0.5, margin_used: Some(210.0), free_collateral: Some(1000.0) });
+
+        let dydx = PositionSnapshot::capture(&positions, &ExchangeId::Dydx, &btc, None);
+        assert_eq!(dydx, PositionSnapshot { size: -1.0, margin_used: None, free_collateral: None });
+    }
+
+    #[test]
+    fn test_capture_without_position_is_flat() {
+        let snapshot = PositionSnapshot::capture(&[], &ExchangeId::Hyperliquid, &Symbol::perp("SOL"), Some(50.0));
+        assert_eq!(snapshot, PositionSnapshot { size: 0.0, margin_used: None, free_collateral: Some(50.0) });
+    }
+
+    #[test]
+    fn test_describe_diff() {
+        let snapshot = TradeSnapshot {
+            before: PositionSnapshot { size: 0.5, margin_used: Some(210.0), free_collateral: None },
+            after: Some(PositionSnapshot { size: 0.75, margin_used: Some(315.0), free_collateral: None }),
+        };
+        assert_eq!(
+            snapshot.describe(&Symbol::perp("BTC")),
+            "position 0.5000 → 0.7500 BTC, margin $210.00 → $315.00"
+        );
+    }
+
+    #[test]
+    fn test_describe_failed_refresh() {
+        let snapshot = TradeSnapshot {
+            before: PositionSnapshot { size: 1.0, margin_used: None, free_collateral: Some(10.0) },
+            after: None,
+        };
+        assert_eq!(
+            snapshot.describe(&Symbol::perp("ETH")),
+            "position 1.0000 ETH (post-trade refresh failed)"
+        );
+    }
+}
+
+#[cfg(test)]
+mod hl_account_tests {
+    use crate::error::AggregatorError;
+    use crate::trading::hl_account::{check_deposit, HlAccountState, HlMarginMode, HL_MIN_DEPOSIT, HL_MIN_WITHDRAWAL};
+
+    const CROSS_AND_ISOLATED: &str = r#"{
+        "marginSummary": {"accountValue": "13109.48", "totalNtlPos": "4000.0", "totalRawUsd": "11109.48", "totalMarginUsed": "300.0"},
+        "crossMarginSummary": {"accountValue": "12909.48", "totalNtlPos": "2000.0", "totalRawUsd": "10909.48", "totalMarginUsed": "100.0"},
+        "crossMaintenanceMarginUsed": "50.0",
+        "withdrawable": "12809.48",
+        "assetPositions": [
+            {"type": "oneWay", "position": {
+                "coin": "ETH", "szi": "1.0", "leverage": {"type": "cross", "value": 20},
+                "entryPx": "2000.0", "positionValue": "2000.0", "unrealizedPnl": "0.0",
+                "returnOnEquity": "0.0", "liquidationPx": null, "marginUsed": "100.0", "maxLeverage": 50
+            }},
+            {"type": "oneWay", "position": {
+                "coin": "BTC", "szi": "-0.02", "leverage": {"type": "isolated", "value": 10, "rawUsd": "2200.0"},
+                "entryPx": "100000.0", "positionValue": "2000.0", "unrealizedPnl": "0.0",
+                "returnOnEquity": "0.0", "liquidationPx": "109000.0", "marginUsed": "200.0", "maxLeverage": 40
+            }}
+        ],
+        "time": 1708622398623
+    }"#;
+
+    const NO_POSITIONS: &str = r#"{
+        "marginSummary": {"accountValue": "250.0", "totalNtlPos": "0.0", "totalRawUsd": "250.0", "totalMarginUsed": "0.0"},
+        "crossMarginSummary": {"accountValue": "250.0", "totalNtlPos": "0.0", "totalRawUsd": "250.0", "totalMarginUsed": "0.0"},
+        "crossMaintenanceMarginUsed": "0.0",
+        "withdrawable": "250.0",
+        "assetPositions": [],
+        "time": 1708622398623
+    }"#;
+
+    // Spot-only accounts have no perp collateral and may omit the perp fields entirely
+    const SPOT_ONLY: &str = r#"{"assetPositions": [], "time": 1708622398623}"#;
+
+    #[test]
+    fn test_parse_cross_and_isolated_positions() {
+        let state = HlAccountState::from_json(CROSS_AND_ISOLATED).unwrap();
+
+        assert_eq!(state.account_value(), 13109.48);
+        assert_eq!(state.margin_used(), 300.0);
+        assert_eq!(state.withdrawable, 12809.48);
+        assert_eq!(state.cross_margin_summary.total_margin_used, 100.0);
+        assert_eq!(state.cross_maintenance_margin_used, 50.0);
+        assert_eq!(state.positions.len(), 2);
+
+        let eth = state.position("ETH").unwrap();
+        assert_eq!(eth.mode, HlMarginMode::Cross);
+        assert_eq!(eth.liquidation_price, None);
+
+        let btc = state.position("BTC").unwrap();
+        assert_eq!(btc.size, -0.02);
+        assert_eq!(btc.mode, HlMarginMode::Isolated { raw_usd: 2200.0 });
+        assert_eq!(btc.liquidation_price, Some(109000.0));
+
+        assert_eq!(state.isolated_margin(), 200.0);
+        assert!((state.cross_margin_ratio().unwrap() - 50.0 / 12909.48).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parse_account_without_positions() {
+        let state = HlAccountState::from_json(NO_POSITIONS).unwrap();
+        assert!(state.positions.is_empty());
+        assert_eq!(state.withdrawable, 250.0);
+        assert_eq!(state.isolated_margin(), 0.0);
+        assert_eq!(state.cross_margin_ratio(), Some(0.0));
+    }
+
+    #[test]
+    fn test_parse_spot_only_account() {
+        let state = HlAccountState::from_json(SPOT_ONLY).unwrap();
+        assert_eq!(state, HlAccountState::default());
+        assert_eq!(state.cross_margin_ratio(), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_numbers() {
+        let json = NO_POSITIONS.replace("\"withdrawable\": \"250.0\"", "\"withdrawable\": \"abc\"");
+        assert!(HlAccountState::from_json(&json).is_err());
+    }
+    #[test]
+    fn test_withdrawal_within_free_balance() {
+        let state = HlAccountState::from_json(NO_POSITIONS).unwrap();
+        assert_eq!(state.withdrawable_balance(), 250.0);
+        assert!(state.check_withdrawal(250.0).is_ok());
+        assert!(state.check_withdrawal(HL_MIN_WITHDRAWAL).is_ok());
+    }
+
+    #[test]
+    fn test_withdrawal_below_minimum_or_over_balance() {
+        let state = HlAccountState::from_json(NO_POSITIONS).unwrap();
+        assert!(matches!(state.check_withdrawal(1.0), Err(AggregatorError::InvalidAmount(_))));
+        assert!(matches!(state.check_withdrawal(f64::NAN), Err(AggregatorError::InvalidAmount(_))));
+        assert!(matches!(state.check_withdrawal(250.01), Err(AggregatorError::InvalidAmount(_))));
+    }
+
+    #[test]
+    fn test_withdrawal_blocked_by_position_margin() {
+        let state = HlAccountState::from_json(CROSS_AND_ISOLATED).unwrap();
+        assert!((state.withdrawable_balance() - 12809.48).abs() < 1e-9);
+        match state.check_withdrawal(13000.0) {
+            Err(AggregatorError::MarginInUse(refusal)) => {
+                assert_eq!(refusal.requested, 13000.0);
+                assert!((refusal.withdrawable - 12809.48).abs() < 1e-9);
+                assert_eq!(refusal.margin_used, 300.0);
+            }
+            other => panic!("expected MarginInUse, got {:?}", other),
+        }
+        // More than the account holds is just too much
+        assert!(matches!(state.check_withdrawal(20000.0), Err(AggregatorError::InvalidAmount(_))));
+    }
+
+    #[test]
+    fn test_deposit_checks_minimum_balance_and_gas() {
+        assert!(check_deposit(HL_MIN_DEPOSIT, 100.0, 0.01, 0.0001).is_ok());
+        assert!(check_deposit(100.0, 100.0, 0.01, 0.0001).is_ok());
+        assert!(matches!(check_deposit(4.99, 100.0, 0.01, 0.0001), Err(AggregatorError::InvalidAmount(_))));
+        assert!(matches!(check_deposit(100.01, 100.0, 0.01, 0.0001), Err(AggregatorError::InsufficientBalance { .. })));
+        let no_gas = check_deposit(50.0, 100.0, 0.00005, 0.0001).unwrap_err();
+        assert!(no_gas.to_string().contains("Gas needs 0.000100 ETH"));
+    }
+}
+
+#[cfg(test)]
+mod reconcile_tests {
+    use std::collections::HashSet;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::test_fixtures::order;
+    use crate::trading::orders::Order;
+    use crate::trading::reconcile::{diff_orders, OrderOrigin, OrderState, OrderStore};
+
+    fn ids(orders: &[Order]) -> Vec<&str> {
+        let mut ids: Vec<&str> = orders.iter().map(|o| o.order_id.as_str()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_diff_empty_sets() {
+        let diff = diff_orders(&[], &[]);
+        assert!(diff.is_empty());
+        assert_eq!(diff.matched, 0);
+    }
+
+    #[test]
+    fn test_diff_identical_sets() {
+        let orders = vec![order(ExchangeId::Hyperliquid, "1"), order(ExchangeId::Hyperliquid, "2")];
+        let diff = diff_orders(&orders, &orders);
+        assert!(diff.is_empty());
+        assert_eq!(diff.matched, 2);
+    }
+
+    #[test]
+    fn test_diff_local_only_is_gone() {
+        let diff = diff_orders(&[order(ExchangeId::Dydx, "a")], &[]);
+        assert_eq!(ids(&diff.gone), vec!["a"]);
+        assert!(diff.new.is_empty());
+    }
+
+    #[test]
+    fn test_diff_venue_only_is_new() {
+        let diff = diff_orders(&[], &[order(ExchangeId::Dydx, "a")]);
+        assert!(diff.gone.is_empty());
+        assert_eq!(ids(&diff.new), vec!["a"]);
+    }
+
+    #[test]
+    fn test_diff_mixed_and_order_independent() {
+        let local = vec![order(ExchangeId::Hyperliquid, "1"), order(ExchangeId::Hyperliquid, "2"), order(ExchangeId::Hyperliquid, "3")];
+        let venue = vec![order(ExchangeId::Hyperliquid, "4"), order(ExchangeId::Hyperliquid, "3"), order(ExchangeId::Hyperliquid, "1")];
+        let diff = diff_orders(&local, &venue);
+        assert_eq!(ids(&diff.gone), vec!["2"]);
+        assert_eq!(ids(&diff.new), vec!["4"]);
+        assert_eq!(diff.matched, 2);
+    }
+
+    #[test]
+    fn test_diff_keys_include_exchange() {
+        let diff = diff_orders(&[order(ExchangeId::Hyperliquid, "7")], &[order(ExchangeId::Dydx, "7")]);
+        assert_eq!(diff.gone.len(), 1);
+        assert_eq!(diff.new.len(), 1);
+        assert_eq!(diff.matched, 0);
+    }
+
+    #[test]
+    fn test_diff_deduplicates_venue_orders() {
+        let venue = vec![order(ExchangeId::Dydx, "a"), order(ExchangeId::Dydx, "a")];
+        assert_eq!(diff_orders(&[], &venue).new.len(), 1);
+    }
+
+    #[test]
+    fn test_first_snapshot_seeds_without_flagging() {
+        let mut store = OrderStore::default();
+        let corrections = store.apply(&ExchangeId::Hyperliquid, vec![order(ExchangeId::Hyperliquid, "1")], &HashSet::new());
+        assert!(corrections.imported.is_empty());
+        assert_eq!(store.orders()[0].origin, OrderOrigin::Local);
+    }
+
+    #[test]
+    fn test_external_orders_are_imported_and_flagged() {
+        let mut store = OrderStore::default();
+        store.apply(&ExchangeId::Hyperliquid, vec![], &HashSet::new());
+        store.record_placed(&ExchangeId::Hyperliquid, "ours");
+
+        let venue = vec![order(ExchangeId::Hyperliquid, "ours"), order(ExchangeId::Hyperliquid, "theirs")];
+        let corrections = store.apply(&ExchangeId::Hyperliquid, venue, &HashSet::new());
+        assert_eq!(ids(&corrections.imported), vec!["theirs"]);
+        assert_eq!(store.open_orders().count(), 2);
+        assert_eq!(store.stats.orders_imported, 1);
+    }
+
+    #[test]
+    fn test_vanished_orders_close_as_filled_or_closed() {
+        let mut store = OrderStore::default();
+        let seed = vec![order(ExchangeId::Dydx, "filled"), order(ExchangeId::Dydx, "cancelled"), order(ExchangeId::Dydx, "open")];
+        store.apply(&ExchangeId::Dydx, seed, &HashSet::new());
+
+        let filled: HashSet<String> = ["filled".to_string()].into_iter().collect();
+        let corrections = store.apply(&ExchangeId::Dydx, vec![order(ExchangeId::Dydx, "open")], &filled);
+        assert_eq!(corrections.closed.len(), 2);
+
+        let state = |id: &str| store.orders().iter().find(|o| o.order.order_id == id).unwrap().state;
+        assert_eq!(state("filled"), OrderState::Filled);
+        assert_eq!(state("cancelled"), OrderState::Closed);
+        assert_eq!(state("open"), OrderState::Open);
+    }
+
+    #[test]
+    fn test_other_venues_are_untouched() {
+        let mut store = OrderStore::default();
+        store.apply(&ExchangeId::Dydx, vec![order(ExchangeId::Dydx, "a")], &HashSet::new());
+        // Hyperliquid reporting nothing must not close dYdX orders
+        let corrections = store.apply(&ExchangeId::Hyperliquid, vec![], &HashSet::new());
+        assert!(corrections.closed.is_empty());
+        assert_eq!(store.open_orders().count(), 1);
+    }
+}
+
+#[cfg(test)]
+mod dead_mans_switch_tests {
+    use crate::error::{AggregatorError, Result};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use crate::trading::dead_mans_switch::{DeadMansSwitch, ScheduleCancel, MIN_TIMEOUT};
+
+    #[derive(Default)]
+    struct MockClient {
+        // Some(after) for a schedule, None for a clear
+        calls: Mutex<Vec<Option<Duration>>>,
+        fail: AtomicBool,
+    }
+
+    impl MockClient {
+        fn calls(&self) -> Vec<Option<Duration>> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl ScheduleCancel for MockClient {
+        async fn schedule_cancel(&self, after: Duration) -> Result<()> {
+            self.calls.lock().unwrap().push(Some(after));
+            if self.fail.load(Ordering::SeqCst) {
+                return Err(AggregatorError::ApiError("network down".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn clear_scheduled_cancel(&self) -> Result<()> {
+            self.calls.lock().unwrap().push(None);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_refresh_interval_is_a_third_of_timeout() {
+        assert_eq!(DeadMansSwitch::refresh_interval(Duration::from_secs(30)), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_short_timeout() {
+        let client = Arc::new(MockClient::default());
+        assert!(DeadMansSwitch::arm(client.clone(), MIN_TIMEOUT - Duration::from_secs(1)).is_err());
+        assert!(client.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_schedule_while_running() {
+        let client = Arc::new(MockClient::default());
+        let timeout = Duration::from_secs(10);
+        let switch = DeadMansSwitch::spawn(client.clone(), timeout, Duration::from_millis(20));
+
+        tokio::time::sleep(Duration::from_millis(110)).await;
+        let calls = client.calls();
+        assert!(calls.len() >= 3, "expected repeated refreshes, got {}", calls.len());
+        assert!(calls.iter().all(|call| *call == Some(timeout)));
+        assert!(switch.is_armed());
+
+        switch.disarm().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_shows_not_armed_and_keeps_trying() {
+        let client = Arc::new(MockClient::default());
+        client.fail.store(true, Ordering::SeqCst);
+        let switch = DeadMansSwitch::spawn(client.clone(), MIN_TIMEOUT, Duration::from_millis(20));
+
+        tokio::time::sleep(Duration::from_millis(70)).await;
+        assert!(!switch.is_armed());
+        assert!(client.calls().len() >= 2);
+
+        client.fail.store(false, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(switch.is_armed());
+
+        switch.disarm().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disarm_clears_schedule_and_stops_refreshing() {
+        let client = Arc::new(MockClient::default());
+        let switch = DeadMansSwitch::spawn(client.clone(), MIN_TIMEOUT, Duration::from_millis(20));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        switch.disarm().await.unwrap();
+        let calls = client.calls();
+        assert_eq!(calls.last(), Some(&None));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(client.calls().len(), calls.len());
+    }
+}
+
+#[cfg(test)]
+mod strategy_tests {
+    use std::collections::HashSet;
+    use uuid::Uuid;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::trading::journal::{JournalEntry, TradeSnapshot};
+    use crate::trading::orders::Order;
+    use crate::trading::strategy::{group_orders, OrderRow, StrategyLegs};
+    use crate::trading::{OrderType, TradeRequest};
+
+    fn leg(strategy_id: Option<Uuid>, id: &str, usd_value: f64) -> JournalEntry {
+        let request = TradeRequest {
+            asset: Symbol::perp("BTC"),
+            is_buy: true,
+            order_type: OrderType::Limit,
+            usd_value,
+            price: Some(100.0),
+            leverage: 1,
+            cross_margin: None,
+            reduce_only: false,
+            strategy_id,
+            max_slippage_bps: None,
+            post_only: false,
+            time_in_force: None,
+            good_til_secs: None,
+            cloid: None,
+        };
+        JournalEntry::new(&ExchangeId::Hyperliquid, request, &Ok(("ok".to_string(), id.to_string())), TradeSnapshot::default())
+    }
+
+    // An open order resting `size` at $100
+    fn open(id: &str, size: f64) -> Order {
+        Order {
+            exchange: ExchangeId::Hyperliquid,
+            asset: "BTC".to_string(),
+            size,
+            price: 100.0,
+            side: "Buy".to_string(),
+            status: "Open".to_string(),
+            order_id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_groups_by_strategy_and_leaves_untagged_orders() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let legs = StrategyLegs::from_journal(&[
+            leg(Some(a), "1", 100.0),
+            leg(Some(a), "2", 100.0),
+            leg(Some(b), "3", 100.0),
+            leg(None, "4", 100.0),
+        ]);
+        let orders = [open("1", 1.0), open("3", 1.0), open("2", 1.0), open("4", 1.0), open("5", 1.0)];
+
+        let grouped = group_orders(&orders, &legs, &HashSet::new());
+        assert_eq!(grouped.groups.len(), 2);
+        assert_eq!(grouped.groups[0].strategy_id, a);
+        assert_eq!(grouped.groups[0].open.len(), 2);
+        assert_eq!(grouped.groups[1].strategy_id, b);
+        assert_eq!(grouped.ungrouped.len(), 2);
+
+        // Collapsed by default: one header per group plus the untagged orders
+        assert_eq!(grouped.rows(&HashSet::new()).len(), 4);
+        let rows = grouped.rows(&[a].into_iter().collect());
+        assert_eq!(rows.len(), 6);
+        assert!(matches!(rows[1], OrderRow::Order(order) if order.order_id == "1"));
+    }
+
+    #[test]
+    fn test_filled_percentage_counts_partial_and_full_fills() {
+        let id = Uuid::new_v4();
+        let legs = StrategyLegs::from_journal(&[
+            leg(Some(id), "1", 100.0),
+            leg(Some(id), "2", 100.0),
+            leg(Some(id), "3", 100.0),
+            leg(Some(id), "4", 100.0),
+        ]);
+        // 1 is half filled, 2 untouched, 3 filled, 4 cancelled
+        let orders = [open("1", 0.5), open("2", 1.0)];
+        let filled = [(ExchangeId::Hyperliquid, "3".to_string())].into_iter().collect();
+
+        let grouped = group_orders(&orders, &legs, &filled);
+        let group = &grouped.groups[0];
+        assert_eq!(group.placed_notional, 400.0);
+        assert_eq!(group.open_notional, 150.0);
+        assert_eq!(group.filled_notional, 150.0);
+        assert!((group.filled_pct() - 37.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cancel_group_skips_filled_leg() {
+        let id = Uuid::new_v4();
+        let legs = StrategyLegs::from_journal(&[
+            leg(Some(id), "1", 100.0),
+            leg(Some(id), "2", 100.0),
+        ]);
+        let filled = [(ExchangeId::Hyperliquid, "1".to_string())].into_iter().collect();
+
+        let grouped = group_orders(&[open("2", 1.0)], &legs, &filled);
+        let targets: Vec<&str> = grouped.groups[0].cancel_targets().iter().map(|o| o.order_id.as_str()).collect();
+        assert_eq!(targets, vec!["2"]);
+        assert_eq!(grouped.groups[0].filled_pct(), 50.0);
+    }
+
+    #[test]
+    fn test_legs_survive_journal_round_trip() {
+        let id = Uuid::new_v4();
+        let line = serde_json::to_string(&leg(Some(id), "7", 100.0)).unwrap();
+        let entry: JournalEntry = serde_json::from_str(&line).unwrap();
+        let legs = StrategyLegs::from_journal(&[entry]);
+        assert_eq!(legs.leg(&ExchangeId::Hyperliquid, "7").map(|leg| leg.strategy_id), Some(id));
+    }
+}
+
+#[cfg(test)]
+mod trailing_tests {
+    use std::path::PathBuf;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::trading::trailing::{TrailDistance, TrailingStop, TrailingStops};
+
+    fn stops_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("hl_aggregator_trailing_{}_{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    // Index of the first price in `path` that triggers the stop
+    fn trigger_index(stop: &mut TrailingStop, path: &[f64]) -> Option<usize> {
+        path.iter().position(|&mark| stop.update(mark, 0) && stop.is_triggered())
+    }
+
+    #[test]
+    fn test_parse_distance() {
+        assert_eq!(TrailDistance::parse("2%").unwrap(), TrailDistance::Percent(2.0));
+        assert_eq!(TrailDistance::parse(" 1.5 % ").unwrap(), TrailDistance::Percent(1.5));
+        assert_eq!(TrailDistance::parse("150").unwrap(), TrailDistance::Absolute(150.0));
+        assert!(TrailDistance::parse("0").is_err());
+        assert!(TrailDistance::parse("100%").is_err());
+        assert!(TrailDistance::parse("abc").is_err());
+    }
+
+    #[test]
+    fn test_long_trails_the_high() {
+        let mut stop = TrailingStop::new(ExchangeId::Hyperliquid, Symbol::perp("BTC"), true, TrailDistance::Percent(10.0), 100.0);
+        // Rallies to 120, so the stop moves up to 108; dips above that don't fire
+        let path = [105.0, 120.0, 110.0, 108.5, 115.0, 107.9, 90.0];
+        assert_eq!(trigger_index(&mut stop, &path), Some(5));
+        assert_eq!(stop.watermark, 120.0);
+        assert!((stop.trigger_level() - 108.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_short_trails_the_low() {
+        let mut stop = TrailingStop::new(ExchangeId::Dydx, Symbol::perp("ETH"), false, TrailDistance::Absolute(50.0), 3000.0);
+        let path = [2990.0, 2900.0, 2940.0, 2949.9, 2950.0];
+        assert_eq!(trigger_index(&mut stop, &path), Some(4));
+        assert_eq!(stop.trigger_level(), 2950.0);
+    }
+
+    #[test]
+    fn test_triggers_once_and_ignores_bad_marks() {
+        let mut stop = TrailingStop::new(ExchangeId::Hyperliquid, Symbol::perp("BTC"), true, TrailDistance::Absolute(5.0), 100.0);
+        assert!(!stop.update(f64::NAN, 0));
+        assert!(!stop.update(0.0, 0));
+        assert!(stop.update(94.0, 7));
+        assert_eq!(stop.triggered_at, Some(7));
+        // Already triggered: further prices change nothing
+        assert!(!stop.update(200.0, 8));
+        assert_eq!(stop.watermark, 100.0);
+    }
+
+    #[test]
+    fn test_watermark_survives_restart() {
+        let path = stops_path("restart");
+        let (hl, btc) = (ExchangeId::Hyperliquid, Symbol::perp("BTC"));
+
+        let mut stops = TrailingStops::load(path.clone()).unwrap();
+        stops.set(TrailingStop::new(hl.clone(), btc.clone(), true, TrailDistance::Percent(5.0), 100.0)).unwrap();
+        assert!(stops.on_mark(&hl, &btc, 140.0).is_none());
+
+        let mut stops = TrailingStops::load(path).unwrap();
+        assert_eq!(stops.get(&hl, &btc).unwrap().watermark, 140.0);
+        let triggered = stops.on_mark(&hl, &btc, 132.0).unwrap();
+        assert!(triggered.is_triggered());
+        // Stays pending until the close succeeds and the stop is removed
+        assert!(stops.on_mark(&hl, &btc, 150.0).is_some());
+        assert!(stops.remove(&hl, &btc).unwrap());
+        assert!(stops.stops().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod file_lock_tests {
+    use std::path::PathBuf;
+    use crate::error::AggregatorError;
+    use crate::trading::file_lock::{AccessMode, FileLock, LockError};
+    use crate::trading::journal::Journal;
+    use crate::trading::wallet::WalletManager;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("hl_aggregator_lock_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn held_by(error: &AggregatorError) -> Option<u32> {
+        match error {
+            AggregatorError::Lock(LockError::Held { pid, .. }) => *pid,
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_second_writing_manager_fails_with_pid() {
+        let dir = temp_dir("wallet");
+        let first = WalletManager::open(dir.clone(), AccessMode::ReadWrite).await.unwrap();
+
+        let error = WalletManager::open(dir.clone(), AccessMode::ReadWrite).await.err().expect("second writer must fail");
+        assert_eq!(held_by(&error), Some(std::process::id()));
+        assert!(error.to_string().contains(&format!("PID {}", std::process::id())));
+
+        // Released on drop
+        drop(first);
+        assert!(WalletManager::open(dir, AccessMode::ReadWrite).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_manager_runs_alongside_writer() {
+        let dir = temp_dir("read_only");
+        let _writer = WalletManager::open(dir.clone(), AccessMode::ReadWrite).await.unwrap();
+
+        let mut observer = WalletManager::open(dir, AccessMode::ReadOnly).await.unwrap();
+        assert!(observer.is_read_only());
+        assert!(observer.create_eth_wallet().await.is_err());
+    }
+
+    #[test]
+    fn test_journal_writer_is_exclusive() {
+        let path = temp_dir("journal").join("journal.jsonl");
+        let _writer = Journal::open(path.clone()).unwrap();
+
+        let error = Journal::open(path.clone()).err().expect("second journal writer must fail");
+        assert_eq!(held_by(&error), Some(std::process::id()));
+        assert!(Journal::open_with_mode(path, AccessMode::ReadOnly).is_ok());
+    }
+
+    #[test]
+    fn test_lock_records_holder_pid() {
+        let path = temp_dir("pid").join("state.lock");
+        let lock = FileLock::acquire(path.clone()).unwrap();
+        assert_eq!(FileLock::holder_pid(lock.path()), Some(std::process::id()));
+        assert!(matches!(FileLock::acquire(path), Err(LockError::Held { .. })));
+    }
+}
+
+#[cfg(test)]
+mod episode_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::analytics::Candle;
+    use crate::test_fixtures;
+    use crate::trading::farm::FundingPayment;
+    use crate::trading::positions::episodes::{build_episodes, episodes_to_csv, fill_pnl, ClosedSummary, Excursion, Fill};
+
+    const HOUR: i64 = 60 * 60 * 1000;
+    const T0: i64 = 1_700_000_000_000 - 1_700_000_000_000 % HOUR;
+
+    fn fill(is_buy: bool, price: f64, size: f64, time: i64) -> Fill {
+        test_fixtures::fill(ExchangeId::Hyperliquid, is_buy, price, size, time)
+    }
+
+    fn buy(price: f64, size: f64, time: i64) -> Fill {
+        fill(true, price, size, time)
+    }
+
+    fn sell(price: f64, size: f64, time: i64) -> Fill {
+        fill(false, price, size, time)
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_open_and_close() {
+        let episodes = build_episodes(&[buy(100.0, 1.0, T0), sell(110.0, 1.0, T0 + HOUR)]);
+        assert_eq!(episodes.len(), 1);
+        let e = &episodes[0];
+        assert!(e.is_long && e.is_closed());
+        assert_eq!((e.entry_avg, e.exit_avg), (100.0, Some(110.0)));
+        assert_eq!(e.realized_pnl, 10.0);
+        assert_eq!(e.holding_ms(0), HOUR);
+        assert_eq!(e.fills, 2);
+    }
+
+    #[test]
+    fn test_scale_in_and_partial_closes() {
+        let episodes = build_episodes(&[
+            buy(100.0, 1.0, T0),
+            buy(110.0, 1.0, T0 + 1),    // entry avg 105
+            sell(120.0, 0.5, T0 + 2),   // +7.5
+            sell(100.0, 1.5, T0 + 3),   // -7.5
+        ]);
+        assert_eq!(episodes.len(), 1);
+        let e = &episodes[0];
+        assert_eq!(e.entry_avg, 105.0);
+        assert_eq!(e.max_size, 2.0);
+        assert!(close(e.realized_pnl, 0.0));
+        assert!(close(e.exit_avg.unwrap(), 105.0));
+        assert_eq!(e.closed_at, Some(T0 + 3));
+    }
+
+    #[test]
+    fn test_partially_closed_episode_stays_open() {
+        let episodes = build_episodes(&[sell(100.0, 2.0, T0), buy(90.0, 0.5, T0 + 1)]);
+        let e = &episodes[0];
+        assert!(!e.is_long && !e.is_closed());
+        assert_eq!(e.open_size, 1.5);
+        assert_eq!(e.realized_pnl, 5.0);
+        assert_eq!(e.holding_ms(T0 + 10), 10);
+    }
+
+    #[test]
+    fn test_flip_ends_one_episode_and_starts_another() {
+        let mut flip = sell(90.0, 3.0, T0 + 1);
+        flip.fee = 0.3;
+        let episodes = build_episodes(&[buy(100.0, 1.0, T0), flip, buy(80.0, 2.0, T0 + 2)]);
+        assert_eq!(episodes.len(), 2);
+
+        let (long, short) = (&episodes[0], &episodes[1]);
+        assert!(long.is_long && long.is_closed());
+        assert_eq!(long.realized_pnl, -10.0);
+        assert!(close(long.fees, 0.1));
+
+        assert!(!short.is_long && short.is_closed());
+        assert_eq!(short.opened_at, T0 + 1);
+        assert_eq!((short.entry_avg, short.max_size), (90.0, 2.0));
+        assert_eq!(short.realized_pnl, 20.0);
+        assert!(close(short.fees, 0.2));
+        assert!(close(short.net_pnl(), 19.8));
+    }
+
+    #[test]
+    fn test_exact_close_then_re_entry() {
+        let episodes = build_episodes(&[
+            buy(100.0, 1.0, T0),
+            sell(105.0, 1.0, T0 + 1),
+            buy(95.0, 2.0, T0 + 2),
+            sell(100.0, 2.0, T0 + 3),
+            sell(101.0, 1.0, T0 + 4),
+        ]);
+        assert_eq!(episodes.len(), 3);
+        assert_eq!(episodes.iter().map(|e| e.realized_pnl).collect::<Vec<_>>(), vec![5.0, 10.0, 0.0]);
+        assert!(episodes[1].is_long && !episodes[2].is_long);
+        assert!(!episodes[2].is_closed());
+    }
+
+    #[test]
+    fn test_markets_are_folded_separately_and_input_is_sorted() {
+        let mut eth_buy = buy(2000.0, 1.0, T0 + 1);
+        eth_buy.asset = "ETH".to_string();
+        let mut dydx_sell = sell(105.0, 1.0, T0 + 2);
+        dydx_sell.exchange = ExchangeId::Dydx;
+
+        // Out of order, interleaved across markets
+        let episodes = build_episodes(&[sell(110.0, 1.0, T0 + 5), eth_buy, dydx_sell, buy(100.0, 1.0, T0)]);
+        assert_eq!(episodes.len(), 3);
+        assert_eq!(episodes[0].asset, "BTC");
+        assert_eq!(episodes[0].exchange, ExchangeId::Hyperliquid);
+        assert_eq!(episodes[0].realized_pnl, 10.0);
+        assert_eq!(episodes[1].asset, "ETH");
+        assert!(!episodes[2].is_long && episodes[2].exchange == ExchangeId::Dydx);
+    }
+
+    #[test]
+    fn test_empty_and_zero_size_fills() {
+        assert!(build_episodes(&[]).is_empty());
+        assert!(build_episodes(&[buy(100.0, 0.0, T0)]).is_empty());
+    }
+
+    #[test]
+    fn test_excursion_from_candles() {
+        let episode = build_episodes(&[buy(100.0, 2.0, T0 + 10), sell(104.0, 2.0, T0 + HOUR + 10)]).remove(0);
+        let candle = |open_time, low, high| Candle { open_time, open: 100.0, high, low, close: 100.0 };
+
+        let candles = vec![candle(T0 - HOUR, 50.0, 150.0), candle(T0, 97.0, 103.0), candle(T0 + HOUR, 99.0, 106.0)];
+        let with = episode.clone().with_excursion(&candles, T0 + 2 * HOUR);
+        // The candle before the episode opened is ignored
+        assert_eq!(with.excursion, Some(Excursion { mae: -6.0, mfe: 12.0 }));
+
+        // Candles that stop before the close leave it unavailable
+        assert_eq!(episode.clone().with_excursion(&candles[..2], T0 + 2 * HOUR).excursion, None);
+        // ...as do candles starting after the open
+        assert_eq!(episode.with_excursion(&candles[2..], T0 + 2 * HOUR).excursion, None);
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let episodes = build_episodes(&[buy(100.0, 1.0, T0), sell(110.0, 1.0, T0 + HOUR), buy(105.0, 1.0, T0 + 2 * HOUR)]);
+        let csv = episodes_to_csv(&episodes, T0 + 3 * HOUR);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("exchange,asset,side,opened_at,closed_at"));
+        assert!(lines[1].starts_with("Hyperliquid,BTC,Long,"));
+        assert!(lines[1].ends_with(",100,110,1,10.00,0.00,0.00,10.00,3600,,"));
+        // Open episode: no close time or exit
+        assert!(lines[2].contains(",,105,,1,"));
+    }
+
+    #[test]
+    fn test_funding_within_the_episode() {
+        let payment = |exchange: ExchangeId, asset: &str, time: i64, amount: f64| FundingPayment { exchange, asset: asset.to_string(), time, amount, rate: None };
+        let payments = [
+            // Before the open, on another market, and on another venue
+            payment(ExchangeId::Hyperliquid, "BTC", T0, -9.0),
+            payment(ExchangeId::Hyperliquid, "ETH", T0 + HOUR, -9.0),
+            payment(ExchangeId::Dydx, "BTC", T0 + HOUR, -9.0),
+            payment(ExchangeId::Hyperliquid, "BTC", T0 + HOUR, -1.5),
+            payment(ExchangeId::Hyperliquid, "BTC", T0 + 2 * HOUR, 0.5),
+            // After the close
+            payment(ExchangeId::Hyperliquid, "BTC", T0 + 3 * HOUR, -9.0),
+        ];
+        let episodes = build_episodes(&[buy(100.0, 1.0, T0), sell(110.0, 1.0, T0 + 2 * HOUR)]);
+        let episode = episodes[0].clone().with_funding(&payments, T0 + 4 * HOUR);
+        assert_eq!(episode.funding, -1.0);
+        assert_eq!(episode.net_pnl(), 9.0);
+    }
+
+    #[test]
+    fn test_closed_summary_window() {
+        let episodes: Vec<_> = build_episodes(&[
+            buy(100.0, 1.0, T0),
+            sell(110.0, 1.0, T0 + HOUR),
+            buy(100.0, 1.0, T0 + 2 * HOUR),
+            sell(95.0, 1.0, T0 + 3 * HOUR),
+            buy(100.0, 1.0, T0 + 4 * HOUR),
+        ]);
+        let all = ClosedSummary::since(&episodes, T0);
+        assert_eq!((all.trades, all.net_pnl()), (2, 5.0));
+        // Only the losing trade closed after the cutoff; the open one never counts
+        let recent = ClosedSummary::since(&episodes, T0 + 2 * HOUR);
+        assert_eq!((recent.trades, recent.realized_pnl), (1, -5.0));
+    }
+
+    #[test]
+    fn test_shared_fixture_realized_pnl() {
+        let episodes = build_episodes(&super::pnl_fixtures::fills());
+        let realized: f64 = episodes.iter().map(|e| e.realized_pnl).sum();
+        // Per-fill attribution folds the same way
+        let per_fill: f64 = fill_pnl(&super::pnl_fixtures::fills()).iter().map(|f| f.realized).sum();
+        assert!(close(per_fill, realized));
+        let net: f64 = episodes.iter().map(|e| e.net_pnl()).sum();
+        assert!(close(realized, super::pnl_fixtures::REALIZED));
+        assert!(close(net, super::pnl_fixtures::REALIZED - super::pnl_fixtures::FEES));
+    }
+}
+
+#[cfg(test)]
+mod order_outcome_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::trading::orders::{parse_dydx_historical_orders, parse_hl_historical_orders, parse_hl_order_status, recent_orders, OrderOutcome};
+
+    fn rejected(reason: &str) -> Option<OrderOutcome> {
+        Some(OrderOutcome::Rejected { reason: reason.to_string() })
+    }
+
+    fn by_venue(reason: &str) -> Option<OrderOutcome> {
+        Some(OrderOutcome::CancelledByVenue { reason: reason.to_string() })
+    }
+
+    #[test]
+    fn test_hl_statuses() {
+        let cases = [
+            ("open", None),
+            ("triggered", None),
+            ("filled", Some(OrderOutcome::Filled)),
+            ("canceled", Some(OrderOutcome::CancelledByUser)),
+            ("scheduledCancel", Some(OrderOutcome::CancelledByDms)),
+            ("rejected", rejected("rejected")),
+            ("tickRejected", rejected("tick")),
+            ("perpMarginRejected", rejected("perp margin")),
+            ("badAloPxRejected", rejected("bad alo px")),
+            ("marginCanceled", by_venue("margin")),
+            ("reduceOnlyCanceled", by_venue("reduce only")),
+            ("selfTradeCanceled", by_venue("self trade")),
+            ("liquidatedCanceled", by_venue("liquidated")),
+        ];
+        for (status, expected) in cases {
+            assert_eq!(OrderOutcome::from_hl_status(status), expected, "{}", status);
+        }
+    }
+
+    #[test]
+    fn test_dydx_statuses() {
+        let cases = [
+            ("OPEN", None, None),
+            ("UNTRIGGERED", None, None),
+            ("BEST_EFFORT_OPENED", None, None),
+            ("FILLED", None, Some(OrderOutcome::Filled)),
+            ("CANCELED", Some("USER_CANCELED"), Some(OrderOutcome::CancelledByUser)),
+            ("CANCELED", None, Some(OrderOutcome::CancelledByUser)),
+            ("CANCELED", Some("EXPIRED"), Some(OrderOutcome::Expired)),
+            ("BEST_EFFORT_CANCELED", Some("ORDER_REMOVAL_REASON_INDEXER_EXPIRED"), Some(OrderOutcome::Expired)),
+            ("CANCELED", Some("POST_ONLY_WOULD_CROSS_MAKER_ORDER"), rejected("post only would cross maker order")),
+            ("CANCELED", Some("UNDERCOLLATERALIZED"), by_venue("undercollateralized")),
+        ];
+        for (status, reason, expected) in cases {
+            assert_eq!(OrderOutcome::from_dydx_status(status, reason), expected, "{} {:?}", status, reason);
+        }
+    }
+
+    #[test]
+    fn test_outcome_display() {
+        assert_eq!(OrderOutcome::Rejected { reason: "tick".to_string() }.to_string(), "Rejected: tick");
+        assert_eq!(OrderOutcome::CancelledByDms.to_string(), "Cancelled by dead man's switch");
+    }
+
+    #[test]
+    fn test_parse_hl_history_skips_live_orders() {
+        let body = r#"[
+            {"order": {"coin": "BTC", "side": "B", "limitPx": "60000.0", "sz": "0.0", "origSz": "0.01", "oid": 11, "timestamp": 1}, "status": "filled", "statusTimestamp": 2000},
+            {"order": {"coin": "ETH", "side": "A", "limitPx": "3000.0", "sz": "1.0", "origSz": "1.0", "oid": 12, "timestamp": 1}, "status": "open", "statusTimestamp": 3000},
+            {"order": {"coin": "SOL", "side": "A", "limitPx": "150.0", "sz": "2.0", "origSz": "2.0", "oid": 13, "timestamp": 1}, "status": "marginCanceled", "statusTimestamp": 4000}
+        ]"#;
+        let orders = recent_orders(parse_hl_historical_orders(body).unwrap(), 10);
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].asset, "SOL");
+        assert_eq!(orders[0].side, "Sell");
+        assert_eq!(orders[0].outcome, by_venue("margin").unwrap());
+        assert_eq!(orders[1].order_id, "11");
+        assert_eq!(orders[1].size, 0.01);
+        assert_eq!(orders[1].exchange, ExchangeId::Hyperliquid);
+    }
+
+    #[test]
+    fn test_parse_hl_order_status() {
+        let live = r#"{"status": "order", "order": {"order": {"coin": "ETH", "side": "B", "limitPx": "3000.0", "sz": "1.0", "origSz": "1.0", "oid": 42, "timestamp": 1, "cloid": "0x00000000000000000000000000000001"}, "status": "open", "statusTimestamp": 5000}}"#;
+        let status = parse_hl_order_status(live).unwrap().unwrap();
+        assert_eq!(status.order_id, "42");
+        assert_eq!(status.asset, "ETH");
+        assert_eq!(status.outcome, None);
+        assert_eq!(status.time, 5000);
+
+        let filled = live.replace(r#""status": "open""#, r#""status": "filled""#);
+        assert_eq!(parse_hl_order_status(&filled).unwrap().unwrap().outcome, Some(OrderOutcome::Filled));
+    }
+
+    #[test]
+    fn test_parse_hl_order_status_unknown() {
+        assert_eq!(parse_hl_order_status(r#"{"status": "unknownOid"}"#).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_dydx_history() {
+        let body = r#"[
+            {"clientId": "7", "ticker": "BTC-USD", "side": "BUY", "price": "60000", "size": "0.01", "status": "CANCELED", "removalReason": "EXPIRED", "updatedAt": "2024-05-01T00:00:01.000Z"},
+            {"clientId": "8", "ticker": "BTC-USD", "side": "SELL", "price": "61000", "size": "0.01", "status": "OPEN", "updatedAt": "2024-05-01T00:00:02.000Z"}
+        ]"#;
+        let orders = parse_dydx_historical_orders(body).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].outcome, OrderOutcome::Expired);
+        assert_eq!(orders[0].side, "Buy");
+        assert_eq!(orders[0].time, 1_714_521_601_000);
+    }
+}
+
+#[cfg(test)]
+mod automation_tests {
+    use crate::error::Result;
+    use async_trait::async_trait;
+    use std::path::PathBuf;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::test_fixtures;
+    use crate::trading::automation::ma_cross::{MaCross, MaCrossConfig};
+    use crate::trading::automation::quoter::{QuoterConfig, SimpleQuoter, REQUOTE_INTERVAL_MS};
+    use crate::trading::automation::{ActionLog, ActionOutcome, ActionRecord, BookTop, RiskLimits, Strategy, StrategyAction, StrategyExecutor, StrategyPnl, StrategyRunner};
+    use crate::trading::positions::episodes::Fill;
+    use crate::trading::{OrderType, TradeRequest};
+
+    // Scripted venue: accepts every order and hands out sequential ids
+    #[derive(Default)]
+    struct MockExecutor {
+        placed: Vec<(ExchangeId, TradeRequest)>,
+        cancelled: Vec<String>,
+    }
+
+    #[async_trait(?Send)]
+    impl StrategyExecutor for MockExecutor {
+        async fn place(&mut self, exchange: &ExchangeId, request: TradeRequest, _price: f64) -> Result<String> {
+            self.placed.push((exchange.clone(), request));
+            Ok(format!("oid-{}", self.placed.len()))
+        }
+
+        async fn cancel(&mut self, _exchange: &ExchangeId, _asset: &str, order_id: &str) -> Result<()> {
+            self.cancelled.push(order_id.to_string());
+            Ok(())
+        }
+    }
+
+    fn config() -> MaCrossConfig {
+        "hyperliquid,BTC,2,4,100".parse().unwrap()
+    }
+
+    fn limits() -> RiskLimits {
+        RiskLimits { max_order_usd: 500.0, max_position_usd: 1000.0 }
+    }
+
+    fn request(usd_value: f64) -> TradeRequest {
+        test_fixtures::request(OrderType::Market, true, usd_value)
+    }
+
+    fn book(mid: f64) -> BookTop {
+        BookTop { exchange: ExchangeId::Hyperliquid, symbol: Symbol::perp("BTC"), best_bid: mid - 0.5, best_ask: mid + 0.5, time: 0 }
+    }
+
+    // One book update then one timer tick per mid; returns every record
+    async fn replay(runner: &mut StrategyRunner, executor: &mut MockExecutor, mids: &[f64]) -> Vec<ActionRecord> {
+        let mut records = Vec::new();
+        for (tick, mid) in mids.iter().enumerate() {
+            records.extend(runner.on_book(book(*mid), executor).await);
+            records.extend(runner.on_timer(tick as i64, executor).await);
+        }
+        records
+    }
+
+    fn places(records: &[ActionRecord]) -> Vec<&ActionRecord> {
+        records.iter().filter(|record| !record.action.starts_with("notify")).collect()
+    }
+
+    #[test]
+    fn test_parse_ma_cross_config() {
+        let config = config();
+        assert_eq!(config.exchange, ExchangeId::Hyperliquid);
+        assert_eq!(config.symbol, Symbol::perp("BTC"));
+        assert_eq!((config.fast, config.slow, config.notional), (2, 4, 100.0));
+        assert!("hyperliquid,BTC,4,2,100".parse::<MaCrossConfig>().is_err());
+        assert!("hyperliquid,BTC,2,4".parse::<MaCrossConfig>().is_err());
+        assert!("hyperliquid,BTC,2,4,-5".parse::<MaCrossConfig>().is_err());
+    }
+
+    #[test]
+    fn test_pnl_through_a_flip() {
+        let mut pnl = StrategyPnl::default();
+        pnl.apply_fill(true, 100.0, 2.0, 0.1);
+        pnl.apply_fill(false, 110.0, 3.0, 0.1);
+        assert!((pnl.realized - 20.0).abs() < 1e-9);
+        assert!((pnl.position + 1.0).abs() < 1e-9);
+        assert_eq!(pnl.entry_avg, 110.0);
+        pnl.mark = Some(105.0);
+        assert!((pnl.unrealized() - 5.0).abs() < 1e-9);
+        assert!((pnl.total() - 24.8).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_ma_cross_fills_on_paper() {
+        let mut runner = StrategyRunner::new(true, limits());
+        let id = runner.add(Box::new(MaCross::new(config())));
+        let mut executor = MockExecutor::default();
+
+        // Stopped strategies see nothing
+        assert!(replay(&mut runner, &mut executor, &[100.0, 101.0, 102.0, 103.0]).await.is_empty());
+
+        runner.set_running(id, true);
+        let records = replay(&mut runner, &mut executor, &[100.0, 100.0, 100.0, 100.0, 104.0, 108.0, 100.0, 92.0]).await;
+        let orders = places(&records);
+        assert_eq!(orders.len(), 2);
+        assert!(orders.iter().all(|record| record.outcome == ActionOutcome::Simulated && record.dry_run && record.strategy_id == id));
+        assert!(orders[0].action.starts_with("buy BTC $100.00"));
+        // The flip closes the long and opens a short of the same size
+        assert!(orders[1].action.starts_with("sell BTC $200.00"));
+        assert!(executor.placed.is_empty());
+
+        let status = &runner.statuses()[0];
+        assert!(status.pnl.position < 0.0);
+        // Bought the breakout, sold the breakdown
+        assert!(status.pnl.realized < 0.0);
+        assert_eq!(runner.take_notifications().len(), 2);
+        assert!(runner.take_notifications().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_live_orders_carry_strategy_id_and_fills_are_attributed() {
+        let mut runner = StrategyRunner::new(false, limits());
+        let id = runner.add(Box::new(MaCross::new(config())));
+        let other = runner.add(Box::new(MaCross::new(config())));
+        runner.set_running(id, true);
+        let mut executor = MockExecutor::default();
+
+        let records = replay(&mut runner, &mut executor, &[100.0, 100.0, 100.0, 100.0, 104.0]).await;
+        assert_eq!(places(&records)[0].outcome, ActionOutcome::Executed { order_id: Some("oid-1".to_string()) });
+        assert_eq!(executor.placed.len(), 1);
+        assert_eq!(executor.placed[0].1.strategy_id, Some(id));
+
+        let fill = |order_id: &str| Fill {
+            exchange: ExchangeId::Hyperliquid,
+            asset: "BTC".to_string(),
+            is_buy: true,
+            price: 104.5,
+            size: 1.0,
+            fee: 0.05,
+            time: 1,
+            order_id: order_id.to_string(),
+            fill_id: String::new(),
+        };
+        runner.on_fill(&fill("oid-1"), &mut executor).await;
+        // Not ours: ignored
+        runner.on_fill(&fill("oid-9"), &mut executor).await;
+
+        let statuses = runner.statuses();
+        let mine = statuses.iter().find(|status| status.id == id).unwrap();
+        let theirs = statuses.iter().find(|status| status.id == other).unwrap();
+        assert_eq!(mine.pnl.position, 1.0);
+        assert_eq!(mine.pnl.fees, 0.05);
+        assert_eq!((theirs.pnl.position, theirs.pnl.fees), (0.0, 0.0));
+    }
+
+    #[tokio::test]
+    async fn test_risk_gate_blocks_before_the_venue() {
+        let tight = RiskLimits { max_order_usd: 50.0, max_position_usd: 1000.0 };
+        let mut runner = StrategyRunner::new(false, tight.clone());
+        let id = runner.add(Box::new(MaCross::new(config())));
+        runner.set_running(id, true);
+        let mut executor = MockExecutor::default();
+
+        let records = replay(&mut runner, &mut executor, &[100.0, 100.0, 100.0, 100.0, 104.0]).await;
+        let orders = places(&records);
+        assert!(matches!(&orders[0].outcome, ActionOutcome::Blocked { reason } if reason.contains("over the $50.00 limit")));
+        assert!(executor.placed.is_empty());
+
+        // Reduce-only always passes; the position limit counts the current position
+        assert!(tight.check(&TradeRequest { reduce_only: true, ..request(600.0) }, 0.0, 100.0).is_ok());
+        assert!(limits().check(&request(300.0), 8.0, 100.0).is_err());
+        assert!(limits().check(&TradeRequest { is_buy: false, ..request(300.0) }, 8.0, 100.0).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_every_action_is_journaled() {
+        let path: PathBuf = std::env::temp_dir()
+            .join(format!("hl_aggregator_strategy_actions_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut runner = StrategyRunner::new(true, limits()).with_log(ActionLog::new(path.clone()));
+        let id = runner.add(Box::new(MaCross::new(config())));
+        runner.set_running(id, true);
+        let mut executor = MockExecutor::default();
+
+        let records = replay(&mut runner, &mut executor, &[100.0, 100.0, 100.0, 100.0, 104.0]).await;
+        let logged: Vec<ActionRecord> = std::fs::read_to_string(&path).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(logged, records);
+        assert!(logged.iter().all(|record| record.strategy_id == id));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    fn quoter_config() -> QuoterConfig {
+        // 20bps wide, $100 quotes, re-quote past 5bps, $500 inventory
+        "hyperliquid,BTC,20,100,5,500".parse().unwrap()
+    }
+
+    fn book_at(mid: f64, time: i64) -> BookTop {
+        BookTop { time, ..book(mid) }
+    }
+
+    fn quoter_fill(is_buy: bool, size: f64, order_id: &str) -> Fill {
+        Fill {
+            exchange: ExchangeId::Hyperliquid,
+            asset: "BTC".to_string(),
+            is_buy,
+            price: 100.0,
+            size,
+            fee: 0.01,
+            time: 1,
+            order_id: order_id.to_string(),
+            fill_id: String::new(),
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn quoted_prices(executor: &MockExecutor, from: usize) -> Vec<(bool, f64)> {
+        executor.placed[from..].iter()
+            .map(|(_, request)| (request.is_buy, request.price.unwrap()))
+            .collect()
+    }
+
+    fn live_quoter() -> (StrategyRunner, uuid::Uuid, MockExecutor) {
+        let mut runner = StrategyRunner::new(false, limits());
+        let id = runner.add(Box::new(SimpleQuoter::new(quoter_config())));
+        runner.set_running(id, true);
+        (runner, id, MockExecutor::default())
+    }
+
+    #[test]
+    fn test_parse_quoter_config() {
+        let config = quoter_config();
+        assert_eq!((config.exchange, config.symbol), (ExchangeId::Hyperliquid, Symbol::perp("BTC")));
+        assert_eq!((config.spread_bps, config.size_usd, config.tolerance_bps, config.max_inventory_usd), (20.0, 100.0, 5.0, 500.0));
+        assert!("hyperliquid,BTC,20,100,5".parse::<QuoterConfig>().is_err());
+        assert!("hyperliquid,BTC,0,100,5,500".parse::<QuoterConfig>().is_err());
+        assert!("hyperliquid,BTC,20,abc,5,500".parse::<QuoterConfig>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quoter_requotes_when_mid_moves_past_tolerance() {
+        let (mut runner, _, mut executor) = live_quoter();
+
+        runner.on_book(book_at(100.0, 0), &mut executor).await;
+        let prices = quoted_prices(&executor, 0);
+        assert!(prices[0].0 && close(prices[0].1, 99.9) && !prices[1].0 && close(prices[1].1, 100.1));
+        assert!(executor.placed.iter().all(|(_, request)| matches!(request.order_type, OrderType::Limit)));
+
+        // Within 5bps: the quotes stay
+        let records = runner.on_book(book_at(100.04, 5_000), &mut executor).await;
+        assert!(records.is_empty());
+
+        // Past it, but inside the throttle: still nothing
+        runner.on_book(book_at(101.0, 1_000), &mut executor).await;
+        assert_eq!(executor.placed.len(), 2);
+
+        // Cancel both, then replace both around the new mid
+        let records = runner.on_book(book_at(101.0, 6_000), &mut executor).await;
+        assert_eq!(executor.cancelled, vec!["oid-1", "oid-2"]);
+        assert!(records[0].action.starts_with("cancel BTC order oid-1"));
+        let prices = quoted_prices(&executor, 2);
+        assert!(close(prices[0].1, 100.899) && close(prices[1].1, 101.101));
+        assert_eq!(runner.statuses()[0].detail.as_deref().map(|detail| detail.starts_with("bid 100.8990  ask 101.1010")), Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_quoter_requotes_after_a_fill() {
+        let (mut runner, id, mut executor) = live_quoter();
+        runner.on_book(book_at(100.0, 0), &mut executor).await;
+
+        runner.on_fill(&quoter_fill(true, 0.5, "oid-1"), &mut executor).await;
+        // Unmoved mid, but the fill forces a re-quote once the throttle allows
+        runner.on_book(book_at(100.0, REQUOTE_INTERVAL_MS - 1), &mut executor).await;
+        assert_eq!(executor.placed.len(), 2);
+        runner.on_book(book_at(100.0, REQUOTE_INTERVAL_MS), &mut executor).await;
+        assert_eq!(executor.placed.len(), 4);
+        assert_eq!(executor.cancelled, vec!["oid-1", "oid-2"]);
+
+        let status = runner.statuses().into_iter().find(|status| status.id == id).unwrap();
+        assert_eq!(status.pnl.position, 0.5);
+        assert!(status.detail.unwrap().contains("inv 0.5000 ($50.00 of $500.00)"));
+    }
+
+    #[tokio::test]
+    async fn test_quoter_skews_and_pulls_on_inventory() {
+        let mut quoter = SimpleQuoter::new(quoter_config());
+        let (bid, ask) = quoter.target(100.0);
+        assert!(close(bid.unwrap(), 99.9) && close(ask.unwrap(), 100.1));
+
+        // Long $250 of $500: both prices shift down by half of half the spread
+        quoter.on_fill(&quoter_fill(true, 2.5, "a"));
+        let (bid, ask) = quoter.target(100.0);
+        assert!(close(bid.unwrap(), 99.85) && close(ask.unwrap(), 100.05));
+
+        // At the limit the bid is pulled; the ask sits on the mid to shed inventory
+        quoter.on_fill(&quoter_fill(true, 2.5, "b"));
+        let (bid, ask) = quoter.target(100.0);
+        assert_eq!(bid, None);
+        assert!(close(ask.unwrap(), 100.0));
+
+        let actions = quoter.on_book(&book_at(100.0, 0));
+        assert!(matches!(&actions[0], StrategyAction::Place { request, .. } if !request.is_buy));
+        assert!(matches!(&actions[1], StrategyAction::Notify(message) if message.contains("inventory limit")));
+        assert_eq!(actions.len(), 2);
+
+        // Short past the limit pulls the ask instead
+        quoter.on_fill(&quoter_fill(false, 10.0, "c"));
+        assert_eq!(quoter.target(100.0).1, None);
+    }
+
+    #[tokio::test]
+    async fn test_stopping_the_quoter_pulls_both_quotes() {
+        let (mut runner, id, mut executor) = live_quoter();
+        runner.on_book(book_at(100.0, 0), &mut executor).await;
+
+        let records = runner.stop(id, &mut executor).await;
+        assert_eq!(executor.cancelled, vec!["oid-1", "oid-2"]);
+        assert_eq!(records.len(), 2);
+        assert!(!runner.statuses()[0].running);
+        assert!(runner.statuses()[0].detail.as_deref().is_some_and(|detail| detail.starts_with("bid -  ask -")));
+
+        // Stopped: no more quotes, and stopping again does nothing
+        runner.on_book(book_at(105.0, 10_000), &mut executor).await;
+        assert!(runner.stop(id, &mut executor).await.is_empty());
+        assert_eq!(executor.placed.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_hold_blocks_new_quotes_but_not_cancels() {
+        let (mut runner, _, mut executor) = live_quoter();
+        runner.on_book(book_at(100.0, 0), &mut executor).await;
+
+        runner.set_hold(Some("dead man's switch not armed".to_string()));
+        let records = runner.on_book(book_at(102.0, 5_000), &mut executor).await;
+        assert_eq!(executor.cancelled.len(), 2);
+        assert_eq!(executor.placed.len(), 2);
+        assert!(records.iter()
+            .filter(|record| record.action.starts_with("buy") || record.action.starts_with("sell"))
+            .all(|record| matches!(&record.outcome, ActionOutcome::Blocked { reason } if reason.contains("dead man's switch"))));
+
+        // Unacknowledged quotes are retried once the hold lifts
+        runner.set_hold(None);
+        runner.on_book(book_at(102.0, 10_000), &mut executor).await;
+        assert_eq!(executor.placed.len(), 4);
+    }
+}
+
+#[cfg(test)]
+mod wallet_overview_tests {
+    use crate::error::{AggregatorError, Result};
+    use async_trait::async_trait;
+    use std::cell::{Cell, RefCell};
+    use crate::trading::hl_account::{HlAccountState, HlMarginSummary};
+    use crate::trading::wallet_overview::{Availability, DydxAccount, EthBalances, WalletOverview, WalletSource};
+
+    // Each component fails while its flag is set and counts its fetches
+    #[derive(Default)]
+    struct MockSource {
+        eth_down: Cell<bool>,
+        hl_down: Cell<bool>,
+        dydx_down: Cell<bool>,
+        calls: RefCell<Vec<&'static str>>,
+    }
+
+    fn outcome<T>(down: bool, value: T) -> Result<Option<T>> {
+        if down {
+            return Err(AggregatorError::Ethereum("429 Too Many Requests".to_string()));
+        }
+        Ok(Some(value))
+    }
+
+    #[async_trait(?Send)]
+    impl WalletSource for MockSource {
+        async fn eth_balances(&self) -> Result<Option<EthBalances>> {
+            self.calls.borrow_mut().push("eth");
+            outcome(self.eth_down.get(), EthBalances { address: "0xabc".to_string(), usdc: 12.5, eth: 0.0042 })
+        }
+
+        async fn hl_account(&self) -> Result<Option<HlAccountState>> {
+            self.calls.borrow_mut().push("hl");
+            let state = HlAccountState {
+                margin_summary: HlMarginSummary { account_value: 1000.0, total_margin_used: 100.0, ..Default::default() },
+                withdrawable: 900.0,
+                ..Default::default()
+            };
+            outcome(self.hl_down.get(), state)
+        }
+
+        async fn dydx_account(&self) -> Result<Option<DydxAccount>> {
+            self.calls.borrow_mut().push("dydx");
+            outcome(self.dydx_down.get(), DydxAccount { address: "dydx1xyz".to_string(), equity: 250.0, free_collateral: 200.0 })
+        }
+    }
+
+    fn lines(overview: &WalletOverview) -> Vec<String> {
+        overview.lines(Some("0xabc".to_string()), Some("dydx1xyz".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_usdc_failure_keeps_the_rest_of_the_screen() {
+        let source = MockSource::default();
+        source.eth_down.set(true);
+        let mut overview = WalletOverview::default();
+        overview.refresh(&source).await;
+
+        let lines = lines(&overview);
+        assert!(lines.contains(&"ETH Address: 0xabc".to_string()));
+        assert!(lines.contains(&"USDC Balance: Ethereum error: 429 Too Many Requests, retrying".to_string()));
+        assert!(lines.contains(&"Hyperliquid Portfolio Value: $1000.00".to_string()));
+        assert!(lines.contains(&"dYdX Balance: $250.00".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("No ETH wallet")));
+    }
+
+    #[tokio::test]
+    async fn test_each_component_fails_independently() {
+        for failing in ["eth", "hl", "dydx"] {
+            let source = MockSource::default();
+            source.eth_down.set(failing == "eth");
+            source.hl_down.set(failing == "hl");
+            source.dydx_down.set(failing == "dydx");
+            let mut overview = WalletOverview::default();
+            overview.refresh(&source).await;
+
+            assert_eq!(matches!(overview.eth, Availability::Failed { .. }), failing == "eth");
+            assert_eq!(matches!(overview.hl, Availability::Failed { .. }), failing == "hl");
+            assert_eq!(matches!(overview.dydx, Availability::Failed { .. }), failing == "dydx");
+            let markers: Vec<String> = lines(&overview).into_iter().filter(|line| line.ends_with("retrying")).collect();
+            assert_eq!(markers.len(), 1, "{}: {:?}", failing, markers);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_fetches_only_failed_components() {
+        let source = MockSource::default();
+        let mut overview = WalletOverview::default();
+        overview.refresh(&source).await;
+        assert!(!overview.has_failures());
+
+        // dYdX goes down: its last balance stays on screen, marked stale
+        source.dydx_down.set(true);
+        overview.refresh(&source).await;
+        assert!(lines(&overview).contains(&"dYdX Balance: $250.00 (stale: Ethereum error: 429 Too Many Requests, retrying)".to_string()));
+
+        source.calls.borrow_mut().clear();
+        overview.retry_failed(&source).await;
+        assert_eq!(*source.calls.borrow(), vec!["dydx"]);
+
+        source.dydx_down.set(false);
+        overview.retry_failed(&source).await;
+        assert!(!overview.has_failures());
+        assert_eq!(overview.dydx.value().map(|account| account.equity), Some(250.0));
+    }
+
+    #[tokio::test]
+    async fn test_missing_wallets() {
+        let mut overview = WalletOverview::default();
+        overview.refresh(&MockSource::default()).await;
+        let lines = overview.lines(None, None);
+        assert_eq!(lines, vec!["No ETH wallet configured".to_string(), "No dYdX wallet configured".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_gas_balance_follows_the_eth_fetch() {
+        let source = MockSource::default();
+        let mut overview = WalletOverview::default();
+        overview.refresh(&source).await;
+        assert!(lines(&overview).contains(&"ETH Balance (gas): 0.004200 ETH".to_string()));
+
+        // A failed refetch keeps showing the last balance
+        source.eth_down.set(true);
+        overview.refresh(&source).await;
+        assert!(lines(&overview).contains(&"ETH Balance (gas): 0.004200 ETH".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod confirmation_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::error::AggregatorError;
+    use crate::test_fixtures::request;
+    use crate::trading::confirmation::{rounded_notional, Confirmation, ConfirmationGate, ConfirmationPolicy, ConfirmationTier, Quote};
+    use crate::trading::{OrderType, TradeRequest};
+
+    const NOW: i64 = 1_700_000_000_000;
+    // BTC at $50,000 with a 0.001 size step: $50 increments
+    const QUOTE: Quote = Quote { price: 50_000.0, size_step: Some(0.001) };
+
+    fn order(usd_value: f64) -> TradeRequest {
+        request(OrderType::Market, true, usd_value)
+    }
+
+    fn gate() -> ConfirmationGate {
+        ConfirmationGate::new(ConfirmationPolicy::default())
+    }
+
+    // Token from a ConfirmationRequired error, checking its tier
+    fn required(result: Result<(), AggregatorError>, expected: ConfirmationTier) -> String {
+        match result {
+            Err(AggregatorError::ConfirmationRequired(required)) if required.tier == expected => required.token,
+            other => panic!("expected {} confirmation, got {:?}", expected, other),
+        }
+    }
+
+    #[test]
+    fn test_policy_tiers() {
+        let policy = ConfirmationPolicy::default();
+        assert_eq!(policy.tier_for(100.0), ConfirmationTier::None);
+        assert_eq!(policy.tier_for(5_000.0), ConfirmationTier::None);
+        assert_eq!(policy.tier_for(5_000.01), ConfirmationTier::Dialog);
+        assert_eq!(policy.tier_for(25_000.0), ConfirmationTier::Dialog);
+        assert_eq!(policy.tier_for(25_001.0), ConfirmationTier::TypedConfirmation);
+        assert_eq!(ConfirmationPolicy::off().tier_for(1e9), ConfirmationTier::None);
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy: ConfirmationPolicy = "25000:typed, $1000:dialog".parse().unwrap();
+        assert_eq!(policy.tier_for(2_000.0), ConfirmationTier::Dialog);
+        assert_eq!(policy.tier_for(30_000.0), ConfirmationTier::TypedConfirmation);
+        assert_eq!("off".parse::<ConfirmationPolicy>().unwrap(), ConfirmationPolicy::off());
+        assert!("5000".parse::<ConfirmationPolicy>().is_err());
+        assert!("5000:maybe".parse::<ConfirmationPolicy>().is_err());
+        assert!("-1:dialog".parse::<ConfirmationPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_uses_post_rounding_notional() {
+        // $5,020 rounds to 0.1 BTC = $5,000: no dialog
+        assert_eq!(rounded_notional(5_020.0, &QUOTE), 5_000.0);
+        assert_eq!(gate().review(&order(5_020.0), &QUOTE), (ConfirmationTier::None, 5_000.0));
+        // $5,030 rounds up to 0.101 BTC = $5,050: dialog
+        assert_eq!(gate().review(&order(5_030.0), &QUOTE).0, ConfirmationTier::Dialog);
+        // No price known: the requested value is all there is
+        assert_eq!(rounded_notional(5_030.0, &Quote { price: 0.0, size_step: None }), 5_030.0);
+    }
+
+    #[test]
+    fn test_small_orders_go_out_unconfirmed() {
+        assert!(gate().check(&ExchangeId::Hyperliquid, &order(1_000.0), &QUOTE, Confirmation::None, NOW).is_ok());
+    }
+
+    #[test]
+    fn test_dialog_tier() {
+        let mut gate = gate();
+        let venue = ExchangeId::Hyperliquid;
+        required(gate.check(&venue, &order(10_000.0), &QUOTE, Confirmation::None, NOW), ConfirmationTier::Dialog);
+        assert!(gate.check(&venue, &order(10_000.0), &QUOTE, Confirmation::Dialog, NOW).is_ok());
+    }
+
+    #[test]
+    fn test_typed_tier() {
+        let mut gate = gate();
+        let venue = ExchangeId::Dydx;
+        // A dialog isn't enough above the typed threshold
+        required(gate.check(&venue, &order(30_000.0), &QUOTE, Confirmation::Dialog, NOW), ConfirmationTier::TypedConfirmation);
+        assert!(matches!(
+            gate.check(&venue, &order(30_000.0), &QUOTE, Confirmation::Typed("3000".to_string()), NOW),
+            Err(AggregatorError::ConfirmationRejected(_))
+        ));
+        assert!(gate.check(&venue, &order(30_000.0), &QUOTE, Confirmation::Typed("$30,000".to_string()), NOW).is_ok());
+    }
+
+    #[test]
+    fn test_token_flow() {
+        let mut gate = gate();
+        let venue = ExchangeId::Hyperliquid;
+
+        // A client that can't prompt gets a token and resubmits with it
+        let token = required(gate.check(&venue, &order(10_000.0), &QUOTE, Confirmation::None, NOW), ConfirmationTier::Dialog);
+        let confirm = Confirmation::Token { token: token.clone(), typed: None };
+        assert!(gate.check(&venue, &order(10_000.0), &QUOTE, confirm.clone(), NOW + 1_000).is_ok());
+
+        // One use only
+        assert!(matches!(gate.check(&venue, &order(10_000.0), &QUOTE, confirm, NOW + 2_000), Err(AggregatorError::ConfirmationRejected(_))));
+    }
+
+    #[test]
+    fn test_typed_token_flow() {
+        let mut gate = gate();
+        let venue = ExchangeId::Hyperliquid;
+        let token = required(gate.check(&venue, &order(30_000.0), &QUOTE, Confirmation::None, NOW), ConfirmationTier::TypedConfirmation);
+
+        // The typed tier needs the amount with the token; a bad attempt burns it
+        let result = gate.check(&venue, &order(30_000.0), &QUOTE, Confirmation::Token { token: token.clone(), typed: None }, NOW);
+        assert!(matches!(result, Err(AggregatorError::ConfirmationRejected(_))));
+        let result = gate.check(&venue, &order(30_000.0), &QUOTE, Confirmation::Token { token, typed: Some("30000".to_string()) }, NOW);
+        assert!(matches!(result, Err(AggregatorError::ConfirmationRejected(_))));
+
+        let token = required(gate.check(&venue, &order(30_000.0), &QUOTE, Confirmation::None, NOW), ConfirmationTier::TypedConfirmation);
+        assert!(gate.check(&venue, &order(30_000.0), &QUOTE, Confirmation::Token { token, typed: Some("30000".to_string()) }, NOW).is_ok());
+    }
+
+    #[test]
+    fn test_token_bound_to_order_and_expires() {
+        let mut gate = gate();
+        let venue = ExchangeId::Hyperliquid;
+
+        let token = required(gate.check(&venue, &order(10_000.0), &QUOTE, Confirmation::None, NOW), ConfirmationTier::Dialog);
+        let confirm = Confirmation::Token { token, typed: None };
+        assert!(gate.check(&venue, &order(12_000.0), &QUOTE, confirm.clone(), NOW).is_err());
+        assert!(gate.check(&ExchangeId::Dydx, &order(10_000.0), &QUOTE, confirm, NOW).is_err());
+
+        let token = required(gate.check(&venue, &order(10_000.0), &QUOTE, Confirmation::None, NOW), ConfirmationTier::Dialog);
+        let late = gate.check(&venue, &order(10_000.0), &QUOTE, Confirmation::Token { token, typed: None }, NOW + 10 * 60 * 1000);
+        assert!(matches!(late, Err(AggregatorError::ConfirmationRejected(_))));
+    }
+}
+
+#[cfg(test)]
+mod amount_tests {
+    use crate::aggregator::symbol::Symbol;
+    use crate::error::AggregatorError;
+    use crate::trading::amount::{parse_amount, parse_usd_value, Amount, AmountContext};
+
+    fn context(symbol: &Symbol) -> AmountContext<'_> {
+        AmountContext { symbol, price: Some(64_000.0), free_collateral: Some(10_000.0), leverage: 1.0 }
+    }
+
+    fn parse(input: &str) -> Result<Amount, AggregatorError> {
+        parse_amount(input, &context(&Symbol::perp("BTC")))
+    }
+
+    // The error text, failing the test when the input parsed
+    fn rejected(input: &str) -> String {
+        match parse(input) {
+            Err(AggregatorError::InvalidAmount(message)) => message,
+            other => panic!("expected '{}' to be rejected, got {:?}", input, other),
+        }
+    }
+
+    #[test]
+    fn test_plain_usd() {
+        assert_eq!(parse("25000").unwrap(), Amount::Usd(25_000.0));
+        assert_eq!(parse("  250.5 ").unwrap(), Amount::Usd(250.5));
+        assert_eq!(parse("$300").unwrap(), Amount::Usd(300.0));
+        assert_eq!(parse("$ 300").unwrap(), Amount::Usd(300.0));
+        assert_eq!(parse(".5").unwrap(), Amount::Usd(0.5));
+        assert_eq!(parse("300usdc").unwrap(), Amount::Usd(300.0));
+        assert_eq!(parse("300 USD").unwrap(), Amount::Usd(300.0));
+    }
+
+    #[test]
+    fn test_thousand_and_million_suffixes() {
+        assert_eq!(parse("25k").unwrap(), Amount::Usd(25_000.0));
+        assert_eq!(parse("25K").unwrap(), Amount::Usd(25_000.0));
+        assert_eq!(parse("$2.5k").unwrap(), Amount::Usd(2_500.0));
+        assert_eq!(parse("1.5m").unwrap(), Amount::Usd(1_500_000.0));
+        assert_eq!(parse("1.5 M").unwrap(), Amount::Usd(1_500_000.0));
+    }
+
+    #[test]
+    fn test_thousands_separators() {
+        assert_eq!(parse("25,000").unwrap(), Amount::Usd(25_000.0));
+        assert_eq!(parse("1,250,000.75").unwrap(), Amount::Usd(1_250_000.75));
+        assert_eq!(parse("25_000").unwrap(), Amount::Usd(25_000.0));
+        assert_eq!(parse("2,500k").unwrap(), Amount::Usd(2_500_000.0));
+    }
+
+    #[test]
+    fn test_ambiguous_commas_rejected() {
+        // European decimals or misplaced separators
+        for input in ["1,5", "25,00", "1,0000", ",500", "1.000,5"] {
+            assert!(rejected(input).contains("ambiguous") || rejected(input).contains("decimal point"), "{}", input);
+        }
+        assert!(rejected("1.2.3").contains("more than one decimal point"));
+    }
+
+    #[test]
+    fn test_base_asset_suffix() {
+        assert_eq!(parse("0.5btc").unwrap(), Amount::Base(0.5));
+        assert_eq!(parse("0.5 BTC").unwrap(), Amount::Base(0.5));
+        let purr = Symbol::spot("PURR", "USDC");
+        assert_eq!(parse_amount("1000purr", &context(&purr)).unwrap(), Amount::Base(1000.0));
+        // Another market's asset is a mistake, not a unit
+        let message = rejected("2eth");
+        assert!(message.contains("'eth'") && message.contains("btc"), "{}", message);
+    }
+
+    #[test]
+    fn test_percent_of_collateral() {
+        assert_eq!(parse("50%").unwrap(), Amount::PercentOfCollateral(50.0));
+        assert_eq!(parse("12.5 %").unwrap(), Amount::PercentOfCollateral(12.5));
+        assert_eq!(parse("100%").unwrap(), Amount::PercentOfCollateral(100.0));
+        assert!(rejected("150%").contains("more than all free collateral"));
+        assert!(rejected("0%").contains("more than zero"));
+    }
+
+    #[test]
+    fn test_mixed_units_rejected() {
+        assert!(rejected("$50%").contains("mixes $"));
+        assert!(rejected("$0.5btc").contains("mixes $"));
+        assert!(rejected("25k%").contains("unknown unit"));
+        assert!(rejected("25kk").contains("unknown unit"));
+        assert!(rejected("1e3").contains("unknown unit"));
+        assert!(rejected("25 k btc").contains("unknown unit"));
+    }
+
+    #[test]
+    fn test_malformed_and_out_of_range() {
+        assert!(rejected("").contains("enter an amount"));
+        assert!(rejected("   ").contains("enter an amount"));
+        assert!(rejected("abc").contains("does not start with a number"));
+        assert!(rejected("k").contains("does not start with a number"));
+        assert!(rejected("$").contains("does not start with a number"));
+        assert!(rejected(".").contains("does not start with a number"));
+        assert!(rejected("-5").contains("positive"));
+        assert!(rejected("$-5k").contains("positive"));
+        assert!(rejected("0").contains("more than zero"));
+        assert!(rejected("0.0btc").contains("more than zero"));
+        assert!(rejected("500m").contains("limit"));
+    }
+
+    #[test]
+    fn test_resolves_to_usd() {
+        let btc = Symbol::perp("BTC");
+        let context = context(&btc);
+        assert_eq!(parse_usd_value("25k", &context).unwrap(), (Amount::Usd(25_000.0), 25_000.0));
+        assert_eq!(parse_usd_value("0.5btc", &context).unwrap().1, 32_000.0);
+        assert_eq!(parse_usd_value("50%", &context).unwrap().1, 5_000.0);
+        // Percent sizes are margin, scaled by leverage
+        let levered = AmountContext { leverage: 3.0, ..context };
+        assert_eq!(parse_usd_value("50%", &levered).unwrap().1, 15_000.0);
+        assert_eq!(Amount::Base(0.5).describe(&context, 32_000.0), "0.5 BTC = $32000.00");
+    }
+
+    #[test]
+    fn test_resolution_needs_price_and_collateral() {
+        let btc = Symbol::perp("BTC");
+        let no_data = AmountContext { symbol: &btc, price: None, free_collateral: None, leverage: 1.0 };
+        // Parsing works without either; resolving explains what is missing
+        assert_eq!(parse_amount("0.5btc", &no_data).unwrap(), Amount::Base(0.5));
+        let message = parse_usd_value("0.5btc", &no_data).unwrap_err().to_string();
+        assert!(message.contains("no BTC price"), "{}", message);
+        let message = parse_usd_value("50%", &no_data).unwrap_err().to_string();
+        assert!(message.contains("free collateral not loaded"), "{}", message);
+        let broke = AmountContext { free_collateral: Some(0.0), ..no_data };
+        assert!(parse_usd_value("50%", &broke).unwrap_err().to_string().contains("no free collateral"));
+        // A resolved size can still be absurd
+        let pricey = AmountContext { price: Some(64_000.0), ..no_data };
+        assert!(parse_usd_value("5000btc", &pricey).unwrap_err().to_string().contains("limit"));
+        assert_eq!(parse_usd_value("25k", &no_data).unwrap().1, 25_000.0);
+    }
+}
+
+#[cfg(test)]
+mod farm_tests {
+    use std::path::PathBuf;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::trading::farm::{parse_dydx_funding_payments, parse_hl_funding_payments, FarmEvent, FarmLeg, FarmTracker, FundingPayment};
+    use crate::trading::journal::{Journal, JournalEntry, TradeSnapshot};
+    use crate::trading::positions::episodes::Fill;
+    use crate::trading::{OrderType, TradeRequest};
+
+    const START: i64 = 1_700_000_000_000;
+    const HOUR: i64 = 60 * 60 * 1000;
+    const DAY: i64 = 24 * HOUR;
+
+    fn btc() -> Symbol {
+        Symbol::perp("BTC")
+    }
+
+    // Long 1 BTC on Hyperliquid against short 1 BTC on dYdX, $25 to open each
+    fn opened() -> FarmEvent {
+        FarmEvent::Opened {
+            symbol: btc(),
+            time: START,
+            legs: vec![
+                FarmLeg { exchange: ExchangeId::Hyperliquid, size: 1.0, entry_price: 50_000.0, open_fees: 25.0 },
+                FarmLeg { exchange: ExchangeId::Dydx, size: -1.0, entry_price: 50_000.0, open_fees: 25.0 },
+            ],
+        }
+    }
+
+    fn payment(exchange: ExchangeId, asset: &str, time: i64, amount: f64) -> FundingPayment {
+        FundingPayment { exchange, asset: asset.to_string(), time, amount, rate: None }
+    }
+
+    // Every hour for `hours`: the short leg receives $2, the long leg pays $1
+    fn hourly(hours: i64) -> Vec<FundingPayment> {
+        (1..=hours)
+            .flat_map(|hour| [
+                payment(ExchangeId::Dydx, "BTC-USD", START + hour * HOUR, 2.0),
+                payment(ExchangeId::Hyperliquid, "BTC", START + hour * HOUR, -1.0),
+            ])
+            .collect()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn test_apr_from_synthetic_payments() {
+        let mut tracker = FarmTracker::replay(&[opened()]);
+        assert_eq!(tracker.record_payments(&hourly(240)).len(), 480);
+
+        let report = tracker.report(&btc(), &[], START + 10 * DAY).unwrap();
+        assert_eq!(report.payments, 480);
+        assert!(close(report.notional, 50_000.0));
+        assert!(close(report.funding, 240.0));
+        assert!(close(report.fees, 50.0));
+        // $240 over 10 days on $50k, then net of the $50 in fees
+        assert!(close(report.funding_apr().unwrap(), 17.52));
+        assert!(close(report.realized_apr().unwrap(), 13.87));
+        assert_eq!(report.days_to_breakeven(), Some(0.0));
+    }
+
+    #[test]
+    fn test_days_to_breakeven_at_current_rate() {
+        let mut tracker = FarmTracker::replay(&[opened()]);
+        tracker.record_payments(&hourly(24));
+        let report = tracker.report(&btc(), &[], START + DAY).unwrap();
+        // $24 a day against $50 of fees: $26 left to earn
+        assert!(close(report.days_to_breakeven().unwrap(), 26.0 / 24.0));
+        assert!(report.realized_apr().unwrap() < 0.0);
+    }
+
+    #[test]
+    fn test_paying_funding_never_breaks_even() {
+        let mut tracker = FarmTracker::replay(&[opened()]);
+        let paying: Vec<_> = (1..=48).map(|hour| payment(ExchangeId::Dydx, "BTC-USD", START + hour * HOUR, -0.5)).collect();
+        tracker.record_payments(&paying);
+        let report = tracker.report(&btc(), &[], START + 2 * DAY).unwrap();
+        assert!(close(report.funding, -24.0));
+        assert_eq!(report.days_to_breakeven(), None);
+        assert!(report.funding_apr().unwrap() < 0.0);
+    }
+
+    #[test]
+    fn test_payments_counted_once_and_only_for_the_farm() {
+        let mut tracker = FarmTracker::replay(&[opened()]);
+        let mut payments = hourly(3);
+        payments.extend([
+            // Before the farm opened
+            payment(ExchangeId::Dydx, "BTC-USD", START - HOUR, 100.0),
+            // Another market on a leg venue
+            payment(ExchangeId::Hyperliquid, "ETH", START + HOUR, 100.0),
+        ]);
+        assert_eq!(tracker.record_payments(&payments).len(), 6);
+        // Venues return overlapping history on every poll
+        assert!(tracker.record_payments(&hourly(4)).len() == 2);
+        let report = tracker.report(&btc(), &[], START + DAY).unwrap();
+        assert!(close(report.funding, 4.0));
+        assert_eq!(report.payments, 8);
+    }
+
+    #[test]
+    fn test_fees_from_fills_after_opening() {
+        let mut tracker = FarmTracker::replay(&[opened()]);
+        let fill = |time: i64, fee: f64| Fill {
+            exchange: ExchangeId::Hyperliquid,
+            asset: "BTC".to_string(),
+            is_buy: true,
+            price: 50_000.0,
+            size: 0.1,
+            fee,
+            time,
+            order_id: String::new(),
+            fill_id: String::new(),
+        };
+        // The opening fill is already in the legs' fees
+        let fills = vec![fill(START - HOUR, 25.0), fill(START + HOUR, 2.5)];
+        assert_eq!(tracker.record_fills(&fills).len(), 1);
+        assert!(tracker.record_fills(&fills).is_empty());
+        assert!(close(tracker.report(&btc(), &[], START + DAY).unwrap().fees, 52.5));
+    }
+
+    #[test]
+    fn test_drift_between_legs() {
+        let tracker = FarmTracker::replay(&[opened()]);
+        let moved = [(ExchangeId::Hyperliquid, 51_000.0), (ExchangeId::Dydx, 51_000.0)];
+        assert!(close(tracker.report(&btc(), &moved, START + DAY).unwrap().drift, 0.0));
+        // The short's venue trades $100 rich
+        let apart = [(ExchangeId::Hyperliquid, 51_000.0), (ExchangeId::Dydx, 51_100.0)];
+        let report = tracker.report(&btc(), &apart, START + DAY).unwrap();
+        assert!(close(report.drift, -100.0));
+        assert!(close(report.net_pnl(), -150.0));
+    }
+
+    #[test]
+    fn test_apr_needs_some_history() {
+        let tracker = FarmTracker::replay(&[opened()]);
+        let report = tracker.report(&btc(), &[], START + 60_000).unwrap();
+        assert_eq!(report.realized_apr(), None);
+        assert!(tracker.report(&Symbol::perp("ETH"), &[], START).is_none());
+    }
+
+    #[test]
+    fn test_closed_farm_is_dropped() {
+        let tracker = FarmTracker::replay(&[opened(), FarmEvent::Closed { symbol: btc(), time: START + DAY }]);
+        assert!(tracker.is_empty());
+        assert!(tracker.report(&btc(), &[], START + DAY).is_none());
+    }
+
+    #[test]
+    fn test_farm_survives_restart_via_journal() {
+        let path: PathBuf = std::env::temp_dir()
+            .join(format!("hl_aggregator_farm_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut tracker = FarmTracker::default();
+        {
+            let mut journal = Journal::open(path.clone()).unwrap();
+            let request = TradeRequest {
+                asset: btc(),
+                is_buy: true,
+                order_type: OrderType::Market,
+                usd_value: 50_000.0,
+                price: None,
+                leverage: 1,
+                cross_margin: None,
+                reduce_only: false,
+                strategy_id: None,
+                max_slippage_bps: None,
+                post_only: false,
+                time_in_force: None,
+                good_til_secs: None,
+                cloid: None,
+            };
+            let result = Ok(("ok".to_string(), "1".to_string()));
+            journal.append(&JournalEntry::new(&ExchangeId::Hyperliquid, request, &result, TradeSnapshot::default())).unwrap();
+            tracker.apply(&opened());
+            journal.append_farm(&opened()).unwrap();
+            for event in tracker.record_payments(&hourly(24)) {
+                journal.append_farm(&event).unwrap();
+            }
+        }
+
+        let journal = Journal::open(path.clone()).unwrap();
+        // Trades and farm events share the file without mixing
+        assert_eq!(journal.entries().unwrap().len(), 1);
+        let restored = FarmTracker::replay(&journal.farm_events().unwrap());
+        assert_eq!(restored.report(&btc(), &[], START + DAY), tracker.report(&btc(), &[], START + DAY));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("lock"));
+    }
+
+    #[test]
+    fn test_parse_venue_payments() {
+        let hl = r#"[{"time":1700003600000,"hash":"0x0","delta":{"type":"funding","coin":"BTC","usdc":"-1.25","szi":"1.0","fundingRate":"0.000025"}}]"#;
+        let rated = |payment: FundingPayment| FundingPayment { rate: Some(0.000025), ..payment };
+        assert_eq!(parse_hl_funding_payments(hl).unwrap(), vec![rated(payment(ExchangeId::Hyperliquid, "BTC", 1_700_003_600_000, -1.25))]);
+        let dydx = r#"{"fundingPayments":[{"createdAt":"2023-11-14T23:13:20.000Z","createdAtHeight":"1","perpetualId":"0","ticker":"BTC-USD","oraclePrice":"50000","size":"-1","side":"SHORT","rate":"0.000025","payment":"1.25","subaccountNumber":"0"}]}"#;
+        assert_eq!(parse_dydx_funding_payments(dydx).unwrap(), vec![rated(payment(ExchangeId::Dydx, "BTC-USD", 1_700_003_600_000, 1.25))]);
+    }
+}
+
+#[cfg(test)]
+mod delisting_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::metadata::Delisting;
+    use crate::test_fixtures;
+    use crate::trading::delisting::delisted_exposures;
+    use crate::trading::orders::Order;
+    use crate::trading::positions::Position;
+
+    fn position(exchange: ExchangeId, asset: &str, size: f64) -> Position {
+        Position {
+            exchange,
+            asset: asset.to_string(),
+            size,
+            entry_price: Some(1.0),
+            liquidation_price: None,
+            unrealized_pnl: 0.0,
+            margin_used: None,
+            leverage: None,
+            roe: None,
+            side: String::new(),
+        }
+    }
+
+    fn order(exchange: ExchangeId, asset: &str) -> Order {
+        Order { asset: asset.to_string(), size: 10.0, price: 1.0, ..test_fixtures::order(exchange, "1") }
+    }
+
+    #[test]
+    fn test_exposure_only_for_held_markets_on_that_venue() {
+        let delistings = vec![
+            (ExchangeId::Dydx, Delisting { base: "XYZ".to_string(), settlement_price: Some(0.5) }),
+            (ExchangeId::Hyperliquid, Delisting { base: "OLD".to_string(), settlement_price: None }),
+        ];
+        let positions = vec![
+            position(ExchangeId::Dydx, "XYZ-USD", -20.0),
+            // Same market on the venue that still lists it
+            position(ExchangeId::Hyperliquid, "XYZ", 5.0),
+        ];
+        let orders = vec![order(ExchangeId::Dydx, "XYZ-USD"), order(ExchangeId::Dydx, "BTC-USD")];
+
+        let exposures = delisted_exposures(&delistings, &positions, &orders);
+        assert_eq!(exposures.len(), 1);
+        assert_eq!(exposures[0].position_size, -20.0);
+        assert_eq!(exposures[0].open_orders, 1);
+        assert_eq!(
+            exposures[0].describe(),
+            "DELISTED: dYdX delisted XYZ, settled at $0.5000; you hold a -20 XYZ position and 1 open order(s)"
+        );
+    }
+
+    #[test]
+    fn test_open_order_alone_is_exposure() {
+        let delistings = vec![(ExchangeId::Hyperliquid, Delisting { base: "OLD".to_string(), settlement_price: None })];
+        let exposures = delisted_exposures(&delistings, &[], &[order(ExchangeId::Hyperliquid, "OLD")]);
+        assert_eq!(exposures[0].describe(), "DELISTED: Hyperliquid delisted OLD; you hold 1 open order(s)");
+        assert!(delisted_exposures(&delistings, &[], &[]).is_empty());
+    }
+}
+
+// Fills shared by the episode and attribution tests, so both stay on the
+// same realized-PnL basis and sign conventions
+#[cfg(test)]
+mod pnl_fixtures {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::test_fixtures;
+    use crate::trading::farm::FundingPayment;
+    use crate::trading::positions::episodes::Fill;
+
+    pub const T0: i64 = 1_700_000_000_000;
+    pub const DAY: i64 = 24 * 60 * 60 * 1000;
+    // BTC on Hyperliquid: +10 then -5; ETH on dYdX: short for +40
+    pub const REALIZED: f64 = 45.0;
+    pub const FEES: f64 = 1.5;
+    pub const FUNDING: f64 = 2.25;
+
+    // Every fill pays the same fee
+    fn fill(exchange: ExchangeId, asset: &str, is_buy: bool, price: f64, size: f64, time: i64, order_id: &str) -> Fill {
+        Fill { asset: asset.to_string(), fee: 0.25, order_id: order_id.to_string(), ..test_fixtures::fill(exchange, is_buy, price, size, time) }
+    }
+
+    pub fn fills() -> Vec<Fill> {
+        vec![
+            fill(ExchangeId::Hyperliquid, "BTC", true, 100.0, 1.0, T0, "1"),
+            fill(ExchangeId::Hyperliquid, "BTC", false, 110.0, 1.0, T0 + DAY, "2"),
+            fill(ExchangeId::Hyperliquid, "BTC", true, 105.0, 1.0, T0 + 10 * DAY, "3"),
+            fill(ExchangeId::Hyperliquid, "BTC", false, 100.0, 1.0, T0 + 11 * DAY, "4"),
+            fill(ExchangeId::Dydx, "ETH-USD", false, 2000.0, 0.2, T0 + 2 * DAY, "5"),
+            fill(ExchangeId::Dydx, "ETH-USD", true, 1800.0, 0.2, T0 + 12 * DAY, "6"),
+        ]
+    }
+
+    pub fn payments() -> Vec<FundingPayment> {
+        vec![
+            FundingPayment { exchange: ExchangeId::Dydx, asset: "ETH-USD".to_string(), time: T0 + 3 * DAY, amount: 3.0, rate: None },
+            FundingPayment { exchange: ExchangeId::Hyperliquid, asset: "BTC".to_string(), time: T0 + 10 * DAY + 1, amount: -0.75, rate: None },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod pnl_tests {
+    use uuid::Uuid;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::trading::journal::{JournalEntry, TradeSnapshot};
+    use crate::trading::pnl::{attribution, parse_group_by, pnl_entries, GroupBy, PnlRange};
+    use crate::trading::strategy::{short_id, StrategyLegs};
+    use crate::trading::{OrderType, TradeRequest};
+    use super::pnl_fixtures::{fills, payments, DAY, FEES, FUNDING, REALIZED, T0};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn all() -> PnlRange {
+        PnlRange { start: i64::MIN, end: i64::MAX }
+    }
+
+    // The ETH short was placed by a strategy
+    fn legs(strategy: Uuid) -> StrategyLegs {
+        let request = TradeRequest {
+            asset: "ETH".parse().unwrap(),
+            is_buy: false,
+            order_type: OrderType::Market,
+            usd_value: 400.0,
+            price: None,
+            leverage: 1,
+            cross_margin: None,
+            reduce_only: false,
+            strategy_id: Some(strategy),
+            max_slippage_bps: None,
+            post_only: false,
+            time_in_force: None,
+            good_til_secs: None,
+            cloid: None,
+        };
+        let accepted = |id: &str| -> crate::error::Result<(String, String)> { Ok(("ok".to_string(), id.to_string())) };
+        StrategyLegs::from_journal(&[
+            JournalEntry::new(&ExchangeId::Dydx, request.clone(), &accepted("5"), TradeSnapshot::default()),
+            JournalEntry::new(&ExchangeId::Dydx, TradeRequest { is_buy: true, ..request }, &accepted("6"), TradeSnapshot::default()),
+        ])
+    }
+
+    #[test]
+    fn test_total_matches_realized_pnl_module() {
+        let entries = pnl_entries(&fills(), &payments(), &StrategyLegs::default());
+        let report = attribution(&entries, all(), &[]);
+        assert!(close(report.total.price, REALIZED));
+        // Fees are negative contributions, funding keeps the venue's sign
+        assert!(close(report.total.fees, -FEES));
+        assert!(close(report.total.funding, FUNDING));
+        assert!(close(report.total.net(), REALIZED - FEES + FUNDING));
+        assert!(report.total.children.is_empty());
+    }
+
+    #[test]
+    fn test_group_by_exchange_then_category() {
+        let entries = pnl_entries(&fills(), &payments(), &StrategyLegs::default());
+        let report = attribution(&entries, all(), &[GroupBy::Exchange, GroupBy::Category]);
+        let keys: Vec<&str> = report.total.children.iter().map(|node| node.key.as_str()).collect();
+        // Biggest contributor first
+        assert_eq!(keys, vec!["dYdX", "Hyperliquid"]);
+
+        let dydx = &report.total.children[0];
+        assert!(close(dydx.price, 40.0));
+        assert!(close(dydx.funding, 3.0));
+        assert!(close(dydx.fees, -0.5));
+        let categories: Vec<(&str, f64)> = dydx.children.iter().map(|node| (node.key.as_str(), node.net())).collect();
+        assert_eq!(categories, vec![("price", 40.0), ("funding", 3.0), ("fees", -0.5)]);
+
+        let hl = report.total.at(&[1]).unwrap();
+        assert!(close(hl.net(), 5.0 - 1.0 - 0.75));
+        assert_eq!(report.total.rows().len(), 2 + 3 + 3);
+    }
+
+    #[test]
+    fn test_assets_merge_across_venues_and_strategy_tags() {
+        let strategy = Uuid::new_v4();
+        let entries = pnl_entries(&fills(), &payments(), &legs(strategy));
+        let by_asset = attribution(&entries, all(), &[GroupBy::Asset]);
+        let keys: Vec<&str> = by_asset.total.children.iter().map(|node| node.key.as_str()).collect();
+        assert_eq!(keys, vec!["ETH", "BTC"]);
+
+        let by_strategy = attribution(&entries, all(), &[GroupBy::Strategy]);
+        let tagged = by_strategy.total.children.iter().find(|node| node.key == short_id(strategy)).unwrap();
+        assert!(close(tagged.price, 40.0));
+        assert!(close(tagged.fees, -0.5));
+        // Funding isn't settled per order, so it stays untagged
+        assert_eq!(tagged.funding, 0.0);
+        let manual = by_strategy.total.children.iter().find(|node| node.key == "manual").unwrap();
+        assert!(close(manual.funding, FUNDING));
+    }
+
+    #[test]
+    fn test_range_keeps_entry_basis_from_earlier_fills() {
+        let entries = pnl_entries(&fills(), &payments(), &StrategyLegs::default());
+        // Only the second BTC round trip and the ETH close fall in range
+        let range = PnlRange { start: T0 + 10 * DAY + 1, end: T0 + 20 * DAY };
+        let report = attribution(&entries, range, &[GroupBy::Asset]);
+        assert!(close(report.total.price, -5.0 + 40.0));
+        assert!(close(report.total.fees, -0.5));
+        assert!(close(report.total.funding, -0.75));
+    }
+
+    #[test]
+    fn test_parse_range_and_grouping() {
+        let now = T0;
+        assert_eq!(PnlRange::parse("30d", now).unwrap().start, now - 30 * DAY);
+        assert_eq!(PnlRange::parse("24h", now).unwrap().start, now - DAY);
+        assert_eq!(PnlRange::parse("all", now).unwrap().start, i64::MIN);
+        // 2023-11-14T22:13:20Z; the month started on the 1st
+        assert_eq!(PnlRange::parse("mtd", now).unwrap().start, 1_698_796_800_000);
+        assert!(PnlRange::parse("0d", now).is_err());
+        assert!(PnlRange::parse("week", now).is_err());
+        assert!(PnlRange::parse("30d", now).unwrap().contains(now));
+
+        assert_eq!(parse_group_by("asset,Strategy").unwrap(), vec![GroupBy::Asset, GroupBy::Strategy]);
+        assert!(parse_group_by("asset,asset").is_err());
+        assert!(parse_group_by("desk").is_err());
+    }
+
+    #[test]
+    fn test_report_serializes_for_json_output() {
+        let entries = pnl_entries(&fills(), &[], &StrategyLegs::default());
+        let report = attribution(&entries, all(), &[GroupBy::Category]);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["group_by"][0], "category");
+        assert_eq!(json["total"]["children"][0]["key"], "price");
+    }
+}
+
+#[cfg(test)]
+mod best_execution_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::types::OrderBook;
+    use crate::error::AggregatorError;
+    use crate::test_fixtures;
+    use crate::trading::best_execution::choose_venue;
+    use crate::trading::{OrderType, TradeRequest};
+
+    const NOW: u64 = 1_700_000_000_000;
+    const MAX_AGE: u64 = 3_000;
+
+    fn book(exchange: ExchangeId, bids: &[(f64, f64)], asks: &[(f64, f64)], age_ms: u64) -> Result<OrderBook, String> {
+        Ok(test_fixtures::book(exchange, bids, asks, NOW - age_ms))
+    }
+
+    fn request(is_buy: bool, usd_value: f64) -> TradeRequest {
+        test_fixtures::request(OrderType::Market, is_buy, usd_value)
+    }
+
+    // Hyperliquid has the better touch, dYdX the deeper book
+    fn books() -> Vec<(ExchangeId, Result<OrderBook, String>)> {
+        vec![
+            (ExchangeId::Hyperliquid, book(ExchangeId::Hyperliquid, &[(100.0, 1.0), (98.0, 10.0)], &[(100.5, 1.0), (102.0, 10.0)], 100)),
+            (ExchangeId::Dydx, book(ExchangeId::Dydx, &[(99.5, 20.0)], &[(101.0, 20.0)], 100)),
+        ]
+    }
+
+    #[test]
+    fn test_small_order_takes_the_better_touch() {
+        let buy = choose_venue(&request(true, 50.0), books(), MAX_AGE, NOW).unwrap();
+        assert_eq!(buy.exchange, ExchangeId::Hyperliquid);
+        assert!((buy.estimate.avg_price - 100.5).abs() < 1e-9);
+        assert_eq!(buy.considered.len(), 2);
+
+        let sell = choose_venue(&request(false, 50.0), books(), MAX_AGE, NOW).unwrap();
+        assert_eq!(sell.exchange, ExchangeId::Hyperliquid);
+        assert!((sell.estimate.avg_price - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_large_order_goes_where_the_average_is_better() {
+        // $500 walks past Hyperliquid's thin touch into its worse second level
+        let buy = choose_venue(&request(true, 500.0), books(), MAX_AGE, NOW).unwrap();
+        assert_eq!(buy.exchange, ExchangeId::Dydx);
+        assert!((buy.estimate.avg_price - 101.0).abs() < 1e-9);
+        assert!(buy.considered[1].1.avg_price > 101.5);
+
+        let sell = choose_venue(&request(false, 500.0), books(), MAX_AGE, NOW).unwrap();
+        assert_eq!(sell.exchange, ExchangeId::Dydx);
+        assert!(sell.describe().starts_with("Routing to dYdX"));
+    }
+
+    #[test]
+    fn test_thin_and_stale_books_are_excluded() {
+        // Only dYdX can absorb $1500; Hyperliquid holds $1120.50 of asks
+        let decision = choose_venue(&request(true, 1500.0), books(), MAX_AGE, NOW).unwrap();
+        assert_eq!(decision.exchange, ExchangeId::Dydx);
+        assert_eq!(decision.excluded.len(), 1);
+        assert!(decision.excluded[0].1.contains("not enough depth"));
+
+        // A stale book is skipped even when its price is better
+        let mut books = books();
+        books[1] = (ExchangeId::Dydx, book(ExchangeId::Dydx, &[(99.5, 20.0)], &[(90.0, 20.0)], MAX_AGE + 1));
+        let decision = choose_venue(&request(true, 50.0), books, MAX_AGE, NOW).unwrap();
+        assert_eq!(decision.exchange, ExchangeId::Hyperliquid);
+        assert!(decision.excluded[0].1.starts_with("stale"));
+    }
+
+    #[test]
+    fn test_no_qualifying_venue_is_an_error() {
+        let books = vec![
+            (ExchangeId::Hyperliquid, Err("connection refused".to_string())),
+            (ExchangeId::Dydx, book(ExchangeId::Dydx, &[(99.5, 1.0)], &[(101.0, 1.0)], 100)),
+        ];
+        let error = choose_venue(&request(true, 5000.0), books, MAX_AGE, NOW).unwrap_err();
+        assert!(matches!(&error, AggregatorError::NoExecutionVenue(refusal) if refusal.symbol == "BTC"));
+        let message = error.to_string();
+        assert!(message.contains("Hyperliquid: connection refused") && message.contains("dYdX: not enough depth"));
+    }
+}
+
+#[cfg(test)]
+mod duplicate_tests {
+    use std::time::Duration;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::error::AggregatorError;
+    use crate::trading::duplicates::DuplicateGuard;
+    use crate::trading::{OrderType, TradeRequest};
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    fn limit(is_buy: bool, usd_value: f64, price: f64) -> TradeRequest {
+        TradeRequest {
+            asset: Symbol::perp("BTC"),
+            is_buy,
+            order_type: OrderType::Limit,
+            usd_value,
+            price: Some(price),
+            leverage: 1,
+            cross_margin: None,
+            reduce_only: false,
+            strategy_id: None,
+            max_slippage_bps: None,
+            post_only: false,
+            time_in_force: None,
+            good_til_secs: None,
+            cloid: None,
+        }
+    }
+
+    #[test]
+    fn test_identical_request_within_window_is_refused() {
+        let mut guard = DuplicateGuard::new(Duration::from_secs(5));
+        let request = limit(true, 100.0, 50_000.0);
+        let original = guard.check(&ExchangeId::Hyperliquid, &request, false, NOW).unwrap();
+
+        match guard.check(&ExchangeId::Hyperliquid, &request, false, NOW + 1_200) {
+            Err(AggregatorError::PossibleDuplicate { client_id, age_ms }) => {
+                assert_eq!(client_id, original);
+                assert_eq!(age_ms, 1_200);
+            }
+            other => panic!("expected a duplicate, got {:?}", other),
+        }
+        assert!(guard.find(&ExchangeId::Hyperliquid, &request, NOW + 4_999).is_err());
+        // The refused attempt didn't restart the window
+        assert!(guard.check(&ExchangeId::Hyperliquid, &request, false, NOW + 5_000).is_ok());
+    }
+
+    #[test]
+    fn test_force_overrides() {
+        let mut guard = DuplicateGuard::default();
+        let request = limit(false, 250.0, 3_000.0);
+        let first = guard.check(&ExchangeId::Dydx, &request, false, NOW).unwrap();
+        let forced = guard.check(&ExchangeId::Dydx, &request, true, NOW + 100).unwrap();
+        assert_ne!(first, forced);
+        // A further resend is measured against the forced one
+        match guard.check(&ExchangeId::Dydx, &request, false, NOW + 200) {
+            Err(AggregatorError::PossibleDuplicate { client_id, age_ms }) => assert_eq!((client_id, age_ms), (forced, 100)),
+            other => panic!("expected a duplicate, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_similar_orders_are_not_blocked() {
+        let mut guard = DuplicateGuard::default();
+        let request = limit(true, 100.0, 50_000.0);
+        guard.check(&ExchangeId::Hyperliquid, &request, false, NOW).unwrap();
+
+        let mut market = request.clone();
+        market.order_type = OrderType::Market;
+        let mut other_asset = request.clone();
+        other_asset.asset = Symbol::perp("ETH");
+        let mut stop = market.clone();
+        stop.order_type = OrderType::StopMarket { trigger_price: 50_000.0 };
+        let mut take_profit = market.clone();
+        take_profit.order_type = OrderType::TakeProfit { trigger_price: 50_000.0 };
+        let similar = [
+            (ExchangeId::Dydx, request.clone()),
+            (ExchangeId::Hyperliquid, limit(false, 100.0, 50_000.0)),
+            (ExchangeId::Hyperliquid, limit(true, 100.01, 50_000.0)),
+            (ExchangeId::Hyperliquid, limit(true, 100.0, 50_001.0)),
+            (ExchangeId::Hyperliquid, market),
+            (ExchangeId::Hyperliquid, other_asset),
+            (ExchangeId::Hyperliquid, stop),
+            (ExchangeId::Hyperliquid, take_profit),
+        ];
+        for (exchange, request) in similar {
+            assert!(guard.check(&exchange, &request, false, NOW + 10).is_ok(), "{} {:?}", exchange, request);
+        }
+    }
+
+    #[test]
+    fn test_entries_evicted_and_forgotten() {
+        let mut guard = DuplicateGuard::new(Duration::from_secs(5));
+        let first = guard.check(&ExchangeId::Hyperliquid, &limit(true, 100.0, 1.0), false, NOW).unwrap();
+        guard.check(&ExchangeId::Hyperliquid, &limit(true, 100.0, 2.0), false, NOW + 3_000).unwrap();
+        assert_eq!(guard.len(), 2);
+
+        guard.check(&ExchangeId::Hyperliquid, &limit(true, 100.0, 3.0), false, NOW + 6_000).unwrap();
+        assert_eq!(guard.len(), 2);
+
+        // A rejected order can be resent straight away
+        let rejected = guard.check(&ExchangeId::Hyperliquid, &limit(true, 100.0, 4.0), false, NOW + 6_000).unwrap();
+        guard.forget(&rejected);
+        guard.forget(&first);
+        assert_eq!(guard.len(), 2);
+        assert!(guard.check(&ExchangeId::Hyperliquid, &limit(true, 100.0, 4.0), false, NOW + 6_001).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod live_pnl_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::trading::farm::FundingPayment;
+    use crate::risk::cross_liquidation_price;
+    use crate::trading::positions::{live_pnl, near_liquidation, partial_close_size, position_funding, set_cross_liquidation_prices, LivePnl, Position};
+
+    fn position(exchange: ExchangeId, size: f64, side: &str, leverage: Option<u32>) -> Position {
+        Position {
+            exchange,
+            asset: "ETH".to_string(),
+            size,
+            entry_price: Some(2_000.0),
+            liquidation_price: None,
+            unrealized_pnl: 7.0,
+            margin_used: None,
+            leverage,
+            roe: Some(0.01),
+            side: side.to_string(),
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_long_recomputed_at_mark() {
+        let live = live_pnl(&position(ExchangeId::Hyperliquid, 2.0, "", Some(10)), Some(2_050.0));
+        assert!(live.local);
+        assert!(close(live.unrealized_pnl, 100.0));
+        // $100 on $400 of initial margin
+        assert!(close(live.roe.unwrap(), 0.25));
+    }
+
+    #[test]
+    fn test_short_recomputed_at_mark() {
+        // Hyperliquid signs the size
+        let hl = live_pnl(&position(ExchangeId::Hyperliquid, -2.0, "", Some(5)), Some(2_050.0));
+        assert!(close(hl.unrealized_pnl, -100.0));
+        assert!(close(hl.roe.unwrap(), -0.125));
+
+        // dYdX carries the side separately and no leverage
+        let dydx = live_pnl(&position(ExchangeId::Dydx, 2.0, "Short", None), Some(1_950.0));
+        assert!(close(dydx.unrealized_pnl, 100.0));
+        assert_eq!(dydx.roe, None);
+        assert!(dydx.local);
+    }
+
+    #[test]
+    fn test_missing_mark_keeps_venue_figures() {
+        let venue = LivePnl { unrealized_pnl: 7.0, roe: Some(0.01), local: false };
+        let position = position(ExchangeId::Hyperliquid, 2.0, "", Some(10));
+        assert_eq!(live_pnl(&position, None), venue);
+        assert_eq!(live_pnl(&position, Some(0.0)), venue);
+
+        let mut no_entry = position.clone();
+        no_entry.entry_price = None;
+        assert_eq!(live_pnl(&no_entry, Some(2_050.0)), venue);
+    }
+
+    #[test]
+    fn test_position_funding() {
+        let payment = |asset: &str, time: i64, amount: f64| FundingPayment { exchange: ExchangeId::Hyperliquid, asset: asset.to_string(), time, amount, rate: None };
+        // Before the open and on another market don't count
+        let payments = [payment("ETH", 1_000, -5.0), payment("BTC", 3_000, -5.0), payment("ETH", 3_000, -1.0), payment("ETH", 4_000, 0.25)];
+        let long = position(ExchangeId::Hyperliquid, 2.0, "", None);
+
+        let funding = position_funding(&long, Some(2_000), &payments, Some(0.0001), Some(2_500.0));
+        assert_eq!(funding.so_far, Some(-0.75));
+        // 2 ETH at $2,500 paying 0.01% an hour
+        assert!(close(funding.projected_daily.unwrap(), -12.0));
+
+        // A short receives the same rate; falls back to the entry price
+        let short = position(ExchangeId::Hyperliquid, -2.0, "", None);
+        let funding = position_funding(&short, None, &payments, Some(0.0001), None);
+        assert_eq!(funding.so_far, None);
+        assert!(close(funding.projected_daily.unwrap(), 9.6));
+        assert_eq!(position_funding(&short, None, &payments, None, None).projected_daily, None);
+    }
+
+    #[test]
+    fn test_cross_liquidation_prices_count_other_positions() {
+        // ETH marked at 2,003.50 and BTC at 1,993 from their entries and PnL
+        let mut btc = position(ExchangeId::Dydx, 1.0, "Short", None);
+        btc.asset = "BTC".to_string();
+        let mut positions = vec![
+            position(ExchangeId::Dydx, 2.0, "Long", None),
+            btc,
+            position(ExchangeId::Hyperliquid, 2.0, "", Some(10)),
+        ];
+        let mmf = |position: &Position| Some(if position.asset == "ETH" { 0.05 } else { 0.03 });
+        set_cross_liquidation_prices(&mut positions, &ExchangeId::Dydx, 1_000.0, mmf);
+
+        let eth = cross_liquidation_price(2.0, 2_003.5, 0.05, 1_000.0, 1_993.0 * 0.03).unwrap();
+        let btc = cross_liquidation_price(-1.0, 1_993.0, 0.03, 1_000.0, 2.0 * 2_003.5 * 0.05).unwrap();
+        assert!(close(positions[0].liquidation_price.unwrap(), eth));
+        assert!(close(positions[1].liquidation_price.unwrap(), btc));
+        assert_eq!(positions[2].liquidation_price, None);
+
+        // Without every market's fraction the others' margin is unknown
+        let mut positions = vec![position(ExchangeId::Dydx, 2.0, "Long", None)];
+        set_cross_liquidation_prices(&mut positions, &ExchangeId::Dydx, 1_000.0, |_| None);
+        assert_eq!(positions[0].liquidation_price, None);
+    }
+
+    #[test]
+    fn test_near_liquidation() {
+        let mut long = position(ExchangeId::Dydx, 2.0, "Long", None);
+        assert!(!near_liquidation(&long, 2_000.0, 0.10));
+        long.liquidation_price = Some(1_850.0);
+        assert!(near_liquidation(&long, 2_000.0, 0.10));
+        assert!(!near_liquidation(&long, 2_100.0, 0.10));
+    }
+
+    #[test]
+    fn test_partial_close_size() {
+        // dYdX sizes are unsigned with a side; the close size is signed
+        let short = position(ExchangeId::Dydx, 0.7, "Short", None);
+        assert!(close(partial_close_size(&short, 50.0, Some(0.1)).unwrap(), -0.3));
+        assert!(close(partial_close_size(&short, 30.0, None).unwrap(), -0.21));
+        assert_eq!(partial_close_size(&short, 100.0, Some(0.1)), Some(-0.7));
+
+        let long = position(ExchangeId::Hyperliquid, 0.3, "", None);
+        assert!(close(partial_close_size(&long, 100.0 / 3.0, Some(0.1)).unwrap(), 0.1));
+        assert_eq!(partial_close_size(&long, 10.0, Some(0.1)), None);
+        assert_eq!(partial_close_size(&long, 0.0, None), None);
+        assert_eq!(partial_close_size(&long, 150.0, None), None);
+    }
+}
+
+#[cfg(test)]
+mod wallet_store_tests {
+    use std::path::PathBuf;
+    use crate::trading::file_lock::AccessMode;
+    use crate::trading::wallet::WalletManager;
+    use crate::error::AggregatorError;
+    use crate::trading::wallet_store::{
+        EncryptedWallet, KeyKind, StoredWallet, WalletFile, WalletKey, ENCRYPTED_FORMAT, ENCRYPTED_VERSION, PURGE_CONFIRMATION,
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("hl_aggregator_wallet_store_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn address(manager: &WalletManager) -> String {
+        format!("{:#x}", ethers::signers::Signer::address(manager.get_wallet().unwrap()))
+    }
+
+    #[test]
+    fn test_set_active_archives_the_replaced_key() {
+        let mut file = WalletFile::default();
+        file.set_active(KeyKind::Eth, "aa".to_string(), None, 1);
+        assert!(file.archived.is_empty());
+
+        file.set_active(KeyKind::Eth, "bb".to_string(), Some("0xA".to_string()), 2);
+        // Same key again changes nothing
+        file.set_active(KeyKind::Eth, "bb".to_string(), Some("0xB".to_string()), 3);
+        assert_eq!(file.active(KeyKind::Eth), Some("bb"));
+        assert_eq!(file.archived.len(), 1);
+        assert_eq!((file.archived[0].secret.as_str(), file.archived[0].address.as_str(), file.archived[0].archived_ms), ("aa", "0xA", 2));
+
+        let restored = file.restore("0xa", Some("0xB".to_string()), 4).unwrap();
+        assert_eq!(restored.kind, KeyKind::Eth);
+        assert_eq!(file.active(KeyKind::Eth), Some("aa"));
+        assert_eq!(file.archived.len(), 1);
+        assert_eq!(file.archived[0].secret, "bb");
+        assert!(file.restore("0xC", None, 5).is_err());
+    }
+
+    #[test]
+    fn test_active_keys_ignore_archived_entries() {
+        let file: WalletFile = serde_json::from_str(r#"{
+            "archived": [{"kind": "eth", "address": "0xA", "secret": "aa", "archived_ms": 1},
+                         {"kind": "dydx", "address": "dydx1a", "secret": "words", "archived_ms": 1}],
+            "note": "kept"
+        }"#).unwrap();
+        assert_eq!(file.active(KeyKind::Eth), None);
+        assert_eq!(file.active(KeyKind::Dydx), None);
+
+        // Unknown fields survive a round trip
+        let json = serde_json::to_value(&file).unwrap();
+        assert_eq!(json["note"], "kept");
+        assert!(json.get("eth_key").is_none());
+    }
+
+    #[test]
+    fn test_purge_requires_confirmation() {
+        let mut file = WalletFile::default();
+        file.set_active(KeyKind::Dydx, "one".to_string(), None, 1);
+        file.set_active(KeyKind::Dydx, "two".to_string(), Some("dydx1a".to_string()), 2);
+        assert!(file.purge("yes").is_err());
+        assert_eq!(file.archived.len(), 1);
+        assert_eq!(file.purge(PURGE_CONFIRMATION).unwrap(), 1);
+        assert!(file.archived.is_empty());
+        assert_eq!(file.active(KeyKind::Dydx), Some("two"));
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_then_restore() {
+        let dir = temp_dir("restore");
+        let mut manager = WalletManager::open(dir.clone(), AccessMode::ReadWrite).await.unwrap();
+        manager.create_eth_wallet().await.unwrap();
+        let first = address(&manager);
+        manager.create_eth_wallet().await.unwrap();
+        let second = address(&manager);
+        assert_ne!(first, second);
+
+        let archived = manager.list_archived().unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!((archived[0].kind, archived[0].address.as_str()), (KeyKind::Eth, first.as_str()));
+
+        manager.restore_archived(&first).await.unwrap();
+        assert_eq!(address(&manager), first);
+        let archived = manager.list_archived().unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].address, second);
+
+        // A reopened manager loads the restored key, not the archived one
+        drop(manager);
+        let reopened = WalletManager::open(dir, AccessMode::ReadWrite).await.unwrap();
+        assert_eq!(address(&reopened), first);
+    }
+
+    #[test]
+    fn test_named_wallets_switch_without_archiving() {
+        let mut file = WalletFile::default();
+        // A key from before wallets had names
+        file.set_active(KeyKind::Eth, "aa".to_string(), None, 1);
+        file.add_wallet("trading", KeyKind::Eth, "bb".to_string()).unwrap();
+        assert_eq!(file.active_name(KeyKind::Eth), Some("default"));
+        assert_eq!(file.active(KeyKind::Eth), Some("aa"));
+        assert!(file.add_wallet("Trading", KeyKind::Eth, "cc".to_string()).is_err());
+        assert!(file.add_wallet("copy", KeyKind::Eth, "bb".to_string()).is_err());
+
+        file.switch_wallet("trading", Some("0xA".to_string()), 2).unwrap();
+        assert_eq!(file.active(KeyKind::Eth), Some("bb"));
+        file.switch_wallet("default", Some("0xB".to_string()), 3).unwrap();
+        assert_eq!(file.active(KeyKind::Eth), Some("aa"));
+        assert!(file.archived.is_empty());
+        assert!(file.switch_wallet("missing", None, 4).is_err());
+
+        // The first dYdX wallet becomes active by itself
+        file.add_wallet("dydx main", KeyKind::Dydx, "words".to_string()).unwrap();
+        assert_eq!(file.active(KeyKind::Dydx), Some("words"));
+        assert_eq!(file.wallets.len(), 3);
+    }
+
+    #[test]
+    fn test_removing_a_wallet_archives_it_but_not_the_active_one() {
+        let mut file = WalletFile::default();
+        file.add_wallet("a", KeyKind::Eth, "aa".to_string()).unwrap();
+        file.add_wallet("b", KeyKind::Eth, "bb".to_string()).unwrap();
+        assert!(file.remove_wallet("a", Some("0xA".to_string()), 1).is_err());
+
+        let removed = file.remove_wallet("B", Some("0xB".to_string()), 2).unwrap();
+        assert_eq!(removed.secret, "bb");
+        assert!(file.find_wallet("b").is_none());
+        assert_eq!(file.find_archived("0xb").map(|key| key.secret.as_str()), Some("bb"));
+    }
+
+    #[tokio::test]
+    async fn test_active_wallet_survives_restart() {
+        let dir = temp_dir("named");
+        let mut manager = WalletManager::open(dir.clone(), AccessMode::ReadWrite).await.unwrap();
+        manager.add_wallet("first", KeyKind::Eth, &format!("0x{}", "11".repeat(32))).await.unwrap();
+        manager.add_wallet("second", KeyKind::Eth, &"22".repeat(32)).await.unwrap();
+        assert!(manager.add_wallet("junk", KeyKind::Eth, "not hex").await.is_err());
+        let first = address(&manager);
+
+        let switched = manager.set_active("second").await.unwrap();
+        assert_ne!(switched.address, first);
+        assert_eq!(address(&manager), switched.address);
+        let wallets = manager.list_wallets().unwrap();
+        assert_eq!(wallets.iter().map(|w| (w.name.as_str(), w.active)).collect::<Vec<_>>(), vec![("first", false), ("second", true)]);
+        drop(manager);
+
+        let mut reopened = WalletManager::open(dir, AccessMode::ReadWrite).await.unwrap();
+        assert_eq!(address(&reopened), switched.address);
+        reopened.remove_wallet("first").unwrap();
+        assert_eq!(reopened.list_archived().unwrap()[0].address, first);
+    }
+
+    #[test]
+    fn test_encrypted_file_round_trips_and_hides_secrets() {
+        let mut file = WalletFile::default();
+        file.set_active(KeyKind::Eth, "deadbeef".to_string(), None, 1);
+        let key = WalletKey::derive("correct horse").unwrap();
+        let sealed = EncryptedWallet::seal(&file, &key).unwrap();
+        assert_eq!((sealed.format.as_str(), sealed.version), (ENCRYPTED_FORMAT, ENCRYPTED_VERSION));
+
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(!json.contains("deadbeef"));
+        let StoredWallet::Encrypted(parsed) = StoredWallet::parse(&json).unwrap() else { panic!("expected an encrypted file") };
+        let (opened, _) = parsed.unlock("correct horse").unwrap();
+        assert_eq!(opened, file);
+
+        let error = parsed.unlock("wrong horse").unwrap_err();
+        assert!(matches!(error, AggregatorError::WrongPassphrase), "{}", error);
+    }
+
+    #[test]
+    fn test_legacy_plaintext_still_parses_and_newer_versions_are_refused() {
+        let legacy = StoredWallet::parse(r#"{"eth_key": "aa"}"#).unwrap();
+        assert!(!legacy.is_encrypted());
+
+        let key = WalletKey::derive("pass").unwrap();
+        let mut future = serde_json::to_value(EncryptedWallet::seal(&WalletFile::default(), &key).unwrap()).unwrap();
+        future["version"] = serde_json::json!(ENCRYPTED_VERSION + 1);
+        assert!(StoredWallet::parse(&future.to_string()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_then_unlock() {
+        let dir = temp_dir("encrypt");
+        let mut manager = WalletManager::open(dir.clone(), AccessMode::ReadWrite).await.unwrap();
+        manager.create_eth_wallet().await.unwrap();
+        let original = address(&manager);
+        assert!(!manager.is_encrypted());
+
+        manager.migrate_to_encrypted("hunter22").unwrap();
+        assert!(manager.is_encrypted());
+        assert!(manager.migrate_to_encrypted("again").is_err());
+        // Saves after migrating stay encrypted
+        manager.create_eth_wallet().await.unwrap();
+        let current = address(&manager);
+        let on_disk = std::fs::read_to_string(dir.join("wallet.key")).unwrap();
+        assert!(on_disk.contains(ENCRYPTED_FORMAT));
+        assert!(matches!(WalletFile::load(&dir.join("wallet.key"), None), Err(AggregatorError::WalletLocked)));
+        drop(manager);
+
+        let error = WalletManager::open_with_prompt(dir.clone(), AccessMode::ReadWrite, Some(|_| Ok("nope".to_string()))).await.err().unwrap();
+        assert!(matches!(error, AggregatorError::WrongPassphrase), "{}", error);
+        let error = WalletManager::open(dir.clone(), AccessMode::ReadWrite).await.err().unwrap();
+        assert!(matches!(error, AggregatorError::WalletLocked), "{}", error);
+
+        let reopened = WalletManager::open_with_prompt(dir, AccessMode::ReadWrite, Some(|_| Ok("hunter22".to_string()))).await.unwrap();
+        assert!(reopened.is_encrypted());
+        assert_eq!(address(&reopened), current);
+        let archived = reopened.list_archived().unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].address, original);
+    }
+}
+
+#[cfg(test)]
+mod trigger_order_tests {
+    use crate::error::AggregatorError;
+    use crate::test_fixtures;
+    use crate::trading::{OrderType, TradeRequest};
+
+    fn request(order_type: OrderType, is_buy: bool) -> TradeRequest {
+        test_fixtures::request(order_type, is_buy, 100.0)
+    }
+
+    #[test]
+    fn test_trigger_must_sit_on_the_far_side_of_the_mark() {
+        let stop = |trigger_price| OrderType::StopMarket { trigger_price };
+        let take_profit = |trigger_price| OrderType::TakeProfit { trigger_price };
+        // (order type, is_buy, passes against a 100 mark)
+        let cases = [
+            (stop(105.0), true, true),
+            (stop(95.0), true, false),
+            (stop(100.0), true, false),
+            (stop(95.0), false, true),
+            (stop(105.0), false, false),
+            (take_profit(95.0), true, true),
+            (take_profit(105.0), true, false),
+            (take_profit(105.0), false, true),
+            (take_profit(100.0), false, false),
+        ];
+        for (order_type, is_buy, passes) in cases {
+            let result = request(order_type.clone(), is_buy).validate_trigger(100.0);
+            assert_eq!(result.is_ok(), passes, "{:?} buy={}: {:?}", order_type, is_buy, result);
+            if !passes {
+                assert!(matches!(result, Err(AggregatorError::TriggerThroughMark(ref refusal)) if refusal.mark == 100.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_trigger_orders_and_unknown_mark_pass() {
+        assert!(request(OrderType::Market, true).validate_trigger(100.0).is_ok());
+        assert!(request(OrderType::Limit, false).validate_trigger(100.0).is_ok());
+        assert!(request(OrderType::StopMarket { trigger_price: 95.0 }, true).validate_trigger(0.0).is_ok());
+        assert!(request(OrderType::StopMarket { trigger_price: -1.0 }, true).validate_trigger(0.0).is_err());
+        assert_eq!(OrderType::TakeProfit { trigger_price: 7.5 }.trigger_price(), Some(7.5));
+        assert_eq!(OrderType::Limit.trigger_price(), None);
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::test_fixtures;
+    use crate::trading::history::{app_order_ids, merge_fills, new_fills, parse_dydx_fills, parse_dydx_order_aliases, parse_hl_fills, parse_since, trade_history, JournaledFill};
+    use crate::trading::journal::{Journal, JournalEntry, TradeSnapshot};
+    use crate::trading::positions::episodes::Fill;
+    use crate::trading::reconcile::OrderOrigin;
+    use crate::trading::{OrderType, TradeRequest};
+
+    fn fill(exchange: ExchangeId, fill_id: &str, order_id: &str, time: i64) -> Fill {
+        Fill { fee: 0.05, order_id: order_id.to_string(), fill_id: fill_id.to_string(), ..test_fixtures::fill(exchange, true, 100.0, 1.0, time) }
+    }
+
+    fn app_orders() -> HashSet<(ExchangeId, String)> {
+        HashSet::from([(ExchangeId::Hyperliquid, "10".to_string())])
+    }
+
+    #[test]
+    fn test_overlapping_pages_are_journaled_once() {
+        let journaled = vec![JournaledFill { fill: fill(ExchangeId::Hyperliquid, "1", "10", 1), origin: OrderOrigin::Local }];
+        // Second page repeats the first page's boundary fill
+        let fetched = vec![
+            fill(ExchangeId::Hyperliquid, "1", "10", 1),
+            fill(ExchangeId::Hyperliquid, "2", "10", 2),
+            fill(ExchangeId::Hyperliquid, "3", "11", 3),
+            fill(ExchangeId::Hyperliquid, "3", "11", 3),
+            // Same id on another venue is a different fill
+            fill(ExchangeId::Dydx, "1", "10", 1),
+        ];
+
+        let fresh = new_fills(&journaled, fetched, &app_orders());
+        let tagged: Vec<_> = fresh.iter().map(|entry| (entry.fill.exchange.clone(), entry.fill.fill_id.as_str(), entry.origin)).collect();
+        assert_eq!(tagged, vec![
+            (ExchangeId::Hyperliquid, "2", OrderOrigin::Local),
+            (ExchangeId::Hyperliquid, "3", OrderOrigin::External),
+            (ExchangeId::Dydx, "1", OrderOrigin::External),
+        ]);
+    }
+
+    #[test]
+    fn test_fills_without_ids_dedupe_by_shape() {
+        let journaled = vec![JournaledFill { fill: fill(ExchangeId::Hyperliquid, "", "10", 1), origin: OrderOrigin::Local }];
+        let mut partial = fill(ExchangeId::Hyperliquid, "", "10", 1);
+        partial.size = 0.5;
+        let fresh = new_fills(&journaled, vec![fill(ExchangeId::Hyperliquid, "", "10", 1), partial], &app_orders());
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].fill.size, 0.5);
+    }
+
+    #[test]
+    fn test_merge_keeps_journaled_history_in_time_order() {
+        let journaled = vec![fill(ExchangeId::Hyperliquid, "1", "10", 1), fill(ExchangeId::Hyperliquid, "2", "10", 5)];
+        let fetched = vec![fill(ExchangeId::Hyperliquid, "3", "11", 3), fill(ExchangeId::Hyperliquid, "2", "10", 5)];
+        let times: Vec<_> = merge_fills(journaled, fetched).iter().map(|fill| fill.time).collect();
+        assert_eq!(times, vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn test_journaled_fills_do_not_parse_as_trades() {
+        let path: PathBuf = std::env::temp_dir()
+            .join(format!("hl_aggregator_history_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut journal = Journal::open(path.clone()).unwrap();
+        let request = TradeRequest {
+            asset: Symbol::perp("BTC"),
+            is_buy: true,
+            order_type: OrderType::Market,
+            usd_value: 100.0,
+            price: None,
+            leverage: 1,
+            cross_margin: None,
+            reduce_only: false,
+            strategy_id: None,
+            max_slippage_bps: None,
+            post_only: false,
+            time_in_force: None,
+            good_til_secs: None,
+            cloid: None,
+        };
+        journal.append(&JournalEntry::new(&ExchangeId::Hyperliquid, request, &Ok(("ok".to_string(), "10".to_string())), TradeSnapshot::default())).unwrap();
+        let app_orders = app_order_ids(&journal.entries().unwrap());
+        for entry in new_fills(&[], vec![fill(ExchangeId::Hyperliquid, "1", "10", 1), fill(ExchangeId::Hyperliquid, "2", "99", 2)], &app_orders) {
+            journal.append_fill(&entry).unwrap();
+        }
+
+        assert_eq!(journal.entries().unwrap().len(), 1);
+        assert!(journal.farm_events().unwrap().is_empty());
+        let origins: Vec<_> = journal.fills().unwrap().iter().map(|entry| entry.origin).collect();
+        assert_eq!(origins, vec![OrderOrigin::Local, OrderOrigin::External]);
+        drop(journal);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("lock"));
+    }
+
+    #[test]
+    fn test_parse_venue_fills() {
+        let hl = r#"[{"coin":"ETH","px":"2500.5","sz":"0.4","side":"A","time":1700000000000,"startPosition":"0","dir":"Open Short","closedPnl":"0","hash":"0x1","oid":42,"crossed":true,"fee":"0.3","tid":777,"feeToken":"USDC"}]"#;
+        let fills = parse_hl_fills(hl).unwrap();
+        assert_eq!((fills[0].fill_id.as_str(), fills[0].order_id.as_str(), fills[0].is_buy), ("777", "42", false));
+
+        let dydx = r#"{"fills":[{"id":"f-1","side":"BUY","liquidity":"TAKER","type":"LIMIT","market":"BTC-USD","marketType":"PERPETUAL","price":"50000","size":"0.01","fee":"0.25","createdAt":"2023-11-14T22:13:20.000Z","createdAtHeight":"1","orderId":"uuid-1","subaccountNumber":0}]}"#;
+        let fills = parse_dydx_fills(dydx).unwrap();
+        assert_eq!((fills[0].fill_id.as_str(), fills[0].time, fills[0].is_buy), ("f-1", 1_700_000_000_000, true));
+
+        let orders = r#"[{"id":"uuid-1","clientId":"123","clobPairId":"0","orderFlags":"64","subaccountNumber":0,"ticker":"BTC-USD"}]"#;
+        assert_eq!(parse_dydx_order_aliases(orders).unwrap().get("uuid-1").map(String::as_str), Some("123:0:64:0"));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("2023-11-14"), Ok(1_699_920_000_000));
+        assert_eq!(parse_since("2023-11-14T22:13:20Z"), Ok(1_700_000_000_000));
+        assert!(parse_since("last week").is_err());
+    }
+
+    #[test]
+    fn test_trade_history_running_totals() {
+        let trade = |exchange: ExchangeId, is_buy: bool, price: f64, time: i64| Fill { is_buy, price, ..fill(exchange, &time.to_string(), "1", time) };
+        let fills = vec![
+            trade(ExchangeId::Hyperliquid, true, 100.0, 1),
+            trade(ExchangeId::Dydx, true, 100.0, 2),
+            trade(ExchangeId::Hyperliquid, false, 110.0, 3),
+            trade(ExchangeId::Hyperliquid, true, 105.0, 4),
+            trade(ExchangeId::Hyperliquid, false, 100.0, 5),
+        ];
+
+        let rows = trade_history(&fills, None);
+        let times: Vec<i64> = rows.iter().map(|row| row.fill.time).collect();
+        assert_eq!(times, [5, 4, 3, 2, 1]);
+        assert_eq!(rows[0].realized, -5.0);
+        assert_eq!(rows[0].total_realized, 5.0);
+        assert!((rows[0].total_fees - 0.2).abs() < 1e-9);
+        // dYdX keeps its own totals
+        assert_eq!(rows[3].fill.exchange, ExchangeId::Dydx);
+        assert!((rows[3].total_fees - 0.05).abs() < 1e-9);
+
+        // Totals restart at the cutoff, but PnL still uses the earlier entry
+        let rows = trade_history(&fills, Some(3));
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2].realized, 10.0);
+        assert_eq!(rows[0].total_realized, 5.0);
+        assert!((rows[0].total_fees - 0.15).abs() < 1e-9);
+    }
+}
+
+#[cfg(test)]
+mod twap_tests {
+    use crate::error::{AggregatorError, Result};
+    use async_trait::async_trait;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::test_fixtures;
+    use crate::trading::positions::episodes::Fill;
+    use crate::trading::twap::{TwapEvent, TwapExecutor, TwapOutcome, TwapVenue};
+    use crate::trading::{OrderType, TradeRequest};
+
+    const TICK: Duration = Duration::from_millis(5);
+
+    // Answers from `script` in order, then accepts everything; each accepted
+    // slice fills at 100 + its number
+    #[derive(Default)]
+    struct ScriptedVenue {
+        script: VecDeque<Result<(), String>>,
+        sent: Arc<Mutex<Vec<TradeRequest>>>,
+    }
+
+    #[async_trait(?Send)]
+    impl TwapVenue for ScriptedVenue {
+        async fn place_slice(&mut self, _exchange: &ExchangeId, request: TradeRequest) -> Result<String> {
+            if let Some(Err(e)) = self.script.pop_front() {
+                return Err(AggregatorError::ApiError(e));
+            }
+            let mut sent = self.sent.lock().unwrap();
+            sent.push(request);
+            Ok(sent.len().to_string())
+        }
+
+        async fn slice_fills(&mut self, exchange: &ExchangeId, order_id: &str) -> Vec<Fill> {
+            let number: f64 = order_id.parse().unwrap();
+            vec![Fill {
+                exchange: exchange.clone(),
+                asset: "BTC".to_string(),
+                is_buy: true,
+                price: 100.0 + number,
+                size: 1.0,
+                fee: 0.0,
+                time: 0,
+                order_id: order_id.to_string(),
+                fill_id: order_id.to_string(),
+            }]
+        }
+    }
+
+    fn request(usd_value: f64) -> TradeRequest {
+        test_fixtures::request(OrderType::Market, true, usd_value)
+    }
+
+    fn script(results: &[bool]) -> VecDeque<Result<(), String>> {
+        results.iter().map(|ok| if *ok { Ok(()) } else { Err("rejected".to_string()) }).collect()
+    }
+
+    #[test]
+    fn test_rejects_unusable_setups() {
+        assert!(TwapExecutor::new(ExchangeId::Hyperliquid, request(100.0), 0, TICK).is_err());
+        assert!(TwapExecutor::new(ExchangeId::Hyperliquid, request(100.0), 4, Duration::ZERO).is_err());
+        assert!(TwapExecutor::new(ExchangeId::Hyperliquid, request(0.0), 4, TICK).is_err());
+        let stop = TradeRequest { order_type: OrderType::StopMarket { trigger_price: 90.0 }, ..request(100.0) };
+        assert!(TwapExecutor::new(ExchangeId::Hyperliquid, stop, 4, TICK).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sends_equal_slices_and_tracks_fills() {
+        let (executor, _handle, mut events) = TwapExecutor::new(ExchangeId::Hyperliquid, request(400.0), 4, TICK).unwrap();
+        let mut venue = ScriptedVenue::default();
+        let (outcome, progress) = executor.run(&mut venue).await;
+
+        assert_eq!(outcome, TwapOutcome::Completed);
+        let sent = venue.sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 4);
+        assert!(sent.iter().all(|slice| slice.usd_value == 100.0));
+        // One strategy id ties the slices together
+        assert!(sent[0].strategy_id.is_some());
+        assert!(sent.iter().all(|slice| slice.strategy_id == sent[0].strategy_id));
+        assert_eq!(progress.percent(), 100.0);
+        assert_eq!(progress.average_price(), Some(102.5));
+
+        let mut percents = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            match event {
+                TwapEvent::SliceSent { progress, .. } => percents.push(progress.percent()),
+                TwapEvent::Finished { outcome, .. } => assert_eq!(outcome, TwapOutcome::Completed),
+                TwapEvent::SliceFailed { .. } => panic!("no slice should fail"),
+            }
+        }
+        assert_eq!(percents, vec![25.0, 50.0, 75.0, 100.0]);
+    }
+
+    #[tokio::test]
+    async fn test_stops_after_three_failures_in_a_row() {
+        let (executor, _handle, _events) = TwapExecutor::new(ExchangeId::Dydx, request(300.0), 3, TICK).unwrap();
+        let mut venue = ScriptedVenue { script: script(&[true, false, false, false]), ..Default::default() };
+        let (outcome, progress) = executor.run(&mut venue).await;
+
+        assert_eq!(outcome, TwapOutcome::Failed("API error: rejected".to_string()));
+        assert_eq!((progress.slices_sent, progress.consecutive_failures), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_the_failure_count() {
+        let (executor, _handle, _events) = TwapExecutor::new(ExchangeId::Dydx, request(200.0), 2, TICK).unwrap();
+        let mut venue = ScriptedVenue { script: script(&[false, false, true, false, false, true]), ..Default::default() };
+        let (outcome, progress) = executor.run(&mut venue).await;
+
+        assert_eq!(outcome, TwapOutcome::Completed);
+        assert_eq!(progress.slices_sent, 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_before_start_sends_nothing() {
+        let (executor, handle, _events) = TwapExecutor::new(ExchangeId::Hyperliquid, request(400.0), 4, TICK).unwrap();
+        handle.cancel();
+        // Cancelled is final
+        handle.resume();
+        let mut venue = ScriptedVenue::default();
+        let (outcome, progress) = executor.run(&mut venue).await;
+
+        assert_eq!(outcome, TwapOutcome::Cancelled);
+        assert_eq!(progress.slices_sent, 0);
+        assert!(venue.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pause_holds_slices_until_resumed() {
+        let (executor, handle, _events) = TwapExecutor::new(ExchangeId::Hyperliquid, request(10_000.0), 100, TICK).unwrap();
+        let mut venue = ScriptedVenue::default();
+        let sent = venue.sent.clone();
+        handle.pause();
+
+        let control = async {
+            tokio::time::sleep(TICK * 6).await;
+            assert!(sent.lock().unwrap().is_empty());
+            handle.resume();
+            tokio::time::sleep(TICK * 6).await;
+            handle.cancel();
+        };
+        let ((outcome, progress), ()) = tokio::join!(executor.run(&mut venue), control);
+
+        assert_eq!(outcome, TwapOutcome::Cancelled);
+        assert!(progress.slices_sent >= 1 && progress.slices_sent < 100, "sent {}", progress.slices_sent);
+    }
+}
+
+#[cfg(test)]
+mod market_gate_tests {
+    use crate::aggregator::book_quality::{BookQuality, MIN_SPREAD_SAMPLES};
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::aggregator::types::{Level, OrderBook};
+    use crate::error::AggregatorError;
+    use crate::test_fixtures::polled_top;
+    use crate::trading::hyperliquid_service::slippage_limit;
+    use crate::trading::market_gate::{check_market_data, check_slippage, MarketGatePolicy};
+    use crate::trading::{OrderType, TradeRequest};
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    fn book(exchange: ExchangeId, bid: Option<f64>, ask: Option<f64>, timestamp: i64) -> OrderBook {
+        polled_top(exchange, bid, ask, timestamp as u64)
+    }
+
+    // dYdX with a 10bps baseline spread around 100
+    fn baseline() -> BookQuality {
+        let mut quality = BookQuality::default();
+        for i in 0..MIN_SPREAD_SAMPLES as i64 {
+            quality.record_book(&book(ExchangeId::Dydx, Some(99.95), Some(100.05), NOW - 10_000 + i));
+        }
+        quality
+    }
+
+    fn check(quality: &BookQuality) -> Result<(), AggregatorError> {
+        check_market_data(&MarketGatePolicy::default(), quality, &ExchangeId::Dydx, &Symbol::perp("BTC"), NOW)
+    }
+
+    #[test]
+    fn test_healthy_book_passes() {
+        let mut quality = baseline();
+        quality.record_book(&book(ExchangeId::Dydx, Some(99.9), Some(100.1), NOW - 100));
+        quality.record_book(&book(ExchangeId::Hyperliquid, Some(100.0), Some(100.2), NOW - 100));
+        assert!(check(&quality).is_ok());
+    }
+
+    #[test]
+    fn test_unsampled_market_passes() {
+        assert!(check(&BookQuality::default()).is_ok());
+    }
+
+    #[test]
+    fn test_stale_book_rejected() {
+        let mut quality = baseline();
+        quality.record_book(&book(ExchangeId::Dydx, Some(99.95), Some(100.05), NOW - 6_000));
+        assert!(matches!(check(&quality), Err(AggregatorError::StaleBook(stale)) if (stale.age_ms, stale.limit_ms) == (6_000, 5_000)));
+    }
+
+    #[test]
+    fn test_one_sided_book_rejected() {
+        let mut quality = baseline();
+        quality.record_book(&book(ExchangeId::Dydx, Some(99.95), None, NOW - 100));
+        assert!(matches!(check(&quality), Err(AggregatorError::OneSidedBook(ref book)) if book.missing == "asks"));
+        quality.record_book(&book(ExchangeId::Dydx, None, Some(100.05), NOW - 50));
+        assert!(matches!(check(&quality), Err(AggregatorError::OneSidedBook(ref book)) if book.missing == "bids"));
+        quality.record_book(&book(ExchangeId::Dydx, None, None, NOW - 10));
+        assert!(matches!(check(&quality), Err(AggregatorError::OneSidedBook(ref book)) if book.missing == "bids or asks"));
+    }
+
+    #[test]
+    fn test_spread_blowout_rejected() {
+        let mut quality = baseline();
+        // ~100bps against a ~10bps average
+        quality.record_book(&book(ExchangeId::Dydx, Some(99.5), Some(100.5), NOW - 100));
+        assert!(matches!(check(&quality), Err(AggregatorError::SpreadBlowout(_))));
+
+        // Without a baseline there's nothing to compare against
+        let mut fresh = BookQuality::default();
+        fresh.record_book(&book(ExchangeId::Dydx, Some(99.5), Some(100.5), NOW - 100));
+        assert!(check(&fresh).is_ok());
+    }
+
+    #[test]
+    fn test_diverging_mids_rejected() {
+        let mut quality = baseline();
+        quality.record_book(&book(ExchangeId::Dydx, Some(99.95), Some(100.05), NOW - 100));
+        quality.record_book(&book(ExchangeId::Hyperliquid, Some(103.95), Some(104.05), NOW - 100));
+        match check(&quality) {
+            Err(AggregatorError::VenueMidsDiverge(divergence)) => {
+                assert_eq!(divergence.other, ExchangeId::Hyperliquid);
+                assert!((divergence.divergence_bps - 4.0 / 104.0 * 10_000.0).abs() < 1e-6);
+            }
+            other => panic!("expected divergence, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stale_or_one_sided_other_venue_ignored() {
+        let mut quality = baseline();
+        quality.record_book(&book(ExchangeId::Dydx, Some(99.95), Some(100.05), NOW - 100));
+        quality.record_book(&book(ExchangeId::Hyperliquid, Some(103.95), Some(104.05), NOW - 9_000));
+        assert!(check(&quality).is_ok());
+        quality.record_book(&book(ExchangeId::Hyperliquid, Some(103.95), None, NOW - 100));
+        assert!(check(&quality).is_ok());
+    }
+
+    fn market_buy(usd_value: f64, max_slippage_bps: Option<u32>) -> TradeRequest {
+        TradeRequest {
+            asset: Symbol::perp("BTC"),
+            is_buy: true,
+            order_type: OrderType::Market,
+            usd_value,
+            price: None,
+            leverage: 1,
+            cross_margin: None,
+            reduce_only: false,
+            strategy_id: None,
+            max_slippage_bps,
+            post_only: false,
+            time_in_force: None,
+            good_til_secs: None,
+            cloid: None,
+        }
+    }
+
+    #[test]
+    fn test_slippage_tolerance_against_the_book() {
+        let mut book = book(ExchangeId::Hyperliquid, Some(99.0), Some(100.0), NOW);
+        book.asks.push(Level { price: 101.0, size: 1.0, orders: 1 });
+
+        // $150 reaches the 101 ask, 100bps past the touch
+        match check_slippage(&book, &market_buy(150.0, Some(50))) {
+            Err(AggregatorError::SlippageExceeded(slippage)) => {
+                assert!((slippage.estimated_bps - 100.0).abs() < 1e-6);
+                assert_eq!(slippage.limit_bps, 50);
+            }
+            other => panic!("expected slippage refusal, got {:?}", other),
+        }
+        assert!(check_slippage(&book, &market_buy(150.0, Some(150))).is_ok());
+        assert!(check_slippage(&book, &market_buy(50.0, Some(0))).is_ok());
+        assert!(check_slippage(&book, &market_buy(150.0, None)).is_ok());
+        let limit = TradeRequest { order_type: OrderType::Limit, price: Some(101.0), ..market_buy(150.0, Some(50)) };
+        assert!(check_slippage(&book, &limit).is_ok());
+    }
+
+    #[test]
+    fn test_hyperliquid_slippage_limit_is_past_the_touch() {
+        assert!((slippage_limit(100.0, true, 50) - 100.5).abs() < 1e-9);
+        assert!((slippage_limit(100.0, false, 50) - 99.5).abs() < 1e-9);
+        assert_eq!(slippage_limit(100.0, true, 0), 100.0);
+    }
+}
+
+#[cfg(test)]
+mod kill_switch_tests {
+    use crate::error::{AggregatorError, Result};
+    use async_trait::async_trait;
+    use std::time::Duration;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::test_fixtures;
+    use crate::trading::kill_switch::{sweep, KillAction, KillScope, KillSwitchReport, KillSwitchVenue};
+    use crate::trading::orders::Order;
+    use crate::trading::positions::Position;
+
+    // Fails the first `close_failures` closes of each market
+    #[derive(Default)]
+    struct ScriptedVenue {
+        orders: Vec<Order>,
+        positions: Vec<Position>,
+        close_failures: u32,
+        orders_unavailable: bool,
+        calls: Vec<String>,
+    }
+
+    #[async_trait(?Send)]
+    impl KillSwitchVenue for ScriptedVenue {
+        fn exchange(&self) -> ExchangeId {
+            ExchangeId::Hyperliquid
+        }
+
+        async fn open_orders(&mut self) -> Result<Vec<Order>> {
+            if self.orders_unavailable {
+                return Err(AggregatorError::ApiError("timed out".to_string()));
+            }
+            Ok(self.orders.clone())
+        }
+
+        async fn cancel(&mut self, order: &Order) -> Result<()> {
+            self.calls.push(format!("cancel {}", order.order_id));
+            if order.order_id == "bad" {
+                return Err(AggregatorError::ApiError("unknown order".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn positions(&mut self) -> Result<Vec<Position>> {
+            self.calls.push("positions".to_string());
+            Ok(self.positions.clone())
+        }
+
+        async fn close(&mut self, symbol: &Symbol, size: f64) -> Result<String> {
+            let attempt = self.calls.iter().filter(|call| call.starts_with(&format!("close {}", symbol))).count() as u32;
+            self.calls.push(format!("close {} {}", symbol, size));
+            if attempt < self.close_failures {
+                return Err(AggregatorError::ApiError("rate limited".to_string()));
+            }
+            Ok("filled".to_string())
+        }
+    }
+
+    fn order(id: &str) -> Order {
+        test_fixtures::order(ExchangeId::Hyperliquid, id)
+    }
+
+    fn position(asset: &str, size: f64) -> Position {
+        Position {
+            exchange: ExchangeId::Hyperliquid,
+            asset: asset.to_string(),
+            size,
+            entry_price: Some(100.0),
+            liquidation_price: None,
+            unrealized_pnl: 0.0,
+            margin_used: None,
+            leverage: None,
+            roe: None,
+            side: if size > 0.0 { "Long" } else { "Short" }.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancels_before_closes() {
+        let mut venue = ScriptedVenue {
+            orders: vec![order("1"), order("2")],
+            positions: vec![position("BTC", 0.5), position("ETH", -2.0)],
+            ..Default::default()
+        };
+        let kill = sweep(&mut venue, KillScope::Flatten, 3, Duration::ZERO).await;
+        assert_eq!(venue.calls, vec!["cancel 1", "cancel 2", "positions", "close BTC 0.5", "close ETH -2"]);
+        assert!(kill.is_clean());
+        assert_eq!(kill.steps.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_close_retried_up_to_attempts() {
+        let mut venue = ScriptedVenue { positions: vec![position("BTC", 0.5)], close_failures: 2, ..Default::default() };
+        let kill = sweep(&mut venue, KillScope::Flatten, 3, Duration::ZERO).await;
+        assert!(kill.is_clean());
+        assert_eq!(kill.steps[0].attempts, 3);
+
+        let mut venue = ScriptedVenue { positions: vec![position("BTC", 0.5)], close_failures: 5, ..Default::default() };
+        let kill = sweep(&mut venue, KillScope::Flatten, 3, Duration::ZERO).await;
+        assert!(!kill.is_clean());
+        assert_eq!(kill.steps[0].attempts, 3);
+        assert_eq!(kill.steps[0].result, Err("API error: rate limited".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_failures_do_not_stop_the_sweep() {
+        let mut venue = ScriptedVenue {
+            orders: vec![order("bad"), order("2")],
+            positions: vec![position("BTC", 0.5)],
+            ..Default::default()
+        };
+        let kill = sweep(&mut venue, KillScope::Flatten, 1, Duration::ZERO).await;
+        assert_eq!(kill.steps.iter().filter(|step| step.result.is_err()).count(), 1);
+        assert!(matches!(&kill.steps[2].action, KillAction::Close { asset, .. } if asset == "BTC"));
+
+        // Positions still closed when orders can't be listed
+        let mut venue = ScriptedVenue { positions: vec![position("BTC", 0.5)], orders_unavailable: true, ..Default::default() };
+        let kill = sweep(&mut venue, KillScope::Flatten, 1, Duration::ZERO).await;
+        assert_eq!(kill.fetch_errors, vec!["listing open orders: API error: timed out".to_string()]);
+        assert!(kill.steps[0].result.is_ok());
+
+        let report = KillSwitchReport { venues: vec![kill] };
+        assert!(!report.is_clean());
+        assert_eq!(report.describe(), "Hyperliquid: 0 orders cancelled, 1 positions closed\n  FAILED listing open orders: API error: timed out");
+    }
+
+    #[tokio::test]
+    async fn test_scopes() {
+        let venue = || ScriptedVenue {
+            orders: vec![order("1")],
+            positions: vec![position("BTC", 0.5), position("ETH", -2.0)],
+            ..Default::default()
+        };
+
+        let mut cancel_only = venue();
+        sweep(&mut cancel_only, KillScope::CancelOrders, 1, Duration::ZERO).await;
+        assert_eq!(cancel_only.calls, vec!["cancel 1"]);
+
+        let mut halve = venue();
+        sweep(&mut halve, KillScope::HalvePositions, 1, Duration::ZERO).await;
+        assert_eq!(halve.calls, vec!["positions", "close BTC 0.25", "close ETH -1"]);
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_short_closes_as_short() {
+        // dYdX reports a short's size unsigned, with the side saying which way
+        let mut short = position("ETH", 2.0);
+        short.side = "Short".to_string();
+        let mut venue = ScriptedVenue { positions: vec![short], ..Default::default() };
+        sweep(&mut venue, KillScope::HalvePositions, 1, Duration::ZERO).await;
+        assert_eq!(venue.calls, vec!["positions", "close ETH -1"]);
+    }
+}
+
+#[cfg(test)]
+mod drawdown_tests {
+    use crate::trading::drawdown::{DrawdownAction, DrawdownBasis, DrawdownGuard, DrawdownPolicy, DrawdownRecord, GuardEvent, GuardState};
+    use crate::trading::journal::Journal;
+    use crate::trading::kill_switch::{KillAction, KillScope, KillStep};
+
+    // 2023-11-14 22:13:20 UTC
+    const NOW: i64 = 1_700_000_000_000;
+    const MINUTE: i64 = 60_000;
+
+    fn policy(basis: DrawdownBasis) -> DrawdownPolicy {
+        DrawdownPolicy { warn_pct: 5.0, trigger_pct: 10.0, basis, action: DrawdownAction::HalvePositions, cooldown_ms: 30 * MINUTE }
+    }
+
+    // One sample a minute; returns the events, None where nothing changed
+    fn run(guard: &mut DrawdownGuard, start_ms: i64, curve: &[f64]) -> Vec<Option<GuardEvent>> {
+        curve.iter().enumerate().map(|(i, &equity)| guard.observe(equity, start_ms + i as i64 * MINUTE)).collect()
+    }
+
+    fn kinds(events: &[Option<GuardEvent>]) -> Vec<&'static str> {
+        events.iter().map(|event| match event {
+            None => "-",
+            Some(GuardEvent::Warned(_)) => "warned",
+            Some(GuardEvent::Recovered(_)) => "recovered",
+            Some(GuardEvent::Triggered(..)) => "triggered",
+            Some(GuardEvent::Rearmed) => "rearmed",
+        }).collect()
+    }
+
+    #[test]
+    fn test_warns_then_recovers() {
+        let mut guard = DrawdownGuard::new(policy(DrawdownBasis::Session));
+        let events = run(&mut guard, NOW, &[1000.0, 1100.0, 1040.0, 1030.0, 1060.0]);
+        assert_eq!(kinds(&events), vec!["-", "-", "warned", "-", "recovered"]);
+        match &events[2] {
+            Some(GuardEvent::Warned(reading)) => {
+                assert_eq!(reading.high, 1100.0);
+                assert!((reading.pct - 60.0 / 1100.0 * 100.0).abs() < 1e-9);
+            }
+            other => panic!("expected a warning, got {:?}", other),
+        }
+        assert_eq!(guard.state(), GuardState::Armed);
+    }
+
+    #[test]
+    fn test_escalates_to_trigger_and_cools_down() {
+        let mut guard = DrawdownGuard::new(policy(DrawdownBasis::Session));
+        let events = run(&mut guard, NOW, &[1000.0, 940.0, 890.0, 850.0]);
+        assert_eq!(kinds(&events), vec!["-", "warned", "triggered", "-"]);
+        match &events[2] {
+            Some(GuardEvent::Triggered(reading, action)) => {
+                assert_eq!(*action, DrawdownAction::HalvePositions);
+                assert!((reading.pct - 11.0).abs() < 1e-9);
+            }
+            other => panic!("expected a trigger, got {:?}", other),
+        }
+        // Nothing more fires while the action runs
+        assert_eq!(guard.state(), GuardState::Triggered);
+        assert!((guard.last_trigger().unwrap().equity - 890.0).abs() < 1e-9);
+
+        let done = NOW + 4 * MINUTE;
+        guard.action_done(done);
+        assert_eq!(guard.state(), GuardState::CoolingDown { until_ms: done + 30 * MINUTE });
+        assert_eq!(guard.observe(700.0, done + 29 * MINUTE), None);
+        assert_eq!(guard.observe(800.0, done + 30 * MINUTE), Some(GuardEvent::Rearmed));
+
+        // The high restarts from the equity found on re-arming
+        let events = run(&mut guard, done + 31 * MINUTE, &[790.0, 750.0, 710.0]);
+        assert_eq!(kinds(&events), vec!["-", "warned", "triggered"]);
+    }
+
+    #[test]
+    fn test_gap_down_triggers_from_armed() {
+        let mut guard = DrawdownGuard::new(policy(DrawdownBasis::Session));
+        let events = run(&mut guard, NOW, &[1000.0, 800.0]);
+        assert_eq!(kinds(&events), vec!["-", "triggered"]);
+    }
+
+    #[test]
+    fn test_daily_basis_resets_high_at_midnight() {
+        // 23:50 UTC
+        let evening = NOW - NOW % 86_400_000 + 86_400_000 - 10 * MINUTE;
+        let mut session = DrawdownGuard::new(policy(DrawdownBasis::Session));
+        let mut daily = DrawdownGuard::new(policy(DrawdownBasis::Daily));
+        let curve = [1000.0, 960.0, 960.0, 960.0, 960.0, 960.0, 960.0, 960.0, 960.0, 960.0, 960.0, 960.0, 920.0];
+        // The last sample is past midnight, 4% below that day's first
+        assert_eq!(kinds(&run(&mut session, evening, &curve)).last(), Some(&"warned"));
+        assert_eq!(kinds(&run(&mut daily, evening, &curve)).last(), Some(&"-"));
+        assert_eq!(daily.state(), GuardState::Armed);
+    }
+
+    #[test]
+    fn test_non_finite_equity_ignored() {
+        let mut guard = DrawdownGuard::new(policy(DrawdownBasis::Session));
+        guard.observe(1000.0, NOW);
+        assert_eq!(guard.observe(f64::NAN, NOW + MINUTE), None);
+        assert_eq!(kinds(&run(&mut guard, NOW + 2 * MINUTE, &[880.0])), vec!["triggered"]);
+    }
+
+    #[test]
+    fn test_actions_parse_and_map_to_scopes() {
+        assert_eq!("flatten".parse::<DrawdownAction>().unwrap().scope(), Some(KillScope::Flatten));
+        assert_eq!("halve".parse::<DrawdownAction>().unwrap().scope(), Some(KillScope::HalvePositions));
+        assert_eq!("Cancel".parse::<DrawdownAction>().unwrap().scope(), Some(KillScope::CancelOrders));
+        assert_eq!("notify".parse::<DrawdownAction>().unwrap().scope(), None);
+        assert!("panic".parse::<DrawdownAction>().is_err());
+        assert_eq!("daily".parse::<DrawdownBasis>(), Ok(DrawdownBasis::Daily));
+    }
+
+    #[test]
+    fn test_records_journaled_with_trigger_values() {
+        let path = std::env::temp_dir().join(format!("drawdown_journal_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut journal = Journal::open(path.clone()).unwrap();
+
+        let mut guard = DrawdownGuard::new(policy(DrawdownBasis::Session));
+        guard.observe(1000.0, NOW);
+        let Some(GuardEvent::Triggered(reading, action)) = guard.observe(850.0, NOW + MINUTE) else { panic!("expected a trigger") };
+        let step = KillStep { action: KillAction::Close { asset: "BTC".to_string(), size: 0.25 }, attempts: 2, result: Err("rate limited".to_string()) };
+        journal.append_drawdown(&DrawdownRecord::trigger(&reading, action)).unwrap();
+        journal.append_drawdown(&DrawdownRecord::step(&reading, action, "Hyperliquid", &step, NOW + 2 * MINUTE)).unwrap();
+
+        let records = journal.drawdown_records().unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|record| record.high == 1000.0 && record.equity == 850.0 && (record.drawdown_pct - 15.0).abs() < 1e-9));
+        assert_eq!(records[0].step, None);
+        assert_eq!(records[1].step.as_deref(), Some("Hyperliquid: close BTC 0.25"));
+        assert_eq!(records[1].result, Some(Err("rate limited".to_string())));
+        // Drawdown lines don't read back as trades or fills
+        assert!(journal.entries().unwrap().is_empty());
+        assert!(journal.fills().unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+#[cfg(test)]
+mod mirror_tests {
+    use crate::error::Result;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::trading::confirmation::{Confirmation, Quote};
+    use crate::trading::journal::{JournalEntry, TradeSnapshot};
+    use crate::error::AggregatorError;
+    use crate::test_fixtures;
+    use crate::trading::mirror::{cancel_linked, twins, MirrorVenue, MirroredOrder};
+    use crate::trading::orders::Order;
+    use crate::trading::strategy::StrategyLegs;
+    use crate::trading::{OrderType, TradeRequest};
+
+    #[derive(Default)]
+    struct MockTrader {
+        // Placements to reject before accepting
+        failures: u32,
+        placed: Vec<(TradeRequest, bool)>,
+        cancelled: Vec<String>,
+    }
+
+    // One mock trader per venue
+    #[derive(Default)]
+    struct MockTraders(HashMap<ExchangeId, MockTrader>);
+
+    #[async_trait(?Send)]
+    impl MirrorVenue for MockTraders {
+        async fn place_leg(&mut self, exchange: &ExchangeId, request: TradeRequest, _quote: Quote, _confirmation: Confirmation, force: bool) -> Result<String> {
+            let trader = self.0.entry(exchange.clone()).or_default();
+            if trader.failures > 0 {
+                trader.failures -= 1;
+                return Err(AggregatorError::ApiError(format!("{} unreachable", exchange)));
+            }
+            trader.placed.push((request, force));
+            Ok(format!("{}-{}", exchange, trader.placed.len()))
+        }
+
+        async fn cancel_leg(&mut self, order: &Order) -> Result<(), AggregatorError> {
+            self.0.entry(order.exchange.clone()).or_default().cancelled.push(order.order_id.clone());
+            Ok(())
+        }
+    }
+
+    fn request() -> TradeRequest {
+        TradeRequest { leverage: 5, cross_margin: Some(true), ..test_fixtures::request(OrderType::Market, true, 1000.0) }
+    }
+
+    fn mirrored() -> MirroredOrder {
+        MirroredOrder::new(request(), vec![
+            (ExchangeId::Hyperliquid, Quote { price: 50_000.0, size_step: Some(0.00001) }, Confirmation::None),
+            (ExchangeId::Dydx, Quote { price: 50_010.0, size_step: Some(0.0001) }, Confirmation::None),
+        ])
+    }
+
+    fn order(exchange: ExchangeId, id: &str) -> Order {
+        Order { size: 0.02, ..test_fixtures::order(exchange, id) }
+    }
+
+    fn entry(exchange: ExchangeId, order_id: &str, strategy_id: Uuid) -> JournalEntry {
+        JournalEntry::new(&exchange, request().with_strategy(strategy_id), &Ok(("ok".to_string(), order_id.to_string())), TradeSnapshot::default())
+    }
+
+    #[tokio::test]
+    async fn test_fans_out_to_both_venues() {
+        let mut traders = MockTraders::default();
+        let mut order = mirrored();
+        order.place(&mut traders, false).await;
+
+        assert!(order.is_complete());
+        let hl = &traders.0[&ExchangeId::Hyperliquid].placed;
+        let dydx = &traders.0[&ExchangeId::Dydx].placed;
+        assert_eq!((hl.len(), dydx.len()), (1, 1));
+        assert_eq!(hl[0].0.strategy_id, Some(order.group_id));
+        assert_eq!(dydx[0].0.strategy_id, Some(order.group_id));
+        assert_eq!((hl[0].0.usd_value, dydx[0].0.usd_value), (1000.0, 1000.0));
+        assert_eq!(order.legs.iter().map(|leg| leg.result.clone()).collect::<Vec<_>>(), vec![Ok("Hyperliquid-1".to_string()), Ok("dYdX-1".to_string())]);
+        // Each venue's own rounding: 0.02 BTC on both steps, at each venue's price
+        assert!((order.legs[0].notional(&order.request) - 1000.0).abs() < 1e-6);
+        assert!((order.legs[1].notional(&order.request) - 0.02 * 50_010.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_one_leg_failure_retries_only_that_leg() {
+        let mut traders = MockTraders::default();
+        traders.0.insert(ExchangeId::Dydx, MockTrader { failures: 1, ..Default::default() });
+        let mut order = mirrored();
+        order.place(&mut traders, false).await;
+
+        assert!(!order.is_complete());
+        let failed: Vec<&ExchangeId> = order.failed().map(|leg| &leg.exchange).collect();
+        assert_eq!(failed, vec![&ExchangeId::Dydx]);
+        assert!(order.describe().contains("dYdX unreachable"));
+
+        order.retry_failed(&mut traders).await;
+        assert!(order.is_complete());
+        assert_eq!(traders.0[&ExchangeId::Hyperliquid].placed.len(), 1);
+        let dydx = &traders.0[&ExchangeId::Dydx].placed;
+        assert_eq!(dydx.len(), 1);
+        // The retry is forced past the duplicate guard
+        assert!(dydx[0].1);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_takes_the_twin() {
+        let mirror_id = Uuid::new_v4();
+        let ladder_id = Uuid::new_v4();
+        let legs = StrategyLegs::from_journal(&[
+            entry(ExchangeId::Hyperliquid, "h1", mirror_id),
+            entry(ExchangeId::Dydx, "d1", mirror_id),
+            entry(ExchangeId::Hyperliquid, "h2", ladder_id),
+            entry(ExchangeId::Hyperliquid, "h3", ladder_id),
+        ]);
+        let open = vec![
+            order(ExchangeId::Hyperliquid, "h1"),
+            order(ExchangeId::Dydx, "d1"),
+            order(ExchangeId::Hyperliquid, "h2"),
+            order(ExchangeId::Hyperliquid, "h3"),
+            order(ExchangeId::Dydx, "loose"),
+        ];
+
+        let found: Vec<Order> = twins(&open[0], &legs, &open).into_iter().cloned().collect();
+        assert_eq!(found.iter().map(|o| o.order_id.as_str()).collect::<Vec<_>>(), vec!["d1"]);
+        // Single-venue strategies and untagged orders have no twins
+        assert!(twins(&open[2], &legs, &open).is_empty());
+        assert!(twins(&open[4], &legs, &open).is_empty());
+
+        let mut traders = MockTraders::default();
+        let failed = cancel_linked(&mut traders, &open[0], &found).await;
+        assert!(failed.is_empty());
+        assert_eq!(traders.0[&ExchangeId::Hyperliquid].cancelled, vec!["h1"]);
+        assert_eq!(traders.0[&ExchangeId::Dydx].cancelled, vec!["d1"]);
+    }
+}
+
+#[cfg(test)]
+mod trader_tests {
+    use hyperliquid_rust_sdk::ExchangeResponseStatus;
+    use serde_json::json;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::trading::hyperliquid_service::cloid_hex;
+    use crate::trading::orders::Order;
+    use async_trait::async_trait;
+    use crate::aggregator::symbol::Symbol;
+    use crate::error::{AggregatorError, Result};
+    use crate::trading::positions::Position;
+    use crate::trading::trader::{hl_status, is_post_only_cross, ExchangeTrader, TradeResult};
+    use crate::trading::TradeRequest;
+    use crate::trading::wallet::parse_order_id;
+
+    fn hl_response(value: serde_json::Value) -> ExchangeResponseStatus {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_cloid_hex() {
+        let cloid = uuid::Uuid::from_u128(0x1f);
+        assert_eq!(cloid_hex(cloid), "0x0000000000000000000000000000001f");
+    }
+
+    #[test]
+    fn hyperliquid_fill_carries_price_and_size() {
+        let result = TradeResult::from_hl_response(hl_response(json!({
+            "status": "ok",
+            "response": {"type": "order", "data": {"statuses": [{"filled": {"totalSz": "0.5", "avgPx": "65000.5", "oid": 42}}]}},
+        })));
+        assert!(result.is_accepted());
+        assert_eq!(result.exchange, ExchangeId::Hyperliquid);
+        assert_eq!(result.order_id, "42");
+        assert_eq!(result.tx_hash, "order");
+        assert_eq!(result.avg_price, Some(65000.5));
+        assert_eq!(result.filled_size, Some(0.5));
+    }
+
+    #[test]
+    fn hyperliquid_resting_order_has_no_fill() {
+        let result = TradeResult::from_hl_response(hl_response(json!({
+            "status": "ok",
+            "response": {"type": "order", "data": {"statuses": [{"resting": {"oid": 7}}]}},
+        })));
+        assert_eq!(result.order_id, "7");
+        assert_eq!(result.avg_price, None);
+        assert_eq!(result.filled_size, None);
+    }
+
+    #[test]
+    fn hyperliquid_rejections_keep_the_reason() {
+        let per_order = TradeResult::from_hl_response(hl_response(json!({
+            "status": "ok",
+            "response": {"type": "order", "data": {"statuses": [{"error": "Trading is halted"}]}},
+        })));
+        assert!(!per_order.is_accepted());
+        assert_eq!(per_order.tx_hash, "Trading is halted");
+
+        let whole = TradeResult::from_hl_response(ExchangeResponseStatus::Err("Insufficient margin".to_string()));
+        assert_eq!(whole, TradeResult::rejected(ExchangeId::Hyperliquid, "Insufficient margin"));
+    }
+
+    #[test]
+    fn hyperliquid_cancel_refused_inside_ok_response_is_an_error() {
+        let cancelled = hl_status(hl_response(json!({
+            "status": "ok",
+            "response": {"type": "cancel", "data": {"statuses": ["success"]}},
+        })));
+        assert_eq!(cancelled.unwrap(), "cancel");
+
+        let refused = hl_status(hl_response(json!({
+            "status": "ok",
+            "response": {"type": "cancel", "data": {"statuses": [{"error": "Order was never placed, already canceled, or filled."}]}},
+        })));
+        assert!(matches!(refused, Err(AggregatorError::OrderRejected { reason }) if reason.starts_with("Order was never placed")));
+    }
+
+    #[test]
+    fn post_only_crossing_is_recognised_on_both_venues() {
+        assert!(is_post_only_cross("Post only order would have immediately matched, bbo was 64999@65000. asset=0"));
+        assert!(is_post_only_cross("ErrPostOnlyWouldCrossMakerOrder: Post-only order would cross one or more maker orders"));
+        assert!(!is_post_only_cross("Trading is halted"));
+        assert!(!is_post_only_cross("Order would cross the maximum leverage"));
+    }
+
+    fn dydx_order(order_id: &str) -> Order {
+        Order {
+            exchange: ExchangeId::Dydx,
+            asset: "ETH-USD".to_string(),
+            size: 1.0,
+            price: 2_000.0,
+            side: "Buy".to_string(),
+            status: "Open".to_string(),
+            order_id: order_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn dydx_order_ids_cancel_from_the_own_subaccount() {
+        // client:clob_pair:flags:indexer subaccount UUID
+        let listed = "1234:1:64:0b1c5f8e-1d2a-5a49-9d13-2c0a1b6e4f77";
+        let id = parse_order_id(listed, "dydx1owner").unwrap();
+        assert_eq!((id.client_id, id.clob_pair_id, id.order_flags), (1234, 1, 64));
+        let subaccount = id.subaccount_id.unwrap();
+        assert_eq!((subaccount.owner.as_str(), subaccount.number), ("dydx1owner", 0));
+        assert!(parse_order_id("1234:1", "dydx1owner").is_err());
+
+        assert!(!dydx_order(listed).is_short_term());
+        assert!(dydx_order("1234:1:0:0b1c5f8e-1d2a-5a49-9d13-2c0a1b6e4f77").is_short_term());
+    }
+
+    // A plugged-in venue implementing only what trading needs
+    struct BareVenue;
+
+    #[async_trait(?Send)]
+    impl ExchangeTrader for BareVenue {
+        fn exchange(&self) -> ExchangeId {
+            ExchangeId::Custom("bare".to_string())
+        }
+
+        async fn place_trade(&mut self, _request: TradeRequest) -> Result<TradeResult> {
+            Ok(TradeResult::rejected(self.exchange(), "unused"))
+        }
+
+        async fn cancel_order(&mut self, _order: &Order) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_open_orders(&self) -> Result<Vec<Order>> {
+            Ok(vec![dydx_order("7")])
+        }
+
+        async fn get_positions(&self) -> Result<Vec<Position>> {
+            Ok(Vec::new())
+        }
+
+        async fn close_position(&mut self, _symbol: &Symbol, _size: f64) -> Result<String> {
+            Ok(String::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn venue_without_history_falls_back_to_defaults() {
+        let venue = BareVenue;
+        let (open, filled) = venue.order_state().await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert!(filled.is_empty());
+        assert!(venue.fills().await.unwrap().is_empty());
+        assert!(venue.fills_since(0, &mut |_| panic!("no pages to report")).await.unwrap().is_empty());
+        assert!(venue.historical_orders(10).await.unwrap().is_empty());
+        assert!(venue.funding_payments(0).await.unwrap().is_empty());
+        assert_eq!(venue.account_equity().await.unwrap_err().to_string(), "Exchange error: Equity not supported on bare");
+        assert!(venue.free_collateral().await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod dydx_sizing_tests {
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+    use crate::aggregator::symbol::Symbol;
+    use crate::trading::dydx_service::{OrderSize, TradeRequest};
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn usd_size_is_converted_at_the_price() {
+        let size = OrderSize::Usd(1000.0).in_base(&decimal("50000")).unwrap();
+        assert_eq!(size, decimal("0.02"));
+    }
+
+    #[test]
+    fn base_size_ignores_the_price() {
+        let size = OrderSize::Base(0.5).in_base(&decimal("50000")).unwrap();
+        assert_eq!(size, decimal("0.5"));
+    }
+
+    #[test]
+    fn usd_size_needs_a_positive_price() {
+        assert!(OrderSize::Usd(1000.0).in_base(&decimal("0")).is_err());
+        assert!(OrderSize::Base(0.5).in_base(&decimal("0")).is_ok());
+    }
+
+    #[test]
+    fn closing_a_position_trades_its_full_size_in_the_asset() {
+        let btc = Symbol::parse_user_input("BTC").unwrap();
+        let close_short = TradeRequest::close(&btc, -2.5);
+        assert!(close_short.is_buy);
+        assert!(close_short.reduce_only);
+        assert_eq!(close_short.size, OrderSize::Base(2.5));
+        assert_eq!(close_short.size.in_base(&decimal("60000")).unwrap(), decimal("2.5"));
+
+        let close_long = TradeRequest::close(&btc, 0.75);
+        assert!(!close_long.is_buy);
+        assert_eq!(close_long.size, OrderSize::Base(0.75));
+    }
+}
+
+#[cfg(test)]
+mod dydx_node_tests {
+    use async_trait::async_trait;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use dydx::indexer::Height;
+    use dydx::node::{Account, Address, NodeError, OrderGoodUntil, OrderId, TxHash, Wallet};
+    use dydx_proto::cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+    use dydx_proto::dydxprotocol::clob::Order as NodeOrder;
+    use crate::aggregator::endpoints;
+    use crate::trading::dydx_service::{DydxNode, DydxService, SharedNode};
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art";
+
+    type CallLog = Arc<std::sync::Mutex<Vec<(&'static str, &'static str)>>>;
+
+    // Every call takes a while, so a second caller arrives mid-call
+    struct SlowNode {
+        log: CallLog,
+    }
+
+    impl SlowNode {
+        async fn call(&self, name: &'static str) {
+            self.log.lock().unwrap().push(("start", name));
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.log.lock().unwrap().push(("end", name));
+        }
+    }
+
+    #[async_trait]
+    impl DydxNode for SlowNode {
+        async fn latest_block_height(&mut self) -> Result<Height, NodeError> {
+            self.call("block height").await;
+            Ok(Height(100))
+        }
+
+        async fn place_order(&mut self, _account: &mut Account, _order: NodeOrder) -> Result<TxHash, NodeError> {
+            self.call("place").await;
+            Ok("place".to_string())
+        }
+
+        async fn cancel_order(&mut self, _account: &mut Account, _order_id: OrderId, _until: OrderGoodUntil) -> Result<TxHash, NodeError> {
+            self.call("cancel").await;
+            Ok("cancel".to_string())
+        }
+
+        async fn account_balances(&mut self, _address: &Address) -> Result<Vec<Coin>, NodeError> {
+            self.call("balances").await;
+            Ok(Vec::new())
+        }
+    }
+
+    fn service(node: &SharedNode) -> DydxService {
+        let account = Wallet::from_mnemonic(MNEMONIC).unwrap().account_offline(0).unwrap();
+        DydxService::with_node(node.clone(), endpoints::dydx().indexer_config(), account)
+    }
+
+    // On the single-threaded test runtime a lock that blocked the thread
+    // would wedge both calls for good
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_place_and_cancel_take_turns_on_the_node() {
+        let log = CallLog::default();
+        let node: SharedNode = Arc::new(Mutex::new(Box::new(SlowNode { log: log.clone() })));
+        let (mut placer, mut canceller) = (service(&node), service(&node));
+
+        let (placed, cancelled) = tokio::time::timeout(Duration::from_secs(10), async {
+            tokio::join!(placer.broadcast_order(NodeOrder::default()), canceller.cancel_order(OrderId::default()))
+        }).await.expect("place and cancel wedged on the node lock");
+        assert_eq!(placed.unwrap(), "place");
+        assert_eq!(cancelled.unwrap(), "cancel");
+
+        // Each call ran to its end before the next one started
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 6);
+        for pair in log.chunks(2) {
+            assert_eq!((pair[0].0, pair[1].0, pair[0].1), ("start", "end", pair[1].1), "{:?}", log);
+        }
+    }
+}
+
+#[cfg(test)]
+mod bridge_tests {
+    use bech32::ToBase32;
+    use ethers::types::U256;
+    use tokio::sync::mpsc;
+    use crate::trading::bridge::{self, cctp_recipient, from_usdc_units, usdc_units, BridgeEvent, BridgeReceipt};
+
+    #[test]
+    fn amounts_are_scaled_to_usdc_decimals() {
+        assert_eq!(usdc_units(12.5).unwrap(), U256::from(12_500_000u64));
+        assert_eq!(usdc_units(0.1).unwrap(), U256::from(100_000u64));
+        assert!(usdc_units(0.0).is_err());
+        assert!(usdc_units(-5.0).is_err());
+        assert!(usdc_units(f64::NAN).is_err());
+        assert_eq!(from_usdc_units(U256::from(2_500_000u64)), 2.5);
+        assert_eq!(from_usdc_units(U256::MAX), f64::INFINITY);
+    }
+
+    #[test]
+    fn recipient_is_the_address_bytes_left_padded() {
+        let bytes: Vec<u8> = (1..=20).collect();
+        let address = bech32::encode("dydx", bytes.to_base32(), bech32::Variant::Bech32).unwrap();
+        let recipient = cctp_recipient(&address).unwrap();
+        assert_eq!(&recipient.as_bytes()[..12], &[0u8; 12]);
+        assert_eq!(&recipient.as_bytes()[12..], bytes.as_slice());
+        assert!(cctp_recipient("not-an-address").is_err());
+    }
+
+    #[test]
+    fn events_describe_each_step() {
+        let short = BridgeEvent::AllowanceChecked { allowance: 0.0, needed: 100.0 };
+        assert_eq!(short.describe(), "Allowance 0.00 USDC is short of 100.00; approving");
+        let enough = BridgeEvent::AllowanceChecked { allowance: f64::INFINITY, needed: 100.0 };
+        assert!(enough.describe().starts_with("Allowance ok"));
+        let confirmed = BridgeEvent::BurnConfirmed { tx_hash: "0xburn".to_string(), block: Some(42) };
+        assert_eq!(confirmed.describe(), "Burn confirmed in block 42: 0xburn");
+
+        let receipt = BridgeReceipt { approve_tx: None, burn_tx: "0xburn".to_string(), amount: 25.0, recipient: "dydx1abc".to_string() };
+        assert!(receipt.describe().contains("Bridged 25.00 USDC to dydx1abc"));
+        assert!(receipt.describe().contains("0xburn"));
+    }
+
+    #[tokio::test]
+    async fn events_reach_the_receiver_and_survive_it_going_away() {
+        let (progress, mut events) = mpsc::channel(4);
+        let progress = Some(progress);
+        bridge::emit(&progress, BridgeEvent::BurnSubmitted { tx_hash: "0xburn".to_string() }).await;
+        assert_eq!(events.recv().await, Some(BridgeEvent::BurnSubmitted { tx_hash: "0xburn".to_string() }));
+
+        drop(events);
+        bridge::emit(&progress, BridgeEvent::BurnConfirmed { tx_hash: "0xburn".to_string(), block: None }).await;
+        bridge::emit(&None, BridgeEvent::Approved { tx_hash: "0xapprove".to_string() }).await;
+    }
+}
+
+#[cfg(test)]
+mod bridge_tracking_tests {
+    use crate::error::{AggregatorError, Result};
+    use async_trait::async_trait;
+    use ethers::abi::{self, Token};
+    use ethers::types::{Log, H256};
+    use ethers::utils::keccak256;
+    use std::cell::{Cell, RefCell};
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use crate::trading::bridge::{self, burn_message, has_arrived, message_hash, Attestation, BridgeEvent, BridgePhase, BridgeTracker, PendingBridge, PendingBridges};
+
+    fn store_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("bridge_{}_{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn pending(baseline: Option<f64>) -> PendingBridge {
+        PendingBridge::new("0xburn".to_string(), 100.0, "dydx1abc".to_string(), baseline, 1)
+    }
+
+    /// Mined after `mined_after` lookups, attested after `attested_after`;
+    /// balances are handed out in order, the last one repeating
+    struct MockTracker {
+        mined_after: u32,
+        attested_after: u32,
+        balances: RefCell<Vec<f64>>,
+        lookups: Cell<u32>,
+        attestations: Cell<u32>,
+        fail_once: Cell<bool>,
+    }
+
+    impl MockTracker {
+        fn new(mined_after: u32, attested_after: u32, balances: Vec<f64>) -> Self {
+            Self { mined_after, attested_after, balances: RefCell::new(balances), lookups: Cell::new(0), attestations: Cell::new(0), fail_once: Cell::new(false) }
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl BridgeTracker for MockTracker {
+        async fn burn_message(&self, _burn_tx: &str) -> Result<Option<Vec<u8>>> {
+            if self.fail_once.replace(false) {
+                return Err(AggregatorError::Ethereum("rpc unavailable".to_string()));
+            }
+            self.lookups.set(self.lookups.get() + 1);
+            Ok((self.lookups.get() > self.mined_after).then(|| b"message".to_vec()))
+        }
+
+        async fn attestation(&self, _message_hash: &str) -> Result<Attestation> {
+            self.attestations.set(self.attestations.get() + 1);
+            Ok(if self.attestations.get() > self.attested_after { Attestation::Complete("0xsig".to_string()) } else { Attestation::Pending })
+        }
+
+        async fn dydx_usdc_balance(&self) -> Result<f64> {
+            let mut balances = self.balances.borrow_mut();
+            Ok(if balances.len() > 1 { balances.remove(0) } else { balances[0] })
+        }
+    }
+
+    #[test]
+    fn phases_read_as_progress() {
+        assert_eq!(BridgePhase::AttestationPending.progress(), "burn confirmed → attestation pending");
+        assert_eq!(BridgePhase::Minted.progress(), "burn confirmed → attested → minted");
+        assert_eq!(BridgeEvent::Phase(BridgePhase::BurnSubmitted).describe(), "Bridge: burn submitted");
+        assert_eq!(pending(None).describe(), "100.00 USDC 0xburn: burn submitted");
+    }
+
+    #[test]
+    fn pending_bridges_survive_a_reload() {
+        let path = store_path("reload");
+        let mut store = PendingBridges::load(path.clone()).unwrap();
+        store.set(pending(Some(5.0))).unwrap();
+        let mut moved = pending(Some(5.0));
+        moved.phase = BridgePhase::Attested;
+        store.set(moved.clone()).unwrap();
+
+        let store = PendingBridges::load(path.clone()).unwrap();
+        assert_eq!(store.bridges(), &[moved]);
+        assert_eq!(store.get("0xBURN").unwrap().phase, BridgePhase::Attested);
+
+        let mut store = store;
+        store.remove("0xburn").unwrap();
+        assert!(PendingBridges::load(path.clone()).unwrap().bridges().is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn attestation_is_complete_only_with_a_signature() {
+        assert_eq!(Attestation::from_json(r#"{"attestation":"0xabc","status":"complete"}"#).unwrap(), Attestation::Complete("0xabc".to_string()));
+        assert_eq!(Attestation::from_json(r#"{"attestation":"PENDING","status":"pending_confirmations"}"#).unwrap(), Attestation::Pending);
+        assert_eq!(Attestation::from_json(r#"{"status":"complete"}"#).unwrap(), Attestation::Pending);
+        assert!(Attestation::from_json("not json").is_err());
+    }
+
+    #[test]
+    fn message_is_read_from_the_message_sent_log() {
+        let message = vec![7u8; 40];
+        let other = Log { topics: vec![H256::from(keccak256("Transfer(address,address,uint256)"))], data: abi::encode(&[Token::Bytes(vec![1])]).into(), ..Default::default() };
+        let sent = Log { topics: vec![H256::from(keccak256("MessageSent(bytes)"))], data: abi::encode(&[Token::Bytes(message.clone())]).into(), ..Default::default() };
+        assert_eq!(burn_message(&[other.clone(), sent]), Some(message.clone()));
+        assert_eq!(burn_message(&[other]), None);
+        assert_eq!(message_hash(&message), format!("{:#x}", H256::from(keccak256(&message))));
+    }
+
+    #[test]
+    fn arrival_allows_for_fees() {
+        assert!(has_arrived(10.0, 109.5, 100.0));
+        assert!(!has_arrived(10.0, 100.0, 100.0));
+    }
+
+    #[tokio::test]
+    async fn tracking_walks_every_phase_then_forgets_the_bridge() {
+        let path = store_path("minted");
+        let mut store = PendingBridges::load(path.clone()).unwrap();
+        let mut bridge = pending(Some(10.0));
+        store.set(bridge.clone()).unwrap();
+        let tracker = MockTracker::new(1, 2, vec![10.0, 10.0, 109.9]);
+        tracker.fail_once.set(true);
+        let (progress, mut events) = mpsc::channel(8);
+
+        bridge::track(&tracker, &mut bridge, &mut store, &Some(progress), Duration::from_millis(1), Duration::from_secs(5)).await.unwrap();
+
+        assert_eq!(bridge.phase, BridgePhase::Minted);
+        assert_eq!(bridge.message_hash, Some(message_hash(b"message")));
+        let mut phases = Vec::new();
+        while let Ok(BridgeEvent::Phase(phase)) = events.try_recv() {
+            phases.push(phase);
+        }
+        assert_eq!(phases, vec![BridgePhase::AttestationPending, BridgePhase::Attested, BridgePhase::Minted]);
+        assert!(PendingBridges::load(path.clone()).unwrap().bridges().is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn a_timeout_leaves_the_bridge_saved_where_it_got_to() {
+        let path = store_path("timeout");
+        let mut store = PendingBridges::load(path.clone()).unwrap();
+        let mut bridge = pending(None);
+        store.set(bridge.clone()).unwrap();
+        let tracker = MockTracker::new(0, u32::MAX, vec![42.0]);
+
+        bridge::track(&tracker, &mut bridge, &mut store, &None, Duration::from_millis(1), Duration::from_millis(20)).await.unwrap();
+
+        assert_eq!(bridge.phase, BridgePhase::AttestationPending);
+        let saved = PendingBridges::load(path.clone()).unwrap();
+        let saved = saved.get("0xburn").unwrap();
+        assert_eq!(saved.phase, BridgePhase::AttestationPending);
+        // No balance from before the burn: the first one read stands in
+        assert_eq!(saved.baseline_balance, Some(42.0));
+        assert!(saved.message.is_some());
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod dydx_config_tests {
+    use crate::trading::dydx_config::{self, EMBEDDED_MAINNET};
+
+    #[test]
+    fn embedded_config_needs_no_file_on_disk() {
+        assert!(dydx_config::override_path().is_none());
+        let config = dydx_config::client_config().unwrap();
+        assert_eq!(config.indexer.rest.endpoint, "https://indexer.dydx.trade");
+        assert_eq!(config.node.endpoint, "http://dydx-dao-grpc-1.polkachu.com:23890");
+    }
+
+    #[test]
+    fn override_file_replaces_the_embedded_config() {
+        let path = std::env::temp_dir().join(format!("dydx_node_{}.toml", std::process::id()));
+        std::fs::write(&path, EMBEDDED_MAINNET.replace("dydx-dao-grpc-1.polkachu.com:23890", "localhost:9090")).unwrap();
+        let config = dydx_config::from_file(&path).unwrap();
+        assert_eq!(config.node.endpoint, "http://localhost:9090");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn testnet_has_its_own_embedded_config() {
+        let config = dydx_config::embedded(true).unwrap();
+        assert_eq!(config.indexer.rest.endpoint, "https://indexer.v4testnet.dydx.exchange");
+        assert_ne!(config.node.endpoint, dydx_config::embedded(false).unwrap().node.endpoint);
+    }
+
+    #[test]
+    fn missing_or_broken_config_is_an_error() {
+        let missing = std::env::temp_dir().join(format!("dydx_node_missing_{}.toml", std::process::id()));
+        let error = dydx_config::from_file(&missing).unwrap_err();
+        assert!(error.to_string().contains("Failed to read dYdX config"));
+        assert!(dydx_config::parse("[node]\nendpoint = 1").is_err());
+    }
+}
+
+#[cfg(test)]
+mod order_validation_tests {
+    use crate::aggregator::metadata::MarketSpec;
+    use crate::error::AggregatorError;
+    use crate::test_fixtures;
+    use crate::trading::hyperliquid_service::hl_time_in_force;
+    use crate::trading::validation::OrderValidationError;
+    use crate::trading::{OrderType, TimeInForce, TradeRequest};
+
+    // Hyperliquid's BTC: $10 minimum, five size decimals, 40x
+    fn spec() -> MarketSpec {
+        MarketSpec {
+            base: "BTC".to_string(),
+            max_leverage: 40.0,
+            size_decimals: Some(5),
+            tick_size: None,
+            step_size: Some(0.00001),
+            margin_tiers: Vec::new(),
+            initial_margin_fraction: None,
+            maintenance_margin_fraction: None,
+            min_notional: 10.0,
+        }
+    }
+
+    fn request(order_type: OrderType, usd_value: f64) -> TradeRequest {
+        TradeRequest { leverage: 5, cross_margin: Some(true), ..test_fixtures::request(order_type, true, usd_value) }
+    }
+
+    #[test]
+    fn test_valid_order_passes() {
+        assert_eq!(request(OrderType::Market, 50.0).validate(&spec(), 65_000.0), Ok(()));
+        let limit = TradeRequest { price: Some(60_000.0), ..request(OrderType::Limit, 50.0) };
+        assert_eq!(limit.validate(&spec(), 65_000.0), Ok(()));
+    }
+
+    #[test]
+    fn test_below_minimum_notional() {
+        let err = request(OrderType::Market, 5.0).validate(&spec(), 65_000.0).unwrap_err();
+        assert_eq!(err, OrderValidationError::BelowMinNotional { notional: 5.0, minimum: 10.0 });
+        assert_eq!(AggregatorError::from(err).to_string(), "Order below $10 minimum ($5.00)");
+        // Closing a small remainder is still allowed
+        let close = TradeRequest { reduce_only: true, ..request(OrderType::Market, 5.0) };
+        assert_eq!(close.validate(&spec(), 65_000.0), Ok(()));
+    }
+
+    #[test]
+    fn test_size_rounding_to_zero() {
+        let dust = MarketSpec { min_notional: 0.0, ..spec() };
+        let result = request(OrderType::Market, 0.3).validate(&dust, 65_000.0);
+        assert!(matches!(result, Err(OrderValidationError::SizeRoundsToZero { step, .. }) if step == 0.00001));
+    }
+
+    #[test]
+    fn test_leverage_over_the_cap() {
+        let request = TradeRequest { leverage: 50, ..request(OrderType::Market, 100.0) };
+        assert_eq!(
+            request.validate(&spec(), 65_000.0),
+            Err(OrderValidationError::LeverageTooHigh { base: "BTC".to_string(), leverage: 50, max: 40.0 })
+        );
+    }
+
+    #[test]
+    fn test_post_only_is_for_limit_orders() {
+        let limit = TradeRequest { price: Some(60_000.0), post_only: true, ..request(OrderType::Limit, 100.0) };
+        assert_eq!(limit.validate(&spec(), 65_000.0), Ok(()));
+        let market = TradeRequest { post_only: true, ..request(OrderType::Market, 100.0) };
+        assert_eq!(market.validate(&spec(), 65_000.0), Err(OrderValidationError::PostOnlyNotLimit));
+    }
+
+    #[test]
+    fn test_time_in_force_defaults_by_order_type() {
+        assert_eq!(request(OrderType::Market, 100.0).effective_time_in_force(), TimeInForce::Ioc);
+        assert_eq!(request(OrderType::Limit, 100.0).effective_time_in_force(), TimeInForce::Gtc);
+        let post_only = TradeRequest { post_only: true, ..request(OrderType::Limit, 100.0) };
+        assert_eq!(post_only.effective_time_in_force(), TimeInForce::PostOnly);
+    }
+
+    #[test]
+    fn test_impossible_time_in_force_combinations() {
+        let with = |order_type: OrderType, time_in_force: TimeInForce| TradeRequest { time_in_force: Some(time_in_force), ..request(order_type, 100.0) };
+        assert_eq!(
+            with(OrderType::Market, TimeInForce::Fok).validate_time_in_force(),
+            Err(OrderValidationError::InvalidTimeInForce { order: "market".to_string(), time_in_force: TimeInForce::Fok })
+        );
+        assert!(with(OrderType::Market, TimeInForce::Ioc).validate_time_in_force().is_ok());
+        for time_in_force in [TimeInForce::Gtc, TimeInForce::Ioc, TimeInForce::Fok, TimeInForce::PostOnly] {
+            assert!(with(OrderType::Limit, time_in_force).validate_time_in_force().is_ok());
+        }
+        assert_eq!(with(OrderType::StopMarket { trigger_price: 90.0 }, TimeInForce::PostOnly).validate_time_in_force(), Err(OrderValidationError::PostOnlyNotLimit));
+
+        // An IOC order never rests, so it can't be long-term
+        let ioc_long_term = TradeRequest { good_til_secs: Some(3600), ..with(OrderType::Limit, TimeInForce::Ioc) };
+        assert_eq!(ioc_long_term.validate_time_in_force(), Err(OrderValidationError::GoodTilNotResting));
+        let gtc_long_term = TradeRequest { good_til_secs: Some(3600), ..with(OrderType::Limit, TimeInForce::Gtc) };
+        assert!(gtc_long_term.validate_time_in_force().is_ok());
+
+        let conflicting = TradeRequest { post_only: true, ..with(OrderType::Limit, TimeInForce::Ioc) };
+        assert!(matches!(conflicting.validate_time_in_force(), Err(OrderValidationError::InvalidTimeInForce { .. })));
+    }
+
+    #[test]
+    fn test_hyperliquid_time_in_force_names() {
+        assert_eq!(hl_time_in_force(TimeInForce::Gtc).unwrap(), "Gtc");
+        assert_eq!(hl_time_in_force(TimeInForce::PostOnly).unwrap(), "Alo");
+        assert!(hl_time_in_force(TimeInForce::Fok).is_err());
+    }
+
+    #[test]
+    fn test_limit_orders_need_a_price() {
+        assert_eq!(request(OrderType::Limit, 100.0).validate(&spec(), 65_000.0), Err(OrderValidationError::MissingLimitPrice));
+        let zero = TradeRequest { price: Some(0.0), ..request(OrderType::Limit, 100.0) };
+        assert_eq!(zero.validate(&spec(), 65_000.0), Err(OrderValidationError::MissingLimitPrice));
+    }
+}
+
+#[cfg(test)]
+mod fill_report_tests {
+    use std::cell::Cell;
+    use std::time::Duration;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::test_fixtures;
+    use crate::trading::fill_report::{poll_until_terminal, FillReport, FillStatus};
+    use crate::trading::orders::{parse_dydx_order_outcome, OrderOutcome};
+    use crate::trading::positions::episodes::Fill;
+
+    fn fill(order_id: &str, price: f64, size: f64, fee: f64) -> Fill {
+        Fill { fee, order_id: order_id.to_string(), ..test_fixtures::fill(ExchangeId::Hyperliquid, true, price, size, 0) }
+    }
+
+    #[test]
+    fn test_report_sums_the_orders_fills() {
+        let fills = [fill("7", 100.0, 1.0, 0.1), fill("8", 500.0, 9.0, 9.0), fill("7", 103.0, 2.0, 0.2)];
+        let report = FillReport::from_fills(&fills, "7", FillStatus::Terminal(OrderOutcome::Filled));
+        assert_eq!(report.filled_size, 3.0);
+        assert!((report.avg_fill_price.unwrap() - 102.0).abs() < 1e-9);
+        assert!((report.fees - 0.3).abs() < 1e-9);
+        assert_eq!(report.describe(), "Filled 3.0000 @ 102.00, fees $0.30");
+    }
+
+    #[test]
+    fn test_report_without_fills() {
+        let report = FillReport::from_fills(&[fill("8", 100.0, 1.0, 0.1)], "7", FillStatus::NonTerminal);
+        assert_eq!(report.filled_size, 0.0);
+        assert_eq!(report.avg_fill_price, None);
+        assert_eq!(report.describe(), "Open, nothing filled");
+    }
+
+    #[tokio::test]
+    async fn test_poll_stops_at_terminal_outcome() {
+        let polls = Cell::new(0);
+        let status = poll_until_terminal(Duration::from_secs(5), Duration::ZERO, || {
+            polls.set(polls.get() + 1);
+            let outcome = (polls.get() == 3).then_some(OrderOutcome::CancelledByUser);
+            async move { Ok::<_, String>(outcome) }
+        }).await;
+        assert_eq!(status, Ok(FillStatus::Terminal(OrderOutcome::CancelledByUser)));
+        assert_eq!(polls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_poll_times_out_as_non_terminal() {
+        let status = poll_until_terminal(Duration::from_millis(20), Duration::from_millis(5), || async { Ok::<_, String>(None) }).await;
+        assert_eq!(status, Ok(FillStatus::NonTerminal));
+    }
+
+    #[tokio::test]
+    async fn test_poll_passes_errors_through() {
+        let status = poll_until_terminal(Duration::from_secs(5), Duration::ZERO, || async { Err::<Option<OrderOutcome>, _>("down".to_string()) }).await;
+        assert_eq!(status, Err("down".to_string()));
+    }
+
+    #[test]
+    fn test_parse_dydx_order_outcome() {
+        let body = r#"[
+            {"clientId": "11", "clobPairId": "0", "orderFlags": "64", "subaccountNumber": 0, "status": "OPEN"},
+            {"clientId": "12", "clobPairId": "1", "orderFlags": "0", "subaccountNumber": 0, "status": "CANCELED", "removalReason": "ORDER_REMOVAL_REASON_USER_CANCELED"}
+        ]"#;
+        assert_eq!(parse_dydx_order_outcome(body, "11:0:64:0").unwrap(), None);
+        assert_eq!(parse_dydx_order_outcome(body, "12:1:0:0").unwrap(), Some(OrderOutcome::CancelledByUser));
+        assert_eq!(parse_dydx_order_outcome(body, "13:1:0:0").unwrap(), None);
+    }
+}
+
+#[cfg(test)]
+mod portfolio_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::trading::hl_account::{HlAccountState, HlMarginSummary};
+    use crate::trading::portfolio::PortfolioSummary;
+    use crate::trading::positions::Position;
+    use crate::trading::wallet_overview::{Availability, DydxAccount, EthBalances};
+
+    fn position(exchange: ExchangeId, asset: &str, size: f64, side: &str, entry: f64) -> Position {
+        Position {
+            exchange,
+            asset: asset.to_string(),
+            size,
+            entry_price: Some(entry),
+            liquidation_price: None,
+            unrealized_pnl: 0.0,
+            margin_used: None,
+            leverage: None,
+            roe: None,
+            side: side.to_string(),
+        }
+    }
+
+    fn summary() -> PortfolioSummary {
+        let mut summary = PortfolioSummary::default();
+        summary.wallets.hl = Availability::Ready(HlAccountState {
+            margin_summary: HlMarginSummary { account_value: 1000.0, total_margin_used: 100.0, ..Default::default() },
+            ..Default::default()
+        });
+        summary.wallets.dydx = Availability::Ready(DydxAccount { address: "dydx1xyz".to_string(), equity: 500.0, free_collateral: 450.0 });
+        summary.wallets.eth = Availability::Ready(EthBalances { address: "0xabc".to_string(), usdc: 25.0, eth: 0.01 });
+        summary.hl_positions = Availability::Ready(vec![
+            position(ExchangeId::Hyperliquid, "BTC", 0.1, "", 60_000.0),
+            position(ExchangeId::Hyperliquid, "ETH", -1.0, "", 3_000.0),
+        ]);
+        summary.dydx_positions = Availability::Ready(vec![position(ExchangeId::Dydx, "BTC-USD", 0.08, "Short", 61_000.0)]);
+        summary.marks.insert((ExchangeId::Hyperliquid, Symbol::parse_user_input("BTC").unwrap()), 62_000.0);
+        summary.marks.insert((ExchangeId::Dydx, Symbol::parse_user_input("BTC").unwrap()), 62_000.0);
+        summary
+    }
+
+    #[test]
+    fn test_totals() {
+        let summary = summary();
+        assert_eq!(summary.total_equity(), 1525.0);
+        assert_eq!(summary.total_margin_used(), 150.0);
+        // BTC long +200, BTC short -80, ETH unmarked at entry
+        assert!((summary.unrealized_pnl() - 120.0).abs() < 1e-6);
+        assert!(summary.problems().is_empty());
+    }
+
+    #[test]
+    fn test_exposure_nets_across_venues() {
+        let exposures = summary().exposures();
+        assert_eq!(exposures.len(), 2);
+        assert_eq!(exposures[0].symbol.to_string(), "ETH");
+        assert_eq!(exposures[0].net_usd, -3_000.0);
+        assert!(exposures[0].at_entry);
+        assert!((exposures[1].net_usd - 1_240.0).abs() < 1e-6);
+        assert!(!exposures[1].at_entry);
+    }
+
+    #[test]
+    fn test_failed_sources_are_flagged() {
+        let mut summary = summary();
+        summary.wallets.dydx = Availability::Failed { error: "timeout".to_string(), last: None };
+        summary.hl_positions = Availability::Failed { error: "429".to_string(), last: summary.hl_positions.value().cloned() };
+        assert_eq!(summary.total_equity(), 1025.0);
+        assert_eq!(summary.problems(), vec!["dYdX equity failed (timeout)".to_string(), "Hyperliquid positions stale (429)".to_string()]);
+        assert!(summary.lines().last().unwrap().starts_with("Not counted or stale: dYdX equity failed"));
+    }
+}
@@ -0,0 +1,323 @@
+use crate::error::{AggregatorError, Result};
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::time::Duration;
+use dydx::indexer::types::{OrderSide, OrderType as DydxOrderType};
+use dydx::node::OrderTimeInForce;
+use hyperliquid_rust_sdk::{ExchangeDataStatus, ExchangeResponseStatus};
+use super::{OrderType, TimeInForce, TradeRequest};
+use super::dydx_service::OrderSize;
+use super::farm::FundingPayment;
+use super::fill_report::FillReport;
+use super::hyperliquid_service::{cloid_hex, HyperliquidService};
+use super::orders::{HistoricalOrder, Order};
+use super::positions::Position;
+use super::positions::episodes::Fill;
+use super::wallet::WalletManager;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+
+/// A placed order, in the same shape whatever the venue
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeResult {
+    pub exchange: ExchangeId,
+    // Empty when the venue turned the order down
+    pub order_id: String,
+    // Transaction hash on dYdX, the response type on Hyperliquid; the
+    // rejection reason when `order_id` is empty
+    pub tx_hash: String,
+    // Known only when the venue reports the fill with the placement
+    pub avg_price: Option<f64>,
+    pub filled_size: Option<f64>,
+    // Client order id the order went out under, where the venue takes one
+    pub cloid: Option<String>,
+}
+
+impl TradeResult {
+    pub fn rejected(exchange: ExchangeId, reason: impl Into<String>) -> Self {
+        Self { exchange, order_id: String::new(), tx_hash: reason.into(), avg_price: None, filled_size: None, cloid: None }
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        !self.order_id.is_empty()
+    }
+
+    /// Read the first order status out of a Hyperliquid response. Per-order
+    /// errors, e.g. a trading halt, come back as rejections.
+    pub fn from_hl_response(response: ExchangeResponseStatus) -> Self {
+        let exchange = ExchangeId::Hyperliquid;
+        let response = match response {
+            ExchangeResponseStatus::Ok(response) => response,
+            ExchangeResponseStatus::Err(message) => return Self::rejected(exchange, message),
+        };
+        let accepted = |order_id: u64, avg_price: Option<f64>, filled_size: Option<f64>| Self {
+            exchange: ExchangeId::Hyperliquid,
+            order_id: order_id.to_string(),
+            tx_hash: response.response_type.clone(),
+            avg_price,
+            filled_size,
+            cloid: None,
+        };
+        match response.data.as_ref().and_then(|data| data.statuses.first()) {
+            Some(ExchangeDataStatus::Resting(order)) => accepted(order.oid, None, None),
+            Some(ExchangeDataStatus::Filled(order)) => accepted(order.oid, order.avg_px.parse().ok(), order.total_sz.parse().ok()),
+            Some(ExchangeDataStatus::Error(message)) => Self::rejected(exchange, message.clone()),
+            _ => Self::rejected(exchange, response.response_type.clone()),
+        }
+    }
+}
+
+/// What the router needs from a venue to trade on it. Implemented by the
+/// built-in venues; others plug in through `TradingRouter::with_trader`.
+/// The history and account methods have defaults for venues that keep no
+/// history or don't report balances. Driven from the UI loop, so the
+/// futures needn't be `Send`.
+#[async_trait(?Send)]
+pub trait ExchangeTrader {
+    fn exchange(&self) -> ExchangeId;
+    async fn place_trade(&mut self, request: TradeRequest) -> Result<TradeResult>;
+    async fn cancel_order(&mut self, order: &Order) -> Result<()>;
+    async fn get_open_orders(&self) -> Result<Vec<Order>>;
+    async fn get_positions(&self) -> Result<Vec<Position>>;
+    /// Reduce-only market close of `size` (negative for shorts); returns the
+    /// venue's response
+    async fn close_position(&mut self, symbol: &Symbol, size: f64) -> Result<String>;
+    /// Wait up to `timeout` for order `order_id` to leave the book and report
+    /// what it filled
+    async fn wait_for_fill(&self, order_id: &str, _timeout: Duration) -> Result<FillReport> {
+        Err(AggregatorError::ExchangeError(format!("Fill tracking not supported on {} (order {})", self.exchange(), order_id)))
+    }
+    /// Open orders plus the ids of recently filled ones, for reconciling
+    /// the order store; just the open orders without a fill history
+    async fn order_state(&self) -> Result<(Vec<Order>, HashSet<String>)> {
+        Ok((self.get_open_orders().await?, HashSet::new()))
+    }
+    /// Recent fills
+    async fn fills(&self) -> Result<Vec<Fill>> {
+        Ok(Vec::new())
+    }
+    /// Every fill since `since_ms`, paging through the venue's history;
+    /// `progress` gets the running count after each page
+    async fn fills_since(&self, _since_ms: i64, _progress: &mut dyn FnMut(usize)) -> Result<Vec<Fill>> {
+        Ok(Vec::new())
+    }
+    /// The `limit` most recent terminal orders
+    async fn historical_orders(&self, _limit: usize) -> Result<Vec<HistoricalOrder>> {
+        Ok(Vec::new())
+    }
+    /// Funding paid and received since `since_ms`
+    async fn funding_payments(&self, _since_ms: i64) -> Result<Vec<FundingPayment>> {
+        Ok(Vec::new())
+    }
+    async fn account_equity(&self) -> Result<f64> {
+        Err(AggregatorError::ExchangeError(format!("Equity not supported on {}", self.exchange())))
+    }
+    async fn free_collateral(&self) -> Result<f64> {
+        Err(AggregatorError::ExchangeError(format!("Collateral not supported on {}", self.exchange())))
+    }
+}
+
+/// Whether a venue's rejection is a post-only order refused for crossing
+/// the book: Hyperliquid's "Post only order would have immediately matched"
+/// or dYdX's "post-only order would cross one or more maker orders"
+pub fn is_post_only_cross(reason: &str) -> bool {
+    let reason = reason.to_lowercase().replace('-', " ");
+    reason.contains("post only") && (reason.contains("would have immediately matched") || reason.contains("would cross"))
+}
+
+// Hyperliquid answers with a response status where other venues error,
+// and reports a refused order inside an otherwise ok response
+pub(crate) fn hl_status(status: ExchangeResponseStatus) -> Result<String> {
+    let response = match status {
+        ExchangeResponseStatus::Ok(response) => response,
+        ExchangeResponseStatus::Err(reason) => return Err(AggregatorError::OrderRejected { reason }),
+    };
+    let refused = response.data.iter().flat_map(|data| &data.statuses).find_map(|status| match status {
+        ExchangeDataStatus::Error(reason) => Some(reason.clone()),
+        _ => None,
+    });
+    if let Some(reason) = refused {
+        return Err(AggregatorError::OrderRejected { reason });
+    }
+    Ok(response.response_type)
+}
+
+#[async_trait(?Send)]
+impl ExchangeTrader for HyperliquidService {
+    fn exchange(&self) -> ExchangeId {
+        ExchangeId::Hyperliquid
+    }
+
+    async fn place_trade(&mut self, request: TradeRequest) -> Result<TradeResult> {
+        let (response, cloid) = HyperliquidService::place_trade(self, request).await?;
+        let result = TradeResult::from_hl_response(response);
+        if !result.is_accepted() && is_post_only_cross(&result.tx_hash) {
+            return Err(AggregatorError::PostOnlyWouldCross { exchange: ExchangeId::Hyperliquid, reason: result.tx_hash });
+        }
+        Ok(TradeResult { cloid: Some(cloid_hex(cloid)), ..result })
+    }
+
+    async fn cancel_order(&mut self, order: &Order) -> Result<()> {
+        let oid = order.order_id.parse::<u64>()
+            .map_err(|_| AggregatorError::ExchangeError(format!("Invalid Hyperliquid order id {}", order.order_id)))?;
+        hl_status(HyperliquidService::cancel_order(self, oid, order.asset.clone()).await?).map(|_| ())
+    }
+
+    async fn get_open_orders(&self) -> Result<Vec<Order>> {
+        Ok(HyperliquidService::get_open_orders(self).await?
+            .iter()
+            .filter_map(|order| Order::from_hl_order(order).ok())
+            .collect())
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>> {
+        HyperliquidService::get_positions(self).await
+    }
+
+    async fn close_position(&mut self, symbol: &Symbol, size: f64) -> Result<String> {
+        hl_status(HyperliquidService::close_position(self, symbol, size).await?)
+    }
+
+    async fn wait_for_fill(&self, order_id: &str, timeout: Duration) -> Result<FillReport> {
+        let oid = order_id.parse::<u64>()
+            .map_err(|_| AggregatorError::ExchangeError(format!("Invalid Hyperliquid order id {}", order_id)))?;
+        HyperliquidService::wait_for_fill(self, oid, timeout).await
+    }
+
+    async fn order_state(&self) -> Result<(Vec<Order>, HashSet<String>)> {
+        let open = ExchangeTrader::get_open_orders(self).await?;
+        Ok((open, self.get_recent_fill_order_ids().await?))
+    }
+
+    async fn fills(&self) -> Result<Vec<Fill>> {
+        self.get_fills().await
+    }
+
+    async fn fills_since(&self, since_ms: i64, progress: &mut dyn FnMut(usize)) -> Result<Vec<Fill>> {
+        HyperliquidService::fills_since(self, since_ms, progress).await
+    }
+
+    async fn historical_orders(&self, limit: usize) -> Result<Vec<HistoricalOrder>> {
+        HyperliquidService::historical_orders(self, limit).await
+    }
+
+    async fn funding_payments(&self, since_ms: i64) -> Result<Vec<FundingPayment>> {
+        HyperliquidService::funding_payments(self, since_ms).await
+    }
+
+    async fn account_equity(&self) -> Result<f64> {
+        Ok(self.get_account_state().await?.account_value())
+    }
+
+    async fn free_collateral(&self) -> Result<f64> {
+        self.get_free_collateral().await
+    }
+}
+
+#[async_trait(?Send)]
+impl ExchangeTrader for WalletManager {
+    fn exchange(&self) -> ExchangeId {
+        ExchangeId::Dydx
+    }
+
+    async fn place_trade(&mut self, request: TradeRequest) -> Result<TradeResult> {
+        let order_type = match request.order_type {
+            OrderType::Market => DydxOrderType::Market,
+            OrderType::Limit => DydxOrderType::Limit,
+            OrderType::StopMarket { .. } => DydxOrderType::StopMarket,
+            OrderType::TakeProfit { .. } => DydxOrderType::TakeProfitMarket,
+        };
+        let placed = self.place_dydx_order(
+            &request.asset,
+            if request.is_buy { OrderSide::Buy } else { OrderSide::Sell },
+            OrderSize::Usd(request.usd_value),
+            request.price,
+            request.order_type.trigger_price(),
+            order_type,
+            match request.effective_time_in_force() {
+                TimeInForce::Gtc => OrderTimeInForce::Unspecified,
+                TimeInForce::Ioc => OrderTimeInForce::Ioc,
+                TimeInForce::Fok => OrderTimeInForce::FillOrKill,
+                TimeInForce::PostOnly => OrderTimeInForce::PostOnly,
+            },
+            request.leverage as f64,
+            request.cross_margin,
+            request.max_slippage_bps,
+            request.good_til_secs,
+        ).await;
+        let (tx_hash, order_id) = match placed {
+            Err(e) if is_post_only_cross(&format!("{:#}", e)) => {
+                return Err(AggregatorError::PostOnlyWouldCross { exchange: ExchangeId::Dydx, reason: e.to_string() });
+            }
+            placed => placed?,
+        };
+        // Fills show up on the indexer later, not in the broadcast result
+        Ok(TradeResult { exchange: ExchangeId::Dydx, order_id, tx_hash, avg_price: None, filled_size: None, cloid: None })
+    }
+
+    async fn cancel_order(&mut self, order: &Order) -> Result<()> {
+        self.cancel_dydx_order(&order.order_id).await?;
+        Ok(())
+    }
+
+    async fn get_open_orders(&self) -> Result<Vec<Order>> {
+        // The indexer returns recent orders of every status
+        Ok(self.get_dydx_orders().await?
+            .iter()
+            .filter_map(|order| Order::from_dydx_order(order).ok())
+            .filter(|order| order.status == "Open")
+            .collect())
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>> {
+        Ok(self.get_dydx_positions().await?)
+    }
+
+    async fn close_position(&mut self, symbol: &Symbol, size: f64) -> Result<String> {
+        Ok(self.close_dydx_position(symbol, size).await?)
+    }
+
+    async fn wait_for_fill(&self, order_id: &str, timeout: Duration) -> Result<FillReport> {
+        Ok(self.wait_for_dydx_fill(order_id, timeout).await?)
+    }
+
+    async fn order_state(&self) -> Result<(Vec<Order>, HashSet<String>)> {
+        // The indexer returns recent orders of every status, fills included
+        let orders: Vec<Order> = self.get_dydx_orders().await?
+            .iter()
+            .filter_map(|order| Order::from_dydx_order(order).ok())
+            .collect();
+        let filled = orders.iter()
+            .filter(|order| order.status == "Filled")
+            .map(|order| order.order_id.clone())
+            .collect();
+        let open = orders.into_iter().filter(|order| order.status == "Open").collect();
+        Ok((open, filled))
+    }
+
+    async fn fills(&self) -> Result<Vec<Fill>> {
+        self.get_dydx_fills().await
+    }
+
+    async fn fills_since(&self, since_ms: i64, progress: &mut dyn FnMut(usize)) -> Result<Vec<Fill>> {
+        self.get_dydx_fills_since(since_ms, progress).await
+    }
+
+    async fn historical_orders(&self, limit: usize) -> Result<Vec<HistoricalOrder>> {
+        self.get_dydx_historical_orders(limit).await
+    }
+
+    async fn funding_payments(&self, since_ms: i64) -> Result<Vec<FundingPayment>> {
+        self.get_dydx_funding_payments(since_ms).await
+    }
+
+    async fn account_equity(&self) -> Result<f64> {
+        self.get_dydx_equity().await?
+            .ok_or_else(|| AggregatorError::WalletNotConfigured("dYdX".to_string()))
+    }
+
+    async fn free_collateral(&self) -> Result<f64> {
+        self.get_dydx_free_collateral().await?
+            .ok_or_else(|| AggregatorError::WalletNotConfigured("dYdX".to_string()))
+    }
+}
@@ -0,0 +1,180 @@
+use crate::error::{AggregatorError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use chrono::Utc;
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+
+/// How far price may retrace from its best level before the stop fires.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TrailDistance {
+    Percent(f64),
+    Absolute(f64),
+}
+
+impl TrailDistance {
+    /// "2%" or "2.5 %" is a percentage, a bare number is a price distance.
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let (number, percent) = match input.strip_suffix('%') {
+            Some(number) => (number.trim(), true),
+            None => (input, false),
+        };
+        let value: f64 = number.parse()
+            .map_err(|_| AggregatorError::InvalidInput(format!("Invalid trail distance: {:?}", input)))?;
+        if !value.is_finite() || value <= 0.0 || (percent && value >= 100.0) {
+            return Err(AggregatorError::InvalidInput(format!("Trail distance out of range: {:?}", input)));
+        }
+        Ok(if percent { Self::Percent(value) } else { Self::Absolute(value) })
+    }
+
+    fn offset(&self, watermark: f64) -> f64 {
+        match self {
+            Self::Percent(pct) => watermark * pct / 100.0,
+            Self::Absolute(distance) => *distance,
+        }
+    }
+}
+
+impl std::fmt::Display for TrailDistance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Percent(pct) => write!(f, "{}%", pct),
+            Self::Absolute(distance) => write!(f, "${}", distance),
+        }
+    }
+}
+
+/// Client-side trailing stop for one position. Only works while the app is
+/// running.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrailingStop {
+    pub exchange: ExchangeId,
+    pub symbol: Symbol,
+    pub is_long: bool,
+    pub distance: TrailDistance,
+    // Best mark price seen so far: the high for longs, the low for shorts
+    pub watermark: f64,
+    // Millis timestamp of the trigger; the close is retried until it succeeds
+    pub triggered_at: Option<i64>,
+}
+
+impl TrailingStop {
+    pub fn new(exchange: ExchangeId, symbol: Symbol, is_long: bool, distance: TrailDistance, mark: f64) -> Self {
+        Self { exchange, symbol, is_long, distance, watermark: mark, triggered_at: None }
+    }
+
+    pub fn trigger_level(&self) -> f64 {
+        let offset = self.distance.offset(self.watermark);
+        if self.is_long { self.watermark - offset } else { self.watermark + offset }
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.triggered_at.is_some()
+    }
+
+    /// Feed a mark price. Returns whether the watermark moved or the stop
+    /// fired, i.e. whether there's new state to persist.
+    pub fn update(&mut self, mark: f64, now_ms: i64) -> bool {
+        if self.is_triggered() || !mark.is_finite() || mark <= 0.0 {
+            return false;
+        }
+
+        let improved = if self.is_long { mark > self.watermark } else { mark < self.watermark };
+        if improved {
+            self.watermark = mark;
+            return true;
+        }
+
+        let retraced = if self.is_long { mark <= self.trigger_level() } else { mark >= self.trigger_level() };
+        if retraced {
+            self.triggered_at = Some(now_ms);
+        }
+        retraced
+    }
+
+    /// e.g. "trail 2% → stop $63700.00"
+    pub fn describe(&self) -> String {
+        if self.is_triggered() {
+            format!("trail {} → TRIGGERED at ${:.2}", self.distance, self.trigger_level())
+        } else {
+            format!("trail {} → stop ${:.2}", self.distance, self.trigger_level())
+        }
+    }
+}
+
+/// Trailing stops keyed by (exchange, symbol), persisted so a restart keeps
+/// the stop and its watermark.
+pub struct TrailingStops {
+    path: PathBuf,
+    stops: Vec<TrailingStop>,
+}
+
+impl TrailingStops {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("trading_aggregator");
+        fs::create_dir_all(&config_dir)?;
+        Ok(config_dir.join("trailing_stops.json"))
+    }
+
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let stops = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, stops })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.stops)?)?;
+        Ok(())
+    }
+
+    pub fn stops(&self) -> &[TrailingStop] {
+        &self.stops
+    }
+
+    pub fn get(&self, exchange: &ExchangeId, symbol: &Symbol) -> Option<&TrailingStop> {
+        self.stops.iter().find(|s| &s.exchange == exchange && &s.symbol == symbol)
+    }
+
+    /// Add or replace the stop for the stop's position.
+    pub fn set(&mut self, stop: TrailingStop) -> Result<()> {
+        self.stops.retain(|s| !(s.exchange == stop.exchange && s.symbol == stop.symbol));
+        self.stops.push(stop);
+        self.save()
+    }
+
+    pub fn remove(&mut self, exchange: &ExchangeId, symbol: &Symbol) -> Result<bool> {
+        let before = self.stops.len();
+        self.stops.retain(|s| !(&s.exchange == exchange && &s.symbol == symbol));
+        let removed = self.stops.len() != before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Feed a mark price to the matching stop. Returns the stop while it is
+    /// triggered, so a failed close is retried on the next price.
+    pub fn on_mark(&mut self, exchange: &ExchangeId, symbol: &Symbol, mark: f64) -> Option<TrailingStop> {
+        let now = Utc::now().timestamp_millis();
+        let stop = self.stops.iter_mut().find(|s| &s.exchange == exchange && &s.symbol == symbol)?;
+
+        let changed = stop.update(mark, now);
+        let triggered = stop.is_triggered().then(|| stop.clone());
+        if changed {
+            if let Err(e) = self.save() {
+                tracing::error!("Failed to save trailing stops: {}", e);
+            }
+        }
+        triggered
+    }
+}
@@ -0,0 +1,254 @@
+use crate::error::{AggregatorError, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+use super::TradeRequest;
+use super::confirmation::{Confirmation, Quote};
+use super::positions::episodes::Fill;
+use super::router::TradingRouter;
+use crate::aggregator::exchange_id::ExchangeId;
+
+// Failures in a row, with no slice getting through, before the TWAP gives up
+pub const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+/// Where slices go. The router in the app; a scripted venue in tests.
+/// Driven from the UI loop, so the futures needn't be `Send`.
+#[async_trait(?Send)]
+pub trait TwapVenue {
+    /// Returns the venue order id
+    async fn place_slice(&mut self, exchange: &ExchangeId, request: TradeRequest) -> Result<String>;
+    /// Fills of `order_id` the venue has reported so far
+    async fn slice_fills(&mut self, exchange: &ExchangeId, order_id: &str) -> Vec<Fill>;
+}
+
+#[async_trait(?Send)]
+impl TwapVenue for TradingRouter {
+    // The parent order was confirmed as a whole, so slices go out without
+    // asking again; a slice big enough to need confirmation fails. Slices are
+    // identical by design, so duplicates are forced.
+    async fn place_slice(&mut self, exchange: &ExchangeId, request: TradeRequest) -> Result<String> {
+        let quote = Quote { price: request.price.unwrap_or(0.0), size_step: None };
+        let (message, order_id) = self.place_trade(exchange, request, quote, Confirmation::None, true).await.result?;
+        if order_id.is_empty() {
+            return Err(AggregatorError::OrderRejected { reason: message });
+        }
+        Ok(order_id)
+    }
+
+    async fn slice_fills(&mut self, exchange: &ExchangeId, order_id: &str) -> Vec<Fill> {
+        self.fills().await.into_iter()
+            .filter(|fill| &fill.exchange == exchange && fill.order_id == order_id)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TwapControl {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+/// Pause, resume or cancel a running TWAP. Cancelling takes effect before the
+/// next slice; one already sent is not pulled back.
+#[derive(Debug, Clone)]
+pub struct TwapHandle {
+    control: Arc<watch::Sender<TwapControl>>,
+}
+
+impl TwapHandle {
+    pub fn pause(&self) {
+        self.set(TwapControl::Paused);
+    }
+
+    pub fn resume(&self) {
+        self.set(TwapControl::Running);
+    }
+
+    pub fn cancel(&self) {
+        self.control.send_replace(TwapControl::Cancelled);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.control.borrow() == TwapControl::Paused
+    }
+
+    // Cancelled is final
+    fn set(&self, control: TwapControl) {
+        self.control.send_if_modified(|current| {
+            let changed = *current != TwapControl::Cancelled && *current != control;
+            if changed {
+                *current = control;
+            }
+            changed
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwapProgress {
+    pub slices_sent: u32,
+    pub slices_total: u32,
+    // From the fills reported for sent slices
+    pub filled_notional: f64,
+    pub filled_size: f64,
+    pub consecutive_failures: u32,
+}
+
+impl TwapProgress {
+    fn new(slices_total: u32) -> Self {
+        Self { slices_sent: 0, slices_total, filled_notional: 0.0, filled_size: 0.0, consecutive_failures: 0 }
+    }
+
+    /// Share of slices sent, 0-100
+    pub fn percent(&self) -> f64 {
+        self.slices_sent as f64 / self.slices_total.max(1) as f64 * 100.0
+    }
+
+    pub fn average_price(&self) -> Option<f64> {
+        (self.filled_size > 0.0).then(|| self.filled_notional / self.filled_size)
+    }
+
+    /// e.g. "4/10 slices, $2000.00 filled at avg 100.2500"
+    pub fn describe(&self) -> String {
+        let filled = match self.average_price() {
+            Some(price) => format!("${:.2} filled at avg {:.4}", self.filled_notional, price),
+            None => "no fills reported yet".to_string(),
+        };
+        format!("{}/{} slices, {}", self.slices_sent, self.slices_total, filled)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.slices_sent >= self.slices_total
+    }
+
+    fn record_slice(&mut self, fills: &[Fill]) {
+        self.slices_sent += 1;
+        self.consecutive_failures = 0;
+        for fill in fills {
+            self.filled_notional += fill.price * fill.size;
+            self.filled_size += fill.size;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TwapOutcome {
+    Completed,
+    Cancelled,
+    // Stopped after too many failures in a row; holds the last error
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TwapEvent {
+    SliceSent { index: u32, order_id: String, progress: TwapProgress },
+    // The same slice is retried on the next interval
+    SliceFailed { index: u32, error: String, progress: TwapProgress },
+    Finished { outcome: TwapOutcome, progress: TwapProgress },
+}
+
+/// Splits one order into equal slices sent `interval` apart, the first right
+/// away. Slices share a strategy id so the journal groups them.
+pub struct TwapExecutor {
+    exchange: ExchangeId,
+    slice: TradeRequest,
+    slices: u32,
+    interval: Duration,
+    control: watch::Receiver<TwapControl>,
+    // Keeps the control channel open if every handle is dropped
+    handle: TwapHandle,
+    events: mpsc::UnboundedSender<TwapEvent>,
+}
+
+impl TwapExecutor {
+    pub fn new(exchange: ExchangeId, request: TradeRequest, slices: u32, interval: Duration) -> Result<(Self, TwapHandle, mpsc::UnboundedReceiver<TwapEvent>)> {
+        if slices == 0 {
+            return Err(AggregatorError::InvalidInput("A TWAP needs at least one slice".to_string()));
+        }
+        if interval.is_zero() {
+            return Err(AggregatorError::InvalidInput("TWAP interval must be positive".to_string()));
+        }
+        if !request.usd_value.is_finite() || request.usd_value <= 0.0 {
+            return Err(AggregatorError::InvalidInput("TWAP size must be positive".to_string()));
+        }
+        if request.order_type.trigger_price().is_some() {
+            return Err(AggregatorError::InvalidInput("TWAP slices can't be trigger orders".to_string()));
+        }
+
+        let slice = TradeRequest {
+            usd_value: request.usd_value / slices as f64,
+            strategy_id: request.strategy_id.or_else(|| Some(Uuid::new_v4())),
+            ..request
+        };
+        let (control, control_rx) = watch::channel(TwapControl::Running);
+        let handle = TwapHandle { control: Arc::new(control) };
+        let (events, events_rx) = mpsc::unbounded_channel();
+        let executor = Self { exchange, slice, slices, interval, control: control_rx, handle: handle.clone(), events };
+        Ok((executor, handle, events_rx))
+    }
+
+    /// What each slice sends
+    pub fn slice_request(&self) -> &TradeRequest {
+        &self.slice
+    }
+
+    /// Send every slice, or stop on cancel or after
+    /// `MAX_CONSECUTIVE_FAILURES` failures in a row.
+    pub async fn run(mut self, venue: &mut impl TwapVenue) -> (TwapOutcome, TwapProgress) {
+        let mut progress = TwapProgress::new(self.slices);
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            let control = *self.control.borrow_and_update();
+            match control {
+                TwapControl::Cancelled => return self.finish(TwapOutcome::Cancelled, progress),
+                TwapControl::Paused => {
+                    let _ = self.control.changed().await;
+                    continue;
+                }
+                TwapControl::Running => {}
+            }
+            if progress.is_complete() {
+                return self.finish(TwapOutcome::Completed, progress);
+            }
+
+            tokio::select! {
+                _ = ticker.tick() => {}
+                // Re-check before sending anything
+                _ = self.control.changed() => continue,
+            }
+
+            let index = progress.slices_sent;
+            match venue.place_slice(&self.exchange, self.slice.clone()).await {
+                Ok(order_id) => {
+                    let fills = venue.slice_fills(&self.exchange, &order_id).await;
+                    progress.record_slice(&fills);
+                    self.emit(TwapEvent::SliceSent { index, order_id, progress: progress.clone() });
+                }
+                Err(e) => {
+                    progress.consecutive_failures += 1;
+                    let error = e.to_string();
+                    self.emit(TwapEvent::SliceFailed { index, error: error.clone(), progress: progress.clone() });
+                    if progress.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                        return self.finish(TwapOutcome::Failed(error), progress);
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(&self, outcome: TwapOutcome, progress: TwapProgress) -> (TwapOutcome, TwapProgress) {
+        self.emit(TwapEvent::Finished { outcome: outcome.clone(), progress: progress.clone() });
+        (outcome, progress)
+    }
+
+    // Nobody listening is fine; the run goes on
+    fn emit(&self, event: TwapEvent) {
+        let _ = self.events.send(event);
+    }
+}
@@ -0,0 +1,83 @@
+use crate::aggregator::metadata::MarketSpec;
+use super::{OrderType, TimeInForce, TradeRequest};
+
+/// Why an order was refused before reaching the venue, worded for the UI
+/// rather than echoing the venue's response.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum OrderValidationError {
+    #[error("Order below ${minimum:.0} minimum (${notional:.2})")]
+    BelowMinNotional { notional: f64, minimum: f64 },
+    #[error("Order of {size} {base} rounds to zero at a {step} size step")]
+    SizeRoundsToZero { base: String, size: f64, step: f64 },
+    #[error("{leverage}x is over the {max}x maximum leverage for {base}")]
+    LeverageTooHigh { base: String, leverage: u32, max: f64 },
+    #[error("Limit orders need a price")]
+    MissingLimitPrice,
+    #[error("Only limit orders can be post-only")]
+    PostOnlyNotLimit,
+    #[error("{time_in_force} is not possible for {order} orders")]
+    InvalidTimeInForce { order: String, time_in_force: TimeInForce },
+    #[error("A good-til time only applies to orders that rest on the book")]
+    GoodTilNotResting,
+}
+
+fn order_name(order_type: &OrderType) -> &'static str {
+    match order_type {
+        OrderType::Market => "market",
+        OrderType::Limit => "limit",
+        OrderType::StopMarket { .. } => "stop",
+        OrderType::TakeProfit { .. } => "take-profit",
+    }
+}
+
+impl TradeRequest {
+    /// Check the order against the market's rules. `price` sizes market
+    /// orders; limit and trigger orders are sized at their own price.
+    /// Reduce-only orders skip the minimum notional, so a small remainder
+    /// can still be closed.
+    pub fn validate(&self, spec: &MarketSpec, price: f64) -> Result<(), OrderValidationError> {
+        self.validate_time_in_force()?;
+        let price = match self.order_type {
+            OrderType::Limit => match self.price {
+                Some(price) if price > 0.0 => price,
+                _ => return Err(OrderValidationError::MissingLimitPrice),
+            },
+            OrderType::StopMarket { trigger_price } | OrderType::TakeProfit { trigger_price } => trigger_price,
+            OrderType::Market => price,
+        };
+        if !self.reduce_only && self.usd_value < spec.min_notional {
+            return Err(OrderValidationError::BelowMinNotional { notional: self.usd_value, minimum: spec.min_notional });
+        }
+        if let Some(step) = spec.size_step().filter(|step| *step > 0.0 && price > 0.0) {
+            let size = self.usd_value / price;
+            if (size / step).round() == 0.0 {
+                return Err(OrderValidationError::SizeRoundsToZero { base: spec.base.clone(), size, step });
+            }
+        }
+        if spec.max_leverage > 0.0 && self.leverage as f64 > spec.max_leverage {
+            return Err(OrderValidationError::LeverageTooHigh { base: spec.base.clone(), leverage: self.leverage, max: spec.max_leverage });
+        }
+        Ok(())
+    }
+
+    /// Market and trigger orders are IOC; limit orders take any, but only
+    /// resting ones (GTC, post-only) have a good-til time
+    pub fn validate_time_in_force(&self) -> Result<(), OrderValidationError> {
+        let time_in_force = self.effective_time_in_force();
+        if self.post_only && time_in_force != TimeInForce::PostOnly {
+            return Err(OrderValidationError::InvalidTimeInForce { order: "post-only".to_string(), time_in_force });
+        }
+        match (&self.order_type, time_in_force) {
+            (OrderType::Limit, _) => {}
+            (_, TimeInForce::PostOnly) => return Err(OrderValidationError::PostOnlyNotLimit),
+            (_, TimeInForce::Ioc) => {}
+            (order_type, time_in_force) => {
+                return Err(OrderValidationError::InvalidTimeInForce { order: order_name(order_type).to_string(), time_in_force });
+            }
+        }
+        if self.good_til_secs.is_some() && !matches!(time_in_force, TimeInForce::Gtc | TimeInForce::PostOnly) {
+            return Err(OrderValidationError::GoodTilNotResting);
+        }
+        Ok(())
+    }
+}
@@ -1,37 +1,54 @@
 use ethers::signers::Signer;
-use hyperliquid_rust_sdk::{BaseUrl, InfoClient};
 use std::io::{self, Write};
-use anyhow::Result;
 use std::fs;
 use std::path::PathBuf;
 use ethers::prelude::*;
-use dydx::config::ClientConfig;
 use std::sync::Arc;
 use serde_json;
 use ethers::signers::LocalWallet as EthWallet;
 use dydx::node::{NodeClient, Wallet as DydxWallet};
 use bip32::{Mnemonic, Language};
-use crate::trading::dydx_service::TradeRequest;
-use bech32;
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use crate::trading::dydx_service::{OrderSize, TradeRequest};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled};
 use std::io::{stdin, stdout};
 use std::fs::OpenOptions;
-use chrono::Local;
 use ethers::types::{U256, Address as EthAddress};
 use ethers::contract::Contract;
 use ethers::providers::{Provider, Http};
-use crate::trading::dydx_service::DydxService;
-use dydx::indexer::{IndexerConfig, RestConfig, SockConfig};
+use crate::trading::dydx_service::{DydxService, DydxServiceError};
+use crate::aggregator::endpoints;
 use dydx::indexer::types::{OrderResponseObject, OrderSide, OrderType};
 use dydx::node::OrderTimeInForce;
 use num_traits::ToPrimitive;
 use ethers::types::Address;
 use crate::trading::positions::Position;
+use crate::trading::positions::episodes::Fill;
+use crate::trading::orders::{parse_dydx_historical_orders, parse_dydx_order_outcome, recent_orders, HistoricalOrder, OrderOutcome};
+use crate::trading::fill_report::{poll_until_terminal, FillReport, FILL_POLL_INTERVAL};
+use crate::trading::farm::{parse_dydx_funding_payments, FundingPayment};
+use crate::trading::history::{parse_dydx_fills, parse_dydx_order_aliases, DYDX_FILL_PAGE};
+use crate::aggregator::exchange_id::ExchangeId;
 use dydx_proto::dydxprotocol::subaccounts::SubaccountId;
 use std::time::Duration;
+use std::collections::HashMap;
 use dydx_proto::dydxprotocol::clob::OrderId;
-
-const ARBITRUM_RPC: &str = "https://arbitrum.llamarpc.com";
+use crate::aggregator::symbol::Symbol;
+use crate::timefmt;
+use crate::trading::hl_account::{check_deposit, HlAccountState};
+use crate::hyperliquid::meta::info_url;
+use crate::trading::wallet_overview::{DydxAccount, EthBalances};
+use crate::trading::file_lock::{AccessMode, FileLock};
+use crate::trading::dydx_config;
+use crate::trading::bridge::{self, BridgeEvent, BridgePhase, BridgeReceipt, PendingBridge, PendingBridges};
+use crate::trading::wallet_store::{ArchivedKey, EncryptedWallet, KeyKind, StoredWallet, WalletEntry, WalletFile, WalletKey};
+use crate::error::{AggregatorError, Result};
+use tokio::sync::mpsc;
+use tracing::info;
+
+pub(crate) const ARBITRUM_RPC: &str = "https://arbitrum.llamarpc.com";
+// USDC as bridged to the dYdX chain
+const DYDX_USDC_DENOM: &str = "ibc/8E27BA2D5493AF5636760E354E46004562C46AB7EC0CC4C1CA14E9E20E2545B5";
 const USDC_ADDRESS: &str = "0xaf88d065e77c8cc2239327c5edb3a432268e5831"; // Arbitrum USDC
 const USDC_ABI: &str = r#"[
     {
@@ -51,6 +68,16 @@ const USDC_ABI: &str = r#"[
         "stateMutability": "view",
         "type": "function"
     },
+    {
+        "inputs": [
+            {"name": "to", "type": "address"},
+            {"name": "amount", "type": "uint256"}
+        ],
+        "name": "transfer",
+        "outputs": [{"name": "", "type": "bool"}],
+        "stateMutability": "nonpayable",
+        "type": "function"
+    },
     {
         "inputs": [
             {"name": "owner", "type": "address"},
@@ -62,6 +89,10 @@ const USDC_ABI: &str = r#"[
         "type": "function"
     }
 ]"#;
+const HL_BRIDGE_ADDRESS: &str = "0x2df1c51e09aecf9cacb7bc98cb1742757f163df7"; // Hyperliquid Bridge2 on Arbitrum
+const HL_DEPOSIT_POLL: Duration = Duration::from_secs(5);
+// Hyperliquid usually credits within a minute
+const HL_DEPOSIT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 const TOKEN_MESSENGER_ADDRESS: &str = "0xbd3fa81b58ba92a82136038b25adec7066af3155"; // Arbitrum TokenMessenger
 const TOKEN_MESSENGER_ABI: &str = r#"[
     {
@@ -96,21 +127,24 @@ const CIRCLE_BRIDGE_ABI: &str = r#"[
     }
 ]"#;
 
-fn parse_order_id(order_id_str: &str) -> Result<OrderId> {
+/// The chain's id for an order listed as "client:clob_pair:flags:subaccount"
+/// (see `Order::from_dydx_order`). The last part is the indexer's subaccount
+/// UUID, which the chain doesn't know; orders are placed from `owner`'s
+/// subaccount 0, so that's the one named.
+pub(crate) fn parse_order_id(order_id_str: &str, owner: &str) -> Result<OrderId> {
     // Split only on the first 3 colons to handle UUID in the last part
     let parts: Vec<&str> = order_id_str.splitn(4, ':').collect();
     if parts.len() != 4 {
-        return Err(anyhow::anyhow!("Invalid order ID format"));
+        return Err(AggregatorError::InvalidInput("Invalid order ID format".to_string()));
     }
 
     let client_id = parts[0].parse::<u32>()?;
     let clob_pair_id = parts[1].parse::<u32>()?;
     let order_flags = parts[2].parse::<u32>()?;
-    
-    // The last part contains the UUID - we'll use it as the subaccount ID
+
     let subaccount_id = Some(SubaccountId {
-        owner: "dydx1phlst3f8nwdvwcdc5qj9tqpempkglp03hk6957".to_string(), // Replace with actual owner
-        number: 0, // Default subaccount number
+        owner: owner.to_string(),
+        number: 0,
     });
 
     Ok(OrderId {
@@ -121,6 +155,76 @@ fn parse_order_id(order_id_str: &str) -> Result<OrderId> {
     })
 }
 
+// Passphrase tries before unlocking gives up
+const UNLOCK_ATTEMPTS: u32 = 3;
+
+/// Asks the user for a passphrase
+pub type PassphrasePrompt = fn(&str) -> Result<String>;
+
+/// Read a passphrase from the terminal without echoing it
+pub fn read_passphrase(prompt: &str) -> Result<String> {
+    let was_raw = is_raw_mode_enabled()?;
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    enable_raw_mode()?;
+    let mut passphrase = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Backspace => {
+                    passphrase.pop();
+                }
+                KeyCode::Esc => break Err(AggregatorError::Wallet("Passphrase entry cancelled".to_string())),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break Err(AggregatorError::Wallet("Passphrase entry cancelled".to_string())),
+                KeyCode::Char(c) => passphrase.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    if !was_raw {
+        disable_raw_mode()?;
+    }
+    print!("\r\n");
+    result.map(|_| passphrase)
+}
+
+// One of the contract addresses above
+fn contract_address(hex: &str) -> Result<Address> {
+    hex.parse().map_err(|e| AggregatorError::Parse(format!("Invalid address {}: {}", hex, e)))
+}
+
+// The dYdX SDK fails key handling with untyped errors; they're wallet errors here
+fn key_error(error: impl std::fmt::Display) -> AggregatorError {
+    AggregatorError::Wallet(error.to_string())
+}
+
+/// Address a key controls; fails if it isn't a valid key of `kind`
+pub fn key_address(kind: KeyKind, secret: &str) -> Result<String> {
+    match kind {
+        KeyKind::Eth => Ok(format!("{:#x}", EthWallet::from_bytes(&hex::decode(secret)?)?.address())),
+        KeyKind::Dydx => Ok(DydxWallet::from_mnemonic(secret).map_err(key_error)?.account_offline(0).map_err(key_error)?.address().to_string()),
+    }
+}
+
+// Up to UNLOCK_ATTEMPTS tries at the passphrase
+fn unlock(encrypted: &EncryptedWallet, prompt: Option<PassphrasePrompt>) -> Result<(WalletFile, WalletKey)> {
+    let prompt = prompt.ok_or(AggregatorError::WalletLocked)?;
+    let mut tries = 0;
+    loop {
+        tries += 1;
+        let passphrase = prompt("Passphrase for wallet.key: ")?;
+        match encrypted.unlock(&passphrase) {
+            Err(AggregatorError::WrongPassphrase) if tries < UNLOCK_ATTEMPTS => {
+                println!("Wrong passphrase, try again");
+            }
+            result => return result,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct WalletManager {
     eth_wallet: Option<EthWallet>,
@@ -128,16 +232,61 @@ pub struct WalletManager {
     dydx_client: Option<NodeClient>,
     config_path: PathBuf,
     dydx_service: Option<DydxService>,
+    mode: AccessMode,
+    // Held for the manager's lifetime in read-write mode
+    _lock: Option<FileLock>,
+    // Set once an encrypted wallet file is unlocked or a plaintext one
+    // encrypted; every save re-encrypts with it
+    wallet_key: Option<WalletKey>,
+    // None where nobody can answer, e.g. under test
+    prompt: Option<PassphrasePrompt>,
+    arbitrum_rpc: String,
+    // Which Hyperliquid network account state is read from
+    testnet: bool,
+}
+
+/// A USDC transfer into Hyperliquid, mined on Arbitrum
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlDeposit {
+    pub transfer_tx: String,
+    pub amount: f64,
+    // Whether the account value showed it before the wait ran out
+    pub credited: bool,
+}
+
+fn wei_to_eth(wei: U256) -> f64 {
+    wei.as_u128() as f64 / 1e18
 }
 
 impl WalletManager {
     pub async fn new() -> Result<Self> {
+        Self::with_mode(AccessMode::ReadWrite).await
+    }
+
+    pub async fn with_mode(mode: AccessMode) -> Result<Self> {
         let config_dir = dirs::config_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("trading_aggregator");
+        Self::open_with_prompt(config_dir, mode, Some(read_passphrase)).await
+    }
+
+    /// Load wallets from `config_dir`. Read-write mode locks the wallet file
+    /// and fails fast if another instance already holds it.
+    pub async fn open(config_dir: PathBuf, mode: AccessMode) -> Result<Self> {
+        Self::open_with_prompt(config_dir, mode, None).await
+    }
+
+    /// As `open`, asking `prompt` for the passphrase of an encrypted wallet
+    /// file. Without a prompt an encrypted file can't be opened, and new
+    /// keys are stored as the file already is.
+    pub async fn open_with_prompt(config_dir: PathBuf, mode: AccessMode, prompt: Option<PassphrasePrompt>) -> Result<Self> {
         fs::create_dir_all(&config_dir)?;
         
         let config_path = config_dir.join("wallet.key");
+        let lock = match mode {
+            AccessMode::ReadWrite => Some(FileLock::acquire(config_path.with_extension("lock"))?),
+            AccessMode::ReadOnly => None,
+        };
         
         let mut manager = Self {
             eth_wallet: None,
@@ -145,25 +294,41 @@ impl WalletManager {
             dydx_client: None,
             config_path,
             dydx_service: None,
+            mode,
+            _lock: lock,
+            wallet_key: None,
+            prompt,
+            arbitrum_rpc: ARBITRUM_RPC.to_string(),
+            testnet: false,
         };
 
-        // Try to load existing wallets
-        if manager.config_path.exists() {
-            if let Ok(data) = fs::read_to_string(&manager.config_path) {
-                if let Ok(wallet_data) = serde_json::from_str::<serde_json::Value>(&data) {
-                    // Load ETH wallet
-                    if let Some(key) = wallet_data["eth_key"].as_str() {
-                        if let Ok(wallet) = EthWallet::from_bytes(&hex::decode(key)?) {
-                            manager.eth_wallet = Some(wallet);
-                        }
-                    }
-                    
-                    // Just load the dYdX wallet, initialize client later
-                    if let Some(mnemonic) = wallet_data["dydx_mnemonic"].as_str() {
-                        if let Ok(wallet) = DydxWallet::from_mnemonic(mnemonic) {
-                            manager.dydx_wallet = Some(wallet);
-                        }
-                    }
+        // Try to load existing wallets; archived keys are never loaded. A
+        // passphrase that doesn't unlock the file is an error, not an empty
+        // wallet.
+        let wallet_file = match StoredWallet::read(&manager.config_path) {
+            Ok(Some(StoredWallet::Encrypted(encrypted))) => {
+                let (wallet_file, key) = unlock(&encrypted, prompt)?;
+                manager.wallet_key = Some(key);
+                Some(wallet_file)
+            }
+            Ok(Some(StoredWallet::Plain(wallet_file))) => Some(wallet_file),
+            Ok(None) => None,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable wallet file: {}", e);
+                None
+            }
+        };
+        if let Some(wallet_file) = wallet_file {
+            if let Some(key) = wallet_file.active(KeyKind::Eth) {
+                if let Ok(wallet) = EthWallet::from_bytes(&hex::decode(key)?) {
+                    manager.eth_wallet = Some(wallet);
+                }
+            }
+
+            // Just load the dYdX wallet, initialize client later
+            if let Some(mnemonic) = wallet_file.active(KeyKind::Dydx) {
+                if let Ok(wallet) = DydxWallet::from_mnemonic(mnemonic) {
+                    manager.dydx_wallet = Some(wallet);
                 }
             }
         }
@@ -175,6 +340,32 @@ impl WalletManager {
         Ok(manager)
     }
 
+    /// Use `url` for Arbitrum instead of the public default
+    pub fn with_arbitrum_rpc(self, url: impl Into<String>) -> Self {
+        Self { arbitrum_rpc: url.into(), ..self }
+    }
+
+    pub fn arbitrum_rpc(&self) -> &str {
+        &self.arbitrum_rpc
+    }
+
+    /// Read Hyperliquid account state from testnet instead of mainnet
+    pub fn with_testnet(self, testnet: bool) -> Self {
+        Self { testnet, ..self }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.mode.is_read_only()
+    }
+
+    /// Guard for anything that writes wallet state or trades
+    pub fn ensure_writable(&self) -> Result<()> {
+        if self.is_read_only() {
+            return Err(AggregatorError::ReadOnly("writes and trading are disabled".to_string()));
+        }
+        Ok(())
+    }
+
     pub fn get_dydx_service(&self) -> Option<&DydxService> {
         self.dydx_service.as_ref()
     }
@@ -187,21 +378,187 @@ impl WalletManager {
         self.dydx_wallet.as_ref()
     }
 
-    pub async fn create_eth_wallet(&mut self) -> Result<()> {
-        let eth_wallet = EthWallet::new(&mut rand::thread_rng());
-        
-        // Read existing wallet data or create new
-        let mut wallet_data = if self.config_path.exists() {
-            serde_json::from_str(&fs::read_to_string(&self.config_path)?)?
-        } else {
-            serde_json::json!({})
+    /// Address the active key of `kind` controls
+    fn active_address(&self, kind: KeyKind) -> Option<String> {
+        match kind {
+            KeyKind::Eth => self.eth_wallet.as_ref().map(|wallet| format!("{:#x}", wallet.address())),
+            KeyKind::Dydx => self.dydx_wallet.as_ref()
+                .and_then(|wallet| wallet.account_offline(0).ok())
+                .map(|account| account.address().to_string()),
+        }
+    }
+
+    /// Whether the wallet file is written encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.wallet_key.is_some()
+    }
+
+    /// Re-write a plaintext wallet file encrypted under `passphrase`;
+    /// every later save stays encrypted.
+    pub fn migrate_to_encrypted(&mut self, passphrase: &str) -> Result<()> {
+        self.ensure_writable()?;
+        if self.is_encrypted() {
+            return Err(AggregatorError::Wallet("The wallet file is already encrypted".to_string()));
+        }
+        if passphrase.is_empty() {
+            return Err(AggregatorError::Wallet("The passphrase can't be empty".to_string()));
+        }
+        let wallet_file = WalletFile::load(&self.config_path, None)?;
+        let key = WalletKey::derive(passphrase)?;
+        wallet_file.save(&self.config_path, Some(&key))?;
+        self.wallet_key = Some(key);
+        Ok(())
+    }
+
+    /// Before keys go into a plaintext file, ask for a passphrase to encrypt
+    /// it with. An empty answer keeps it plaintext.
+    pub fn offer_encryption(&mut self) -> Result<()> {
+        let Some(prompt) = self.prompt else { return Ok(()) };
+        if self.is_encrypted() {
+            return Ok(());
+        }
+        let passphrase = prompt("Choose a passphrase to encrypt wallet.key (empty to store keys unencrypted): ")?;
+        if passphrase.is_empty() {
+            println!("Keys will be stored unencrypted");
+            return Ok(());
+        }
+        if prompt("Repeat the passphrase: ")? != passphrase {
+            return Err(AggregatorError::Wallet("Passphrases don't match; nothing was saved".to_string()));
+        }
+        self.migrate_to_encrypted(&passphrase)
+    }
+
+    /// Write `secret` as the active key of `kind`, archiving the one it
+    /// replaces rather than overwriting it
+    fn store_key(&self, kind: KeyKind, secret: String) -> Result<()> {
+        let mut wallet_file = WalletFile::load(&self.config_path, self.wallet_key.as_ref())?;
+        wallet_file.set_active(kind, secret, self.active_address(kind), chrono::Utc::now().timestamp_millis());
+        wallet_file.save(&self.config_path, self.wallet_key.as_ref())
+    }
+
+    /// Keys replaced by a create, import or restore, oldest first
+    pub fn list_archived(&self) -> Result<Vec<ArchivedKey>> {
+        Ok(WalletFile::load(&self.config_path, self.wallet_key.as_ref())?.archived)
+    }
+
+    /// Make the archived key for `address` active again. The key it
+    /// replaces is archived in turn.
+    pub async fn restore_archived(&mut self, address: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let mut wallet_file = WalletFile::load(&self.config_path, self.wallet_key.as_ref())?;
+        let kind = wallet_file.find_archived(address)
+            .ok_or_else(|| AggregatorError::Wallet(format!("No archived key for {}", address)))?
+            .kind;
+        let restored = wallet_file.restore(address, self.active_address(kind), chrono::Utc::now().timestamp_millis())?;
+        // Parse before saving so a corrupt entry leaves the file untouched
+        match kind {
+            KeyKind::Eth => {
+                let wallet = EthWallet::from_bytes(&hex::decode(&restored.secret)?)?;
+                wallet_file.save(&self.config_path, self.wallet_key.as_ref())?;
+                self.eth_wallet = Some(wallet);
+            }
+            KeyKind::Dydx => {
+                let wallet = DydxWallet::from_mnemonic(&restored.secret).map_err(key_error)?;
+                wallet_file.save(&self.config_path, self.wallet_key.as_ref())?;
+                self.dydx_wallet = Some(wallet);
+                self.dydx_client = None;
+                self.dydx_service = None;
+                if let Err(e) = self.init_dydx_service().await {
+                    tracing::warn!("dYdX service unavailable after restore: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Named wallets, oldest first, with the active one of each kind flagged
+    pub fn list_wallets(&self) -> Result<Vec<WalletEntry>> {
+        let wallet_file = WalletFile::load(&self.config_path, self.wallet_key.as_ref())?;
+        Ok(wallet_file.wallets.iter()
+            .map(|wallet| WalletEntry {
+                name: wallet.name.clone(),
+                kind: wallet.kind,
+                address: key_address(wallet.kind, &wallet.secret).unwrap_or_default(),
+                active: wallet_file.active(wallet.kind) == Some(wallet.secret.as_str()),
+            })
+            .collect())
+    }
+
+    /// Save a key under `name`: a hex private key for ETH, a mnemonic for
+    /// dYdX. It becomes active only if there is no active key of its kind.
+    pub async fn add_wallet(&mut self, name: &str, kind: KeyKind, secret: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let secret = match kind {
+            KeyKind::Eth => secret.trim().trim_start_matches("0x").to_lowercase(),
+            KeyKind::Dydx => secret.split_whitespace().collect::<Vec<_>>().join(" "),
         };
+        key_address(kind, &secret).map_err(|e| AggregatorError::Wallet(format!("Not a valid {} key: {}", kind, e)))?;
+        let mut wallet_file = WalletFile::load(&self.config_path, self.wallet_key.as_ref())?;
+        let had_active = wallet_file.active(kind).is_some();
+        wallet_file.add_wallet(name, kind, secret.clone())?;
+        wallet_file.save(&self.config_path, self.wallet_key.as_ref())?;
+        if !had_active {
+            self.load_key(kind, &secret).await?;
+        }
+        Ok(())
+    }
 
-        // Update ETH wallet data
-        wallet_data["eth_key"] = serde_json::Value::String(hex::encode(eth_wallet.signer().to_bytes()));
-        
-        // Save updated wallet data
-        fs::write(&self.config_path, serde_json::to_string_pretty(&wallet_data)?)?;
+    /// Switch the active key of the named wallet's kind to it. The choice
+    /// is saved, so it holds across restarts.
+    pub async fn set_active(&mut self, name: &str) -> Result<WalletEntry> {
+        self.ensure_writable()?;
+        let mut wallet_file = WalletFile::load(&self.config_path, self.wallet_key.as_ref())?;
+        let kind = wallet_file.find_wallet(name)
+            .ok_or_else(|| AggregatorError::Wallet(format!("No wallet named {}", name.trim())))?
+            .kind;
+        let wallet = wallet_file.switch_wallet(name, self.active_address(kind), chrono::Utc::now().timestamp_millis())?;
+        let address = key_address(kind, &wallet.secret)?;
+        wallet_file.save(&self.config_path, self.wallet_key.as_ref())?;
+        self.load_key(kind, &wallet.secret).await?;
+        Ok(WalletEntry { name: wallet.name, kind, address, active: true })
+    }
+
+    /// Drop a named wallet that isn't active. Its key is archived, not
+    /// deleted.
+    pub fn remove_wallet(&mut self, name: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let mut wallet_file = WalletFile::load(&self.config_path, self.wallet_key.as_ref())?;
+        let address = wallet_file.find_wallet(name).and_then(|wallet| key_address(wallet.kind, &wallet.secret).ok());
+        wallet_file.remove_wallet(name, address, chrono::Utc::now().timestamp_millis())?;
+        wallet_file.save(&self.config_path, self.wallet_key.as_ref())
+    }
+
+    // Make `secret` the loaded key of its kind
+    async fn load_key(&mut self, kind: KeyKind, secret: &str) -> Result<()> {
+        match kind {
+            KeyKind::Eth => self.eth_wallet = Some(EthWallet::from_bytes(&hex::decode(secret)?)?),
+            KeyKind::Dydx => {
+                self.dydx_wallet = Some(DydxWallet::from_mnemonic(secret).map_err(key_error)?);
+                self.dydx_client = None;
+                self.dydx_service = None;
+                if let Err(e) = self.init_dydx_service().await {
+                    tracing::warn!("dYdX service unavailable after switching wallets: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Permanently delete every archived key. `typed` must be
+    /// `wallet_store::PURGE_CONFIRMATION`; returns how many were deleted.
+    pub fn purge_archived(&mut self, typed: &str) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut wallet_file = WalletFile::load(&self.config_path, self.wallet_key.as_ref())?;
+        let purged = wallet_file.purge(typed)?;
+        wallet_file.save(&self.config_path, self.wallet_key.as_ref())?;
+        Ok(purged)
+    }
+
+    pub async fn create_eth_wallet(&mut self) -> Result<()> {
+        self.ensure_writable()?;
+        self.offer_encryption()?;
+        let eth_wallet = EthWallet::new(&mut rand::thread_rng());
+        self.store_key(KeyKind::Eth, hex::encode(eth_wallet.signer().to_bytes()))?;
         self.eth_wallet = Some(eth_wallet);
         
         println!("\nETH wallet created successfully");
@@ -209,28 +566,22 @@ impl WalletManager {
     }
 
     pub async fn create_dydx_wallet(&mut self) -> Result<()> {
-        // Load config
-        let config = ClientConfig::from_file("./src/bridge_config/mainnet.toml").await?;
+        self.ensure_writable()?;
+        self.offer_encryption()?;
+        let config = dydx_config::client_config()?;
 
         // Generate new mnemonic
         let mnemonic = Mnemonic::random(&mut rand::thread_rng(), Language::English);
         let phrase = mnemonic.phrase();
         
         // Create wallet
-        let dydx_wallet = DydxWallet::from_mnemonic(phrase)?;
+        let dydx_wallet = DydxWallet::from_mnemonic(phrase).map_err(key_error)?;
 
         // Initialize client
-        let dydx_client = NodeClient::connect(config.node).await?;
+        let dydx_client = NodeClient::connect(config.node).await.map_err(DydxServiceError::from)?;
 
         // Save wallet data
-        let mut wallet_data = if self.config_path.exists() {
-            serde_json::from_str(&fs::read_to_string(&self.config_path)?)?
-        } else {
-            serde_json::json!({})
-        };
-
-        wallet_data["dydx_mnemonic"] = serde_json::Value::String(phrase.to_string());
-        fs::write(&self.config_path, serde_json::to_string_pretty(&wallet_data)?)?;
+        self.store_key(KeyKind::Dydx, phrase.to_string())?;
         
         self.dydx_wallet = Some(dydx_wallet);
         self.dydx_client = Some(dydx_client);
@@ -241,6 +592,8 @@ impl WalletManager {
     }
 
     pub async fn import_eth_wallet(&mut self) -> Result<()> {
+        self.ensure_writable()?;
+        self.offer_encryption()?;
         // Disable raw mode to allow normal input
         disable_raw_mode()?;
         
@@ -266,18 +619,7 @@ impl WalletManager {
             Ok(bytes) => {
                 match EthWallet::from_bytes(&bytes) {
                     Ok(eth_wallet) => {
-                        // Read existing wallet data or create new
-                        let mut wallet_data = if self.config_path.exists() {
-                            serde_json::from_str(&fs::read_to_string(&self.config_path)?)?
-                        } else {
-                            serde_json::json!({})
-                        };
-
-                        // Update ETH wallet data
-                        wallet_data["eth_key"] = serde_json::Value::String(hex::encode(eth_wallet.signer().to_bytes()));
-                        
-                        // Save updated wallet data
-                        fs::write(&self.config_path, serde_json::to_string_pretty(&wallet_data)?)?;
+                        self.store_key(KeyKind::Eth, hex::encode(eth_wallet.signer().to_bytes()))?;
                         self.eth_wallet = Some(eth_wallet);
                         
                         println!("\nETH wallet imported successfully");
@@ -294,6 +636,8 @@ impl WalletManager {
     }
 
     pub async fn import_dydx_wallet(&mut self) -> Result<()> {
+        self.ensure_writable()?;
+        self.offer_encryption()?;
         // Disable raw mode to allow normal input
         disable_raw_mode()?;
         
@@ -310,18 +654,7 @@ impl WalletManager {
 
         match DydxWallet::from_mnemonic(mnemonic_input) {
             Ok(dydx_wallet) => {
-                // Read existing wallet data
-                let mut wallet_data = if self.config_path.exists() {
-                    serde_json::from_str(&fs::read_to_string(&self.config_path)?)?
-                } else {
-                    serde_json::json!({})
-                };
-
-                // Update dYdX wallet data
-                wallet_data["dydx_mnemonic"] = serde_json::Value::String(mnemonic_input.to_string());
-                
-                // Save updated wallet data
-                fs::write(&self.config_path, serde_json::to_string_pretty(&wallet_data)?)?;
+                self.store_key(KeyKind::Dydx, mnemonic_input.to_string())?;
                 self.dydx_wallet = Some(dydx_wallet);
                 
                 println!("\ndYdX wallet imported successfully");
@@ -348,69 +681,77 @@ impl WalletManager {
         Ok(())
     }
 
-    pub async fn get_wallet_info(&self) -> Result<(Option<String>, Option<String>, f64, f64, f64)> {
-        if let Some(wallet) = &self.eth_wallet {
-            // Create provider with chain ID
-            let provider = Provider::<Http>::try_from(ARBITRUM_RPC)?;
-            let chain_id = 42161u64;
-            let wallet_with_chain_id = wallet.clone().with_chain_id(chain_id);
-            let client = Arc::new(provider);
-            let client_with_signer = Arc::new(SignerMiddleware::new(
-                client.clone(),
-                wallet_with_chain_id,
-            ));
-
-            // Get Hyperliquid info
-            let info_client = InfoClient::new(None, Some(BaseUrl::Mainnet)).await?;
-            let user_state = info_client.user_state(wallet.address()).await?;
-            
-            let account_value = user_state.margin_summary.account_value.parse::<f64>()?;
-            let margin_used = user_state.margin_summary.total_margin_used.parse::<f64>()?;
+    /// Address, Arbitrum USDC and native ETH for gas; None without an ETH
+    /// wallet.
+    pub async fn eth_balances(&self) -> Result<Option<EthBalances>> {
+        let Some(wallet) = &self.eth_wallet else { return Ok(None) };
+        let provider = Provider::<Http>::try_from(self.arbitrum_rpc.as_str())?;
+        let client = Arc::new(provider);
+
+        let usdc_address = contract_address(USDC_ADDRESS)?;
+        let abi: ethers::abi::Abi = serde_json::from_str(USDC_ABI)?;
+        let contract = Contract::new(usdc_address, abi, client.clone());
+
+        let balance: U256 = contract
+            .method::<_, U256>("balanceOf", wallet.address())?
+            .call()
+            .await?;
+        let gas: U256 = client.get_balance(wallet.address(), None).await?;
+
+        Ok(Some(EthBalances {
+            address: format!("{:#x}", wallet.address()),
+            usdc: balance.as_u128() as f64 / 1_000_000.0,
+            eth: gas.as_u128() as f64 / 1e18,
+        }))
+    }
 
-            // Create USDC contract
-            let usdc_address = USDC_ADDRESS.strip_prefix("0x")
-                .unwrap_or(USDC_ADDRESS)
-                .parse::<Address>()?;
-            
-            let abi: ethers::abi::Abi = serde_json::from_str(USDC_ABI)?;
-            let contract = Contract::new(
-                usdc_address,
-                abi,
-                client_with_signer.clone()
-            );
+    pub async fn hl_account(&self) -> Result<Option<HlAccountState>> {
+        match &self.eth_wallet {
+            Some(wallet) => Ok(Some(HlAccountState::fetch_from(info_url(self.testnet), wallet.address()).await?)),
+            None => Ok(None),
+        }
+    }
 
-            // Get USDC balance
-            let balance: U256 = contract
-                .method::<_, U256>("balanceOf", wallet.address())?
-                .call()
-                .await?;
+    /// Parent subaccount address, equity and free collateral; None without a
+    /// dYdX wallet or before the indexer client is up.
+    pub async fn dydx_account(&self) -> Result<Option<DydxAccount>> {
+        let (Some(dydx_service), Some(dydx_wallet)) = (&self.dydx_service, &self.dydx_wallet) else {
+            return Ok(None);
+        };
+        let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+        let parent_subaccount_info = dydx_service.indexer()
+            .accounts()
+            .get_parent_subaccount(&account.subaccount(0).map_err(key_error)?.parent())
+            .await.map_err(DydxServiceError::from)?;
+        Ok(Some(DydxAccount {
+            address: account.address().to_string(),
+            equity: parent_subaccount_info.equity.to_f64().unwrap_or(0.0),
+            free_collateral: parent_subaccount_info.free_collateral.to_f64().unwrap_or(0.0),
+        }))
+    }
 
-            let decimal_balance = balance.as_u128() as f64 / 1_000_000.0;
-            let address = format!("{:#x}", wallet.address());
-            let private_key = fs::read_to_string(&self.config_path)?;
-            
-            Ok((Some(address), Some(private_key), account_value, margin_used, decimal_balance))
-        } else {
-            Ok((None, None, 0.0, 0.0, 0.0))
+    pub async fn get_dydx_free_collateral(&self) -> Result<Option<f64>> {
+        if let (Some(dydx_service), Some(dydx_wallet)) = (&self.dydx_service, &self.dydx_wallet) {
+            let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+            let parent_subaccount_info = dydx_service.indexer()
+                .accounts()
+                .get_parent_subaccount(&account.subaccount(0).map_err(key_error)?.parent())
+                .await.map_err(DydxServiceError::from)?;
+            return Ok(Some(parent_subaccount_info.free_collateral.to_f64().unwrap_or(0.0)));
         }
+        Ok(None)
     }
 
-    pub async fn get_dydx_balance(&mut self) -> Result<Option<f64>> {
-        if let Some(dydx_service) = &self.dydx_service {
-            if let Some(dydx_wallet) = &self.dydx_wallet {
-                if let Ok(account) = dydx_wallet.account_offline(0) {
-                    if let Ok(subaccount) = account.subaccount(0) {
-                        // Use parent subaccount endpoint instead
-                        let parent_subaccount_info = dydx_service.indexer_client
-                            .accounts()
-                            .get_parent_subaccount(&subaccount.parent())
-                            .await?;
-                        
-                        // Return the equity as the balance
-                        return Ok(Some(parent_subaccount_info.equity.to_f64().unwrap_or(0.0)));
-                    }
-                }
-            }
+    /// Total equity of the parent subaccount, positions marked to market.
+    pub async fn get_dydx_equity(&self) -> Result<Option<f64>> {
+        if let (Some(dydx_service), Some(dydx_wallet)) = (&self.dydx_service, &self.dydx_wallet) {
+            let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+            let parent_subaccount_info = dydx_service.indexer()
+                .accounts()
+                .get_parent_subaccount(&account.subaccount(0).map_err(key_error)?.parent())
+                .await
+                .map_err(DydxServiceError::from)?;
+            return Ok(Some(parent_subaccount_info.equity.to_f64().unwrap_or(0.0)));
         }
         Ok(None)
     }
@@ -420,18 +761,18 @@ impl WalletManager {
             if let Some(client) = &mut self.dydx_client {
                 if let Ok(account) = dydx_wallet.account_offline(0) {
                     let address = account.address().to_string();
-                    let balances = client.get_account_balances(account.address()).await?;
+                    let balances = client.get_account_balances(account.address()).await.map_err(DydxServiceError::from)?;
                     let mut usdc_balance = 0.0;
                     
                     for balance in balances {
-                        if balance.denom == "ibc/8E27BA2D5493AF5636760E354E46004562C46AB7EC0CC4C1CA14E9E20E2545B5" {
+                        if balance.denom == DYDX_USDC_DENOM {
                             usdc_balance = balance.amount.parse::<f64>()? / 1_000_000.0;
                             break;
                         }
                     }
 
                     // Get account info from client
-                    let account_info = client.get_account(account.address()).await?;
+                    let account_info = client.get_account(account.address()).await.map_err(DydxServiceError::from)?;
                     
                     return Ok(Some((
                         address,
@@ -446,35 +787,20 @@ impl WalletManager {
 
     pub async fn init_dydx_client(&mut self) -> Result<()> {
         if self.dydx_wallet.is_some() && self.dydx_client.is_none() {
-            if let Ok(config) = ClientConfig::from_file("./src/bridge_config/mainnet.toml").await {
-                // Clone the config.node for the second use
-                let node_config = config.node.clone();
-                if let Ok(client) = NodeClient::connect(config.node).await {
-                    // Initialize DydxService
-                    if let Some(ref dydx_wallet) = self.dydx_wallet {
-                        let indexer_config = IndexerConfig {
-                            rest: RestConfig {
-                                endpoint: "https://indexer.dydx.trade".to_string(),
-                            },
-                            sock: SockConfig {
-                                endpoint: "wss://indexer.dydx.trade/v4/ws".to_string(),
-                                timeout: 1000,
-                                rate_limit: std::num::NonZeroU32::new(2).unwrap(),
-                            },
-                        };
-                        
-                        if let Ok(account) = dydx_wallet.account_offline(0) {
-                            let dydx_service = DydxService::new(
-                                node_config,
-                                indexer_config,
-                                account
-                            ).await?;
-                            self.dydx_service = Some(dydx_service);
-                        }
-                    }
-                    self.dydx_client = Some(client);
-                }
+            let config = dydx_config::client_config()?;
+            // Clone the config.node for the second use
+            let node_config = config.node.clone();
+            let client = NodeClient::connect(config.node).await.map_err(DydxServiceError::from)?;
+            if let Some(ref dydx_wallet) = self.dydx_wallet {
+                let indexer_config = endpoints::dydx().indexer_config();
+                let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+                self.dydx_service = Some(DydxService::new(
+                    node_config,
+                    indexer_config,
+                    account
+                ).await?);
             }
+            self.dydx_client = Some(client);
         }
         Ok(())
     }
@@ -486,7 +812,7 @@ impl WalletManager {
                     let subaccount = account.subaccount(0);
                     match subaccount {
                         Ok(sub) => {
-                            match dydx_service.indexer_client
+                            match dydx_service.indexer()
                                 .accounts()
                                 .list_parent_positions(
                                     &sub.parent(),
@@ -519,47 +845,229 @@ impl WalletManager {
         }
     }
 
+    /// Recent fills across the parent subaccount, as returned by the indexer.
+    pub async fn get_dydx_fills(&self) -> Result<Vec<Fill>> {
+        if let (Some(dydx_service), Some(dydx_wallet)) = (&self.dydx_service, &self.dydx_wallet) {
+            let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+            let fills = dydx_service.indexer()
+                .accounts()
+                .get_parent_fills(&account.subaccount(0).map_err(key_error)?.parent(), None)
+                .await
+                .map_err(DydxServiceError::from)?;
+            let mut fills: Vec<Fill> = fills.iter()
+                .map(|fill| Fill {
+                    exchange: ExchangeId::Dydx,
+                    asset: fill.market.0.clone(),
+                    is_buy: matches!(fill.side, OrderSide::Buy),
+                    price: fill.price.0.to_f64().unwrap_or(0.0),
+                    size: fill.size.to_f64().unwrap_or(0.0),
+                    fee: fill.fee.to_f64().unwrap_or(0.0),
+                    time: fill.created_at.timestamp_millis(),
+                    order_id: fill.order_id.as_ref().map(|id| id.0.clone()).unwrap_or_default(),
+                    fill_id: fill.id.0.clone(),
+                })
+                .collect();
+            self.resolve_dydx_order_ids(&mut fills).await;
+            return Ok(fills);
+        }
+        Ok(Vec::new())
+    }
+
+    /// Every fill since `since_ms` the indexer still holds, paging backward
+    /// from the newest. `progress` gets the running count after each page.
+    pub async fn get_dydx_fills_since(&self, since_ms: i64, mut progress: impl FnMut(usize)) -> Result<Vec<Fill>> {
+        let Some(dydx_wallet) = &self.dydx_wallet else { return Ok(Vec::new()) };
+        let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+        let mut fills = Vec::new();
+        let mut before: Option<i64> = None;
+        loop {
+            let mut query = vec![
+                ("address", account.address().to_string()),
+                ("parentSubaccountNumber", "0".to_string()),
+                ("limit", DYDX_FILL_PAGE.to_string()),
+            ];
+            if let Some(before) = before.and_then(chrono::DateTime::<chrono::Utc>::from_timestamp_millis) {
+                query.push(("createdBeforeOrAt", before.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)));
+            }
+            let body = reqwest::Client::new()
+                .get(endpoints::dydx().url("/v4/fills/parentSubaccountNumber"))
+                .query(&query)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let page = parse_dydx_fills(&body)?;
+            let full = page.len() >= DYDX_FILL_PAGE;
+            let oldest = page.iter().map(|fill| fill.time).min();
+            fills.extend(page.into_iter().filter(|fill| fill.time >= since_ms));
+            progress(fills.len());
+            match oldest {
+                // Pages overlap on the boundary timestamp; stop if that's all there is
+                Some(oldest) if full && oldest >= since_ms && before != Some(oldest) => before = Some(oldest),
+                _ => break,
+            }
+        }
+        self.resolve_dydx_order_ids(&mut fills).await;
+        Ok(fills)
+    }
+
+    /// Swap the indexer's order uuids on `fills` for the ids the journal
+    /// records. Only orders in the latest orders listing can be resolved;
+    /// older ones keep their uuid.
+    async fn resolve_dydx_order_ids(&self, fills: &mut [Fill]) {
+        let aliases = match self.get_dydx_order_aliases().await {
+            Ok(aliases) => aliases,
+            Err(e) => {
+                tracing::warn!("Failed to fetch dYdX order ids: {}", e);
+                return;
+            }
+        };
+        for fill in fills {
+            if let Some(id) = aliases.get(&fill.order_id) {
+                fill.order_id = id.clone();
+            }
+        }
+    }
+
+    async fn get_dydx_order_aliases(&self) -> Result<HashMap<String, String>> {
+        let Some(dydx_wallet) = &self.dydx_wallet else { return Ok(HashMap::new()) };
+        let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+        let body = reqwest::Client::new()
+            .get(endpoints::dydx().url("/v4/orders/parentSubaccountNumber"))
+            .query(&[
+                ("address", account.address().to_string()),
+                ("parentSubaccountNumber", "0".to_string()),
+                ("limit", DYDX_FILL_PAGE.to_string()),
+                ("returnLatestOrders", "true".to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_dydx_order_aliases(&body)
+    }
+
+    /// Latest orders across the parent subaccount that have left the book,
+    /// with the indexer's status and `removalReason` normalized.
+    pub async fn get_dydx_historical_orders(&self, limit: usize) -> Result<Vec<HistoricalOrder>> {
+        let Some(dydx_wallet) = &self.dydx_wallet else { return Ok(Vec::new()) };
+        let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+        let body = reqwest::Client::new()
+            .get(endpoints::dydx().url("/v4/orders/parentSubaccountNumber"))
+            .query(&[
+                ("address", account.address().to_string()),
+                ("parentSubaccountNumber", "0".to_string()),
+                ("limit", limit.max(1).to_string()),
+                ("returnLatestOrders", "true".to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(recent_orders(parse_dydx_historical_orders(&body)?, limit))
+    }
+
+    /// How dYdX order `order_id` left the book; None while it's live
+    pub async fn get_dydx_order_outcome(&self, order_id: &str) -> Result<Option<OrderOutcome>> {
+        let Some(dydx_wallet) = &self.dydx_wallet else { return Ok(None) };
+        let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+        let body = reqwest::Client::new()
+            .get(endpoints::dydx().url("/v4/orders/parentSubaccountNumber"))
+            .query(&[
+                ("address", account.address().to_string()),
+                ("parentSubaccountNumber", "0".to_string()),
+                ("limit", DYDX_FILL_PAGE.to_string()),
+                ("returnLatestOrders", "true".to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_dydx_order_outcome(&body, order_id)
+    }
+
+    /// Poll dYdX order `order_id` until it leaves the book or `timeout`
+    /// passes, then report what it filled; see
+    /// `HyperliquidService::wait_for_fill`
+    pub async fn wait_for_dydx_fill(&self, order_id: &str, timeout: Duration) -> Result<FillReport> {
+        let status = poll_until_terminal(timeout, FILL_POLL_INTERVAL, move || self.get_dydx_order_outcome(order_id)).await?;
+        Ok(FillReport::from_fills(&self.get_dydx_fills().await?, order_id, status))
+    }
+
+    /// Funding settled on the parent subaccount since `since_ms`.
+    pub async fn get_dydx_funding_payments(&self, since_ms: i64) -> Result<Vec<FundingPayment>> {
+        let Some(dydx_wallet) = &self.dydx_wallet else { return Ok(Vec::new()) };
+        let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+        let body = reqwest::Client::new()
+            .get(endpoints::dydx().url("/v4/fundingPayments/parentSubaccount"))
+            .query(&[
+                ("address", account.address().to_string()),
+                ("parentSubaccountNumber", "0".to_string()),
+                ("limit", "1000".to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let payments = parse_dydx_funding_payments(&body)?;
+        Ok(payments.into_iter().filter(|payment| payment.time >= since_ms).collect())
+    }
+
     pub async fn get_dydx_orders(&self) -> Result<Vec<OrderResponseObject>> {
         if let Some(ref dydx_service) = self.dydx_service {
             if let Some(ref dydx_wallet) = self.dydx_wallet {
                 if let Ok(account) = dydx_wallet.account_offline(0) {
-                    return Ok(dydx_service.indexer_client
+                    return Ok(dydx_service.indexer()
                         .accounts()
                         .list_parent_orders(
-                            &account.subaccount(0)?.parent(),
+                            &account.subaccount(0).map_err(key_error)?.parent(),
                             Some(dydx::indexer::ListOrdersOpts {
                                 status: Some(dydx::indexer::OrderStatus::Open),
                                 ..Default::default()
                             }),
                         )
-                        .await?);
+                        .await.map_err(DydxServiceError::from)?);
                 }
             }
         }
         Ok(Vec::new())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn place_dydx_order(
         &mut self,
-        market: &str,
+        market: &Symbol,
         side: OrderSide,
-        size: f64,
+        size: OrderSize,
         price: Option<f64>,
+        trigger_price: Option<f64>,
         order_type: OrderType,
         time_in_force: OrderTimeInForce,
         leverage: f64,
         cross_margin: Option<bool>,
+        max_slippage_bps: Option<u32>,
+        good_til_secs: Option<u64>,
     ) -> Result<(String, String)> {
+        self.ensure_writable()?;
         if let Some(ref mut dydx_service) = self.dydx_service {
             let (tx_hash, order_id) = dydx_service.place_trade(TradeRequest {
-                asset: market.to_string(),
+                asset: market.clone(),
                 is_buy: matches!(side, OrderSide::Buy),
                 size,
                 price,
+                trigger_price,
                 order_type,
                 reduce_only: false,
                 leverage,
                 cross_margin,
+                max_slippage_bps,
+                time_in_force,
+                good_til_secs,
             }, leverage).await?;
             
             // Format order ID as "client_id:clob_pair_id:order_flags:subaccount_id"
@@ -573,32 +1081,23 @@ impl WalletManager {
             
             Ok((tx_hash, formatted_order_id))
         } else {
-            Err(anyhow::anyhow!("dYdX service not initialized"))
+            Err(AggregatorError::WalletNotConfigured("dYdX".to_string()))
         }
     }
 
     pub async fn init_dydx_service(&mut self) -> Result<()> {
         if let Some(ref dydx_wallet) = self.dydx_wallet {
-            let config = ClientConfig::from_file("./src/bridge_config/mainnet.toml").await?;
+            let config = dydx_config::client_config()?;
             
             // Create the configs
             let node_config = config.node.clone();
-            let indexer_config = IndexerConfig {
-                rest: RestConfig {
-                    endpoint: "https://indexer.dydx.trade".to_string(),
-                },
-                sock: SockConfig {
-                    endpoint: "wss://indexer.dydx.trade/v4/ws".to_string(),
-                    timeout: 1000,
-                    rate_limit: std::num::NonZeroU32::new(2).unwrap(),
-                },
-            };
+            let indexer_config = endpoints::dydx().indexer_config();
 
             // Connect to the node client first
-            let mut node_client = NodeClient::connect(node_config.clone()).await?;
+            let mut node_client = NodeClient::connect(node_config.clone()).await.map_err(DydxServiceError::from)?;
             
             // Get the account with chain info instead of offline
-            let account = dydx_wallet.account(0, &mut node_client).await?;
+            let account = dydx_wallet.account(0, &mut node_client).await.map_err(DydxServiceError::from)?;
 
             // Create the DydxService with properly initialized account
             let service = DydxService::new(
@@ -612,189 +1111,220 @@ impl WalletManager {
         Ok(())
     }
 
-    pub async fn bridge_to_dydx(&self, amount: f64) -> Result<()> {
-        // Open log file with timestamp
-        let log_path = "./logs/bridge.log";
-        let mut log_file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(log_path)?;
-
-        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        writeln!(log_file, "\n=== Bridge Operation Started at {} ===", timestamp)?;
-
-        if let Some(wallet) = &self.eth_wallet {
-            // Log ETH wallet details
-            writeln!(log_file, "ETH Wallet Address: {}", wallet.address())?;
-            writeln!(log_file, "Amount to Bridge: {} USDC", amount)?;
-            
-            // Setup provider and wallet with chain ID
-            let provider = Provider::<Http>::try_from(ARBITRUM_RPC)?;
-            let chain_id = 42161u64;
-            let wallet_with_chain_id = wallet.clone().with_chain_id(chain_id);
-            let client = Arc::new(provider);
-            let client_with_signer = Arc::new(SignerMiddleware::new(
-                client.clone(),
-                wallet_with_chain_id,
-            ));
-
-            // Initialize Circle Bridge contract
-            let circle_bridge_address: Address = CIRCLE_BRIDGE_ADDRESS.parse()?;
-            let circle_bridge_abi: ethers::abi::Abi = serde_json::from_str(CIRCLE_BRIDGE_ABI)?;
-            let circle_bridge_contract = Contract::new(
-                circle_bridge_address,
-                circle_bridge_abi,
-                client_with_signer.clone()
-            );
-
-            // Initialize USDC contract
-            let usdc_address: Address = USDC_ADDRESS.parse()?;
-            let usdc_abi: ethers::abi::Abi = serde_json::from_str(USDC_ABI)?;
-            let usdc_contract = Contract::new(
-                usdc_address,
-                usdc_abi,
-                client_with_signer
-            );
+    /// Bridge `amount` USDC from Arbitrum to the dYdX account through
+    /// Circle's CCTP, approving the bridge first if needed. Steps go to
+    /// `progress` as they happen. Returns once the burn is mined; the USDC
+    /// is minted on dYdX after Circle attests it.
+    pub async fn bridge_to_dydx(&self, amount: f64, progress: Option<mpsc::Sender<BridgeEvent>>) -> Result<BridgeReceipt> {
+        self.ensure_writable()?;
+        let wallet = self.eth_wallet.as_ref().ok_or_else(|| AggregatorError::WalletNotConfigured("ETH".to_string()))?;
+        let dydx_wallet = self.dydx_wallet.as_ref().ok_or_else(|| AggregatorError::WalletNotConfigured("dYdX".to_string()))?;
+        let recipient = dydx_wallet.account_offline(0)
+            .map_err(|e| AggregatorError::Dydx(e.into()))?
+            .address()
+            .to_string();
+        let recipient_bytes = bridge::cctp_recipient(&recipient)?;
+        let amount_units = bridge::usdc_units(amount)?;
+        info!(from = %format!("{:#x}", wallet.address()), to = %recipient, amount, "Bridging USDC to dYdX");
+
+        // Setup provider and wallet with chain ID
+        let provider = Provider::<Http>::try_from(self.arbitrum_rpc.as_str())?;
+        let chain_id = 42161u64;
+        let wallet_with_chain_id = wallet.clone().with_chain_id(chain_id);
+        let client = Arc::new(provider);
+        let client_with_signer = Arc::new(SignerMiddleware::new(
+            client.clone(),
+            wallet_with_chain_id,
+        ));
+
+        // Initialize Circle Bridge contract
+        let circle_bridge_address = contract_address(CIRCLE_BRIDGE_ADDRESS)?;
+        let circle_bridge_abi: ethers::abi::Abi = serde_json::from_str(CIRCLE_BRIDGE_ABI)?;
+        let circle_bridge_contract = Contract::new(
+            circle_bridge_address,
+            circle_bridge_abi,
+            client_with_signer.clone()
+        );
+
+        // Initialize USDC contract
+        let usdc_address = contract_address(USDC_ADDRESS)?;
+        let usdc_abi: ethers::abi::Abi = serde_json::from_str(USDC_ABI)?;
+        let usdc_contract = Contract::new(
+            usdc_address,
+            usdc_abi,
+            client_with_signer
+        );
+
+        // Check if allowance is sufficient
+        let allowance: U256 = usdc_contract
+            .method::<_, U256>("allowance", (wallet.address(), circle_bridge_address))?
+            .call()
+            .await?;
+        bridge::emit(&progress, BridgeEvent::AllowanceChecked {
+            allowance: bridge::from_usdc_units(allowance),
+            needed: amount,
+        }).await;
+
+        let approve_tx = if allowance < amount_units {
+            let receipt = usdc_contract
+                .method::<_, bool>(
+                    "approve",
+                    (circle_bridge_address, U256::from(2).pow(U256::from(256)) - U256::from(1)),
+                )?
+                .send()
+                .await?
+                .await?
+                .ok_or_else(|| AggregatorError::Ethereum("USDC approval was dropped from the mempool".to_string()))?;
+            let tx_hash = format!("{:#x}", receipt.transaction_hash);
+            bridge::emit(&progress, BridgeEvent::Approved { tx_hash: tx_hash.clone() }).await;
+            Some(tx_hash)
+        } else {
+            None
+        };
 
-            writeln!(log_file, "Connected to Arbitrum RPC: {}", ARBITRUM_RPC)?;
-            writeln!(log_file, "USDC Contract Address: {}", USDC_ADDRESS)?;
-            writeln!(log_file, "Circle Bridge Address: {}", CIRCLE_BRIDGE_ADDRESS)?;
+        // Check USDC balance
+        let balance: U256 = usdc_contract
+            .method::<_, U256>("balanceOf", wallet.address())?
+            .call()
+            .await?;
+        if balance < amount_units {
+            return Err(AggregatorError::InsufficientBalance { needed: amount, available: bridge::from_usdc_units(balance) });
+        }
 
-            // Convert and log amount details
-            let amount_in_wei = U256::from((amount * 1_000_000.0) as u64);
-            writeln!(log_file, "Amount in USDC (with 6 decimals): {}", amount_in_wei)?;
+        let burn = circle_bridge_contract
+            .method::<_, u64>(
+                "depositForBurn",
+                (
+                    amount_units,
+                    bridge::DYDX_DOMAIN,
+                    recipient_bytes,
+                    usdc_address,
+                ),
+            )?;
+        // Add buffer to estimated gas
+        let gas_estimate = burn.estimate_gas().await?;
+        let burn = burn.gas(gas_estimate.as_u64() + 50_000);
+        // Arrival on dYdX is measured against the balance from before
+        let baseline = self.get_dydx_usdc_balance().await.unwrap_or_else(|e| {
+            tracing::warn!("No dYdX balance before bridging: {}", e);
+            None
+        });
+        let pending = burn.send().await?;
+        let burn_tx = format!("{:#x}", pending.tx_hash());
+        // Saved before anything else can fail, so a restart can pick it up
+        let mut store = PendingBridges::load(self.pending_bridges_path())?;
+        store.set(PendingBridge::new(burn_tx.clone(), amount, recipient.clone(), baseline, chrono::Utc::now().timestamp_millis()))?;
+        bridge::emit(&progress, BridgeEvent::BurnSubmitted { tx_hash: burn_tx.clone() }).await;
+
+        let receipt = pending.await?
+            .ok_or_else(|| AggregatorError::Ethereum(format!("Bridge burn {} was dropped from the mempool", burn_tx)))?;
+        bridge::emit(&progress, BridgeEvent::BurnConfirmed {
+            tx_hash: burn_tx.clone(),
+            block: receipt.block_number.map(|block| block.as_u64()),
+        }).await;
+
+        Ok(BridgeReceipt { approve_tx, burn_tx, amount, recipient })
+    }
 
-            // Check and log allowance
-            let allowance: U256 = usdc_contract
-                .method::<_, U256>("allowance", (wallet.address(), circle_bridge_address))?
-                .call()
-                .await?;
-            writeln!(log_file, "Current USDC Allowance: {}", allowance)?;
-
-            // Check if allowance is sufficient
-            if allowance < amount_in_wei {
-                writeln!(log_file, "Insufficient allowance. Current: {}, Required: {}", allowance, amount_in_wei)?;
-                writeln!(log_file, "Approving USDC spend...")?;
-                let approve_tx = usdc_contract
-                    .method::<_, bool>(
-                        "approve",
-                        (circle_bridge_address, U256::from(2).pow(U256::from(256)) - U256::from(1)),
-                    )?
-                    .send()
-                    .await?
-                    .await?;
-                writeln!(log_file, "USDC Approval TX: {:?}", approve_tx)?;
-                
-                // Verify new allowance
-                let new_allowance: U256 = usdc_contract
-                    .method::<_, U256>("allowance", (wallet.address(), circle_bridge_address))?
-                    .call()
-                    .await?;
-                writeln!(log_file, "New USDC Allowance: {}", new_allowance)?;
-            } else {
-                writeln!(log_file, "USDC spending already approved")?;
+    /// Send `amount` USDC from the Arbitrum wallet to Hyperliquid's bridge,
+    /// then wait for the account value to reflect it. `credited` is false
+    /// if it hadn't by `HL_DEPOSIT_TIMEOUT`; the deposit still lands.
+    pub async fn deposit_to_hyperliquid(&self, amount: f64) -> Result<HlDeposit> {
+        self.ensure_writable()?;
+        let wallet = self.eth_wallet.as_ref().ok_or_else(|| AggregatorError::WalletNotConfigured("ETH".to_string()))?;
+        let amount_units = bridge::usdc_units(amount)?;
+
+        let provider = Provider::<Http>::try_from(self.arbitrum_rpc.as_str())?;
+        let client = Arc::new(SignerMiddleware::new(provider.clone(), wallet.clone().with_chain_id(42161u64)));
+        let usdc_abi: ethers::abi::Abi = serde_json::from_str(USDC_ABI)?;
+        let usdc_contract = Contract::new(contract_address(USDC_ADDRESS)?, usdc_abi, client);
+
+        let balance: U256 = usdc_contract
+            .method::<_, U256>("balanceOf", wallet.address())?
+            .call()
+            .await?;
+        let transfer = usdc_contract
+            .method::<_, bool>("transfer", (contract_address(HL_BRIDGE_ADDRESS)?, amount_units))?;
+        let gas_cost = transfer.estimate_gas().await? * provider.get_gas_price().await?;
+        let eth = provider.get_balance(wallet.address(), None).await?;
+        check_deposit(amount, bridge::from_usdc_units(balance), wei_to_eth(eth), wei_to_eth(gas_cost))?;
+
+        let before = HlAccountState::fetch_from(info_url(self.testnet), wallet.address()).await?.account_value();
+        let pending = transfer.send().await?;
+        let transfer_tx = format!("{:#x}", pending.tx_hash());
+        info!(tx = %transfer_tx, amount, "Depositing USDC to Hyperliquid");
+        pending.await?
+            .ok_or_else(|| AggregatorError::Ethereum(format!("Deposit {} was dropped from the mempool", transfer_tx)))?;
+
+        let deadline = tokio::time::Instant::now() + HL_DEPOSIT_TIMEOUT;
+        let credited = loop {
+            match HlAccountState::fetch_from(info_url(self.testnet), wallet.address()).await {
+                Ok(state) if bridge::has_arrived(before, state.account_value(), amount) => break true,
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Hyperliquid account unavailable while waiting for deposit: {}", e),
             }
-
-            // Check USDC balance
-            let balance: U256 = usdc_contract
-                .method::<_, U256>("balanceOf", wallet.address())?
-                .call()
-                .await?;
-            writeln!(log_file, "Current USDC Balance: {}", balance)?;
-
-            if balance < amount_in_wei {
-                return Err(anyhow::anyhow!("Insufficient USDC balance. Have: {}, Need: {}", balance, amount_in_wei));
+            if tokio::time::Instant::now() + HL_DEPOSIT_POLL > deadline {
+                break false;
             }
+            tokio::time::sleep(HL_DEPOSIT_POLL).await;
+        };
+        Ok(HlDeposit { transfer_tx, amount, credited })
+    }
 
-            // Get dYdX recipient details
-            let dydx_recipient = if let Some(dydx_wallet) = &self.dydx_wallet {
-                if let Ok(account) = dydx_wallet.account_offline(0) {
-                    let address_str = account.address().to_string();
-                    writeln!(log_file, "dYdX Recipient Address (bech32): {}", address_str)?;
-                    
-                    let (_, data, _) = bech32::decode(&address_str)?;
-                    let address_bytes = bech32::convert_bits(&data, 5, 8, false)?;
-                    let mut recipient_bytes = [0u8; 32];
-                    recipient_bytes[12..].copy_from_slice(&address_bytes);
-                    writeln!(log_file, "Recipient Bytes (hex): 0x{}", hex::encode(&recipient_bytes))?;
-                    H256::from(recipient_bytes)
-                } else {
-                    let err = "Failed to get dYdX account";
-                    writeln!(log_file, "Error: {}", err)?;
-                    return Err(anyhow::anyhow!(err));
-                }
-            } else {
-                let err = "No dYdX wallet configured";
-                writeln!(log_file, "Error: {}", err)?;
-                return Err(anyhow::anyhow!(err));
-            };
-
-            writeln!(log_file, "Initiating bridge transfer...")?;
-
-            // Log all parameters before sending
-            writeln!(log_file, "Transaction Parameters:")?;
-            writeln!(log_file, "Amount: {}", amount_in_wei)?;
-            writeln!(log_file, "Destination Domain: {}", 4u32)?;
-            writeln!(log_file, "Recipient: 0x{}", hex::encode(dydx_recipient.as_bytes()))?;
-            writeln!(log_file, "Token Address: {}", usdc_address)?;
-
-            // Estimate gas and send the transaction
-            let gas_estimate = circle_bridge_contract
-                .method::<_, u64>(
-                    "depositForBurn",
-                    (
-                        amount_in_wei,
-                        4u32,
-                        dydx_recipient,
-                        usdc_address,
-                    ),
-                )?
-                .estimate_gas()
-                .await?;
+    fn pending_bridges_path(&self) -> PathBuf {
+        self.config_path.with_file_name("pending_bridges.json")
+    }
 
-            writeln!(log_file, "Estimated gas: {}", gas_estimate)?;
-
-            let bridge_tx = circle_bridge_contract
-                .method::<_, u64>(
-                    "depositForBurn",
-                    (
-                        amount_in_wei,
-                        4u32,
-                        dydx_recipient,
-                        usdc_address,
-                    ),
-                )?
-                .gas(gas_estimate.as_u64() + 50_000) // Add buffer to estimated gas
-                .send()
-                .await?
-                .await?;
+    /// Bridges not yet seen minted on dYdX, e.g. from before a restart
+    pub fn pending_bridges(&self) -> Result<Vec<PendingBridge>> {
+        Ok(PendingBridges::load(self.pending_bridges_path())?.bridges().to_vec())
+    }
 
-            writeln!(log_file, "Bridge transfer completed. TX: {:?}", bridge_tx)?;
-            writeln!(log_file, "\nPlease wait 10-15 minutes for the funds to arrive on dYdX")?;
-            
-            writeln!(log_file, "=== Bridge Operation Completed Successfully ===\n")?;
-            Ok(())
-        } else {
-            let err = "No ETH wallet configured";
-            writeln!(log_file, "Error: {}", err)?;
-            Err(anyhow::anyhow!(err))
+    /// Follow the bridge burned in `burn_tx` through Circle's attestation
+    /// until its USDC shows up on dYdX, or `BRIDGE_TIMEOUT` runs out.
+    /// Returns the bridge as far as it got; one short of `Minted` stays
+    /// saved to resume later.
+    pub async fn wait_for_bridge_completion(&self, burn_tx: &str, progress: Option<mpsc::Sender<BridgeEvent>>) -> Result<PendingBridge> {
+        let mut store = PendingBridges::load(self.pending_bridges_path())?;
+        let mut pending = store.get(burn_tx).cloned()
+            .ok_or_else(|| AggregatorError::InvalidInput(format!("No pending bridge with burn tx {}", burn_tx)))?;
+        bridge::emit(&progress, BridgeEvent::Phase(pending.phase)).await;
+        bridge::track(self, &mut pending, &mut store, &progress, bridge::BRIDGE_POLL_INTERVAL, bridge::BRIDGE_TIMEOUT).await?;
+        if pending.phase != BridgePhase::Minted {
+            info!("Bridge {} still {}", pending.burn_tx, pending.phase.progress());
         }
+        Ok(pending)
+    }
+
+    /// Wallet USDC on the dYdX chain; None without a dYdX wallet
+    pub async fn get_dydx_usdc_balance(&self) -> Result<Option<f64>> {
+        let (Some(dydx_service), Some(dydx_wallet)) = (&self.dydx_service, &self.dydx_wallet) else {
+            return Ok(None);
+        };
+        let account = dydx_wallet.account_offline(0).map_err(key_error)?;
+        let balances = dydx_service.node_client.lock().await.account_balances(account.address()).await.map_err(DydxServiceError::from)?;
+        Ok(Some(balances.iter()
+            .find(|balance| balance.denom == DYDX_USDC_DENOM)
+            .map(|balance| balance.amount.parse::<f64>())
+            .transpose()?
+            .unwrap_or(0.0) / 1_000_000.0))
     }
 
     pub async fn cancel_dydx_order(&mut self, order_id: &str) -> Result<String> {
+        self.ensure_writable()?;
         let log_path = "./logs/trading.log";
         let mut log_file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(log_path)?;
 
-        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        writeln!(log_file, "\n=== Cancel Order Operation Started at {} ===", timestamp)?;
+        writeln!(log_file, "\n=== Cancel Order Operation Started at {} ===", timefmt::fmt_now())?;
         writeln!(log_file, "Attempting to cancel order ID: {}", order_id)?;
 
-        if let Some(dydx_service) = &mut self.dydx_service {
+        if let (Some(dydx_service), Some(dydx_wallet)) = (&mut self.dydx_service, &self.dydx_wallet) {
+            let owner = dydx_wallet.account_offline(0).map_err(key_error)?.address().to_string();
             // Parse the order ID
-            let parsed_order_id = match parse_order_id(order_id) {
+            let parsed_order_id = match parse_order_id(order_id, &owner) {
                 Ok(id) => {
                     writeln!(log_file, "Successfully parsed order ID: {:?}", id)?;
                     id
@@ -816,25 +1346,26 @@ impl WalletManager {
                 Err(e) => {
                     writeln!(log_file, "Failed to cancel order: {}", e)?;
                     writeln!(log_file, "=== Cancel Order Operation Failed ===\n")?;
-                    Err(anyhow::anyhow!("Failed to cancel order: {}", e))
+                    Err(AggregatorError::Dydx(e))
                 }
             }
         } else {
-            let error = "dYdX service not initialized";
+            let error = AggregatorError::WalletNotConfigured("dYdX".to_string());
             writeln!(log_file, "Error: {}", error)?;
             writeln!(log_file, "=== Cancel Order Operation Failed ===\n")?;
-            Err(anyhow::anyhow!(error))
+            Err(error)
         }
     }
 
-    pub async fn close_dydx_position(&mut self, asset: String, size: f64) -> Result<String> {
+    pub async fn close_dydx_position(&mut self, asset: &Symbol, size: f64) -> Result<String> {
+        self.ensure_writable()?;
         if let Some(dydx_service) = &mut self.dydx_service {
             // Extract just the transaction hash from the tuple
             dydx_service.close_position(asset, size).await
                 .map(|(tx_hash, _)| tx_hash)  // Only keep the tx_hash
-                .map_err(|e| anyhow::anyhow!("Failed to close dYdX position: {}", e))
+                .map_err(AggregatorError::Dydx)
         } else {
-            Err(anyhow::anyhow!("dYdX service not initialized"))
+            Err(AggregatorError::WalletNotConfigured("dYdX".to_string()))
         }
     }
 }
\ No newline at end of file
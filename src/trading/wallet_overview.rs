@@ -0,0 +1,197 @@
+use crate::error::Result;
+use async_trait::async_trait;
+use super::hl_account::HlAccountState;
+use super::wallet::WalletManager;
+
+// Longest error text shown on a wallet line
+const MAX_ERROR_LEN: usize = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EthBalances {
+    pub address: String,
+    // Arbitrum USDC
+    pub usdc: f64,
+    // Native Arbitrum ETH, which pays the gas for bridging
+    pub eth: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DydxAccount {
+    pub address: String,
+    pub equity: f64,
+    pub free_collateral: f64,
+}
+
+/// State of one independently fetched part of the wallet screen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Availability<T> {
+    // Not fetched yet
+    Pending,
+    // No wallet for this component
+    NotConfigured,
+    Ready(T),
+    // Keeps the last good value so a flaky endpoint doesn't blank the line
+    Failed { error: String, last: Option<T> },
+}
+
+impl<T: Clone> Availability<T> {
+    /// Latest value, including a stale one kept across a failure
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Self::Ready(value) => Some(value),
+            Self::Failed { last, .. } => last.as_ref(),
+            Self::Pending | Self::NotConfigured => None,
+        }
+    }
+
+    pub fn needs_fetch(&self) -> bool {
+        matches!(self, Self::Pending | Self::Failed { .. })
+    }
+
+    pub(crate) fn update(&mut self, result: Result<Option<T>>) {
+        *self = match result {
+            Ok(Some(value)) => Self::Ready(value),
+            Ok(None) => Self::NotConfigured,
+            Err(e) => Self::Failed { error: short_error(&e.to_string()), last: self.value().cloned() },
+        };
+    }
+}
+
+fn short_error(error: &str) -> String {
+    let line = error.lines().next().unwrap_or_default().trim();
+    if line.chars().count() <= MAX_ERROR_LEN {
+        return line.to_string();
+    }
+    format!("{}…", line.chars().take(MAX_ERROR_LEN).collect::<String>())
+}
+
+/// The wallet screen's data sources. Ok(None) means no wallet is configured
+/// for that component.
+#[async_trait(?Send)]
+pub trait WalletSource {
+    async fn eth_balances(&self) -> Result<Option<EthBalances>>;
+    async fn hl_account(&self) -> Result<Option<HlAccountState>>;
+    async fn dydx_account(&self) -> Result<Option<DydxAccount>>;
+}
+
+#[async_trait(?Send)]
+impl WalletSource for WalletManager {
+    async fn eth_balances(&self) -> Result<Option<EthBalances>> {
+        WalletManager::eth_balances(self).await
+    }
+
+    async fn hl_account(&self) -> Result<Option<HlAccountState>> {
+        WalletManager::hl_account(self).await
+    }
+
+    async fn dydx_account(&self) -> Result<Option<DydxAccount>> {
+        WalletManager::dydx_account(self).await
+    }
+}
+
+/// Everything the wallet screen shows, each part fetched on its own so one
+/// failing endpoint only affects its own lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletOverview {
+    pub eth: Availability<EthBalances>,
+    pub hl: Availability<HlAccountState>,
+    pub dydx: Availability<DydxAccount>,
+}
+
+impl Default for WalletOverview {
+    fn default() -> Self {
+        Self { eth: Availability::Pending, hl: Availability::Pending, dydx: Availability::Pending }
+    }
+}
+
+impl WalletOverview {
+    /// Refetch every component, e.g. after a wallet was created or imported.
+    pub async fn refresh(&mut self, source: &dyn WalletSource) {
+        self.eth.update(source.eth_balances().await);
+        self.hl.update(source.hl_account().await);
+        self.dydx.update(source.dydx_account().await);
+    }
+
+    /// Refetch only the components that failed or were never fetched.
+    pub async fn retry_failed(&mut self, source: &dyn WalletSource) {
+        if self.eth.needs_fetch() {
+            self.eth.update(source.eth_balances().await);
+        }
+        if self.hl.needs_fetch() {
+            self.hl.update(source.hl_account().await);
+        }
+        if self.dydx.needs_fetch() {
+            self.dydx.update(source.dydx_account().await);
+        }
+    }
+
+    pub fn has_failures(&self) -> bool {
+        [self.eth.needs_fetch(), self.hl.needs_fetch(), self.dydx.needs_fetch()].contains(&true)
+    }
+
+    /// Status lines for the wallet screen. `eth_address` and `dydx_address`
+    /// come from the local wallets, so they show even when every fetch fails.
+    pub fn lines(&self, eth_address: Option<String>, dydx_address: Option<String>) -> Vec<String> {
+        let mut lines = Vec::new();
+        match eth_address {
+            Some(address) => {
+                lines.push(format!("ETH Address: {}", address));
+                lines.push(field_line("USDC Balance", &self.eth, |eth| format!("${:.2}", eth.usdc)));
+                // Rides on the USDC fetch, whose line already reports failures
+                if let Some(eth) = self.eth.value() {
+                    lines.push(format!("ETH Balance (gas): {:.6} ETH", eth.eth));
+                }
+                match (&self.hl, self.hl.value()) {
+                    (Availability::Ready(_), Some(account)) => lines.extend(hl_lines(account)),
+                    (_, Some(account)) => {
+                        lines.extend(hl_lines(account));
+                        lines.push(marker_line("Hyperliquid", &self.hl));
+                    }
+                    _ => lines.push(marker_line("Hyperliquid", &self.hl)),
+                }
+            }
+            None => lines.push("No ETH wallet configured".to_string()),
+        }
+        match dydx_address {
+            Some(address) => {
+                lines.push(format!("dYdX Address: {}", address));
+                lines.push(field_line("dYdX Balance", &self.dydx, |dydx| format!("${:.2}", dydx.equity)));
+            }
+            None => lines.push("No dYdX wallet configured".to_string()),
+        }
+        lines
+    }
+}
+
+fn hl_lines(account: &HlAccountState) -> Vec<String> {
+    let mut lines = vec![
+        format!("Hyperliquid Portfolio Value: ${:.2}", account.account_value()),
+        format!("Hyperliquid Margin Used: ${:.2}", account.margin_used()),
+        format!("Hyperliquid Balance: ${:.2}", account.account_value() - account.margin_used()),
+        format!("Hyperliquid Withdrawable: ${:.2}", account.withdrawable),
+        format!("Hyperliquid Maintenance Margin: ${:.2}", account.cross_maintenance_margin_used),
+    ];
+    if account.isolated_margin() > 0.0 {
+        lines.push(format!("Hyperliquid Isolated Margin: ${:.2}", account.isolated_margin()));
+    }
+    lines
+}
+
+// "USDC Balance: $12.00", or the value marked stale, or just the error
+fn field_line<T: Clone>(label: &str, field: &Availability<T>, show: impl Fn(&T) -> String) -> String {
+    match field {
+        Availability::Ready(value) => format!("{}: {}", label, show(value)),
+        Availability::Failed { error, last: Some(value) } => format!("{}: {} (stale: {}, retrying)", label, show(value), error),
+        _ => marker_line(label, field),
+    }
+}
+
+fn marker_line<T>(label: &str, field: &Availability<T>) -> String {
+    match field {
+        Availability::Pending => format!("{}: loading…", label),
+        Availability::NotConfigured => format!("{}: unavailable", label),
+        Availability::Failed { error, last: Some(_) } => format!("{}: stale ({}, retrying)", label, error),
+        Availability::Failed { error, last: None } => format!("{}: {}, retrying", label, error),
+        Availability::Ready(_) => format!("{}: ok", label),
+    }
+}
@@ -0,0 +1,372 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+use crate::error::{AggregatorError, Result};
+
+// What has to be typed before archived keys are destroyed
+pub const PURGE_CONFIRMATION: &str = "PURGE";
+
+// Header of an encrypted wallet file
+pub const ENCRYPTED_FORMAT: &str = "hl_aggregator_wallet";
+pub const ENCRYPTED_VERSION: u32 = 1;
+// scrypt cost as log2(N); cheap under test so the suite stays fast
+const SCRYPT_LOG_N: u8 = if cfg!(test) { 10 } else { 15 };
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyKind {
+    Eth,
+    Dydx,
+}
+
+impl std::fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KeyKind::Eth => "ETH",
+            KeyKind::Dydx => "dYdX",
+        })
+    }
+}
+
+/// A key taken out of use by a create, import or restore. Kept until purged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedKey {
+    pub kind: KeyKind,
+    // Address the key controlled; empty if it couldn't be derived
+    pub address: String,
+    // Hex private key or mnemonic, as it was stored when active
+    pub secret: String,
+    pub archived_ms: i64,
+}
+
+/// A saved key the user can switch to by name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedWallet {
+    pub name: String,
+    pub kind: KeyKind,
+    // Hex private key or mnemonic
+    pub secret: String,
+}
+
+/// A named wallet as listed to the user
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletEntry {
+    pub name: String,
+    pub kind: KeyKind,
+    // Empty if the key couldn't be read
+    pub address: String,
+    pub active: bool,
+}
+
+// Name given to an active key from before wallets had names
+const UNNAMED_WALLET: &str = "default";
+
+/// The wallet file. Active keys stay at the top level under their original
+/// names; replaced ones go to `archived` and are never loaded as active.
+/// Named wallets are kept in `wallets`; switching copies one into its
+/// active slot.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WalletFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dydx_mnemonic: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wallets: Vec<NamedWallet>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archived: Vec<ArchivedKey>,
+    // Anything else in the file, kept as is
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// scrypt settings an encrypted file was written with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+    // Hex
+    pub salt: String,
+}
+
+/// AES-256-GCM key derived from a passphrase, kept while the wallet is
+/// unlocked so saves needn't ask again
+#[derive(Clone)]
+pub struct WalletKey {
+    key: [u8; 32],
+    kdf: KdfParams,
+}
+
+impl std::fmt::Debug for WalletKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalletKey").field("kdf", &self.kdf).finish_non_exhaustive()
+    }
+}
+
+impl WalletKey {
+    /// A key for a newly encrypted file, with a fresh salt
+    pub fn derive(passphrase: &str) -> Result<Self> {
+        Self::derive_with(passphrase, KdfParams {
+            log_n: SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt: hex::encode(rand::random::<[u8; 16]>()),
+        })
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key))
+    }
+
+    fn derive_with(passphrase: &str, kdf: KdfParams) -> Result<Self> {
+        let params = scrypt::Params::new(kdf.log_n, kdf.r, kdf.p, 32)
+            .map_err(|e| AggregatorError::Wallet(format!("Invalid scrypt parameters in the wallet file: {}", e)))?;
+        let mut key = [0u8; 32];
+        scrypt::scrypt(passphrase.as_bytes(), &hex::decode(&kdf.salt)?, &params, &mut key)
+            .map_err(|e| AggregatorError::Wallet(format!("Key derivation failed: {}", e)))?;
+        Ok(Self { key, kdf })
+    }
+}
+
+/// The wallet file as written with a passphrase. The plaintext is the
+/// JSON of a `WalletFile`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncryptedWallet {
+    pub format: String,
+    pub version: u32,
+    pub kdf: KdfParams,
+    // Hex; fresh for every save
+    pub nonce: String,
+    // Hex, GCM tag included
+    pub ciphertext: String,
+}
+
+impl EncryptedWallet {
+    pub fn seal(file: &WalletFile, key: &WalletKey) -> Result<Self> {
+        let nonce = rand::random::<[u8; 12]>();
+        let ciphertext = key.cipher()
+            .encrypt(Nonce::from_slice(&nonce), serde_json::to_vec(file)?.as_slice())
+            .map_err(|_| AggregatorError::Wallet("Failed to encrypt the wallet file".to_string()))?;
+        Ok(Self {
+            format: ENCRYPTED_FORMAT.to_string(),
+            version: ENCRYPTED_VERSION,
+            kdf: key.kdf.clone(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    /// Derive the key from `passphrase` and decrypt. A wrong passphrase is
+    /// `AggregatorError::WrongPassphrase`.
+    pub fn unlock(&self, passphrase: &str) -> Result<(WalletFile, WalletKey)> {
+        let key = WalletKey::derive_with(passphrase, self.kdf.clone())?;
+        Ok((self.open(&key)?, key))
+    }
+
+    pub fn open(&self, key: &WalletKey) -> Result<WalletFile> {
+        let nonce = hex::decode(&self.nonce)?;
+        if nonce.len() != 12 {
+            return Err(AggregatorError::Wallet("Corrupt wallet file: bad nonce".to_string()));
+        }
+        let plaintext = key.cipher()
+            .decrypt(Nonce::from_slice(&nonce), hex::decode(&self.ciphertext)?.as_slice())
+            .map_err(|_| AggregatorError::WrongPassphrase)?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+}
+
+/// What is on disk: a legacy plaintext file or an encrypted one
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoredWallet {
+    Plain(WalletFile),
+    Encrypted(EncryptedWallet),
+}
+
+impl StoredWallet {
+    /// None if there is no file yet
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::parse(&fs::read_to_string(path)?).map(Some)
+    }
+
+    pub fn parse(json: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(json)?;
+        if value.get("format").and_then(Value::as_str) != Some(ENCRYPTED_FORMAT) {
+            return Ok(Self::Plain(serde_json::from_value(value)?));
+        }
+        let encrypted: EncryptedWallet = serde_json::from_value(value)?;
+        if encrypted.version > ENCRYPTED_VERSION {
+            return Err(AggregatorError::Wallet(format!(
+                "Wallet file format version {} is newer than this build reads ({}); upgrade first",
+                encrypted.version, ENCRYPTED_VERSION,
+            )));
+        }
+        Ok(Self::Encrypted(encrypted))
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self, Self::Encrypted(_))
+    }
+}
+
+impl WalletFile {
+    /// The file at `path`, or an empty one if there is none yet. An
+    /// encrypted file needs the `key` it was unlocked with.
+    pub fn load(path: &Path, key: Option<&WalletKey>) -> Result<Self> {
+        match (StoredWallet::read(path)?, key) {
+            (None, _) => Ok(Self::default()),
+            (Some(StoredWallet::Plain(file)), _) => Ok(file),
+            (Some(StoredWallet::Encrypted(encrypted)), Some(key)) => encrypted.open(key),
+            (Some(StoredWallet::Encrypted(_)), None) => Err(AggregatorError::WalletLocked),
+        }
+    }
+
+    /// Write the file, encrypted under `key` if given
+    pub fn save(&self, path: &Path, key: Option<&WalletKey>) -> Result<()> {
+        let json = match key {
+            Some(key) => serde_json::to_string_pretty(&EncryptedWallet::seal(self, key)?)?,
+            None => serde_json::to_string_pretty(self)?,
+        };
+        let tmp = path.with_extension("key.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn active(&self, kind: KeyKind) -> Option<&str> {
+        match kind {
+            KeyKind::Eth => self.eth_key.as_deref(),
+            KeyKind::Dydx => self.dydx_mnemonic.as_deref(),
+        }
+    }
+
+    fn slot(&mut self, kind: KeyKind) -> &mut Option<String> {
+        match kind {
+            KeyKind::Eth => &mut self.eth_key,
+            KeyKind::Dydx => &mut self.dydx_mnemonic,
+        }
+    }
+
+    /// Make `secret` the active key of its kind. The key it replaces is
+    /// archived under `replaced_address` unless it is a named wallet;
+    /// setting the same key again is a no-op.
+    pub fn set_active(&mut self, kind: KeyKind, secret: String, replaced_address: Option<String>, now_ms: i64) {
+        if self.active(kind) == Some(secret.as_str()) {
+            return;
+        }
+        let named = self.active_name(kind).is_some();
+        if let Some(previous) = self.slot(kind).replace(secret).filter(|_| !named) {
+            self.archived.push(ArchivedKey {
+                kind,
+                address: replaced_address.unwrap_or_default(),
+                secret: previous,
+                archived_ms: now_ms,
+            });
+        }
+    }
+
+    pub fn find_wallet(&self, name: &str) -> Option<&NamedWallet> {
+        self.wallets.iter().find(|wallet| wallet.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Name of the active key of `kind`, if it was saved under one
+    pub fn active_name(&self, kind: KeyKind) -> Option<&str> {
+        let active = self.active(kind)?;
+        self.wallets.iter()
+            .find(|wallet| wallet.kind == kind && wallet.secret == active)
+            .map(|wallet| wallet.name.as_str())
+    }
+
+    /// Save `secret` under `name`. An unnamed active key of the same kind is
+    /// named "default" first so switching away can't lose it; the first
+    /// wallet of a kind becomes active.
+    pub fn add_wallet(&mut self, name: &str, kind: KeyKind, secret: String) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AggregatorError::Wallet("A wallet needs a name".to_string()));
+        }
+        if self.find_wallet(name).is_some() {
+            return Err(AggregatorError::Wallet(format!("There is already a wallet named {}", name)));
+        }
+        if let Some(existing) = self.wallets.iter().find(|wallet| wallet.kind == kind && wallet.secret == secret) {
+            return Err(AggregatorError::Wallet(format!("That key is already saved as {}", existing.name)));
+        }
+        if let Some(active) = self.active(kind).filter(|_| self.active_name(kind).is_none()).map(str::to_string) {
+            let mut unnamed = UNNAMED_WALLET.to_string();
+            let mut n = 1;
+            while self.find_wallet(&unnamed).is_some() || unnamed.eq_ignore_ascii_case(name) {
+                n += 1;
+                unnamed = format!("{}-{}", UNNAMED_WALLET, n);
+            }
+            self.wallets.push(NamedWallet { name: unnamed, kind, secret: active });
+        }
+        if self.active(kind).is_none() {
+            *self.slot(kind) = Some(secret.clone());
+        }
+        self.wallets.push(NamedWallet { name: name.to_string(), kind, secret });
+        Ok(())
+    }
+
+    /// Make the wallet called `name` active. Named keys stay in `wallets`
+    /// when switched away from; an unnamed one is archived under
+    /// `replaced_address`.
+    pub fn switch_wallet(&mut self, name: &str, replaced_address: Option<String>, now_ms: i64) -> Result<NamedWallet> {
+        let wallet = self.find_wallet(name).cloned()
+            .ok_or_else(|| AggregatorError::Wallet(format!("No wallet named {}", name.trim())))?;
+        self.set_active(wallet.kind, wallet.secret.clone(), replaced_address, now_ms);
+        Ok(wallet)
+    }
+
+    /// Take the wallet called `name` off the list, archiving its key under
+    /// `address`. The active wallet can't be removed; switch away first.
+    pub fn remove_wallet(&mut self, name: &str, address: Option<String>, now_ms: i64) -> Result<NamedWallet> {
+        let index = self.wallets.iter().position(|wallet| wallet.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| AggregatorError::Wallet(format!("No wallet named {}", name.trim())))?;
+        let wallet = &self.wallets[index];
+        if self.active(wallet.kind) == Some(wallet.secret.as_str()) {
+            return Err(AggregatorError::Wallet(format!("{} is the active {} wallet; switch to another first", wallet.name, wallet.kind)));
+        }
+        let wallet = self.wallets.remove(index);
+        self.archived.push(ArchivedKey {
+            kind: wallet.kind,
+            address: address.unwrap_or_default(),
+            secret: wallet.secret.clone(),
+            archived_ms: now_ms,
+        });
+        Ok(wallet)
+    }
+
+    /// The newest archived key for `address`, case-insensitively
+    pub fn find_archived(&self, address: &str) -> Option<&ArchivedKey> {
+        self.archived.iter().rev().find(|key| key.address.eq_ignore_ascii_case(address))
+    }
+
+    /// Move the newest archived key for `address` back to active, archiving
+    /// the key it displaces. Returns the restored entry.
+    pub fn restore(&mut self, address: &str, replaced_address: Option<String>, now_ms: i64) -> Result<ArchivedKey> {
+        let index = self.archived.iter().rposition(|key| key.address.eq_ignore_ascii_case(address))
+            .ok_or_else(|| AggregatorError::Wallet(format!("No archived key for {}", address)))?;
+        let restored = self.archived.remove(index);
+        self.set_active(restored.kind, restored.secret.clone(), replaced_address, now_ms);
+        Ok(restored)
+    }
+
+    /// Destroy every archived key, if `typed` is the confirmation phrase.
+    /// Returns how many were removed.
+    pub fn purge(&mut self, typed: &str) -> Result<usize> {
+        if typed.trim() != PURGE_CONFIRMATION {
+            return Err(AggregatorError::Wallet(format!("Type {} to purge archived keys", PURGE_CONFIRMATION)));
+        }
+        let purged = self.archived.len();
+        self.archived.clear();
+        Ok(purged)
+    }
+}
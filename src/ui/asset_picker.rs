@@ -0,0 +1,127 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use crate::aggregator::assets::{AssetDirectory, ListedAsset};
+use super::input::PromptEvent;
+use super::theme::Styles;
+
+/// The change-symbol screen: every venue's assets, narrowed by typing.
+/// Enter picks the highlighted asset; with no asset lists at all it takes
+/// the typed text as-is.
+#[derive(Debug, Clone, Default)]
+pub struct AssetPicker {
+    directory: AssetDirectory,
+    query: String,
+    selected: usize,
+}
+
+impl AssetPicker {
+    pub fn new(directory: AssetDirectory) -> Self {
+        Self { directory, ..Self::default() }
+    }
+
+    pub fn directory(&self) -> &AssetDirectory {
+        &self.directory
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Assets matching the query, best first
+    pub fn matches(&self) -> Vec<&ListedAsset> {
+        self.directory.search(&self.query)
+    }
+
+    /// The highlighted asset, if anything matches
+    pub fn selected(&self) -> Option<&ListedAsset> {
+        self.matches().get(self.selected).copied()
+    }
+
+    /// Apply one key press; Enter submits the chosen base asset
+    pub fn handle_key(&mut self, key: KeyCode) -> PromptEvent<String> {
+        match key {
+            KeyCode::Esc => return PromptEvent::Cancelled,
+            KeyCode::Enter => {
+                if let Some(asset) = self.selected() {
+                    return PromptEvent::Submitted(asset.base.clone());
+                }
+                let typed = self.query.trim();
+                if self.directory.assets.is_empty() && !typed.is_empty() {
+                    return PromptEvent::Submitted(typed.to_string());
+                }
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => {
+                if self.selected + 1 < self.matches().len() {
+                    self.selected += 1;
+                }
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        PromptEvent::Pending
+    }
+
+    /// Draw the search line and as many matches as fit, keeping the
+    /// highlighted one in view
+    pub fn render(&self, f: &mut Frame, area: Rect, styles: &Styles) {
+        let matches = self.matches();
+        let mut lines = vec![Line::from(vec![
+            Span::raw("Search: "),
+            Span::styled(format!("{}_", self.query), styles.selected),
+            Span::styled(format!("  {} of {}", matches.len(), self.directory.assets.len()), styles.muted),
+        ])];
+        for (exchange, error) in &self.directory.failed {
+            lines.push(Line::styled(format!("{} assets unavailable: {}", exchange, error), styles.alert));
+        }
+        if self.directory.assets.is_empty() {
+            lines.push(Line::styled("No asset lists loaded; Enter uses the typed symbol", styles.warn));
+        } else if matches.is_empty() {
+            lines.push(Line::styled("No matching assets", styles.muted));
+        }
+
+        // Inside the border, below the header lines
+        let rows = (area.height as usize).saturating_sub(2 + lines.len()).max(1);
+        let first = self.selected.saturating_sub(rows - 1);
+        for (i, asset) in matches.iter().enumerate().skip(first).take(rows) {
+            let venues: Vec<String> = asset.venues.iter().map(|venue| venue.to_string()).collect();
+            let mut spans = vec![
+                Span::raw(format!("{:<12}", asset.base)),
+                Span::styled(venues.join(", "), styles.muted),
+            ];
+            if let Some(venue) = asset.only_on() {
+                spans.push(Span::styled(format!("  ({} only)", venue), styles.warn));
+            }
+            let line = Line::from(spans);
+            lines.push(if i == self.selected { line.style(styles.selected) } else { line });
+        }
+
+        let widget = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Change Symbol  type to filter  ↑/↓ select  Enter ok  Esc cancel"));
+        f.render_widget(Clear, area);
+        f.render_widget(widget, area);
+    }
+
+    /// Draw the picker over most of the screen
+    pub fn render_popup(&self, f: &mut Frame, styles: &Styles) {
+        self.render(f, picker_area(f.area()), styles);
+    }
+}
+
+fn picker_area(outer: Rect) -> Rect {
+    let width = (outer.width * 3 / 5).max(40).min(outer.width);
+    let height = (outer.height * 4 / 5).max(8).min(outer.height);
+    Rect::new(outer.x + (outer.width - width) / 2, outer.y + (outer.height - height) / 2, width, height)
+}
@@ -0,0 +1,223 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+use super::theme::Styles;
+
+/// What a key did to an `InputPrompt`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptEvent<T> {
+    // Still editing, or the last Enter didn't parse
+    Pending,
+    Submitted(T),
+    Cancelled,
+}
+
+/// A one-line text field drawn inside the TUI, in place of dropping to
+/// cooked mode for stdin. Enter runs the caller's parser; a value that
+/// doesn't parse stays in the field with the reason under it.
+#[derive(Debug, Clone, Default)]
+pub struct InputPrompt {
+    title: String,
+    label: String,
+    // Shown above the field, e.g. the order being confirmed
+    detail: Vec<String>,
+    input: String,
+    error: Option<String>,
+    masked: bool,
+}
+
+impl InputPrompt {
+    pub fn new(title: &str, label: &str) -> Self {
+        Self { title: title.to_string(), label: label.to_string(), ..Self::default() }
+    }
+
+    /// Lines to read before answering, drawn above the field
+    pub fn with_detail(mut self, detail: &str) -> Self {
+        self.detail = detail.lines().map(str::to_string).collect();
+        self
+    }
+
+    /// Draw what's typed as asterisks, for passphrases
+    pub fn masked(mut self) -> Self {
+        self.masked = true;
+        self
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The field as drawn
+    pub fn shown_input(&self) -> String {
+        if self.masked { "*".repeat(self.input.chars().count()) } else { self.input.clone() }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Apply one key press. Enter hands the trimmed line to `parse`; Esc
+    /// gives up.
+    pub fn handle_key<T>(&mut self, key: KeyCode, parse: impl Fn(&str) -> Result<T, String>) -> PromptEvent<T> {
+        match key {
+            KeyCode::Esc => return PromptEvent::Cancelled,
+            KeyCode::Enter => match parse(self.input.trim()) {
+                Ok(value) => return PromptEvent::Submitted(value),
+                Err(e) => self.error = Some(e),
+            },
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.error = None;
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.error = None;
+            }
+            _ => {}
+        }
+        PromptEvent::Pending
+    }
+
+    /// Draw the field into `area`, e.g. a layout's input row
+    pub fn render(&self, f: &mut Frame, area: Rect, styles: &Styles) {
+        let mut lines: Vec<Line> = self.detail.iter().map(|line| Line::raw(line.clone())).collect();
+        lines.push(Line::from(vec![
+            Span::raw(self.label.clone()),
+            Span::styled(format!("{}_", self.shown_input()), styles.selected),
+        ]));
+        if let Some(error) = &self.error {
+            lines.push(Line::styled(error.clone(), styles.alert));
+        }
+        let widget = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(format!("{}  Enter ok  Esc cancel", self.title)));
+        f.render_widget(Clear, area);
+        f.render_widget(widget, area);
+    }
+
+    /// Draw the field as a popup across the middle of the screen
+    pub fn render_popup(&self, f: &mut Frame, styles: &Styles) {
+        let height = 5 + self.detail.len() as u16;
+        self.render(f, band(f.area(), height), styles);
+    }
+}
+
+/// Lines of text in a popup: progress while something runs, then the
+/// outcome to read before going back
+#[derive(Debug, Clone, Default)]
+pub struct MessagePanel {
+    title: String,
+    lines: Vec<String>,
+    done: bool,
+}
+
+impl MessagePanel {
+    pub fn new(title: &str) -> Self {
+        Self { title: title.to_string(), ..Self::default() }
+    }
+
+    /// Add each line of `text` at the bottom
+    pub fn push(&mut self, text: &str) {
+        self.lines.extend(text.lines().map(str::to_string));
+    }
+
+    /// Nothing more is coming; the title asks for a key
+    pub fn finish(&mut self) {
+        self.done = true;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// The newest lines that fit in `rows`, oldest first
+    pub fn visible_lines(&self, rows: usize) -> &[String] {
+        &self.lines[self.lines.len().saturating_sub(rows)..]
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, styles: &Styles) {
+        let rows = area.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self.visible_lines(rows).iter().map(|line| Line::raw(line.clone())).collect();
+        let title = if self.done {
+            Line::from(vec![Span::raw(format!("{}  ", self.title)), Span::styled("any key to close", styles.muted)])
+        } else {
+            Line::from(vec![Span::raw(format!("{}  ", self.title)), Span::styled("working...", styles.warn)])
+        };
+        let widget = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(Clear, area);
+        f.render_widget(widget, area);
+    }
+
+    /// Draw the panel across the middle of the screen, as tall as its lines
+    pub fn render_popup(&self, f: &mut Frame, styles: &Styles) {
+        let height = (self.lines.len() as u16).max(1) + 2;
+        self.render(f, band(f.area(), height), styles);
+    }
+}
+
+/// A band across the middle of `outer`, tall enough for the label and an error
+pub fn popup_area(outer: Rect) -> Rect {
+    band(outer, 5)
+}
+
+// `height` rows across the middle of `outer`, clipped to it
+fn band(outer: Rect, height: u16) -> Rect {
+    let width = (outer.width * 4 / 5).max(20).min(outer.width);
+    let height = height.min(outer.height);
+    Rect::new(outer.x + (outer.width - width) / 2, outer.y + (outer.height - height) / 2, width, height)
+}
+
+/// A number above zero
+pub fn positive_number(input: &str) -> Result<f64, String> {
+    match input.parse::<f64>() {
+        Ok(number) if number > 0.0 && number.is_finite() => Ok(number),
+        _ => Err(format!("Expected a positive number, got '{}'", input)),
+    }
+}
+
+/// A whole number above zero
+pub fn positive_integer(input: &str) -> Result<u32, String> {
+    match input.parse::<u32>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(format!("Expected a whole number above zero, got '{}'", input)),
+    }
+}
+
+/// Any line that isn't empty
+pub fn required(input: &str) -> Result<String, String> {
+    if input.is_empty() {
+        return Err("Type something, or Esc to cancel".to_string());
+    }
+    Ok(input.to_string())
+}
+
+/// The line as typed, empty included
+pub fn any_text(input: &str) -> Result<String, String> {
+    Ok(input.to_string())
+}
+
+/// y or n; empty takes `default`, when there is one
+pub fn yes_no(default: Option<bool>) -> impl Fn(&str) -> Result<bool, String> {
+    move |input| match input.to_lowercase().as_str() {
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        "" => default.ok_or_else(|| "Expected y or n".to_string()),
+        other => Err(format!("Expected y or n, got '{}'", other)),
+    }
+}
+
+/// A value of `T`; empty takes `default`
+pub fn or_default<T: std::str::FromStr + Clone>(default: T) -> impl Fn(&str) -> Result<T, String> {
+    move |input| {
+        if input.is_empty() {
+            return Ok(default.clone());
+        }
+        input.parse().map_err(|_| format!("Invalid value '{}'", input))
+    }
+}
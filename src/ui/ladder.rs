@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use ratatui::{
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use crate::aggregator::types::OrderBook;
+use crate::trading::orders::Order;
+use super::theme::Styles;
+
+/// One price level of the ladder, with book depth and my own resting size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LadderRow {
+    pub price: f64,
+    pub bid_size: f64,
+    pub ask_size: f64,
+    pub own_buy: f64,
+    pub own_sell: f64,
+}
+
+/// Scroll position of the ladder. The highlighted level is always the
+/// middle row, `offset` ticks away from mid.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LadderState {
+    pub offset: i64,
+}
+
+impl LadderState {
+    pub fn scroll_up(&mut self) {
+        self.offset += 1;
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.offset -= 1;
+    }
+
+    pub fn recenter(&mut self) {
+        self.offset = 0;
+    }
+}
+
+/// Smallest gap between book prices, for venues whose tick size we don't
+/// have cached.
+pub fn infer_tick(book: &OrderBook) -> Option<f64> {
+    let mut prices: Vec<f64> = book.bids.iter().chain(&book.asks).map(|level| level.price).collect();
+    prices.sort_by(|a, b| a.total_cmp(b));
+    prices.windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .filter(|gap| *gap > 1e-12)
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+/// Decimal places needed to show prices on a `tick` grid.
+pub fn price_decimals(tick: f64) -> usize {
+    (-tick.log10()).ceil().max(0.0) as usize
+}
+
+fn bucket(price: f64, tick: f64) -> i64 {
+    (price / tick).round() as i64
+}
+
+/// `rows` levels of `tick`, highest price first, centred on mid shifted by
+/// the scroll offset. Book levels and own orders are summed into the tick
+/// they round to. Empty when the book has no prices or the tick is invalid.
+pub fn build_ladder(book: &OrderBook, own_orders: &[Order], tick: f64, state: LadderState, rows: usize) -> Vec<LadderRow> {
+    if tick.is_nan() || tick <= 0.0 {
+        return Vec::new();
+    }
+    let mid = match (book.bids.first(), book.asks.first()) {
+        (Some(bid), Some(ask)) => (bid.price + ask.price) / 2.0,
+        (Some(bid), None) => bid.price,
+        (None, Some(ask)) => ask.price,
+        (None, None) => return Vec::new(),
+    };
+
+    let mut bids: HashMap<i64, f64> = HashMap::new();
+    for level in &book.bids {
+        *bids.entry(bucket(level.price, tick)).or_default() += level.size;
+    }
+    let mut asks: HashMap<i64, f64> = HashMap::new();
+    for level in &book.asks {
+        *asks.entry(bucket(level.price, tick)).or_default() += level.size;
+    }
+    let mut own: HashMap<i64, (f64, f64)> = HashMap::new();
+    for order in own_orders {
+        let entry = own.entry(bucket(order.price, tick)).or_default();
+        if order.side == "Buy" {
+            entry.0 += order.size;
+        } else {
+            entry.1 += order.size;
+        }
+    }
+
+    let top = bucket(mid, tick) + state.offset + rows as i64 / 2;
+    (0..rows as i64)
+        .map(|i| {
+            let level = top - i;
+            let (own_buy, own_sell) = own.get(&level).copied().unwrap_or_default();
+            LadderRow {
+                price: level as f64 * tick,
+                bid_size: bids.get(&level).copied().unwrap_or_default(),
+                ask_size: asks.get(&level).copied().unwrap_or_default(),
+                own_buy,
+                own_sell,
+            }
+        })
+        .collect()
+}
+
+/// The highlighted (middle) row.
+pub fn highlighted(rows: &[LadderRow]) -> Option<&LadderRow> {
+    rows.get(rows.len() / 2)
+}
+
+fn size_cell(size: f64) -> String {
+    if size > 0.0 { format!("{:.4}", size) } else { String::new() }
+}
+
+/// Text lines for the ladder: bid size | price | ask size | own orders.
+pub fn ladder_lines(rows: &[LadderRow], tick: f64, styles: &Styles) -> Vec<Line<'static>> {
+    let decimals = price_decimals(tick);
+    let highlight = rows.len() / 2;
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let own = match (row.own_buy > 0.0, row.own_sell > 0.0) {
+                (true, true) => format!("B{} S{}", row.own_buy, row.own_sell),
+                (true, false) => format!("B{}", row.own_buy),
+                (false, true) => format!("S{}", row.own_sell),
+                (false, false) => String::new(),
+            };
+            let line = Line::from(vec![
+                Span::styled(format!("{:>10} ", size_cell(row.bid_size)), styles.up),
+                Span::raw(format!("{:>12.*} ", decimals, row.price)),
+                Span::styled(format!("{:<10}", size_cell(row.ask_size)), styles.down),
+                Span::styled(format!(" {}", own), styles.warn),
+            ]);
+            if i == highlight {
+                line.style(styles.selected)
+            } else {
+                line
+            }
+        })
+        .collect()
+}
+
+pub fn render_ladder(f: &mut Frame, area: Rect, rows: &[LadderRow], tick: f64, title: &str, styles: &Styles) {
+    let ladder = Paragraph::new(ladder_lines(rows, tick, styles))
+        .block(Block::default().borders(Borders::ALL).title(title.to_string()));
+    f.render_widget(ladder, area);
+}
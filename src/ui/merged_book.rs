@@ -0,0 +1,51 @@
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use crate::aggregator::types::{MergedBook, MergedLevel};
+use super::ladder::price_decimals;
+use super::theme::Styles;
+
+// Levels shown per side
+const DEPTH: usize = 5;
+
+fn level_line(level: &MergedLevel, decimals: usize, style: Style, styles: &Styles) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{:>10.4}  {:>12.*}", level.size, decimals, level.price), style),
+        Span::styled(format!("  {}", level.detail()), styles.muted),
+    ])
+}
+
+/// Asks highest first above the bids, each with its per-venue split.
+pub fn merged_lines(book: &MergedBook, depth: usize, styles: &Styles) -> Vec<Line<'static>> {
+    // Enough decimals for the finest gap between shown prices
+    let decimals = book.bids.iter().take(depth).chain(book.asks.iter().take(depth))
+        .map(|level| level.price)
+        .collect::<Vec<_>>()
+        .windows(2)
+        .map(|pair| (pair[0] - pair[1]).abs())
+        .filter(|gap| *gap > 1e-12)
+        .min_by(|a, b| a.total_cmp(b))
+        .map_or(2, |gap| price_decimals(gap).max(2));
+
+    let mut lines = vec![Line::from("      Size         Price  Venues")];
+    for ask in book.asks.iter().take(depth).rev() {
+        lines.push(level_line(ask, decimals, styles.down, styles));
+    }
+    if let (Some(ask), Some(bid)) = (book.asks.first(), book.bids.first()) {
+        lines.push(Line::from(format!("Spread: {:.*}", decimals, ask.price - bid.price)));
+    }
+    for bid in book.bids.iter().take(depth) {
+        lines.push(level_line(bid, decimals, styles.up, styles));
+    }
+    lines
+}
+
+pub fn render_merged_book(f: &mut Frame, area: Rect, book: &MergedBook, title: &str, styles: &Styles) {
+    let widget = Paragraph::new(merged_lines(book, DEPTH, styles))
+        .block(Block::default().borders(Borders::ALL).title(title.to_string()));
+    f.render_widget(widget, area);
+}
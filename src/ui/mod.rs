@@ -0,0 +1,28 @@
+pub mod ladder;
+pub mod merged_book;
+pub mod pnl;
+pub mod sparkline;
+pub mod strategies;
+pub mod theme;
+pub mod orderbook;
+pub mod input;
+pub mod asset_picker;
+pub mod palette;
+pub mod table;
+pub mod trade_flow;
+pub mod watchdog;
+
+use crossterm::{
+    cursor,
+    execute,
+    terminal::{disable_raw_mode, LeaveAlternateScreen},
+};
+
+/// Put the tty back into a usable state (cooked mode, main screen, visible cursor).
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(std::io::stdout(), LeaveAlternateScreen, cursor::Show);
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,92 @@
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+use crate::aggregator::types::OrderBook;
+use crate::alerts::{place_line, Alert, LinePlacement};
+use super::theme::Styles;
+
+// Levels shown per side
+pub const BOOK_DEPTH: usize = 5;
+const RULE: &str = "------------------------------";
+
+fn format_price(price: f64) -> String {
+    if price < 10.0 {
+        format!("${:>10.6}", price)
+    } else {
+        format!("${:>10.2}", price)
+    }
+}
+
+/// A level's size and price columns, with the share `size / max_size` of
+/// the row filled in by `depth` from the left
+pub fn level_spans(size: f64, price: f64, max_size: f64, style: Style, depth: Style) -> Vec<Span<'static>> {
+    let row = format!("{:>10.4}     {}", size, format_price(price));
+    let filled = if max_size > 0.0 {
+        ((size / max_size).clamp(0.0, 1.0) * row.len() as f64).round() as usize
+    } else {
+        0
+    };
+    let (bar, rest) = row.split_at(filled);
+    [(bar, style.patch(depth)), (rest, style)].into_iter()
+        .filter(|(text, _)| !text.is_empty())
+        .map(|(text, style)| Span::styled(text.to_string(), style))
+        .collect()
+}
+
+fn marker_spans(alerts: &[&Alert], styles: &Styles) -> Vec<Span<'static>> {
+    alerts.iter()
+        .map(|alert| if alert.is_triggered() {
+            Span::styled(format!(" <- alert {} (hit)", alert.rule.label()), styles.muted)
+        } else {
+            Span::styled(format!(" <- alert {}", alert.rule.label()), styles.warn)
+        })
+        .collect()
+}
+
+fn off_book_line(arrow: &str, alert: &Alert, styles: &Styles) -> Line<'static> {
+    Line::styled(
+        format!("  {} alert {} (off book){}", arrow, alert.rule.label(), if alert.is_triggered() { " (hit)" } else { "" }),
+        styles.warn,
+    )
+}
+
+/// Asks above bids, best levels nearest the mid, with alert lines snapped
+/// to the level they fall on. Depth bars are scaled to the largest level
+/// shown on either side.
+pub fn orderbook_lines(book: &OrderBook, depth: usize, alerts: &[&Alert], styles: &Styles) -> Vec<Line<'static>> {
+    let visible_asks = &book.asks[..book.asks.len().min(depth)];
+    let visible_bids = &book.bids[..book.bids.len().min(depth)];
+    let placed: Vec<(LinePlacement, &Alert)> = alerts.iter()
+        // Only price lines have a place in the book
+        .filter_map(|alert| place_line(alert.price()?, visible_asks, visible_bids).map(|p| (p, *alert)))
+        .collect();
+    let at = |placement: LinePlacement| -> Vec<&Alert> {
+        placed.iter().filter(|(p, _)| *p == placement).map(|(_, a)| *a).collect()
+    };
+    let max_size = visible_asks.iter().chain(visible_bids).map(|level| level.size).fold(0.0, f64::max);
+    let level_line = |size: f64, price: f64, placement: LinePlacement, style: Style, depth: Style| {
+        let mut spans = level_spans(size, price, max_size, style, depth);
+        spans.extend(marker_spans(&at(placement), styles));
+        Line::from(spans)
+    };
+
+    let mut lines = vec![Line::from("Asks:"), Line::from("      Size          Price"), Line::from(RULE)];
+    lines.extend(at(LinePlacement::AboveBook).into_iter().map(|alert| off_book_line("^", alert, styles)));
+    for (i, ask) in visible_asks.iter().enumerate().rev() {
+        lines.push(level_line(ask.size, ask.price, LinePlacement::Ask(i), styles.down, styles.ask_depth));
+    }
+
+    if let Some(mid) = book.mid_price() {
+        lines.push(Line::from(RULE));
+        lines.push(Line::from(format!("Market Price: ${:.2}", mid)));
+        lines.push(Line::from(RULE));
+    }
+
+    lines.push(Line::from("Bids:"));
+    for (i, bid) in visible_bids.iter().enumerate() {
+        lines.push(level_line(bid.size, bid.price, LinePlacement::Bid(i), styles.up, styles.bid_depth));
+    }
+    lines.extend(at(LinePlacement::BelowBook).into_iter().map(|alert| off_book_line("v", alert, styles)));
+    lines
+}
@@ -0,0 +1,253 @@
+use ratatui::{
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+use crate::aggregator::exchange_id::ExchangeId;
+use crate::aggregator::symbol::Symbol;
+use crate::analytics::{liquidation_price, maintenance_margin_rate};
+use super::theme::Styles;
+
+pub const COMMANDS: [&str; 4] = ["conv", "liq", "spread", "fund"];
+// Notional the funding projection is quoted on
+pub const FUNDING_NOTIONAL: f64 = 10_000.0;
+const HISTORY_LIMIT: usize = 50;
+
+/// One palette expression, e.g. `liq 0.5 btc 10x long`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    // Base size to USD at each venue's mark
+    Conv { size: f64, symbol: Symbol },
+    // Estimated liquidation price of an isolated position opened at the mark
+    Liq { size: f64, symbol: Symbol, leverage: f64, is_long: bool },
+    // Mark difference between the venues
+    Spread { symbol: Symbol },
+    // Funding paid or received on FUNDING_NOTIONAL over a period
+    Fund { symbol: Symbol, hours: f64 },
+}
+
+impl Command {
+    pub fn symbol(&self) -> &Symbol {
+        match self {
+            Self::Conv { symbol, .. } | Self::Liq { symbol, .. } | Self::Spread { symbol } | Self::Fund { symbol, .. } => symbol,
+        }
+    }
+}
+
+fn usage(command: &str) -> &'static str {
+    match command {
+        "conv" => "conv <size> <symbol>",
+        "liq" => "liq <size> <symbol> <leverage>x <long|short>",
+        "spread" => "spread <symbol>",
+        _ => "fund <symbol> <period, e.g. 8h, 3d, 1w>",
+    }
+}
+
+fn positive(value: &str, what: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(number) if number > 0.0 && number.is_finite() => Ok(number),
+        _ => Err(format!("{} must be a positive number, got '{}'", what, value)),
+    }
+}
+
+fn symbol(value: &str) -> Result<Symbol, String> {
+    Symbol::parse_user_input(value).map_err(|e| e.to_string())
+}
+
+/// "8h", "3d" or "1w" in hours; a bare number is hours
+fn parse_hours(value: &str) -> Result<f64, String> {
+    let lower = value.to_lowercase();
+    let (number, unit) = match lower.char_indices().last() {
+        Some((i, unit @ ('h' | 'd' | 'w'))) => (&lower[..i], unit),
+        _ => (lower.as_str(), 'h'),
+    };
+    let hours = positive(number, "Period").map_err(|_| format!("Expected a period like 8h, 3d or 1w, got '{}'", value))?;
+    Ok(match unit {
+        'd' => hours * 24.0,
+        'w' => hours * 24.0 * 7.0,
+        _ => hours,
+    })
+}
+
+/// Parse a palette line; the leading ':' is optional. Unknown commands list
+/// the available ones.
+pub fn parse(input: &str) -> Result<Command, String> {
+    let input = input.trim().trim_start_matches(':');
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let Some((&name, args)) = words.split_first() else {
+        return Err(format!("Available: {}", COMMANDS.join(", ")));
+    };
+    let name = name.to_lowercase();
+    let wrong_args = || format!("Usage: {}", usage(&name));
+    match name.as_str() {
+        "conv" => match args {
+            [size, base] => Ok(Command::Conv { size: positive(size, "Size")?, symbol: symbol(base)? }),
+            _ => Err(wrong_args()),
+        },
+        "liq" => match args {
+            [size, base, leverage, side] => {
+                let leverage = positive(leverage.trim_end_matches(['x', 'X']), "Leverage")?;
+                let is_long = match side.to_lowercase().as_str() {
+                    "long" | "buy" => true,
+                    "short" | "sell" => false,
+                    other => return Err(format!("Side must be long or short, got '{}'", other)),
+                };
+                Ok(Command::Liq { size: positive(size, "Size")?, symbol: symbol(base)?, leverage, is_long })
+            }
+            _ => Err(wrong_args()),
+        },
+        "spread" => match args {
+            [base] => Ok(Command::Spread { symbol: symbol(base)? }),
+            _ => Err(wrong_args()),
+        },
+        "fund" => match args {
+            [base, period] => Ok(Command::Fund { symbol: symbol(base)?, hours: parse_hours(period)? }),
+            _ => Err(wrong_args()),
+        },
+        other => Err(format!("Unknown command '{}'. Available: {}", other, COMMANDS.join(", "))),
+    }
+}
+
+/// What the evaluator needs from one venue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VenueQuote {
+    pub exchange: ExchangeId,
+    pub mark: f64,
+    // Hourly, as a fraction
+    pub funding_rate: f64,
+    pub max_leverage: Option<f64>,
+}
+
+/// Result lines for `command` against each venue's quote.
+pub fn evaluate(command: &Command, quotes: &[VenueQuote]) -> Result<Vec<String>, String> {
+    let base = command.symbol().base();
+    let quotes: Vec<&VenueQuote> = quotes.iter().filter(|quote| quote.mark > 0.0).collect();
+    if quotes.is_empty() {
+        return Err(format!("No market data for {}", command.symbol()));
+    }
+    match command {
+        Command::Conv { size, .. } => Ok(quotes.iter()
+            .map(|quote| format!("{}: {} {} = ${:.2} at {:.4}", quote.exchange, size, base, size * quote.mark, quote.mark))
+            .collect()),
+        Command::Liq { size, leverage, is_long, .. } => Ok(quotes.iter()
+            .map(|quote| {
+                let side = if *is_long { "long" } else { "short" };
+                if let Some(max) = quote.max_leverage.filter(|max| leverage > max) {
+                    return format!("{}: {}x is above the {}x max", quote.exchange, leverage, max);
+                }
+                let rate = maintenance_margin_rate(quote.max_leverage.unwrap_or(*leverage));
+                match liquidation_price(quote.mark, *leverage, *is_long, rate) {
+                    Some(price) => format!(
+                        "{}: {} {} {} at {}x from {:.4}, margin ${:.2}, liquidates near {:.4} ({:+.2}%)",
+                        quote.exchange, side, size, base, leverage, quote.mark,
+                        size * quote.mark / leverage, price, (price / quote.mark - 1.0) * 100.0
+                    ),
+                    None => format!("{}: {}x leaves no room over maintenance margin", quote.exchange, leverage),
+                }
+            })
+            .collect()),
+        Command::Spread { .. } => {
+            let [low, high] = match quotes.as_slice() {
+                [a, b, ..] if a.mark <= b.mark => [a, b],
+                [a, b, ..] => [b, a],
+                _ => return Err(format!("Only {} has {}; need two venues for a spread", quotes[0].exchange, base)),
+            };
+            let bps = (high.mark - low.mark) / low.mark * 10_000.0;
+            Ok(vec![format!(
+                "{} spread {:.2} bps: {} {:.4} over {} {:.4}",
+                base, bps, high.exchange, high.mark, low.exchange, low.mark
+            )])
+        }
+        Command::Fund { hours, .. } => Ok(quotes.iter()
+            .map(|quote| {
+                // Longs pay a positive rate, shorts receive it
+                let payment = FUNDING_NOTIONAL * quote.funding_rate * hours;
+                format!(
+                    "{}: {:+.4}%/h over {}h on ${:.0}: long {:+.2}, short {:+.2}",
+                    quote.exchange, quote.funding_rate * 100.0, hours, FUNDING_NOTIONAL, -payment, payment
+                )
+            })
+            .collect()),
+    }
+}
+
+/// One evaluated line and what it produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteEntry {
+    pub input: String,
+    pub result: Result<Vec<String>, String>,
+}
+
+/// Past palette lines with their results, newest last. Up and Down walk
+/// back through the inputs.
+#[derive(Debug, Default)]
+pub struct PaletteHistory {
+    entries: Vec<PaletteEntry>,
+    // Index being recalled, None when editing a fresh line
+    cursor: Option<usize>,
+}
+
+impl PaletteHistory {
+    pub fn push(&mut self, input: String, result: Result<Vec<String>, String>) {
+        self.entries.push(PaletteEntry { input, result });
+        if self.entries.len() > HISTORY_LIMIT {
+            self.entries.remove(0);
+        }
+        self.cursor = None;
+    }
+
+    pub fn entries(&self) -> &[PaletteEntry] {
+        &self.entries
+    }
+
+    /// The input before the one last recalled
+    pub fn previous(&mut self) -> Option<&str> {
+        let index = match self.cursor {
+            Some(0) => 0,
+            Some(index) => index - 1,
+            None => self.entries.len().checked_sub(1)?,
+        };
+        self.cursor = Some(index);
+        Some(&self.entries[index].input)
+    }
+
+    /// The input after the one last recalled; None back at a fresh line
+    pub fn next(&mut self) -> Option<&str> {
+        let index = self.cursor? + 1;
+        if index >= self.entries.len() {
+            self.cursor = None;
+            return None;
+        }
+        self.cursor = Some(index);
+        Some(&self.entries[index].input)
+    }
+}
+
+/// The palette popup: recent results over the line being typed.
+pub fn render_palette(f: &mut Frame, input: &str, history: &PaletteHistory, styles: &Styles) {
+    let outer = f.area();
+    let width = (outer.width * 4 / 5).max(20).min(outer.width);
+    let height = (outer.height / 2).max(6).min(outer.height);
+    let area = Rect::new(outer.x + (outer.width - width) / 2, outer.y + (outer.height - height) / 2, width, height);
+
+    let mut lines: Vec<Line> = Vec::new();
+    for entry in history.entries() {
+        lines.push(Line::styled(format!(":{}", entry.input), styles.muted));
+        match &entry.result {
+            Ok(results) => lines.extend(results.iter().map(|result| Line::from(format!("  {}", result)))),
+            Err(e) => lines.push(Line::styled(format!("  {}", e), styles.alert)),
+        }
+    }
+    // Keep the newest results in view above the input line
+    let visible = height.saturating_sub(3) as usize;
+    let skip = lines.len().saturating_sub(visible);
+    let mut lines: Vec<Line> = lines.into_iter().skip(skip).collect();
+    lines.push(Line::from(vec![Span::styled(":", styles.selected), Span::raw(format!("{}_", input))]));
+
+    let popup = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(format!("Palette: {}  Enter run  Up/Down history  Esc close", COMMANDS.join(", "))));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
@@ -0,0 +1,43 @@
+use ratatui::{
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use crate::trading::pnl::AttributionNode;
+use super::theme::{signed, Styles};
+
+fn amounts(node: &AttributionNode) -> String {
+    format!("{:>12.2} {:>12.2} {:>12.2}", node.price, node.funding, node.fees)
+}
+
+/// A node's children as table rows, the selected one highlighted, with the
+/// node's own total underneath.
+pub fn attribution_lines(node: &AttributionNode, selected: usize, styles: &Styles) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::from(format!("  {:<20} {:>12} {:>12} {:>12} {:>14}", "Group", "Price", "Funding", "Fees", "Net"))];
+    if node.children.is_empty() {
+        lines.push(Line::styled("  No fills or funding in this range", styles.muted));
+    }
+    for (index, child) in node.children.iter().enumerate() {
+        let marker = if index == selected { ">" } else { " " };
+        let mut line = Line::from(vec![
+            Span::raw(format!("{} {:<20} {} ", marker, child.key, amounts(child))),
+            Span::styled(format!("{:>14}", signed(child.net(), 2)), styles.direction(child.net())),
+        ]);
+        if index == selected {
+            line = line.style(styles.selected);
+        }
+        lines.push(line);
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::raw(format!("  {:<20} {} ", "Total", amounts(node))),
+        Span::styled(format!("{:>14}", signed(node.net(), 2)), styles.direction(node.net())),
+    ]));
+    lines
+}
+
+pub fn render_attribution(f: &mut Frame, node: &AttributionNode, selected: usize, title: &str, styles: &Styles) {
+    let widget = Paragraph::new(attribution_lines(node, selected, styles))
+        .block(Block::default().borders(Borders::ALL).title(title.to_string()));
+    f.render_widget(widget, f.area());
+}
@@ -0,0 +1,39 @@
+use ratatui::{
+    layout::Rect,
+    widgets::Sparkline,
+    Frame,
+};
+use super::theme::Styles;
+
+// Resolution of a price sparkline: the range maps onto this many steps
+const SPARKLINE_STEPS: f64 = 1000.0;
+
+/// Scale prices into the sparkline's integer range relative to the window's
+/// low. Every value is at least 1, so a flat market still draws a line.
+pub fn price_sparkline_values(prices: &[f64]) -> Vec<u64> {
+    let min = prices.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = prices.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    prices.iter()
+        .map(|price| {
+            let scaled = if range > 0.0 { (price - min) / range * SPARKLINE_STEPS } else { 0.0 };
+            scaled.round().max(0.0) as u64 + 1
+        })
+        .collect()
+}
+
+/// Recent mids, styled by whether the window closed up or down.
+pub fn render_price_sparkline(f: &mut Frame, area: Rect, prices: &[f64], styles: &Styles) {
+    if area.height == 0 || prices.is_empty() {
+        return;
+    }
+    let change = match (prices.first(), prices.last()) {
+        (Some(first), Some(last)) => last - first,
+        _ => 0.0,
+    };
+    let values = price_sparkline_values(prices);
+    let sparkline = Sparkline::default()
+        .data(&values)
+        .style(styles.direction(change));
+    f.render_widget(sparkline, area);
+}
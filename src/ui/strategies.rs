@@ -0,0 +1,83 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use crate::trading::automation::{ActionOutcome, StrategyStatus};
+use crate::trading::farm::FarmReport;
+use super::theme::{signed, Styles};
+
+/// One numbered row per strategy: state, position, PnL and its last action.
+pub fn strategy_lines(statuses: &[StrategyStatus], styles: &Styles) -> Vec<Line<'static>> {
+    if statuses.is_empty() {
+        return vec![Line::from("No strategies configured. Set HL_MA_CROSS or HL_QUOTER to enable one.")];
+    }
+    let mut lines = Vec::new();
+    for (index, status) in statuses.iter().enumerate() {
+        let total = status.pnl.total();
+        let (state, state_style) = if status.running { ("RUNNING", styles.up) } else { ("stopped", styles.muted) };
+        lines.push(Line::from(vec![
+            Span::raw(format!("{}. {:<28} ", index + 1, status.name)),
+            Span::styled(format!("{:<8}", state), state_style),
+            Span::raw(format!(" pos {:>10.4}  rPnL {:>9.2}  uPnL {:>9.2}  ", status.pnl.position, status.pnl.realized, status.pnl.unrealized())),
+            Span::styled(format!("PnL {}", signed(total, 2)), styles.direction(total)),
+        ]));
+        if let Some(detail) = &status.detail {
+            lines.push(Line::from(format!("   {}", detail)));
+        }
+        if let Some(action) = &status.last_action {
+            let outcome = match &action.outcome {
+                ActionOutcome::Executed { .. } => "executed".to_string(),
+                ActionOutcome::Simulated => "simulated".to_string(),
+                ActionOutcome::Blocked { reason } => format!("blocked: {}", reason),
+                ActionOutcome::Failed { error } => format!("failed: {}", error),
+            };
+            lines.push(Line::from(format!("   last: {} ({})", action.action, outcome)));
+        }
+    }
+    lines
+}
+
+/// Three rows per tracked funding farm, realized APR styled by sign.
+pub fn farm_lines(reports: &[FarmReport], styles: &Styles) -> Vec<Line<'static>> {
+    if reports.is_empty() {
+        return vec![Line::from("No funding farms tracked. Open a long and a short across venues, then press f.")];
+    }
+    reports.iter()
+        .flat_map(|report| {
+            let style = match report.realized_apr() {
+                Some(apr) => styles.direction(apr),
+                None => styles.muted,
+            };
+            let mut lines: Vec<Line<'static>> = report.lines().into_iter().map(Line::from).collect();
+            if let Some(last) = lines.last_mut() {
+                *last = last.clone().style(style);
+            }
+            lines
+        })
+        .collect()
+}
+
+pub fn render_strategies(f: &mut Frame, statuses: &[StrategyStatus], farms: &[FarmReport], dry_run: bool, status: Option<&str>, styles: &Styles) {
+    let farm_rows = farm_lines(farms, styles);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Min(0), Constraint::Length(farm_rows.len() as u16 + 2), Constraint::Length(3)])
+        .split(f.area());
+
+    let title = if dry_run { "Strategies (dry run)" } else { "Strategies (LIVE)" };
+    let list = Paragraph::new(strategy_lines(statuses, styles))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(list, chunks[0]);
+
+    let farms = Paragraph::new(farm_rows)
+        .block(Block::default().borders(Borders::ALL).title("Funding farms"));
+    f.render_widget(farms, chunks[1]);
+
+    let keys = "1-9. Start/stop strategy  f. Track farm in current symbol  x. Stop tracking  q. Back";
+    let help = Paragraph::new(status.map_or_else(|| keys.to_string(), |status| format!("{}  |  {}", status, keys)))
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(help, chunks[2]);
+}
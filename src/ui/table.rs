@@ -0,0 +1,68 @@
+/// Column-aligned output for the CLI subcommands.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Self {
+        Self { headers: headers.iter().map(|header| header.to_string()).collect(), rows: Vec::new() }
+    }
+
+    /// Missing cells are left blank; extra ones are dropped.
+    pub fn row(&mut self, cells: Vec<String>) {
+        let mut cells = cells;
+        cells.resize(self.headers.len(), String::new());
+        self.rows.push(cells);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// `plain` is ASCII only, without ANSI styling, for screen readers and
+    /// pipes. Otherwise the header is bold and columns are ruled.
+    pub fn render(&self, plain: bool) -> String {
+        let widths: Vec<usize> = (0..self.headers.len())
+            .map(|column| {
+                std::iter::once(&self.headers[column])
+                    .chain(self.rows.iter().map(|row| &row[column]))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let separator = if plain { "  " } else { " │ " };
+        let line = |cells: &[String]| -> String {
+            cells.iter().zip(&widths)
+                .map(|(cell, &width)| {
+                    // Numbers line up on the right
+                    if cell.trim_start_matches(['$', '+', '-']).parse::<f64>().is_ok() {
+                        format!("{:>width$}", cell)
+                    } else {
+                        format!("{:<width$}", cell)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(separator)
+                .trim_end()
+                .to_string()
+        };
+
+        let header = line(&self.headers);
+        let rule = widths.iter()
+            .map(|&width| if plain { "-".repeat(width) } else { "─".repeat(width) })
+            .collect::<Vec<_>>()
+            .join(if plain { "  " } else { "─┼─" });
+        let mut out = if plain { header } else { format!("\x1b[1m{}\x1b[0m", header) };
+        out.push('\n');
+        out.push_str(&rule);
+        out.push('\n');
+        for row in &self.rows {
+            out.push_str(&line(row));
+            out.push('\n');
+        }
+        out
+    }
+}
@@ -0,0 +1,583 @@
+#[cfg(test)]
+mod watchdog_tests {
+    use std::time::Duration;
+    use crate::ui::watchdog::{check_stall, format_status, OperationCell, StallCheck};
+
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn test_idle_loop_is_not_a_stall() {
+        // Waiting on a key press for minutes with nothing in flight
+        assert_eq!(check_stall(Duration::from_secs(600), None, TIMEOUT), StallCheck::Healthy);
+    }
+
+    #[test]
+    fn test_long_operation_is_a_stall() {
+        let op = Some(("placing dYdX order".to_string(), Duration::from_secs(31)));
+        assert_eq!(
+            check_stall(Duration::from_secs(31), op, TIMEOUT),
+            StallCheck::Stalled { label: "placing dYdX order".to_string(), elapsed: Duration::from_secs(31) }
+        );
+    }
+
+    #[test]
+    fn test_recent_heartbeat_is_not_a_stall() {
+        let op = Some(("refreshing market data".to_string(), Duration::from_secs(45)));
+        assert_eq!(check_stall(Duration::from_secs(1), op, TIMEOUT), StallCheck::Healthy);
+    }
+
+    #[test]
+    fn test_operation_guard_clears_cell() {
+        let cell = OperationCell::default();
+        {
+            let _guard = cell.begin("closing Hyperliquid position");
+            assert_eq!(cell.current().unwrap().0, "closing Hyperliquid position");
+        }
+        assert!(cell.current().is_none());
+        assert!(cell.status_line().is_none());
+    }
+
+    #[test]
+    fn test_format_status() {
+        assert_eq!(format_status("placing dYdX order", Duration::from_millis(4200)), "placing dYdX order… 4s");
+    }
+}
+
+#[cfg(test)]
+mod ladder_tests {
+    use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::types::OrderBook;
+    use crate::test_fixtures::{self, levels};
+    use crate::trading::orders::Order;
+    use crate::ui::ladder::{build_ladder, highlighted, infer_tick, price_decimals, render_ladder, LadderState};
+    use crate::ui::theme::Styles;
+
+    fn book() -> OrderBook {
+        test_fixtures::book(ExchangeId::Hyperliquid, &[(99.5, 2.0), (99.0, 1.0)], &[(100.5, 3.0), (101.0, 1.0)], 0)
+    }
+
+    fn order(side: &str, price: f64, size: f64) -> Order {
+        Order { side: side.to_string(), price, size, ..test_fixtures::order(ExchangeId::Hyperliquid, "1") }
+    }
+
+    // Rendered rows inside the border, trailing blanks trimmed
+    fn content_lines(buffer: &Buffer) -> Vec<String> {
+        let area = buffer.area;
+        (1..area.height - 1)
+            .map(|y| {
+                (1..area.width - 1)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ladder_is_centred_on_mid_and_bucketed() {
+        // 99.7 and 99.8 both round onto the 99.5 tick
+        let mut book = book();
+        book.bids = levels(&[(99.7, 1.5), (99.5, 0.5), (99.0, 1.0)]);
+        let rows = build_ladder(&book, &[], 0.5, LadderState::default(), 5);
+
+        let prices: Vec<f64> = rows.iter().map(|row| row.price).collect();
+        assert_eq!(prices, vec![101.0, 100.5, 100.0, 99.5, 99.0]);
+        assert_eq!(highlighted(&rows).unwrap().price, 100.0);
+        assert_eq!(rows[3].bid_size, 2.0);
+        assert_eq!(rows[1].ask_size, 3.0);
+    }
+
+    #[test]
+    fn test_scrolling_moves_the_highlight() {
+        let mut state = LadderState::default();
+        state.scroll_up();
+        state.scroll_up();
+        let rows = build_ladder(&book(), &[], 0.5, state, 5);
+        assert_eq!(highlighted(&rows).unwrap().price, 101.0);
+
+        state.scroll_down();
+        state.recenter();
+        assert_eq!(state, LadderState::default());
+    }
+
+    #[test]
+    fn test_infer_tick_and_decimals() {
+        assert_eq!(infer_tick(&book()), Some(0.5));
+        assert_eq!(price_decimals(0.5), 1);
+        assert_eq!(price_decimals(0.01), 2);
+        assert_eq!(price_decimals(1.0), 0);
+        assert!(build_ladder(&book(), &[], 0.0, LadderState::default(), 5).is_empty());
+    }
+
+    #[test]
+    fn test_render_snapshot_with_own_orders() {
+        let own = vec![order("Buy", 99.0, 0.1), order("Sell", 100.52, 0.2)];
+        let rows = build_ladder(&book(), &own, 0.5, LadderState::default(), 5);
+
+        let mut terminal = Terminal::new(TestBackend::new(44, 7)).unwrap();
+        terminal.draw(|f| render_ladder(f, f.area(), &rows, 0.5, "Ladder", &Styles::default())).unwrap();
+
+        assert_eq!(content_lines(terminal.backend().buffer()), vec![
+            "                  101.0 1.0000",
+            "                  100.5 3.0000     S0.2",
+            "                  100.0",
+            "    2.0000         99.5",
+            "    1.0000         99.0            B0.1",
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod trade_flow_tests {
+    use crate::aggregator::trade_flow::ProfileLevel;
+    use crate::ui::theme::Styles;
+    use crate::ui::trade_flow::{profile_lines, sparkline_values};
+
+    fn text(line: &ratatui::text::Line) -> String {
+        line.spans.iter().map(|span| span.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_profile_bars_scale_to_busiest_bucket() {
+        let profile = vec![
+            ProfileLevel { price: 101.0, buy_volume: 1.0, sell_volume: 1.0 },
+            ProfileLevel { price: 100.0, buy_volume: 3.0, sell_volume: 1.0 },
+        ];
+        let lines = profile_lines(&profile, 1.0, 8, &Styles::default());
+        assert_eq!(text(&lines[0]), "         101 ████ 2.0000");
+        assert_eq!(text(&lines[1]), "         100 ████████ 4.0000");
+        // Buy share first, then sells
+        assert_eq!(lines[1].spans[1].content, "██████");
+        assert_eq!(lines[1].spans[2].content, "██");
+        assert!(profile_lines(&[], 1.0, 8, &Styles::default()).is_empty());
+    }
+
+    #[test]
+    fn test_sparkline_values_shift_to_zero() {
+        assert_eq!(sparkline_values(&[-1.0, 0.0, 0.5]), vec![0, 10_000, 15_000]);
+        assert!(sparkline_values(&[]).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod sparkline_tests {
+    use crate::ui::sparkline::price_sparkline_values;
+
+    #[test]
+    fn test_price_values_span_window_range() {
+        assert_eq!(price_sparkline_values(&[100.0, 100.5, 101.0]), vec![1, 501, 1001]);
+        // A flat market still draws
+        assert_eq!(price_sparkline_values(&[50.0, 50.0]), vec![1, 1]);
+        assert!(price_sparkline_values(&[]).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod theme_tests {
+    use ratatui::style::Modifier;
+    use ratatui::text::Line;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::types::OrderBook;
+    use crate::test_fixtures;
+    use crate::trading::positions::{LivePnl, Position};
+    use crate::trading::pnl::AttributionNode;
+    use crate::ui::orderbook::{level_spans, orderbook_lines, BOOK_DEPTH};
+    use crate::ui::pnl::attribution_lines;
+    use crate::ui::table::Table;
+    use crate::ui::theme::{signed, Styles, Theme};
+
+    // Each span as text plus its foreground color and modifiers, so a
+    // snapshot pins down both what is drawn and how
+    fn snapshot(lines: &[Line]) -> Vec<String> {
+        lines.iter()
+            .map(|line| line.spans.iter()
+                .map(|span| {
+                    let mut tags = Vec::new();
+                    if let Some(fg) = span.style.fg {
+                        tags.push(format!("{:?}", fg));
+                    }
+                    for (modifier, name) in [(Modifier::BOLD, "bold"), (Modifier::UNDERLINED, "underlined")] {
+                        if span.style.add_modifier.contains(modifier) {
+                            tags.push(name.to_string());
+                        }
+                    }
+                    if tags.is_empty() { span.content.to_string() } else { format!("{}[{}]", span.content, tags.join(",")) }
+                })
+                .collect::<String>())
+            .collect()
+    }
+
+    fn book() -> OrderBook {
+        test_fixtures::book(ExchangeId::Hyperliquid, &[(99.5, 2.0)], &[(100.5, 3.0)], 0)
+    }
+
+    fn losing_position() -> Position {
+        Position {
+            exchange: ExchangeId::Hyperliquid,
+            asset: "BTC".to_string(),
+            size: -0.5,
+            entry_price: Some(100.0),
+            liquidation_price: None,
+            unrealized_pnl: -12.5,
+            margin_used: None,
+            leverage: None,
+            roe: Some(-0.25),
+            side: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_orderbook_snapshot_per_theme() {
+        let render = |theme| snapshot(&orderbook_lines(&book(), BOOK_DEPTH, &[], &Styles::for_theme(theme)));
+        let ask = |tags: &str| format!("    3.0000     $    100.50{}", tags);
+        // The largest level's depth bar fills its row; a smaller one's splits it
+        let bid = |tags: &str| format!("    2.0000     $ {}    99.50{}", tags, tags);
+
+        let default = render(Theme::Default);
+        assert_eq!(default[3], ask("[Red]"));
+        assert_eq!(default[5], "Market Price: $100.00");
+        assert_eq!(default[8], bid("[Green]"));
+
+        let contrast = render(Theme::HighContrast);
+        assert_eq!(contrast[3], ask("[LightMagenta,bold]"));
+        assert_eq!(contrast[8], bid("[LightBlue,bold]"));
+
+        // Sides still differ without any color
+        let mono = render(Theme::Monochrome);
+        assert_eq!(mono[3], ask(""));
+        assert_eq!(mono[8], bid("[bold]"));
+    }
+
+    #[test]
+    fn test_depth_bars_scale_to_the_largest_level() {
+        let styles = Styles::default();
+        let spans = level_spans(1.0, 100.0, 4.0, styles.up, styles.bid_depth);
+        assert_eq!(spans.len(), 2);
+        // A quarter of the 26-column row, rounded
+        assert_eq!(spans[0].content.len(), 7);
+        assert_eq!(spans[0].style.bg, styles.bid_depth.bg);
+        assert_eq!(spans[0].style.fg, styles.up.fg);
+        assert_eq!(spans[1].style.bg, None);
+
+        let lines = orderbook_lines(&book(), BOOK_DEPTH, &[], &styles);
+        assert_eq!(lines[3].spans[0].style.bg, styles.ask_depth.bg);
+        assert!(lines.iter().all(|line| line.spans.iter().all(|span| !span.content.contains('\x1b'))));
+
+        let empty = level_spans(0.0, 100.0, 0.0, styles.down, styles.ask_depth);
+        assert_eq!(empty.len(), 1);
+        assert_eq!(empty[0].style, styles.down);
+    }
+
+    #[test]
+    fn test_position_snapshot_per_theme() {
+        let render = |theme| snapshot(&losing_position().position_lines(&Styles::for_theme(theme)));
+        assert_eq!(render(Theme::Default), vec![
+            "Size: -0.5 ",
+            "Entry Price: $100.00",
+            "Unrealized PnL: ▼ -$12.50[Red]",
+            "ROE: ▼ -25.00%[Red]",
+        ]);
+        assert_eq!(render(Theme::HighContrast)[2], "Unrealized PnL: ▼ -$12.50[LightMagenta,bold]");
+        // The arrow and sign carry the direction on their own
+        assert_eq!(render(Theme::Monochrome)[2], "Unrealized PnL: ▼ -$12.50");
+    }
+
+    #[test]
+    fn test_locally_marked_position_has_dot() {
+        let position = losing_position();
+        let live = LivePnl { unrealized_pnl: -15.0, roe: Some(-0.3), local: true };
+        let lines = snapshot(&position.position_lines_at(&live, &Styles::for_theme(Theme::Monochrome)));
+        assert_eq!(lines[2], "Unrealized PnL: ▼ -$15.00 •");
+        assert_eq!(lines[3], "ROE: ▼ -30.00% •");
+    }
+
+    #[test]
+    fn test_monochrome_uses_no_color() {
+        let styles = Styles::for_theme(Theme::Monochrome);
+        for style in [styles.up, styles.down, styles.warn, styles.alert, styles.muted, styles.selected] {
+            assert_eq!(style.fg, None);
+            assert_eq!(style.bg, None);
+        }
+        assert_ne!(styles.up, styles.down);
+        assert_ne!(styles.buy_bar, styles.sell_bar);
+    }
+
+    #[test]
+    fn test_signed_marks_direction() {
+        assert_eq!(signed(12.5, 2), "▲ +12.50");
+        assert_eq!(signed(-3.0, 1), "▼ -3.0");
+        assert_eq!(signed(0.0, 0), "▲ +0");
+    }
+
+    #[test]
+    fn test_theme_parses_names() {
+        assert_eq!("High-Contrast".parse::<Theme>(), Ok(Theme::HighContrast));
+        assert_eq!("mono".parse::<Theme>(), Ok(Theme::Monochrome));
+        assert_eq!(" default ".parse::<Theme>(), Ok(Theme::Default));
+        assert!("neon".parse::<Theme>().is_err());
+        assert_eq!(Theme::HighContrast.to_string().parse::<Theme>(), Ok(Theme::HighContrast));
+    }
+
+    #[test]
+    fn test_plain_table_is_aligned_ascii() {
+        let mut table = Table::new(&["Exchange", "Symbol", "New points"]);
+        table.row(vec!["Hyperliquid".to_string(), "BTC".to_string(), "120".to_string()]);
+        table.row(vec!["dYdX".to_string(), "ETH".to_string()]);
+        assert_eq!(table.render(true), concat!(
+            "Exchange     Symbol  New points\n",
+            "-----------  ------  ----------\n",
+            "Hyperliquid  BTC            120\n",
+            "dYdX         ETH\n",
+        ));
+
+        let styled = table.render(false);
+        assert!(styled.starts_with("\x1b[1m"));
+        assert!(styled.contains(" │ "));
+        assert!(!table.render(true).contains('\x1b'));
+    }
+
+    #[test]
+    fn test_attribution_marks_selection_and_sign() {
+        let child = |key: &str, price| AttributionNode { key: key.to_string(), price, fees: -1.0, ..AttributionNode::default() };
+        let node = AttributionNode {
+            key: "total".to_string(),
+            price: 8.0,
+            fees: -2.0,
+            children: vec![child("ETH", 10.0), child("BTC", -2.0)],
+            ..AttributionNode::default()
+        };
+        let lines = attribution_lines(&node, 1, &Styles::for_theme(Theme::Monochrome));
+        let text: Vec<String> = snapshot(&lines);
+        assert!(text[1].starts_with("  ETH "));
+        assert!(text[1].ends_with("▲ +9.00[bold]"));
+        assert!(text[2].starts_with("> BTC "));
+        assert!(text[2].ends_with("▼ -3.00"));
+        assert_eq!(lines[2].style, Styles::default().selected);
+        assert!(text[4].starts_with("  Total"));
+        assert!(text[4].ends_with("▲ +6.00[bold]"));
+    }
+}
+
+#[cfg(test)]
+mod palette_tests {
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::aggregator::symbol::Symbol;
+    use crate::ui::palette::{evaluate, parse, Command, PaletteHistory, VenueQuote};
+
+    fn quotes() -> Vec<VenueQuote> {
+        vec![
+            VenueQuote { exchange: ExchangeId::Dydx, mark: 50_000.0, funding_rate: 0.0001, max_leverage: Some(20.0) },
+            VenueQuote { exchange: ExchangeId::Hyperliquid, mark: 50_025.0, funding_rate: -0.00005, max_leverage: Some(40.0) },
+        ]
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        let btc = Symbol::perp("BTC");
+        assert_eq!(parse(":conv 0.5 btc"), Ok(Command::Conv { size: 0.5, symbol: btc.clone() }));
+        assert_eq!(
+            parse("liq 0.5 BTC-USD 10x long"),
+            Ok(Command::Liq { size: 0.5, symbol: btc.clone(), leverage: 10.0, is_long: true })
+        );
+        assert_eq!(parse("  SPREAD eth "), Ok(Command::Spread { symbol: Symbol::perp("ETH") }));
+        assert_eq!(parse(":fund sol 3d"), Ok(Command::Fund { symbol: Symbol::perp("SOL"), hours: 72.0 }));
+        assert_eq!(parse("fund sol 1w"), Ok(Command::Fund { symbol: Symbol::perp("SOL"), hours: 168.0 }));
+        assert_eq!(parse("fund sol 8"), Ok(Command::Fund { symbol: Symbol::perp("SOL"), hours: 8.0 }));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let unknown = parse(":price btc").unwrap_err();
+        assert!(unknown.contains("Unknown command 'price'"));
+        assert!(unknown.contains("conv, liq, spread, fund"));
+        assert!(parse("").unwrap_err().contains("conv, liq, spread, fund"));
+        assert!(parse("conv btc").unwrap_err().starts_with("Usage: conv"));
+        assert!(parse("conv -1 btc").unwrap_err().contains("Size"));
+        assert!(parse("liq 1 btc 10x sideways").unwrap_err().contains("long or short"));
+        assert!(parse("fund sol 3m").unwrap_err().contains("8h, 3d or 1w"));
+    }
+
+    #[test]
+    fn test_conv_at_each_mark() {
+        let lines = evaluate(&parse("conv 0.5 btc").unwrap(), &quotes()).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("$25000.00"), "{}", lines[0]);
+        assert!(lines[1].contains("$25012.50"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_liq_estimate_and_leverage_cap() {
+        let lines = evaluate(&parse("liq 0.5 btc 10x long").unwrap(), &quotes()).unwrap();
+        // dYdX: 10% margin less 2.5% maintenance at 20x max
+        assert!(lines[0].contains("liquidates near 46250.0000"), "{}", lines[0]);
+        assert!(lines[0].contains("margin $2500.00"), "{}", lines[0]);
+
+        let lines = evaluate(&parse("liq 1 btc 30x short").unwrap(), &quotes()).unwrap();
+        assert!(lines[0].contains("above the 20x max"), "{}", lines[0]);
+        assert!(lines[1].contains("liquidates near"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_spread_in_bps() {
+        let lines = evaluate(&parse("spread btc").unwrap(), &quotes()).unwrap();
+        let want = format!("BTC spread 5.00 bps: {} 50025.0000 over {} 50000.0000", ExchangeId::Hyperliquid, ExchangeId::Dydx);
+        assert_eq!(lines, vec![want]);
+        assert!(evaluate(&parse("spread btc").unwrap(), &quotes()[..1]).unwrap_err().contains("need two venues"));
+    }
+
+    #[test]
+    fn test_fund_projection_on_notional() {
+        let lines = evaluate(&parse("fund btc 3d").unwrap(), &quotes()).unwrap();
+        // $10k at 0.01%/h for 72h is $72
+        assert!(lines[0].contains("long -72.00, short +72.00"), "{}", lines[0]);
+        assert!(lines[1].contains("long +36.00, short -36.00"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_no_market_data() {
+        assert!(evaluate(&parse("conv 1 btc").unwrap(), &[]).unwrap_err().contains("No market data"));
+    }
+
+    #[test]
+    fn test_history_recall() {
+        let mut history = PaletteHistory::default();
+        assert_eq!(history.previous(), None);
+        history.push("conv 1 btc".to_string(), Ok(vec!["x".to_string()]));
+        history.push("spread eth".to_string(), Err("no data".to_string()));
+        assert_eq!(history.previous(), Some("spread eth"));
+        assert_eq!(history.previous(), Some("conv 1 btc"));
+        assert_eq!(history.previous(), Some("conv 1 btc"));
+        assert_eq!(history.next(), Some("spread eth"));
+        assert_eq!(history.next(), None);
+        assert_eq!(history.entries().len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod input_tests {
+    use crossterm::event::KeyCode;
+    use crate::ui::input::{or_default, positive_integer, positive_number, required, yes_no, InputPrompt, MessagePanel, PromptEvent};
+
+    fn type_in(prompt: &mut InputPrompt, text: &str) {
+        for c in text.chars() {
+            assert_eq!(prompt.handle_key(KeyCode::Char(c), positive_number), PromptEvent::Pending);
+        }
+    }
+
+    #[test]
+    fn test_edit_and_submit() {
+        let mut prompt = InputPrompt::new("Trade", "Amount: ");
+        type_in(&mut prompt, "12x");
+        assert_eq!(prompt.handle_key(KeyCode::Backspace, positive_number), PromptEvent::Pending);
+        type_in(&mut prompt, ".5");
+        assert_eq!(prompt.input(), "12.5");
+        assert_eq!(prompt.handle_key(KeyCode::Enter, positive_number), PromptEvent::Submitted(12.5));
+    }
+
+    #[test]
+    fn test_bad_value_reprompts() {
+        let mut prompt = InputPrompt::new("Trade", "Amount: ");
+        type_in(&mut prompt, "-3");
+        assert_eq!(prompt.handle_key(KeyCode::Enter, positive_number), PromptEvent::Pending);
+        assert!(prompt.error().unwrap().contains("-3"));
+        // Editing clears the error and keeps the line
+        assert_eq!(prompt.handle_key(KeyCode::Backspace, positive_number), PromptEvent::Pending);
+        assert_eq!(prompt.error(), None);
+        assert_eq!(prompt.input(), "-");
+        assert_eq!(prompt.handle_key(KeyCode::Esc, positive_number), PromptEvent::Cancelled);
+    }
+
+    #[test]
+    fn test_parsers() {
+        let yes_no_default = yes_no(Some(false));
+        assert_eq!(yes_no_default(""), Ok(false));
+        assert_eq!(yes_no_default("Y"), Ok(true));
+        assert!(yes_no(None)("").is_err());
+        assert!(yes_no(None)("maybe").is_err());
+
+        let leverage = or_default(3u32);
+        assert_eq!(leverage(""), Ok(3));
+        assert_eq!(leverage("10"), Ok(10));
+        assert!(leverage("ten").is_err());
+        assert!(positive_number("0").is_err());
+        assert_eq!(positive_integer("12"), Ok(12));
+        assert!(positive_integer("0").is_err());
+        assert!(positive_integer("1.5").is_err());
+        assert!(required("").is_err());
+    }
+
+    #[test]
+    fn test_masked_prompt_hides_what_is_typed() {
+        let mut prompt = InputPrompt::new("Wallet", "Passphrase: ").masked();
+        for c in "hunter2".chars() {
+            prompt.handle_key(KeyCode::Char(c), required);
+        }
+        assert_eq!(prompt.shown_input(), "*******");
+        assert_eq!(prompt.handle_key(KeyCode::Enter, required), PromptEvent::Submitted("hunter2".to_string()));
+    }
+
+    #[test]
+    fn test_message_panel_keeps_the_newest_lines_in_view() {
+        let mut panel = MessagePanel::new("Bridge");
+        panel.push("approved\nburned");
+        panel.push("attested");
+        assert_eq!(panel.visible_lines(2), ["burned", "attested"]);
+        assert_eq!(panel.visible_lines(10).len(), 3);
+        assert!(!panel.is_done());
+        panel.finish();
+        assert!(panel.is_done());
+    }
+}
+
+#[cfg(test)]
+mod asset_picker_tests {
+    use crossterm::event::KeyCode;
+    use crate::aggregator::assets::AssetDirectory;
+    use crate::aggregator::exchange_id::ExchangeId;
+    use crate::ui::asset_picker::AssetPicker;
+    use crate::ui::input::PromptEvent;
+
+    fn picker() -> AssetPicker {
+        let listings = vec![
+            (ExchangeId::Hyperliquid, vec!["BTC".to_string(), "SOL".to_string()]),
+            (ExchangeId::Dydx, vec!["BTC".to_string(), "SEI".to_string()]),
+        ];
+        AssetPicker::new(AssetDirectory::from_listings(listings, Vec::new()))
+    }
+
+    #[test]
+    fn test_typing_narrows_and_enter_picks() {
+        let mut picker = picker();
+        assert_eq!(picker.handle_key(KeyCode::Char('s')), PromptEvent::Pending);
+        assert_eq!(picker.matches().len(), 2);
+        assert_eq!(picker.handle_key(KeyCode::Down), PromptEvent::Pending);
+        // Stops at the last match
+        assert_eq!(picker.handle_key(KeyCode::Down), PromptEvent::Pending);
+        assert_eq!(picker.selected().unwrap().base, "SOL");
+        // Editing the query goes back to the best match
+        picker.handle_key(KeyCode::Char('e'));
+        assert_eq!(picker.query(), "se");
+        assert_eq!(picker.handle_key(KeyCode::Enter), PromptEvent::Submitted("SEI".to_string()));
+    }
+
+    #[test]
+    fn test_enter_without_a_match_stays_open() {
+        let mut picker = picker();
+        picker.handle_key(KeyCode::Char('z'));
+        assert_eq!(picker.handle_key(KeyCode::Enter), PromptEvent::Pending);
+        assert_eq!(picker.handle_key(KeyCode::Esc), PromptEvent::Cancelled);
+    }
+
+    #[test]
+    fn test_no_asset_lists_takes_the_typed_symbol() {
+        let failed = vec![(ExchangeId::Dydx, "timeout".to_string())];
+        let mut picker = AssetPicker::new(AssetDirectory::from_listings(Vec::new(), failed));
+        for c in "doge".chars() {
+            picker.handle_key(KeyCode::Char(c));
+        }
+        // Taken as typed; the caller parses it into a symbol
+        assert_eq!(picker.handle_key(KeyCode::Enter), PromptEvent::Submitted("doge".to_string()));
+    }
+}
@@ -0,0 +1,127 @@
+use ratatui::style::{Color, Modifier, Style};
+
+/// Color scheme for every screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    #[default]
+    Default,
+    // Blue/magenta instead of green/red, bold, no dark gray text
+    HighContrast,
+    // No color at all; direction is carried by symbols and modifiers
+    Monochrome,
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::HighContrast => write!(f, "high-contrast"),
+            Self::Monochrome => write!(f, "monochrome"),
+        }
+    }
+}
+
+impl std::str::FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "default" => Ok(Self::Default),
+            "high-contrast" | "high_contrast" | "contrast" => Ok(Self::HighContrast),
+            "monochrome" | "mono" => Ok(Self::Monochrome),
+            other => Err(format!("Unknown theme '{}'; expected default, high-contrast or monochrome", other)),
+        }
+    }
+}
+
+/// Every style the widgets use, so none of them picks colors inline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Styles {
+    pub theme: Theme,
+    // Rising prices, profits, bids and buys
+    pub up: Style,
+    // Falling prices, losses, asks and sells
+    pub down: Style,
+    // The user's own orders and degraded venues
+    pub warn: Style,
+    // Halted venues
+    pub alert: Style,
+    // Secondary detail and unavailable entries
+    pub muted: Style,
+    pub selected: Style,
+    // Bars in the volume profile; told apart by shape where color can't
+    pub buy_bar: &'static str,
+    pub sell_bar: &'static str,
+    // Depth bars drawn behind book levels, laid over the side's own style
+    pub bid_depth: Style,
+    pub ask_depth: Style,
+}
+
+impl Default for Styles {
+    fn default() -> Self {
+        Self::for_theme(Theme::Default)
+    }
+}
+
+impl Styles {
+    pub fn for_theme(theme: Theme) -> Self {
+        let selected = Style::default().add_modifier(Modifier::REVERSED);
+        match theme {
+            Theme::Default => Self {
+                theme,
+                up: Style::default().fg(Color::Green),
+                down: Style::default().fg(Color::Red),
+                warn: Style::default().fg(Color::Yellow),
+                alert: Style::default().fg(Color::Red),
+                muted: Style::default().fg(Color::DarkGray),
+                selected,
+                buy_bar: "█",
+                sell_bar: "█",
+                bid_depth: Style::default().bg(Color::Rgb(0, 60, 0)),
+                ask_depth: Style::default().bg(Color::Rgb(70, 0, 0)),
+            },
+            Theme::HighContrast => Self {
+                theme,
+                up: Style::default().fg(Color::LightBlue).add_modifier(Modifier::BOLD),
+                down: Style::default().fg(Color::LightMagenta).add_modifier(Modifier::BOLD),
+                warn: Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+                alert: Style::default().fg(Color::White).bg(Color::Magenta).add_modifier(Modifier::BOLD),
+                muted: Style::default().fg(Color::Gray),
+                selected,
+                buy_bar: "█",
+                sell_bar: "░",
+                bid_depth: Style::default().bg(Color::Rgb(0, 0, 90)),
+                ask_depth: Style::default().bg(Color::Rgb(80, 0, 80)),
+            },
+            Theme::Monochrome => Self {
+                theme,
+                up: Style::default().add_modifier(Modifier::BOLD),
+                down: Style::default(),
+                warn: Style::default().add_modifier(Modifier::UNDERLINED),
+                alert: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                muted: Style::default(),
+                selected,
+                buy_bar: "█",
+                sell_bar: "░",
+                bid_depth: Style::default().add_modifier(Modifier::REVERSED),
+                ask_depth: Style::default().add_modifier(Modifier::REVERSED),
+            },
+        }
+    }
+
+    /// `up` for zero and above, `down` below
+    pub fn direction(&self, value: f64) -> Style {
+        if value < 0.0 { self.down } else { self.up }
+    }
+}
+
+/// "▲" for zero and above, "▼" below
+pub fn arrow(value: f64) -> &'static str {
+    if value < 0.0 { "▼" } else { "▲" }
+}
+
+/// A signed amount with an arrow, e.g. "▲ +12.50" or "▼ -3.00", so the
+/// direction reads without color.
+pub fn signed(value: f64, decimals: usize) -> String {
+    format!("{} {:+.*}", arrow(value), decimals, value)
+}
@@ -0,0 +1,59 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Sparkline},
+    Frame,
+};
+use crate::aggregator::trade_flow::{ProfileLevel, TradeFlowSnapshot};
+use super::ladder::price_decimals;
+use super::theme::{signed, Styles};
+
+/// Sideways volume histogram, highest price first. Each bar is split into
+/// buy (green) and sell (red) volume and scaled to the busiest bucket.
+pub fn profile_lines(profile: &[ProfileLevel], bucket: f64, bar_width: usize, styles: &Styles) -> Vec<Line<'static>> {
+    let max = profile.iter().map(ProfileLevel::volume).fold(0.0, f64::max);
+    if max <= 0.0 {
+        return Vec::new();
+    }
+    let decimals = price_decimals(bucket);
+    profile.iter()
+        .map(|level| {
+            let scale = |volume: f64| (volume / max * bar_width as f64).round() as usize;
+            let buys = scale(level.buy_volume);
+            let sells = scale(level.volume()).saturating_sub(buys);
+            Line::from(vec![
+                Span::raw(format!("{:>12.*} ", decimals, level.price)),
+                Span::styled(styles.buy_bar.repeat(buys), styles.up),
+                Span::styled(styles.sell_bar.repeat(sells), styles.down),
+                Span::raw(format!(" {:.4}", level.volume())),
+            ])
+        })
+        .collect()
+}
+
+/// Shift the delta series so its minimum sits at zero, for a sparkline.
+pub fn sparkline_values(series: &[f64]) -> Vec<u64> {
+    let min = series.iter().copied().fold(f64::INFINITY, f64::min);
+    series.iter()
+        .map(|value| ((value - min) * 1e4).round().max(0.0) as u64)
+        .collect()
+}
+
+pub fn render_trade_flow(f: &mut Frame, area: Rect, flow: &TradeFlowSnapshot, title: &str, styles: &Styles) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(4)])
+        .split(area);
+
+    let bar_width = (chunks[0].width as usize).saturating_sub(28).max(1);
+    let profile = Paragraph::new(profile_lines(&flow.profile, flow.bucket, bar_width, styles))
+        .block(Block::default().borders(Borders::ALL).title(title.to_string()));
+    f.render_widget(profile, chunks[0]);
+
+    let values = sparkline_values(&flow.cvd_series);
+    let cvd = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(format!("CVD {} ({} trades)", signed(flow.cvd, 4), flow.trades)))
+        .data(&values)
+        .style(styles.direction(flow.cvd));
+    f.render_widget(cvd, chunks[1]);
+}
@@ -0,0 +1,175 @@
+use std::future::Future;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::Print,
+    terminal::{self, Clear, ClearType},
+};
+
+// How often the status line is repainted while an operation is running
+const STATUS_REFRESH: Duration = Duration::from_millis(250);
+// Short operations finish before the status line would just flicker
+const STATUS_DELAY: Duration = Duration::from_secs(1);
+
+struct Operation {
+    label: String,
+    started: Instant,
+}
+
+/// The operation the UI loop is currently awaiting, shared with the watchdog
+/// thread and rendered in the status line.
+#[derive(Clone, Default)]
+pub struct OperationCell {
+    inner: Arc<Mutex<Option<Operation>>>,
+}
+
+/// Clears the operation when dropped, so early returns and `?` can't leave a
+/// stale label behind.
+pub struct OperationGuard {
+    cell: OperationCell,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if let Ok(mut current) = self.cell.inner.lock() {
+            *current = None;
+        }
+    }
+}
+
+impl OperationCell {
+    pub fn begin(&self, label: &str) -> OperationGuard {
+        if let Ok(mut current) = self.inner.lock() {
+            *current = Some(Operation {
+                label: label.to_string(),
+                started: Instant::now(),
+            });
+        }
+        OperationGuard { cell: self.clone() }
+    }
+
+    pub fn current(&self) -> Option<(String, Duration)> {
+        self.inner.lock().ok()?
+            .as_ref()
+            .map(|op| (op.label.clone(), op.started.elapsed()))
+    }
+
+    /// e.g. "placing dYdX order… 4s"
+    pub fn status_line(&self) -> Option<String> {
+        self.current().map(|(label, elapsed)| format_status(&label, elapsed))
+    }
+}
+
+pub fn format_status(label: &str, elapsed: Duration) -> String {
+    format!("{}… {}s", label, elapsed.as_secs())
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StallCheck {
+    Healthy,
+    Stalled { label: String, elapsed: Duration },
+}
+
+/// The loop is only considered frozen while it is awaiting an operation;
+/// sitting on a blocking key read in a menu is normal idling.
+pub fn check_stall(since_heartbeat: Duration, operation: Option<(String, Duration)>, timeout: Duration) -> StallCheck {
+    match operation {
+        Some((label, elapsed)) if elapsed > timeout && since_heartbeat > timeout => {
+            StallCheck::Stalled { label, elapsed }
+        }
+        _ => StallCheck::Healthy,
+    }
+}
+
+/// Background thread that restores the terminal and exits when the UI loop
+/// has been stuck on one operation for longer than `timeout`.
+///
+/// The loop awaits its operations inline, so there is no task to abort from
+/// here; getting the tty back and reporting what hung is the recovery.
+pub struct Watchdog {
+    last_heartbeat: Arc<Mutex<Instant>>,
+}
+
+impl Watchdog {
+    pub fn spawn<F>(timeout: Duration, operation: OperationCell, on_stall: F) -> Self
+    where
+        F: Fn() + Send + 'static,
+    {
+        let last_heartbeat = Arc::new(Mutex::new(Instant::now()));
+        let heartbeat = last_heartbeat.clone();
+        let interval = std::cmp::max(timeout / 4, Duration::from_millis(250));
+
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            let since_heartbeat = match heartbeat.lock() {
+                Ok(last) => last.elapsed(),
+                Err(_) => continue,
+            };
+
+            if let StallCheck::Stalled { label, elapsed } = check_stall(since_heartbeat, operation.current(), timeout) {
+                on_stall();
+                eprintln!(
+                    "\nUI event loop stalled for {}s while {}; terminal restored, exiting.",
+                    elapsed.as_secs(),
+                    label
+                );
+                tracing::error!("Watchdog: UI loop stalled for {:?} while {}", elapsed, label);
+                std::process::exit(1);
+            }
+        });
+
+        Self { last_heartbeat }
+    }
+
+    pub fn heartbeat(&self) {
+        if let Ok(mut last) = self.last_heartbeat.lock() {
+            *last = Instant::now();
+        }
+    }
+}
+
+/// Await `future` as the current operation, painting its progress on the
+/// bottom terminal row until it completes.
+pub async fn run_with_status<F: Future>(operation: &OperationCell, label: &str, future: F) -> F::Output {
+    let _guard = operation.begin(label);
+    let mut ticker = tokio::time::interval(STATUS_REFRESH);
+    let mut painted = false;
+    tokio::pin!(future);
+
+    loop {
+        tokio::select! {
+            output = &mut future => {
+                if painted {
+                    let _ = paint_status_line("");
+                }
+                return output;
+            }
+            _ = ticker.tick() => {
+                if let Some((label, elapsed)) = operation.current() {
+                    if elapsed >= STATUS_DELAY {
+                        painted = paint_status_line(&format_status(&label, elapsed)).is_ok();
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Drawn with crossterm directly: ratatui only repaints on the next frame,
+// which is exactly what an in-flight operation is blocking.
+fn paint_status_line(text: &str) -> io::Result<()> {
+    let (_, rows) = terminal::size()?;
+    let mut stdout = io::stdout();
+    queue!(
+        stdout,
+        MoveTo(0, rows.saturating_sub(1)),
+        Clear(ClearType::CurrentLine),
+        Print(text)
+    )?;
+    stdout.flush()
+}